@@ -0,0 +1,51 @@
+mod store;
+
+use serde::Deserialize;
+use spin_factor_blob_store::runtime_config::spin::MakeBlobStore;
+pub use store::{BlobStoreAzure, BlobStoreAzureAuthOptions};
+
+/// A blob store that uses an Azure Blob Storage container as the backend.
+#[derive(Default)]
+pub struct AzureBlobStore {
+    _priv: (),
+}
+
+impl AzureBlobStore {
+    /// Creates a new `AzureBlobStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the Azure Blob Storage blob store.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AzureBlobStoreRuntimeConfig {
+    /// The Azure Storage account name.
+    account: String,
+    /// The container where objects are stored.
+    container: String,
+    /// The access key for the storage account. If not set, credentials are
+    /// taken from the environment.
+    key: Option<String>,
+}
+
+impl MakeBlobStore for AzureBlobStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "azure_blob";
+
+    type RuntimeConfig = AzureBlobStoreRuntimeConfig;
+
+    type Store = BlobStoreAzure;
+
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::Store> {
+        let auth_options = match runtime_config.key {
+            Some(key) => BlobStoreAzureAuthOptions::AccessKey(key),
+            None => BlobStoreAzureAuthOptions::Environmental,
+        };
+        BlobStoreAzure::new(
+            runtime_config.account,
+            runtime_config.container,
+            auth_options,
+        )
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use azure_core::StatusCode;
+use azure_storage::StorageCredentials;
+use azure_storage_blobs::prelude::{
+    BlobBlockType, BlockId, BlockList, ClientBuilder, ContainerClient,
+};
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use spin_factor_blob_store::{BlobStore, IncomingObject, ObjectWriter};
+use spin_world::spin::blob_store::types::{Error, ObjectInfo};
+
+/// The size of the blocks in which large objects are uploaded. Objects no
+/// larger than this are uploaded in a single request.
+const BLOCK_SIZE: usize = 8 * 1024 * 1024;
+
+pub struct BlobStoreAzure {
+    container: String,
+    client: ContainerClient,
+}
+
+/// Azure Blob Storage enumeration for the possible authentication options
+#[derive(Clone, Debug)]
+pub enum BlobStoreAzureAuthOptions {
+    /// The storage account's access key has been specified directly
+    AccessKey(String),
+    /// Environmental indicates that the environment variables of the process should be used to
+    /// create the TokenCredential for the storage client. This will use the Azure Rust SDK's
+    /// DefaultCredentialChain to derive the TokenCredential based on what environment variables
+    /// have been set, as for the Azure Cosmos key-value store.
+    Environmental,
+}
+
+impl BlobStoreAzure {
+    pub fn new(
+        account: String,
+        container: String,
+        auth_options: BlobStoreAzureAuthOptions,
+    ) -> Result<Self> {
+        let credentials = match auth_options {
+            BlobStoreAzureAuthOptions::AccessKey(key) => {
+                StorageCredentials::access_key(account.clone(), key)
+            }
+            BlobStoreAzureAuthOptions::Environmental => {
+                StorageCredentials::token_credential(azure_identity::create_default_credential()?)
+            }
+        };
+        let client = ClientBuilder::new(account, credentials).container_client(&container);
+        Ok(Self { container, client })
+    }
+}
+
+#[async_trait]
+impl BlobStore for BlobStoreAzure {
+    async fn get(&self, name: &str) -> Result<Option<IncomingObject>, Error> {
+        let blob = self.client.blob_client(name);
+        // The size is only reported per range of the body, so it is fetched
+        // separately
+        let size = match blob.get_properties().await {
+            Ok(response) => response.blob.properties.content_length,
+            Err(e) if is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(sdk_error(e)),
+        };
+        let body = blob
+            .get()
+            .into_stream()
+            .map_ok(|response| response.data.map_err(sdk_error))
+            .map_err(sdk_error)
+            .try_flatten();
+        Ok(Some(IncomingObject {
+            size,
+            body: body.boxed(),
+        }))
+    }
+
+    async fn put(&self, name: &str) -> Result<Box<dyn ObjectWriter>, Error> {
+        Ok(Box::new(AzureWriter {
+            client: self.client.clone(),
+            name: name.to_owned(),
+            buffer: BytesMut::new(),
+            upload_id: uuid::Uuid::new_v4().simple().to_string(),
+            blocks: vec![],
+        }))
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), Error> {
+        match self.client.blob_client(name).delete().await {
+            Ok(_) => Ok(()),
+            Err(e) if is_not_found(&e) => Ok(()),
+            Err(e) => Err(sdk_error(e)),
+        }
+    }
+
+    async fn info(&self, name: &str) -> Result<Option<ObjectInfo>, Error> {
+        match self.client.blob_client(name).get_properties().await {
+            Ok(response) => Ok(Some(ObjectInfo {
+                name: name.to_owned(),
+                size: response.blob.properties.content_length,
+            })),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(sdk_error(e)),
+        }
+    }
+
+    fn list(&self, prefix: Option<String>) -> BoxStream<'static, Result<ObjectInfo, Error>> {
+        let mut builder = self.client.list_blobs();
+        if let Some(prefix) = prefix {
+            builder = builder.prefix(prefix);
+        }
+        builder
+            .into_stream()
+            .map_ok(|page| {
+                let objects = page
+                    .blobs
+                    .blobs()
+                    .map(|blob| {
+                        Ok(ObjectInfo {
+                            name: blob.name.clone(),
+                            size: blob.properties.content_length,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                stream::iter(objects)
+            })
+            .map_err(sdk_error)
+            .try_flatten()
+            .boxed()
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("Azure Blob Storage container {}", self.container))
+    }
+}
+
+/// An object being uploaded. Objects are uploaded in a single request when
+/// they are finished, unless they are larger than [`BLOCK_SIZE`], in which
+/// case they are uploaded in blocks as they are written and the blocks are
+/// committed when they are finished.
+///
+/// Blocks which are never committed are discarded by Azure after a week, so
+/// unfinished uploads need no clean up.
+struct AzureWriter {
+    client: ContainerClient,
+    name: String,
+    /// Data written but not yet uploaded.
+    buffer: BytesMut,
+    /// A unique ID for the upload, so that the blocks of concurrent uploads
+    /// of the same object don't collide.
+    upload_id: String,
+    /// The blocks uploaded so far.
+    blocks: Vec<BlockId>,
+}
+
+impl AzureWriter {
+    /// Uploads the buffered data as the next block.
+    async fn upload_block(&mut self) -> Result<(), Error> {
+        // Block IDs must all be the same length
+        let block_id = BlockId::new(format!("{}-{:06}", self.upload_id, self.blocks.len()));
+        let body = self.buffer.split().freeze();
+        self.client
+            .blob_client(&self.name)
+            .put_block(block_id.clone(), body)
+            .await
+            .map_err(sdk_error)?;
+        self.blocks.push(block_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ObjectWriter for AzureWriter {
+    async fn write(&mut self, data: Bytes) -> Result<(), Error> {
+        self.buffer.extend_from_slice(&data);
+        if self.buffer.len() >= BLOCK_SIZE {
+            self.upload_block().await?;
+        }
+        Ok(())
+    }
+
+    async fn finish(mut self: Box<Self>) -> Result<(), Error> {
+        if self.blocks.is_empty() {
+            let body = self.buffer.split().freeze();
+            self.client
+                .blob_client(&self.name)
+                .put_block_blob(body)
+                .await
+                .map_err(sdk_error)?;
+            return Ok(());
+        }
+        if !self.buffer.is_empty() {
+            self.upload_block().await?;
+        }
+        let block_list = BlockList {
+            blocks: self
+                .blocks
+                .drain(..)
+                .map(BlobBlockType::new_uncommitted)
+                .collect(),
+        };
+        self.client
+            .blob_client(&self.name)
+            .put_block_list(block_list)
+            .await
+            .map_err(sdk_error)?;
+        Ok(())
+    }
+}
+
+fn is_not_found(e: &azure_core::Error) -> bool {
+    e.as_http_error()
+        .is_some_and(|e| e.status() == StatusCode::NotFound)
+}
+
+fn sdk_error(e: azure_core::Error) -> Error {
+    Error::Other(e.to_string())
+}
@@ -0,0 +1,80 @@
+mod store;
+
+use serde::Deserialize;
+use spin_factor_blob_store::runtime_config::spin::MakeBlobStore;
+pub use store::{BlobStoreS3, S3Credentials};
+
+/// A blob store that uses an S3 bucket, or a bucket of an S3-compatible
+/// service, as the backend.
+#[derive(Default)]
+pub struct S3BlobStore {
+    _priv: (),
+}
+
+impl S3BlobStore {
+    /// Creates a new `S3BlobStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the S3 blob store.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct S3BlobStoreRuntimeConfig {
+    /// The bucket where objects are stored.
+    bucket: String,
+    /// The AWS region where the bucket is located. If not set, it is taken
+    /// from the environment.
+    region: Option<String>,
+    /// The URL of an S3-compatible service, such as MinIO, to use instead
+    /// of AWS.
+    endpoint: Option<String>,
+    /// Whether to address the bucket in the path of URLs rather than in the
+    /// host name. Defaults to `true` if `endpoint` is set, as many
+    /// S3-compatible services require.
+    force_path_style: Option<bool>,
+    /// The access key for the bucket.
+    access_key: Option<String>,
+    /// The secret key for the bucket.
+    secret_key: Option<String>,
+    /// The session token for the bucket.
+    token: Option<String>,
+}
+
+impl MakeBlobStore for S3BlobStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "s3";
+
+    type RuntimeConfig = S3BlobStoreRuntimeConfig;
+
+    type Store = BlobStoreS3;
+
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::Store> {
+        let S3BlobStoreRuntimeConfig {
+            bucket,
+            region,
+            endpoint,
+            force_path_style,
+            access_key,
+            secret_key,
+            token,
+        } = runtime_config;
+        let credentials = match (access_key, secret_key) {
+            (Some(access_key), Some(secret_key)) => Some(S3Credentials {
+                access_key,
+                secret_key,
+                token,
+            }),
+            (None, None) => None,
+            _ => anyhow::bail!("`access_key` and `secret_key` must be set together"),
+        };
+        let force_path_style = force_path_style.unwrap_or(endpoint.is_some());
+        Ok(BlobStoreS3::new(
+            bucket,
+            region,
+            endpoint,
+            force_path_style,
+            credentials,
+        ))
+    }
+}
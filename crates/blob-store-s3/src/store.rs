@@ -0,0 +1,369 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, Region};
+use aws_credential_types::Credentials;
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::operation::head_object::HeadObjectError;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, BoxStream, StreamExt};
+use spin_factor_blob_store::{BlobStore, IncomingObject, ObjectWriter};
+use spin_world::spin::blob_store::types::{Error, ObjectInfo};
+use tokio::sync::OnceCell;
+
+/// The size of the parts in which large objects are uploaded. Objects no
+/// larger than this are uploaded in a single request.
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Credentials given in the runtime config.
+#[derive(Clone, Debug)]
+pub struct S3Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub token: Option<String>,
+}
+
+pub struct BlobStoreS3 {
+    bucket: String,
+    config: Arc<ClientConfig>,
+    /// The client, which is created when the store is first used.
+    client: Arc<OnceCell<Client>>,
+}
+
+/// The configuration of a store's client.
+struct ClientConfig {
+    region: Option<String>,
+    endpoint: Option<String>,
+    force_path_style: bool,
+    /// Credentials given in the runtime config. If `None`, credentials are
+    /// taken from the environment, as the AWS CLI does.
+    credentials: Option<S3Credentials>,
+}
+
+impl BlobStoreS3 {
+    pub fn new(
+        bucket: String,
+        region: Option<String>,
+        endpoint: Option<String>,
+        force_path_style: bool,
+        credentials: Option<S3Credentials>,
+    ) -> Self {
+        Self {
+            bucket,
+            config: Arc::new(ClientConfig {
+                region,
+                endpoint,
+                force_path_style,
+                credentials,
+            }),
+            client: Default::default(),
+        }
+    }
+
+    async fn client(&self) -> Client {
+        client(&self.client, &self.config).await
+    }
+}
+
+async fn client(cell: &OnceCell<Client>, config: &ClientConfig) -> Client {
+    cell.get_or_init(|| async {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = &config.region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        if let Some(credentials) = &config.credentials {
+            loader = loader.credentials_provider(Credentials::new(
+                credentials.access_key.clone(),
+                credentials.secret_key.clone(),
+                credentials.token.clone(),
+                None, // Optional expiration time
+                "spin_custom_aws_provider",
+            ));
+        }
+        let sdk_config = loader.load().await;
+        let mut s3_config = aws_sdk_s3::config::Builder::from(&sdk_config)
+            .force_path_style(config.force_path_style);
+        if let Some(endpoint) = &config.endpoint {
+            s3_config = s3_config.endpoint_url(endpoint);
+        }
+        Client::from_conf(s3_config.build())
+    })
+    .await
+    .clone()
+}
+
+#[async_trait]
+impl BlobStore for BlobStoreS3 {
+    async fn get(&self, name: &str) -> Result<Option<IncomingObject>, Error> {
+        let result = self
+            .client()
+            .await
+            .get_object()
+            .bucket(&self.bucket)
+            .key(name)
+            .send()
+            .await;
+        let output = match result {
+            Ok(output) => output,
+            Err(e) if matches!(e.as_service_error(), Some(GetObjectError::NoSuchKey(_))) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(sdk_error(e)),
+        };
+        let size = output.content_length().unwrap_or_default().max(0) as u64;
+        let body = stream::try_unfold(output.body, |mut body| async move {
+            match body.next().await.transpose().map_err(sdk_error)? {
+                Some(chunk) => Ok(Some((chunk, body))),
+                None => Ok(None),
+            }
+        });
+        Ok(Some(IncomingObject {
+            size,
+            body: body.boxed(),
+        }))
+    }
+
+    async fn put(&self, name: &str) -> Result<Box<dyn ObjectWriter>, Error> {
+        Ok(Box::new(S3Writer {
+            client: self.client().await,
+            bucket: self.bucket.clone(),
+            key: name.to_owned(),
+            buffer: BytesMut::new(),
+            upload: None,
+        }))
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), Error> {
+        self.client()
+            .await
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(name)
+            .send()
+            .await
+            .map_err(sdk_error)?;
+        Ok(())
+    }
+
+    async fn info(&self, name: &str) -> Result<Option<ObjectInfo>, Error> {
+        let result = self
+            .client()
+            .await
+            .head_object()
+            .bucket(&self.bucket)
+            .key(name)
+            .send()
+            .await;
+        match result {
+            Ok(output) => Ok(Some(ObjectInfo {
+                name: name.to_owned(),
+                size: output.content_length().unwrap_or_default().max(0) as u64,
+            })),
+            Err(e) if matches!(e.as_service_error(), Some(HeadObjectError::NotFound(_))) => {
+                Ok(None)
+            }
+            Err(e) => Err(sdk_error(e)),
+        }
+    }
+
+    fn list(&self, prefix: Option<String>) -> BoxStream<'static, Result<ObjectInfo, Error>> {
+        struct State {
+            objects: VecDeque<ObjectInfo>,
+            /// The token for the next page, if the last page has not yet
+            /// been listed.
+            next_page: Option<Option<String>>,
+        }
+
+        let bucket = self.bucket.clone();
+        let cell = self.client.clone();
+        let config = self.config.clone();
+        let state = State {
+            objects: VecDeque::new(),
+            next_page: Some(None),
+        };
+        stream::try_unfold(state, move |mut state| {
+            let bucket = bucket.clone();
+            let prefix = prefix.clone();
+            let cell = cell.clone();
+            let config = config.clone();
+            async move {
+                loop {
+                    if let Some(object) = state.objects.pop_front() {
+                        return Ok(Some((object, state)));
+                    }
+                    let Some(token) = state.next_page.take() else {
+                        return Ok(None);
+                    };
+                    let output = client(&cell, &config)
+                        .await
+                        .list_objects_v2()
+                        .bucket(&bucket)
+                        .set_prefix(prefix.clone())
+                        .set_continuation_token(token)
+                        .send()
+                        .await
+                        .map_err(sdk_error)?;
+                    state
+                        .objects
+                        .extend(output.contents().iter().filter_map(|object| {
+                            Some(ObjectInfo {
+                                name: object.key()?.to_owned(),
+                                size: object.size().unwrap_or_default().max(0) as u64,
+                            })
+                        }));
+                    if output.is_truncated().unwrap_or_default() {
+                        state.next_page = Some(output.next_continuation_token().map(Into::into));
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("S3 bucket {}", self.bucket))
+    }
+}
+
+/// An object being uploaded. Objects are uploaded in a single request when
+/// they are finished, unless they are larger than [`PART_SIZE`], in which
+/// case they are uploaded in parts as they are written.
+struct S3Writer {
+    client: Client,
+    bucket: String,
+    key: String,
+    /// Data written but not yet uploaded.
+    buffer: BytesMut,
+    /// The multipart upload, once one has been started.
+    upload: Option<MultipartUpload>,
+}
+
+struct MultipartUpload {
+    id: String,
+    parts: Vec<CompletedPart>,
+}
+
+impl S3Writer {
+    /// Uploads the buffered data as the next part of the multipart upload,
+    /// starting the upload if need be.
+    async fn upload_part(&mut self) -> Result<(), Error> {
+        if self.upload.is_none() {
+            let output = self
+                .client
+                .create_multipart_upload()
+                .bucket(&self.bucket)
+                .key(&self.key)
+                .send()
+                .await
+                .map_err(sdk_error)?;
+            let id = output
+                .upload_id()
+                .ok_or_else(|| Error::Other("S3 did not return an upload ID".into()))?;
+            self.upload = Some(MultipartUpload {
+                id: id.to_owned(),
+                parts: vec![],
+            });
+        }
+        let upload = self.upload.as_mut().unwrap();
+        let part_number = upload.parts.len() as i32 + 1;
+        let body = self.buffer.split().freeze();
+        let output = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&upload.id)
+            .part_number(part_number)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .map_err(sdk_error)?;
+        upload.parts.push(
+            CompletedPart::builder()
+                .part_number(part_number)
+                .set_e_tag(output.e_tag)
+                .build(),
+        );
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ObjectWriter for S3Writer {
+    async fn write(&mut self, data: Bytes) -> Result<(), Error> {
+        self.buffer.extend_from_slice(&data);
+        if self.buffer.len() >= PART_SIZE {
+            self.upload_part().await?;
+        }
+        Ok(())
+    }
+
+    async fn finish(mut self: Box<Self>) -> Result<(), Error> {
+        if self.upload.is_none() {
+            let body = self.buffer.split().freeze();
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(&self.key)
+                .body(ByteStream::from(body))
+                .send()
+                .await
+                .map_err(sdk_error)?;
+            return Ok(());
+        }
+        if !self.buffer.is_empty() {
+            self.upload_part().await?;
+        }
+        let upload = self.upload.take().unwrap();
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(upload.id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(upload.parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(sdk_error)?;
+        Ok(())
+    }
+}
+
+impl Drop for S3Writer {
+    fn drop(&mut self) {
+        // An unfinished multipart upload keeps its parts, which are billed
+        // for, until it is aborted
+        let Some(upload) = self.upload.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let request = self
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(upload.id);
+        runtime.spawn(async move {
+            if let Err(e) = request.send().await {
+                tracing::warn!(
+                    "failed to abort S3 multipart upload: {}",
+                    DisplayErrorContext(&e)
+                );
+            }
+        });
+    }
+}
+
+fn sdk_error(e: impl std::error::Error) -> Error {
+    Error::Other(DisplayErrorContext(e).to_string())
+}
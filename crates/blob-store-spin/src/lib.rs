@@ -0,0 +1,71 @@
+//! A blob store kept in a local directory, or in memory.
+
+mod store;
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use spin_factor_blob_store::runtime_config::spin::MakeBlobStore;
+pub use store::{LocalBlobStore, StoreLocation};
+
+/// A blob store that keeps objects as files in a local directory.
+pub struct SpinBlobStore {
+    /// The directory against which relative store paths are resolved.
+    base_path: Option<PathBuf>,
+}
+
+impl SpinBlobStore {
+    /// Create a new SpinBlobStore with the given base path.
+    ///
+    /// If it's `Some`, relative paths in the runtime configuration are
+    /// resolved against `base_path`.
+    pub fn new(base_path: Option<PathBuf>) -> Self {
+        Self { base_path }
+    }
+}
+
+impl MakeBlobStore for SpinBlobStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "spin";
+
+    type RuntimeConfig = SpinBlobStoreRuntimeConfig;
+
+    type Store = LocalBlobStore;
+
+    fn make_store(&self, runtime_config: Self::RuntimeConfig) -> anyhow::Result<Self::Store> {
+        let location = match (&self.base_path, runtime_config.path) {
+            (Some(base_path), Some(path)) => {
+                StoreLocation::Dir(resolve_relative_path(&path, base_path))
+            }
+            (None, Some(path)) => StoreLocation::Dir(path),
+            // Without a path, objects are kept in memory
+            (_, None) => StoreLocation::InMemory,
+        };
+        Ok(LocalBlobStore::new(location))
+    }
+}
+
+/// The serialized runtime configuration for the local blob store.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpinBlobStoreRuntimeConfig {
+    /// The directory in which objects are kept.
+    path: Option<PathBuf>,
+}
+
+impl SpinBlobStoreRuntimeConfig {
+    /// Create a new SpinBlobStoreRuntimeConfig with the given directory
+    /// where the blob store will live.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+}
+
+/// Resolve a relative path against a base dir.
+///
+/// If the path is absolute, it is returned as is. Otherwise, it is resolved against the base dir.
+fn resolve_relative_path(path: &Path, base_dir: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_owned();
+    }
+    base_dir.join(path)
+}
@@ -0,0 +1,401 @@
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, BoxStream, StreamExt};
+use spin_factor_blob_store::{BlobStore, IncomingObject, ObjectWriter};
+use spin_world::spin::blob_store::types::{Error, ObjectInfo};
+use tempfile::TempPath;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// The size of the chunks in which object bodies are read from files.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Where a [`LocalBlobStore`] keeps its objects.
+#[derive(Clone, Debug)]
+pub enum StoreLocation {
+    /// Objects are files under the `objects` directory of this directory.
+    /// Objects being written are kept under its `tmp` directory until they
+    /// are finished.
+    Dir(PathBuf),
+    /// Objects are kept in memory, and lost when the process exits.
+    InMemory,
+}
+
+/// A blob store in a local directory, or in memory.
+pub struct LocalBlobStore {
+    location: StoreLocation,
+    /// The objects of an in-memory store.
+    memory: Arc<Mutex<BTreeMap<String, Bytes>>>,
+}
+
+impl LocalBlobStore {
+    /// Create a store at the given location. Directories are created when
+    /// the store is first written to.
+    pub fn new(location: StoreLocation) -> Self {
+        Self {
+            location,
+            memory: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn get(&self, name: &str) -> Result<Option<IncomingObject>, Error> {
+        let dir = match &self.location {
+            StoreLocation::Dir(dir) => dir,
+            StoreLocation::InMemory => {
+                let memory = self.memory.lock().unwrap();
+                return Ok(memory.get(name).map(|body| IncomingObject {
+                    size: body.len() as u64,
+                    body: stream::once(std::future::ready(Ok(body.clone()))).boxed(),
+                }));
+            }
+        };
+        let file = match tokio::fs::File::open(object_path(dir, name)).await {
+            Ok(file) => file,
+            Err(e) if is_missing(&e) => return Ok(None),
+            Err(e) => return Err(other(e)),
+        };
+        let metadata = file.metadata().await.map_err(other)?;
+        if !metadata.is_file() {
+            return Ok(None);
+        }
+        let body = stream::try_unfold(file, |mut file| async move {
+            let mut chunk = BytesMut::with_capacity(READ_CHUNK_SIZE);
+            if file.read_buf(&mut chunk).await.map_err(other)? == 0 {
+                return Ok(None);
+            }
+            Ok(Some((chunk.freeze(), file)))
+        });
+        Ok(Some(IncomingObject {
+            size: metadata.len(),
+            body: body.boxed(),
+        }))
+    }
+
+    async fn put(&self, name: &str) -> Result<Box<dyn ObjectWriter>, Error> {
+        let dir = match &self.location {
+            StoreLocation::Dir(dir) => dir,
+            StoreLocation::InMemory => {
+                return Ok(Box::new(MemoryWriter {
+                    memory: self.memory.clone(),
+                    name: name.to_owned(),
+                    body: BytesMut::new(),
+                }))
+            }
+        };
+        let tmp_dir = dir.join("tmp");
+        let target = object_path(dir, name);
+        let (file, path) = tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(&tmp_dir).with_context(|| {
+                format!(
+                    "failed to create blob store directory '{}'",
+                    tmp_dir.display()
+                )
+            })?;
+            let file = tempfile::NamedTempFile::new_in(&tmp_dir)
+                .context("failed to create temporary file")?;
+            anyhow::Ok(file.into_parts())
+        })
+        .await
+        .map_err(other)?
+        .map_err(|e| other(format!("{e:#}")))?;
+        Ok(Box::new(FileWriter {
+            file: tokio::fs::File::from_std(file),
+            path,
+            target,
+        }))
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), Error> {
+        let dir = match &self.location {
+            StoreLocation::Dir(dir) => dir,
+            StoreLocation::InMemory => {
+                self.memory.lock().unwrap().remove(name);
+                return Ok(());
+            }
+        };
+        let path = object_path(dir, name);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if is_missing(&e) => return Ok(()),
+            Err(e) => return Err(other(e)),
+        }
+        // Remove the directories which only held the object, so that they
+        // don't accumulate
+        let objects_dir = dir.join("objects");
+        let mut parent = path.parent();
+        while let Some(dir) = parent.filter(|dir| *dir != objects_dir) {
+            if tokio::fs::remove_dir(dir).await.is_err() {
+                break;
+            }
+            parent = dir.parent();
+        }
+        Ok(())
+    }
+
+    async fn info(&self, name: &str) -> Result<Option<ObjectInfo>, Error> {
+        let dir = match &self.location {
+            StoreLocation::Dir(dir) => dir,
+            StoreLocation::InMemory => {
+                let memory = self.memory.lock().unwrap();
+                return Ok(memory.get(name).map(|body| ObjectInfo {
+                    name: name.to_owned(),
+                    size: body.len() as u64,
+                }));
+            }
+        };
+        match tokio::fs::metadata(object_path(dir, name)).await {
+            Ok(metadata) if metadata.is_file() => Ok(Some(ObjectInfo {
+                name: name.to_owned(),
+                size: metadata.len(),
+            })),
+            Ok(_) => Ok(None),
+            Err(e) if is_missing(&e) => Ok(None),
+            Err(e) => Err(other(e)),
+        }
+    }
+
+    fn list(&self, prefix: Option<String>) -> BoxStream<'static, Result<ObjectInfo, Error>> {
+        let prefix = prefix.unwrap_or_default();
+        let dir = match &self.location {
+            StoreLocation::Dir(dir) => dir.join("objects"),
+            StoreLocation::InMemory => {
+                let memory = self.memory.lock().unwrap();
+                let objects = memory
+                    .range(prefix.clone()..)
+                    .take_while(|(name, _)| name.starts_with(&prefix))
+                    .map(|(name, body)| {
+                        Ok(ObjectInfo {
+                            name: name.clone(),
+                            size: body.len() as u64,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                return stream::iter(objects).boxed();
+            }
+        };
+        let objects = tokio::task::spawn_blocking(move || {
+            let mut objects = vec![];
+            list_dir(&dir, "", &prefix, &mut objects)?;
+            objects.sort_by(|a: &ObjectInfo, b| a.name.cmp(&b.name));
+            Ok(objects)
+        });
+        stream::once(async move { objects.await.map_err(other)? })
+            .map(|objects: Result<Vec<_>, Error>| match objects {
+                Ok(objects) => stream::iter(objects.into_iter().map(Ok)).left_stream(),
+                Err(e) => stream::once(std::future::ready(Err(e))).right_stream(),
+            })
+            .flatten()
+            .boxed()
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(match &self.location {
+            StoreLocation::Dir(dir) => format!("directory {}", dir.display()),
+            StoreLocation::InMemory => "in memory".to_owned(),
+        })
+    }
+}
+
+/// Collects the objects in `dir`, whose names are their paths relative to
+/// the directory appended to `name_prefix`, if their names start with
+/// `prefix`.
+fn list_dir(
+    dir: &Path,
+    name_prefix: &str,
+    prefix: &str,
+    objects: &mut Vec<ObjectInfo>,
+) -> Result<(), Error> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if is_missing(&e) => return Ok(()),
+        Err(e) => return Err(other(e)),
+    };
+    for entry in entries {
+        let entry = entry.map_err(other)?;
+        // Objects can only have UTF-8 names, so other files aren't objects
+        let Ok(file_name) = entry.file_name().into_string() else {
+            continue;
+        };
+        let name = format!("{name_prefix}{file_name}");
+        let file_type = entry.file_type().map_err(other)?;
+        if file_type.is_dir() {
+            let dir_prefix = format!("{name}/");
+            // Only descend into directories which may hold matching objects
+            if dir_prefix.starts_with(prefix) || prefix.starts_with(&dir_prefix) {
+                list_dir(&entry.path(), &dir_prefix, prefix, objects)?;
+            }
+        } else if file_type.is_file() && name.starts_with(prefix) {
+            let size = entry.metadata().map_err(other)?.len();
+            objects.push(ObjectInfo { name, size });
+        }
+    }
+    Ok(())
+}
+
+/// An object being written to a file, which is moved into place when it is
+/// finished, and deleted if it is dropped before then.
+struct FileWriter {
+    file: tokio::fs::File,
+    path: TempPath,
+    target: PathBuf,
+}
+
+#[async_trait]
+impl ObjectWriter for FileWriter {
+    async fn write(&mut self, data: Bytes) -> Result<(), Error> {
+        self.file.write_all(&data).await.map_err(other)
+    }
+
+    async fn finish(mut self: Box<Self>) -> Result<(), Error> {
+        self.file.flush().await.map_err(other)?;
+        let FileWriter { file, path, target } = *self;
+        drop(file);
+        tokio::task::spawn_blocking(move || {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(other)?;
+            }
+            path.persist(&target).map_err(|e| other(e.error))
+        })
+        .await
+        .map_err(other)?
+    }
+}
+
+/// An object being written to an in-memory store.
+struct MemoryWriter {
+    memory: Arc<Mutex<BTreeMap<String, Bytes>>>,
+    name: String,
+    body: BytesMut,
+}
+
+#[async_trait]
+impl ObjectWriter for MemoryWriter {
+    async fn write(&mut self, data: Bytes) -> Result<(), Error> {
+        self.body.extend_from_slice(&data);
+        Ok(())
+    }
+
+    async fn finish(self: Box<Self>) -> Result<(), Error> {
+        let MemoryWriter { memory, name, body } = *self;
+        memory.lock().unwrap().insert(name, body.freeze());
+        Ok(())
+    }
+}
+
+/// The path of the file of the object with the given name, which must have
+/// been checked by [`spin_factor_blob_store::validate_name`].
+fn object_path(dir: &Path, name: &str) -> PathBuf {
+    let mut path = dir.join("objects");
+    path.extend(name.split('/'));
+    path
+}
+
+/// Whether an error means that a file doesn't exist, including when a
+/// directory in its path is a file.
+fn is_missing(e: &std::io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory)
+}
+
+fn other(e: impl std::fmt::Display) -> Error {
+    Error::Other(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    async fn put(store: &LocalBlobStore, name: &str, chunks: &[&str]) {
+        let mut writer = store.put(name).await.unwrap();
+        for chunk in chunks {
+            writer
+                .write(Bytes::copy_from_slice(chunk.as_bytes()))
+                .await
+                .unwrap();
+        }
+        writer.finish().await.unwrap();
+    }
+
+    async fn get(store: &LocalBlobStore, name: &str) -> Option<String> {
+        let object = store.get(name).await.unwrap()?;
+        let chunks: Vec<Bytes> = object.body.try_collect().await.unwrap();
+        let body = String::from_utf8(chunks.concat()).unwrap();
+        assert_eq!(object.size, body.len() as u64);
+        Some(body)
+    }
+
+    async fn list(store: &LocalBlobStore, prefix: Option<&str>) -> Vec<String> {
+        let objects: Vec<ObjectInfo> = store
+            .list(prefix.map(ToOwned::to_owned))
+            .try_collect()
+            .await
+            .unwrap();
+        objects.into_iter().map(|object| object.name).collect()
+    }
+
+    #[tokio::test]
+    async fn stores_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        for location in [
+            StoreLocation::Dir(dir.path().to_owned()),
+            StoreLocation::InMemory,
+        ] {
+            let store = LocalBlobStore::new(location);
+            assert_eq!(get(&store, "a.txt").await, None);
+            assert!(list(&store, None).await.is_empty());
+
+            put(&store, "a.txt", &["hello, ", "world"]).await;
+            put(&store, "docs/b.txt", &["b"]).await;
+            put(&store, "docs/nested/c.txt", &[]).await;
+            put(&store, "docsets.txt", &["d"]).await;
+            assert_eq!(get(&store, "a.txt").await.as_deref(), Some("hello, world"));
+            assert_eq!(store.info("docs/b.txt").await.unwrap().unwrap().size, 1);
+            assert_eq!(get(&store, "docs").await, None);
+            assert_eq!(
+                list(&store, None).await,
+                ["a.txt", "docs/b.txt", "docs/nested/c.txt", "docsets.txt"]
+            );
+            assert_eq!(
+                list(&store, Some("docs/")).await,
+                ["docs/b.txt", "docs/nested/c.txt"]
+            );
+            assert_eq!(list(&store, Some("docs/n")).await, ["docs/nested/c.txt"]);
+
+            put(&store, "a.txt", &["replaced"]).await;
+            assert_eq!(get(&store, "a.txt").await.as_deref(), Some("replaced"));
+            store.delete("docs/nested/c.txt").await.unwrap();
+            store.delete("missing.txt").await.unwrap();
+            assert_eq!(list(&store, Some("docs/")).await, ["docs/b.txt"]);
+        }
+        assert!(!dir.path().join("objects/docs/nested").exists());
+    }
+
+    #[tokio::test]
+    async fn unfinished_objects_are_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        for location in [
+            StoreLocation::Dir(dir.path().to_owned()),
+            StoreLocation::InMemory,
+        ] {
+            let store = LocalBlobStore::new(location);
+            let mut writer = store.put("a.txt").await.unwrap();
+            writer.write(Bytes::from_static(b"partial")).await.unwrap();
+            assert_eq!(get(&store, "a.txt").await, None);
+            drop(writer);
+            assert_eq!(get(&store, "a.txt").await, None);
+        }
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("tmp")).unwrap().count(),
+            0
+        );
+    }
+}
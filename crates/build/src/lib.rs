@@ -15,28 +15,69 @@ use subprocess::{Exec, Redirection};
 
 use crate::manifest::component_build_configs;
 
-/// If present, run the build command of each component.
-pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()> {
-    let (components, manifest_err) =
-        component_build_configs(manifest_file)
-            .await
-            .with_context(|| {
-                format!(
-                    "Cannot read manifest file from {}",
-                    quoted_path(manifest_file)
-                )
-            })?;
+/// If present, run the build command of each component, using the build
+/// commands of the given manifest environment and build profile if any.
+pub async fn build(
+    manifest_file: &Path,
+    component_ids: &[String],
+    environment: Option<&str>,
+    profile: Option<&str>,
+) -> Result<()> {
+    let (components, manifest_err) = component_build_configs(manifest_file, environment)
+        .await
+        .with_context(|| {
+            format!(
+                "Cannot read manifest file from {}",
+                quoted_path(manifest_file)
+            )
+        })?;
     let app_dir = parent_dir(manifest_file)?;
 
-    let build_result = build_components(component_ids, components, app_dir);
+    let build_result = apply_profile(components, profile)
+        .and_then(|components| build_components(component_ids, components, app_dir));
 
     if let Some(e) = manifest_err {
         terminal::warn!("The manifest has errors not related to the Wasm component build. Error details:\n{e:#}");
+    } else if let Ok(diagnostics) = spin_manifest::validate::validate_file(manifest_file) {
+        if !diagnostics.is_empty() {
+            let details = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            terminal::warn!(
+                "The manifest has problems not related to the Wasm component build:\n{details}"
+            );
+        }
     }
 
     build_result
 }
 
+/// Resolves each component's build configuration for the given profile.
+/// It is an error if no component defines the profile.
+fn apply_profile(
+    components: Vec<ComponentBuildInfo>,
+    profile: Option<&str>,
+) -> Result<Vec<ComponentBuildInfo>> {
+    if let Some(profile) = profile {
+        let defined = components
+            .iter()
+            .filter_map(|c| c.build.as_ref())
+            .any(|b| b.profile(profile).is_some());
+        if !defined {
+            bail!("No component defines the build profile '{profile}'");
+        }
+    }
+    Ok(components
+        .into_iter()
+        .map(|c| ComponentBuildInfo {
+            build: c.build.map(|b| b.with_profile(profile)),
+            ..c
+        })
+        .collect())
+}
+
 fn build_components(
     component_ids: &[String],
     components: Vec<ComponentBuildInfo>,
@@ -111,7 +152,12 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
                     println!("Working directory: {}", quoted_path(&workdir));
                 }
 
-                let exit_status = Exec::shell(command)
+                let exit_status = b
+                    .environment
+                    .iter()
+                    .fold(Exec::shell(command), |exec, (key, value)| {
+                        exec.env(key, value)
+                    })
                     .cwd(workdir)
                     .stdout(Redirection::None)
                     .stderr(Redirection::None)
@@ -171,6 +217,15 @@ mod tests {
     #[tokio::test]
     async fn can_load_even_if_trigger_invalid() {
         let bad_trigger_file = test_data_root().join("bad_trigger.toml");
-        build(&bad_trigger_file, &[]).await.unwrap();
+        build(&bad_trigger_file, &[], None, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn unknown_profile_is_an_error() {
+        let bad_trigger_file = test_data_root().join("bad_trigger.toml");
+        let err = build(&bad_trigger_file, &[], None, Some("release"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("'release'"), "{err}");
     }
 }
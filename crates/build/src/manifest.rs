@@ -8,12 +8,16 @@ use spin_manifest::{schema::v2, ManifestVersion};
 /// given (v1 or v2) manifest path. If the manifest cannot be loaded, the
 /// function attempts fallback: if fallback succeeds, result is Ok but the load error
 /// is also returned via the second part of the return value tuple.
+///
+/// If `environment` is given, that manifest environment's build commands are
+/// used. Environments are not applied in fallback.
 pub async fn component_build_configs(
     manifest_file: impl AsRef<Path>,
+    environment: Option<&str>,
 ) -> Result<(Vec<ComponentBuildInfo>, Option<spin_manifest::Error>)> {
     let manifest = spin_manifest::manifest_from_file(&manifest_file);
     match manifest {
-        Ok(manifest) => Ok((build_configs_from_manifest(manifest), None)),
+        Ok(manifest) => Ok((build_configs_from_manifest(manifest, environment)?, None)),
         Err(e) => fallback_load_build_configs(&manifest_file)
             .await
             .map(|bc| (bc, Some(e))),
@@ -22,17 +26,22 @@ pub async fn component_build_configs(
 
 fn build_configs_from_manifest(
     mut manifest: spin_manifest::schema::v2::AppManifest,
-) -> Vec<ComponentBuildInfo> {
+    environment: Option<&str>,
+) -> Result<Vec<ComponentBuildInfo>> {
     spin_manifest::normalize::normalize_manifest(&mut manifest);
 
-    manifest
+    if let Some(environment) = environment {
+        manifest.apply_environment(environment)?;
+    }
+
+    Ok(manifest
         .components
         .into_iter()
         .map(|(id, c)| ComponentBuildInfo {
             id: id.to_string(),
             build: c.build,
         })
-        .collect()
+        .collect())
 }
 
 async fn fallback_load_build_configs(
@@ -0,0 +1,147 @@
+//! Executables with an embedded bundle, which run the bundled app.
+//!
+//! A bundle is embedded by appending it to a copy of a Spin executable,
+//! followed by a trailer of the bundle's length, as a little-endian `u64`,
+//! and a magic number. Executable formats ignore data after their end, so
+//! the copy still runs as Spin, and can find the bundle in itself.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use spin_common::ui::quoted_path;
+
+const MAGIC: &[u8; 8] = b"SPINBNDL";
+const TRAILER_LEN: u64 = 16;
+
+/// Writes an executable to the given path, which is a copy of the given
+/// launcher with the given bundle embedded in it.
+pub fn embed(launcher: &Path, bundle: &Path, output: &Path) -> Result<()> {
+    let launcher_exe = std::fs::read(launcher)
+        .with_context(|| format!("failed to read launcher {}", quoted_path(launcher)))?;
+    // If the launcher is itself an app, its bundle is replaced rather than
+    // nested
+    let launcher_exe = match find(&launcher_exe)? {
+        Some(range) => &launcher_exe[..range.start as usize],
+        None => &launcher_exe[..],
+    };
+    let bundle_bytes = std::fs::read(bundle)
+        .with_context(|| format!("failed to read bundle {}", quoted_path(bundle)))?;
+
+    let mut file = File::create(output)
+        .with_context(|| format!("failed to create {}", quoted_path(output)))?;
+    file.write_all(launcher_exe)?;
+    file.write_all(&bundle_bytes)?;
+    file.write_all(&(bundle_bytes.len() as u64).to_le_bytes())?;
+    file.write_all(MAGIC)?;
+    set_executable(&file)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_executable(file: &File) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_file: &File) -> Result<()> {
+    Ok(())
+}
+
+/// Whether the file at the given path has an embedded bundle.
+pub fn has_embedded_bundle(path: &Path) -> bool {
+    File::open(path)
+        .ok()
+        .and_then(|mut file| embedded_range(&mut file).ok())
+        .flatten()
+        .is_some()
+}
+
+/// Opens the bundle at the given path, which is either a bundle or an
+/// executable with an embedded one.
+pub(crate) fn open(path: &Path) -> Result<Box<dyn Read>> {
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", quoted_path(path)))?;
+    match embedded_range(&mut file)? {
+        Some(range) => {
+            file.seek(SeekFrom::Start(range.start))?;
+            Ok(Box::new(file.take(range.end - range.start)))
+        }
+        None => {
+            file.rewind()?;
+            Ok(Box::new(file))
+        }
+    }
+}
+
+/// The byte range of the bundle embedded in the file, if any.
+fn embedded_range(file: &mut File) -> Result<Option<Range<u64>>> {
+    let len = file.metadata()?.len();
+    if len < TRAILER_LEN {
+        return Ok(None);
+    }
+    let mut trailer = [0; TRAILER_LEN as usize];
+    file.seek(SeekFrom::Start(len - TRAILER_LEN))?;
+    file.read_exact(&mut trailer)?;
+    parse_trailer(&trailer, len)
+}
+
+/// The byte range of the bundle embedded in the given executable, if any.
+fn find(exe: &[u8]) -> Result<Option<Range<u64>>> {
+    let len = exe.len() as u64;
+    if len < TRAILER_LEN {
+        return Ok(None);
+    }
+    parse_trailer(&exe[(len - TRAILER_LEN) as usize..], len)
+}
+
+fn parse_trailer(trailer: &[u8], file_len: u64) -> Result<Option<Range<u64>>> {
+    let (bundle_len, magic) = trailer.split_at(8);
+    if magic != MAGIC {
+        return Ok(None);
+    }
+    let bundle_len = u64::from_le_bytes(bundle_len.try_into()?);
+    let end = file_len - TRAILER_LEN;
+    let start = end
+        .checked_sub(bundle_len)
+        .context("the embedded bundle is truncated")?;
+    Ok(Some(start..end))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn embedded_bundles_are_found() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let launcher = dir.path().join("spin");
+        let bundle = dir.path().join("app.tar.gz");
+        std::fs::write(&launcher, "launcher")?;
+        std::fs::write(&bundle, "bundle")?;
+        assert!(!has_embedded_bundle(&launcher));
+
+        let app = dir.path().join("app");
+        embed(&launcher, &bundle, &app)?;
+        assert!(has_embedded_bundle(&app));
+        let mut embedded = String::new();
+        open(&app)?.read_to_string(&mut embedded)?;
+        assert_eq!(embedded, "bundle");
+
+        std::fs::write(&bundle, "other bundle")?;
+        let other_app = dir.path().join("other-app");
+        embed(&app, &bundle, &other_app)?;
+        assert_eq!(
+            std::fs::read(&other_app)?.len(),
+            "launcher".len() + "other bundle".len() + TRAILER_LEN as usize
+        );
+        let mut embedded = String::new();
+        open(&other_app)?.read_to_string(&mut embedded)?;
+        assert_eq!(embedded, "other bundle");
+        Ok(())
+    }
+}
@@ -0,0 +1,357 @@
+//! Bundles of Spin applications, which hold everything needed to run an
+//! application where there is no registry or network access.
+//!
+//! A bundle is a gzipped tar archive laid out as:
+//!
+//! ```text
+//! spin.lock                            the locked app, with sources relative to the bundle
+//! wasm/<hex digest>.wasm               the Wasm of components and their dependencies
+//! files/<component id>/...             the files mounted into components
+//! runtime-config/runtime-config.toml   the runtime config file, if one was bundled
+//! runtime-config/...                   the files the runtime config refers to
+//! ```
+//!
+//! A bundle may also be embedded in a Spin executable; see [`executable`].
+
+#![deny(missing_docs)]
+
+pub mod executable;
+mod runtime_config;
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use spin_common::ui::quoted_path;
+use spin_loader::{FilesMountStrategy, LoadOptions};
+use spin_locked_app::locked::{ContentRef, DigestRef, LockedApp, LockedComponentDependency};
+use url::Url;
+
+const LOCKED_APP_FILE: &str = "spin.lock";
+const WASM_DIR: &str = "wasm";
+const FILES_DIR: &str = "files";
+const RUNTIME_CONFIG_DIR: &str = "runtime-config";
+const RUNTIME_CONFIG_FILE: &str = "runtime-config.toml";
+
+/// Writes a bundle of the app with the given manifest to the given path.
+///
+/// The app is loaded with the given options, which say how its `spin.lock`
+/// is used. Remote component sources are downloaded, to the given cache
+/// directory or the default one. If a runtime config file is given, it is
+/// bundled with the files it refers to by relative paths.
+pub async fn export(
+    manifest_path: &Path,
+    load_options: LoadOptions,
+    runtime_config_file: Option<&Path>,
+    cache_root: Option<PathBuf>,
+    output: &Path,
+) -> Result<()> {
+    let staging_dir = tempfile::tempdir()?;
+    // Loaded sources are canonical paths, which must be relative to this
+    let root = staging_dir.path().canonicalize()?;
+
+    let mut locked_app = spin_loader::from_file_with_options(
+        manifest_path,
+        FilesMountStrategy::Copy(root.join(FILES_DIR)),
+        cache_root,
+        load_options,
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "failed to load manifest from {}",
+            quoted_path(manifest_path)
+        )
+    })?;
+    locked_app.metadata.remove("origin");
+
+    for component in &mut locked_app.components {
+        let id = &component.id;
+        bundle_wasm(&root, &mut component.source.content)
+            .await
+            .with_context(|| format!("failed to bundle Wasm of component {id:?}"))?;
+        for dependency in component.dependencies.values_mut() {
+            bundle_dependency(&root, dependency)
+                .await
+                .with_context(|| format!("failed to bundle dependencies of component {id:?}"))?;
+        }
+        for file in &mut component.files {
+            let path = file_source_path(&file.content)?;
+            let relative = path
+                .strip_prefix(&root)
+                .with_context(|| format!("files of component {id:?} were not copied"))?;
+            file.content.source = Some(bundle_path(relative));
+        }
+    }
+
+    if let Some(runtime_config_file) = runtime_config_file {
+        runtime_config::bundle(runtime_config_file, &root.join(RUNTIME_CONFIG_DIR))
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to bundle runtime config {}",
+                    quoted_path(runtime_config_file)
+                )
+            })?;
+    }
+
+    let locked_json = serde_json::to_vec_pretty(&locked_app)?;
+    tokio::fs::write(root.join(LOCKED_APP_FILE), locked_json).await?;
+
+    let output = output.to_owned();
+    tokio::task::spawn_blocking(move || write_archive(&root, &output)).await?
+}
+
+/// Copies Wasm into the bundle, and points the content ref at the copy.
+async fn bundle_wasm(root: &Path, content: &mut ContentRef) -> Result<()> {
+    let path = file_source_path(content)?;
+    let wasm = tokio::fs::read(&path)
+        .await
+        .with_context(|| format!("failed to read {}", quoted_path(&path)))?;
+    let digest = DigestRef::sha256(&wasm);
+    let relative = format!("{WASM_DIR}/{}.wasm", digest.hex());
+    tokio::fs::create_dir_all(root.join(WASM_DIR)).await?;
+    tokio::fs::write(root.join(&relative), wasm).await?;
+    *content = ContentRef {
+        source: Some(relative),
+        digest: Some(digest),
+        ..Default::default()
+    };
+    Ok(())
+}
+
+async fn bundle_dependency(root: &Path, dependency: &mut LockedComponentDependency) -> Result<()> {
+    bundle_wasm(root, &mut dependency.source.content).await?;
+    for nested in dependency.dependencies.values_mut() {
+        Box::pin(bundle_dependency(root, nested)).await?;
+    }
+    Ok(())
+}
+
+fn write_archive(root: &Path, output: &Path) -> Result<()> {
+    let file = std::fs::File::create(output)
+        .with_context(|| format!("failed to create {}", quoted_path(output)))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder
+        .append_dir_all(".", root)
+        .with_context(|| format!("failed to write {}", quoted_path(output)))?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// A bundle unpacked into a directory, ready to run.
+pub struct UnpackedBundle {
+    /// The bundled app, with its sources resolved to the unpacked files.
+    pub locked_app: LockedApp,
+    /// The bundled runtime config file, if any.
+    pub runtime_config_file: Option<PathBuf>,
+}
+
+/// Unpacks the bundle at the given path into the given directory. The path
+/// may also be of an executable with an embedded bundle.
+pub async fn unpack(bundle: &Path, dir: &Path) -> Result<UnpackedBundle> {
+    let (bundle, dest) = (bundle.to_owned(), dir.to_owned());
+    tokio::task::spawn_blocking(move || read_archive(&bundle, &dest)).await??;
+    let root = dir.canonicalize()?;
+
+    let locked_path = root.join(LOCKED_APP_FILE);
+    let locked_json = tokio::fs::read(&locked_path)
+        .await
+        .context("the bundle has no locked app")?;
+    let mut locked_app =
+        LockedApp::from_json(&locked_json).context("failed to decode the bundle's locked app")?;
+
+    for component in &mut locked_app.components {
+        let id = &component.id;
+        resolve_wasm(&root, &mut component.source.content)
+            .await
+            .with_context(|| format!("invalid Wasm of component {id:?}"))?;
+        for dependency in component.dependencies.values_mut() {
+            resolve_dependency(&root, dependency)
+                .await
+                .with_context(|| format!("invalid dependencies of component {id:?}"))?;
+        }
+        for file in &mut component.files {
+            let path = resolve_source(&root, &file.content)
+                .with_context(|| format!("invalid files of component {id:?}"))?;
+            file.content.source = Some(file_url(&path)?);
+        }
+    }
+
+    let runtime_config_file = root.join(RUNTIME_CONFIG_DIR).join(RUNTIME_CONFIG_FILE);
+    Ok(UnpackedBundle {
+        locked_app,
+        runtime_config_file: runtime_config_file.is_file().then_some(runtime_config_file),
+    })
+}
+
+fn read_archive(bundle: &Path, dest: &Path) -> Result<()> {
+    let file = executable::open(bundle)?;
+    // Entries which would be unpacked outside the destination are skipped
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(dest)
+        .with_context(|| format!("failed to unpack bundle {}", quoted_path(bundle)))
+}
+
+/// Checks the unpacked Wasm against its digest, and points the content ref
+/// at it.
+async fn resolve_wasm(root: &Path, content: &mut ContentRef) -> Result<()> {
+    let path = resolve_source(root, content)?;
+    let digest = content
+        .digest
+        .as_ref()
+        .context("bundled Wasm should have a digest")?;
+    let wasm = tokio::fs::read(&path)
+        .await
+        .with_context(|| format!("failed to read {}", quoted_path(&path)))?;
+    digest
+        .verify(wasm)
+        .map_err(|e| anyhow!("{}: {e}", quoted_path(&path)))?;
+    content.source = Some(file_url(&path)?);
+    Ok(())
+}
+
+async fn resolve_dependency(root: &Path, dependency: &mut LockedComponentDependency) -> Result<()> {
+    resolve_wasm(root, &mut dependency.source.content).await?;
+    for nested in dependency.dependencies.values_mut() {
+        Box::pin(resolve_dependency(root, nested)).await?;
+    }
+    Ok(())
+}
+
+/// The unpacked path of a source relative to the bundle.
+fn resolve_source(root: &Path, content: &ContentRef) -> Result<PathBuf> {
+    let source = content
+        .source
+        .as_deref()
+        .context("bundled content should have a source")?;
+    let relative = Path::new(source);
+    ensure!(
+        relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_))),
+        "bundled source {source:?} is outside the bundle"
+    );
+    Ok(root.join(relative))
+}
+
+fn file_source_path(content: &ContentRef) -> Result<PathBuf> {
+    let source = content
+        .source
+        .as_deref()
+        .context("content loaded from disk should have a file source")?;
+    Url::parse(source)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .with_context(|| format!("expected a file URL, got {source:?}"))
+}
+
+fn file_url(path: &Path) -> Result<String> {
+    Url::from_file_path(path)
+        .map(String::from)
+        .map_err(|_| anyhow!("cannot convert to file URL: {}", quoted_path(path)))
+}
+
+/// A relative path in the bundle, in the same form on all platforms.
+fn bundle_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(path: impl AsRef<Path>, contents: &str) {
+        let path = path.as_ref();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bundles_run_where_they_are_unpacked() -> Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        let app_dir = app_dir.path();
+        write(
+            app_dir.join("spin.toml"),
+            r#"
+            spin_manifest_version = 2
+
+            [application]
+            name = "bundled"
+            version = "1.0.0"
+
+            [[trigger.http]]
+            route = "/..."
+            component = "api"
+
+            [component.api]
+            source = "api.wasm"
+            files = [{ source = "static", destination = "/" }]
+            "#,
+        );
+        write(app_dir.join("api.wasm"), "(component)");
+        write(app_dir.join("static/index.html"), "<h1>hello</h1>");
+        write(
+            app_dir.join("config/runtime-config.toml"),
+            r#"
+            [tls]
+            cert_file = "certs/cert.pem"
+            key_file = "/etc/ssl/private/key.pem"
+            "#,
+        );
+        write(app_dir.join("config/certs/cert.pem"), "CERTIFICATE");
+
+        let output_dir = tempfile::tempdir()?;
+        let bundle = output_dir.path().join("bundled.tar.gz");
+        export(
+            &app_dir.join("spin.toml"),
+            Default::default(),
+            Some(&app_dir.join("config/runtime-config.toml")),
+            None,
+            &bundle,
+        )
+        .await?;
+
+        let unpack_dir = tempfile::tempdir()?;
+        let unpacked = unpack(&bundle, unpack_dir.path()).await?;
+        let root = unpack_dir.path().canonicalize()?;
+
+        let component = &unpacked.locked_app.components[0];
+        let wasm = file_source_path(&component.source.content)?;
+        assert!(wasm.starts_with(&root));
+        assert_eq!(std::fs::read_to_string(wasm)?, "(component)");
+        let files = file_source_path(&component.files[0].content)?;
+        assert_eq!(
+            std::fs::read_to_string(files.join("index.html"))?,
+            "<h1>hello</h1>"
+        );
+
+        let runtime_config_file = unpacked.runtime_config_file.context("bundled")?;
+        let config_dir = runtime_config_file.parent().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(config_dir.join("certs/cert.pem"))?,
+            "CERTIFICATE"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tampered_wasm_is_rejected() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        write(root.path().join("wasm/app.wasm"), "(component)");
+        let mut content = ContentRef {
+            source: Some("wasm/app.wasm".into()),
+            digest: Some(DigestRef::sha256("(module)")),
+            ..Default::default()
+        };
+        resolve_wasm(root.path(), &mut content).await.unwrap_err();
+
+        content.source = Some("../app.wasm".into());
+        resolve_wasm(root.path(), &mut content).await.unwrap_err();
+        Ok(())
+    }
+}
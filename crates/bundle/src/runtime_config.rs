@@ -0,0 +1,64 @@
+//! Bundling of a runtime config file with the files it refers to.
+
+use std::path::{Component, Path};
+
+use anyhow::{Context, Result};
+use spin_common::ui::quoted_path;
+
+use crate::RUNTIME_CONFIG_FILE;
+
+/// Copies a runtime config file into the given directory, with the files
+/// which its values refer to by relative paths, such as TLS certificates or
+/// trusted keys, at the same paths relative to it.
+///
+/// Files referred to by absolute paths, or outside the runtime config
+/// file's directory, are not bundled, and must exist wherever the bundle is
+/// run.
+pub(crate) async fn bundle(path: &Path, dest: &Path) -> Result<()> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("failed to read {}", quoted_path(path)))?;
+    let toml: toml::Table = toml::from_str(&contents)
+        .with_context(|| format!("failed to parse {} as toml", quoted_path(path)))?;
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    tokio::fs::create_dir_all(dest).await?;
+    tokio::fs::write(dest.join(RUNTIME_CONFIG_FILE), &contents).await?;
+
+    let mut values = vec![];
+    string_values(&toml::Value::Table(toml), &mut values);
+    for value in values {
+        let reference = Path::new(&value);
+        if !base.join(reference).is_file() {
+            continue;
+        }
+        let is_bundleable = reference
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !is_bundleable {
+            terminal::warn!(
+                "The runtime config refers to {}, which is not in its directory, so is not bundled.",
+                quoted_path(reference)
+            );
+            continue;
+        }
+        let target = dest.join(reference);
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(base.join(reference), &target)
+            .await
+            .with_context(|| format!("failed to copy {}", quoted_path(reference)))?;
+    }
+    Ok(())
+}
+
+/// Collects the strings in a value, at any depth.
+fn string_values(value: &toml::Value, values: &mut Vec<String>) {
+    match value {
+        toml::Value::String(s) => values.push(s.clone()),
+        toml::Value::Array(array) => array.iter().for_each(|v| string_values(v, values)),
+        toml::Value::Table(table) => table.values().for_each(|v| string_values(v, values)),
+        _ => {}
+    }
+}
@@ -31,7 +31,9 @@ pub async fn compose<L: ComponentSourceLoader>(
     loader: &L,
     component: &LockedComponent,
 ) -> Result<Vec<u8>, ComposeError> {
-    Composer::new(loader).compose(component).await
+    Composer::new(loader)
+        .compose(&component.id, &component.source, &component.dependencies)
+        .await
 }
 
 /// This trait is used to load component source code from a locked component source across various embdeddings.
@@ -129,22 +131,29 @@ struct Composer<'a, L> {
 }
 
 impl<'a, L: ComponentSourceLoader> Composer<'a, L> {
-    async fn compose(mut self, component: &LockedComponent) -> Result<Vec<u8>, ComposeError> {
+    async fn compose(
+        mut self,
+        component_id: &str,
+        source: &locked::LockedComponentSource,
+        dependencies: &BTreeMap<DependencyName, LockedComponentDependency>,
+    ) -> Result<Vec<u8>, ComposeError> {
         let source = self
             .loader
-            .load_component_source(&component.source)
+            .load_component_source(source)
             .await
             .map_err(ComposeError::PrepareError)?;
 
-        if component.dependencies.is_empty() {
+        if dependencies.is_empty() {
             return Ok(source);
         }
 
         let (world_id, instantiation_id) = self
-            .register_package(&component.id, None, source)
+            .register_package(component_id, None, source)
             .map_err(ComposeError::PrepareError)?;
 
-        let prepared = self.prepare_dependencies(world_id, component).await?;
+        let prepared = self
+            .prepare_dependencies(world_id, component_id, dependencies)
+            .await?;
 
         let arguments = self
             .build_instantiation_arguments(world_id, prepared)
@@ -180,7 +189,8 @@ impl<'a, L: ComponentSourceLoader> Composer<'a, L> {
     async fn prepare_dependencies(
         &mut self,
         world_id: WorldId,
-        component: &LockedComponent,
+        component_id: &str,
+        dependencies: &BTreeMap<DependencyName, LockedComponentDependency>,
     ) -> Result<IndexMap<String, DependencyInfo>, ComposeError> {
         let imports = self.graph.types()[world_id].imports.clone();
 
@@ -188,7 +198,7 @@ impl<'a, L: ComponentSourceLoader> Composer<'a, L> {
 
         let mut mappings: BTreeMap<String, Vec<DependencyInfo>> = BTreeMap::new();
 
-        for (dependency_name, dependency) in &component.dependencies {
+        for (dependency_name, dependency) in dependencies {
             let mut matched = Vec::new();
 
             for import_name in &import_keys {
@@ -201,7 +211,7 @@ impl<'a, L: ComponentSourceLoader> Composer<'a, L> {
 
             if matched.is_empty() {
                 return Err(ComposeError::UnmatchedDependencyName {
-                    component_id: component.id.clone(),
+                    component_id: component_id.to_owned(),
                     dependency_name: dependency_name.clone(),
                 });
             }
@@ -225,7 +235,7 @@ impl<'a, L: ComponentSourceLoader> Composer<'a, L> {
 
         if !conflicts.is_empty() {
             return Err(ComposeError::DependencyConflicts {
-                component_id: component.id.clone(),
+                component_id: component_id.to_owned(),
                 conflicts: conflicts
                     .into_iter()
                     .map(|(import_name, infos)| {
@@ -324,24 +334,28 @@ impl<'a, L: ComponentSourceLoader> Composer<'a, L> {
     }
 
     // This function registers a dependency with the composition graph.
-    // Additionally if the locked component specifies that configuration
-    // inheritance is disabled, the `deny-all` adapter is applied to the
-    // dependency.
+    // If the dependency has dependencies of its own, they are composed into
+    // it first. Additionally if the locked component specifies that
+    // configuration inheritance is disabled, the `deny-all` adapter is
+    // applied to the dependency.
     async fn register_dependency(
         &mut self,
         dependency_name: DependencyName,
         dependency: &LockedComponentDependency,
     ) -> anyhow::Result<DependencyInfo> {
-        let mut dependency_source = self
-            .loader
-            .load_component_source(&dependency.source)
-            .await?;
-
         let package_name = match &dependency_name {
             DependencyName::Package(name) => name.package.to_string(),
             DependencyName::Plain(name) => name.to_string(),
         };
 
+        let mut dependency_source = Box::pin(Composer::new(self.loader).compose(
+            &package_name,
+            &dependency.source,
+            &dependency.dependencies,
+        ))
+        .await
+        .with_context(|| format!("failed to compose dependency '{dependency_name}'"))?;
+
         match &dependency.inherit {
             InheritConfiguration::Some(configurations) => {
                 if configurations.is_empty() {
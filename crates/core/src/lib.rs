@@ -25,7 +25,7 @@ pub use wasmtime::{
     Instance as ModuleInstance, Module, Trap,
 };
 
-pub use store::{AsState, Store, StoreBuilder};
+pub use store::{AsState, ResourceUsage, Store, StoreBuilder};
 
 /// The default [`EngineBuilder::epoch_tick_interval`].
 pub const DEFAULT_EPOCH_TICK_INTERVAL: Duration = Duration::from_millis(10);
@@ -38,6 +38,7 @@ const GB: usize = 1 << 30;
 /// This is currently only used for advanced (undocumented) use cases.
 pub struct Config {
     inner: wasmtime::Config,
+    fuel_metering: bool,
 }
 
 impl Config {
@@ -61,6 +62,17 @@ impl Config {
         Ok(())
     }
 
+    /// Enable fuel metering, so that each [`Store`] reports the fuel its
+    /// instances consume in its [`ResourceUsage`].
+    ///
+    /// Fuel is consumed roughly per Wasm instruction executed. Metering slows
+    /// execution somewhat, so it is disabled by default.
+    pub fn enable_fuel_metering(&mut self) -> &mut Self {
+        self.inner.consume_fuel(true);
+        self.fuel_metering = true;
+        self
+    }
+
     /// Disable the pooling instance allocator.
     pub fn disable_pooling(&mut self) -> &mut Self {
         self.inner
@@ -122,7 +134,10 @@ impl Default for Config {
             inner.allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config));
         }
 
-        return Self { inner };
+        return Self {
+            inner,
+            fuel_metering: false,
+        };
 
         fn env<T>(name: &str, default: T) -> T
         where
@@ -204,7 +219,9 @@ pub struct State {
 }
 
 impl State {
-    /// Get the amount of memory in bytes consumed by instances in the store
+    /// Get the amount of memory in bytes consumed by instances in the store.
+    ///
+    /// Linear memories never shrink, so this is also the store's peak memory.
     pub fn memory_consumed(&self) -> u64 {
         self.store_limits.memory_consumed()
     }
@@ -218,6 +235,7 @@ pub struct EngineBuilder<T: 'static> {
     linker: Linker<T>,
     epoch_tick_interval: Duration,
     epoch_ticker_thread: bool,
+    fuel_metering: bool,
 }
 
 impl<T: 'static> EngineBuilder<T> {
@@ -229,6 +247,7 @@ impl<T: 'static> EngineBuilder<T> {
             linker,
             epoch_tick_interval: DEFAULT_EPOCH_TICK_INTERVAL,
             epoch_ticker_thread: true,
+            fuel_metering: config.fuel_metering,
         })
     }
 
@@ -281,6 +300,7 @@ impl<T: 'static> EngineBuilder<T> {
             inner: self.engine,
             linker: self.linker,
             epoch_tick_interval: self.epoch_tick_interval,
+            fuel_metering: self.fuel_metering,
         }
     }
 }
@@ -291,6 +311,7 @@ pub struct Engine<T: 'static> {
     inner: wasmtime::Engine,
     linker: Linker<T>,
     epoch_tick_interval: Duration,
+    fuel_metering: bool,
 }
 
 impl<T: 'static> Engine<T> {
@@ -301,7 +322,11 @@ impl<T: 'static> Engine<T> {
 
     /// Creates a new [`StoreBuilder`].
     pub fn store_builder(&self) -> StoreBuilder {
-        StoreBuilder::new(self.inner.clone(), self.epoch_tick_interval)
+        StoreBuilder::new(
+            self.inner.clone(),
+            self.epoch_tick_interval,
+            self.fuel_metering,
+        )
     }
 
     /// Creates a new [`InstancePre`] for the given [`Component`].
@@ -64,6 +64,11 @@ impl StoreLimitsAsync {
         }
     }
 
+    /// The maximum memory allocation limit in bytes, if any
+    pub fn max_memory_size(&self) -> Option<usize> {
+        self.max_memory_size
+    }
+
     /// How much memory has been consumed in bytes
     pub fn memory_consumed(&self) -> u64 {
         self.memory_consumed
@@ -4,7 +4,23 @@ use std::time::{Duration, Instant};
 use crate::{limits::StoreLimitsAsync, State, WasmtimeEngine};
 
 #[cfg(doc)]
-use crate::EngineBuilder;
+use crate::{Config, EngineBuilder};
+
+// The fuel a store starts with when fuel metering is enabled; "practically
+// unlimited", as metering is for accounting rather than for limiting.
+const INITIAL_FUEL: u64 = u64::MAX / 2;
+
+/// The resources used by the instances in a [`Store`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// The fuel consumed, if fuel metering is enabled with
+    /// [`Config::enable_fuel_metering`].
+    pub fuel_consumed: Option<u64>,
+    /// The peak memory, in bytes, of the instances' linear memories.
+    pub peak_memory: u64,
+}
+
+type UsageReporter = Box<dyn FnOnce(ResourceUsage) + Send>;
 
 /// A `Store` holds the runtime state of a Spin instance.
 ///
@@ -15,6 +31,10 @@ use crate::EngineBuilder;
 pub struct Store<T: 'static> {
     inner: wasmtime::Store<T>,
     epoch_tick_interval: Duration,
+    execution_timeout: Option<Duration>,
+    fuel_metering: bool,
+    peak_memory: fn(&mut T) -> u64,
+    usage_reporter: Option<UsageReporter>,
 }
 
 impl<T: 'static> Store<T> {
@@ -32,13 +52,39 @@ impl<T: 'static> Store<T> {
             tracing::warn!("Execution deadline set in past: {deadline:?} < {now:?}");
             0
         } else {
-            let ticks = duration.as_micros() / self.epoch_tick_interval.as_micros();
-            let ticks = ticks.min(u64::MAX as u128) as u64;
-            ticks + 1 // Add one to allow for current partially-completed tick
+            deadline_ticks(duration, self.epoch_tick_interval)
         };
         self.inner.set_epoch_deadline(ticks);
     }
 
+    /// Restarts the deadline set by [`StoreBuilder::execution_timeout`] from
+    /// now, for a store which was built some time before it is used.
+    pub fn restart_deadline(&mut self) {
+        if let Some(timeout) = self.execution_timeout {
+            self.set_deadline(Instant::now() + timeout);
+        }
+    }
+
+    /// Returns the resources used by the store's instances so far.
+    pub fn resource_usage(&mut self) -> ResourceUsage {
+        let fuel_consumed = if self.fuel_metering {
+            // Fuel can only be unavailable if metering is disabled
+            self.inner.get_fuel().ok().map(|fuel| INITIAL_FUEL - fuel)
+        } else {
+            None
+        };
+        ResourceUsage {
+            fuel_consumed,
+            peak_memory: (self.peak_memory)(self.inner.data_mut()),
+        }
+    }
+
+    /// Has the store pass its [`ResourceUsage`] to `report` when it is
+    /// dropped, at the end of its invocation.
+    pub fn report_usage_on_drop(&mut self, report: impl FnOnce(ResourceUsage) + Send + 'static) {
+        self.usage_reporter = Some(Box::new(report));
+    }
+
     /// Provides access to the inner [`wasmtime::Store`]'s data.
     pub fn data(&self) -> &T {
         self.inner.data()
@@ -50,6 +96,14 @@ impl<T: 'static> Store<T> {
     }
 }
 
+impl<T: 'static> Drop for Store<T> {
+    fn drop(&mut self) {
+        if let Some(report) = self.usage_reporter.take() {
+            report(self.resource_usage());
+        }
+    }
+}
+
 impl<T: 'static> AsRef<wasmtime::Store<T>> for Store<T> {
     fn as_ref(&self) -> &wasmtime::Store<T> {
         &self.inner
@@ -83,15 +137,23 @@ pub struct StoreBuilder {
     engine: WasmtimeEngine,
     epoch_tick_interval: Duration,
     store_limits: StoreLimitsAsync,
+    execution_timeout: Option<Duration>,
+    fuel_metering: bool,
 }
 
 impl StoreBuilder {
     // Called by Engine::store_builder.
-    pub(crate) fn new(engine: WasmtimeEngine, epoch_tick_interval: Duration) -> Self {
+    pub(crate) fn new(
+        engine: WasmtimeEngine,
+        epoch_tick_interval: Duration,
+        fuel_metering: bool,
+    ) -> Self {
         Self {
             engine,
             epoch_tick_interval,
             store_limits: StoreLimitsAsync::default(),
+            execution_timeout: None,
+            fuel_metering,
         }
     }
 
@@ -103,6 +165,23 @@ impl StoreBuilder {
         self.store_limits = StoreLimitsAsync::new(Some(max_memory_size), None);
     }
 
+    /// Lowers the maximum memory allocation limit to `max_memory_size`,
+    /// unless a lower limit is already set.
+    pub fn limit_memory_size(&mut self, max_memory_size: usize) {
+        let limit = match self.store_limits.max_memory_size() {
+            Some(current) => current.min(max_memory_size),
+            None => max_memory_size,
+        };
+        self.max_memory_size(limit);
+    }
+
+    /// Sets the execution timeout, counted from when the [`Store`] is built.
+    ///
+    /// Like [`Store::set_deadline`], this is a rough deadline.
+    pub fn execution_timeout(&mut self, timeout: Duration) {
+        self.execution_timeout = Some(timeout);
+    }
+
     /// Builds a [`Store`] from this builder with given host state data.
     ///
     /// The `T` parameter must provide access to a [`State`] via `impl
@@ -117,15 +196,34 @@ impl StoreBuilder {
         // or execution will trap immediately. Since this is a delta, we need
         // to avoid overflow so we'll use 2^63 which is still "practically
         // forever" for any plausible tick interval.
-        inner.set_epoch_deadline(u64::MAX / 2);
+        let ticks = match self.execution_timeout {
+            Some(timeout) => deadline_ticks(timeout, self.epoch_tick_interval),
+            None => u64::MAX / 2,
+        };
+        inner.set_epoch_deadline(ticks);
+
+        if self.fuel_metering {
+            inner.set_fuel(INITIAL_FUEL)?;
+        }
 
         Ok(Store {
             inner,
             epoch_tick_interval: self.epoch_tick_interval,
+            execution_timeout: self.execution_timeout,
+            fuel_metering: self.fuel_metering,
+            peak_memory: |data| data.as_state().memory_consumed(),
+            usage_reporter: None,
         })
     }
 }
 
+// Converts a duration from now into a number of epoch ticks.
+fn deadline_ticks(duration: Duration, epoch_tick_interval: Duration) -> u64 {
+    let ticks = duration.as_micros() / epoch_tick_interval.as_micros();
+    let ticks = ticks.min(u64::MAX as u128 / 2) as u64;
+    ticks + 1 // Add one to allow for current partially-completed tick
+}
+
 /// For consumers that need to use a type other than [`State`] as the [`Store`]
 /// `data`, this trait must be implemented for that type.
 pub trait AsState {
@@ -1,5 +1,6 @@
 use std::{
     path::PathBuf,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
@@ -47,6 +48,27 @@ async fn test_max_memory_size_violated() {
     assert_eq!(trap.0, 1);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_limit_memory_size_keeps_lower_limit() {
+    let max = 10_000_000;
+    let alloc = max * 2;
+    let err = run_test(
+        ["alloc", &format!("{alloc}")],
+        |store_builder| {
+            store_builder.max_memory_size(max);
+            store_builder.limit_memory_size(max * 10);
+        },
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    let trap = err
+        .root_cause()
+        .downcast_ref::<I32Exit>()
+        .expect("trap error was not an I32Exit");
+    assert_eq!(trap.0, 1);
+}
+
 // FIXME: racy timing test
 #[tokio::test(flavor = "multi_thread")]
 async fn test_set_deadline_obeyed() {
@@ -76,6 +98,59 @@ async fn test_set_deadline_violated() {
     assert_eq!(trap, Trap::Interrupt);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_execution_timeout_violated() {
+    let err = run_test(
+        ["sleep", "100"],
+        |store_builder| {
+            store_builder.execution_timeout(Duration::from_millis(10));
+        },
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    let trap = err.downcast::<Trap>().expect("trap");
+    assert_eq!(trap, Trap::Interrupt);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_restart_deadline() {
+    run_test(
+        ["sleep", "10"],
+        |store_builder| {
+            store_builder.execution_timeout(Duration::from_millis(100));
+        },
+        |store| {
+            // Let the deadline set when the store was built pass
+            std::thread::sleep(Duration::from_millis(150));
+            store.restart_deadline();
+        },
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_resource_usage_reported() {
+    let mut config = Config::default();
+    config.enable_fuel_metering();
+    let reported = Arc::new(Mutex::new(None));
+    run_test_with_config(
+        config,
+        ["alloc", "1000000"],
+        |_| {},
+        |store| {
+            let reported = reported.clone();
+            store.report_usage_on_drop(move |usage| *reported.lock().unwrap() = Some(usage));
+        },
+    )
+    .await
+    .unwrap();
+    let usage = reported.lock().unwrap().expect("usage should be reported");
+    assert!(usage.fuel_consumed.unwrap() > 0);
+    assert!(usage.peak_memory >= 1_000_000);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_panic() {
     let err = run_test(["panic"], |_| {}, |_| {}).await.unwrap_err();
@@ -109,12 +184,20 @@ async fn run_test(
     args: impl IntoIterator<Item = &'_ str>,
     update_store_builder: impl FnOnce(&mut StoreBuilder),
     update_store: impl FnOnce(&mut Store<TestState>),
+) -> anyhow::Result<()> {
+    run_test_with_config(Config::default(), args, update_store_builder, update_store).await
+}
+
+async fn run_test_with_config(
+    mut config: Config,
+    args: impl IntoIterator<Item = &'_ str>,
+    update_store_builder: impl FnOnce(&mut StoreBuilder),
+    update_store: impl FnOnce(&mut Store<TestState>),
 ) -> anyhow::Result<()> {
     let mut factors = TestFactors {
         wasi: WasiFactor::new(DummyFilesMounter),
     };
 
-    let mut config = Config::default();
     config
         .wasmtime_config()
         .wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
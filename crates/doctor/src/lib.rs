@@ -37,6 +37,7 @@ impl Checkup {
             .add_diagnostic::<manifest::upgrade::UpgradeDiagnostic>()
             .add_diagnostic::<manifest::version::VersionDiagnostic>()
             .add_diagnostic::<manifest::trigger::TriggerDiagnostic>()
+            .add_diagnostic::<manifest::validate::ValidationDiagnostic>()
             .add_diagnostic::<rustlang::target::TargetDiagnostic>() // Do toolchain checks _before_ build check
             .add_diagnostic::<wasm::missing::WasmMissingDiagnostic>();
         Ok(checkup)
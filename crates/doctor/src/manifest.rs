@@ -11,6 +11,8 @@ use crate::Treatment;
 pub mod trigger;
 /// Diagnose old app manifest versions.
 pub mod upgrade;
+/// Diagnose problems reported by app manifest validation.
+pub mod validate;
 /// Diagnose upgradable app manifest versions.
 pub mod version;
 
@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use spin_common::ui::quoted_path;
-use spin_manifest::{compat::v1_to_v2_app, schema::v1::AppManifestV1, ManifestVersion};
+use spin_manifest::{compat::convert_v1_to_v2, schema::v1::AppManifestV1, ManifestVersion};
 use toml_edit::{de::from_document, ser::to_document, Item, Table};
 
 use crate::{Diagnosis, Diagnostic, PatientApp, Treatment};
@@ -51,7 +51,8 @@ impl Treatment for UpgradeDiagnosis {
     async fn treat(&self, patient: &mut PatientApp) -> Result<()> {
         let v1: AppManifestV1 = from_document(patient.manifest_doc.clone())
             .context("failed to decode AppManifestV1")?;
-        let v2 = v1_to_v2_app(v1).context("failed to upgrade version 1 manifest to version 2")?;
+        let (v2, warnings) =
+            convert_v1_to_v2(v1).context("failed to upgrade version 1 manifest to version 2")?;
         let mut v2_doc = to_document(&v2)?;
 
         // Format [application] table
@@ -107,6 +108,9 @@ impl Treatment for UpgradeDiagnosis {
         std::fs::write(&patient.manifest_path, v2_doc.to_string())
             .context("failed to write version 2 manifest")?;
         patient.manifest_doc = v2_doc;
+        for warning in warnings {
+            terminal::warn!("{warning}");
+        }
 
         Ok(())
     }
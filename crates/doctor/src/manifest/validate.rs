@@ -0,0 +1,99 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use spin_manifest::validate::{self, Severity};
+use toml::Value;
+
+use crate::{Diagnosis, Diagnostic, PatientApp};
+
+/// ValidationDiagnostic detects problems reported by manifest validation,
+/// such as references to undefined components or undeclared variables.
+#[derive(Default)]
+pub struct ValidationDiagnostic;
+
+#[async_trait]
+impl Diagnostic for ValidationDiagnostic {
+    type Diagnosis = ValidationDiagnosis;
+
+    async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
+        let manifest: toml::Value = toml_edit::de::from_document(patient.manifest_doc.clone())?;
+
+        if manifest.get("spin_manifest_version") != Some(&Value::Integer(2)) {
+            // Only applicable to manifest V2
+            return Ok(vec![]);
+        }
+
+        Ok(validate::validate_file(&patient.manifest_path)?
+            .into_iter()
+            .map(ValidationDiagnosis)
+            .collect())
+    }
+}
+
+/// ValidationDiagnosis represents a problem reported by manifest validation.
+#[derive(Debug)]
+pub struct ValidationDiagnosis(pub validate::Diagnostic);
+
+impl Diagnosis for ValidationDiagnosis {
+    fn description(&self) -> String {
+        let diagnostic = &self.0;
+        let mut description = diagnostic.message.clone();
+        if let Some(location) = &diagnostic.location {
+            description = format!("{}:{}: {description}", location.line, location.column);
+        }
+        if !diagnostic.key.is_empty() {
+            description = format!("{description} (at `{}`)", diagnostic.key.join("."));
+        }
+        description
+    }
+
+    fn is_critical(&self) -> bool {
+        self.0.severity == Severity::Error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::TestPatient;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_correct() {
+        let patient = TestPatient::from_toml_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "app"
+            [[trigger.http]]
+            route = "/..."
+            component = "web"
+            [component.web]
+            source = "web.wasm"
+            "#,
+        );
+        let diags = ValidationDiagnostic.diagnose(&patient).await.unwrap();
+        assert!(diags.is_empty(), "expected correct file; got {diags:?}");
+    }
+
+    #[tokio::test]
+    async fn test_undefined_component() {
+        let patient = TestPatient::from_toml_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "app"
+            [[trigger.http]]
+            route = "/..."
+            component = "missing"
+            "#,
+        );
+        let diags = ValidationDiagnostic.diagnose(&patient).await.unwrap();
+        assert_eq!(diags.len(), 1, "expected one diagnosis, got {diags:?}");
+        assert!(diags[0].is_critical());
+        let description = diags[0].description();
+        assert!(
+            description.contains("undefined component \"missing\""),
+            "{description}"
+        );
+    }
+}
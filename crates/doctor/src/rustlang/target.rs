@@ -14,8 +14,7 @@ impl Diagnostic for TargetDiagnostic {
     async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
         // TODO: this, down to the "does the app use Rust" check, probably ought to move up to the Rust level
         // but we can defer this until we have more Rust diagnoses
-        let manifest_str = patient.manifest_doc.to_string();
-        let manifest = spin_manifest::manifest_from_str(&manifest_str)?;
+        let manifest = spin_manifest::manifest_from_file(&patient.manifest_path)?;
         let uses_rust = manifest.components.values().any(|c| {
             c.build
                 .as_ref()
@@ -77,8 +77,7 @@ impl<T: WasmDiagnostic + Send + Sync> Diagnostic for T {
     type Diagnosis = <Self as WasmDiagnostic>::Diagnosis;
 
     async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
-        let manifest_str = patient.manifest_doc.to_string();
-        let manifest = spin_manifest::manifest_from_str(&manifest_str)?;
+        let manifest = spin_manifest::manifest_from_file(&patient.manifest_path)?;
         let app_dir = parent_dir(&patient.manifest_path)?;
         let mut diagnoses = vec![];
         for (component_id, component) in manifest.components {
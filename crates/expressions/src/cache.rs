@@ -0,0 +1,306 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+
+use crate::{Key, Provider};
+
+type Versioned = (String, Option<String>);
+
+/// A [`Provider`] which caches the values of another provider.
+///
+/// Values are reused until they are older than the TTL, which can be set per
+/// key. Keys the provider has no value for are cached too, for the negative
+/// TTL. Errors are never cached.
+///
+/// With a stale-while-revalidate window, a value which has expired less than
+/// that long ago is still returned straight away, while a fresh value is
+/// fetched in the background for the next caller.
+#[derive(Debug)]
+pub struct CachedProvider<P: ?Sized = dyn Provider> {
+    inner: Arc<P>,
+    ttl: Duration,
+    key_ttls: HashMap<String, Duration>,
+    negative_ttl: Duration,
+    stale_while_revalidate: Duration,
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+    // keys being fetched in the background
+    revalidating: Arc<Mutex<HashSet<String>>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Option<Versioned>,
+    fetched: Instant,
+}
+
+/// Whether a cached entry can be used.
+enum Freshness {
+    Fresh,
+    Stale,
+    Expired,
+}
+
+impl<P: Provider + ?Sized + 'static> CachedProvider<P> {
+    /// Wraps a provider, caching its values for the given TTL.
+    ///
+    /// Missing values are cached for the same TTL, and stale values are not
+    /// served, unless configured otherwise.
+    pub fn new(provider: Arc<P>, ttl: Duration) -> Self {
+        Self {
+            inner: provider,
+            ttl,
+            key_ttls: Default::default(),
+            negative_ttl: ttl,
+            stale_while_revalidate: Duration::ZERO,
+            entries: Default::default(),
+            revalidating: Default::default(),
+        }
+    }
+
+    /// Caches the value of the given key for a different TTL.
+    pub fn with_key_ttl(mut self, key: impl Into<String>, ttl: Duration) -> Self {
+        self.key_ttls.insert(key.into(), ttl);
+        self
+    }
+
+    /// Caches the absence of values for the given TTL.
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /// Serves values for up to the given time after they expire, while
+    /// fetching fresh values in the background.
+    pub fn with_stale_while_revalidate(mut self, window: Duration) -> Self {
+        self.stale_while_revalidate = window;
+        self
+    }
+
+    fn freshness(&self, key: &str, entry: &Entry) -> Freshness {
+        let ttl = match entry.value {
+            Some(_) => self.key_ttls.get(key).copied().unwrap_or(self.ttl),
+            None => self.negative_ttl,
+        };
+        let age = entry.fetched.elapsed();
+        if age < ttl {
+            Freshness::Fresh
+        } else if entry.value.is_some() && age < ttl + self.stale_while_revalidate {
+            Freshness::Stale
+        } else {
+            Freshness::Expired
+        }
+    }
+
+    /// Returns the cached value for the key if it can be used, starting a
+    /// background fetch if it is stale.
+    fn cached(&self, key: &Key) -> Option<Option<Versioned>> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(key.as_str())?;
+        match self.freshness(key.as_str(), entry) {
+            Freshness::Fresh => Some(entry.value.clone()),
+            Freshness::Stale => {
+                self.revalidate(key.as_str());
+                Some(entry.value.clone())
+            }
+            Freshness::Expired => None,
+        }
+    }
+
+    fn store(&self, key: &str, value: Option<Versioned>) {
+        store(&self.entries, key, value);
+    }
+
+    fn revalidate(&self, key: &str) {
+        if !self.revalidating.lock().unwrap().insert(key.to_owned()) {
+            return;
+        }
+        let inner = self.inner.clone();
+        let entries = self.entries.clone();
+        let revalidating = self.revalidating.clone();
+        let key = key.to_owned();
+        tokio::spawn(async move {
+            match inner.get_versioned(&Key(&key)).await {
+                Ok(value) => store(&entries, &key, value),
+                // Keep serving the stale value until it expires
+                Err(err) => tracing::debug!("failed to revalidate variable {key:?}: {err:#}"),
+            }
+            revalidating.lock().unwrap().remove(&key);
+        });
+    }
+}
+
+fn store(entries: &Mutex<HashMap<String, Entry>>, key: &str, value: Option<Versioned>) {
+    let entry = Entry {
+        value,
+        fetched: Instant::now(),
+    };
+    entries.lock().unwrap().insert(key.to_owned(), entry);
+}
+
+#[async_trait]
+impl<P: Provider + ?Sized + 'static> Provider for CachedProvider<P> {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        Ok(self.get_versioned(key).await?.map(|(value, _)| value))
+    }
+
+    async fn get_versioned(&self, key: &Key) -> anyhow::Result<Option<(String, Option<String>)>> {
+        if let Some(value) = self.cached(key) {
+            return Ok(value);
+        }
+        let value = self.inner.get_versioned(key).await?;
+        self.store(key.as_str(), value.clone());
+        Ok(value)
+    }
+
+    // Keys which aren't cached are fetched from the provider in one call
+    async fn get_many_versioned(
+        &self,
+        keys: &[Key<'_>],
+    ) -> anyhow::Result<Vec<Option<(String, Option<String>)>>> {
+        let mut values = Vec::with_capacity(keys.len());
+        let mut missing = vec![];
+        for (idx, key) in keys.iter().enumerate() {
+            match self.cached(key) {
+                Some(value) => values.push(value),
+                None => {
+                    values.push(None);
+                    missing.push(idx);
+                }
+            }
+        }
+        if missing.is_empty() {
+            return Ok(values);
+        }
+        let missing_keys: Vec<_> = missing.iter().map(|&i| Key(keys[i].as_str())).collect();
+        let found = self.inner.get_many_versioned(&missing_keys).await?;
+        anyhow::ensure!(
+            found.len() == missing_keys.len(),
+            "provider {:?} returned {} values for {} keys",
+            self.inner.name(),
+            found.len(),
+            missing_keys.len()
+        );
+        for (idx, value) in missing.into_iter().zip(found) {
+            self.store(keys[idx].as_str(), value.clone());
+            values[idx] = value;
+        }
+        Ok(values)
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn subscribe(&self, key: &Key) -> Option<BoxStream<'static, ()>> {
+        self.inner.subscribe(key)
+    }
+
+    fn config_warnings(&self, keys: &[Key]) -> Vec<String> {
+        self.inner.config_warnings(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Provides `value-N` for `present`, where N counts the calls made.
+    #[derive(Debug, Default)]
+    struct CountingProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Provider for CountingProvider {
+        async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok((key.as_str() == "present").then(|| format!("value-{calls}")))
+        }
+    }
+
+    fn key(key: &str) -> Key<'_> {
+        Key(key)
+    }
+
+    #[tokio::test]
+    async fn caches_values_until_ttl() {
+        let inner = Arc::new(CountingProvider::default());
+        let cached = CachedProvider::new(inner.clone(), Duration::from_secs(60))
+            .with_key_ttl("present", Duration::ZERO);
+        assert_eq!(
+            cached.get(&key("present")).await.unwrap().unwrap(),
+            "value-1"
+        );
+        assert_eq!(
+            cached.get(&key("present")).await.unwrap().unwrap(),
+            "value-2"
+        );
+
+        // Missing values are cached for the negative TTL
+        assert_eq!(cached.get(&key("missing")).await.unwrap(), None);
+        assert_eq!(cached.get(&key("missing")).await.unwrap(), None);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn serves_stale_while_revalidating() {
+        let inner = Arc::new(CountingProvider::default());
+        let cached = CachedProvider::new(inner.clone(), Duration::ZERO)
+            .with_stale_while_revalidate(Duration::from_secs(60));
+        assert_eq!(
+            cached.get(&key("present")).await.unwrap().unwrap(),
+            "value-1"
+        );
+        // The stale value is served while a fresh one is fetched
+        assert_eq!(
+            cached.get(&key("present")).await.unwrap().unwrap(),
+            "value-1"
+        );
+        while inner.calls.load(Ordering::SeqCst) < 2
+            || !cached.revalidating.lock().unwrap().is_empty()
+        {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            cached.get(&key("present")).await.unwrap().unwrap(),
+            "value-2"
+        );
+    }
+
+    /// Provides `value` at version `1` for every key.
+    #[derive(Debug)]
+    struct VersionedProvider;
+
+    #[async_trait]
+    impl Provider for VersionedProvider {
+        async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+            Ok(self.get_versioned(key).await?.map(|(value, _)| value))
+        }
+
+        async fn get_versioned(&self, _: &Key) -> anyhow::Result<Option<Versioned>> {
+            Ok(Some(("value".into(), Some("1".into()))))
+        }
+    }
+
+    #[tokio::test]
+    async fn bulk_fetches_keep_versions() {
+        let cached = CachedProvider::new(Arc::new(VersionedProvider), Duration::from_secs(60));
+        let versioned = Some(("value".to_string(), Some("1".to_string())));
+        assert_eq!(
+            cached
+                .get_many_versioned(&[key("a"), key("b")])
+                .await
+                .unwrap(),
+            [versioned.clone(), versioned.clone()]
+        );
+        // Served from the cache, with the version the provider returned
+        assert_eq!(cached.get_versioned(&key("a")).await.unwrap(), versioned);
+    }
+}
@@ -0,0 +1,208 @@
+use std::{collections::HashMap, sync::Arc};
+
+use base64::Engine as _;
+
+use crate::{template::FilterCall, Error, Result, SecretString};
+
+/// A transformation applied to a value in a template expression, such as
+/// `upper` in `{{ name | upper }}`.
+pub trait Filter: Send + Sync {
+    /// Applies the filter to a value, with the filter's argument if one was
+    /// given (e.g. `'/'` in `{{ url | trim_suffix:'/' }}`).
+    ///
+    /// Errors should not include the value, which may be a secret.
+    fn apply(&self, value: &str, arg: Option<&str>) -> anyhow::Result<String>;
+}
+
+impl<F> Filter for F
+where
+    F: Fn(&str, Option<&str>) -> anyhow::Result<String> + Send + Sync,
+{
+    fn apply(&self, value: &str, arg: Option<&str>) -> anyhow::Result<String> {
+        self(value, arg)
+    }
+}
+
+/// A set of named [`Filter`]s available to templates.
+///
+/// The default set contains the built-in filters:
+/// - `upper`, `lower`: change the case of the value
+/// - `trim`: remove leading and trailing whitespace
+/// - `trim_prefix:'<prefix>'`, `trim_suffix:'<suffix>'`: remove a prefix or
+///   suffix if present
+/// - `base64`, `base64_decode`: encode or decode standard base64
+#[derive(Clone)]
+pub struct Filters {
+    filters: HashMap<String, Arc<dyn Filter>>,
+}
+
+impl Filters {
+    /// Returns a set containing no filters, not even the built-in ones.
+    pub fn empty() -> Self {
+        Self {
+            filters: HashMap::new(),
+        }
+    }
+
+    /// Registers a filter under the given name, replacing any existing filter
+    /// (including built-in filters) with that name.
+    pub fn register(&mut self, name: impl Into<String>, filter: impl Filter + 'static) {
+        self.filters.insert(name.into(), Arc::new(filter));
+    }
+
+    /// Returns true if a filter with the given name is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.filters.contains_key(name)
+    }
+
+    /// Applies each of the given filters to the value in turn.
+    ///
+    /// If the value is `secret`, filter errors are redacted in case they
+    /// include (part of) the value.
+    pub(crate) fn apply(
+        &self,
+        key: &str,
+        value: String,
+        calls: &[FilterCall],
+        secret: bool,
+    ) -> Result<String> {
+        calls.iter().try_fold(value, |value, call| {
+            let filter = self.filters.get(call.name()).ok_or_else(|| {
+                Error::InvalidTemplate(format!("unknown filter {:?}", call.name()))
+            })?;
+            filter.apply(&value, call.arg()).map_err(|err| {
+                let err: &dyn std::fmt::Display = if secret {
+                    &SecretString::new(err.to_string())
+                } else {
+                    &err
+                };
+                Error::InvalidValue(format!(
+                    "filter {:?} failed for {key:?}: {err}",
+                    call.name()
+                ))
+            })
+        })
+    }
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        let mut filters = Self::empty();
+        filters.register("upper", no_arg(|value| Ok(value.to_uppercase())));
+        filters.register("lower", no_arg(|value| Ok(value.to_lowercase())));
+        filters.register("trim", no_arg(|value| Ok(value.trim().to_owned())));
+        filters.register(
+            "trim_prefix",
+            required_arg(|value, prefix| Ok(value.strip_prefix(prefix).unwrap_or(value).into())),
+        );
+        filters.register(
+            "trim_suffix",
+            required_arg(|value, suffix| Ok(value.strip_suffix(suffix).unwrap_or(value).into())),
+        );
+        filters.register(
+            "base64",
+            no_arg(|value| Ok(base64::engine::general_purpose::STANDARD.encode(value))),
+        );
+        filters.register(
+            "base64_decode",
+            no_arg(|value| {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(value)
+                    .map_err(|_| anyhow::anyhow!("value is not valid base64"))?;
+                String::from_utf8(bytes)
+                    .map_err(|_| anyhow::anyhow!("decoded value is not valid UTF-8"))
+            }),
+        );
+        filters
+    }
+}
+
+impl std::fmt::Debug for Filters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<_> = self.filters.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+fn no_arg(
+    f: impl Fn(&str) -> anyhow::Result<String> + Send + Sync,
+) -> impl Fn(&str, Option<&str>) -> anyhow::Result<String> + Send + Sync {
+    move |value, arg| match arg {
+        None => f(value),
+        Some(_) => anyhow::bail!("filter does not take an argument"),
+    }
+}
+
+fn required_arg(
+    f: impl Fn(&str, &str) -> anyhow::Result<String> + Send + Sync,
+) -> impl Fn(&str, Option<&str>) -> anyhow::Result<String> + Send + Sync {
+    move |value, arg| match arg {
+        Some(arg) => f(value, arg),
+        None => anyhow::bail!("filter requires an argument"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::{Part, Template};
+
+    fn apply(expr: &str, value: &str) -> Result<String> {
+        let template = Template::new(expr).unwrap();
+        let Some(Part::Expr(expr)) = template.parts().next() else {
+            panic!("expected an expression");
+        };
+        Filters::default().apply("key", value.into(), expr.filters(), false)
+    }
+
+    #[test]
+    fn builtin_filters() {
+        for (expr, value, expected) in [
+            ("{{ key | upper }}", "abc", "ABC"),
+            ("{{ key | lower }}", "ABC", "abc"),
+            ("{{ key | trim | upper }}", " abc ", "ABC"),
+            ("{{ key | trim_prefix:'https://' }}", "https://a.b", "a.b"),
+            ("{{ key | trim_suffix:'/' }}", "https://a.b/", "https://a.b"),
+            (
+                "{{ key | trim_suffix:\"/\" }}",
+                "https://a.b",
+                "https://a.b",
+            ),
+            ("{{ key | base64 }}", "secret", "c2VjcmV0"),
+            ("{{ key | base64_decode }}", "c2VjcmV0", "secret"),
+        ] {
+            assert_eq!(apply(expr, value).unwrap(), expected, "{expr}");
+        }
+    }
+
+    #[test]
+    fn filter_errors_do_not_include_value() {
+        let err = apply("{{ key | base64_decode }}", "not*base64").unwrap_err();
+        assert!(matches!(err, Error::InvalidValue(_)), "{err}");
+        assert!(!err.to_string().contains("not*base64"), "{err}");
+
+        apply("{{ key | upper:'x' }}", "abc").unwrap_err();
+        apply("{{ key | trim_suffix }}", "abc").unwrap_err();
+        apply("{{ key | nope }}", "abc").unwrap_err();
+    }
+
+    #[test]
+    fn custom_filter() {
+        let mut filters = Filters::default();
+        filters.register("repeat", |value: &str, arg: Option<&str>| {
+            let n: usize = arg.unwrap_or("2").parse()?;
+            Ok(value.repeat(n))
+        });
+        let template = Template::new("{{ key | repeat:3 }}").unwrap();
+        let Some(Part::Expr(expr)) = template.parts().next() else {
+            panic!("expected an expression");
+        };
+        assert_eq!(
+            filters
+                .apply("key", "ab".into(), expr.filters(), false)
+                .unwrap(),
+            "ababab"
+        );
+    }
+}
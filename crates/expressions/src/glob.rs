@@ -0,0 +1,83 @@
+use crate::{Error, Result};
+
+/// A glob pattern over variable keys, e.g. `db_*`.
+///
+/// `*` matches any sequence of characters (including none) and `?` matches a
+/// single character. All other characters match themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Glob(Vec<u8>);
+
+impl Glob {
+    pub(crate) fn new(pattern: &str) -> Result<Self> {
+        if let Some(invalid) = pattern
+            .chars()
+            .find(|&c| !matches!(c, 'a'..='z' | '0'..='9' | '_' | '*' | '?'))
+        {
+            return Err(Error::InvalidName(format!(
+                "{pattern:?}: invalid character {invalid:?} in pattern; must contain only lowercase letters, numbers, underscores, `*` and `?`"
+            )));
+        }
+        Ok(Self(pattern.as_bytes().to_vec()))
+    }
+
+    pub(crate) fn matches(&self, key: &str) -> bool {
+        let (pattern, key) = (self.0.as_slice(), key.as_bytes());
+        let (mut p, mut k) = (0, 0);
+        // The position of the last `*` and the key position it is matched up to
+        let mut backtrack = None;
+        while k < key.len() {
+            match pattern.get(p) {
+                Some(b'*') => {
+                    backtrack = Some((p, k));
+                    p += 1;
+                }
+                Some(&c) if c == b'?' || c == key[k] => {
+                    p += 1;
+                    k += 1;
+                }
+                _ => match backtrack {
+                    // Let the last `*` match one more character and try again
+                    Some((star, star_k)) => {
+                        backtrack = Some((star, star_k + 1));
+                        p = star + 1;
+                        k = star_k + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        pattern[p..].iter().all(|&c| c == b'*')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches() {
+        for (pattern, key, expected) in [
+            ("db_*", "db_host", true),
+            ("db_*", "db_", true),
+            ("db_*", "cache_host", false),
+            ("*_host", "db_host", true),
+            ("*_host", "db_hostname", false),
+            ("db_*_port", "db_primary_port", true),
+            ("db_*_port", "db_port", false),
+            ("db_?", "db_1", true),
+            ("db_?", "db_12", false),
+            ("*", "anything", true),
+            ("exact", "exact", true),
+            ("exact", "exactly", false),
+            ("a*b*c", "aXbYbZc", true),
+        ] {
+            let glob = Glob::new(pattern).unwrap();
+            assert_eq!(glob.matches(key), expected, "{pattern:?} vs {key:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(Glob::new("app.db.*").is_err());
+    }
+}
@@ -1,15 +1,39 @@
+mod cache;
+mod filter;
+mod glob;
+mod merge;
+mod metrics;
 pub mod provider;
+mod secret;
+mod snapshot;
 mod template;
+mod value;
 
-use std::{borrow::Cow, collections::HashMap, fmt::Debug};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Mutex,
+};
+
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use tracing::Instrument;
 
 use spin_locked_app::Variable;
 
 pub use async_trait;
 
-pub use provider::Provider;
+pub use cache::CachedProvider;
+pub use filter::{Filter, Filters};
+pub use merge::{merge_with, MergeStrategy};
+pub use metrics::{ProviderMetrics, ResolverMetrics};
+pub use provider::{Provider, ProviderChain, RotationHook, RoutedProvider, Source};
+pub use secret::SecretString;
+pub use snapshot::{Snapshot, SnapshotVariable};
 use template::Part;
-pub use template::Template;
+pub use template::{Delimiters, Template};
+use value::Constraints;
+pub use value::{TypedValue, VariableType};
 
 /// A [`ProviderResolver`] that can be shared.
 pub type SharedPreparedResolver =
@@ -19,7 +43,11 @@ pub type SharedPreparedResolver =
 #[derive(Debug, Default)]
 pub struct ProviderResolver {
     internal: Resolver,
-    providers: Vec<Box<dyn Provider>>,
+    providers: ProviderChain,
+    // variable key -> provided value, fetched in bulk but not yet used
+    prefetched: Mutex<HashMap<String, SecretString>>,
+    // the name of the profile whose variables were merged in, if any
+    profile: Option<String>,
 }
 
 impl ProviderResolver {
@@ -28,9 +56,32 @@ impl ProviderResolver {
         Ok(Self {
             internal: Resolver::new(variables)?,
             providers: Default::default(),
+            prefetched: Default::default(),
+            profile: None,
         })
     }
 
+    /// Creates a Resolver for the given Tree, overlaid with the variables of
+    /// the named profile.
+    ///
+    /// Profile variables replace application variables of the same name.
+    pub fn with_profile(
+        variables: impl IntoIterator<Item = (String, Variable)>,
+        profile: impl Into<String>,
+        overlay: impl IntoIterator<Item = (String, Variable)>,
+    ) -> Result<Self> {
+        let variables = merge_with(variables, overlay, MergeStrategy::PreferOther)?;
+        Ok(Self {
+            profile: Some(profile.into()),
+            ..Self::new(variables)?
+        })
+    }
+
+    /// Returns the name of the active profile, if any.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
     /// Adds component variable values to the Resolver.
     pub fn add_component_variables(
         &mut self,
@@ -41,21 +92,145 @@ impl ProviderResolver {
             .add_component_variables(component_id, variables)
     }
 
+    /// Allows the given component to read application variables directly, in
+    /// addition to the variables in its own `variables` table.
+    pub fn trust_component(&mut self, component_id: impl Into<String>) {
+        self.internal.trust_component(component_id)
+    }
+
+    /// Registers a template filter, replacing any existing filter with that name.
+    ///
+    /// Filters must be registered before adding component variables which use them.
+    pub fn add_filter(&mut self, name: impl Into<String>, filter: impl Filter + 'static) {
+        self.internal.filters.register(name, filter);
+    }
+
+    /// Replaces the set of template filters.
+    ///
+    /// Filters must be set before adding component variables which use them.
+    pub fn set_filters(&mut self, filters: Filters) {
+        self.internal.filters = filters;
+    }
+
+    /// Sets the delimiters which enclose expressions in component variable
+    /// values, instead of `{{` and `}}`.
+    ///
+    /// Delimiters must be set before adding component variables. They do not
+    /// apply to variable defaults.
+    pub fn set_delimiters(&mut self, delimiters: Delimiters) {
+        self.internal.delimiters = delimiters;
+    }
+
     /// Adds a variable Provider to the Resolver.
+    ///
+    /// Providers are consulted in the order they were added.
     pub fn add_provider(&mut self, provider: Box<dyn Provider>) {
         self.providers.push(provider);
     }
 
+    /// Returns warnings about the configuration of the providers, such as
+    /// routes which no variable can reach.
+    pub fn provider_warnings(&self) -> Vec<String> {
+        let keys: Vec<_> = self.internal.variables.keys().map(|k| Key(k)).collect();
+        self.providers.config_warnings(&keys)
+    }
+
+    /// Returns a handle to counters describing how variables have been
+    /// resolved, such as the latency and failures of each provider.
+    pub fn metrics(&self) -> ResolverMetrics {
+        self.providers.metrics()
+    }
+
+    /// Returns where each variable resolved so far got its value from.
+    pub fn resolved_sources(&self) -> Vec<(String, Source)> {
+        self.providers.resolved_sources()
+    }
+
+    /// Returns the names of the variables which start with the given prefix,
+    /// in sorted order.
+    pub fn iter_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let mut keys: Vec<&str> = self
+            .internal
+            .variables
+            .keys()
+            .map(String::as_str)
+            .filter(|key| key.starts_with(prefix))
+            .collect();
+        keys.sort_unstable();
+        keys.into_iter()
+    }
+
+    /// Returns the names of the variables which match the given glob pattern,
+    /// in sorted order.
+    ///
+    /// In the pattern, `*` matches any sequence of characters and `?` matches
+    /// any single character, so `db_*` matches all variables starting with
+    /// `db_`.
+    pub fn query(&self, pattern: &str) -> Result<Vec<&str>> {
+        let glob = glob::Glob::new(pattern)?;
+        let mut keys: Vec<&str> = self
+            .internal
+            .variables
+            .keys()
+            .map(String::as_str)
+            .filter(|key| glob.matches(key))
+            .collect();
+        keys.sort_unstable();
+        Ok(keys)
+    }
+
+    /// Returns the version of the variable's most recently resolved value, if
+    /// its provider reports versions.
+    pub fn version(&self, key: &str) -> Option<String> {
+        self.providers.version(key)
+    }
+
+    /// Registers a hook which is called with a variable's name and new version
+    /// when its provider reports that the value has been rotated, so that
+    /// anything holding on to the old value can resolve it again.
+    ///
+    /// Rotation is detected when a variable is resolved, by comparing the
+    /// version reported by [`Provider::get_versioned`] with the version last
+    /// seen. The hook is also called for variables whose defaults refer to
+    /// the rotated variable.
+    pub fn on_rotation(&mut self, hook: impl Fn(&str, &str) + Send + Sync + 'static) {
+        let dependents: HashMap<String, Vec<String>> = self
+            .internal
+            .variables
+            .keys()
+            .map(|key| (key.clone(), self.internal.dependents(key)))
+            .collect();
+        self.providers
+            .on_rotation(std::sync::Arc::new(move |key: &str, version: &str| {
+                for dependent in dependents.get(key).into_iter().flatten() {
+                    hook(dependent, version);
+                }
+            }));
+    }
+
     /// Resolves a variable value for the given path.
     pub async fn resolve(&self, component_id: &str, key: Key<'_>) -> Result<String> {
         let template = self.internal.get_template(component_id, key)?;
-        self.resolve_template(template).await
+        self.resolve_template(&template).await
+    }
+
+    /// Returns a stream of the names of variables whose values may have changed.
+    ///
+    /// Only changes reported by providers which support
+    /// [`Provider::subscribe`] are observed. When a variable changes, any
+    /// variables whose defaults refer to it are reported too. Resolve the
+    /// variables again to get their new values.
+    pub fn changes(&self) -> BoxStream<'static, String> {
+        let streams = self.internal.variables.keys().filter_map(|key| {
+            let changes = self.providers.subscribe(&Key(key))?;
+            let affected = self.internal.dependents(key);
+            Some(changes.flat_map(move |()| futures::stream::iter(affected.clone())))
+        });
+        futures::stream::select_all(streams).boxed()
     }
 
     /// Resolves all variables for the given component.
     pub async fn resolve_all(&self, component_id: &str) -> Result<Vec<(String, String)>> {
-        use futures::FutureExt;
-
         let Some(keys2templates) = self.internal.component_configs.get(component_id) else {
             return Ok(vec![]);
         };
@@ -74,39 +249,177 @@ impl ProviderResolver {
         for part in template.parts() {
             resolved_parts.push(match part {
                 Part::Lit(lit) => lit.as_ref().into(),
-                Part::Expr(var) => self.resolve_variable(var).await?.into(),
+                Part::Expr(expr) => {
+                    let value = self.resolve_variable(expr.var()).await?;
+                    let secret = self.internal.is_secret(expr.var());
+                    self.internal
+                        .filters
+                        .apply(expr.var(), value, expr.filters(), secret)?
+                        .into()
+                }
             });
         }
         Ok(resolved_parts.concat())
     }
 
+    /// Fetches the values of all variables from the providers in bulk, using
+    /// [`Provider::get_many_versioned`].
+    ///
+    /// Each prefetched value is used the next time its variable is resolved,
+    /// saving a round trip to the provider; later resolutions query the
+    /// providers again so that changed values are still observed. Calling
+    /// this at startup keeps slow providers from delaying the first request.
+    pub async fn prefetch(&self) -> Result<()> {
+        let keys: Vec<_> = self.internal.variables.keys().map(|k| Key(k)).collect();
+        let values = self
+            .providers
+            .get_many(&keys)
+            .await
+            .map_err(Error::Provider)?;
+        let mut prefetched = self.prefetched.lock().unwrap();
+        for (key, value) in keys.iter().zip(values) {
+            if let Some(value) = value {
+                prefetched.insert(key.as_str().to_owned(), value.into());
+            }
+        }
+        Ok(())
+    }
+
     /// Fully resolve all variables into a [`PreparedResolver`].
     pub async fn prepare(&self) -> Result<PreparedResolver> {
+        self.prefetch().await?;
         let mut variables = HashMap::new();
         for name in self.internal.variables.keys() {
             let value = self.resolve_variable(name).await?;
             variables.insert(name.clone(), value);
         }
-        Ok(PreparedResolver { variables })
+        let secrets = variables
+            .keys()
+            .filter(|key| self.internal.is_secret(key))
+            .cloned()
+            .collect();
+        Ok(PreparedResolver {
+            variables,
+            secrets,
+            filters: self.internal.filters.clone(),
+        })
+    }
+
+    /// Resolves all variables which declare a type or value constraints,
+    /// returning an error for the first which is missing or invalid.
+    ///
+    /// This allows invalid values to be reported at startup rather than
+    /// when a component first reads them.
+    pub async fn validate_constraints(&self) -> Result<()> {
+        for (key, var) in &self.internal.variables {
+            if !var.has_constraints() {
+                continue;
+            }
+            self.peek_variable(key).await?;
+        }
+        Ok(())
     }
 
-    async fn resolve_variable(&self, key: &str) -> Result<String> {
-        for provider in &self.providers {
-            if let Some(value) = provider.get(&Key(key)).await.map_err(Error::Provider)? {
+    /// Resolves all variables into a [`Snapshot`], with secret values redacted.
+    ///
+    /// Unlike [`ProviderResolver::prepare`], variables which fail to resolve
+    /// are included in the snapshot along with the error.
+    pub async fn snapshot(&self) -> Snapshot {
+        let mut variables = std::collections::BTreeMap::new();
+        for key in self.internal.variables.keys() {
+            let secret = self.internal.is_secret(key);
+            let variable = match self.peek_variable(key).await {
+                Ok(value) => SnapshotVariable {
+                    value: (!secret).then_some(value),
+                    secret,
+                    source: self.providers.source(key),
+                    version: self.providers.version(key),
+                    error: None,
+                },
+                Err(err) => SnapshotVariable {
+                    secret,
+                    error: Some(err.to_string()),
+                    ..Default::default()
+                },
+            };
+            variables.insert(key.clone(), variable);
+        }
+        Snapshot {
+            profile: self.profile.clone(),
+            variables,
+        }
+    }
+
+    /// Resolves an application variable and parses it as its declared type.
+    pub async fn resolve_typed(&self, key: Key<'_>) -> Result<TypedValue> {
+        let value = self.resolve_variable(key.as_str()).await?;
+        let variable_type = self.internal.variable_type(key.as_str())?;
+        parse_value(key.as_str(), variable_type, &value)
+    }
+
+    /// Resolves a variable like `resolve_variable`, but without using up any
+    /// prefetched value.
+    async fn peek_variable(&self, key: &str) -> Result<String> {
+        let prefetched = self.prefetched.lock().unwrap().get(key).cloned();
+        match prefetched {
+            Some(value) => {
+                self.internal.check_value(key, value.expose_secret())?;
+                Ok(value.into_inner())
+            }
+            None => self.resolve_variable(key).await,
+        }
+    }
+
+    // Boxed because templated defaults resolve the variables they refer to
+    fn resolve_variable<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<String>> {
+        async move {
+            let key = Key(key);
+            let prefetched = self.prefetched.lock().unwrap().remove(key.as_str());
+            if let Some(value) = prefetched {
+                // The source was recorded when the value was fetched
+                self.providers.metrics().record_cache_hit();
+                let value = value.into_inner();
+                self.internal.check_value(key.as_str(), &value)?;
+                return Ok(value);
+            }
+            if let Some(value) = self.providers.get(&key).await.map_err(Error::Provider)? {
+                self.internal.check_value(key.as_str(), &value)?;
                 return Ok(value);
             }
+            let value = match self.internal.defaults.get(key.as_str()) {
+                // Referenced variables may come from providers, so resolve them here
+                Some(default) if !default.is_literal() => {
+                    let value = self.resolve_template(default).await?;
+                    self.internal.check_value(key.as_str(), &value)?;
+                    value
+                }
+                _ => self.internal.resolve_variable(key.as_str())?,
+            };
+            self.providers.record(&key, Source::Default);
+            Ok(value)
         }
-        self.internal.resolve_variable(key)
+        .instrument(tracing::debug_span!("spin_variables.resolve", key))
+        .boxed()
     }
 }
 
 /// A variable resolver.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct Resolver {
     // variable key -> variable
     variables: HashMap<String, Variable>,
+    // variable key -> constraints, for variables which declare any
+    constraints: HashMap<String, Constraints>,
+    // variable key -> default value template, for variables with a default
+    defaults: HashMap<String, Template>,
     // component ID -> variable key -> variable value template
     component_configs: HashMap<String, HashMap<String, Template>>,
+    // IDs of components which may read application variables directly
+    trusted_components: HashSet<String>,
+    // filters available to templates
+    filters: Filters,
+    // delimiters of expressions in component variable values
+    delimiters: Delimiters,
 }
 
 impl Resolver {
@@ -115,10 +428,44 @@ impl Resolver {
         let variables: HashMap<_, _> = variables.into_iter().collect();
         // Validate keys so that we can rely on them during resolution
         variables.keys().try_for_each(|key| Key::validate(key))?;
-        Ok(Self {
+        let constraints = variables
+            .iter()
+            .filter(|(_, var)| var.has_constraints())
+            .map(|(key, var)| Ok((key.clone(), Constraints::new(key, var)?)))
+            .collect::<Result<_>>()?;
+        let defaults = variables
+            .iter()
+            .filter_map(|(key, var)| Some((key, var.default.as_deref()?)))
+            .map(|(key, default)| {
+                let template = Template::new(default).map_err(|err| {
+                    Error::InvalidDefinition(format!("invalid default for {key:?}: {err}"))
+                })?;
+                if let Some(unknown) = template.variables().find(|v| !variables.contains_key(*v)) {
+                    return Err(Error::InvalidDefinition(format!(
+                        "default for {key:?} refers to unknown variable {unknown:?}"
+                    )));
+                }
+                Ok((key.clone(), template))
+            })
+            .collect::<Result<_>>()?;
+        check_default_cycles(&defaults)?;
+        let resolver = Self {
             variables,
+            constraints,
+            defaults,
             component_configs: Default::default(),
-        })
+            trusted_components: Default::default(),
+            filters: Default::default(),
+            delimiters: Default::default(),
+        };
+        // Validate literal defaults so that invalid values are reported up
+        // front; templated defaults are checked when they are resolved
+        resolver
+            .defaults
+            .iter()
+            .filter(|(_, default)| default.is_literal())
+            .try_for_each(|(key, _)| resolver.check_value(key, &resolver.resolve_variable(key)?))?;
+        Ok(resolver)
     }
 
     /// Adds component variable values to the Resolver.
@@ -143,10 +490,16 @@ impl Resolver {
         Ok(())
     }
 
+    /// Allows the given component to read application variables directly, in
+    /// addition to the variables in its own `variables` table.
+    pub fn trust_component(&mut self, component_id: impl Into<String>) {
+        self.trusted_components.insert(component_id.into());
+    }
+
     /// Resolves a variable value for the given path.
     pub fn resolve(&self, component_id: &str, key: Key<'_>) -> Result<String> {
         let template = self.get_template(component_id, key)?;
-        self.resolve_template(template)
+        self.resolve_template(&template)
     }
 
     /// Resolves the given template.
@@ -155,55 +508,142 @@ impl Resolver {
         for part in template.parts() {
             resolved_parts.push(match part {
                 Part::Lit(lit) => lit.as_ref().into(),
-                Part::Expr(var) => self.resolve_variable(var)?.into(),
+                Part::Expr(expr) => {
+                    let value = self.resolve_variable(expr.var())?;
+                    let secret = self.is_secret(expr.var());
+                    self.filters
+                        .apply(expr.var(), value, expr.filters(), secret)?
+                        .into()
+                }
             });
         }
         Ok(resolved_parts.concat())
     }
 
     /// Gets a template for the given path.
-    fn get_template(&self, component_id: &str, key: Key<'_>) -> Result<&Template> {
-        let configs = self.component_configs.get(component_id).ok_or_else(|| {
-            Error::Undefined(format!("no variable for component {component_id:?}"))
-        })?;
+    ///
+    /// Components may only read the variables in their own `variables` table,
+    /// unless they are trusted to read application variables directly.
+    fn get_template(&self, component_id: &str, key: Key<'_>) -> Result<Cow<'_, Template>> {
         let key = key.as_ref();
-        let template = configs
-            .get(key)
-            .ok_or_else(|| Error::Undefined(format!("no variable for {component_id:?}.{key:?}")))?;
-        Ok(template)
+        let configs = self.component_configs.get(component_id);
+        if let Some(template) = configs.and_then(|configs| configs.get(key)) {
+            return Ok(Cow::Borrowed(template));
+        }
+        if self.variables.contains_key(key) {
+            if self.trusted_components.contains(component_id) {
+                return Ok(Cow::Owned(Template::new(format!("{{{{ {key} }}}}"))?));
+            }
+            return Err(Error::Undefined(format!(
+                "component {component_id:?} may not read application variable {key:?}; \
+                add it to the component's `variables` table"
+            )));
+        }
+        if configs.is_none() {
+            return Err(Error::Undefined(format!(
+                "no variable for component {component_id:?}"
+            )));
+        }
+        Err(Error::Undefined(format!(
+            "no variable for {component_id:?}.{key:?}"
+        )))
     }
 
     fn resolve_variable(&self, key: &str) -> Result<String> {
-        let var = self
-            .variables
-            .get(key)
+        if !self.variables.contains_key(key) {
             // This should have been caught by validate_template
-            .ok_or_else(|| Error::InvalidName(key.to_string()))?;
-
-        var.default.clone().ok_or_else(|| {
+            return Err(Error::InvalidName(key.to_string()));
+        }
+        let default = self.defaults.get(key).ok_or_else(|| {
             Error::Provider(anyhow::anyhow!(
                 "no provider resolved required variable {key:?}"
             ))
-        })
+        })?;
+        let value = self.resolve_template(default)?;
+        if !default.is_literal() {
+            self.check_value(key, &value)?;
+        }
+        Ok(value)
+    }
+
+    /// Returns the given variable and all variables whose defaults refer to
+    /// it, directly or indirectly.
+    fn dependents(&self, key: &str) -> Vec<String> {
+        let mut dependents = vec![key.to_owned()];
+        let mut idx = 0;
+        while let Some(current) = dependents.get(idx).cloned() {
+            for (dependent, default) in &self.defaults {
+                if default.variables().any(|var| var == current) && !dependents.contains(dependent)
+                {
+                    dependents.push(dependent.clone());
+                }
+            }
+            idx += 1;
+        }
+        dependents
+    }
+
+    /// Returns true if the given variable is declared with `secret = true`.
+    fn is_secret(&self, key: &str) -> bool {
+        self.variables.get(key).is_some_and(|var| var.secret)
+    }
+
+    fn variable_type(&self, key: &str) -> Result<VariableType> {
+        self.variables
+            .get(key)
+            .map(|var| var.variable_type)
+            .ok_or_else(|| Error::InvalidName(key.to_string()))
+    }
+
+    /// Checks that a value satisfies the given variable's type and constraints.
+    fn check_value(&self, key: &str, value: &str) -> Result<()> {
+        match self.constraints.get(key) {
+            Some(constraints) => constraints.check(key, value).map(|_| ()),
+            None => Ok(()),
+        }
     }
 
     fn validate_template(&self, template: String) -> Result<Template> {
-        let template = Template::new(template)?;
-        // Validate template variables are valid
+        let template = Template::with_delimiters(template, self.delimiters.clone())?;
+        // Validate template variables and filters are valid
         template.parts().try_for_each(|part| match part {
-            Part::Expr(var) if !self.variables.contains_key(var.as_ref()) => {
-                Err(Error::InvalidTemplate(format!("unknown variable {var:?}")))
-            }
+            Part::Expr(expr) if !self.variables.contains_key(expr.var()) => Err(
+                Error::InvalidTemplate(format!("unknown variable {:?}", expr.var())),
+            ),
+            Part::Expr(expr) => expr.filters().iter().try_for_each(|filter| {
+                if self.filters.contains(filter.name()) {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidTemplate(format!(
+                        "unknown filter {:?}",
+                        filter.name()
+                    )))
+                }
+            }),
             _ => Ok(()),
         })?;
         Ok(template)
     }
 }
 
+// Defaults and constraints are omitted as they may contain secret values
+impl Debug for Resolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resolver")
+            .field("variables", &self.variables)
+            .field("component_configs", &self.component_configs)
+            .field("filters", &self.filters)
+            .finish_non_exhaustive()
+    }
+}
+
 /// A resolver who has resolved all variables.
 #[derive(Default)]
 pub struct PreparedResolver {
     variables: HashMap<String, String>,
+    // keys of variables declared with `secret = true`
+    secrets: HashSet<String>,
+    filters: Filters,
 }
 
 impl PreparedResolver {
@@ -213,7 +653,13 @@ impl PreparedResolver {
         for part in template.parts() {
             resolved_parts.push(match part {
                 Part::Lit(lit) => lit.as_ref().into(),
-                Part::Expr(var) => self.resolve_variable(var)?.into(),
+                Part::Expr(expr) => {
+                    let value = self.resolve_variable(expr.var())?;
+                    let secret = self.secrets.contains(expr.var());
+                    self.filters
+                        .apply(expr.var(), value, expr.filters(), secret)?
+                        .into()
+                }
             });
         }
         Ok(resolved_parts.concat())
@@ -225,6 +671,107 @@ impl PreparedResolver {
             .cloned()
             .ok_or(Error::InvalidName(key.to_string()))
     }
+
+    /// Returns a resolved variable parsed as an integer.
+    pub fn get_int(&self, key: &str) -> Result<i64> {
+        self.get_parsed(key, VariableType::Int, value::parse_int)
+    }
+
+    /// Returns a resolved variable parsed as a float.
+    pub fn get_float(&self, key: &str) -> Result<f64> {
+        self.get_parsed(key, VariableType::Float, value::parse_float)
+    }
+
+    /// Returns a resolved variable parsed as a boolean.
+    pub fn get_bool(&self, key: &str) -> Result<bool> {
+        self.get_parsed(key, VariableType::Bool, value::parse_bool)
+    }
+
+    /// Returns a resolved variable parsed as a duration.
+    pub fn get_duration(&self, key: &str) -> Result<std::time::Duration> {
+        self.get_parsed(key, VariableType::Duration, value::parse_duration)
+    }
+
+    /// Returns a resolved variable parsed as a comma-separated list of strings.
+    pub fn get_string_list(&self, key: &str) -> Result<Vec<String>> {
+        self.get_parsed(key, VariableType::StringList, value::parse_string_list)
+    }
+
+    fn get_parsed<T>(
+        &self,
+        key: &str,
+        variable_type: VariableType,
+        parse: impl FnOnce(&str) -> std::result::Result<T, String>,
+    ) -> Result<T> {
+        parse(&self.resolve_variable(key)?)
+            .map_err(|reason| value::invalid_value(key, variable_type, reason))
+    }
+}
+
+impl Debug for PreparedResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let variables: HashMap<_, _> = self
+            .variables
+            .iter()
+            .map(|(key, value)| {
+                let value: Box<dyn Debug> = if self.secrets.contains(key) {
+                    Box::new(SecretString::new(value.as_str()))
+                } else {
+                    Box::new(value)
+                };
+                (key, value)
+            })
+            .collect();
+        f.debug_struct("PreparedResolver")
+            .field("variables", &variables)
+            .field("filters", &self.filters)
+            .finish()
+    }
+}
+
+/// Checks that no variable's default refers back to itself, directly or
+/// through other defaults, reporting the full chain of references if so.
+fn check_default_cycles(defaults: &HashMap<String, Template>) -> Result<()> {
+    fn visit<'a>(
+        key: &'a str,
+        defaults: &'a HashMap<String, Template>,
+        path: &mut Vec<&'a str>,
+        checked: &mut HashSet<&'a str>,
+    ) -> Result<()> {
+        if checked.contains(key) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|k| *k == key) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(key);
+            return Err(Error::InvalidDefinition(format!(
+                "variable defaults refer to each other in a cycle: {}",
+                cycle.join(" -> ")
+            )));
+        }
+        if let Some(default) = defaults.get(key) {
+            path.push(key);
+            for var in default.variables() {
+                visit(var, defaults, path, checked)?;
+            }
+            path.pop();
+        }
+        checked.insert(key);
+        Ok(())
+    }
+
+    // Visit in a stable order so that the reported cycle is deterministic
+    let mut keys: Vec<_> = defaults.keys().collect();
+    keys.sort();
+    let mut checked = HashSet::new();
+    keys.into_iter()
+        .try_for_each(|key| visit(key, defaults, &mut vec![], &mut checked))
+}
+
+/// Parses a variable value, naming the variable and type on failure.
+fn parse_value(key: &str, variable_type: VariableType, value: &str) -> Result<TypedValue> {
+    TypedValue::parse(variable_type, value)
+        .map_err(|reason| value::invalid_value(key, variable_type, reason))
 }
 
 /// A variable key
@@ -242,30 +789,11 @@ impl<'a> Key<'a> {
         self.0
     }
 
-    // To allow various (env var, file path) transformations:
-    // - must start with an ASCII letter
-    // - underscores are allowed; one at a time between other characters
-    // - all other characters must be ASCII alphanumeric
+    // Keys follow the same rules as variable names in the manifest, which
+    // allow various (env var, file path) transformations.
     fn validate(key: &str) -> Result<()> {
-        {
-            if key.is_empty() {
-                Err("must not be empty".to_string())
-            } else if let Some(invalid) = key
-                .chars()
-                .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == &'_'))
-            {
-                Err(format!("invalid character {invalid:?}. Variable names may contain only lower-case letters, numbers, and underscores."))
-            } else if !key.bytes().next().unwrap().is_ascii_lowercase() {
-                Err("must start with a lowercase ASCII letter".to_string())
-            } else if !key.bytes().last().unwrap().is_ascii_alphanumeric() {
-                Err("must end with a lowercase ASCII letter or digit".to_string())
-            } else if key.contains("__") {
-                Err("must not contain multiple consecutive underscores".to_string())
-            } else {
-                Ok(())
-            }
-        }
-        .map_err(|reason| Error::InvalidName(format!("{key:?}: {reason}")))
+        spin_serde::LowerSnakeId::validate(key)
+            .map_err(|reason| Error::InvalidName(format!("{key:?}: {reason}")))
     }
 }
 
@@ -303,10 +831,23 @@ pub enum Error {
     /// Undefined variable.
     #[error("undefined variable: {0}")]
     Undefined(String),
+
+    /// Variable value is invalid for its declared type or constraints.
+    #[error("invalid variable value: {0}")]
+    InvalidValue(String),
+
+    /// Invalid variable definition, e.g. a malformed constraint.
+    #[error("invalid variable definition: {0}")]
+    InvalidDefinition(String),
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
     use async_trait::async_trait;
 
     use super::*;
@@ -323,6 +864,10 @@ mod tests {
                 _ => Ok(None),
             }
         }
+
+        fn name(&self) -> &str {
+            "test"
+        }
     }
 
     async fn test_resolve(template: &str) -> Result<String> {
@@ -331,14 +876,14 @@ mod tests {
                 "required".into(),
                 Variable {
                     default: None,
-                    secret: false,
+                    ..Default::default()
                 },
             ),
             (
                 "default".into(),
                 Variable {
                     default: Some("default-value".into()),
-                    secret: false,
+                    ..Default::default()
                 },
             ),
         ])
@@ -363,6 +908,554 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn resolve_variable_filters() {
+        assert_eq!(
+            test_resolve("{{ default | upper }}/{{ required | trim_prefix:'provider-' }}")
+                .await
+                .unwrap(),
+            "DEFAULT-VALUE/value"
+        );
+    }
+
+    #[tokio::test]
+    async fn custom_filter() {
+        let mut resolver = ProviderResolver::new([(
+            "name".into(),
+            Variable {
+                default: Some("spin".into()),
+                ..Default::default()
+            },
+        )])
+        .unwrap();
+        resolver
+            .add_component_variables(
+                "test-component",
+                [("key".into(), "{{ name | shout }}".into())],
+            )
+            .unwrap_err();
+        resolver.add_filter("shout", |value: &str, _: Option<&str>| {
+            Ok(format!("{value}!"))
+        });
+        resolver
+            .add_component_variables(
+                "test-component",
+                [("key".into(), "{{ name | shout }}".into())],
+            )
+            .unwrap();
+        assert_eq!(
+            resolver
+                .resolve("test-component", Key("key"))
+                .await
+                .unwrap(),
+            "spin!"
+        );
+        let prepared = resolver.prepare().await.unwrap();
+        assert_eq!(
+            prepared
+                .resolve_template(&Template::new("{{ name | shout | upper }}").unwrap())
+                .unwrap(),
+            "SPIN!"
+        );
+    }
+
+    #[tokio::test]
+    async fn templated_defaults() {
+        let mut resolver = ProviderResolver::new([
+            ("required".into(), Variable::default()),
+            (
+                "base".into(),
+                Variable {
+                    default: Some("{{ required }}/api".into()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "users".into(),
+                Variable {
+                    default: Some("{{ base | upper }}/users".into()),
+                    ..Default::default()
+                },
+            ),
+        ])
+        .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        let prepared = resolver.prepare().await.unwrap();
+        assert_eq!(
+            prepared.resolve_variable("users").unwrap(),
+            "PROVIDER-VALUE/API/users"
+        );
+    }
+
+    #[derive(Debug)]
+    struct ChangingProvider(
+        std::sync::Mutex<Option<futures::channel::mpsc::UnboundedReceiver<()>>>,
+    );
+
+    #[async_trait]
+    impl Provider for ChangingProvider {
+        async fn get(&self, _key: &Key) -> anyhow::Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn subscribe(&self, key: &Key) -> Option<BoxStream<'static, ()>> {
+            if key.as_str() != "secret" {
+                return None;
+            }
+            Some(self.0.lock().unwrap().take()?.boxed())
+        }
+    }
+
+    #[tokio::test]
+    async fn changes_include_dependents() {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let mut resolver = ProviderResolver::new([
+            ("secret".into(), Variable::default()),
+            (
+                "url".into(),
+                Variable {
+                    default: Some("https://user:{{ secret }}@example.com".into()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "unrelated".into(),
+                Variable {
+                    default: Some("unrelated".into()),
+                    ..Default::default()
+                },
+            ),
+        ])
+        .unwrap();
+        resolver.add_provider(Box::new(ChangingProvider(Some(rx).into())));
+        let mut changes = resolver.changes();
+
+        tx.unbounded_send(()).unwrap();
+        let mut changed = vec![changes.next().await.unwrap(), changes.next().await.unwrap()];
+        changed.sort();
+        assert_eq!(changed, ["secret", "url"]);
+
+        drop(tx);
+        assert_eq!(changes.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn secrets_are_redacted_from_errors() {
+        const SENTINEL: &str = "SENTINEL-hunter2";
+
+        #[derive(Debug)]
+        struct SecretProvider;
+
+        #[async_trait]
+        impl Provider for SecretProvider {
+            async fn get(&self, _key: &Key) -> anyhow::Result<Option<String>> {
+                Ok(Some(SENTINEL.into()))
+            }
+        }
+
+        let secret = |variable: Variable| Variable {
+            secret: true,
+            ..variable
+        };
+        let mut resolver = ProviderResolver::new([
+            (
+                "typed".into(),
+                secret(Variable {
+                    variable_type: VariableType::Int,
+                    ..Default::default()
+                }),
+            ),
+            (
+                "allowed".into(),
+                secret(Variable {
+                    allowed_values: vec!["a".into(), "b".into()],
+                    ..Default::default()
+                }),
+            ),
+            (
+                "patterned".into(),
+                secret(Variable {
+                    pattern: Some("[0-9]+".into()),
+                    ..Default::default()
+                }),
+            ),
+            ("filtered".into(), secret(Variable::default())),
+            (
+                "defaulted".into(),
+                secret(Variable {
+                    default: Some(SENTINEL.into()),
+                    ..Default::default()
+                }),
+            ),
+        ])
+        .unwrap();
+        // Echoes the value in its errors, which a custom filter might well do
+        resolver.add_filter("echo", |value: &str, _: Option<&str>| {
+            anyhow::bail!("can't handle {value:?}")
+        });
+        resolver
+            .add_component_variables(
+                "component",
+                [
+                    ("typed".into(), "{{ typed }}".into()),
+                    ("allowed".into(), "{{ allowed }}".into()),
+                    ("patterned".into(), "{{ patterned }}".into()),
+                    ("filtered".into(), "{{ filtered | upper | echo }}".into()),
+                ],
+            )
+            .unwrap();
+        assert!(!format!("{resolver:?}").contains(SENTINEL));
+
+        resolver.add_provider(Box::new(SecretProvider));
+        for key in ["typed", "allowed", "patterned", "filtered"] {
+            let err = resolver.resolve("component", Key(key)).await.unwrap_err();
+            let output = format!("{err} {err:?}");
+            assert!(output.contains(key), "{output}");
+            assert!(
+                !output
+                    .to_ascii_uppercase()
+                    .contains(SENTINEL.to_ascii_uppercase().as_str()),
+                "{output}"
+            );
+        }
+
+        let mut resolver =
+            ProviderResolver::new([("password".into(), secret(Variable::default()))]).unwrap();
+        resolver.add_provider(Box::new(SecretProvider));
+        let prepared = resolver.prepare().await.unwrap();
+        assert!(!format!("{prepared:?}").contains(SENTINEL));
+        assert_eq!(prepared.resolve_variable("password").unwrap(), SENTINEL);
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct CountingProvider {
+        gets: Arc<AtomicUsize>,
+        get_manys: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Provider for CountingProvider {
+        async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+            self.gets.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(format!("{}-value", key.as_str())))
+        }
+
+        async fn get_many_versioned(
+            &self,
+            keys: &[Key<'_>],
+        ) -> anyhow::Result<Vec<Option<(String, Option<String>)>>> {
+            self.get_manys.fetch_add(1, Ordering::SeqCst);
+            Ok(keys
+                .iter()
+                .map(|key| Some((format!("{}-value", key.as_str()), None)))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn prefetch_batches_provider_calls() {
+        let provider = CountingProvider::default();
+        let mut resolver = ProviderResolver::new(
+            ["a", "b", "c"].map(|key| (key.to_string(), Variable::default())),
+        )
+        .unwrap();
+        resolver.add_provider(Box::new(provider.clone()));
+
+        let prepared = resolver.prepare().await.unwrap();
+        assert_eq!(prepared.resolve_variable("b").unwrap(), "b-value");
+        assert_eq!(provider.get_manys.load(Ordering::SeqCst), 1);
+        assert_eq!(provider.gets.load(Ordering::SeqCst), 0);
+
+        // Prefetched values are only used once
+        resolver.prefetch().await.unwrap();
+        resolver.resolve_variable("a").await.unwrap();
+        assert_eq!(provider.gets.load(Ordering::SeqCst), 0);
+        resolver.resolve_variable("a").await.unwrap();
+        assert_eq!(provider.gets.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn prefix_and_glob_queries() {
+        let resolver = ProviderResolver::new(
+            ["db_host", "db_port", "db_replica_port", "cache_host"]
+                .map(|key| (key.to_string(), Variable::default())),
+        )
+        .unwrap();
+        assert_eq!(
+            resolver.iter_prefix("db_").collect::<Vec<_>>(),
+            ["db_host", "db_port", "db_replica_port"]
+        );
+        assert_eq!(resolver.query("*_host").unwrap(), ["cache_host", "db_host"]);
+        assert_eq!(resolver.query("db_*_port").unwrap(), ["db_replica_port"]);
+        assert!(resolver.query("db.*").is_err());
+    }
+
+    #[tokio::test]
+    async fn component_scope() {
+        let mut resolver = ProviderResolver::new([
+            (
+                "shared".into(),
+                Variable {
+                    default: Some("shared-value".into()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "private".into(),
+                Variable {
+                    default: Some("private-value".into()),
+                    ..Default::default()
+                },
+            ),
+        ])
+        .unwrap();
+        for component_id in ["app", "admin"] {
+            resolver
+                .add_component_variables(component_id, [("shared".into(), "{{ shared }}".into())])
+                .unwrap();
+        }
+        resolver.trust_component("admin");
+
+        let resolve = |component_id, key| resolver.resolve(component_id, Key(key));
+        assert_eq!(resolve("app", "shared").await.unwrap(), "shared-value");
+        let err = resolve("app", "private").await.unwrap_err();
+        assert!(matches!(err, Error::Undefined(_)), "{err:?}");
+        let msg = err.to_string();
+        assert!(
+            msg.contains("\"app\"") && msg.contains("\"private\""),
+            "{msg}"
+        );
+        assert_eq!(resolve("admin", "private").await.unwrap(), "private-value");
+        assert!(resolve("admin", "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rotation_hooks() {
+        #[derive(Clone, Debug, Default)]
+        struct VersionedProvider {
+            version: Arc<AtomicUsize>,
+        }
+
+        #[async_trait]
+        impl Provider for VersionedProvider {
+            async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+                Ok(self.get_versioned(key).await?.map(|(value, _)| value))
+            }
+
+            async fn get_versioned(
+                &self,
+                key: &Key,
+            ) -> anyhow::Result<Option<(String, Option<String>)>> {
+                if key.as_str() != "password" {
+                    return Ok(None);
+                }
+                let version = self.version.load(Ordering::SeqCst).to_string();
+                Ok(Some((format!("secret-{version}"), Some(version))))
+            }
+        }
+
+        let provider = VersionedProvider::default();
+        let mut resolver = ProviderResolver::new([
+            (
+                "password".into(),
+                Variable {
+                    secret: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                "dsn".into(),
+                Variable {
+                    default: Some("db://user:{{ password }}@host".into()),
+                    ..Default::default()
+                },
+            ),
+        ])
+        .unwrap();
+        resolver.add_provider(Box::new(provider.clone()));
+        let rotated = Arc::new(Mutex::new(vec![]));
+        resolver.on_rotation({
+            let rotated = rotated.clone();
+            move |key, version| rotated.lock().unwrap().push(format!("{key}@{version}"))
+        });
+
+        assert_eq!(
+            resolver.resolve_variable("password").await.unwrap(),
+            "secret-0"
+        );
+        assert_eq!(resolver.version("password").as_deref(), Some("0"));
+        assert!(rotated.lock().unwrap().is_empty());
+
+        provider.version.store(1, Ordering::SeqCst);
+        assert_eq!(
+            resolver.resolve_variable("password").await.unwrap(),
+            "secret-1"
+        );
+        assert_eq!(resolver.version("password").as_deref(), Some("1"));
+        let mut rotated = rotated.lock().unwrap().clone();
+        rotated.sort();
+        assert_eq!(rotated, ["dsn@1", "password@1"]);
+    }
+
+    #[tokio::test]
+    async fn profile_overrides_variables() {
+        let resolver = ProviderResolver::with_profile(
+            [
+                (
+                    "log_level".to_string(),
+                    Variable {
+                        default: Some("debug".into()),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "host".to_string(),
+                    Variable {
+                        default: Some("localhost".into()),
+                        ..Default::default()
+                    },
+                ),
+            ],
+            "prod",
+            [(
+                "log_level".to_string(),
+                Variable {
+                    default: Some("warn".into()),
+                    ..Default::default()
+                },
+            )],
+        )
+        .unwrap();
+        assert_eq!(resolver.profile(), Some("prod"));
+        assert_eq!(
+            resolver.resolve_variable("log_level").await.unwrap(),
+            "warn"
+        );
+        assert_eq!(
+            resolver.resolve_variable("host").await.unwrap(),
+            "localhost"
+        );
+        assert_eq!(resolver.snapshot().await.profile.as_deref(), Some("prod"));
+    }
+
+    #[tokio::test]
+    async fn metrics() {
+        let mut resolver = ProviderResolver::new(
+            ["required", "broken"].map(|key| (key.to_string(), Variable::default())),
+        )
+        .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        let metrics = resolver.metrics();
+
+        resolver.resolve_variable("required").await.unwrap();
+        resolver.resolve_variable("broken").await.unwrap_err();
+        let test = &metrics.providers()["test"];
+        assert_eq!(test.calls, 2);
+        assert_eq!(test.failures, 1);
+        assert!(test.max_latency <= test.total_latency);
+        assert_eq!(metrics.cache_hits(), 0);
+
+        let mut resolver =
+            ProviderResolver::new([("required".to_string(), Variable::default())]).unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        resolver.prefetch().await.unwrap();
+        resolver.resolve_variable("required").await.unwrap();
+        assert_eq!(resolver.metrics().cache_hits(), 1);
+        assert_eq!(resolver.metrics().providers()["test"].calls, 1);
+    }
+
+    #[tokio::test]
+    async fn snapshot() {
+        let mut resolver = ProviderResolver::new([
+            (
+                "required".into(),
+                Variable {
+                    secret: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                "default".into(),
+                Variable {
+                    default: Some("default-value".into()),
+                    ..Default::default()
+                },
+            ),
+            ("broken".into(), Variable::default()),
+        ])
+        .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        let snapshot = resolver.snapshot().await;
+
+        let required = &snapshot.variables["required"];
+        assert_eq!(required.value, None);
+        assert!(required.secret);
+        assert_eq!(required.source, Some(Source::Provider("test".into())));
+
+        let default = &snapshot.variables["default"];
+        assert_eq!(default.value.as_deref(), Some("default-value"));
+        assert_eq!(default.source, Some(Source::Default));
+
+        let broken = &snapshot.variables["broken"];
+        assert!(broken.error.as_ref().unwrap().contains("broken"));
+    }
+
+    #[test]
+    fn templated_default_cycles() {
+        let variable = |default: &str| Variable {
+            default: Some(default.into()),
+            ..Default::default()
+        };
+        let Err(Error::InvalidDefinition(msg)) = Resolver::new([
+            ("a".into(), variable("{{ b }}")),
+            ("b".into(), variable("x-{{ c }}")),
+            ("c".into(), variable("{{ a }}-y")),
+        ]) else {
+            panic!("expected a cycle error");
+        };
+        assert!(msg.contains("a -> b -> c -> a"), "{msg}");
+
+        Resolver::new([("a".into(), variable("{{ a }}"))]).unwrap_err();
+        Resolver::new([("a".into(), variable("{{ unknown }}"))]).unwrap_err();
+        // Diamonds are fine
+        Resolver::new([
+            ("a".into(), variable("{{ b }}{{ c }}")),
+            ("b".into(), variable("{{ c }}")),
+            ("c".into(), variable("c")),
+        ])
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn resolved_sources() {
+        let mut resolver = ProviderResolver::new([
+            (
+                "required".into(),
+                Variable {
+                    default: None,
+                    ..Default::default()
+                },
+            ),
+            (
+                "default".into(),
+                Variable {
+                    default: Some("default-value".into()),
+                    ..Default::default()
+                },
+            ),
+        ])
+        .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        resolver.prepare().await.unwrap();
+        assert_eq!(
+            resolver.resolved_sources(),
+            [
+                ("default".to_string(), Source::Default),
+                ("required".to_string(), Source::Provider("test".to_string())),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn resolve_variable_provider() {
         assert_eq!(
@@ -371,16 +1464,113 @@ mod tests {
         );
     }
 
+    fn typed_resolver() -> ProviderResolver {
+        let mut resolver = ProviderResolver::new([
+            (
+                "port".into(),
+                Variable {
+                    default: Some("8080".into()),
+                    variable_type: VariableType::Int,
+                    ..Default::default()
+                },
+            ),
+            (
+                "required".into(),
+                Variable {
+                    variable_type: VariableType::Bool,
+                    ..Default::default()
+                },
+            ),
+        ])
+        .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        resolver
+    }
+
+    #[tokio::test]
+    async fn resolve_typed() {
+        let resolver = typed_resolver();
+        assert_eq!(
+            resolver.resolve_typed(Key("port")).await.unwrap(),
+            TypedValue::Int(8080)
+        );
+        // TestProvider provides "provider-value" which is not a bool
+        let Err(Error::InvalidValue(msg)) = resolver.prepare().await else {
+            panic!("expected invalid value error")
+        };
+        assert!(msg.contains("\"required\""), "{msg}");
+        assert!(!msg.contains("provider-value"), "{msg}");
+    }
+
+    #[test]
+    fn invalid_typed_default() {
+        ProviderResolver::new([(
+            "port".into(),
+            Variable {
+                default: Some("eighty".into()),
+                variable_type: VariableType::Int,
+                ..Default::default()
+            },
+        )])
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn validate_constraints() {
+        let mut resolver = ProviderResolver::new([(
+            "required".into(),
+            Variable {
+                allowed_values: vec!["provider-value".into()],
+                ..Default::default()
+            },
+        )])
+        .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        resolver.validate_constraints().await.unwrap();
+
+        let mut resolver = ProviderResolver::new([(
+            "required".into(),
+            Variable {
+                pattern: Some("https://.*".into()),
+                ..Default::default()
+            },
+        )])
+        .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        let err = resolver.validate_constraints().await.unwrap_err();
+        assert!(matches!(err, Error::InvalidValue(_)), "{err}");
+    }
+
+    #[test]
+    fn prepared_typed_getters() {
+        let prepared = PreparedResolver {
+            variables: [
+                ("port".to_string(), "8080".to_string()),
+                ("timeout".to_string(), "5s".to_string()),
+                ("hosts".to_string(), "a,b".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        assert_eq!(prepared.get_int("port").unwrap(), 8080);
+        assert_eq!(
+            prepared.get_duration("timeout").unwrap(),
+            std::time::Duration::from_secs(5)
+        );
+        assert_eq!(prepared.get_string_list("hosts").unwrap(), ["a", "b"]);
+        prepared.get_bool("port").unwrap_err();
+    }
+
     #[test]
     fn keys_good() {
-        for key in ["a", "abc", "a1b2c3", "a_1", "a_1_b_3"] {
+        for key in ["a", "abc", "a1b2c3", "a_b1", "a_b1_c3"] {
             Key::new(key).expect(key);
         }
     }
 
     #[test]
     fn keys_bad() {
-        for key in ["", "aX", "1bc", "_x", "x.y", "x_", "a__b", "x-y"] {
+        for key in ["", "aX", "1bc", "_x", "x.y", "x_", "a__b", "x-y", "a_1"] {
             Key::new(key).expect_err(key);
         }
     }
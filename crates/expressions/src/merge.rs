@@ -0,0 +1,94 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use spin_locked_app::Variable;
+
+use crate::{Error, Result};
+
+/// How [`merge_with`] handles a variable which is declared on both sides.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Fail with an error naming the variable.
+    #[default]
+    Error,
+    /// Keep the base declaration.
+    PreferSelf,
+    /// Replace the base declaration with the other one, e.g. to let an
+    /// overlay override a default.
+    PreferOther,
+}
+
+/// Merges two sets of variable declarations, such as a base configuration
+/// and an overlay for a particular environment.
+///
+/// Declarations are replaced as a whole: an overriding declaration does not
+/// inherit the type, default or constraints of the declaration it replaces.
+pub fn merge_with(
+    base: impl IntoIterator<Item = (String, Variable)>,
+    other: impl IntoIterator<Item = (String, Variable)>,
+    strategy: MergeStrategy,
+) -> Result<HashMap<String, Variable>> {
+    let mut merged: HashMap<_, _> = base.into_iter().collect();
+    for (key, variable) in other {
+        match (merged.entry(key), strategy) {
+            (Entry::Vacant(entry), _) => {
+                entry.insert(variable);
+            }
+            (Entry::Occupied(entry), MergeStrategy::Error) => {
+                return Err(Error::InvalidDefinition(format!(
+                    "variable {:?} is declared more than once",
+                    entry.key()
+                )));
+            }
+            (Entry::Occupied(_), MergeStrategy::PreferSelf) => (),
+            (Entry::Occupied(mut entry), MergeStrategy::PreferOther) => {
+                entry.insert(variable);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_default(default: &str) -> Variable {
+        Variable {
+            default: Some(default.into()),
+            ..Default::default()
+        }
+    }
+
+    fn merge(strategy: MergeStrategy) -> Result<HashMap<String, Variable>> {
+        merge_with(
+            [
+                ("host".to_string(), with_default("localhost")),
+                ("port".to_string(), with_default("80")),
+            ],
+            [
+                ("host".to_string(), with_default("example.com")),
+                ("debug".to_string(), with_default("true")),
+            ],
+            strategy,
+        )
+    }
+
+    fn default_of(merged: &HashMap<String, Variable>, key: &str) -> Option<String> {
+        merged[key].default.clone()
+    }
+
+    #[test]
+    fn merge_strategies() {
+        let err = merge(MergeStrategy::Error).unwrap_err();
+        assert!(err.to_string().contains("\"host\""), "{err}");
+
+        let merged = merge(MergeStrategy::PreferSelf).unwrap();
+        assert_eq!(default_of(&merged, "host").unwrap(), "localhost");
+        assert_eq!(default_of(&merged, "port").unwrap(), "80");
+        assert_eq!(default_of(&merged, "debug").unwrap(), "true");
+
+        let merged = merge(MergeStrategy::PreferOther).unwrap();
+        assert_eq!(default_of(&merged, "host").unwrap(), "example.com");
+        assert_eq!(merged.len(), 3);
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// A handle to counters describing how variables have been resolved.
+///
+/// Clones share the same counters, so a handle taken from
+/// [`ProviderResolver::metrics`](crate::ProviderResolver::metrics) keeps
+/// observing the resolver.
+#[derive(Clone, Debug, Default)]
+pub struct ResolverMetrics {
+    inner: Arc<Mutex<MetricsInner>>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    cache_hits: u64,
+    providers: BTreeMap<String, ProviderMetrics>,
+}
+
+/// Counters for calls to one provider.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProviderMetrics {
+    /// The number of calls made to the provider.
+    pub calls: u64,
+    /// The number of calls which failed.
+    pub failures: u64,
+    /// The total time spent waiting for the provider.
+    pub total_latency: Duration,
+    /// The longest time spent waiting for a single call.
+    pub max_latency: Duration,
+}
+
+impl ProviderMetrics {
+    /// Returns the mean time spent waiting for a call, if any were made.
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let calls = u32::try_from(self.calls).ok().filter(|&calls| calls > 0)?;
+        Some(self.total_latency / calls)
+    }
+}
+
+impl ResolverMetrics {
+    /// Returns the number of values which were served without asking a
+    /// provider, e.g. because they were prefetched.
+    pub fn cache_hits(&self) -> u64 {
+        self.inner.lock().unwrap().cache_hits
+    }
+
+    /// Returns the counters for each provider which has been called, by
+    /// provider name.
+    pub fn providers(&self) -> BTreeMap<String, ProviderMetrics> {
+        self.inner.lock().unwrap().providers.clone()
+    }
+
+    pub(crate) fn record_cache_hit(&self) {
+        self.inner.lock().unwrap().cache_hits += 1;
+    }
+
+    pub(crate) fn record_call(&self, provider: &str, latency: Duration, failed: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let metrics = inner.providers.entry(provider.to_owned()).or_default();
+        metrics.calls += 1;
+        metrics.failures += u64::from(failed);
+        metrics.total_latency += latency;
+        metrics.max_latency = metrics.max_latency.max(latency);
+    }
+}
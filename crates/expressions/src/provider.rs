@@ -1,12 +1,583 @@
-use std::fmt::Debug;
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
 use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
 
-use crate::Key;
+use crate::{Key, ResolverMetrics};
 
 /// A config provider.
 #[async_trait]
 pub trait Provider: Debug + Send + Sync {
     /// Returns the value at the given config path, if it exists.
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>>;
+
+    /// Returns the value at the given config path along with its version, if
+    /// the provider tracks versions of values.
+    ///
+    /// Versions are opaque strings which change whenever the value is
+    /// rotated, e.g. a secret manager's version ID. The default returns the
+    /// value from [`Provider::get`] without a version.
+    async fn get_versioned(&self, key: &Key) -> anyhow::Result<Option<(String, Option<String>)>> {
+        Ok(self.get(key).await?.map(|value| (value, None)))
+    }
+
+    /// Returns the values at the given config paths, in the same order.
+    ///
+    /// The default returns the values from [`Provider::get_many_versioned`]
+    /// without their versions.
+    async fn get_many(&self, keys: &[Key<'_>]) -> anyhow::Result<Vec<Option<String>>> {
+        let values = self.get_many_versioned(keys).await?;
+        Ok(values
+            .into_iter()
+            .map(|value| value.map(|(value, _)| value))
+            .collect())
+    }
+
+    /// Returns the values at the given config paths along with their
+    /// versions, in the same order.
+    ///
+    /// Providers backed by a network service should override this to fetch
+    /// the values in as few requests as possible. The default calls
+    /// [`Provider::get_versioned`] for each key in turn.
+    async fn get_many_versioned(
+        &self,
+        keys: &[Key<'_>],
+    ) -> anyhow::Result<Vec<Option<(String, Option<String>)>>> {
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push(self.get_versioned(key).await?);
+        }
+        Ok(values)
+    }
+
+    /// Returns a short, human-readable name for the provider, e.g. `"env"`.
+    ///
+    /// This is used to report where resolved values came from.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Subscribes to changes to the value at the given config path.
+    ///
+    /// The returned stream yields each time the value may have changed, after
+    /// which [`Provider::get`] returns the new value. Returns `None` if the
+    /// provider does not report changes, which is the default.
+    fn subscribe(&self, key: &Key) -> Option<BoxStream<'static, ()>> {
+        let _ = key;
+        None
+    }
+
+    /// Checks the provider's configuration against the keys of all the
+    /// application's variables, returning a warning for anything which looks
+    /// like a mistake. The default finds nothing to warn about.
+    fn config_warnings(&self, keys: &[Key]) -> Vec<String> {
+        let _ = keys;
+        vec![]
+    }
+}
+
+/// Where a resolved variable value came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// The value was provided by the named [`Provider`].
+    Provider(String),
+    /// No provider had a value so the variable's default was used.
+    Default,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Provider(name) => f.write_str(name),
+            Source::Default => f.write_str("default"),
+        }
+    }
+}
+
+/// A callback invoked with a key and its new version when a provider reports
+/// that the key's value has been rotated.
+pub type RotationHook = Arc<dyn Fn(&str, &str) + Send + Sync>;
+
+/// An ordered list of [`Provider`]s.
+///
+/// Keys are looked up in each provider in turn, and the first provider that
+/// has a value wins. The chain records which provider satisfied each key,
+/// which can be reported with [`ProviderChain::resolved_sources`].
+#[derive(Default)]
+pub struct ProviderChain {
+    providers: Vec<Box<dyn Provider>>,
+    // variable key -> source of the most recently resolved value
+    sources: Mutex<BTreeMap<String, Source>>,
+    // variable key -> version of the most recently resolved value, if known
+    versions: Mutex<BTreeMap<String, String>>,
+    rotation_hooks: Vec<RotationHook>,
+    metrics: ResolverMetrics,
+}
+
+impl ProviderChain {
+    /// Creates a chain from providers in order of precedence (highest first).
+    pub fn new(providers: impl IntoIterator<Item = Box<dyn Provider>>) -> Self {
+        Self {
+            providers: providers.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds a provider with lower precedence than all existing providers.
+    pub fn push(&mut self, provider: Box<dyn Provider>) {
+        self.providers.push(provider);
+    }
+
+    /// Returns the providers in order of precedence.
+    pub fn providers(&self) -> impl ExactSizeIterator<Item = &dyn Provider> {
+        self.providers.iter().map(|p| p.as_ref())
+    }
+
+    /// Returns where each key resolved so far got its value from, ordered by key.
+    pub fn resolved_sources(&self) -> Vec<(String, Source)> {
+        self.sources
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, source)| (key.clone(), source.clone()))
+            .collect()
+    }
+
+    /// Returns a handle to the counters of calls made to each provider.
+    pub fn metrics(&self) -> ResolverMetrics {
+        self.metrics.clone()
+    }
+
+    /// Awaits a call to the given provider, recording its latency and outcome.
+    async fn timed<T>(
+        &self,
+        provider: &dyn Provider,
+        call: impl Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        let start = Instant::now();
+        let result = call.await;
+        let latency = start.elapsed();
+        tracing::debug!(
+            provider = provider.name(),
+            ?latency,
+            failed = result.is_err(),
+            "variables provider call"
+        );
+        self.metrics
+            .record_call(provider.name(), latency, result.is_err());
+        result
+    }
+
+    /// Returns the version of the given key's most recently resolved value,
+    /// if its provider reported one.
+    pub fn version(&self, key: &str) -> Option<String> {
+        self.versions.lock().unwrap().get(key).cloned()
+    }
+
+    /// Registers a hook which is called when a provider reports a different
+    /// version of a value than it did the last time the key was resolved.
+    pub fn on_rotation(&mut self, hook: RotationHook) {
+        self.rotation_hooks.push(hook);
+    }
+
+    fn record_version(&self, key: &Key, version: Option<String>) {
+        let previous = {
+            let mut versions = self.versions.lock().unwrap();
+            match &version {
+                Some(version) => versions.insert(key.as_str().to_owned(), version.clone()),
+                None => versions.remove(key.as_str()),
+            }
+        };
+        if let (Some(previous), Some(version)) = (previous, version) {
+            if previous != version {
+                for hook in &self.rotation_hooks {
+                    hook(key.as_str(), &version);
+                }
+            }
+        }
+    }
+
+    /// Returns where the given key most recently got its value from.
+    pub(crate) fn source(&self, key: &str) -> Option<Source> {
+        self.sources.lock().unwrap().get(key).cloned()
+    }
+
+    pub(crate) fn record(&self, key: &Key, source: Source) {
+        self.sources
+            .lock()
+            .unwrap()
+            .insert(key.as_str().to_owned(), source);
+    }
+}
+
+impl Debug for ProviderChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderChain")
+            .field("providers", &self.providers)
+            .field("sources", &self.sources)
+            .field("versions", &self.versions)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Provider for ProviderChain {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        Ok(self.get_versioned(key).await?.map(|(value, _)| value))
+    }
+
+    async fn get_versioned(&self, key: &Key) -> anyhow::Result<Option<(String, Option<String>)>> {
+        for provider in &self.providers {
+            let provided = self
+                .timed(provider.as_ref(), provider.get_versioned(key))
+                .await?;
+            if let Some((value, version)) = provided {
+                self.record(key, Source::Provider(provider.name().to_owned()));
+                self.record_version(key, version.clone());
+                return Ok(Some((value, version)));
+            }
+        }
+        Ok(None)
+    }
+
+    // Each provider is asked for all the keys which earlier providers didn't have
+    async fn get_many_versioned(
+        &self,
+        keys: &[Key<'_>],
+    ) -> anyhow::Result<Vec<Option<(String, Option<String>)>>> {
+        let mut values = vec![None; keys.len()];
+        for provider in &self.providers {
+            let missing: Vec<usize> = (0..keys.len()).filter(|&i| values[i].is_none()).collect();
+            if missing.is_empty() {
+                break;
+            }
+            let missing_keys: Vec<_> = missing.iter().map(|&i| Key(keys[i].as_str())).collect();
+            let found = self
+                .timed(
+                    provider.as_ref(),
+                    provider.get_many_versioned(&missing_keys),
+                )
+                .await?;
+            anyhow::ensure!(
+                found.len() == missing_keys.len(),
+                "provider {:?} returned {} values for {} keys",
+                provider.name(),
+                found.len(),
+                missing_keys.len()
+            );
+            for (i, value) in missing.into_iter().zip(found) {
+                if let Some((_, version)) = &value {
+                    self.record(&keys[i], Source::Provider(provider.name().to_owned()));
+                    self.record_version(&keys[i], version.clone());
+                    values[i] = value;
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    fn subscribe(&self, key: &Key) -> Option<BoxStream<'static, ()>> {
+        let streams: Vec<_> = self
+            .providers
+            .iter()
+            .filter_map(|provider| provider.subscribe(key))
+            .collect();
+        (!streams.is_empty()).then(|| futures::stream::select_all(streams).boxed())
+    }
+
+    fn config_warnings(&self, keys: &[Key]) -> Vec<String> {
+        self.providers
+            .iter()
+            .flat_map(|provider| provider.config_warnings(keys))
+            .collect()
+    }
+}
+
+/// A [`Provider`] which routes each key to the provider registered for the
+/// longest prefix of the key.
+///
+/// Only the selected provider is consulted; if it has no value for the key,
+/// neither does the `RoutedProvider`. Keys which match no route have no value.
+/// An empty prefix matches every key, so can be used as a fallback route.
+#[derive(Debug, Default)]
+pub struct RoutedProvider {
+    // (prefix, provider) in the order they were added
+    routes: Vec<(String, Box<dyn Provider>)>,
+}
+
+impl RoutedProvider {
+    /// Creates a provider with no routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes keys starting with the given prefix to the given provider.
+    ///
+    /// If several routes have the same prefix, the first one added wins.
+    pub fn route(&mut self, prefix: impl Into<String>, provider: Box<dyn Provider>) {
+        self.routes.push((prefix.into(), provider));
+    }
+
+    /// Returns the index of the route for the given key, if any.
+    fn route_index(&self, key: &str) -> Option<usize> {
+        let mut best: Option<(usize, usize)> = None;
+        for (idx, (prefix, _)) in self.routes.iter().enumerate() {
+            let longer = best.is_none_or(|(_, len)| prefix.len() > len);
+            if key.starts_with(prefix.as_str()) && longer {
+                best = Some((idx, prefix.len()));
+            }
+        }
+        best.map(|(idx, _)| idx)
+    }
+
+    fn provider_for(&self, key: &Key) -> Option<&dyn Provider> {
+        self.route_index(key.as_str())
+            .map(|idx| self.routes[idx].1.as_ref())
+    }
+}
+
+#[async_trait]
+impl Provider for RoutedProvider {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        match self.provider_for(key) {
+            Some(provider) => provider.get(key).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn get_versioned(&self, key: &Key) -> anyhow::Result<Option<(String, Option<String>)>> {
+        match self.provider_for(key) {
+            Some(provider) => provider.get_versioned(key).await,
+            None => Ok(None),
+        }
+    }
+
+    // Each route's provider is asked for all of its keys at once
+    async fn get_many_versioned(
+        &self,
+        keys: &[Key<'_>],
+    ) -> anyhow::Result<Vec<Option<(String, Option<String>)>>> {
+        let mut values = vec![None; keys.len()];
+        for route in 0..self.routes.len() {
+            let indices: Vec<usize> = (0..keys.len())
+                .filter(|&i| self.route_index(keys[i].as_str()) == Some(route))
+                .collect();
+            if indices.is_empty() {
+                continue;
+            }
+            let route_keys: Vec<_> = indices.iter().map(|&i| Key(keys[i].as_str())).collect();
+            let provider = &self.routes[route].1;
+            let found = provider.get_many_versioned(&route_keys).await?;
+            anyhow::ensure!(
+                found.len() == route_keys.len(),
+                "provider {:?} returned {} values for {} keys",
+                provider.name(),
+                found.len(),
+                route_keys.len()
+            );
+            for (i, value) in indices.into_iter().zip(found) {
+                values[i] = value;
+            }
+        }
+        Ok(values)
+    }
+
+    fn name(&self) -> &str {
+        "routed"
+    }
+
+    fn subscribe(&self, key: &Key) -> Option<BoxStream<'static, ()>> {
+        self.provider_for(key)?.subscribe(key)
+    }
+
+    fn config_warnings(&self, keys: &[Key]) -> Vec<String> {
+        let mut used = vec![false; self.routes.len()];
+        for key in keys {
+            if let Some(idx) = self.route_index(key.as_str()) {
+                used[idx] = true;
+            }
+        }
+        let mut warnings = vec![];
+        for (idx, (prefix, provider)) in self.routes.iter().enumerate() {
+            if used[idx] {
+                let keys: Vec<_> = keys
+                    .iter()
+                    .filter(|key| self.route_index(key.as_str()) == Some(idx))
+                    .map(|key| Key(key.as_str()))
+                    .collect();
+                warnings.extend(provider.config_warnings(&keys));
+            } else if self.routes[..idx].iter().any(|(p, _)| p == prefix) {
+                warnings.push(format!(
+                    "variables route {prefix:?} is unreachable: an earlier route has the same prefix"
+                ));
+            } else if keys
+                .iter()
+                .any(|key| key.as_str().starts_with(prefix.as_str()))
+            {
+                warnings.push(format!(
+                    "variables route {prefix:?} is unreachable: all matching variables are routed by longer prefixes"
+                ));
+            } else {
+                warnings.push(format!(
+                    "variables route {prefix:?} is unreachable: no variables start with that prefix"
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct SingleKeyProvider(&'static str, &'static str);
+
+    #[async_trait]
+    impl Provider for SingleKeyProvider {
+        async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+            Ok((key.as_str() == self.1).then(|| format!("{}-value", self.0)))
+        }
+
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn routed_longest_prefix_wins() {
+        let mut routed = RoutedProvider::new();
+        routed.route("db_", Box::new(SingleKeyProvider("db", "db_password")));
+        routed.route(
+            "db_replica_",
+            Box::new(SingleKeyProvider("replica", "db_replica_password")),
+        );
+        // Would also provide "db_password" if it were consulted
+        routed.route("", Box::new(SingleKeyProvider("fallback", "db_password")));
+
+        let routed = &routed;
+        let get = |key| async move { routed.get(&Key(key)).await.unwrap() };
+        assert_eq!(get("db_password").await.as_deref(), Some("db-value"));
+        assert_eq!(
+            get("db_replica_password").await.as_deref(),
+            Some("replica-value")
+        );
+        assert_eq!(get("api_key").await, None);
+    }
+
+    /// Returns no values from [`Provider::get_many_versioned`], however many
+    /// keys.
+    #[derive(Debug)]
+    struct ShortProvider;
+
+    #[async_trait]
+    impl Provider for ShortProvider {
+        async fn get(&self, _: &Key) -> anyhow::Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn get_many_versioned(
+            &self,
+            _: &[Key<'_>],
+        ) -> anyhow::Result<Vec<Option<(String, Option<String>)>>> {
+            Ok(vec![])
+        }
+
+        fn name(&self) -> &str {
+            "short"
+        }
+    }
+
+    #[tokio::test]
+    async fn routed_get_many_rejects_short_responses() {
+        let mut routed = RoutedProvider::new();
+        routed.route("db_", Box::new(SingleKeyProvider("db", "db_password")));
+        routed.route("", Box::new(ShortProvider));
+
+        let values = routed.get_many(&[Key("db_password")]).await.unwrap();
+        assert_eq!(values, [Some("db-value".into())]);
+
+        let err = routed
+            .get_many(&[Key("db_password"), Key("api_key")])
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("returned 0 values for 1 keys"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn routed_warns_on_unreachable_routes() {
+        let mut routed = RoutedProvider::new();
+        routed.route("db_", Box::new(SingleKeyProvider("db", "")));
+        routed.route("db_", Box::new(SingleKeyProvider("duplicate", "")));
+        routed.route("db_main_", Box::new(SingleKeyProvider("main", "")));
+        routed.route("cache_", Box::new(SingleKeyProvider("cache", "")));
+        routed.route("api_", Box::new(SingleKeyProvider("api", "")));
+
+        let warnings = routed.config_warnings(&[Key("db_main_url"), Key("api_key")]);
+        assert_eq!(warnings.len(), 3, "{warnings:?}");
+        assert!(warnings[0].contains("\"db_\"") && warnings[0].contains("longer"));
+        assert!(warnings[1].contains("\"db_\"") && warnings[1].contains("same prefix"));
+        assert!(warnings[2].contains("\"cache_\"") && warnings[2].contains("no variables"));
+    }
+
+    #[tokio::test]
+    async fn chain_get_many() {
+        let chain = ProviderChain::new([
+            Box::new(SingleKeyProvider("env", "a")) as _,
+            Box::new(SingleKeyProvider("vault", "b")) as _,
+            Box::new(SingleKeyProvider("shadowed", "a")) as _,
+        ]);
+        let values = chain
+            .get_many(&[Key("a"), Key("b"), Key("c")])
+            .await
+            .unwrap();
+        assert_eq!(
+            values,
+            [Some("env-value".into()), Some("vault-value".into()), None]
+        );
+        assert_eq!(
+            chain.resolved_sources(),
+            [
+                ("a".to_string(), Source::Provider("env".into())),
+                ("b".to_string(), Source::Provider("vault".into())),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_records_sources() {
+        let chain = ProviderChain::new([
+            Box::new(SingleKeyProvider("env", "a")) as _,
+            Box::new(SingleKeyProvider("vault", "b")) as _,
+            Box::new(SingleKeyProvider("shadowed", "a")) as _,
+        ]);
+
+        let chain = &chain;
+        let get = |key| async move { chain.get(&Key(key)).await.unwrap() };
+        assert_eq!(get("a").await.as_deref(), Some("env-value"));
+        assert_eq!(get("b").await.as_deref(), Some("vault-value"));
+        assert_eq!(get("c").await, None);
+
+        assert_eq!(
+            chain.resolved_sources(),
+            [
+                ("a".to_string(), Source::Provider("env".into())),
+                ("b".to_string(), Source::Provider("vault".into())),
+            ]
+        );
+    }
 }
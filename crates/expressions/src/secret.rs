@@ -0,0 +1,47 @@
+use std::fmt;
+
+/// Stands in for secret values in formatted output.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// A string which is redacted when formatted with `Display` or `Debug`.
+///
+/// This holds the values of variables declared with `secret = true`, so that
+/// they can't end up in logs or error messages by accident. Use
+/// [`SecretString::expose_secret`] to get at the value deliberately.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wraps a secret value.
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Returns the secret value.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the secret value, consuming the wrapper.
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Source;
+
+/// The resolved values of an application's variables at a point in time,
+/// with secret values redacted.
+///
+/// Snapshots serialize to JSON for debugging, and can be read back by a
+/// provider to reproduce the same configuration elsewhere.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The active profile, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Variable key -> resolved variable.
+    pub variables: BTreeMap<String, SnapshotVariable>,
+}
+
+impl Snapshot {
+    /// Serializes the snapshot as pretty-printed JSON.
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+    }
+
+    /// Deserializes a snapshot from JSON.
+    pub fn from_json(json: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(json)
+    }
+}
+
+/// A variable in a [`Snapshot`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotVariable {
+    /// The resolved value. Omitted for secrets and variables which failed to
+    /// resolve.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Whether the variable is declared with `secret = true`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+    /// Where the value came from.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "source_serde"
+    )]
+    pub source: Option<Source>,
+    /// The version of the value, if its provider reports versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Why the variable failed to resolve, if it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Serializes a [`Source`] as its display form, i.e. a provider name or
+/// `"default"`.
+mod source_serde {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::Source;
+
+    pub fn serialize<S: Serializer>(source: &Option<Source>, ser: S) -> Result<S::Ok, S::Error> {
+        match source {
+            Some(source) => ser.collect_str(source),
+            None => ser.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Option<Source>, D::Error> {
+        Ok(
+            Option::<String>::deserialize(de)?.map(|source| match source.as_str() {
+                "default" => Source::Default,
+                _ => Source::Provider(source),
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let snapshot = Snapshot {
+            profile: None,
+            variables: [
+                (
+                    "api_url".to_string(),
+                    SnapshotVariable {
+                        value: Some("https://example.com".into()),
+                        source: Some(Source::Default),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "password".to_string(),
+                    SnapshotVariable {
+                        secret: true,
+                        source: Some(Source::Provider("vault".into())),
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+        };
+        let json = String::from_utf8(snapshot.to_json().unwrap()).unwrap();
+        assert!(json.contains(r#""source": "vault""#), "{json}");
+        assert_eq!(Snapshot::from_json(json.as_bytes()).unwrap(), snapshot);
+    }
+}
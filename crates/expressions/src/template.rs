@@ -4,42 +4,68 @@ use crate::{Error, Result};
 
 /// Template represents a simple string template that allows expressions in
 /// double curly braces, similar to Mustache or Liquid.
+///
+/// An expression names a variable, optionally followed by a pipeline of
+/// filters separated by `|`, e.g. `{{ url | trim_suffix:'/' | upper }}`. A
+/// filter argument follows a `:` and may be quoted with `'` or `"`.
+///
+/// A backslash before the opening delimiter escapes it, so `\{{ name }}` is
+/// the literal text `{{ name }}`. For values which contain a lot of
+/// mustache-like content, such as templated JSON bodies, use
+/// [`Template::with_delimiters`] to choose different delimiters instead.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Template {
     parts: Vec<Part>,
+    delimiters: Delimiters,
 }
 
 impl Template {
     pub fn new(template: impl Into<Box<str>>) -> Result<Self> {
+        Self::with_delimiters(template, Delimiters::default())
+    }
+
+    /// Parses a template whose expressions are enclosed in the given
+    /// delimiters rather than `{{` and `}}`.
+    pub fn with_delimiters(template: impl Into<Box<str>>, delimiters: Delimiters) -> Result<Self> {
+        let template: Box<str> = template.into();
+        let (open, close) = (delimiters.open(), delimiters.close());
+        let escaped_open = format!("\\{open}");
         let mut parts = vec![];
-        let mut remainder: Box<str> = template.into();
+        let mut lit = String::new();
+        let mut remainder: &str = &template;
         while !remainder.is_empty() {
-            let (part, rest) = if let Some(expr_rest) = remainder.strip_prefix("{{") {
+            if let Some(rest) = remainder.strip_prefix(escaped_open.as_str()) {
+                // An escaped delimiter is part of the literal
+                lit.push_str(open);
+                remainder = rest;
+            } else if let Some(expr_rest) = remainder.strip_prefix(open) {
                 // Expression should be next
-                if let Some((expr, rest)) = expr_rest.split_once("}}") {
-                    // Take up through the next '}}'...
-                    (Part::expr(expr.trim()), rest)
-                } else {
-                    // ...or we have unmatched braces
-                    return Err(Error::InvalidTemplate(
-                        "unmatched '{{' in template".to_string(),
-                    ));
+                let Some((expr, rest)) = expr_rest.split_once(close) else {
+                    return Err(Error::InvalidTemplate(format!(
+                        "unmatched '{open}' in template; use '{escaped_open}' for a literal '{open}'"
+                    )));
+                };
+                if !lit.is_empty() {
+                    parts.push(Part::lit(std::mem::take(&mut lit)));
                 }
+                parts.push(Part::Expr(Expr::parse(expr)?));
+                remainder = rest;
             } else {
-                // Literal is next
-                if let Some(idx) = remainder.find("{{") {
-                    // Take up to the next '{{'...
-                    let (lit, rest) = remainder.split_at(idx);
-                    (Part::lit(lit), rest)
-                } else {
-                    // ...or end of string
-                    (Part::lit(remainder), "")
-                }
-            };
-            parts.push(part);
-            remainder = rest.into();
+                // Literal is next; take up to the next delimiter or escape
+                let idx = [remainder.find(escaped_open.as_str()), remainder.find(open)]
+                    .into_iter()
+                    .flatten()
+                    .min()
+                    .unwrap_or(remainder.len());
+                let (text, rest) = remainder.split_at(idx);
+                lit.push_str(text);
+                remainder = rest;
+            }
+        }
+        if !lit.is_empty() {
+            parts.push(Part::lit(lit));
         }
-        Ok(Template { parts })
+        Ok(Template { parts, delimiters })
     }
 
     pub fn is_literal(&self) -> bool {
@@ -49,21 +75,71 @@ impl Template {
     pub(crate) fn parts(&self) -> std::slice::Iter<Part> {
         self.parts.iter()
     }
+
+    /// Returns the names of the variables referenced by the template.
+    pub(crate) fn variables(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Expr(expr) => Some(expr.var()),
+            Part::Lit(_) => None,
+        })
+    }
 }
 
 impl Display for Template {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (open, close) = (self.delimiters.open(), self.delimiters.close());
         self.parts().try_for_each(|part| match part {
-            Part::Lit(lit) => f.write_str(lit),
-            Part::Expr(expr) => write!(f, "{{ {expr} }}"),
+            Part::Lit(lit) => f.write_str(&lit.replace(open, &format!("\\{open}"))),
+            Part::Expr(expr) => write!(f, "{open} {expr} {close}"),
         })
     }
 }
 
+/// The strings which open and close expressions in a [`Template`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delimiters {
+    open: Box<str>,
+    close: Box<str>,
+}
+
+impl Delimiters {
+    /// Creates delimiters, e.g. `Delimiters::new("${", "}")`.
+    ///
+    /// The delimiters must be non-empty and must not contain whitespace.
+    pub fn new(open: impl Into<Box<str>>, close: impl Into<Box<str>>) -> Result<Self> {
+        let (open, close) = (open.into(), close.into());
+        for delimiter in [&open, &close] {
+            if delimiter.is_empty() || delimiter.contains(char::is_whitespace) {
+                return Err(Error::InvalidTemplate(format!(
+                    "invalid template delimiter {delimiter:?}"
+                )));
+            }
+        }
+        Ok(Self { open, close })
+    }
+
+    pub fn open(&self) -> &str {
+        &self.open
+    }
+
+    pub fn close(&self) -> &str {
+        &self.close
+    }
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            open: "{{".into(),
+            close: "}}".into(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Part {
     Lit(Box<str>),
-    Expr(Box<str>),
+    Expr(Expr),
 }
 
 impl Part {
@@ -71,11 +147,147 @@ impl Part {
         Self::Lit(lit.into())
     }
 
-    pub fn expr(expr: impl Into<Box<str>>) -> Self {
-        Self::Expr(expr.into())
+    #[cfg(test)]
+    pub fn expr(var: impl Into<Box<str>>) -> Self {
+        Self::Expr(Expr {
+            var: var.into(),
+            filters: vec![],
+        })
+    }
+}
+
+/// A template expression: a variable and the filters to apply to its value.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Expr {
+    var: Box<str>,
+    filters: Vec<FilterCall>,
+}
+
+impl Expr {
+    fn parse(expr: &str) -> Result<Self> {
+        let mut segments = split_unquoted(expr, '|')?.into_iter();
+        let var = segments.next().unwrap_or_default().trim();
+        if var.is_empty() {
+            return Err(Error::InvalidTemplate(format!(
+                "missing variable name in expression {expr:?}"
+            )));
+        }
+        let filters = segments.map(FilterCall::parse).collect::<Result<_>>()?;
+        Ok(Self {
+            var: var.into(),
+            filters,
+        })
+    }
+
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    pub fn filters(&self) -> &[FilterCall] {
+        &self.filters
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.var)?;
+        self.filters
+            .iter()
+            .try_for_each(|filter| write!(f, " | {filter}"))
+    }
+}
+
+/// A filter in an expression, with its argument if any.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FilterCall {
+    name: Box<str>,
+    arg: Option<Box<str>>,
+}
+
+impl FilterCall {
+    fn parse(filter: &str) -> Result<Self> {
+        let (name, arg) = match split_unquoted(filter, ':')?.as_slice() {
+            [name] => (name.trim(), None),
+            [name, arg] => (name.trim(), Some(unquote(arg.trim())?)),
+            _ => {
+                return Err(Error::InvalidTemplate(format!(
+                    "filter {:?} has more than one argument",
+                    filter.trim()
+                )))
+            }
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::InvalidTemplate(format!(
+                "invalid filter name {name:?}"
+            )));
+        }
+        Ok(Self {
+            name: name.into(),
+            arg: arg.map(Into::into),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arg(&self) -> Option<&str> {
+        self.arg.as_deref()
     }
 }
 
+impl Display for FilterCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        match self.arg() {
+            Some(arg) if arg.contains('\'') => write!(f, ":\"{arg}\""),
+            Some(arg) => write!(f, ":'{arg}'"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Splits on the given separator, ignoring separators inside quotes.
+fn split_unquoted(s: &str, sep: char) -> Result<Vec<&str>> {
+    let mut segments = vec![];
+    let mut start = 0;
+    let mut quote = None;
+    for (idx, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == sep => {
+                segments.push(&s[start..idx]);
+                start = idx + c.len_utf8();
+            }
+            None => (),
+        }
+    }
+    if quote.is_some() {
+        return Err(Error::InvalidTemplate(format!(
+            "unterminated quote in expression {s:?}"
+        )));
+    }
+    segments.push(&s[start..]);
+    Ok(segments)
+}
+
+/// Removes matching quotes around a filter argument, if present.
+fn unquote(arg: &str) -> Result<&str> {
+    for q in ['\'', '"'] {
+        if let Some(rest) = arg.strip_prefix(q) {
+            return match rest.strip_suffix(q) {
+                Some(inner) if !inner.contains(q) => Ok(inner),
+                _ => Err(Error::InvalidTemplate(format!(
+                    "invalid quoted filter argument {arg:?}"
+                ))),
+            };
+        }
+    }
+    Ok(arg)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,4 +321,69 @@ mod tests {
     fn template_parts_bad() {
         Template::new("{{ matched }} {{ unmatched").unwrap_err();
     }
+
+    #[test]
+    fn template_escapes() {
+        let template = Template::new(r"a \{{ lit }} {{ expr }} \{{").unwrap();
+        assert!(template.parts().eq(&[
+            Part::lit("a {{ lit }} "),
+            Part::expr("expr"),
+            Part::lit(" {{"),
+        ]));
+        assert_eq!(template.to_string(), r"a \{{ lit }} {{ expr }} \{{");
+        assert!(Template::new(r"\{{ not_a_var }}").unwrap().is_literal());
+    }
+
+    #[test]
+    fn template_delimiters() {
+        let delimiters = Delimiters::new("${", "}").unwrap();
+        let template =
+            Template::with_delimiters(r#"{"user": {{user}}, "host": "${ host }"}"#, delimiters)
+                .unwrap();
+        assert!(template.parts().eq(&[
+            Part::lit(r#"{"user": {{user}}, "host": ""#),
+            Part::expr("host"),
+            Part::lit(r#""}"#),
+        ]));
+        assert_eq!(
+            template.to_string(),
+            r#"{"user": {{user}}, "host": "${ host }"}"#
+        );
+
+        Delimiters::new("", "}").unwrap_err();
+        Delimiters::new("{ {", "}").unwrap_err();
+    }
+
+    #[test]
+    fn template_filters() {
+        let template = Template::new("{{ url | trim_suffix:'/' | upper }}").unwrap();
+        let Some(Part::Expr(expr)) = template.parts().next() else {
+            panic!("expected an expression");
+        };
+        assert_eq!(expr.var(), "url");
+        let filters: Vec<_> = expr.filters().iter().map(|f| (f.name(), f.arg())).collect();
+        assert_eq!(filters, [("trim_suffix", Some("/")), ("upper", None)]);
+        assert_eq!(template.to_string(), "{{ url | trim_suffix:'/' | upper }}");
+
+        // Separators inside quotes are part of the argument
+        let template = Template::new("{{ a | trim_prefix:\"x|y:z\" }}").unwrap();
+        let Some(Part::Expr(expr)) = template.parts().next() else {
+            panic!("expected an expression");
+        };
+        assert_eq!(expr.filters()[0].arg(), Some("x|y:z"));
+    }
+
+    #[test]
+    fn template_filters_bad() {
+        for tmpl in [
+            "{{ | upper }}",
+            "{{ a | }}",
+            "{{ a | up-per }}",
+            "{{ a | trim_suffix:'/ }}",
+            "{{ a | trim_suffix:'/'x }}",
+            "{{ a | f:1:2 }}",
+        ] {
+            Template::new(tmpl).expect_err(tmpl);
+        }
+    }
 }
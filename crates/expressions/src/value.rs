@@ -0,0 +1,238 @@
+use std::time::Duration;
+
+use spin_locked_app::Variable;
+pub use spin_locked_app::VariableType;
+pub(crate) use spin_serde::duration::parse as parse_duration;
+
+use crate::Error;
+
+/// A variable value parsed according to a [`VariableType`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypedValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Duration(Duration),
+    StringList(Vec<String>),
+}
+
+impl TypedValue {
+    /// Parses a raw value as the given type.
+    ///
+    /// On failure, returns a reason which never includes the value itself, so
+    /// that it is safe to report for secret variables.
+    pub fn parse(variable_type: VariableType, value: &str) -> Result<Self, String> {
+        Ok(match variable_type {
+            VariableType::String => Self::String(value.to_owned()),
+            VariableType::Int => Self::Int(parse_int(value)?),
+            VariableType::Float => Self::Float(parse_float(value)?),
+            VariableType::Bool => Self::Bool(parse_bool(value)?),
+            VariableType::Duration => Self::Duration(parse_duration(value)?),
+            VariableType::StringList => Self::StringList(parse_string_list(value)?),
+        })
+    }
+
+    /// Returns the type of this value.
+    pub fn variable_type(&self) -> VariableType {
+        match self {
+            Self::String(_) => VariableType::String,
+            Self::Int(_) => VariableType::Int,
+            Self::Float(_) => VariableType::Float,
+            Self::Bool(_) => VariableType::Bool,
+            Self::Duration(_) => VariableType::Duration,
+            Self::StringList(_) => VariableType::StringList,
+        }
+    }
+}
+
+/// The type and value constraints declared by a [`Variable`].
+#[derive(Debug)]
+pub(crate) struct Constraints {
+    variable_type: VariableType,
+    secret: bool,
+    // (pattern as declared, anchored regex)
+    pattern: Option<(String, regex::Regex)>,
+    allowed_values: Vec<String>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Constraints {
+    pub fn new(key: &str, variable: &Variable) -> crate::Result<Self> {
+        let pattern = variable
+            .pattern
+            .as_deref()
+            .map(|pattern| {
+                // Require the pattern to match the whole value
+                let regex = regex::Regex::new(&format!("^(?:{pattern})$")).map_err(|err| {
+                    Error::InvalidDefinition(format!("{key:?} has an invalid pattern: {err}"))
+                })?;
+                Ok((pattern.to_owned(), regex))
+            })
+            .transpose()?;
+        Ok(Self {
+            variable_type: variable.variable_type,
+            secret: variable.secret,
+            pattern,
+            allowed_values: variable.allowed_values.clone(),
+            min: variable.min,
+            max: variable.max,
+        })
+    }
+
+    /// Checks that the given value satisfies the constraints, naming the
+    /// variable and the failed constraint on error. The value itself is
+    /// never included in errors.
+    pub fn check(&self, key: &str, value: &str) -> crate::Result<TypedValue> {
+        let violation = |constraint: String| {
+            Error::InvalidValue(format!("{key:?} does not satisfy {constraint}"))
+        };
+        let typed = TypedValue::parse(self.variable_type, value)
+            .map_err(|reason| invalid_value(key, self.variable_type, reason))?;
+        if let Some((pattern, regex)) = &self.pattern {
+            if !regex.is_match(value) {
+                return Err(violation(format!("`pattern = {pattern:?}`")));
+            }
+        }
+        if !self.allowed_values.is_empty() && !self.allowed_values.iter().any(|v| v == value) {
+            // The allowed values of a secret narrow down what it could be
+            return Err(violation(if self.secret {
+                "`allowed_values`".to_owned()
+            } else {
+                format!("`allowed_values = {:?}`", self.allowed_values)
+            }));
+        }
+        let number = match typed {
+            TypedValue::Int(i) => Some(i as f64),
+            TypedValue::Float(f) => Some(f),
+            _ => None,
+        };
+        if let Some(number) = number {
+            if let Some(min) = self.min.filter(|min| number < *min) {
+                return Err(violation(format!("`min = {min}`")));
+            }
+            if let Some(max) = self.max.filter(|max| number > *max) {
+                return Err(violation(format!("`max = {max}`")));
+            }
+        }
+        Ok(typed)
+    }
+}
+
+pub(crate) fn invalid_value(key: &str, variable_type: VariableType, reason: String) -> Error {
+    Error::InvalidValue(format!("{key:?} is not a valid {variable_type}: {reason}"))
+}
+
+pub(crate) fn parse_int(value: &str) -> Result<i64, String> {
+    value.trim().parse().map_err(|e| format!("{e}"))
+}
+
+pub(crate) fn parse_float(value: &str) -> Result<f64, String> {
+    value.trim().parse().map_err(|e| format!("{e}"))
+}
+
+pub(crate) fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err("expected `true` or `false`".into()),
+    }
+}
+
+pub(crate) fn parse_string_list(value: &str) -> Result<Vec<String>, String> {
+    if value.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(value.split(',').map(|s| s.trim().to_owned()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_typed_values() {
+        for (ty, value, expected) in [
+            (
+                VariableType::String,
+                " a ",
+                TypedValue::String(" a ".into()),
+            ),
+            (VariableType::Int, "8080", TypedValue::Int(8080)),
+            (VariableType::Int, "-1", TypedValue::Int(-1)),
+            (VariableType::Float, "0.5", TypedValue::Float(0.5)),
+            (VariableType::Bool, "true", TypedValue::Bool(true)),
+            (
+                VariableType::Duration,
+                "1h30m",
+                TypedValue::Duration(Duration::from_secs(5400)),
+            ),
+            (
+                VariableType::Duration,
+                "250ms",
+                TypedValue::Duration(Duration::from_millis(250)),
+            ),
+            (
+                VariableType::StringList,
+                "a, b,c",
+                TypedValue::StringList(vec!["a".into(), "b".into(), "c".into()]),
+            ),
+            (VariableType::StringList, "", TypedValue::StringList(vec![])),
+        ] {
+            assert_eq!(TypedValue::parse(ty, value).unwrap(), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn constraints() {
+        let variable = Variable {
+            variable_type: VariableType::Int,
+            pattern: Some("[0-9]+".into()),
+            allowed_values: vec!["80".into(), "443".into(), "8080".into()],
+            min: Some(100.0),
+            max: Some(1000.0),
+            ..Default::default()
+        };
+        let constraints = Constraints::new("port", &variable).unwrap();
+        assert_eq!(
+            constraints.check("port", "443").unwrap(),
+            TypedValue::Int(443)
+        );
+        for (value, constraint) in [
+            ("+443", "pattern"),
+            ("444", "allowed_values"),
+            ("80", "min"),
+            ("8080", "max"),
+        ] {
+            let err = constraints.check("port", value).unwrap_err().to_string();
+            assert!(err.contains("\"port\""), "{err}");
+            assert!(err.contains(constraint), "{err}");
+            assert!(!err.contains(value), "{err}");
+        }
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let variable = Variable {
+            pattern: Some("(".into()),
+            ..Default::default()
+        };
+        Constraints::new("bad", &variable).unwrap_err();
+    }
+
+    #[test]
+    fn parse_typed_values_bad() {
+        for (ty, value) in [
+            (VariableType::Int, "80.5"),
+            (VariableType::Float, "half"),
+            (VariableType::Bool, "yes"),
+            (VariableType::Duration, ""),
+            (VariableType::Duration, "10"),
+            (VariableType::Duration, "s"),
+            (VariableType::Duration, "10 minutes"),
+        ] {
+            TypedValue::parse(ty, value).expect_err(value);
+        }
+    }
+}
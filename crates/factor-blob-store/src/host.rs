@@ -0,0 +1,265 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::stream::{BoxStream, Fuse, StreamExt};
+use spin_factors::wasmtime::component::Resource;
+use spin_factors::{anyhow, SelfInstanceBuilder};
+use spin_resource_table::Table;
+use spin_world::spin::blob_store::blob_store::{
+    self, IncomingObject, ObjectListing, OutgoingObject, Store,
+};
+use spin_world::spin::blob_store::types::{self, Error, ObjectInfo};
+use tracing::field::Empty;
+use tracing::{instrument, Level};
+
+use crate::{validate_name, BlobStore, ObjectWriter};
+
+pub struct InstanceState {
+    allowed_stores: Arc<HashSet<String>>,
+    /// A map from store label to store.
+    stores: HashMap<String, Arc<dyn BlobStore>>,
+    /// A resource table of open stores.
+    open_stores: Table<Arc<dyn BlobStore>>,
+    /// A resource table of objects being read.
+    incoming: Table<IncomingState>,
+    /// A resource table of objects being written.
+    outgoing: Table<Box<dyn ObjectWriter>>,
+    /// A resource table of object listings.
+    listings: Table<Fuse<BoxStream<'static, Result<ObjectInfo, Error>>>>,
+}
+
+/// An object being read by a component.
+struct IncomingState {
+    size: u64,
+    body: Fuse<BoxStream<'static, Result<Bytes, Error>>>,
+    /// Received bytes which the component has not yet read.
+    buffered: Bytes,
+}
+
+impl InstanceState {
+    /// Create a new `InstanceState`
+    pub fn new(
+        allowed_stores: Arc<HashSet<String>>,
+        stores: HashMap<String, Arc<dyn BlobStore>>,
+    ) -> Self {
+        Self {
+            allowed_stores,
+            stores,
+            open_stores: Table::new(256),
+            incoming: Table::new(256),
+            outgoing: Table::new(256),
+            listings: Table::new(256),
+        }
+    }
+
+    fn get_store(&self, store: &Resource<Store>) -> Result<Arc<dyn BlobStore>, Error> {
+        let store = self
+            .open_stores
+            .get(store.rep())
+            .cloned()
+            .ok_or_else(|| Error::Other("could not find store for resource".into()))?;
+        tracing::Span::current().record(
+            "blob_store.backend",
+            store.summary().as_deref().unwrap_or("unknown"),
+        );
+        Ok(store)
+    }
+
+    fn incoming_object(
+        &mut self,
+        object: &Resource<IncomingObject>,
+    ) -> Result<&mut IncomingState, Error> {
+        self.incoming
+            .get_mut(object.rep())
+            .ok_or_else(|| Error::Other("could not find incoming object for resource".into()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl blob_store::Host for InstanceState {}
+
+impl blob_store::HostStore for InstanceState {
+    #[instrument(name = "spin_blob_store.open", skip(self), err(level = Level::INFO), fields(otel.kind = "client", blob_store.backend = Empty))]
+    async fn open(&mut self, label: String) -> Result<Resource<Store>, Error> {
+        if !self.allowed_stores.contains(&label) {
+            return Err(Error::AccessDenied);
+        }
+        let store = self.stores.get(&label).ok_or(Error::NoSuchStore)?.clone();
+        tracing::Span::current().record(
+            "blob_store.backend",
+            store.summary().as_deref().unwrap_or("unknown"),
+        );
+        self.open_stores
+            .push(store)
+            .map_err(|()| Error::Other("too many stores opened".into()))
+            .map(Resource::new_own)
+    }
+
+    #[instrument(name = "spin_blob_store.get", skip(self, store), err(level = Level::INFO), fields(otel.kind = "client", blob_store.backend = Empty))]
+    async fn get(
+        &mut self,
+        store: Resource<Store>,
+        name: String,
+    ) -> Result<Option<Resource<IncomingObject>>, Error> {
+        validate_name(&name)?;
+        let Some(object) = self.get_store(&store)?.get(&name).await? else {
+            return Ok(None);
+        };
+        self.incoming
+            .push(IncomingState {
+                size: object.size,
+                body: object.body.fuse(),
+                buffered: Bytes::new(),
+            })
+            .map_err(|()| Error::Other("too many objects opened for reading".into()))
+            .map(|rep| Some(Resource::new_own(rep)))
+    }
+
+    #[instrument(name = "spin_blob_store.put", skip(self, store), err(level = Level::INFO), fields(otel.kind = "client", blob_store.backend = Empty))]
+    async fn put(
+        &mut self,
+        store: Resource<Store>,
+        name: String,
+    ) -> Result<Resource<OutgoingObject>, Error> {
+        validate_name(&name)?;
+        let writer = self.get_store(&store)?.put(&name).await?;
+        self.outgoing
+            .push(writer)
+            .map_err(|()| Error::Other("too many objects opened for writing".into()))
+            .map(Resource::new_own)
+    }
+
+    #[instrument(name = "spin_blob_store.delete", skip(self, store), err(level = Level::INFO), fields(otel.kind = "client", blob_store.backend = Empty))]
+    async fn delete(&mut self, store: Resource<Store>, name: String) -> Result<(), Error> {
+        validate_name(&name)?;
+        self.get_store(&store)?.delete(&name).await
+    }
+
+    #[instrument(name = "spin_blob_store.info", skip(self, store), err(level = Level::INFO), fields(otel.kind = "client", blob_store.backend = Empty))]
+    async fn info(
+        &mut self,
+        store: Resource<Store>,
+        name: String,
+    ) -> Result<Option<ObjectInfo>, Error> {
+        validate_name(&name)?;
+        self.get_store(&store)?.info(&name).await
+    }
+
+    #[instrument(name = "spin_blob_store.list_objects", skip(self, store), err(level = Level::INFO), fields(otel.kind = "client", blob_store.backend = Empty))]
+    async fn list_objects(
+        &mut self,
+        store: Resource<Store>,
+        prefix: Option<String>,
+    ) -> Result<Resource<ObjectListing>, Error> {
+        let listing = self.get_store(&store)?.list(prefix).fuse();
+        self.listings
+            .push(listing)
+            .map_err(|()| Error::Other("too many object listings opened".into()))
+            .map(Resource::new_own)
+    }
+
+    async fn drop(&mut self, store: Resource<Store>) -> anyhow::Result<()> {
+        self.open_stores.remove(store.rep());
+        Ok(())
+    }
+}
+
+impl blob_store::HostIncomingObject for InstanceState {
+    async fn size(&mut self, object: Resource<IncomingObject>) -> anyhow::Result<u64> {
+        let object = self
+            .incoming
+            .get(object.rep())
+            .ok_or_else(|| anyhow::anyhow!("could not find incoming object for resource"))?;
+        Ok(object.size)
+    }
+
+    async fn read(
+        &mut self,
+        object: Resource<IncomingObject>,
+        max_len: u32,
+    ) -> Result<Vec<u8>, Error> {
+        let object = self.incoming_object(&object)?;
+        if max_len == 0 {
+            return Ok(vec![]);
+        }
+        while object.buffered.is_empty() {
+            match object.body.next().await.transpose()? {
+                Some(chunk) => object.buffered = chunk,
+                None => return Ok(vec![]),
+            }
+        }
+        let len = object.buffered.len().min(max_len as usize);
+        Ok(object.buffered.split_to(len).to_vec())
+    }
+
+    async fn drop(&mut self, object: Resource<IncomingObject>) -> anyhow::Result<()> {
+        self.incoming.remove(object.rep());
+        Ok(())
+    }
+}
+
+impl blob_store::HostOutgoingObject for InstanceState {
+    async fn write(
+        &mut self,
+        object: Resource<OutgoingObject>,
+        data: Vec<u8>,
+    ) -> Result<(), Error> {
+        let writer = self
+            .outgoing
+            .get_mut(object.rep())
+            .ok_or_else(|| Error::Other("could not find outgoing object for resource".into()))?;
+        if data.is_empty() {
+            return Ok(());
+        }
+        writer.write(data.into()).await
+    }
+
+    #[instrument(name = "spin_blob_store.finish", skip_all, err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn finish(&mut self, object: Resource<OutgoingObject>) -> Result<(), Error> {
+        let writer = self
+            .outgoing
+            .remove(object.rep())
+            .ok_or_else(|| Error::Other("could not find outgoing object for resource".into()))?;
+        writer.finish().await
+    }
+
+    async fn drop(&mut self, object: Resource<OutgoingObject>) -> anyhow::Result<()> {
+        // Dropping an unfinished writer discards the object
+        self.outgoing.remove(object.rep());
+        Ok(())
+    }
+}
+
+impl blob_store::HostObjectListing for InstanceState {
+    async fn next(
+        &mut self,
+        listing: Resource<ObjectListing>,
+        max_len: u32,
+    ) -> Result<Vec<ObjectInfo>, Error> {
+        let listing = self
+            .listings
+            .get_mut(listing.rep())
+            .ok_or_else(|| Error::Other("could not find object listing for resource".into()))?;
+        let mut objects = vec![];
+        while objects.len() < max_len as usize {
+            match listing.next().await.transpose()? {
+                Some(object) => objects.push(object),
+                None => break,
+            }
+        }
+        Ok(objects)
+    }
+
+    async fn drop(&mut self, listing: Resource<ObjectListing>) -> anyhow::Result<()> {
+        self.listings.remove(listing.rep());
+        Ok(())
+    }
+}
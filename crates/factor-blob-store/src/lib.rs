@@ -0,0 +1,228 @@
+mod host;
+pub mod runtime_config;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use host::InstanceState;
+use spin_factors::{anyhow, Factor, FactorData};
+use spin_locked_app::MetadataKey;
+use spin_world::spin::blob_store::blob_store;
+use spin_world::spin::blob_store::types::{Error, ObjectInfo};
+
+pub use runtime_config::RuntimeConfig;
+
+/// Metadata key for a list of allowed blob stores for a component.
+pub const ALLOWED_STORES_KEY: MetadataKey<Vec<String>> = MetadataKey::new("blob_stores");
+
+/// A factor that lets components store and retrieve objects, such as files,
+/// which are too large for a key-value store.
+#[derive(Default)]
+pub struct BlobStoreFactor {
+    _priv: (),
+}
+
+impl BlobStoreFactor {
+    /// Create a new `BlobStoreFactor`
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for BlobStoreFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(blob_store::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: spin_factors::RuntimeFactors>(
+        &self,
+        mut ctx: spin_factors::ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let stores = ctx.take_runtime_config().unwrap_or_default().stores;
+
+        let allowed_stores = ctx
+            .app()
+            .components()
+            .map(|component| {
+                Ok((
+                    component.id().to_string(),
+                    Arc::new(
+                        component
+                            .get_metadata(ALLOWED_STORES_KEY)?
+                            .unwrap_or_default()
+                            .into_iter()
+                            .collect::<HashSet<_>>(),
+                    ),
+                ))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+        ensure_allowed_stores_are_configured(&allowed_stores, |label| stores.contains_key(label))?;
+
+        Ok(AppState {
+            allowed_stores,
+            stores,
+        })
+    }
+
+    fn prepare<T: spin_factors::RuntimeFactors>(
+        &self,
+        ctx: spin_factors::PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let allowed_stores = ctx
+            .app_state()
+            .allowed_stores
+            .get(ctx.app_component().id())
+            .cloned()
+            .unwrap_or_default();
+        Ok(InstanceState::new(
+            allowed_stores,
+            ctx.app_state().stores.clone(),
+        ))
+    }
+}
+
+/// Ensure that all the stores in the allowed stores list for each component are configured
+fn ensure_allowed_stores_are_configured(
+    allowed_stores: &HashMap<String, Arc<HashSet<String>>>,
+    is_configured: impl Fn(&str) -> bool,
+) -> anyhow::Result<()> {
+    let mut errors = Vec::new();
+    for (component_id, stores) in allowed_stores {
+        for allowed in stores.iter() {
+            if !is_configured(allowed) {
+                errors.push(format!(
+                    "- Component {component_id} uses blob store '{allowed}'"
+                ));
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        let prologue = vec![
+            "One or more components use blob stores which are not defined.",
+            "Check the spelling, or pass a runtime configuration file that defines these stores.",
+            "Details:",
+        ];
+        let lines: Vec<_> = prologue
+            .into_iter()
+            .map(|s| s.to_owned())
+            .chain(errors)
+            .collect();
+        return Err(anyhow::anyhow!(lines.join("\n")));
+    }
+    Ok(())
+}
+
+pub struct AppState {
+    /// A map from component id to a set of allowed store labels.
+    allowed_stores: HashMap<String, Arc<HashSet<String>>>,
+    /// A map from store label to store.
+    stores: HashMap<String, Arc<dyn BlobStore>>,
+}
+
+/// The body of an object being read from a store.
+pub struct IncomingObject {
+    /// The size of the object in bytes.
+    pub size: u64,
+    /// The chunks of the body.
+    pub body: BoxStream<'static, Result<Bytes, Error>>,
+}
+
+/// A store of objects, such as files, identified by name.
+///
+/// Names passed to a store have been checked by [`validate_name`].
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Start reading the object with the given name, if it exists.
+    async fn get(&self, name: &str) -> Result<Option<IncomingObject>, Error>;
+
+    /// Start writing the object with the given name. The object must not be
+    /// visible until the writer is finished, and must be discarded if the
+    /// writer is dropped before then.
+    async fn put(&self, name: &str) -> Result<Box<dyn ObjectWriter>, Error>;
+
+    /// Delete the object with the given name, if it exists.
+    async fn delete(&self, name: &str) -> Result<(), Error>;
+
+    /// Get information about the object with the given name, if it exists.
+    async fn info(&self, name: &str) -> Result<Option<ObjectInfo>, Error>;
+
+    /// List the objects whose names start with `prefix`, or all objects if
+    /// there is none.
+    fn list(&self, prefix: Option<String>) -> BoxStream<'static, Result<ObjectInfo, Error>>;
+
+    /// A human-readable summary of the store's configuration
+    ///
+    /// Example: "S3 bucket my-bucket"
+    fn summary(&self) -> Option<String> {
+        None
+    }
+}
+
+/// The body of an object being written to a store.
+#[async_trait]
+pub trait ObjectWriter: Send {
+    /// Append `data` to the body.
+    async fn write(&mut self, data: Bytes) -> Result<(), Error>;
+
+    /// Finish writing the body, storing the object.
+    async fn finish(self: Box<Self>) -> Result<(), Error>;
+}
+
+/// Checks that an object name is a non-empty path of `/`-separated segments,
+/// none of which are empty, `.` or `..`, so that stores may map names to
+/// paths without escaping their directory.
+pub fn validate_name(name: &str) -> Result<(), Error> {
+    let invalid = |reason: &str| Err(Error::InvalidName(format!("{name:?} {reason}")));
+    if name.is_empty() {
+        return invalid("is empty");
+    }
+    if name.len() > 1024 {
+        return invalid("is longer than 1024 bytes");
+    }
+    if name.contains(['\\', '\0']) {
+        return invalid("contains a backslash or NUL character");
+    }
+    if name
+        .split('/')
+        .any(|segment| segment.is_empty() || segment == "." || segment == "..")
+    {
+        return invalid("has an empty, '.' or '..' path segment");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_must_be_relative_paths() {
+        for name in ["report.pdf", "uploads/2024/photo.jpg", ".hidden", "a..b"] {
+            assert!(validate_name(name).is_ok(), "{name:?} should be valid");
+        }
+        for name in [
+            "",
+            "/etc/passwd",
+            "uploads/",
+            "uploads//photo.jpg",
+            "../secret",
+            "a/./b",
+            "a\\b",
+        ] {
+            assert!(
+                matches!(validate_name(name), Err(Error::InvalidName(_))),
+                "{name:?} should be invalid"
+            );
+        }
+    }
+}
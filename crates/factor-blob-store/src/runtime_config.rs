@@ -0,0 +1,13 @@
+pub mod spin;
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::BlobStore;
+
+/// A runtime configuration for blob stores.
+///
+/// Maps store labels to stores.
+#[derive(Default)]
+pub struct RuntimeConfig {
+    pub stores: HashMap<String, Arc<dyn BlobStore>>,
+}
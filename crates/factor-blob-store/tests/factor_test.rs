@@ -0,0 +1,164 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt};
+use spin_factor_blob_store::{
+    BlobStore, BlobStoreFactor, IncomingObject, ObjectWriter, RuntimeConfig,
+};
+use spin_factors::{
+    anyhow::{self, bail, Context as _},
+    wasmtime::component::Resource,
+    RuntimeFactors,
+};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::{
+    async_trait,
+    spin::blob_store::blob_store::{HostIncomingObject as _, HostStore as _},
+    spin::blob_store::types::{Error, ObjectInfo},
+};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    blob_store: BlobStoreFactor,
+}
+
+#[tokio::test]
+async fn errors_when_non_configured_store_used() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        blob_store: BlobStoreFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        blob_stores = ["foo"]
+    });
+    let Err(err) = env.build_instance_state().await else {
+        bail!("Expected build_instance_state to error but it did not");
+    };
+
+    assert!(err
+        .to_string()
+        .contains("One or more components use blob stores which are not defined."));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_when_store_not_allowed() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        blob_store: BlobStoreFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+        })
+        .runtime_config(runtime_config())?;
+    let mut state = env
+        .build_instance_state()
+        .await
+        .context("build_instance_state failed")?;
+
+    assert!(matches!(
+        state.blob_store.open("foo".into()).await,
+        Err(Error::AccessDenied)
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reads_object_from_configured_store() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        blob_store: BlobStoreFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            blob_stores = ["foo"]
+        })
+        .runtime_config(runtime_config())?;
+    let mut state = env
+        .build_instance_state()
+        .await
+        .context("build_instance_state failed")?;
+
+    let store = state.blob_store.open("foo".into()).await?;
+    assert!(matches!(
+        state
+            .blob_store
+            .get(Resource::new_borrow(store.rep()), "../secret".into())
+            .await,
+        Err(Error::InvalidName(_))
+    ));
+    let object = state
+        .blob_store
+        .get(Resource::new_borrow(store.rep()), "greeting.txt".into())
+        .await?
+        .context("object should exist")?;
+    assert_eq!(
+        state
+            .blob_store
+            .size(Resource::new_borrow(object.rep()))
+            .await?,
+        11
+    );
+
+    // Reads span the chunks of the body
+    let mut body = vec![];
+    loop {
+        let chunk = state
+            .blob_store
+            .read(Resource::new_borrow(object.rep()), 4)
+            .await?;
+        if chunk.is_empty() {
+            break;
+        }
+        assert!(chunk.len() <= 4);
+        body.extend(chunk);
+    }
+    assert_eq!(body, b"hello world");
+    Ok(())
+}
+
+fn runtime_config() -> TestFactorsRuntimeConfig {
+    let mut stores = HashMap::new();
+    stores.insert("foo".to_owned(), Arc::new(MockStore) as _);
+    TestFactorsRuntimeConfig {
+        blob_store: Some(RuntimeConfig { stores }),
+    }
+}
+
+/// A read-only store holding a single object.
+struct MockStore;
+
+#[async_trait]
+impl BlobStore for MockStore {
+    async fn get(&self, name: &str) -> Result<Option<IncomingObject>, Error> {
+        if name != "greeting.txt" {
+            return Ok(None);
+        }
+        let chunks = ["hello", " ", "world"].map(|chunk| Ok(Bytes::from_static(chunk.as_bytes())));
+        Ok(Some(IncomingObject {
+            size: 11,
+            body: stream::iter(chunks).boxed(),
+        }))
+    }
+
+    async fn put(&self, _name: &str) -> Result<Box<dyn ObjectWriter>, Error> {
+        Err(Error::Other("store is read-only".into()))
+    }
+
+    async fn delete(&self, _name: &str) -> Result<(), Error> {
+        Err(Error::Other("store is read-only".into()))
+    }
+
+    async fn info(&self, _name: &str) -> Result<Option<ObjectInfo>, Error> {
+        Ok(None)
+    }
+
+    fn list(&self, _prefix: Option<String>) -> BoxStream<'static, Result<ObjectInfo, Error>> {
+        stream::empty().boxed()
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use spin_factors_executor::InvocationContext;
+use spin_world::spin::context::invocation::{self, Context, HttpRequest, Pair, Trigger};
+
+/// The prefix of the fields which hold the values of an HTTP route's named
+/// parameters.
+const PARAM_FIELD_PREFIX: &str = "param.";
+
+pub struct InstanceState {
+    app_id: String,
+    component_id: String,
+    triggers: Vec<Trigger>,
+    invocation_context: Option<InvocationContext>,
+}
+
+impl InstanceState {
+    pub fn new(app_id: String, component_id: String, triggers: Vec<Trigger>) -> Self {
+        Self {
+            app_id,
+            component_id,
+            triggers,
+            invocation_context: None,
+        }
+    }
+
+    /// Sets the invocation context whose fields are given to the instance.
+    ///
+    /// The context is shared with the instance's executor state, so fields
+    /// set after the instance is prepared, or for a warm instance's later
+    /// invocation, are seen too.
+    pub fn set_invocation_context(&mut self, invocation_context: InvocationContext) {
+        self.invocation_context = Some(invocation_context);
+    }
+}
+
+impl invocation::Host for InstanceState {
+    async fn get_context(&mut self) -> Result<Context> {
+        let mut fields = self
+            .invocation_context
+            .as_ref()
+            .map(InvocationContext::fields)
+            .unwrap_or_default();
+        let http = fields.remove("route").map(|route| HttpRequest {
+            route,
+            parameters: fields
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some(Pair {
+                        name: name.strip_prefix(PARAM_FIELD_PREFIX)?.to_owned(),
+                        value: value.clone(),
+                    })
+                })
+                .collect(),
+            path_info: fields.remove("path_info").unwrap_or_default(),
+            client_ip: fields.remove("client_ip").unwrap_or_default(),
+        });
+        Ok(Context {
+            app_id: self.app_id.clone(),
+            component_id: self.component_id.clone(),
+            triggers: self.triggers.clone(),
+            request_id: fields.remove("request_id"),
+            trace_id: spin_telemetry::guest::current_trace_id(),
+            http,
+        })
+    }
+
+    async fn get_field(&mut self, name: String) -> Result<Option<String>> {
+        Ok(self
+            .invocation_context
+            .as_ref()
+            .and_then(|context| context.fields().remove(&name)))
+    }
+}
@@ -0,0 +1,89 @@
+mod host;
+
+use std::{collections::HashMap, sync::Arc};
+
+use host::InstanceState;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::context::invocation::Trigger;
+
+/// A factor that lets components get the context of their invocation, such
+/// as the route and client of an HTTP request, from the host.
+///
+/// The context is read from the fields of the instance's
+/// [`InvocationContext`](spin_factors_executor::InvocationContext), which
+/// triggers set; an executor hook gives the context to the factor's instance
+/// builder with `set_invocation_context`.
+#[derive(Default)]
+pub struct ContextFactor {
+    _priv: (),
+}
+
+impl ContextFactor {
+    /// Create a new ContextFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for ContextFactor {
+    type RuntimeConfig = ();
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(
+            spin_world::spin::context::invocation::add_to_linker::<_, FactorData<Self>>,
+        )?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let mut component_triggers = HashMap::<_, Vec<_>>::new();
+        for trigger in ctx.app().triggers() {
+            // Triggers without a component never invoke one
+            let Ok(component) = trigger.component() else {
+                continue;
+            };
+            component_triggers
+                .entry(component.id().to_owned())
+                .or_default()
+                .push(Trigger {
+                    id: trigger.id().to_owned(),
+                    trigger_type: trigger.trigger_type().to_owned(),
+                });
+        }
+        Ok(AppState {
+            component_triggers: Arc::new(component_triggers),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let app_component = ctx.app_component();
+        let triggers = ctx
+            .app_state()
+            .component_triggers
+            .get(app_component.id())
+            .cloned()
+            .unwrap_or_default();
+        Ok(InstanceState::new(
+            app_component.app.id().to_owned(),
+            app_component.id().to_owned(),
+            triggers,
+        ))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// The triggers of each of an app's components.
+pub struct AppState {
+    component_triggers: Arc<HashMap<String, Vec<Trigger>>>,
+}
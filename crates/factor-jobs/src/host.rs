@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use spin_world::spin::jobs::jobs;
+use spin_world::spin::jobs::types::{self, Error};
+use tracing::{instrument, Level};
+
+use crate::queue::JobQueue;
+
+pub struct InstanceState {
+    queue: JobQueue,
+}
+
+impl InstanceState {
+    pub fn new(queue: JobQueue) -> Self {
+        Self { queue }
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl jobs::Host for InstanceState {
+    #[instrument(name = "spin_jobs.enqueue", skip(self, payload), err(level = Level::INFO), fields(otel.kind = "producer"))]
+    async fn enqueue(
+        &mut self,
+        name: String,
+        payload: Vec<u8>,
+        delay_ms: u64,
+    ) -> Result<String, Error> {
+        if name.is_empty() {
+            return Err(Error::InvalidName);
+        }
+        self.queue
+            .enqueue(&name, payload, Duration::from_millis(delay_ms))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to enqueue job {name:?}: {e:?}");
+                Error::StoreUnavailable(format!("{e:#}"))
+            })
+    }
+}
@@ -0,0 +1,77 @@
+mod host;
+pub mod queue;
+pub mod runtime_config;
+
+use anyhow::{ensure, Context as _};
+use host::InstanceState;
+use queue::JobQueue;
+use runtime_config::RuntimeConfig;
+use spin_factor_key_value::KeyValueFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+
+/// A factor that lets components enqueue background jobs, which the job
+/// trigger runs. Jobs are kept in a key-value store.
+#[derive(Default)]
+pub struct JobsFactor {
+    _priv: (),
+}
+
+impl JobsFactor {
+    /// Create a new JobsFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for JobsFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::jobs::jobs::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let config = ctx.take_runtime_config().unwrap_or_default();
+        let store_manager = ctx
+            .app_state::<KeyValueFactor>()
+            .context("JobsFactor depends on KeyValueFactor")?
+            .store_manager();
+        ensure!(
+            store_manager.is_defined(&config.store),
+            "jobs store {:?} is not a configured key-value store",
+            config.store
+        );
+        Ok(AppState {
+            queue: JobQueue::new(store_manager, &config.store),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        Ok(InstanceState::new(ctx.app_state().queue.clone()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// The job queue of an app.
+pub struct AppState {
+    queue: JobQueue,
+}
+
+impl AppState {
+    /// Returns the queue holding the app's jobs.
+    pub fn queue(&self) -> &JobQueue {
+        &self.queue
+    }
+}
@@ -0,0 +1,319 @@
+//! Jobs kept in a key-value store, shared by the components which enqueue
+//! them and the job trigger which runs them.
+//!
+//! Each pending job is a JSON value under the key `spin-jobs/pending/<id>`,
+//! and each dead letter under `spin-jobs/dead/<id>`. Job triggers claim a
+//! job with a compare-and-swap before running it, so that a job is run by one
+//! trigger at a time even if several share the store.
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use spin_factor_key_value::{Store, StoreManager, SwapError};
+use spin_serde::Base64Bytes;
+use tokio::sync::OnceCell;
+
+const PENDING_PREFIX: &str = "spin-jobs/pending/";
+const DEAD_PREFIX: &str = "spin-jobs/dead/";
+
+/// A job which has not yet succeeded or run out of attempts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub name: String,
+    pub payload: Base64Bytes,
+    /// When the job is next due to run, in milliseconds since the Unix epoch.
+    pub due_at: u64,
+    /// How many attempts at the job have failed.
+    pub failures: u32,
+    /// The error of the last failed attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Until when a trigger has claimed the job, in milliseconds since the
+    /// Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    claimed_until: Option<u64>,
+}
+
+/// A job which failed on every attempt the trigger allowed it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    #[serde(flatten)]
+    pub job: Job,
+    /// When the last attempt failed, in milliseconds since the Unix epoch.
+    pub failed_at: u64,
+}
+
+/// The jobs in a key-value store. The store is opened when first used.
+#[derive(Clone)]
+pub struct JobQueue {
+    store_manager: Arc<dyn StoreManager>,
+    label: Arc<str>,
+    store: Arc<OnceCell<Arc<dyn Store>>>,
+}
+
+impl JobQueue {
+    /// Creates a queue of the jobs in the store with the given label.
+    pub fn new(store_manager: Arc<dyn StoreManager>, label: &str) -> Self {
+        Self {
+            store_manager,
+            label: label.into(),
+            store: Default::default(),
+        }
+    }
+
+    /// The label of the key-value store holding the jobs.
+    pub fn store_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn store(&self) -> anyhow::Result<&dyn Store> {
+        let store = self
+            .store
+            .get_or_try_init(|| self.store_manager.get(&self.label))
+            .await
+            .with_context(|| format!("failed to open key-value store {:?}", self.label))?;
+        Ok(store.as_ref())
+    }
+
+    /// Adds a job, due after the given delay. Returns the job's ID.
+    pub async fn enqueue(
+        &self,
+        name: &str,
+        payload: Vec<u8>,
+        delay: Duration,
+    ) -> anyhow::Result<String> {
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_owned(),
+            payload: payload.into(),
+            due_at: now_millis().saturating_add(millis(delay)),
+            failures: 0,
+            last_error: None,
+            claimed_until: None,
+        };
+        self.put(&job).await?;
+        Ok(job.id)
+    }
+
+    /// Returns the jobs which are due and not claimed, earliest first.
+    pub async fn due_jobs(&self) -> anyhow::Result<Vec<Job>> {
+        let store = self.store().await?;
+        let keys = store
+            .get_keys()
+            .await
+            .context("failed to list jobs")?
+            .into_iter()
+            .filter(|key| key.starts_with(PENDING_PREFIX))
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+        let now = now_millis();
+        let mut jobs = Vec::new();
+        for (key, value) in store.get_many(keys).await.context("failed to get jobs")? {
+            // The job may have been completed since the keys were listed
+            let Some(value) = value else {
+                continue;
+            };
+            match serde_json::from_slice::<Job>(&value) {
+                Ok(job) if job.due_at <= now && !job.is_claimed(now) => jobs.push(job),
+                Ok(_) => {}
+                Err(err) => tracing::warn!("Ignoring invalid job {key:?}: {err}"),
+            }
+        }
+        jobs.sort_by_key(|job| job.due_at);
+        Ok(jobs)
+    }
+
+    /// Claims a due job for the given time, so that no other trigger runs it
+    /// meanwhile. Returns false if the job has been claimed, completed or
+    /// changed since it was listed.
+    pub async fn claim(&self, job: &Job, lease: Duration) -> anyhow::Result<bool> {
+        let store = self.store().await?;
+        let cas = store
+            .new_compare_and_swap(0, &pending_key(&job.id))
+            .await
+            .context("failed to claim job")?;
+        let current = cas.current().await.context("failed to claim job")?;
+        let now = now_millis();
+        match current.map(|value| serde_json::from_slice::<Job>(&value)) {
+            Some(Ok(current)) if current == *job && !current.is_claimed(now) => {}
+            _ => return Ok(false),
+        }
+        let claimed = Job {
+            claimed_until: Some(now.saturating_add(millis(lease))),
+            ..job.clone()
+        };
+        match cas.swap(serde_json::to_vec(&claimed)?).await {
+            Ok(()) => Ok(true),
+            Err(SwapError::CasFailed(_)) => Ok(false),
+            Err(SwapError::Other(err)) => Err(anyhow::anyhow!(err).context("failed to claim job")),
+        }
+    }
+
+    /// Removes a claimed job which succeeded.
+    pub async fn complete(&self, job: &Job) -> anyhow::Result<()> {
+        self.store()
+            .await?
+            .delete(&pending_key(&job.id))
+            .await
+            .context("failed to delete completed job")
+    }
+
+    /// Releases a claimed job which failed, to run again after the given
+    /// delay.
+    pub async fn retry(&self, job: &Job, error: &str, delay: Duration) -> anyhow::Result<()> {
+        self.put(&Job {
+            due_at: now_millis().saturating_add(millis(delay)),
+            failures: job.failures + 1,
+            last_error: Some(error.to_owned()),
+            claimed_until: None,
+            ..job.clone()
+        })
+        .await
+    }
+
+    /// Moves a claimed job which failed for the last time to the dead
+    /// letters.
+    pub async fn dead_letter(&self, job: &Job, error: &str) -> anyhow::Result<()> {
+        let dead_letter = DeadLetter {
+            job: Job {
+                failures: job.failures + 1,
+                last_error: Some(error.to_owned()),
+                claimed_until: None,
+                ..job.clone()
+            },
+            failed_at: now_millis(),
+        };
+        let store = self.store().await?;
+        store
+            .set(
+                &format!("{DEAD_PREFIX}{}", job.id),
+                &serde_json::to_vec(&dead_letter)?,
+            )
+            .await
+            .context("failed to store dead letter")?;
+        store
+            .delete(&pending_key(&job.id))
+            .await
+            .context("failed to delete dead job")
+    }
+
+    async fn put(&self, job: &Job) -> anyhow::Result<()> {
+        self.store()
+            .await?
+            .set(&pending_key(&job.id), &serde_json::to_vec(job)?)
+            .await
+            .context("failed to store job")
+    }
+}
+
+impl Job {
+    fn is_claimed(&self, now: u64) -> bool {
+        self.claimed_until.is_some_and(|until| until > now)
+    }
+}
+
+fn pending_key(id: &str) -> String {
+    format!("{PENDING_PREFIX}{id}")
+}
+
+fn now_millis() -> u64 {
+    millis(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+    )
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
+    use spin_key_value_memory::MemoryKeyValueStore;
+
+    use super::*;
+
+    const LEASE: Duration = Duration::from_secs(60);
+
+    fn queue() -> JobQueue {
+        let store_manager = MemoryKeyValueStore::new()
+            .make_store(Default::default())
+            .unwrap();
+        JobQueue::new(Arc::new(store_manager), "default")
+    }
+
+    #[tokio::test]
+    async fn due_jobs_are_listed_earliest_first() -> anyhow::Result<()> {
+        let queue = queue();
+        queue
+            .store()
+            .await?
+            .set("spin-jobs-unrelated", b"not a job")
+            .await?;
+        let later = queue
+            .enqueue("email", b"later".to_vec(), Duration::ZERO)
+            .await?;
+        queue
+            .enqueue("email", b"future".to_vec(), Duration::from_secs(3600))
+            .await?;
+        let jobs = queue.due_jobs().await?;
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, later);
+        assert_eq!(&*jobs[0].payload, b"later");
+        assert_eq!(jobs[0].failures, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn claimed_jobs_are_not_listed_or_claimed_again() -> anyhow::Result<()> {
+        let queue = queue();
+        queue.enqueue("email", vec![], Duration::ZERO).await?;
+        let job = queue.due_jobs().await?.remove(0);
+        assert!(queue.claim(&job, LEASE).await?);
+        assert!(!queue.claim(&job, LEASE).await?);
+        assert!(queue.due_jobs().await?.is_empty());
+
+        queue.complete(&job).await?;
+        assert!(!queue.claim(&job, LEASE).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_jobs_are_retried_then_dead_lettered() -> anyhow::Result<()> {
+        let queue = queue();
+        queue.enqueue("email", vec![], Duration::ZERO).await?;
+        let job = queue.due_jobs().await?.remove(0);
+        assert!(queue.claim(&job, LEASE).await?);
+        queue.retry(&job, "timed out", Duration::ZERO).await?;
+
+        let job = queue.due_jobs().await?.remove(0);
+        assert_eq!(job.failures, 1);
+        assert_eq!(job.last_error.as_deref(), Some("timed out"));
+        assert!(queue.claim(&job, LEASE).await?);
+        queue.dead_letter(&job, "timed out again").await?;
+        assert!(queue.due_jobs().await?.is_empty());
+
+        let store = queue.store().await?;
+        let dead_letter: DeadLetter = serde_json::from_slice(
+            &store
+                .get(&format!("spin-jobs/dead/{}", job.id))
+                .await?
+                .context("dead letter should be stored")?,
+        )?;
+        assert_eq!(dead_letter.job.failures, 2);
+        assert_eq!(
+            dead_letter.job.last_error.as_deref(),
+            Some("timed out again")
+        );
+        Ok(())
+    }
+}
@@ -1,11 +1,13 @@
 use super::{Cas, SwapError};
 use anyhow::{Context, Result};
 use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_expressions::{ProviderResolver as ExpressionResolver, Template};
 use spin_resource_table::Table;
 use spin_telemetry::traces::{self, Blame};
+use spin_world::spin::key_value::key_value as spin_key_value;
 use spin_world::v2::key_value;
 use spin_world::wasi::keyvalue as wasi_keyvalue;
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 use tracing::instrument;
 
 const DEFAULT_STORE_TABLE_CAPACITY: u32 = 256;
@@ -33,6 +35,10 @@ pub trait Store: Sync + Send {
     }
     async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
     async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error>;
+    /// Sets a value which expires after `ttl`, which is non-zero. An expired
+    /// key behaves as if it had been deleted. `set` and compare-and-swap
+    /// clear any expiry; `increment` keeps it.
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error>;
     async fn delete(&self, key: &str) -> Result<(), Error>;
     async fn exists(&self, key: &str) -> Result<bool, Error>;
     async fn get_keys(&self) -> Result<Vec<String>, Error>;
@@ -42,10 +48,35 @@ pub trait Store: Sync + Send {
     async fn increment(&self, key: String, delta: i64) -> Result<i64, Error>;
     async fn new_compare_and_swap(&self, bucket_rep: u32, key: &str)
         -> Result<Arc<dyn Cas>, Error>;
+
+    /// Acquires the lock at `key` for the owner identified by `token`, for
+    /// `ttl`, unless another owner holds it. Returns whether the lock was
+    /// acquired.
+    ///
+    /// The default implementation keeps a lease in the value, changed with
+    /// compare-and-swap; stores with native locking may override it, in
+    /// which case all three lock methods must be overridden together.
+    async fn acquire_lock(&self, key: &str, token: &str, ttl: Duration) -> Result<bool, Error> {
+        crate::lock::acquire(self, key, token, ttl).await
+    }
+
+    /// Extends the lock at `key` to expire `ttl` from now. Returns false,
+    /// changing nothing, if `token` does not hold the lock, e.g. because it
+    /// has expired.
+    async fn renew_lock(&self, key: &str, token: &str, ttl: Duration) -> Result<bool, Error> {
+        crate::lock::renew(self, key, token, ttl).await
+    }
+
+    /// Releases the lock at `key`. Returns false, changing nothing, if
+    /// `token` does not hold the lock.
+    async fn release_lock(&self, key: &str, token: &str) -> Result<bool, Error> {
+        crate::lock::release(self, key, token).await
+    }
 }
 
 pub struct KeyValueDispatch {
     allowed_stores: HashSet<String>,
+    templated_stores: Option<(Arc<[Template]>, Arc<ExpressionResolver>)>,
     manager: Arc<dyn StoreManager>,
     stores: Table<Arc<dyn Store>>,
     compare_and_swaps: Table<Arc<dyn Cas>>,
@@ -63,12 +94,57 @@ impl KeyValueDispatch {
     ) -> Self {
         Self {
             allowed_stores,
+            templated_stores: None,
             manager,
             stores: Table::new(capacity),
             compare_and_swaps: Table::new(capacity),
         }
     }
 
+    /// Also allows the stores named by the given label templates. The
+    /// templates are resolved when a store is first opened, so that variable
+    /// values are only fetched if they are needed.
+    pub fn with_templated_stores(
+        mut self,
+        templates: Arc<[Template]>,
+        resolver: Arc<ExpressionResolver>,
+    ) -> Self {
+        self.templated_stores = Some((templates, resolver));
+        self
+    }
+
+    /// Returns whether the named store may be opened, resolving any
+    /// templated store labels first.
+    async fn is_allowed(&mut self, name: &str) -> Result<bool, String> {
+        if self.allowed_stores.contains(name) {
+            return Ok(true);
+        }
+        if let Some((templates, resolver)) = &self.templated_stores {
+            for template in templates.iter() {
+                // The error does not include the values of (possibly
+                // secret) variables, nor does the resolved label appear
+                // in logs.
+                let label = resolver.resolve_template(template).await.map_err(|err| {
+                    tracing::error!(
+                        %err, "error.type" = "variable_resolution_failed",
+                        "Error resolving key_value_stores label",
+                    );
+                    "failed to resolve key_value_stores label".to_owned()
+                })?;
+                if !self.manager.is_defined(&label) {
+                    tracing::error!(
+                        "error.type" = "unknown_store",
+                        "Templated key_value_stores label resolved to an undefined store",
+                    );
+                    return Err("key_value_stores label refers to an undefined store".to_owned());
+                }
+                self.allowed_stores.insert(label);
+            }
+            self.templated_stores = None;
+        }
+        Ok(self.allowed_stores.contains(name))
+    }
+
     pub fn get_store<T: 'static>(&self, store: Resource<T>) -> anyhow::Result<&Arc<dyn Store>> {
         let res = self.stores.get(store.rep()).context("invalid store");
         if let Err(err) = &res {
@@ -114,7 +190,7 @@ impl key_value::HostStore for KeyValueDispatch {
     #[instrument(name = "spin_key_value.open", skip(self), err, fields(otel.kind = "client", kv.backend=self.manager.summary(&name).unwrap_or("unknown".to_string())))]
     async fn open(&mut self, name: String) -> Result<Result<Resource<key_value::Store>, Error>> {
         Ok(async {
-            if self.allowed_stores.contains(&name) {
+            if self.is_allowed(&name).await.map_err(Error::Other)? {
                 let store = self.manager.get(&name).await?;
                 store.after_open().await?;
                 let store_idx = self
@@ -210,7 +286,11 @@ impl wasi_keyvalue::store::Host for KeyValueDispatch {
         &mut self,
         identifier: String,
     ) -> Result<Resource<wasi_keyvalue::store::Bucket>, wasi_keyvalue::store::Error> {
-        if self.allowed_stores.contains(&identifier) {
+        if self
+            .is_allowed(&identifier)
+            .await
+            .map_err(wasi_keyvalue::store::Error::Other)?
+        {
             let store = self.manager.get(&identifier).await.map_err(to_wasi_err)?;
             store.after_open().await.map_err(to_wasi_err)?;
             let store_idx = self
@@ -433,6 +513,54 @@ impl wasi_keyvalue::atomics::Host for KeyValueDispatch {
     }
 }
 
+impl spin_key_value::Host for KeyValueDispatch {
+    #[instrument(name = "spin_key_value.set_with_ttl", skip_all, fields(otel.kind = "client"))]
+    async fn set_with_ttl(
+        &mut self,
+        bucket: Resource<spin_key_value::Bucket>,
+        key: String,
+        value: Vec<u8>,
+        ttl_ms: u64,
+    ) -> Result<(), wasi_keyvalue::store::Error> {
+        if ttl_ms == 0 {
+            return Err(wasi_keyvalue::store::Error::Other(
+                "set_with_ttl: ttl must be greater than zero".to_owned(),
+            ));
+        }
+        let store = self.get_store_wasi(bucket)?;
+        store
+            .set_with_ttl(&key, &value, Duration::from_millis(ttl_ms))
+            .await
+            .map_err(to_wasi_err)
+    }
+
+    // Built on the stores' `Cas` implementations, so that it is atomic
+    // wherever `wasi:keyvalue/atomics` is.
+    #[instrument(name = "spin_key_value.compare_and_swap", skip_all, fields(otel.kind = "client"))]
+    async fn compare_and_swap(
+        &mut self,
+        bucket: Resource<spin_key_value::Bucket>,
+        key: String,
+        expected: Option<Vec<u8>>,
+        value: Vec<u8>,
+    ) -> Result<bool, wasi_keyvalue::store::Error> {
+        let bucket_rep = bucket.rep();
+        let store = self.get_store_wasi(bucket)?;
+        let cas = store
+            .new_compare_and_swap(bucket_rep, &key)
+            .await
+            .map_err(to_wasi_err)?;
+        if cas.current().await.map_err(to_wasi_err)? != expected {
+            return Ok(false);
+        }
+        match cas.swap(value).await {
+            Ok(()) => Ok(true),
+            Err(SwapError::CasFailed(_)) => Ok(false),
+            Err(SwapError::Other(msg)) => Err(wasi_keyvalue::store::Error::Other(msg)),
+        }
+    }
+}
+
 pub fn log_error(err: impl std::fmt::Debug) -> Error {
     tracing::warn!("key-value error: {err:?}");
     Error::Other(format!("{err:?}"))
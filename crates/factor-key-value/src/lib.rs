@@ -1,4 +1,5 @@
 mod host;
+mod lock;
 pub mod runtime_config;
 mod util;
 
@@ -7,7 +8,9 @@ use std::{
     sync::Arc,
 };
 
-use anyhow::ensure;
+use anyhow::{ensure, Context};
+use spin_expressions::{ProviderResolver as ExpressionResolver, Template};
+use spin_factor_variables::VariablesFactor;
 use spin_factors::{
     ConfigureAppContext, Factor, FactorData, FactorInstanceBuilder, InitContext, PrepareContext,
     RuntimeFactors,
@@ -47,6 +50,9 @@ impl Factor for KeyValueFactor {
         ctx.link_bindings(
             spin_world::wasi::keyvalue::atomics::add_to_linker::<_, FactorData<Self>>,
         )?;
+        ctx.link_bindings(
+            spin_world::spin::key_value::key_value::add_to_linker::<_, FactorData<Self>>,
+        )?;
         Ok(())
     }
 
@@ -61,43 +67,78 @@ impl Factor for KeyValueFactor {
 
         // Build component -> allowed stores map
         let mut component_allowed_stores = HashMap::new();
+        let mut component_templated_stores = HashMap::new();
         for component in ctx.app().components() {
             let component_id = component.id().to_string();
-            let key_value_stores = component
+            let mut key_value_stores = HashSet::new();
+            let mut templated_stores = vec![];
+            for label in component
                 .get_metadata(KEY_VALUE_STORES_KEY)?
                 .unwrap_or_default()
-                .into_iter()
-                .collect::<HashSet<_>>();
-            for label in &key_value_stores {
+            {
+                let template = Template::new(label.as_str()).with_context(|| {
+                    format!(
+                        "invalid key_value_stores label {label:?} for component {component_id:?}"
+                    )
+                })?;
+                // Templated labels depend on variable values, so they can
+                // only be checked when the component opens a store.
+                if !template.is_literal() {
+                    templated_stores.push(template);
+                    continue;
+                }
                 // TODO: port nicer errors from KeyValueComponent (via error type?)
                 ensure!(
-                    store_manager.is_defined(label),
+                    store_manager.is_defined(&label),
                     "unknown key_value_stores label {label:?} for component {component_id:?}"
                 );
+                key_value_stores.insert(label);
+            }
+            component_allowed_stores.insert(component_id.clone(), key_value_stores);
+            if !templated_stores.is_empty() {
+                component_templated_stores.insert(component_id, Arc::from(templated_stores));
             }
-            component_allowed_stores.insert(component_id, key_value_stores);
             // TODO: warn (?) on unused store?
         }
 
         Ok(AppState {
             store_manager,
             component_allowed_stores,
+            component_templated_stores,
         })
     }
 
     fn prepare<T: RuntimeFactors>(
         &self,
-        ctx: PrepareContext<T, Self>,
+        mut ctx: PrepareContext<T, Self>,
     ) -> anyhow::Result<InstanceBuilder> {
         let app_state = ctx.app_state();
+        let component_id = ctx.app_component().id();
         let allowed_stores = app_state
             .component_allowed_stores
-            .get(ctx.app_component().id())
+            .get(component_id)
             .expect("component should be in component_stores")
             .clone();
+        let templated_stores = app_state
+            .component_templated_stores
+            .get(component_id)
+            .cloned();
+        let store_manager = app_state.store_manager.clone();
+        let templated_stores = match templated_stores {
+            Some(templates) => {
+                let resolver = ctx
+                    .instance_builder::<VariablesFactor>()
+                    .context("templated key_value_stores labels require variables")?
+                    .expression_resolver()
+                    .clone();
+                Some((templates, resolver))
+            }
+            None => None,
+        };
         Ok(InstanceBuilder {
-            store_manager: app_state.store_manager.clone(),
+            store_manager,
             allowed_stores,
+            templated_stores,
         })
     }
 }
@@ -116,6 +157,10 @@ pub struct AppState {
     /// This is a map from component ID to the set of store labels that the
     /// component is allowed to use.
     component_allowed_stores: HashMap<String, HashSet<String>>,
+    /// The store labels for each component which contain variable
+    /// templates, and so are resolved when the component first opens a
+    /// store.
+    component_templated_stores: HashMap<String, Arc<[Template]>>,
 }
 
 impl AppState {
@@ -125,6 +170,9 @@ impl AppState {
     }
 
     /// Returns true if the given store label is used by any component.
+    ///
+    /// Templated labels are not resolved, so a store which is only used via
+    /// a templated label is not reported as used.
     pub fn store_is_used(&self, label: &str) -> bool {
         self.component_allowed_stores
             .values()
@@ -135,6 +183,12 @@ impl AppState {
     pub async fn get_store(&self, label: &str) -> Option<Arc<dyn Store>> {
         self.store_manager.get(label).await.ok()
     }
+
+    /// Returns the store manager, for host features which keep their own
+    /// data in the app's stores.
+    pub fn store_manager(&self) -> Arc<dyn StoreManager> {
+        self.store_manager.clone()
+    }
 }
 
 /// `SwapError` are errors that occur during compare and swap operations
@@ -177,6 +231,9 @@ pub struct InstanceBuilder {
     store_manager: Arc<AppStoreManager>,
     /// The allowed stores for this component instance.
     allowed_stores: HashSet<String>,
+    /// The templated store labels of this component, and the resolver for
+    /// their variables.
+    templated_stores: Option<(Arc<[Template]>, Arc<ExpressionResolver>)>,
 }
 
 impl FactorInstanceBuilder for InstanceBuilder {
@@ -186,11 +243,12 @@ impl FactorInstanceBuilder for InstanceBuilder {
         let Self {
             store_manager,
             allowed_stores,
+            templated_stores,
         } = self;
-        Ok(KeyValueDispatch::new_with_capacity(
-            allowed_stores,
-            store_manager,
-            u32::MAX,
-        ))
+        let dispatch = KeyValueDispatch::new_with_capacity(allowed_stores, store_manager, u32::MAX);
+        Ok(match templated_stores {
+            Some((templates, resolver)) => dispatch.with_templated_stores(templates, resolver),
+            None => dispatch,
+        })
     }
 }
@@ -0,0 +1,110 @@
+//! The default implementation of the lock methods of [`Store`], for stores
+//! with no native locking.
+//!
+//! A lock is a JSON lease under its key, naming the owner's token and when
+//! the lease expires, which is changed with compare-and-swap. Expiry is
+//! judged by the clock of whichever host looks at the lease, so hosts sharing
+//! a store need roughly agreeing clocks.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Cas, Error, Store, SwapError};
+
+#[derive(Serialize, Deserialize)]
+struct Lease {
+    token: String,
+    /// When the lease expires, in milliseconds since the Unix epoch.
+    expires_at: u64,
+}
+
+impl Lease {
+    fn new(token: &str, ttl: Duration) -> Self {
+        let ttl = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+        Self {
+            token: token.to_owned(),
+            expires_at: now_millis().saturating_add(ttl),
+        }
+    }
+
+    fn is_held_by(&self, token: &str) -> bool {
+        self.token == token && !self.is_expired()
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at <= now_millis()
+    }
+}
+
+pub(crate) async fn acquire<S: Store + ?Sized>(
+    store: &S,
+    key: &str,
+    token: &str,
+    ttl: Duration,
+) -> Result<bool, Error> {
+    let cas = store.new_compare_and_swap(0, key).await?;
+    // A value which isn't a lease can't be a live lock
+    if let Some(lease) = cas.current().await?.as_deref().and_then(parse_lease) {
+        if !lease.is_expired() {
+            return Ok(false);
+        }
+    }
+    swap(cas.as_ref(), Lease::new(token, ttl)).await
+}
+
+pub(crate) async fn renew<S: Store + ?Sized>(
+    store: &S,
+    key: &str,
+    token: &str,
+    ttl: Duration,
+) -> Result<bool, Error> {
+    let cas = store.new_compare_and_swap(0, key).await?;
+    if !is_held_by(cas.current().await?, token) {
+        return Ok(false);
+    }
+    swap(cas.as_ref(), Lease::new(token, ttl)).await
+}
+
+pub(crate) async fn release<S: Store + ?Sized>(
+    store: &S,
+    key: &str,
+    token: &str,
+) -> Result<bool, Error> {
+    let cas = store.new_compare_and_swap(0, key).await?;
+    if !is_held_by(cas.current().await?, token) {
+        return Ok(false);
+    }
+    // The lease is expired rather than the key deleted, so that a lease taken
+    // by someone else in the meantime is never removed.
+    swap(cas.as_ref(), Lease::new(token, Duration::ZERO)).await
+}
+
+fn is_held_by(value: Option<Vec<u8>>, token: &str) -> bool {
+    value
+        .as_deref()
+        .and_then(parse_lease)
+        .is_some_and(|lease| lease.is_held_by(token))
+}
+
+fn parse_lease(value: &[u8]) -> Option<Lease> {
+    serde_json::from_slice(value).ok()
+}
+
+async fn swap(cas: &dyn Cas, lease: Lease) -> Result<bool, Error> {
+    let value = serde_json::to_vec(&lease).map_err(|e| Error::Other(e.to_string()))?;
+    match cas.swap(value).await {
+        Ok(()) => Ok(true),
+        Err(SwapError::CasFailed(_)) => Ok(false),
+        Err(SwapError::Other(e)) => Err(Error::Other(e)),
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+        .try_into()
+        .unwrap_or(u64::MAX)
+}
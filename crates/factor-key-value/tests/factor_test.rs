@@ -1,10 +1,11 @@
 use anyhow::bail;
 use spin_core::async_trait;
 use spin_factor_key_value::{Cas, KeyValueFactor, RuntimeConfig, Store, StoreManager};
+use spin_factor_variables::VariablesFactor;
 use spin_factors::RuntimeFactors;
 use spin_factors_test::{toml, TestEnvironment};
 use spin_world::v2::key_value::{Error, HostStore};
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 #[derive(RuntimeFactors)]
 struct TestFactors {
@@ -98,6 +99,48 @@ async fn errors_when_store_is_not_allowed() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn resolves_templated_store_labels_on_open() -> anyhow::Result<()> {
+    #[derive(RuntimeFactors)]
+    struct WithVariables {
+        variables: VariablesFactor,
+        key_value: KeyValueFactor,
+    }
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.add_store_manager("tenant-cache".into(), mock_store_manager());
+    let factors = WithVariables {
+        variables: VariablesFactor::default(),
+        key_value: KeyValueFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [variables]
+        tenant = { default = "tenant" }
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        key_value_stores = ["{{ tenant }}-cache"]
+    });
+    let mut state = env
+        .runtime_config(WithVariablesRuntimeConfig {
+            key_value: Some(runtime_config),
+            ..Default::default()
+        })?
+        .build_instance_state()
+        .await?;
+
+    assert!(matches!(
+        state.key_value.open("other".to_owned()).await?,
+        Err(Error::AccessDenied)
+    ));
+    assert!(state
+        .key_value
+        .open("tenant-cache".to_owned())
+        .await?
+        .is_ok());
+    assert!(state.key_value.allowed_stores().contains("tenant-cache"));
+    Ok(())
+}
+
 fn mock_store_manager() -> Arc<dyn StoreManager> {
     Arc::new(MockStoreManager)
 }
@@ -129,6 +172,10 @@ impl Store for MockStore {
         let _ = (key, value);
         todo!()
     }
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let _ = (key, value, ttl);
+        todo!()
+    }
     async fn delete(&self, key: &str) -> Result<(), Error> {
         let _ = key;
         todo!()
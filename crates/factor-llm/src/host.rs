@@ -1,3 +1,6 @@
+use futures::stream::{Fuse, StreamExt};
+use spin_core::wasmtime::component::Resource;
+use spin_world::spin::llm::streaming::{self, InferencingStream};
 use spin_world::v1::llm::{self as v1};
 use spin_world::v2::llm::{self as v2};
 use tracing::field::Empty;
@@ -5,6 +8,24 @@ use tracing::{instrument, Level};
 
 use crate::InstanceState;
 
+/// An inferencing stream opened by a component.
+pub(crate) struct StreamState {
+    stream: Fuse<crate::InferencingStream>,
+    /// The usage information received from the stream, if any yet.
+    usage: Option<v2::InferencingUsage>,
+}
+
+impl InstanceState {
+    fn get_stream(
+        &mut self,
+        stream: &Resource<InferencingStream>,
+    ) -> Result<&mut StreamState, v2::Error> {
+        self.streams.get_mut(stream.rep()).ok_or_else(|| {
+            v2::Error::RuntimeError("could not find inferencing stream for resource".into())
+        })
+    }
+}
+
 impl v2::Host for InstanceState {
     #[instrument(name = "spin_llm.infer", skip(self, prompt), err(level = Level::INFO), fields(otel.kind = "client", llm.backend = Empty))]
     async fn infer(
@@ -19,18 +40,7 @@ impl v2::Host for InstanceState {
         let mut engine = self.engine.lock().await;
         tracing::Span::current().record("llm.backend", engine.summary());
         engine
-            .infer(
-                model,
-                prompt,
-                params.unwrap_or(v2::InferencingParams {
-                    max_tokens: 100,
-                    repeat_penalty: 1.1,
-                    repeat_penalty_last_n_token_count: 64,
-                    temperature: 0.8,
-                    top_k: 40,
-                    top_p: 0.9,
-                }),
-            )
+            .infer(model, prompt, params.unwrap_or_else(default_params))
             .await
     }
 
@@ -82,6 +92,77 @@ impl v1::Host for InstanceState {
     }
 }
 
+impl streaming::Host for InstanceState {
+    #[instrument(name = "spin_llm.infer_stream", skip(self, prompt), err(level = Level::INFO), fields(otel.kind = "client", llm.backend = Empty))]
+    async fn infer_stream(
+        &mut self,
+        model: v2::InferencingModel,
+        prompt: String,
+        params: Option<v2::InferencingParams>,
+    ) -> Result<Resource<InferencingStream>, v2::Error> {
+        if !self.allowed_models.contains(&model) {
+            return Err(access_denied_error(&model));
+        }
+        let stream = {
+            let mut engine = self.engine.lock().await;
+            tracing::Span::current().record("llm.backend", engine.summary());
+            engine
+                .infer_stream(model, prompt, params.unwrap_or_else(default_params))
+                .await?
+        };
+        self.streams
+            .push(StreamState {
+                stream: stream.fuse(),
+                usage: None,
+            })
+            .map(Resource::new_own)
+            .map_err(|_| v2::Error::RuntimeError("too many inferencing streams are open".into()))
+    }
+}
+
+impl streaming::HostInferencingStream for InstanceState {
+    async fn next(
+        &mut self,
+        stream: Resource<InferencingStream>,
+    ) -> Result<Option<String>, v2::Error> {
+        let state = self.get_stream(&stream)?;
+        while let Some(chunk) = state.stream.next().await {
+            let chunk = chunk?;
+            if chunk.usage.is_some() {
+                state.usage = chunk.usage;
+            }
+            if !chunk.text.is_empty() {
+                return Ok(Some(chunk.text));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn usage(
+        &mut self,
+        stream: Resource<InferencingStream>,
+    ) -> anyhow::Result<Option<v2::InferencingUsage>> {
+        Ok(self.get_stream(&stream)?.usage)
+    }
+
+    async fn drop(&mut self, stream: Resource<InferencingStream>) -> anyhow::Result<()> {
+        self.streams.remove(stream.rep());
+        Ok(())
+    }
+}
+
+/// The params used when a component doesn't give any.
+fn default_params() -> v2::InferencingParams {
+    v2::InferencingParams {
+        max_tokens: 100,
+        repeat_penalty: 1.1,
+        repeat_penalty_last_n_token_count: 64,
+        temperature: 0.8,
+        top_k: 40,
+        top_p: 0.9,
+    }
+}
+
 fn access_denied_error(model: &str) -> v2::Error {
     v2::Error::InvalidInput(format!(
         "The component does not have access to use '{model}'. To give the component access, add '{model}' to the 'ai_models' key for the component in your spin.toml manifest"
@@ -5,6 +5,7 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
 use spin_factors::{
     ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
 };
@@ -39,6 +40,7 @@ impl Factor for LlmFactor {
     fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
         ctx.link_bindings(spin_world::v1::llm::add_to_linker::<_, FactorData<Self>>)?;
         ctx.link_bindings(spin_world::v2::llm::add_to_linker::<_, FactorData<Self>>)?;
+        ctx.link_bindings(spin_world::spin::llm::streaming::add_to_linker::<_, FactorData<Self>>)?;
         Ok(())
     }
 
@@ -86,6 +88,7 @@ impl Factor for LlmFactor {
         Ok(InstanceState {
             engine,
             allowed_models,
+            streams: spin_resource_table::Table::new(1024),
         })
     }
 }
@@ -100,6 +103,7 @@ pub struct AppState {
 pub struct InstanceState {
     engine: Arc<Mutex<dyn LlmEngine>>,
     pub allowed_models: Arc<HashSet<String>>,
+    streams: spin_resource_table::Table<host::StreamState>,
 }
 
 /// The runtime configuration for the LLM factor.
@@ -119,6 +123,25 @@ pub trait LlmEngine: Send + Sync {
         params: v2::InferencingParams,
     ) -> Result<v2::InferencingResult, v2::Error>;
 
+    /// Performs inferencing as [`LlmEngine::infer`] does, returning the
+    /// generated text as the model generates it.
+    ///
+    /// By default, the text is returned all at once when inferencing
+    /// finishes.
+    async fn infer_stream(
+        &mut self,
+        model: v2::InferencingModel,
+        prompt: String,
+        params: v2::InferencingParams,
+    ) -> Result<InferencingStream, v2::Error> {
+        let result = self.infer(model, prompt, params).await?;
+        Ok(stream::iter([Ok(InferencingChunk {
+            text: result.text,
+            usage: Some(result.usage),
+        })])
+        .boxed())
+    }
+
     async fn generate_embeddings(
         &mut self,
         model: v2::EmbeddingModel,
@@ -133,6 +156,17 @@ pub trait LlmEngine: Send + Sync {
     }
 }
 
+/// The text generated by an inferencing request, as the model generates it.
+pub type InferencingStream = BoxStream<'static, Result<InferencingChunk, v2::Error>>;
+
+/// A piece of the text generated by an inferencing request.
+pub struct InferencingChunk {
+    /// The text generated since the previous chunk.
+    pub text: String,
+    /// Usage information about the request, which comes with the last chunk.
+    pub usage: Option<v2::InferencingUsage>,
+}
+
 /// A creator for an LLM engine.
 pub trait LlmEngineCreator: Send + Sync {
     fn create(&self) -> Arc<Mutex<dyn LlmEngine>>;
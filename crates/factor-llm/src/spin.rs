@@ -1,15 +1,18 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use futures::stream::StreamExt;
 use spin_factors::runtime_config::toml::GetTomlValue;
-use spin_llm_remote_http::RemoteHttpLlmEngine;
+use spin_llm_remote_http::{ApiType, RemoteHttpLlmEngine};
 use spin_world::async_trait;
 use spin_world::v1::llm::{self as v1};
 use spin_world::v2::llm::{self as v2};
 use tokio::sync::Mutex;
 use url::Url;
 
-use crate::{LlmEngine, LlmEngineCreator, RuntimeConfig};
+use crate::{InferencingChunk, InferencingStream, LlmEngine, LlmEngineCreator, RuntimeConfig};
 
 #[cfg(feature = "llm")]
 mod local {
@@ -46,14 +49,7 @@ pub fn default_engine_creator(
     state_dir: Option<PathBuf>,
 ) -> anyhow::Result<impl LlmEngineCreator + 'static> {
     #[cfg(feature = "llm")]
-    let engine = {
-        use anyhow::Context as _;
-        let models_dir_parent = match state_dir {
-            Some(ref dir) => dir.clone(),
-            None => std::env::current_dir().context("failed to get current working directory")?,
-        };
-        spin_llm_local::LocalLlmEngine::new(models_dir_parent.join("ai-models"))
-    };
+    let engine = spin_llm_local::LocalLlmEngine::new(default_models_dir(state_dir)?);
     #[cfg(not(feature = "llm"))]
     let engine = {
         let _ = state_dir;
@@ -63,6 +59,18 @@ pub fn default_engine_creator(
     Ok(move || engine.clone())
 }
 
+/// The directory local models are loaded from, if the runtime config doesn't
+/// set one.
+#[cfg(feature = "llm")]
+fn default_models_dir(state_dir: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    use anyhow::Context as _;
+    let models_dir_parent = match state_dir {
+        Some(dir) => dir,
+        None => std::env::current_dir().context("failed to get current working directory")?,
+    };
+    Ok(models_dir_parent.join("ai-models"))
+}
+
 #[async_trait]
 impl LlmEngine for RemoteHttpLlmEngine {
     async fn infer(
@@ -75,6 +83,24 @@ impl LlmEngine for RemoteHttpLlmEngine {
         self.infer(model, prompt, params).await
     }
 
+    async fn infer_stream(
+        &mut self,
+        model: v2::InferencingModel,
+        prompt: String,
+        params: v2::InferencingParams,
+    ) -> Result<InferencingStream, v2::Error> {
+        spin_telemetry::monotonic_counter!(spin.llm_infer = 1, model_name = model);
+        let deltas = self.infer_stream(model, prompt, params).await?;
+        Ok(deltas
+            .map(|delta| {
+                delta.map(|delta| InferencingChunk {
+                    text: delta.text,
+                    usage: delta.usage,
+                })
+            })
+            .boxed())
+    }
+
     async fn generate_embeddings(
         &mut self,
         model: v2::EmbeddingModel,
@@ -84,7 +110,10 @@ impl LlmEngine for RemoteHttpLlmEngine {
     }
 
     fn summary(&self) -> Option<String> {
-        Some(format!("model at {}", self.url()))
+        match self.api_type() {
+            ApiType::Default => Some(format!("model at {}", self.url())),
+            ApiType::OpenAi => Some(format!("OpenAI-compatible model at {}", self.url())),
+        }
     }
 }
 
@@ -105,7 +134,7 @@ pub fn runtime_config_from_toml(
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum LlmCompute {
-    Spin,
+    Spin(SpinCompute),
     RemoteHttp(RemoteHttpCompute),
 }
 
@@ -113,25 +142,58 @@ impl LlmCompute {
     fn into_engine(self, state_dir: Option<PathBuf>) -> anyhow::Result<Arc<Mutex<dyn LlmEngine>>> {
         let engine: Arc<Mutex<dyn LlmEngine>> = match self {
             #[cfg(not(feature = "llm"))]
-            LlmCompute::Spin => {
+            LlmCompute::Spin(_) => {
                 let _ = state_dir;
                 Arc::new(Mutex::new(noop::NoopLlmEngine))
             }
             #[cfg(feature = "llm")]
-            LlmCompute::Spin => default_engine_creator(state_dir)?.create(),
-            LlmCompute::RemoteHttp(config) => Arc::new(Mutex::new(RemoteHttpLlmEngine::new(
-                config.url,
-                config.auth_token,
-            ))),
+            LlmCompute::Spin(config) => {
+                let models_dir = match config.models_dir {
+                    Some(dir) => dir,
+                    None => default_models_dir(state_dir)?,
+                };
+                let gguf_options = spin_llm_local::GgufOptions {
+                    context_length: config.context_length,
+                    gpu_layers: config.gpu_layers,
+                };
+                Arc::new(Mutex::new(
+                    spin_llm_local::LocalLlmEngine::new(models_dir).with_gguf_options(gguf_options),
+                ))
+            }
+            LlmCompute::RemoteHttp(config) => Arc::new(Mutex::new(
+                RemoteHttpLlmEngine::new(config.url, config.auth_token)
+                    .with_api_type(config.api_type)
+                    .with_models(config.models),
+            )),
         };
         Ok(engine)
     }
 }
 
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "llm"), allow(dead_code))]
+pub struct SpinCompute {
+    /// The directory models are loaded from. Defaults to `ai-models` in the
+    /// state directory.
+    models_dir: Option<PathBuf>,
+    /// The context length of GGUF models, in tokens.
+    context_length: Option<NonZeroU32>,
+    /// The number of layers of GGUF models offloaded to the GPU.
+    gpu_layers: Option<u32>,
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct RemoteHttpCompute {
     url: Url,
     auth_token: String,
+    /// The API served at `url`.
+    #[serde(default)]
+    api_type: ApiType,
+    /// Maps the names of models used by apps to the names the API knows them
+    /// by, e.g. `"llama2-chat" = "gpt-4o-mini"`.
+    #[serde(default)]
+    models: HashMap<String, String>,
 }
 
 /// A noop engine used when the local engine feature is disabled.
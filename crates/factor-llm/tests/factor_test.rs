@@ -1,9 +1,11 @@
 use std::collections::HashSet;
 use std::sync::Arc;
 
+use spin_core::wasmtime::component::Resource;
 use spin_factor_llm::{LlmEngine, LlmFactor};
 use spin_factors::{anyhow, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::llm::streaming::{self, HostInferencingStream};
 use spin_world::v1::llm::{self as v1};
 use spin_world::v2::llm::{self as v2, Host};
 use tokio::sync::Mutex;
@@ -70,6 +72,27 @@ async fn llm_works() -> anyhow::Result<()> {
         .llm
         .infer("llama2-chat".into(), "some prompt".into(), None)
         .await?;
+
+    let stream = streaming::Host::infer_stream(
+        &mut state.llm,
+        "llama2-chat".into(),
+        "some prompt".into(),
+        None,
+    )
+    .await?;
+    let borrow = || Resource::new_borrow(stream.rep());
+    assert!(state.llm.usage(borrow()).await?.is_none());
+    assert_eq!(state.llm.next(borrow()).await?.as_deref(), Some("response"));
+    assert_eq!(state.llm.next(borrow()).await?, None);
+    assert_eq!(
+        state
+            .llm
+            .usage(borrow())
+            .await?
+            .map(|u| u.generated_token_count),
+        Some(1)
+    );
+    state.llm.drop(stream).await?;
     Ok(())
 }
 
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use anyhow::Context as _;
+use spin_factors::wasmtime::component::Resource;
+use spin_resource_table::Table;
+use spin_world::spin::locks::locks::{self, Error, Lock};
+use tracing::{instrument, Level};
+
+use crate::locks::{HeldLock, LockStore};
+
+pub struct InstanceState {
+    locks: LockStore,
+    /// A resource table of the locks acquired by the instance.
+    held: Table<HeldLock>,
+}
+
+impl InstanceState {
+    pub fn new(locks: LockStore) -> Self {
+        Self {
+            locks,
+            held: Table::new(256),
+        }
+    }
+
+    fn held_lock(&self, lock: &Resource<Lock>) -> Result<&HeldLock, Error> {
+        self.held
+            .get(lock.rep())
+            .ok_or_else(|| Error::Other("could not find lock for resource".into()))
+    }
+}
+
+impl locks::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl locks::HostLock for InstanceState {
+    #[instrument(name = "spin_locks.acquire", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn acquire(
+        &mut self,
+        name: String,
+        ttl_ms: u64,
+    ) -> Result<Option<Resource<Lock>>, Error> {
+        if name.is_empty() {
+            return Err(Error::InvalidName);
+        }
+        let Some(lock) = self
+            .locks
+            .acquire(&name, ttl(ttl_ms)?)
+            .await
+            .map_err(store_unavailable)?
+        else {
+            return Ok(None);
+        };
+        self.held
+            .push(lock)
+            .map_err(|()| Error::Other("too many locks held".into()))
+            .map(|rep| Some(Resource::new_own(rep)))
+    }
+
+    async fn name(&mut self, lock: Resource<Lock>) -> anyhow::Result<String> {
+        let lock = self.held.get(lock.rep()).context("invalid lock")?;
+        Ok(lock.name.clone())
+    }
+
+    #[instrument(name = "spin_locks.renew", skip(self, lock), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn renew(&mut self, lock: Resource<Lock>, ttl_ms: u64) -> Result<bool, Error> {
+        let ttl = ttl(ttl_ms)?;
+        let lock = self.held_lock(&lock)?;
+        self.locks.renew(lock, ttl).await.map_err(store_unavailable)
+    }
+
+    #[instrument(name = "spin_locks.release", skip(self, lock), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn release(&mut self, lock: Resource<Lock>) -> Result<bool, Error> {
+        let lock = self.held_lock(&lock)?;
+        self.locks.release(lock).await.map_err(store_unavailable)
+    }
+
+    async fn drop(&mut self, lock: Resource<Lock>) -> anyhow::Result<()> {
+        // The lock stays held until it is released or expires
+        self.held.remove(lock.rep());
+        Ok(())
+    }
+}
+
+fn ttl(ttl_ms: u64) -> Result<Duration, Error> {
+    if ttl_ms == 0 {
+        return Err(Error::InvalidTtl);
+    }
+    Ok(Duration::from_millis(ttl_ms))
+}
+
+fn store_unavailable(e: anyhow::Error) -> Error {
+    tracing::error!("Failed to use lock store: {e:?}");
+    Error::StoreUnavailable(format!("{e:#}"))
+}
@@ -0,0 +1,77 @@
+mod host;
+pub mod locks;
+pub mod runtime_config;
+
+use anyhow::{ensure, Context as _};
+use host::InstanceState;
+use locks::LockStore;
+use runtime_config::RuntimeConfig;
+use spin_factor_key_value::KeyValueFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+
+/// A factor that lets the replicas of an app coordinate singleton work with
+/// locks. Locks are kept in a key-value store.
+#[derive(Default)]
+pub struct LocksFactor {
+    _priv: (),
+}
+
+impl LocksFactor {
+    /// Create a new LocksFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for LocksFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::locks::locks::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let config = ctx.take_runtime_config().unwrap_or_default();
+        let store_manager = ctx
+            .app_state::<KeyValueFactor>()
+            .context("LocksFactor depends on KeyValueFactor")?
+            .store_manager();
+        ensure!(
+            store_manager.is_defined(&config.store),
+            "locks store {:?} is not a configured key-value store",
+            config.store
+        );
+        Ok(AppState {
+            locks: LockStore::new(store_manager, &config.store),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        Ok(InstanceState::new(ctx.app_state().locks.clone()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// The locks of an app.
+pub struct AppState {
+    locks: LockStore,
+}
+
+impl AppState {
+    /// Returns the store holding the app's locks.
+    pub fn locks(&self) -> &LockStore {
+        &self.locks
+    }
+}
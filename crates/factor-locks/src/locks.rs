@@ -0,0 +1,182 @@
+//! Locks kept in a key-value store, shared by the replicas of an app.
+//!
+//! Each lock is kept under the key `spin-locks/<name>`, and is held by
+//! whoever set it, as identified by a random token. How a lock is stored
+//! depends on the store's [`Store::acquire_lock`] implementation.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use spin_factor_key_value::{Store, StoreManager};
+use tokio::sync::OnceCell;
+
+const LOCK_PREFIX: &str = "spin-locks/";
+
+/// A lock which was acquired, by which it can be renewed or released.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeldLock {
+    pub name: String,
+    token: String,
+}
+
+/// The locks in a key-value store. The store is opened when first used.
+#[derive(Clone)]
+pub struct LockStore {
+    store_manager: Arc<dyn StoreManager>,
+    label: Arc<str>,
+    store: Arc<OnceCell<Arc<dyn Store>>>,
+}
+
+impl LockStore {
+    /// Creates the locks in the store with the given label.
+    pub fn new(store_manager: Arc<dyn StoreManager>, label: &str) -> Self {
+        Self {
+            store_manager,
+            label: label.into(),
+            store: Default::default(),
+        }
+    }
+
+    /// The label of the key-value store holding the locks.
+    pub fn store_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn store(&self) -> anyhow::Result<&dyn Store> {
+        let store = self
+            .store
+            .get_or_try_init(|| self.store_manager.get(&self.label))
+            .await
+            .with_context(|| format!("failed to open key-value store {:?}", self.label))?;
+        Ok(store.as_ref())
+    }
+
+    /// Acquires the named lock for the given time-to-live, which must be
+    /// non-zero. Returns `None` if the lock is held by someone else.
+    pub async fn acquire(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<HeldLock>> {
+        let token = uuid::Uuid::new_v4().to_string();
+        let acquired = self
+            .store()
+            .await?
+            .acquire_lock(&lock_key(name), &token, ttl)
+            .await
+            .with_context(|| format!("failed to acquire lock {name:?}"))?;
+        Ok(acquired.then(|| HeldLock {
+            name: name.to_owned(),
+            token,
+        }))
+    }
+
+    /// Extends a lock to expire after the given time-to-live. Returns false
+    /// if the lock is no longer held.
+    pub async fn renew(&self, lock: &HeldLock, ttl: Duration) -> anyhow::Result<bool> {
+        self.store()
+            .await?
+            .renew_lock(&lock_key(&lock.name), &lock.token, ttl)
+            .await
+            .with_context(|| format!("failed to renew lock {:?}", lock.name))
+    }
+
+    /// Releases a lock. Returns false if the lock was no longer held.
+    pub async fn release(&self, lock: &HeldLock) -> anyhow::Result<bool> {
+        self.store()
+            .await?
+            .release_lock(&lock_key(&lock.name), &lock.token)
+            .await
+            .with_context(|| format!("failed to release lock {:?}", lock.name))
+    }
+}
+
+fn lock_key(name: &str) -> String {
+    format!("{LOCK_PREFIX}{name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
+    use spin_key_value_memory::MemoryKeyValueStore;
+    use spin_key_value_spin::{SpinKeyValueRuntimeConfig, SpinKeyValueStore};
+
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn locks() -> LockStore {
+        let store_manager = MemoryKeyValueStore::new()
+            .make_store(Default::default())
+            .unwrap();
+        LockStore::new(Arc::new(store_manager), "default")
+    }
+
+    /// Locks in an in-memory SQLite store, which uses the default lock
+    /// implementation.
+    fn sqlite_locks() -> LockStore {
+        let store_manager = SpinKeyValueStore::new(None)
+            .make_store(SpinKeyValueRuntimeConfig::new(None))
+            .unwrap();
+        LockStore::new(Arc::new(store_manager), "default")
+    }
+
+    /// Races a number of acquirers for the named lock, returning how many
+    /// acquired it.
+    async fn contend(locks: &LockStore, name: &str, ttl: Duration) -> anyhow::Result<usize> {
+        let acquirers = (0..4).map(|_| {
+            let (locks, name) = (locks.clone(), name.to_owned());
+            tokio::spawn(async move { locks.acquire(&name, ttl).await })
+        });
+        let mut acquired = 0;
+        for acquirer in acquirers.collect::<Vec<_>>() {
+            if acquirer.await??.is_some() {
+                acquired += 1;
+            }
+        }
+        Ok(acquired)
+    }
+
+    #[tokio::test]
+    async fn held_locks_are_not_acquired_again() -> anyhow::Result<()> {
+        let locks = locks();
+        let lock = locks.acquire("cron", TTL).await?.context("lock is free")?;
+        assert!(locks.acquire("cron", TTL).await?.is_none());
+        assert!(locks.acquire("other", TTL).await?.is_some());
+
+        assert!(locks.release(&lock).await?);
+        assert!(!locks.release(&lock).await?);
+        assert!(locks.acquire("cron", TTL).await?.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_locks_are_lost() -> anyhow::Result<()> {
+        let locks = locks();
+        let lock = locks
+            .acquire("cron", Duration::from_millis(1))
+            .await?
+            .context("lock is free")?;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let other = locks.acquire("cron", TTL).await?.context("lock expired")?;
+        assert!(!locks.renew(&lock, TTL).await?);
+        assert!(!locks.release(&lock).await?);
+        assert!(locks.renew(&other, TTL).await?);
+        assert!(locks.acquire("cron", TTL).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn contended_locks_are_acquired_once() -> anyhow::Result<()> {
+        for locks in [locks(), sqlite_locks()] {
+            for round in 0..20 {
+                let name = format!("free-{round}");
+                assert_eq!(1, contend(&locks, &name, TTL).await?, "lock {name}");
+
+                // Taking over an expired lock is also contended
+                let name = format!("expired-{round}");
+                locks.acquire(&name, Duration::from_millis(1)).await?;
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                assert_eq!(1, contend(&locks, &name, TTL).await?, "lock {name}");
+            }
+        }
+        Ok(())
+    }
+}
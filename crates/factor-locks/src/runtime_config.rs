@@ -0,0 +1,27 @@
+pub mod spin;
+
+use serde::Deserialize;
+
+/// The label of the key-value store which holds locks if none is configured.
+pub const DEFAULT_STORE_LABEL: &str = "default";
+
+/// Runtime configuration for locks.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// The label of the key-value store which holds locks.
+    #[serde(default = "default_store_label")]
+    pub store: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            store: default_store_label(),
+        }
+    }
+}
+
+fn default_store_label() -> String {
+    DEFAULT_STORE_LABEL.to_owned()
+}
@@ -0,0 +1,73 @@
+use anyhow::{ensure, Context};
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// Get the runtime configuration for locks from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [locks]
+/// store = "locks"
+/// ```
+pub fn runtime_config_from_toml(
+    table: &impl GetTomlValue,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("locks") else {
+        return Ok(None);
+    };
+    let config: RuntimeConfig = value
+        .clone()
+        .try_into()
+        .context("failed to parse [locks] table")?;
+    ensure!(!config.store.is_empty(), "[locks] store must not be empty");
+    Ok(Some(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_config() -> anyhow::Result<()> {
+        let maybe_config = runtime_config_from_toml(&toml::toml! {
+            [some_other_config]
+            relevant = false
+        })?;
+        assert!(maybe_config.is_none(), "{maybe_config:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_store() -> anyhow::Result<()> {
+        let config = runtime_config_from_toml(&toml::toml! {
+            [locks]
+            store = "locks"
+        })?
+        .context("expected config, got None")?;
+        assert_eq!(config.store, "locks");
+
+        let config = runtime_config_from_toml(&toml::toml! {
+            [locks]
+        })?
+        .context("expected config, got None")?;
+        assert_eq!(config, RuntimeConfig::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        for table in [
+            toml::toml! {
+                [locks]
+                store = ""
+            },
+            toml::toml! {
+                [locks]
+                stor = "locks"
+            },
+        ] {
+            runtime_config_from_toml(&table).unwrap_err();
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use spin_telemetry::guest::{self, GuestMetricError, GuestMetrics};
+use spin_world::spin::observe::types::{self, Attribute, Error};
+use spin_world::spin::observe::{metrics, traces};
+
+pub struct InstanceState {
+    metrics: Arc<GuestMetrics>,
+    app_id: String,
+    component_id: String,
+}
+
+impl InstanceState {
+    pub fn new(metrics: Arc<GuestMetrics>, app_id: String, component_id: String) -> Self {
+        Self {
+            metrics,
+            app_id,
+            component_id,
+        }
+    }
+
+    /// The attributes of a measurement, which always include the app and
+    /// component which recorded it.
+    fn measurement_attributes(&self, attributes: Vec<Attribute>) -> Vec<(String, String)> {
+        attributes
+            .into_iter()
+            .map(|attr| (attr.key, attr.value))
+            .filter(|(key, _)| key != "app_id" && key != "component_id")
+            .chain([
+                ("app_id".to_owned(), self.app_id.clone()),
+                ("component_id".to_owned(), self.component_id.clone()),
+            ])
+            .collect()
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl metrics::Host for InstanceState {
+    async fn counter_add(
+        &mut self,
+        name: String,
+        value: u64,
+        attributes: Vec<Attribute>,
+    ) -> Result<(), Error> {
+        let attributes = self.measurement_attributes(attributes);
+        self.metrics
+            .counter_add(&name, value, attributes)
+            .map_err(to_wit_error)
+    }
+
+    async fn histogram_record(
+        &mut self,
+        name: String,
+        value: f64,
+        attributes: Vec<Attribute>,
+    ) -> Result<(), Error> {
+        let attributes = self.measurement_attributes(attributes);
+        self.metrics
+            .histogram_record(&name, value, attributes)
+            .map_err(to_wit_error)
+    }
+}
+
+impl traces::Host for InstanceState {
+    async fn add_event(&mut self, name: String, attributes: Vec<Attribute>) -> Result<()> {
+        let attributes = attributes
+            .into_iter()
+            .map(|attr| (attr.key, attr.value))
+            .collect();
+        guest::add_span_event(&name, attributes);
+        Ok(())
+    }
+
+    async fn set_attribute(&mut self, attribute: Attribute) -> Result<()> {
+        guest::set_span_attribute(attribute.key, attribute.value);
+        Ok(())
+    }
+}
+
+fn to_wit_error(err: GuestMetricError) -> Error {
+    match err {
+        GuestMetricError::InvalidName(msg) => Error::InvalidName(msg),
+        GuestMetricError::KindMismatch(msg) => Error::KindMismatch(msg),
+    }
+}
@@ -0,0 +1,65 @@
+mod host;
+
+use std::sync::Arc;
+
+use host::InstanceState;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_telemetry::guest::GuestMetrics;
+
+/// A factor that lets components record metrics and annotate the trace of
+/// their invocations, through the host's own telemetry.
+#[derive(Default)]
+pub struct ObserveFactor {
+    _priv: (),
+}
+
+impl ObserveFactor {
+    /// Create a new ObserveFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for ObserveFactor {
+    type RuntimeConfig = ();
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(
+            spin_world::spin::observe::metrics::add_to_linker::<_, FactorData<Self>>,
+        )?;
+        ctx.link_bindings(spin_world::spin::observe::traces::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        _ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(AppState {
+            metrics: Default::default(),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let app_component = ctx.app_component();
+        Ok(InstanceState::new(
+            ctx.app_state().metrics.clone(),
+            app_component.app.id().to_owned(),
+            app_component.id().to_owned(),
+        ))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// The metric instruments the components of an app have recorded to.
+pub struct AppState {
+    metrics: Arc<GuestMetrics>,
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_factor_outbound_networking::config::allowed_hosts::OutboundAllowedHosts;
+use spin_world::spin::amqp::publisher::{self, Connection};
+use spin_world::spin::amqp::types::{self, Error, Properties};
+use tracing::{instrument, Level};
+
+use crate::AppState;
+
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    app_state: AppState,
+    connections: spin_resource_table::Table<Arc<dyn AmqpConnection>>,
+}
+
+impl InstanceState {
+    pub fn new(allowed_hosts: OutboundAllowedHosts, app_state: AppState) -> Self {
+        Self {
+            allowed_hosts,
+            app_state,
+            connections: spin_resource_table::Table::new(1024),
+        }
+    }
+}
+
+#[async_trait]
+pub trait AmqpConnection: Send + Sync {
+    async fn publish(
+        &self,
+        exchange: String,
+        routing_key: String,
+        payload: Vec<u8>,
+        properties: Properties,
+    ) -> Result<(), Error>;
+}
+
+impl InstanceState {
+    async fn is_address_allowed(&self, address: &str) -> Result<bool> {
+        self.allowed_hosts.check_url(address, "amqp").await
+    }
+
+    fn get_conn(&self, connection: Resource<Connection>) -> Result<&dyn AmqpConnection, Error> {
+        self.connections
+            .get(connection.rep())
+            .ok_or(Error::Other(
+                "could not find connection for resource".into(),
+            ))
+            .map(|c| c.as_ref())
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl publisher::Host for InstanceState {}
+
+impl publisher::HostConnection for InstanceState {
+    #[instrument(name = "spin_outbound_amqp.open_connection", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn open(&mut self, address: String) -> Result<Resource<Connection>, Error> {
+        if !self
+            .is_address_allowed(&address)
+            .await
+            .map_err(other_error)?
+        {
+            return Err(Error::InvalidAddress);
+        }
+        let connection = self.app_state.get_or_create_connection(address).await?;
+        self.connections
+            .push(connection)
+            .map(Resource::new_own)
+            .map_err(|_| Error::TooManyConnections)
+    }
+
+    #[instrument(name = "spin_outbound_amqp.publish", skip(self, connection, payload, properties), err(level = Level::INFO),
+        fields(otel.kind = "producer", otel.name = format!("{} publish", exchange), messaging.operation = "publish",
+        messaging.system = "rabbitmq"))]
+    async fn publish(
+        &mut self,
+        connection: Resource<Connection>,
+        exchange: String,
+        routing_key: String,
+        payload: Vec<u8>,
+        properties: Properties,
+    ) -> Result<(), Error> {
+        self.get_conn(connection)?
+            .publish(exchange, routing_key, payload, properties)
+            .await
+    }
+
+    async fn drop(&mut self, connection: Resource<Connection>) -> anyhow::Result<()> {
+        self.connections.remove(connection.rep());
+        Ok(())
+    }
+}
+
+pub fn other_error(e: impl std::fmt::Display) -> Error {
+    Error::Other(e.to_string())
+}
@@ -0,0 +1,270 @@
+mod host;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use host::other_error;
+use host::InstanceState;
+use lapin::options::{BasicPublishOptions, ConfirmSelectOptions};
+use lapin::types::{AMQPValue, FieldTable, ShortString};
+use lapin::{BasicProperties, Channel, ConnectionProperties};
+use spin_core::async_trait;
+use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::amqp::types::{Error, Header, Properties};
+use tokio::sync::Mutex;
+
+pub use host::AmqpConnection;
+
+pub struct OutboundAmqpFactor {
+    create_connection: Arc<dyn ConnectionCreator>,
+}
+
+impl OutboundAmqpFactor {
+    pub fn new(create_connection: Arc<dyn ConnectionCreator>) -> Self {
+        Self { create_connection }
+    }
+}
+
+impl Factor for OutboundAmqpFactor {
+    type RuntimeConfig = ();
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::amqp::publisher::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        _ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(AppState {
+            create_connection: self.create_connection.clone(),
+            connections: Default::default(),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let allowed_hosts = ctx
+            .instance_builder::<OutboundNetworkingFactor>()?
+            .allowed_hosts();
+        Ok(InstanceState::new(allowed_hosts, ctx.app_state().clone()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// Connections shared by all instances of an app.
+///
+/// Opening an AMQP connection takes several round trips to the broker, so
+/// connections are kept for the lifetime of the app rather than being opened
+/// for each instance.
+#[derive(Clone)]
+pub struct AppState {
+    create_connection: Arc<dyn ConnectionCreator>,
+    connections: Arc<Mutex<HashMap<String, Arc<dyn AmqpConnection>>>>,
+}
+
+impl AppState {
+    /// Returns the connection to the given address, opening it if there is
+    /// not one already.
+    async fn get_or_create_connection(
+        &self,
+        address: String,
+    ) -> Result<Arc<dyn AmqpConnection>, Error> {
+        let mut connections = self.connections.lock().await;
+        if let Some(connection) = connections.get(&address) {
+            return Ok(connection.clone());
+        }
+        let connection = self.create_connection.create(address.clone()).await?;
+        connections.insert(address, connection.clone());
+        Ok(connection)
+    }
+}
+
+/// Converts message properties to their AMQP representation.
+pub fn properties_to_amqp(properties: Properties) -> BasicProperties {
+    let mut amqp = BasicProperties::default();
+    if let Some(content_type) = properties.content_type {
+        amqp = amqp.with_content_type(content_type.into());
+    }
+    if let Some(correlation_id) = properties.correlation_id {
+        amqp = amqp.with_correlation_id(correlation_id.into());
+    }
+    if let Some(reply_to) = properties.reply_to {
+        amqp = amqp.with_reply_to(reply_to.into());
+    }
+    if let Some(message_id) = properties.message_id {
+        amqp = amqp.with_message_id(message_id.into());
+    }
+    if !properties.headers.is_empty() {
+        let mut headers = FieldTable::default();
+        for header in properties.headers {
+            headers.insert(
+                header.key.into(),
+                AMQPValue::LongString(header.value.into()),
+            );
+        }
+        amqp = amqp.with_headers(headers);
+    }
+    amqp
+}
+
+/// Converts the properties of a message received from a broker.
+pub fn properties_from_amqp(properties: &BasicProperties) -> Properties {
+    let string = |s: &Option<ShortString>| s.as_ref().map(|s| s.as_str().to_owned());
+    let headers = properties
+        .headers()
+        .iter()
+        .flat_map(FieldTable::inner)
+        .filter_map(|(key, value)| {
+            Some(Header {
+                key: key.as_str().to_owned(),
+                value: header_value(value)?,
+            })
+        })
+        .collect();
+    Properties {
+        content_type: string(properties.content_type()),
+        correlation_id: string(properties.correlation_id()),
+        reply_to: string(properties.reply_to()),
+        message_id: string(properties.message_id()),
+        headers,
+    }
+}
+
+/// Returns the text of a string, number or boolean header value.
+fn header_value(value: &AMQPValue) -> Option<String> {
+    Some(match value {
+        AMQPValue::Boolean(b) => b.to_string(),
+        AMQPValue::ShortShortInt(i) => i.to_string(),
+        AMQPValue::ShortShortUInt(i) => i.to_string(),
+        AMQPValue::ShortInt(i) => i.to_string(),
+        AMQPValue::ShortUInt(i) => i.to_string(),
+        AMQPValue::LongInt(i) => i.to_string(),
+        AMQPValue::LongUInt(i) => i.to_string(),
+        AMQPValue::LongLongInt(i) => i.to_string(),
+        AMQPValue::Float(f) => f.to_string(),
+        AMQPValue::Double(f) => f.to_string(),
+        AMQPValue::ShortString(s) => s.as_str().to_owned(),
+        AMQPValue::LongString(s) => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+        _ => return None,
+    })
+}
+
+// This is a concrete implementation of the AMQP connection using lapin.
+pub struct NetworkedAmqpConnection {
+    address: String,
+    session: Mutex<Session>,
+}
+
+/// A connection to a broker, and a channel on it in which the broker
+/// confirms published messages.
+struct Session {
+    connection: lapin::Connection,
+    channel: Channel,
+}
+
+impl NetworkedAmqpConnection {
+    /// Create a [`ConnectionCreator`] that creates a [`NetworkedAmqpConnection`].
+    pub fn creator() -> Arc<dyn ConnectionCreator> {
+        Arc::new(NetworkedConnectionCreator)
+    }
+
+    /// Open a new [`NetworkedAmqpConnection`] to the given address.
+    pub async fn create(address: String) -> Result<Self, Error> {
+        let connection = connect(&address).await?;
+        let channel = open_confirm_channel(&connection).await?;
+        Ok(Self {
+            address,
+            session: Mutex::new(Session {
+                connection,
+                channel,
+            }),
+        })
+    }
+
+    /// Returns the session's channel, first reopening the connection if the
+    /// broker has closed it, or the channel if a failed operation has closed
+    /// it.
+    async fn channel(&self) -> Result<Channel, Error> {
+        let mut session = self.session.lock().await;
+        if !session.connection.status().connected() {
+            session.connection = connect(&self.address).await?;
+        }
+        if !session.channel.status().connected() {
+            session.channel = open_confirm_channel(&session.connection).await?;
+        }
+        Ok(session.channel.clone())
+    }
+}
+
+async fn connect(address: &str) -> Result<lapin::Connection, Error> {
+    lapin::Connection::connect(address, ConnectionProperties::default())
+        .await
+        .map_err(|e| {
+            tracing::error!("AMQP connection error: {e:?}");
+            Error::ConnectionFailed(e.to_string())
+        })
+}
+
+async fn open_confirm_channel(connection: &lapin::Connection) -> Result<Channel, Error> {
+    let channel = connection.create_channel().await.map_err(other_error)?;
+    channel
+        .confirm_select(ConfirmSelectOptions::default())
+        .await
+        .map_err(other_error)?;
+    Ok(channel)
+}
+
+struct NetworkedConnectionCreator;
+
+#[async_trait]
+impl ConnectionCreator for NetworkedConnectionCreator {
+    async fn create(&self, address: String) -> Result<Arc<dyn AmqpConnection>, Error> {
+        Ok(Arc::new(NetworkedAmqpConnection::create(address).await?))
+    }
+}
+
+#[async_trait]
+impl AmqpConnection for NetworkedAmqpConnection {
+    async fn publish(
+        &self,
+        exchange: String,
+        routing_key: String,
+        payload: Vec<u8>,
+        properties: Properties,
+    ) -> Result<(), Error> {
+        let confirmation = self
+            .channel()
+            .await?
+            .basic_publish(
+                &exchange,
+                &routing_key,
+                BasicPublishOptions::default(),
+                &payload,
+                properties_to_amqp(properties),
+            )
+            .await
+            .map_err(other_error)?
+            .await
+            .map_err(other_error)?;
+        if !confirmation.is_ack() {
+            return Err(Error::NotConfirmed);
+        }
+        Ok(())
+    }
+}
+
+/// A trait for creating AMQP connections.
+#[async_trait]
+pub trait ConnectionCreator: Send + Sync {
+    async fn create(&self, address: String) -> Result<Arc<dyn AmqpConnection>, Error>;
+}
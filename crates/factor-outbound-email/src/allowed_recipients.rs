@@ -0,0 +1,84 @@
+/// The addresses a component is allowed to send mail to, from its
+/// `allowed_email_recipients`.
+#[derive(Clone, Debug, Default)]
+pub struct AllowedRecipients {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Pattern {
+    /// `*`
+    Any,
+    /// `*@example.com`, in lower case
+    Domain(String),
+    /// `ops@example.com`, in lower case
+    Address(String),
+}
+
+impl AllowedRecipients {
+    /// Parses the patterns of a component's `allowed_email_recipients`.
+    pub fn parse(patterns: &[String]) -> anyhow::Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                if pattern == "*" {
+                    return Ok(Pattern::Any);
+                }
+                if let Some(domain) = pattern.strip_prefix("*@") {
+                    crate::message::validate_address(&format!("any@{domain}"))
+                        .map_err(|_| anyhow::anyhow!("{pattern:?} has an invalid domain"))?;
+                    return Ok(Pattern::Domain(domain.to_ascii_lowercase()));
+                }
+                crate::message::validate_address(pattern).map_err(|err| {
+                    anyhow::anyhow!(
+                        "{pattern:?} is not an email address, `*@` followed by a domain, or `*`: {err}"
+                    )
+                })?;
+                Ok(Pattern::Address(pattern.to_ascii_lowercase()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Whether mail may be sent to the address.
+    pub fn allows(&self, address: &str) -> bool {
+        let address = address.to_ascii_lowercase();
+        let domain = address.rsplit_once('@').map(|(_, domain)| domain);
+        self.patterns.iter().any(|pattern| match pattern {
+            Pattern::Any => true,
+            Pattern::Domain(allowed) => domain == Some(allowed.as_str()),
+            Pattern::Address(allowed) => *allowed == address,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(patterns: &[&str]) -> AllowedRecipients {
+        AllowedRecipients::parse(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+            .unwrap()
+    }
+
+    #[test]
+    fn recipients_are_matched() {
+        let recipients = allowed(&["Ops@Example.com", "*@customers.example.com"]);
+        assert!(recipients.allows("ops@example.com"));
+        assert!(recipients.allows("OPS@EXAMPLE.COM"));
+        assert!(recipients.allows("alice@customers.example.com"));
+        assert!(!recipients.allows("dev@example.com"));
+        assert!(!recipients.allows("alice@eu.customers.example.com"));
+        assert!(!recipients.allows("alice@customers.example.com.evil"));
+
+        assert!(!allowed(&[]).allows("ops@example.com"));
+        assert!(allowed(&["*"]).allows("anyone@anywhere.example"));
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        for pattern in ["ops", "*@", "*@*.example.com", "Ops <ops@example.com>"] {
+            AllowedRecipients::parse(&[pattern.to_owned()]).unwrap_err();
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use spin_world::spin::email::email;
+use spin_world::spin::email::types::{self, Error, Message};
+use tracing::{instrument, Level};
+
+use crate::allowed_recipients::AllowedRecipients;
+use crate::message::{self, validate_address};
+use crate::smtp::Mailer;
+
+pub struct InstanceState {
+    mailer: Option<Arc<Mailer>>,
+    allowed_recipients: Arc<AllowedRecipients>,
+}
+
+impl InstanceState {
+    pub fn new(mailer: Option<Arc<Mailer>>, allowed_recipients: Arc<AllowedRecipients>) -> Self {
+        Self {
+            mailer,
+            allowed_recipients,
+        }
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl email::Host for InstanceState {
+    #[instrument(name = "spin_email.send", skip_all, err(level = Level::INFO))]
+    async fn send(&mut self, message: Message) -> Result<(), Error> {
+        let Some(mailer) = &self.mailer else {
+            return Err(Error::NotConfigured);
+        };
+        let recipients = message
+            .to
+            .iter()
+            .chain(&message.cc)
+            .chain(&message.bcc)
+            .cloned()
+            .collect::<Vec<_>>();
+        if recipients.is_empty() {
+            return Err(Error::InvalidMessage(
+                "the message has no recipients".to_owned(),
+            ));
+        }
+        for address in recipients.iter().chain(&message.reply_to) {
+            validate_address(address).map_err(Error::InvalidMessage)?;
+        }
+        if let Some(recipient) = recipients
+            .iter()
+            .find(|recipient| !self.allowed_recipients.allows(recipient))
+        {
+            tracing::error!("Email recipient not allowed: {recipient}");
+            return Err(Error::RecipientNotAllowed(recipient.clone()));
+        }
+
+        let from = message
+            .from
+            .as_deref()
+            .or(mailer.default_from())
+            .ok_or_else(|| {
+                Error::InvalidMessage(
+                    "the message has no sender, and the host has no default sender".to_owned(),
+                )
+            })?
+            .to_owned();
+        validate_address(&from).map_err(Error::InvalidMessage)?;
+        let data = message::format(&message, &from).map_err(Error::InvalidMessage)?;
+        mailer
+            .send(&from, &recipients, &data)
+            .await
+            .map_err(|err| Error::SendFailed(format!("{err:#}")))
+    }
+}
@@ -0,0 +1,100 @@
+mod allowed_recipients;
+mod host;
+mod message;
+pub mod runtime_config;
+mod smtp;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use allowed_recipients::AllowedRecipients;
+use anyhow::Context as _;
+use host::InstanceState;
+use runtime_config::RuntimeConfig;
+use smtp::Mailer;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_locked_app::MetadataKey;
+
+pub const ALLOWED_RECIPIENTS_KEY: MetadataKey<Vec<String>> =
+    MetadataKey::new("allowed_email_recipients");
+
+/// A factor that lets components send email through the mail server in the
+/// runtime config, to the recipients their manifest allows.
+#[derive(Default)]
+pub struct OutboundEmailFactor {
+    _priv: (),
+}
+
+impl OutboundEmailFactor {
+    /// Create a new OutboundEmailFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for OutboundEmailFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::email::email::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let component_allowed_recipients = ctx
+            .app()
+            .components()
+            .map(|component| {
+                let patterns = component
+                    .get_metadata(ALLOWED_RECIPIENTS_KEY)?
+                    .unwrap_or_default();
+                let allowed = AllowedRecipients::parse(&patterns).with_context(|| {
+                    format!(
+                        "component {:?} has an invalid `allowed_email_recipients` entry",
+                        component.id()
+                    )
+                })?;
+                Ok((component.id().to_string(), Arc::new(allowed)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let mailer = ctx
+            .take_runtime_config()
+            .map(|config| Arc::new(Mailer::new(&config)));
+        Ok(AppState {
+            mailer,
+            component_allowed_recipients,
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let allowed_recipients = ctx
+            .app_state()
+            .component_allowed_recipients
+            .get(ctx.app_component().id())
+            .cloned()
+            .unwrap_or_default();
+        Ok(InstanceState::new(
+            ctx.app_state().mailer.clone(),
+            allowed_recipients,
+        ))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// The mail server and allowed recipients of an app.
+pub struct AppState {
+    /// The mail server, if one is configured.
+    mailer: Option<Arc<Mailer>>,
+    component_allowed_recipients: HashMap<String, Arc<AllowedRecipients>>,
+}
@@ -0,0 +1,329 @@
+//! Formatting messages as MIME documents to send over SMTP.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use spin_world::spin::email::types::{Attachment, Message};
+
+/// The longest line of base64-encoded content.
+const BASE64_LINE_LEN: usize = 76;
+
+/// The most bytes of text in one RFC 2047 encoded word, which keeps each
+/// word within its limit of 75 characters.
+const ENCODED_WORD_BYTES: usize = 45;
+
+/// Checks that an address is a plain address such as `ops@example.com`,
+/// which can be put in headers and SMTP commands as it is.
+pub fn validate_address(address: &str) -> Result<(), String> {
+    let Some((local, domain)) = address.rsplit_once('@') else {
+        return Err(format!("{address:?} is not an email address"));
+    };
+    let is_plain = |part: &str| {
+        !part.is_empty()
+            && !part.contains(|c: char| {
+                c.is_whitespace()
+                    || c.is_control()
+                    || matches!(
+                        c,
+                        '@' | '<' | '>' | '(' | ')' | '[' | ']' | ',' | ';' | ':' | '"' | '\\'
+                    )
+            })
+    };
+    if !is_plain(local) || !is_plain(domain) || domain.contains('*') {
+        return Err(format!(
+            "{address:?} is not a plain email address, such as `ops@example.com`"
+        ));
+    }
+    Ok(())
+}
+
+/// Formats the message as a MIME document from the given sender. The
+/// message's `Bcc` recipients are not listed in it.
+pub fn format(message: &Message, from: &str) -> Result<Vec<u8>, String> {
+    for attachment in &message.attachments {
+        if !is_content_type(&attachment.content_type) {
+            return Err(format!(
+                "attachment {:?} has an invalid content type {:?}",
+                attachment.filename, attachment.content_type
+            ));
+        }
+    }
+
+    let domain = from
+        .rsplit_once('@')
+        .map_or("localhost", |(_, domain)| domain);
+    let mut out = String::new();
+    header(&mut out, "Date", &chrono::Utc::now().to_rfc2822());
+    header(
+        &mut out,
+        "Message-ID",
+        &format!("<{}@{domain}>", uuid::Uuid::new_v4().simple()),
+    );
+    header(&mut out, "From", from);
+    if !message.to.is_empty() {
+        header(&mut out, "To", &message.to.join(", "));
+    }
+    if !message.cc.is_empty() {
+        header(&mut out, "Cc", &message.cc.join(", "));
+    }
+    if let Some(reply_to) = &message.reply_to {
+        header(&mut out, "Reply-To", reply_to);
+    }
+    header(&mut out, "Subject", &encode_text(&message.subject));
+    header(&mut out, "MIME-Version", "1.0");
+    write_part(&mut out, &body(message));
+    Ok(out.into_bytes())
+}
+
+/// A part of a MIME document.
+enum Part<'a> {
+    Content {
+        content_type: &'a str,
+        filename: Option<&'a str>,
+        content: &'a [u8],
+    },
+    Multipart {
+        subtype: &'static str,
+        parts: Vec<Part<'a>>,
+    },
+}
+
+/// The body of the message: its text, with any HTML alternative, followed
+/// by its attachments.
+fn body(message: &Message) -> Part<'_> {
+    let text = Part::Content {
+        content_type: "text/plain; charset=utf-8",
+        filename: None,
+        content: message.text_body.as_bytes(),
+    };
+    let text = match &message.html_body {
+        Some(html) => Part::Multipart {
+            subtype: "alternative",
+            parts: vec![
+                text,
+                Part::Content {
+                    content_type: "text/html; charset=utf-8",
+                    filename: None,
+                    content: html.as_bytes(),
+                },
+            ],
+        },
+        None => text,
+    };
+    if message.attachments.is_empty() {
+        return text;
+    }
+    let mut parts = vec![text];
+    parts.extend(message.attachments.iter().map(
+        |Attachment {
+             filename,
+             content_type,
+             content,
+         }| {
+            Part::Content {
+                content_type,
+                filename: Some(filename),
+                content,
+            }
+        },
+    ));
+    Part::Multipart {
+        subtype: "mixed",
+        parts,
+    }
+}
+
+/// Writes the headers and content of a part. All content is base64-encoded,
+/// so that no line of it is too long, begins with a dot or can be mistaken
+/// for a boundary.
+fn write_part(out: &mut String, part: &Part) {
+    match part {
+        Part::Content {
+            content_type,
+            filename,
+            content,
+        } => {
+            header(out, "Content-Type", content_type);
+            header(out, "Content-Transfer-Encoding", "base64");
+            if let Some(filename) = filename {
+                header(
+                    out,
+                    "Content-Disposition",
+                    &format!("attachment; filename=\"{}\"", encode_filename(filename)),
+                );
+            }
+            out.push_str("\r\n");
+            let encoded = STANDARD.encode(content);
+            for line in encoded.as_bytes().chunks(BASE64_LINE_LEN) {
+                // Base64 is ASCII
+                out.push_str(std::str::from_utf8(line).unwrap());
+                out.push_str("\r\n");
+            }
+        }
+        Part::Multipart { subtype, parts } => {
+            // `=` can't appear at the start of a base64 line, so the boundary
+            // can't appear in the content
+            let boundary = format!("=_{}", uuid::Uuid::new_v4().simple());
+            header(
+                out,
+                "Content-Type",
+                &format!("multipart/{subtype}; boundary=\"{boundary}\""),
+            );
+            out.push_str("\r\n");
+            for part in parts {
+                out.push_str(&format!("--{boundary}\r\n"));
+                write_part(out, part);
+            }
+            out.push_str(&format!("--{boundary}--\r\n"));
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, value: &str) {
+    out.push_str(name);
+    out.push_str(": ");
+    out.push_str(value);
+    out.push_str("\r\n");
+}
+
+/// Encodes header text which isn't short, printable ASCII as RFC 2047
+/// encoded words, folded onto separate lines.
+fn encode_text(text: &str) -> String {
+    if text.len() <= BASE64_LINE_LEN && text.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+        return text.to_owned();
+    }
+    encoded_words(text).join("\r\n ")
+}
+
+/// Encodes a filename to be quoted in a header, as RFC 2047 encoded words
+/// unless it is printable ASCII which needs no escaping.
+fn encode_filename(filename: &str) -> String {
+    if filename
+        .bytes()
+        .all(|b| (b' '..=b'~').contains(&b) && b != b'"' && b != b'\\')
+    {
+        return filename.to_owned();
+    }
+    encoded_words(filename).join(" ")
+}
+
+fn encoded_words(text: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        // Words may not split a character
+        let mut end = rest.len().min(ENCODED_WORD_BYTES);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (word, next) = rest.split_at(end);
+        words.push(format!("=?utf-8?b?{}?=", STANDARD.encode(word)));
+        rest = next;
+    }
+    words
+}
+
+/// Whether a content type is a plain `type/subtype`, with no parameters.
+fn is_content_type(content_type: &str) -> bool {
+    let is_token = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+    };
+    content_type
+        .split_once('/')
+        .is_some_and(|(kind, subtype)| is_token(kind) && is_token(subtype))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> Message {
+        Message {
+            from: None,
+            to: vec!["ops@example.com".into()],
+            cc: vec!["dev@example.com".into()],
+            bcc: vec!["audit@example.com".into()],
+            reply_to: None,
+            subject: "Deploy finished".into(),
+            text_body: "All good.".into(),
+            html_body: None,
+            attachments: vec![],
+        }
+    }
+
+    fn decode(content: &str) -> String {
+        let content = content.replace("\r\n", "");
+        String::from_utf8(STANDARD.decode(content).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn addresses_are_validated() {
+        validate_address("ops@example.com").unwrap();
+        validate_address("first.last+tag@mail.example.com").unwrap();
+        for address in [
+            "ops",
+            "@example.com",
+            "ops@",
+            "Ops <ops@example.com>",
+            "ops@example.com\r\nBcc: victim@example.com",
+            "ops@*.example.com",
+        ] {
+            validate_address(address).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn text_messages_are_formatted() {
+        let formatted = format(&message(), "noreply@example.com").unwrap();
+        let formatted = String::from_utf8(formatted).unwrap();
+        let (headers, content) = formatted.split_once("\r\n\r\n").unwrap();
+        assert!(headers.contains("From: noreply@example.com\r\n"));
+        assert!(headers.contains("To: ops@example.com\r\n"));
+        assert!(headers.contains("Cc: dev@example.com\r\n"));
+        assert!(headers.contains("Subject: Deploy finished\r\n"));
+        assert!(headers.contains("Message-ID: <"));
+        assert!(headers.contains("@example.com>\r\n"));
+        assert!(headers.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(!formatted.contains("audit@example.com"));
+        assert_eq!(decode(content), "All good.");
+    }
+
+    #[test]
+    fn html_and_attachments_are_multipart() {
+        let mut message = message();
+        message.subject = "Déploiement terminé".into();
+        message.html_body = Some("<p>All good.</p>".into());
+        message.attachments.push(Attachment {
+            filename: "rapport \"final\".csv".into(),
+            content_type: "text/csv".into(),
+            content: b"a,b\n1,2\n".to_vec(),
+        });
+        let formatted =
+            String::from_utf8(format(&message, "noreply@example.com").unwrap()).unwrap();
+        assert!(formatted.contains("Subject: =?utf-8?b?"));
+        assert!(formatted.contains("Content-Type: multipart/mixed; boundary=\"=_"));
+        assert!(formatted.contains("Content-Type: multipart/alternative; boundary=\"=_"));
+        assert!(formatted.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        assert!(formatted.contains("Content-Disposition: attachment; filename=\"=?utf-8?b?"));
+        assert!(formatted.contains(&STANDARD.encode("a,b\n1,2\n")));
+        assert!(formatted.ends_with("--\r\n"));
+
+        message.attachments[0].content_type = "text/csv\r\nBcc: victim@example.com".into();
+        format(&message, "noreply@example.com").unwrap_err();
+    }
+
+    #[test]
+    fn long_text_is_encoded_in_words() {
+        let subject = "é".repeat(100);
+        let encoded = encode_text(&subject);
+        let words: Vec<_> = encoded.split("\r\n ").collect();
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|word| word.len() <= 75));
+        let decoded: String = words
+            .iter()
+            .map(|word| decode(word.trim_start_matches("=?utf-8?b?").trim_end_matches("?=")))
+            .collect();
+        assert_eq!(decoded, subject);
+    }
+}
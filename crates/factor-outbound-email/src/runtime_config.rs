@@ -0,0 +1,55 @@
+pub mod spin;
+
+use serde::Deserialize;
+
+/// Runtime configuration for outbound email: the mail server which
+/// components send messages through.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// The host name of the mail server.
+    pub host: String,
+    /// The port of the mail server, if not the default for the TLS mode.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// How the connection to the mail server is secured.
+    #[serde(default)]
+    pub tls: TlsMode,
+    /// The user name to authenticate to the mail server with.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The password to authenticate to the mail server with.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// The sender of messages which don't name one.
+    #[serde(default)]
+    pub from: Option<String>,
+}
+
+impl RuntimeConfig {
+    /// The port of the mail server.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.tls {
+            TlsMode::StartTls => 587,
+            TlsMode::Tls => 465,
+            TlsMode::None => 25,
+        })
+    }
+}
+
+/// How the connection to a mail server is secured.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum TlsMode {
+    /// The connection is upgraded to TLS with `STARTTLS` before anything is
+    /// sent.
+    #[default]
+    #[serde(rename = "starttls")]
+    StartTls,
+    /// The connection is made over TLS.
+    #[serde(rename = "tls")]
+    Tls,
+    /// The connection is not secured. Only suitable for a mail server on the
+    /// local machine or network, such as one for testing.
+    #[serde(rename = "none")]
+    None,
+}
@@ -0,0 +1,118 @@
+use anyhow::{bail, ensure, Context};
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::{RuntimeConfig, TlsMode};
+
+/// Get the runtime configuration for outbound email from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [email]
+/// host = "smtp.example.com"
+/// port = 587
+/// tls = "starttls"
+/// username = "spin"
+/// password = "..."
+/// from = "noreply@example.com"
+/// ```
+pub fn runtime_config_from_toml(
+    table: &impl GetTomlValue,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("email") else {
+        return Ok(None);
+    };
+    let config: RuntimeConfig = value
+        .clone()
+        .try_into()
+        .context("failed to parse [email] table")?;
+    ensure!(!config.host.is_empty(), "[email] host must not be empty");
+    match (&config.username, &config.password) {
+        (Some(_), Some(_)) if config.tls == TlsMode::None => {
+            bail!("[email] credentials may not be sent without TLS")
+        }
+        (Some(_), None) | (None, Some(_)) => {
+            bail!("[email] username and password must be set together")
+        }
+        _ => {}
+    }
+    if let Some(from) = &config.from {
+        crate::message::validate_address(from)
+            .map_err(|err| anyhow::anyhow!("[email] from is invalid: {err}"))?;
+    }
+    Ok(Some(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_config() -> anyhow::Result<()> {
+        let maybe_config = runtime_config_from_toml(&toml::toml! {
+            [some_other_config]
+            relevant = false
+        })?;
+        assert!(maybe_config.is_none(), "{maybe_config:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_server() -> anyhow::Result<()> {
+        let config = runtime_config_from_toml(&toml::toml! {
+            [email]
+            host = "smtp.example.com"
+            username = "spin"
+            password = "secret"
+            from = "noreply@example.com"
+        })?
+        .context("expected config, got None")?;
+        assert_eq!(config.tls, TlsMode::StartTls);
+        assert_eq!(config.port(), 587);
+        assert_eq!(config.from.as_deref(), Some("noreply@example.com"));
+
+        let config = runtime_config_from_toml(&toml::toml! {
+            [email]
+            host = "localhost"
+            port = 1025
+            tls = "none"
+        })?
+        .context("expected config, got None")?;
+        assert_eq!(config.tls, TlsMode::None);
+        assert_eq!(config.port(), 1025);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        for table in [
+            toml::toml! {
+                [email]
+                host = ""
+            },
+            toml::toml! {
+                [email]
+                host = "smtp.example.com"
+                tls = "ssl"
+            },
+            toml::toml! {
+                [email]
+                host = "smtp.example.com"
+                username = "spin"
+            },
+            toml::toml! {
+                [email]
+                host = "localhost"
+                tls = "none"
+                username = "spin"
+                password = "secret"
+            },
+            toml::toml! {
+                [email]
+                host = "smtp.example.com"
+                from = "Spin <noreply@example.com>"
+            },
+        ] {
+            runtime_config_from_toml(&table).unwrap_err();
+        }
+    }
+}
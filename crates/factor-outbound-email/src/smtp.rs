@@ -0,0 +1,321 @@
+//! A minimal SMTP client, which sends each message over a new connection.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::runtime_config::{RuntimeConfig, TlsMode};
+
+/// How long sending a message may take, from connecting to the server to
+/// it accepting the message.
+const SEND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The name the client greets the server with.
+const CLIENT_NAME: &str = "localhost";
+
+/// Sends messages through a mail server.
+pub struct Mailer {
+    host: String,
+    port: u16,
+    tls: TlsMode,
+    credentials: Option<(String, String)>,
+    from: Option<String>,
+    tls_config: Arc<rustls::ClientConfig>,
+}
+
+impl Mailer {
+    pub fn new(config: &RuntimeConfig) -> Self {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let tls_config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Self {
+            host: config.host.clone(),
+            port: config.port(),
+            tls: config.tls,
+            credentials: config.username.clone().zip(config.password.clone()),
+            from: config.from.clone(),
+            tls_config: Arc::new(tls_config),
+        }
+    }
+
+    /// The sender of messages which don't name one.
+    pub fn default_from(&self) -> Option<&str> {
+        self.from.as_deref()
+    }
+
+    /// Sends a formatted message from the sender to the recipients.
+    pub async fn send(&self, from: &str, recipients: &[String], data: &[u8]) -> anyhow::Result<()> {
+        tokio::time::timeout(SEND_TIMEOUT, self.send_unbounded(from, recipients, data))
+            .await
+            .context("timed out sending the message")?
+    }
+
+    async fn send_unbounded(
+        &self,
+        from: &str,
+        recipients: &[String],
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .with_context(|| format!("failed to connect to {}:{}", self.host, self.port))?;
+        let stream: Box<dyn Stream> = match self.tls {
+            TlsMode::Tls => Box::new(self.tls_connect(tcp).await?),
+            TlsMode::StartTls | TlsMode::None => Box::new(tcp),
+        };
+        let mut conn = Connection::new(stream);
+        conn.expect(220, "greeting").await?;
+        let mut ehlo = conn
+            .command("EHLO", &format!("EHLO {CLIENT_NAME}"), 250)
+            .await?;
+
+        if self.tls == TlsMode::StartTls {
+            conn.command("STARTTLS", "STARTTLS", 220).await?;
+            let stream = self.tls_connect(conn.into_inner()).await?;
+            conn = Connection::new(Box::new(stream));
+            ehlo = conn
+                .command("EHLO", &format!("EHLO {CLIENT_NAME}"), 250)
+                .await?;
+        }
+
+        if let Some((username, password)) = &self.credentials {
+            let mechanisms = ehlo.auth_mechanisms();
+            if mechanisms.iter().any(|m| m == "PLAIN") {
+                let token = STANDARD.encode(format!("\0{username}\0{password}"));
+                conn.command("AUTH PLAIN", &format!("AUTH PLAIN {token}"), 235)
+                    .await?;
+            } else if mechanisms.iter().any(|m| m == "LOGIN") {
+                conn.command("AUTH LOGIN", "AUTH LOGIN", 334).await?;
+                conn.command("AUTH LOGIN", &STANDARD.encode(username), 334)
+                    .await?;
+                conn.command("AUTH LOGIN", &STANDARD.encode(password), 235)
+                    .await?;
+            } else {
+                bail!("the mail server supports neither PLAIN nor LOGIN authentication");
+            }
+        }
+
+        conn.command("MAIL FROM", &format!("MAIL FROM:<{from}>"), 250)
+            .await?;
+        for recipient in recipients {
+            conn.command("RCPT TO", &format!("RCPT TO:<{recipient}>"), 250)
+                .await?;
+        }
+        conn.command("DATA", "DATA", 354).await?;
+        conn.write(&dot_stuff(data)).await?;
+        conn.expect(250, "message").await?;
+        // The message has been accepted, whatever becomes of the connection
+        if let Err(err) = conn.command("QUIT", "QUIT", 221).await {
+            tracing::debug!("Failed to close mail server connection: {err:?}");
+        }
+        Ok(())
+    }
+
+    async fn tls_connect(
+        &self,
+        stream: impl AsyncRead + AsyncWrite + Unpin + Send,
+    ) -> anyhow::Result<impl AsyncRead + AsyncWrite + Unpin + Send> {
+        let server_name = rustls::pki_types::ServerName::try_from(self.host.clone())
+            .with_context(|| format!("invalid mail server host {:?}", self.host))?;
+        tokio_rustls::TlsConnector::from(self.tls_config.clone())
+            .connect(server_name, stream)
+            .await
+            .context("TLS handshake with the mail server failed")
+    }
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// A connection to a mail server.
+struct Connection {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+/// A reply from a mail server.
+struct Reply {
+    code: u16,
+    lines: Vec<String>,
+}
+
+impl Reply {
+    /// The authentication mechanisms advertised in a reply to `EHLO`.
+    fn auth_mechanisms(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter_map(|line| {
+                let (keyword, params) = line.split_once(' ')?;
+                keyword.eq_ignore_ascii_case("AUTH").then_some(params)
+            })
+            .flat_map(|params| params.split_whitespace())
+            .map(str::to_ascii_uppercase)
+            .collect()
+    }
+}
+
+impl Connection {
+    fn new(stream: Box<dyn Stream>) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> Box<dyn Stream> {
+        self.stream.into_inner()
+    }
+
+    /// Sends a command line, and expects a reply of the same class as the
+    /// given code. The command is named in errors by `name`, so that
+    /// credentials sent in it are not revealed.
+    async fn command(&mut self, name: &str, line: &str, code: u16) -> anyhow::Result<Reply> {
+        self.write(format!("{line}\r\n").as_bytes()).await?;
+        self.expect(code, name).await
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        let stream = self.stream.get_mut();
+        stream.write_all(bytes).await?;
+        stream.flush().await?;
+        Ok(())
+    }
+
+    async fn expect(&mut self, code: u16, name: &str) -> anyhow::Result<Reply> {
+        let reply = self.reply().await?;
+        if reply.code / 100 != code / 100 {
+            bail!(
+                "the mail server refused {name}: {} {}",
+                reply.code,
+                reply.lines.join(" ")
+            );
+        }
+        Ok(reply)
+    }
+
+    /// Reads a reply, which may span several lines.
+    async fn reply(&mut self) -> anyhow::Result<Reply> {
+        let mut lines = vec![];
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                bail!("the mail server closed the connection");
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            let Some(code) = line.get(..3).and_then(|code| code.parse::<u16>().ok()) else {
+                bail!("the mail server sent a malformed reply {line:?}");
+            };
+            // `250-...` is followed by more lines, and `250 ...` is the last
+            let more = line.as_bytes().get(3) == Some(&b'-');
+            lines.push(line.get(4..).unwrap_or_default().to_owned());
+            if !more {
+                return Ok(Reply { code, lines });
+            }
+        }
+    }
+}
+
+/// Escapes lines of message data which begin with a dot, and terminates the
+/// data with a line holding only a dot.
+fn dot_stuff(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 5);
+    let mut line_start = true;
+    for &byte in data {
+        if line_start && byte == b'.' {
+            out.push(b'.');
+        }
+        out.push(byte);
+        line_start = byte == b'\n';
+    }
+    if !out.is_empty() && !out.ends_with(b"\r\n") {
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b".\r\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn data_is_dot_stuffed() {
+        assert_eq!(
+            dot_stuff(b"Subject: hi\r\n\r\n.hidden\r\nlast"),
+            b"Subject: hi\r\n\r\n..hidden\r\nlast\r\n.\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn messages_are_sent() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            let mut stream = BufReader::new(stream);
+            let mut received = vec![];
+            stream.get_mut().write_all(b"220 test ESMTP\r\n").await?;
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await? == 0 {
+                    break;
+                }
+                let reply: &[u8] = match line.trim_end() {
+                    "EHLO localhost" => b"250-test\r\n250 AUTH LOGIN PLAIN\r\n",
+                    "DATA" => b"354 go ahead\r\n",
+                    "." => b"250 queued\r\n",
+                    "QUIT" => b"221 bye\r\n",
+                    _ if received.contains(&"DATA".to_owned()) => b"",
+                    _ => b"250 ok\r\n",
+                };
+                received.push(line.trim_end().to_owned());
+                stream.get_mut().write_all(reply).await?;
+            }
+            anyhow::Ok(received)
+        });
+
+        let mailer = Mailer {
+            credentials: Some(("spin".into(), "secret".into())),
+            ..Mailer::new(&RuntimeConfig {
+                host: "127.0.0.1".into(),
+                port: Some(port),
+                tls: TlsMode::None,
+                username: None,
+                password: None,
+                from: None,
+            })
+        };
+        mailer
+            .send(
+                "noreply@example.com",
+                &["ops@example.com".into(), "dev@example.com".into()],
+                b"Subject: hi\r\n\r\n.\r\n",
+            )
+            .await?;
+
+        let received = server.await??;
+        assert_eq!(
+            received,
+            [
+                "EHLO localhost",
+                &format!("AUTH PLAIN {}", STANDARD.encode("\0spin\0secret")),
+                "MAIL FROM:<noreply@example.com>",
+                "RCPT TO:<ops@example.com>",
+                "RCPT TO:<dev@example.com>",
+                "DATA",
+                "Subject: hi",
+                "",
+                "..",
+                ".",
+                "QUIT",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -18,6 +18,10 @@ pub trait OutboundHttpInterceptor: Send + Sync {
     /// If this method returns [`InterceptOutcome::Complete`], the inner result
     /// will be returned as the result of the request, bypassing the default
     /// handler. The `request` will also be dropped immediately.
+    ///
+    /// If this method returns an error holding an `ErrorCode`, such as
+    /// `ErrorCode::DestinationNotFound.into()`, that is the result of the
+    /// request. Other errors trap.
     async fn intercept(&self, request: InterceptRequest) -> HttpResult<InterceptOutcome>;
 }
 
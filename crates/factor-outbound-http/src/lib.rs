@@ -1,10 +1,11 @@
 pub mod intercept;
+mod pool;
 mod spin;
 mod wasi;
 pub mod wasi_2023_10_18;
 pub mod wasi_2023_11_10;
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Context;
 use http::{
@@ -12,9 +13,10 @@ use http::{
     HeaderValue, Uri,
 };
 use intercept::OutboundHttpInterceptor;
+use pool::HttpClients;
 use spin_factor_outbound_networking::{
-    config::{allowed_hosts::OutboundAllowedHosts, blocked_networks::BlockedNetworks},
-    ComponentTlsClientConfigs, OutboundNetworkingFactor,
+    config::allowed_hosts::OutboundAllowedHosts, ComponentTlsClientConfigs,
+    OutboundNetworkingFactor,
 };
 use spin_factors::{
     anyhow, ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors,
@@ -35,7 +37,7 @@ pub struct OutboundHttpFactor {
 
 impl Factor for OutboundHttpFactor {
     type RuntimeConfig = ();
-    type AppState = ();
+    type AppState = AppState;
     type InstanceBuilder = InstanceState;
 
     fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
@@ -46,9 +48,14 @@ impl Factor for OutboundHttpFactor {
 
     fn configure_app<T: RuntimeFactors>(
         &self,
-        _ctx: ConfigureAppContext<T, Self>,
+        ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
-        Ok(())
+        let blocked_networks = ctx
+            .app_state::<OutboundNetworkingFactor>()?
+            .blocked_networks();
+        Ok(AppState {
+            wasi_http_clients: HttpClients::new(blocked_networks),
+        })
     }
 
     fn prepare<T: RuntimeFactors>(
@@ -57,29 +64,37 @@ impl Factor for OutboundHttpFactor {
     ) -> anyhow::Result<Self::InstanceBuilder> {
         let outbound_networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
         let allowed_hosts = outbound_networking.allowed_hosts();
-        let blocked_networks = outbound_networking.blocked_networks();
         let component_tls_configs = outbound_networking.component_tls_configs();
         Ok(InstanceState {
             wasi_http_ctx: WasiHttpCtx::new(),
             allowed_hosts,
-            blocked_networks,
             component_tls_configs,
+            http_clients: ctx.app_state().wasi_http_clients.clone(),
             self_request_origin: None,
             request_interceptor: None,
             spin_http_client: None,
+            spin_http_tls_clients: HashMap::new(),
         })
     }
 }
 
+pub struct AppState {
+    // Connection-pooling clients for 'wasi:http/outgoing-handler', shared by
+    // all instances
+    wasi_http_clients: HttpClients,
+}
+
 pub struct InstanceState {
     wasi_http_ctx: WasiHttpCtx,
     allowed_hosts: OutboundAllowedHosts,
-    blocked_networks: BlockedNetworks,
     component_tls_configs: ComponentTlsClientConfigs,
+    http_clients: HttpClients,
     self_request_origin: Option<SelfRequestOrigin>,
     request_interceptor: Option<Arc<dyn OutboundHttpInterceptor>>,
     // Connection-pooling client for 'fermyon:spin/http' interface
     spin_http_client: Option<reqwest::Client>,
+    // Clients for hosts with their own TLS configuration, keyed by host
+    spin_http_tls_clients: HashMap<String, reqwest::Client>,
 }
 
 impl InstanceState {
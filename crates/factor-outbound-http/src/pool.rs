@@ -0,0 +1,330 @@
+//! Connection pooling for outbound wasi-http requests
+//!
+//! Connections are kept open after a response has been read, and reused by
+//! later requests to the same authority from any instance of the app.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use http::Uri;
+use http_body_util::combinators::BoxBody;
+use hyper_util::{
+    client::legacy::{
+        connect::{Connected, Connection},
+        Client,
+    },
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+};
+use spin_factor_outbound_networking::{config::blocked_networks::BlockedNetworks, TlsClientConfig};
+use tokio::{net::TcpStream, time::timeout};
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::wasi::dns_error;
+
+/// How long a connection may sit unused in the pool before it is closed.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+pub(crate) type HttpClient = Client<HttpConnector, BoxBody<Bytes, ErrorCode>>;
+
+tokio::task_local! {
+    /// The options for any connection made while sending a request.
+    ///
+    /// `Client::request` has no way to pass these to the connector, so they
+    /// are set for the duration of the call. They are ignored if a pooled
+    /// connection is used instead.
+    pub(crate) static CONNECT_OPTIONS: ConnectOptions;
+}
+
+/// Options for making a new connection.
+#[derive(Clone)]
+pub(crate) struct ConnectOptions {
+    pub connect_timeout: Duration,
+}
+
+/// The HTTP clients shared by all instances of an app.
+///
+/// Each client has its own pool of connections, keyed by authority. As
+/// components may use different TLS configurations for the same host, each
+/// TLS configuration has its own client.
+#[derive(Clone)]
+pub(crate) struct HttpClients {
+    http1: HttpClient,
+    /// For cleartext HTTP/2 to hosts with prior knowledge of support.
+    http2: HttpClient,
+    /// HTTPS clients keyed by the address of their TLS configuration.
+    https: Arc<Mutex<HashMap<usize, HttpClient>>>,
+    /// The cleartext connector, from which HTTPS connectors are made.
+    connector: HttpConnector,
+}
+
+impl HttpClients {
+    pub fn new(blocked_networks: BlockedNetworks) -> Self {
+        let connector = HttpConnector {
+            blocked_networks,
+            tls: None,
+        };
+        Self {
+            http1: build_client(connector.clone(), false),
+            http2: build_client(connector.clone(), true),
+            https: Arc::new(Mutex::new(HashMap::new())),
+            connector,
+        }
+    }
+
+    /// Returns the client to use for a request with the given TLS
+    /// configuration, or for a cleartext request if `tls` is `None`.
+    pub fn get(&self, tls: Option<&TlsClientConfig>, http2_prior_knowledge: bool) -> HttpClient {
+        let Some(tls) = tls else {
+            return if http2_prior_knowledge {
+                self.http2.clone()
+            } else {
+                self.http1.clone()
+            };
+        };
+        let key = Arc::as_ptr(&tls.inner()) as usize;
+        self.https
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| {
+                // The connector holds the configuration, so its address
+                // can't be reused by another while the client exists.
+                let mut config = (**tls).clone();
+                config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+                let connector = HttpConnector {
+                    tls: Some((tls.clone(), Arc::new(config))),
+                    ..self.connector.clone()
+                };
+                build_client(connector, false)
+            })
+            .clone()
+    }
+}
+
+fn build_client(connector: HttpConnector, http2_only: bool) -> HttpClient {
+    Client::builder(TokioExecutor::new())
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .pool_timer(TokioTimer::new())
+        .http2_only(http2_only)
+        .build(connector)
+}
+
+/// Makes new connections for an [`HttpClient`], applying the request's
+/// [`ConnectOptions`] and refusing to connect to blocked networks.
+#[derive(Clone)]
+pub(crate) struct HttpConnector {
+    blocked_networks: BlockedNetworks,
+    /// The configuration as given, and a copy which negotiates HTTP/2.
+    tls: Option<(TlsClientConfig, Arc<rustls::ClientConfig>)>,
+}
+
+impl tower_service::Service<Uri> for HttpConnector {
+    type Response = PooledStream;
+    type Error = ErrorCode;
+    type Future = Pin<Box<dyn Future<Output = Result<PooledStream, ErrorCode>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ErrorCode>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        // This is called while sending the request, but the returned future
+        // may be polled elsewhere, so the options are read now.
+        let options = CONNECT_OPTIONS.try_with(Clone::clone);
+        let blocked_networks = self.blocked_networks.clone();
+        let tls = self.tls.as_ref().map(|(_, config)| config.clone());
+        Box::pin(async move {
+            let options = options.map_err(|_| {
+                ErrorCode::InternalError(Some("connection options not set".to_owned()))
+            })?;
+            connect(uri, &blocked_networks, tls, options).await
+        })
+    }
+}
+
+async fn connect(
+    uri: Uri,
+    blocked_networks: &BlockedNetworks,
+    tls: Option<Arc<rustls::ClientConfig>>,
+    options: ConnectOptions,
+) -> Result<PooledStream, ErrorCode> {
+    let authority = uri.authority().ok_or(ErrorCode::HttpRequestUriInvalid)?;
+    let host = authority.host();
+    let authority_str = if authority.port().is_some() {
+        authority.to_string()
+    } else {
+        let port = if tls.is_some() { 443 } else { 80 };
+        format!("{authority}:{port}")
+    };
+
+    // Resolve the authority to IP addresses
+    let mut socket_addrs = tokio::net::lookup_host(&authority_str)
+        .await
+        .map_err(|_| dns_error("address not available".into(), 0))?
+        .collect::<Vec<_>>();
+
+    // Remove blocked IPs
+    let blocked_addrs = blocked_networks.remove_blocked(&mut socket_addrs);
+    if socket_addrs.is_empty() && !blocked_addrs.is_empty() {
+        tracing::error!(
+            "error.type" = "destination_ip_prohibited",
+            ?blocked_addrs,
+            "all destination IP(s) prohibited by runtime config"
+        );
+        return Err(ErrorCode::DestinationIpProhibited);
+    }
+
+    let tcp_stream = timeout(
+        options.connect_timeout,
+        TcpStream::connect(socket_addrs.as_slice()),
+    )
+    .await
+    .map_err(|_| ErrorCode::ConnectionTimeout)?
+    .map_err(|err| match err.kind() {
+        std::io::ErrorKind::AddrNotAvailable => dns_error("address not available".into(), 0),
+        _ => ErrorCode::ConnectionRefused,
+    })?;
+
+    let stream = match tls {
+        None => PooledStream::Tcp(TokioIo::new(tcp_stream)),
+        Some(tls_client_config) => {
+            tls_connect(host, tcp_stream, tls_client_config, options.connect_timeout).await?
+        }
+    };
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.outbound_http.connection_count = 1,
+        server_address = host,
+        protocol = stream.protocol()
+    );
+    Ok(stream)
+}
+
+#[cfg(any(target_arch = "riscv64", target_arch = "s390x"))]
+async fn tls_connect(
+    _host: &str,
+    _tcp_stream: TcpStream,
+    _tls_client_config: Arc<rustls::ClientConfig>,
+    _connect_timeout: Duration,
+) -> Result<PooledStream, ErrorCode> {
+    Err(ErrorCode::InternalError(Some(
+        "unsupported architecture for SSL".to_string(),
+    )))
+}
+
+#[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+async fn tls_connect(
+    host: &str,
+    tcp_stream: TcpStream,
+    tls_client_config: Arc<rustls::ClientConfig>,
+    connect_timeout: Duration,
+) -> Result<PooledStream, ErrorCode> {
+    use rustls::pki_types::ServerName;
+
+    let connector = tokio_rustls::TlsConnector::from(tls_client_config);
+    // IPv6 addresses are bracketed in URIs, but not in server names
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let domain = ServerName::try_from(host)
+        .map_err(|e| {
+            tracing::warn!("dns lookup error: {e:?}");
+            dns_error("invalid dns name".to_string(), 0)
+        })?
+        .to_owned();
+    let stream = timeout(connect_timeout, connector.connect(domain, tcp_stream))
+        .await
+        .map_err(|_| ErrorCode::ConnectionTimeout)?
+        .map_err(|e| {
+            tracing::warn!("tls protocol error: {e:?}");
+            ErrorCode::TlsProtocolError
+        })?;
+    Ok(PooledStream::Tls(Box::new(TokioIo::new(stream))))
+}
+
+/// A connection made by an [`HttpConnector`].
+pub(crate) enum PooledStream {
+    Tcp(TokioIo<TcpStream>),
+    #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+    Tls(Box<TokioIo<tokio_rustls::client::TlsStream<TcpStream>>>),
+}
+
+impl PooledStream {
+    fn is_http2(&self) -> bool {
+        match self {
+            PooledStream::Tcp(_) => false,
+            #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+            PooledStream::Tls(stream) => stream.inner().get_ref().1.alpn_protocol() == Some(b"h2"),
+        }
+    }
+
+    fn protocol(&self) -> &'static str {
+        match self {
+            PooledStream::Tcp(_) => "tcp",
+            #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+            PooledStream::Tls(_) if self.is_http2() => "tls+h2",
+            #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+            PooledStream::Tls(_) => "tls",
+        }
+    }
+}
+
+impl Connection for PooledStream {
+    fn connected(&self) -> Connected {
+        // The cleartext HTTP/2 client only speaks HTTP/2, so only TLS
+        // connections need to report what was negotiated.
+        if self.is_http2() {
+            Connected::new().negotiated_h2()
+        } else {
+            Connected::new()
+        }
+    }
+}
+
+impl hyper::rt::Read for PooledStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            PooledStream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+            PooledStream::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl hyper::rt::Write for PooledStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            PooledStream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+            PooledStream::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            PooledStream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+            PooledStream::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            PooledStream::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
+            PooledStream::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::{collections::hash_map::Entry, time::Instant};
+
 use http_body_util::BodyExt;
 use spin_world::v1::{
     http as spin_http,
@@ -90,9 +92,30 @@ impl spin_http::Host for crate::InstanceState {
 
         // Allow reuse of Client's internal connection pool for multiple requests
         // in a single component execution
-        let client = self.spin_http_client.get_or_insert_with(Default::default);
+        let host = req.url().host_str().unwrap_or_default();
+        let client = match self
+            .component_tls_configs
+            .get_configured_client_config(host)
+        {
+            None => self.spin_http_client.get_or_insert_with(Default::default),
+            Some(tls_client_config) => match self.spin_http_tls_clients.entry(host.to_owned()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(
+                    reqwest::Client::builder()
+                        .use_preconfigured_tls((*tls_client_config.inner()).clone())
+                        .build()
+                        .map_err(log_reqwest_error)?,
+                ),
+            },
+        };
 
-        let resp = client.execute(req).await.map_err(log_reqwest_error)?;
+        let started = Instant::now();
+        let resp = client.execute(req).await;
+        spin_telemetry::metrics::histogram!(
+            spin.outbound_http.request_duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+            server_address = req_url.host().unwrap_or_default()
+        );
+        let resp = resp.map_err(log_reqwest_error)?;
 
         tracing::trace!("Returning response from outbound request to {req_url}");
         span.record("http.response.status_code", resp.status().as_u16());
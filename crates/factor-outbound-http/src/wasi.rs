@@ -1,29 +1,26 @@
-use std::{error::Error, future::Future, pin::Pin, sync::Arc, time::Duration};
+use std::{error::Error, sync::Arc, time::Instant};
 
 use anyhow::Context;
-use bytes::Bytes;
 use http::{header::HOST, Request};
-use http_body_util::{combinators::BoxBody, BodyExt};
-use hyper_util::rt::TokioExecutor;
+use http_body_util::BodyExt;
 use spin_factor_outbound_networking::{
-    config::{allowed_hosts::OutboundAllowedHosts, blocked_networks::BlockedNetworks},
-    ComponentTlsClientConfigs, TlsClientConfig,
+    config::allowed_hosts::OutboundAllowedHosts, ComponentTlsClientConfigs, TlsClientConfig,
 };
 use spin_factors::{wasmtime::component::ResourceTable, RuntimeFactorsInstanceState};
-use tokio::{net::TcpStream, time::timeout};
+use tokio::time::timeout;
 use tracing::{field::Empty, instrument, Instrument};
 use wasmtime::component::HasData;
 use wasmtime_wasi::p2::{IoImpl, IoView};
 use wasmtime_wasi_http::{
     bindings::http::types::ErrorCode,
     body::HyperOutgoingBody,
-    io::TokioIo,
     types::{HostFutureIncomingResponse, IncomingResponse},
     WasiHttpCtx, WasiHttpImpl, WasiHttpView,
 };
 
 use crate::{
     intercept::{InterceptOutcome, OutboundHttpInterceptor},
+    pool::{ConnectOptions, HttpClients, CONNECT_OPTIONS},
     wasi_2023_10_18, wasi_2023_11_10, InstanceState, OutboundHttpFactor, SelfRequestOrigin,
 };
 
@@ -112,7 +109,7 @@ impl WasiHttpView for WasiHttpImplInner<'_> {
                     self.state.component_tls_configs.clone(),
                     self.state.request_interceptor.clone(),
                     self.state.self_request_origin.clone(),
-                    self.state.blocked_networks.clone(),
+                    self.state.http_clients.clone(),
                 )
                 .in_current_span(),
             ),
@@ -127,7 +124,7 @@ async fn send_request_impl(
     component_tls_configs: ComponentTlsClientConfigs,
     request_interceptor: Option<Arc<dyn OutboundHttpInterceptor>>,
     self_request_origin: Option<SelfRequestOrigin>,
-    blocked_networks: BlockedNetworks,
+    http_clients: HttpClients,
 ) -> anyhow::Result<Result<IncomingResponse, ErrorCode>> {
     // wasmtime-wasi-http fills in scheme and authority for relative URLs
     // (e.g. https://:443/<path>), which makes them hard to reason about.
@@ -190,11 +187,11 @@ async fn send_request_impl(
 
     if let Some(interceptor) = request_interceptor {
         let intercept_request = std::mem::take(&mut request).into();
-        match interceptor.intercept(intercept_request).await? {
-            InterceptOutcome::Continue(req) => {
+        match interceptor.intercept(intercept_request).await {
+            Ok(InterceptOutcome::Continue(req)) => {
                 request = req.into_hyper_request();
             }
-            InterceptOutcome::Complete(resp) => {
+            Ok(InterceptOutcome::Complete(resp)) => {
                 let resp = IncomingResponse {
                     resp,
                     worker: None,
@@ -202,6 +199,8 @@ async fn send_request_impl(
                 };
                 return Ok(Ok(resp));
             }
+            // Error codes are returned to the guest; anything else traps
+            Err(err) => return Ok(Err(err.downcast()?)),
         }
     }
 
@@ -211,14 +210,15 @@ async fn send_request_impl(
         span.record("server.port", port.as_u16());
     }
 
-    Ok(send_request_handler(request, config, tls_client_config, blocked_networks).await)
+    Ok(send_request_handler(request, config, tls_client_config, http_clients).await)
 }
 
-/// This is a fork of wasmtime_wasi_http::default_send_request_handler function
-/// forked from bytecodealliance/wasmtime commit-sha 29a76b68200fcfa69c8fb18ce6c850754279a05b
-/// This fork provides the ability to configure client cert auth for mTLS
+/// Sends a request over a pooled connection.
+///
+/// Based on wasmtime_wasi_http::default_send_request_handler, with the
+/// ability to configure client cert auth for mTLS.
 async fn send_request_handler(
-    mut request: http::Request<HyperOutgoingBody>,
+    request: http::Request<HyperOutgoingBody>,
     wasmtime_wasi_http::types::OutgoingRequestConfig {
         use_tls,
         connect_timeout,
@@ -226,209 +226,75 @@ async fn send_request_handler(
         between_bytes_timeout,
     }: wasmtime_wasi_http::types::OutgoingRequestConfig,
     tls_client_config: TlsClientConfig,
-    blocked_networks: BlockedNetworks,
+    http_clients: HttpClients,
 ) -> Result<wasmtime_wasi_http::types::IncomingResponse, ErrorCode> {
-    let authority_str = if let Some(authority) = request.uri().authority() {
-        if authority.port().is_some() {
-            authority.to_string()
-        } else {
-            let port = if use_tls { 443 } else { 80 };
-            format!("{authority}:{port}")
-        }
-    } else {
+    let Some(authority) = request.uri().authority() else {
         return Err(ErrorCode::HttpRequestUriInvalid);
     };
 
-    // Resolve the authority to IP addresses
-    let mut socket_addrs = tokio::net::lookup_host(&authority_str)
-        .await
-        .map_err(|_| dns_error("address not available".into(), 0))?
-        .collect::<Vec<_>>();
-
-    // Remove blocked IPs
-    let blocked_addrs = blocked_networks.remove_blocked(&mut socket_addrs);
-    if socket_addrs.is_empty() && !blocked_addrs.is_empty() {
-        tracing::error!(
-            "error.type" = "destination_ip_prohibited",
-            ?blocked_addrs,
-            "all destination IP(s) prohibited by runtime config"
-        );
-        return Err(ErrorCode::DestinationIpProhibited);
-    }
-
-    let tcp_stream = timeout(connect_timeout, TcpStream::connect(socket_addrs.as_slice()))
-        .await
-        .map_err(|_| ErrorCode::ConnectionTimeout)?
-        .map_err(|err| match err.kind() {
-            std::io::ErrorKind::AddrNotAvailable => dns_error("address not available".into(), 0),
-            _ => ErrorCode::ConnectionRefused,
-        })?;
-
-    let (mut sender, worker, is_http2) = if use_tls {
-        #[cfg(any(target_arch = "riscv64", target_arch = "s390x"))]
-        {
-            return Err(ErrorCode::InternalError(Some(
-                "unsupported architecture for SSL".to_string(),
-            )));
-        }
-
-        #[cfg(not(any(target_arch = "riscv64", target_arch = "s390x")))]
-        {
-            use rustls::pki_types::ServerName;
-
-            let mut tls_client_config = (*tls_client_config).clone();
-            tls_client_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
-
-            let connector = tokio_rustls::TlsConnector::from(Arc::new(tls_client_config));
-            let mut parts = authority_str.split(':');
-            let host = parts.next().unwrap_or(&authority_str);
-            let domain = ServerName::try_from(host)
-                .map_err(|e| {
-                    tracing::warn!("dns lookup error: {e:?}");
-                    dns_error("invalid dns name".to_string(), 0)
-                })?
-                .to_owned();
-            let stream = connector.connect(domain, tcp_stream).await.map_err(|e| {
-                tracing::warn!("tls protocol error: {e:?}");
-                ErrorCode::TlsProtocolError
-            })?;
-
-            let is_http2 = stream.get_ref().1.alpn_protocol() == Some(b"h2");
-
-            let stream = TokioIo::new(stream);
-
-            let (sender, conn) = new_sender_and_conn(stream, is_http2, connect_timeout).await?;
-
-            let worker = wasmtime_wasi::runtime::spawn(async move {
-                match conn.await {
-                    Ok(()) => {}
-                    // TODO: shouldn't throw away this error and ideally should
-                    // surface somewhere.
-                    Err(e) => tracing::warn!("dropping error {e}"),
-                }
-            });
-
-            (sender, worker, is_http2)
-        }
-    } else {
-        let tcp_stream = TokioIo::new(tcp_stream);
-
-        let is_http2 = std::env::var_os("SPIN_OUTBOUND_H2C_PRIOR_KNOWLEDGE").is_some_and(|v| {
-            request
-                .uri()
-                .authority()
-                .is_some_and(|authority| authority.as_str() == v)
-        });
-
-        let (sender, conn) = new_sender_and_conn(tcp_stream, is_http2, connect_timeout).await?;
-
-        let worker = wasmtime_wasi::runtime::spawn(async move {
-            match conn.await {
-                Ok(()) => {}
-                // TODO: same as above, shouldn't throw this error away.
-                Err(e) => tracing::warn!("dropping error {e}"),
-            }
-        });
-
-        (sender, worker, is_http2)
-    };
-
-    if !is_http2 {
-        // at this point, the request contains the scheme and the authority, but
-        // the http packet should only include those if addressing a proxy, so
-        // remove them here, since SendRequest::send_request does not do it for us
-        *request.uri_mut() = http::Uri::builder()
-            .path_and_query(
-                request
-                    .uri()
-                    .path_and_query()
-                    .map(|p| p.as_str())
-                    .unwrap_or("/"),
-            )
-            .build()
-            .expect("comes from valid request");
-    }
-
-    let resp = timeout(first_byte_timeout, sender.send_request(request))
-        .await
+    let http2_prior_knowledge = !use_tls
+        && std::env::var_os("SPIN_OUTBOUND_H2C_PRIOR_KNOWLEDGE")
+            .is_some_and(|v| authority.as_str() == v);
+    let client = http_clients.get(use_tls.then_some(&tls_client_config), http2_prior_knowledge);
+
+    let server_address = authority.host().to_owned();
+    spin_telemetry::metrics::monotonic_counter!(
+        spin.outbound_http.request_count = 1,
+        server_address = server_address
+    );
+
+    let connect_options = ConnectOptions { connect_timeout };
+    let started = Instant::now();
+    let resp = CONNECT_OPTIONS
+        .scope(
+            connect_options,
+            timeout(first_byte_timeout, client.request(request)),
+        )
+        .await;
+    // The time to the response head, as the body is streamed to the guest
+    spin_telemetry::metrics::histogram!(
+        spin.outbound_http.request_duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+        server_address = server_address
+    );
+    let resp = resp
         .map_err(|_| ErrorCode::ConnectionReadTimeout)?
-        .map_err(hyper_request_error)?
-        .map(|body| body.map_err(hyper_request_error).boxed());
+        .map_err(client_request_error)?
+        .map(|body| body.map_err(|err| hyper_request_error(&err)).boxed());
 
     tracing::Span::current().record("http.response.status_code", resp.status().as_u16());
 
     Ok(wasmtime_wasi_http::types::IncomingResponse {
         resp,
-        worker: Some(worker),
+        // The connection is driven by the client, and outlives the response
+        // if it is returned to the pool.
+        worker: None,
         between_bytes_timeout,
     })
 }
 
-async fn new_sender_and_conn<T: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static>(
-    stream: T,
-    is_http2: bool,
-    connect_timeout: Duration,
-) -> Result<(HttpSender, HttpConn<T>), ErrorCode> {
-    if is_http2 {
-        timeout(
-            connect_timeout,
-            hyper::client::conn::http2::handshake(TokioExecutor::default(), stream),
-        )
-        .await
-        .map_err(|_| ErrorCode::ConnectionTimeout)?
-        .map_err(hyper_request_error)
-        .map(|(sender, conn)| (HttpSender::Http2(sender), HttpConn::Http2(conn)))
-    } else {
-        timeout(
-            connect_timeout,
-            hyper::client::conn::http1::handshake(stream),
-        )
-        .await
-        .map_err(|_| ErrorCode::ConnectionTimeout)?
-        .map_err(hyper_request_error)
-        .map(|(sender, conn)| (HttpSender::Http1(sender), HttpConn::Http1(conn)))
-    }
-}
-
-enum HttpSender {
-    Http1(hyper::client::conn::http1::SendRequest<BoxBody<Bytes, ErrorCode>>),
-    Http2(hyper::client::conn::http2::SendRequest<BoxBody<Bytes, ErrorCode>>),
-}
-
-#[allow(clippy::large_enum_variant)]
-enum HttpConn<T: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static> {
-    Http1(hyper::client::conn::http1::Connection<T, BoxBody<Bytes, ErrorCode>>),
-    Http2(hyper::client::conn::http2::Connection<T, BoxBody<Bytes, ErrorCode>, TokioExecutor>),
-}
-
-impl<T: hyper::rt::Read + hyper::rt::Write + Unpin + Send> Future for HttpConn<T> {
-    type Output = Result<(), hyper::Error>;
-
-    fn poll(
-        self: Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Self::Output> {
-        match self.get_mut() {
-            HttpConn::Http1(conn) => Pin::new(conn).poll(cx),
-            HttpConn::Http2(conn) => Pin::new(conn).poll(cx),
+/// Translate a client error to a wasi-http `ErrorCode` in the context of a request.
+fn client_request_error(err: hyper_util::client::legacy::Error) -> ErrorCode {
+    // The connector's errors, and errors from the request body, are
+    // `ErrorCode`s somewhere in the chain of sources.
+    let mut source = err.source();
+    while let Some(cause) = source {
+        if let Some(err) = cause.downcast_ref::<ErrorCode>() {
+            return err.clone();
         }
+        source = cause.source();
     }
-}
 
-impl HttpSender {
-    async fn send_request(
-        &mut self,
-        request: http::Request<BoxBody<Bytes, ErrorCode>>,
-    ) -> Result<http::Response<hyper::body::Incoming>, hyper::Error> {
-        match self {
-            HttpSender::Http1(sender) => sender.send_request(request).await,
-            HttpSender::Http2(sender) => sender.send_request(request).await,
-        }
+    tracing::warn!("http client request error: {err:?}");
+
+    if err.is_connect() {
+        ErrorCode::ConnectionRefused
+    } else {
+        ErrorCode::HttpProtocolError
     }
 }
 
 /// Translate a [`hyper::Error`] to a wasi-http `ErrorCode` in the context of a request.
-fn hyper_request_error(err: hyper::Error) -> ErrorCode {
+fn hyper_request_error(err: &hyper::Error) -> ErrorCode {
     // If there's a source, we might be able to extract a wasi-http error from it.
     if let Some(cause) = err.source() {
         if let Some(err) = cause.downcast_ref::<ErrorCode>() {
@@ -441,7 +307,7 @@ fn hyper_request_error(err: hyper::Error) -> ErrorCode {
     ErrorCode::HttpProtocolError
 }
 
-fn dns_error(rcode: String, info_code: u16) -> ErrorCode {
+pub(crate) fn dns_error(rcode: String, info_code: u16) -> ErrorCode {
     ErrorCode::DnsError(wasmtime_wasi_http::bindings::http::types::DnsErrorPayload {
         rcode: Some(rcode),
         info_code: Some(info_code),
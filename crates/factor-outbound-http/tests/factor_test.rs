@@ -1,15 +1,31 @@
-use std::time::Duration;
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
 
 use anyhow::bail;
-use http::{Request, Uri};
-use spin_factor_outbound_http::{OutboundHttpFactor, SelfRequestOrigin};
+use bytes::Bytes;
+use http::{Request, Response, Uri};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Body, Frame};
+use hyper_util::rt::TokioIo;
+use spin_factor_outbound_http::{
+    intercept::{InterceptOutcome, InterceptRequest, OutboundHttpInterceptor},
+    OutboundHttpFactor, SelfRequestOrigin,
+};
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
 use spin_factor_variables::VariablesFactor;
 use spin_factors::{anyhow, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
 use wasmtime_wasi::p2::Pollable;
 use wasmtime_wasi_http::{
-    bindings::http::types::ErrorCode, types::OutgoingRequestConfig, WasiHttpView,
+    bindings::http::types::ErrorCode, types::OutgoingRequestConfig, HttpResult, WasiHttpView,
 };
 
 #[derive(RuntimeFactors)]
@@ -104,6 +120,117 @@ async fn disallowed_private_ips_fails() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn interceptor_error_codes_are_returned() -> anyhow::Result<()> {
+    struct NotFound;
+
+    #[spin_world::async_trait]
+    impl OutboundHttpInterceptor for NotFound {
+        async fn intercept(&self, _request: InterceptRequest) -> HttpResult<InterceptOutcome> {
+            Err(ErrorCode::DestinationNotFound.into())
+        }
+    }
+
+    let mut state = test_instance_state("http://*.spin.internal", true).await?;
+    state.http.set_request_interceptor(NotFound)?;
+    let mut wasi_http = OutboundHttpFactor::get_wasi_http_impl(&mut state).unwrap();
+
+    let req = Request::get("http://missing.spin.internal").body(Default::default())?;
+    let mut future_resp = wasi_http.send_request(req, test_request_config())?;
+    future_resp.ready().await;
+    match future_resp.unwrap_ready().unwrap() {
+        Ok(_) => bail!("expected Err, got Ok"),
+        Err(err) => assert!(matches!(err, ErrorCode::DestinationNotFound), "{err:?}"),
+    };
+    Ok(())
+}
+
+#[tokio::test]
+async fn connections_are_reused() -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            accepted.fetch_add(1, Ordering::SeqCst);
+            let service = hyper::service::service_fn(|_| async {
+                Ok::<_, Infallible>(Response::new(Full::new(Bytes::from_static(b"hello"))))
+            });
+            tokio::spawn(
+                hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service),
+            );
+        }
+    });
+
+    let mut state = test_instance_state("http://127.0.0.1:*", true).await?;
+    let mut wasi_http = OutboundHttpFactor::get_wasi_http_impl(&mut state).unwrap();
+    for _ in 0..3 {
+        let req = Request::get(format!("http://{addr}/")).body(Default::default())?;
+        let mut future_resp = wasi_http.send_request(req, test_request_config())?;
+        future_resp.ready().await;
+        let resp = future_resp.unwrap_ready().unwrap()?;
+        // The connection is only returned to the pool once the body is read
+        let body = resp.resp.into_body().collect().await?.to_bytes();
+        assert_eq!("hello", body);
+    }
+    assert_eq!(1, connections.load(Ordering::SeqCst));
+    Ok(())
+}
+
+#[tokio::test]
+async fn response_bodies_are_streamed() -> anyhow::Result<()> {
+    let (chunks, body) = tokio::sync::mpsc::channel(1);
+    let body = Arc::new(Mutex::new(Some(ChannelBody(body))));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let service = hyper::service::service_fn(move |_| {
+            let body = body.lock().unwrap().take().unwrap();
+            async move { Ok::<_, Infallible>(Response::new(body)) }
+        });
+        hyper::server::conn::http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await
+    });
+
+    let mut state = test_instance_state("http://127.0.0.1:*", true).await?;
+    let mut wasi_http = OutboundHttpFactor::get_wasi_http_impl(&mut state).unwrap();
+    let req = Request::get(format!("http://{addr}/")).body(Default::default())?;
+    let mut future_resp = wasi_http.send_request(req, test_request_config())?;
+
+    // Each chunk must reach the guest while the upstream body is still open
+    chunks.send(Bytes::from_static(b"first")).await?;
+    tokio::time::timeout(Duration::from_secs(5), future_resp.ready()).await?;
+    let mut body = future_resp.unwrap_ready().unwrap()?.resp.into_body();
+    let frame = tokio::time::timeout(Duration::from_secs(5), body.frame()).await?;
+    assert_eq!("first", frame.unwrap()?.into_data().unwrap());
+
+    chunks.send(Bytes::from_static(b"second")).await?;
+    drop(chunks);
+    assert_eq!("second", body.collect().await?.to_bytes());
+    Ok(())
+}
+
+/// A response body whose chunks are sent by the test.
+struct ChannelBody(tokio::sync::mpsc::Receiver<Bytes>);
+
+impl Body for ChannelBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        self.0
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| Ok(Frame::data(chunk))))
+    }
+}
+
 async fn test_instance_state(
     allowed_outbound_hosts: &str,
     allow_private_ips: bool,
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_factor_outbound_networking::config::allowed_hosts::OutboundAllowedHosts;
+use spin_world::spin::kafka::producer::{self, Producer};
+use spin_world::spin::kafka::types::{self, Error, Header};
+use tracing::{instrument, Level};
+
+use crate::AppState;
+
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    app_state: AppState,
+    producers: spin_resource_table::Table<Arc<dyn KafkaProducer>>,
+}
+
+impl InstanceState {
+    pub fn new(allowed_hosts: OutboundAllowedHosts, app_state: AppState) -> Self {
+        Self {
+            allowed_hosts,
+            app_state,
+            producers: spin_resource_table::Table::new(1024),
+        }
+    }
+}
+
+#[async_trait]
+pub trait KafkaProducer: Send + Sync {
+    async fn send(
+        &self,
+        topic: String,
+        key: Option<Vec<u8>>,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+    ) -> Result<(), Error>;
+}
+
+impl InstanceState {
+    /// Checks that each broker is a `<host>:<port>` address allowed by the
+    /// component's `kafka://` outbound hosts.
+    ///
+    /// Only the bootstrap brokers are checked; the cluster may direct the
+    /// producer to other brokers it advertises.
+    async fn are_brokers_allowed(&self, brokers: &[String]) -> Result<bool> {
+        if brokers.is_empty() {
+            return Ok(false);
+        }
+        for broker in brokers {
+            if broker.contains("://") {
+                return Ok(false);
+            }
+            let url = format!("kafka://{broker}");
+            if !self.allowed_hosts.check_url(&url, "kafka").await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn get_producer(&self, producer: Resource<Producer>) -> Result<&dyn KafkaProducer, Error> {
+        self.producers
+            .get(producer.rep())
+            .ok_or(Error::Other("could not find producer for resource".into()))
+            .map(|p| p.as_ref())
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl producer::Host for InstanceState {}
+
+impl producer::HostProducer for InstanceState {
+    #[instrument(name = "spin_outbound_kafka.open_producer", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn open(&mut self, brokers: Vec<String>) -> Result<Resource<Producer>, Error> {
+        if !self
+            .are_brokers_allowed(&brokers)
+            .await
+            .map_err(other_error)?
+        {
+            return Err(Error::InvalidAddress);
+        }
+        let producer = self.app_state.get_or_create_producer(brokers)?;
+        self.producers
+            .push(producer)
+            .map(Resource::new_own)
+            .map_err(|_| Error::TooManyProducers)
+    }
+
+    #[instrument(name = "spin_outbound_kafka.send", skip(self, producer, key, payload, headers), err(level = Level::INFO),
+        fields(otel.kind = "producer", otel.name = format!("{} publish", topic), messaging.operation = "publish",
+        messaging.system = "kafka"))]
+    async fn send(
+        &mut self,
+        producer: Resource<Producer>,
+        topic: String,
+        key: Option<Vec<u8>>,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+    ) -> Result<(), Error> {
+        self.get_producer(producer)?
+            .send(topic, key, payload, headers)
+            .await
+    }
+
+    async fn drop(&mut self, producer: Resource<Producer>) -> anyhow::Result<()> {
+        self.producers.remove(producer.rep());
+        Ok(())
+    }
+}
+
+pub fn other_error(e: impl std::fmt::Display) -> Error {
+    Error::Other(e.to_string())
+}
@@ -0,0 +1,164 @@
+mod host;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use host::other_error;
+use host::InstanceState;
+use rdkafka::message::{Header as KafkaHeader, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use spin_core::async_trait;
+use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::kafka::types::{Error, Header};
+
+pub use host::KafkaProducer;
+
+pub struct OutboundKafkaFactor {
+    create_producer: Arc<dyn ProducerCreator>,
+}
+
+impl OutboundKafkaFactor {
+    pub fn new(create_producer: Arc<dyn ProducerCreator>) -> Self {
+        Self { create_producer }
+    }
+}
+
+impl Factor for OutboundKafkaFactor {
+    type RuntimeConfig = ();
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::kafka::producer::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        _ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        Ok(AppState {
+            create_producer: self.create_producer.clone(),
+            producers: Default::default(),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let allowed_hosts = ctx
+            .instance_builder::<OutboundNetworkingFactor>()?
+            .allowed_hosts();
+        Ok(InstanceState::new(allowed_hosts, ctx.app_state().clone()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// Producers shared by all instances of an app.
+///
+/// A Kafka producer holds connections to every broker in the cluster, so
+/// producers are kept for the lifetime of the app rather than being created
+/// for each instance.
+#[derive(Clone)]
+pub struct AppState {
+    create_producer: Arc<dyn ProducerCreator>,
+    producers: Arc<Mutex<HashMap<Vec<String>, Arc<dyn KafkaProducer>>>>,
+}
+
+impl AppState {
+    /// Returns the producer for the given bootstrap brokers, creating it if
+    /// there is not one already.
+    fn get_or_create_producer(
+        &self,
+        brokers: Vec<String>,
+    ) -> Result<Arc<dyn KafkaProducer>, Error> {
+        let mut producers = self.producers.lock().unwrap();
+        if let Some(producer) = producers.get(&brokers) {
+            return Ok(producer.clone());
+        }
+        let producer = self.create_producer.create(brokers.clone())?;
+        producers.insert(brokers, producer.clone());
+        Ok(producer)
+    }
+}
+
+// This is a concrete implementation of the Kafka producer using rdkafka.
+pub struct NetworkedKafkaProducer {
+    inner: FutureProducer,
+}
+
+/// How long to wait for space in the producer's queue before failing a send.
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl NetworkedKafkaProducer {
+    /// Create a [`ProducerCreator`] that creates a [`NetworkedKafkaProducer`].
+    pub fn creator() -> Arc<dyn ProducerCreator> {
+        Arc::new(|brokers| Ok(Arc::new(NetworkedKafkaProducer::create(brokers)?) as _))
+    }
+
+    /// Create a new [`NetworkedKafkaProducer`] with the given bootstrap brokers.
+    pub fn create(brokers: Vec<String>) -> Result<Self, Error> {
+        let inner = rdkafka::ClientConfig::new()
+            .set("bootstrap.servers", brokers.join(","))
+            .create()
+            .map_err(|e| {
+                tracing::error!("Kafka producer creation error: {e:?}");
+                other_error(e)
+            })?;
+        Ok(Self { inner })
+    }
+}
+
+#[async_trait]
+impl KafkaProducer for NetworkedKafkaProducer {
+    async fn send(
+        &self,
+        topic: String,
+        key: Option<Vec<u8>>,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+    ) -> Result<(), Error> {
+        let headers = headers.iter().fold(
+            OwnedHeaders::new_with_capacity(headers.len()),
+            |headers, header| {
+                headers.insert(KafkaHeader {
+                    key: &header.key,
+                    value: header.value.as_deref(),
+                })
+            },
+        );
+        let mut record = FutureRecord::<Vec<u8>, Vec<u8>>::to(&topic)
+            .payload(&payload)
+            .headers(headers);
+        if let Some(key) = &key {
+            record = record.key(key);
+        }
+        // Resolves once the cluster has acknowledged the message, or the
+        // delivery has failed.
+        self.inner
+            .send(record, QUEUE_TIMEOUT)
+            .await
+            .map_err(|(e, _)| other_error(e))?;
+        Ok(())
+    }
+}
+
+/// A trait for creating Kafka producers.
+pub trait ProducerCreator: Send + Sync {
+    fn create(&self, brokers: Vec<String>) -> Result<Arc<dyn KafkaProducer>, Error>;
+}
+
+impl<F> ProducerCreator for F
+where
+    F: Fn(Vec<String>) -> Result<Arc<dyn KafkaProducer>, Error> + Send + Sync,
+{
+    fn create(&self, brokers: Vec<String>) -> Result<Arc<dyn KafkaProducer>, Error> {
+        self(brokers)
+    }
+}
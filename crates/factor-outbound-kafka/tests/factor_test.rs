@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use spin_core::async_trait;
+use spin_factor_outbound_kafka::{KafkaProducer, OutboundKafkaFactor, ProducerCreator};
+use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::kafka::producer::HostProducer;
+use spin_world::spin::kafka::types::{Error, Header};
+
+pub struct MockKafkaProducer {}
+
+#[async_trait]
+impl KafkaProducer for MockKafkaProducer {
+    async fn send(
+        &self,
+        _topic: String,
+        _key: Option<Vec<u8>>,
+        _payload: Vec<u8>,
+        _headers: Vec<Header>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ProducerCreator for MockKafkaProducer {
+    fn create(&self, _brokers: Vec<String>) -> Result<Arc<dyn KafkaProducer>, Error> {
+        Ok(Arc::new(MockKafkaProducer {}))
+    }
+}
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    variables: VariablesFactor,
+    networking: OutboundNetworkingFactor,
+    kafka: OutboundKafkaFactor,
+}
+
+fn factors() -> TestFactors {
+    TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        kafka: OutboundKafkaFactor::new(Arc::new(MockKafkaProducer {})),
+    }
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(factors()).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["kafka://kafka.test"]
+    })
+}
+
+#[tokio::test]
+async fn disallowed_host_fails() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(factors()).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env.build_instance_state().await?;
+
+    let res = state.kafka.open(vec!["kafka.test:9092".to_string()]).await;
+    let Err(err) = res else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::InvalidAddress));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn every_broker_must_be_allowed() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    for brokers in [
+        vec![],
+        vec!["kafka.test:9092".to_string(), "other.test:9092".to_string()],
+        vec!["kafka.test:9093".to_string()],
+        vec!["kafka://kafka.test:9092".to_string()],
+    ] {
+        let res = state.kafka.open(brokers.clone()).await;
+        assert!(
+            matches!(res, Err(Error::InvalidAddress)),
+            "expected brokers {brokers:?} to be refused"
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allowed_host_succeeds() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let res = state.kafka.open(vec!["kafka.test:9092".to_string()]).await;
+    let Ok(_) = res else {
+        bail!("expected Ok, got Err");
+    };
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn exercise_send() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let producer = state
+        .kafka
+        .open(vec!["kafka.test:9092".to_string()])
+        .await?;
+
+    state
+        .kafka
+        .send(
+            producer,
+            "orders".to_string(),
+            Some(b"key".to_vec()),
+            b"test message".to_vec(),
+            vec![Header {
+                key: "source".to_string(),
+                value: Some(b"test".to_vec()),
+            }],
+        )
+        .await?;
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use anyhow::Result;
 use spin_core::wasmtime::component::Resource;
+use spin_factor_outbound_networking::TlsClientConfig;
+use spin_world::spin::mysql::mysql::{self as v3};
 use spin_world::v1::mysql as v1;
 use spin_world::v2::mysql::{self as v2, Connection};
 use spin_world::v2::rdbms_types as v2_types;
@@ -11,10 +13,14 @@ use crate::client::Client;
 use crate::InstanceState;
 
 impl<C: Client> InstanceState<C> {
-    async fn open_connection(&mut self, address: &str) -> Result<Resource<Connection>, v2::Error> {
+    async fn open_connection<Conn: 'static>(
+        &mut self,
+        address: &str,
+    ) -> Result<Resource<Conn>, v2::Error> {
+        let tls = self.tls_client_config(address);
         self.connections
             .push(
-                C::build_client(address)
+                C::build_client(address, tls)
                     .await
                     .map_err(|e| v2::Error::ConnectionFailed(format!("{e:?}")))?,
             )
@@ -22,12 +28,37 @@ impl<C: Client> InstanceState<C> {
             .map(Resource::new_own)
     }
 
-    async fn get_client(&mut self, connection: Resource<Connection>) -> Result<&mut C, v2::Error> {
+    async fn get_client<Conn: 'static>(
+        &mut self,
+        connection: &Resource<Conn>,
+    ) -> Result<&mut C, v2::Error> {
         self.connections
             .get_mut(connection.rep())
             .ok_or_else(|| v2::Error::ConnectionFailed("no connection found".into()))
     }
 
+    /// Closes a connection, rolling back any transaction in progress so that
+    /// it isn't left to the server to notice the connection has gone.
+    async fn close_connection(&mut self, rep: u32) {
+        let Some(mut client) = self.connections.remove(rep) else {
+            return;
+        };
+        if self.transactions.remove(&rep) {
+            if let Err(e) = client.rollback().await {
+                tracing::warn!("failed to roll back MySQL transaction: {e:?}");
+            }
+        }
+    }
+
+    /// Returns the TLS configuration for the address's host, if one has been
+    /// configured for this component.
+    fn tls_client_config(&self, address: &str) -> Option<TlsClientConfig> {
+        let url = url::Url::parse(address).ok()?;
+        self.component_tls_configs
+            .get_configured_client_config(url.host_str()?)
+            .cloned()
+    }
+
     async fn is_address_allowed(&self, address: &str) -> Result<bool> {
         self.allowed_hosts.check_url(address, "mysql").await
     }
@@ -59,10 +90,11 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
         statement: String,
         params: Vec<ParameterValue>,
     ) -> Result<(), v2::Error> {
-        self.get_client(connection)
+        self.get_client(&connection)
             .await?
             .execute(statement, params)
-            .await
+            .await?;
+        Ok(())
     }
 
     #[instrument(name = "spin_outbound_mysql.query", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
@@ -72,14 +104,99 @@ impl<C: Client> v2::HostConnection for InstanceState<C> {
         statement: String,
         params: Vec<ParameterValue>,
     ) -> Result<v2_types::RowSet, v2::Error> {
-        self.get_client(connection)
+        self.get_client(&connection)
             .await?
             .query(statement, params)
             .await
     }
 
     async fn drop(&mut self, connection: Resource<Connection>) -> Result<()> {
-        self.connections.remove(connection.rep());
+        self.close_connection(connection.rep()).await;
+        Ok(())
+    }
+}
+
+impl<C: Client> v3::Host for InstanceState<C> {}
+
+impl<C: Client> v3::HostConnection for InstanceState<C> {
+    #[instrument(name = "spin_outbound_mysql.open", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", db.address = Empty, server.port = Empty, db.namespace = Empty))]
+    async fn open(&mut self, address: String) -> Result<Resource<v3::Connection>, v2::Error> {
+        spin_factor_outbound_networking::record_address_fields(&address);
+
+        if !self
+            .is_address_allowed(&address)
+            .await
+            .map_err(|e| v2::Error::Other(e.to_string()))?
+        {
+            return Err(v2::Error::ConnectionFailed(format!(
+                "address {address} is not permitted"
+            )));
+        }
+        self.open_connection(&address).await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.execute", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
+    async fn execute(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<u64, v2::Error> {
+        self.get_client(&connection)
+            .await?
+            .execute(statement, params)
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.query", skip(self, connection, params), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql", otel.name = statement))]
+    async fn query(
+        &mut self,
+        connection: Resource<v3::Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<v2_types::RowSet, v2::Error> {
+        self.get_client(&connection)
+            .await?
+            .query(statement, params)
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.begin_transaction", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql"))]
+    async fn begin_transaction(
+        &mut self,
+        connection: Resource<v3::Connection>,
+    ) -> Result<(), v2::Error> {
+        if self.transactions.contains(&connection.rep()) {
+            return Err(v2::Error::Other(
+                "a transaction is already in progress".into(),
+            ));
+        }
+        self.get_client(&connection)
+            .await?
+            .begin_transaction()
+            .await?;
+        self.transactions.insert(connection.rep());
+        Ok(())
+    }
+
+    #[instrument(name = "spin_outbound_mysql.commit", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql"))]
+    async fn commit(&mut self, connection: Resource<v3::Connection>) -> Result<(), v2::Error> {
+        if !self.transactions.remove(&connection.rep()) {
+            return Err(v2::Error::Other("no transaction is in progress".into()));
+        }
+        self.get_client(&connection).await?.commit().await
+    }
+
+    #[instrument(name = "spin_outbound_mysql.rollback", skip(self, connection), err(level = Level::INFO), fields(otel.kind = "client", db.system = "mysql"))]
+    async fn rollback(&mut self, connection: Resource<v3::Connection>) -> Result<(), v2::Error> {
+        if !self.transactions.remove(&connection.rep()) {
+            return Err(v2::Error::Other("no transaction is in progress".into()));
+        }
+        self.get_client(&connection).await?.rollback().await
+    }
+
+    async fn drop(&mut self, connection: Resource<v3::Connection>) -> Result<()> {
+        self.close_connection(connection.rep()).await;
         Ok(())
     }
 }
@@ -1,12 +1,16 @@
 pub mod client;
 mod host;
 
+use std::collections::HashSet;
+
 use client::Client;
 use mysql_async::Conn as MysqlClient;
 use spin_factor_outbound_networking::{
-    config::allowed_hosts::OutboundAllowedHosts, OutboundNetworkingFactor,
+    config::allowed_hosts::OutboundAllowedHosts, ComponentTlsClientConfigs,
+    OutboundNetworkingFactor,
 };
 use spin_factors::{Factor, FactorData, InitContext, RuntimeFactors, SelfInstanceBuilder};
+use spin_world::spin::mysql::mysql::{self as v3};
 use spin_world::v1::mysql as v1;
 use spin_world::v2::mysql::{self as v2};
 
@@ -22,6 +26,7 @@ impl<C: Send + Sync + Client + 'static> Factor for OutboundMysqlFactor<C> {
     fn init(&mut self, ctx: &mut impl InitContext<Self>) -> anyhow::Result<()> {
         ctx.link_bindings(v1::add_to_linker::<_, FactorData<Self>>)?;
         ctx.link_bindings(v2::add_to_linker::<_, FactorData<Self>>)?;
+        ctx.link_bindings(v3::add_to_linker::<_, FactorData<Self>>)?;
         Ok(())
     }
 
@@ -36,12 +41,14 @@ impl<C: Send + Sync + Client + 'static> Factor for OutboundMysqlFactor<C> {
         &self,
         mut ctx: spin_factors::PrepareContext<T, Self>,
     ) -> anyhow::Result<Self::InstanceBuilder> {
-        let allowed_hosts = ctx
-            .instance_builder::<OutboundNetworkingFactor>()?
-            .allowed_hosts();
+        let outbound_networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        let allowed_hosts = outbound_networking.allowed_hosts();
+        let component_tls_configs = outbound_networking.component_tls_configs();
         Ok(InstanceState {
             allowed_hosts,
+            component_tls_configs,
             connections: Default::default(),
+            transactions: Default::default(),
         })
     }
 }
@@ -62,7 +69,10 @@ impl<C> OutboundMysqlFactor<C> {
 
 pub struct InstanceState<C> {
     allowed_hosts: OutboundAllowedHosts,
+    component_tls_configs: ComponentTlsClientConfigs,
     connections: spin_resource_table::Table<C>,
+    /// The connections with a transaction in progress.
+    transactions: HashSet<u32>,
 }
 
 impl<C: Send + 'static> SelfInstanceBuilder for InstanceState<C> {}
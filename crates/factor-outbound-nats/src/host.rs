@@ -0,0 +1,130 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_factor_outbound_networking::config::allowed_hosts::OutboundAllowedHosts;
+use spin_world::spin::nats::messaging::{self, Connection};
+use spin_world::spin::nats::types::{self, Error, Header, Message};
+use tracing::{instrument, Level};
+
+use crate::AppState;
+
+pub struct InstanceState {
+    allowed_hosts: OutboundAllowedHosts,
+    app_state: AppState,
+    connections: spin_resource_table::Table<Arc<dyn NatsClient>>,
+}
+
+impl InstanceState {
+    pub fn new(allowed_hosts: OutboundAllowedHosts, app_state: AppState) -> Self {
+        Self {
+            allowed_hosts,
+            app_state,
+            connections: spin_resource_table::Table::new(1024),
+        }
+    }
+}
+
+#[async_trait]
+pub trait NatsClient: Send + Sync {
+    async fn publish(
+        &self,
+        subject: String,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+    ) -> Result<(), Error>;
+
+    async fn request(
+        &self,
+        subject: String,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+        timeout: Option<Duration>,
+    ) -> Result<Message, Error>;
+}
+
+impl InstanceState {
+    async fn is_address_allowed(&self, address: &str) -> Result<bool> {
+        self.allowed_hosts.check_url(address, "nats").await
+    }
+
+    fn get_conn(&self, connection: Resource<Connection>) -> Result<&dyn NatsClient, Error> {
+        self.connections
+            .get(connection.rep())
+            .ok_or(Error::Other(
+                "could not find connection for resource".into(),
+            ))
+            .map(|c| c.as_ref())
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl messaging::Host for InstanceState {}
+
+impl messaging::HostConnection for InstanceState {
+    #[instrument(name = "spin_outbound_nats.open_connection", skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn open(&mut self, name: String) -> Result<Resource<Connection>, Error> {
+        let config = self
+            .app_state
+            .connection_config(&name)
+            .ok_or(Error::NoSuchConnection)?;
+        if !self
+            .is_address_allowed(&config.url)
+            .await
+            .map_err(other_error)?
+        {
+            return Err(Error::AccessDenied);
+        }
+        let client = self.app_state.get_or_create_client(&name).await?;
+        self.connections
+            .push(client)
+            .map(Resource::new_own)
+            .map_err(|_| Error::Other("too many connections".into()))
+    }
+
+    #[instrument(name = "spin_outbound_nats.publish", skip(self, connection, payload, headers), err(level = Level::INFO),
+        fields(otel.kind = "producer", otel.name = format!("{} publish", subject), messaging.operation = "publish",
+        messaging.system = "nats"))]
+    async fn publish(
+        &mut self,
+        connection: Resource<Connection>,
+        subject: String,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+    ) -> Result<(), Error> {
+        self.get_conn(connection)?
+            .publish(subject, payload, headers)
+            .await
+    }
+
+    #[instrument(name = "spin_outbound_nats.request", skip(self, connection, payload, headers), err(level = Level::INFO),
+        fields(otel.kind = "client", otel.name = format!("{} request", subject), messaging.operation = "publish",
+        messaging.system = "nats"))]
+    async fn request(
+        &mut self,
+        connection: Resource<Connection>,
+        subject: String,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+        timeout_ms: Option<u32>,
+    ) -> Result<Message, Error> {
+        let timeout = timeout_ms.map(|ms| Duration::from_millis(ms.into()));
+        self.get_conn(connection)?
+            .request(subject, payload, headers, timeout)
+            .await
+    }
+
+    async fn drop(&mut self, connection: Resource<Connection>) -> anyhow::Result<()> {
+        self.connections.remove(connection.rep());
+        Ok(())
+    }
+}
+
+pub fn other_error(e: impl std::fmt::Display) -> Error {
+    Error::Other(e.to_string())
+}
@@ -0,0 +1,226 @@
+mod host;
+pub mod runtime_config;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use async_nats::{HeaderMap, RequestErrorKind};
+use host::other_error;
+use host::InstanceState;
+use runtime_config::{ConnectionConfig, RuntimeConfig, DEFAULT_CONNECTION_LABEL};
+use spin_core::async_trait;
+use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use spin_world::spin::nats::types::{Error, Header, Message};
+use tokio::sync::Mutex;
+
+pub use host::NatsClient;
+
+pub struct OutboundNatsFactor {
+    create_client: Arc<dyn ClientCreator>,
+}
+
+impl OutboundNatsFactor {
+    pub fn new(create_client: Arc<dyn ClientCreator>) -> Self {
+        Self { create_client }
+    }
+}
+
+impl Factor for OutboundNatsFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(spin_world::spin::nats::messaging::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let mut connections = ctx.take_runtime_config().unwrap_or_default().connections;
+        connections
+            .entry(DEFAULT_CONNECTION_LABEL.into())
+            .or_insert_with(ConnectionConfig::local);
+        Ok(AppState {
+            connections: Arc::new(connections),
+            create_client: self.create_client.clone(),
+            clients: Default::default(),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        mut ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let allowed_hosts = ctx
+            .instance_builder::<OutboundNetworkingFactor>()?
+            .allowed_hosts();
+        Ok(InstanceState::new(allowed_hosts, ctx.app_state().clone()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// The configured connections of an app, and the clients shared by all of
+/// its instances.
+#[derive(Clone)]
+pub struct AppState {
+    connections: Arc<HashMap<String, ConnectionConfig>>,
+    create_client: Arc<dyn ClientCreator>,
+    clients: Arc<Mutex<HashMap<String, Arc<dyn NatsClient>>>>,
+}
+
+impl AppState {
+    /// Returns the settings of the connection with the given label.
+    pub fn connection_config(&self, label: &str) -> Option<&ConnectionConfig> {
+        self.connections.get(label)
+    }
+
+    /// Returns the client for the connection with the given label, connecting
+    /// if there is not one already.
+    async fn get_or_create_client(&self, label: &str) -> Result<Arc<dyn NatsClient>, Error> {
+        let config = self
+            .connection_config(label)
+            .ok_or(Error::NoSuchConnection)?;
+        let mut clients = self.clients.lock().await;
+        if let Some(client) = clients.get(label) {
+            return Ok(client.clone());
+        }
+        let client = self.create_client.create(config).await?;
+        clients.insert(label.to_owned(), client.clone());
+        Ok(client)
+    }
+}
+
+/// Connects to the NATS server with the given settings.
+pub async fn connect(config: &ConnectionConfig) -> anyhow::Result<async_nats::Client> {
+    let mut options = async_nats::ConnectOptions::new()
+        .require_tls(config.require_tls)
+        .request_timeout(config.request_timeout.as_ref().map(|t| t.duration()));
+    if let Some(token) = &config.token {
+        options = options.token(token.clone());
+    }
+    if let (Some(user), Some(password)) = (&config.user, &config.password) {
+        options = options.user_and_password(user.clone(), password.clone());
+    }
+    options
+        .connect(config.url.as_str())
+        .await
+        .with_context(|| format!("failed to connect to NATS server at {}", config.url))
+}
+
+/// Converts a message received from a NATS server.
+pub fn message_from_nats(message: async_nats::Message) -> Message {
+    let headers = message
+        .headers
+        .iter()
+        .flat_map(HeaderMap::iter)
+        .flat_map(|(key, values)| {
+            values.iter().map(move |value| Header {
+                key: key.to_string(),
+                value: value.as_str().to_owned(),
+            })
+        })
+        .collect();
+    Message {
+        subject: message.subject.to_string(),
+        reply_to: message.reply.map(|reply| reply.to_string()),
+        headers,
+        payload: message.payload.into(),
+    }
+}
+
+fn headers_to_nats(headers: Vec<Header>) -> Option<HeaderMap> {
+    if headers.is_empty() {
+        return None;
+    }
+    let mut map = HeaderMap::new();
+    for header in headers {
+        map.append(header.key, header.value);
+    }
+    Some(map)
+}
+
+// This is a concrete implementation of the NATS client using async-nats.
+pub struct NetworkedNatsClient {
+    inner: async_nats::Client,
+}
+
+impl NetworkedNatsClient {
+    /// Create a [`ClientCreator`] that creates a [`NetworkedNatsClient`].
+    pub fn creator() -> Arc<dyn ClientCreator> {
+        Arc::new(NetworkedClientCreator)
+    }
+}
+
+struct NetworkedClientCreator;
+
+#[async_trait]
+impl ClientCreator for NetworkedClientCreator {
+    async fn create(&self, config: &ConnectionConfig) -> Result<Arc<dyn NatsClient>, Error> {
+        let inner = connect(config).await.map_err(|e| {
+            tracing::error!("NATS connection error: {e:?}");
+            Error::ConnectionFailed(format!("{e:#}"))
+        })?;
+        Ok(Arc::new(NetworkedNatsClient { inner }))
+    }
+}
+
+#[async_trait]
+impl NatsClient for NetworkedNatsClient {
+    async fn publish(
+        &self,
+        subject: String,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+    ) -> Result<(), Error> {
+        match headers_to_nats(headers) {
+            Some(headers) => {
+                self.inner
+                    .publish_with_headers(subject, headers, payload.into())
+                    .await
+            }
+            None => self.inner.publish(subject, payload.into()).await,
+        }
+        .map_err(other_error)
+    }
+
+    async fn request(
+        &self,
+        subject: String,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+        timeout: Option<Duration>,
+    ) -> Result<Message, Error> {
+        let mut request = async_nats::Request::new().payload(payload.into());
+        if let Some(headers) = headers_to_nats(headers) {
+            request = request.headers(headers);
+        }
+        if let Some(timeout) = timeout {
+            request = request.timeout(Some(timeout));
+        }
+        let reply = self
+            .inner
+            .send_request(subject, request)
+            .await
+            .map_err(|e| match e.kind() {
+                RequestErrorKind::TimedOut => Error::TimedOut,
+                RequestErrorKind::NoResponders => Error::NoResponders,
+                RequestErrorKind::Other => other_error(e),
+            })?;
+        Ok(message_from_nats(reply))
+    }
+}
+
+/// A trait for creating NATS clients.
+#[async_trait]
+pub trait ClientCreator: Send + Sync {
+    async fn create(&self, config: &ConnectionConfig) -> Result<Arc<dyn NatsClient>, Error>;
+}
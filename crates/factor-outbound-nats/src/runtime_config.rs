@@ -0,0 +1,51 @@
+pub mod spin;
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use spin_serde::HumanDuration;
+
+/// The label of the connection which is available without configuration.
+pub const DEFAULT_CONNECTION_LABEL: &str = "default";
+
+/// Runtime configuration for outbound NATS.
+#[derive(Debug, Default)]
+pub struct RuntimeConfig {
+    /// Connection settings, by label.
+    pub connections: HashMap<String, ConnectionConfig>,
+}
+
+/// Settings for connecting to a NATS server.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionConfig {
+    /// The server URL, e.g. `nats://nats.example.com:4222`.
+    pub url: String,
+    /// A token to authenticate with.
+    pub token: Option<String>,
+    /// A user to authenticate as, with `password`.
+    pub user: Option<String>,
+    /// The password of `user`.
+    pub password: Option<String>,
+    /// Whether to refuse to connect without TLS.
+    #[serde(default)]
+    pub require_tls: bool,
+    /// How long requests wait for a reply if they do not set their own
+    /// timeout.
+    pub request_timeout: Option<HumanDuration>,
+}
+
+impl ConnectionConfig {
+    /// The settings of the default connection when it is not configured: a
+    /// server on the local host, without authentication.
+    pub fn local() -> Self {
+        Self {
+            url: "nats://127.0.0.1:4222".into(),
+            token: None,
+            user: None,
+            password: None,
+            require_tls: false,
+            request_timeout: None,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use anyhow::{ensure, Context};
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::{ConnectionConfig, RuntimeConfig};
+
+/// Get the runtime configuration for outbound NATS from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [nats_connection.default]
+/// url = "nats://nats.example.com:4222"
+/// user = "app"
+/// password = "secret"
+/// require_tls = true
+/// request_timeout = "5s"
+/// ```
+pub fn runtime_config_from_toml(
+    table: &impl GetTomlValue,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("nats_connection") else {
+        return Ok(None);
+    };
+    let connections: HashMap<String, ConnectionConfig> = value
+        .clone()
+        .try_into()
+        .context("failed to parse [nats_connection] table")?;
+    for (label, config) in &connections {
+        validate(config).with_context(|| format!("invalid [nats_connection.{label}]"))?;
+    }
+    Ok(Some(RuntimeConfig { connections }))
+}
+
+fn validate(config: &ConnectionConfig) -> anyhow::Result<()> {
+    ensure!(
+        config.url.starts_with("nats://"),
+        "url {:?} must start with `nats://`; use `require_tls` to connect with TLS",
+        config.url
+    );
+    ensure!(
+        config.user.is_some() == config.password.is_some(),
+        "`user` and `password` must be set together"
+    );
+    ensure!(
+        config.token.is_none() || config.user.is_none(),
+        "only one of `token` and `user` may be set"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_no_config() -> anyhow::Result<()> {
+        let maybe_config = runtime_config_from_toml(&toml::toml! {
+            [some_other_config]
+            relevant = false
+        })?;
+        assert!(maybe_config.is_none(), "{maybe_config:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_connections() -> anyhow::Result<()> {
+        let config = runtime_config_from_toml(&toml::toml! {
+            [nats_connection.default]
+            url = "nats://nats.example.com"
+
+            [nats_connection.events]
+            url = "nats://events.example.com:4223"
+            user = "app"
+            password = "secret"
+            require_tls = true
+            request_timeout = "5s"
+        })?
+        .context("expected config, got None")?;
+
+        assert_eq!(config.connections["default"].url, "nats://nats.example.com");
+        assert!(!config.connections["default"].require_tls);
+
+        let events = &config.connections["events"];
+        assert_eq!(events.user.as_deref(), Some("app"));
+        assert_eq!(events.password.as_deref(), Some("secret"));
+        assert!(events.require_tls);
+        assert_eq!(
+            events.request_timeout.as_ref().map(|t| t.duration()),
+            Some(Duration::from_secs(5))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_connections() {
+        for table in [
+            toml::toml! {
+                [nats_connection.default]
+                url = "tls://nats.example.com"
+            },
+            toml::toml! {
+                [nats_connection.default]
+                url = "nats://nats.example.com"
+                user = "app"
+            },
+            toml::toml! {
+                [nats_connection.default]
+                url = "nats://nats.example.com"
+                token = "t0ken"
+                user = "app"
+                password = "secret"
+            },
+            toml::toml! {
+                [nats_connection.default]
+                url = "nats://nats.example.com"
+                request_timeout = "soon"
+            },
+            toml::toml! {
+                [nats_connection.default]
+                url = "nats://nats.example.com"
+                servers = ["nats://other.example.com"]
+            },
+        ] {
+            assert!(
+                runtime_config_from_toml(&table).is_err(),
+                "expected error for {table}"
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use spin_core::async_trait;
+use spin_core::wasmtime::component::Resource;
+use spin_factor_outbound_nats::runtime_config::{ConnectionConfig, RuntimeConfig};
+use spin_factor_outbound_nats::{ClientCreator, NatsClient, OutboundNatsFactor};
+use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::spin::nats::messaging::HostConnection;
+use spin_world::spin::nats::types::{Error, Header, Message};
+
+pub struct MockNatsClient {}
+
+#[async_trait]
+impl NatsClient for MockNatsClient {
+    async fn publish(
+        &self,
+        _subject: String,
+        _payload: Vec<u8>,
+        _headers: Vec<Header>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        subject: String,
+        payload: Vec<u8>,
+        headers: Vec<Header>,
+        _timeout: Option<Duration>,
+    ) -> Result<Message, Error> {
+        Ok(Message {
+            subject,
+            reply_to: None,
+            headers,
+            payload,
+        })
+    }
+}
+
+#[async_trait]
+impl ClientCreator for MockNatsClient {
+    async fn create(&self, _config: &ConnectionConfig) -> Result<Arc<dyn NatsClient>, Error> {
+        Ok(Arc::new(MockNatsClient {}))
+    }
+}
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    variables: VariablesFactor,
+    networking: OutboundNetworkingFactor,
+    nats: OutboundNatsFactor,
+}
+
+fn factors() -> TestFactors {
+    TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        nats: OutboundNatsFactor::new(Arc::new(MockNatsClient {})),
+    }
+}
+
+fn test_env() -> TestEnvironment<TestFactors> {
+    TestEnvironment::new(factors()).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["nats://127.0.0.1"]
+    })
+}
+
+#[tokio::test]
+async fn disallowed_host_fails() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(factors()).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env.build_instance_state().await?;
+
+    let res = state.nats.open("default".to_string()).await;
+    let Err(err) = res else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::AccessDenied));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_connection_fails() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let res = state.nats.open("events".to_string()).await;
+    let Err(err) = res else {
+        bail!("expected Err, got Ok");
+    };
+    assert!(matches!(err, Error::NoSuchConnection));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn configured_connection_is_checked_against_its_url() -> anyhow::Result<()> {
+    let env = TestEnvironment::new(factors())
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            allowed_outbound_hosts = ["nats://events.test:4223"]
+        })
+        .runtime_config(TestFactorsRuntimeConfig {
+            nats: Some(RuntimeConfig {
+                connections: [(
+                    "events".to_string(),
+                    ConnectionConfig {
+                        url: "nats://events.test:4223".to_string(),
+                        ..ConnectionConfig::local()
+                    },
+                )]
+                .into(),
+            }),
+            ..Default::default()
+        })?;
+    let mut state = env.build_instance_state().await?;
+
+    let res = state.nats.open("events".to_string()).await;
+    let Ok(_) = res else {
+        bail!("expected Ok, got Err");
+    };
+    // The default connection is still to the local server, which is not allowed
+    let res = state.nats.open("default".to_string()).await;
+    assert!(matches!(res, Err(Error::AccessDenied)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn exercise_publish_and_request() -> anyhow::Result<()> {
+    let mut state = test_env().build_instance_state().await?;
+
+    let connection = state.nats.open("default".to_string()).await?;
+    state
+        .nats
+        .publish(
+            Resource::new_borrow(connection.rep()),
+            "orders".to_string(),
+            b"test message".to_vec(),
+            vec![],
+        )
+        .await?;
+
+    let reply = state
+        .nats
+        .request(
+            connection,
+            "prices".to_string(),
+            b"widget".to_vec(),
+            vec![Header {
+                key: "source".to_string(),
+                value: "test".to_string(),
+            }],
+            Some(100),
+        )
+        .await?;
+    assert_eq!(reply.subject, "prices");
+    assert_eq!(reply.payload, b"widget");
+
+    Ok(())
+}
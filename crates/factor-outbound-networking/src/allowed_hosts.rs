@@ -30,6 +30,18 @@ pub fn allowed_outbound_hosts(component: &AppComponent) -> anyhow::Result<Vec<St
     Ok(allowed_hosts)
 }
 
+/// Returns whether any of the raw `allowed_outbound_hosts` values could allow
+/// TCP or UDP sockets.
+///
+/// Templated schemes aren't known until variables are resolved, so they are
+/// assumed to allow sockets.
+pub(crate) fn allows_sockets(hosts: &[String]) -> bool {
+    hosts.iter().any(|host| {
+        let scheme = host.split_once("://").map_or("", |(scheme, _)| scheme);
+        matches!(scheme, "*" | "tcp" | "udp") || scheme.contains("{{")
+    })
+}
+
 /// Validates that all service chaining of an app will be satisfied by the
 /// supplied subset of components.
 ///
@@ -15,11 +15,13 @@ use spin_outbound_networking_config::allowed_hosts::{DisallowedHostHandler, Outb
 use url::Url;
 
 use crate::{
-    allowed_hosts::allowed_outbound_hosts, runtime_config::RuntimeConfig, tls::TlsClientConfigs,
+    allowed_hosts::{allowed_outbound_hosts, allows_sockets},
+    runtime_config::RuntimeConfig,
+    tls::TlsClientConfigs,
 };
 pub use allowed_hosts::validate_service_chaining_for_components;
 
-pub use crate::tls::{ComponentTlsClientConfigs, TlsClientConfig};
+pub use crate::tls::{ComponentTlsClientConfigs, TlsClientConfig, TlsSettings};
 use config::allowed_hosts::AllowedHostsConfig;
 use config::blocked_networks::BlockedNetworks;
 pub use spin_outbound_networking_config as config;
@@ -90,6 +92,7 @@ impl Factor for OutboundNetworkingFactor {
             .get(ctx.app_component().id())
             .cloned()
             .context("missing component allowed hosts")?;
+        let allows_sockets = allows_sockets(&hosts);
         let resolver = ctx
             .instance_builder::<VariablesFactor>()?
             .expression_resolver()
@@ -119,6 +122,9 @@ impl Factor for OutboundNetworkingFactor {
 
         match ctx.instance_builder::<WasiFactor>() {
             Ok(wasi_builder) => {
+                // Components which may connect sockets may look up the
+                // addresses of the hosts they connect to
+                wasi_builder.allow_ip_name_lookup(allows_sockets);
                 // Update Wasi socket allowed ports
                 let allowed_hosts = allowed_hosts.clone();
                 wasi_builder.outbound_socket_addr_check(move |addr, addr_use| {
@@ -133,7 +139,7 @@ impl Factor for OutboundNetworkingFactor {
                             | SocketAddrUse::UdpOutgoingDatagram => "udp",
                         };
                         if !allowed_hosts
-                            .check_url(&addr.to_string(), scheme)
+                            .check_socket_addr(addr, scheme)
                             .await
                             .unwrap_or(
                                 // TODO: should this trap (somehow)?
@@ -180,6 +186,13 @@ pub struct AppState {
     tls_client_configs: TlsClientConfigs,
 }
 
+impl AppState {
+    /// Returns the IP networks which outbound connections may not be made to.
+    pub fn blocked_networks(&self) -> BlockedNetworks {
+        self.blocked_networks.clone()
+    }
+}
+
 pub struct InstanceBuilder {
     allowed_hosts: OutboundAllowedHosts,
     blocked_networks: BlockedNetworks,
@@ -29,6 +29,9 @@ pub struct ClientTlsRuntimeConfig {
     /// A certificate and private key to be used as the client certificate for
     /// "mutual TLS" (mTLS).
     pub client_cert: Option<ClientCertRuntimeConfig>,
+    /// The minimum TLS version to negotiate, or `None` for the TLS library's
+    /// default.
+    pub min_tls_version: Option<TlsVersion>,
 }
 
 impl Default for ClientTlsRuntimeConfig {
@@ -40,6 +43,7 @@ impl Default for ClientTlsRuntimeConfig {
             // Use webpki roots by default
             use_webpki_roots: true,
             client_cert: None,
+            min_tls_version: None,
         }
     }
 }
@@ -49,3 +53,10 @@ pub struct ClientCertRuntimeConfig {
     pub cert_chain: Vec<CertificateDer<'static>>,
     pub key_der: PrivateKeyDer<'static>,
 }
+
+/// A TLS protocol version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
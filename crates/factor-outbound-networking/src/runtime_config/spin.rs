@@ -8,7 +8,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use super::ClientTlsRuntimeConfig;
+use super::{ClientTlsRuntimeConfig, TlsVersion};
 
 /// Spin's default handling of the runtime configuration for outbound networking.
 pub struct SpinRuntimeConfig {
@@ -40,6 +40,7 @@ impl SpinRuntimeConfig {
     /// ca_roots_file = "path/to/roots.crt"
     /// client_cert_file = "path/to/client.crt"
     /// client_private_key_file = "path/to/client.key"
+    /// min_tls_version = "1.3"
     /// ```
     pub fn config_from_table(
         &self,
@@ -121,6 +122,7 @@ impl SpinRuntimeConfig {
             ca_roots_file,
             client_cert_file,
             client_private_key_file,
+            min_tls_version,
         } = toml_config;
         ensure!(
             !component_ids.is_empty(),
@@ -166,6 +168,7 @@ impl SpinRuntimeConfig {
             root_certificates,
             use_webpki_roots,
             client_cert,
+            min_tls_version,
         })
     }
 
@@ -201,6 +204,8 @@ struct ClientTlsToml {
     ca_roots_file: Option<PathBuf>,
     client_cert_file: Option<PathBuf>,
     client_private_key_file: Option<PathBuf>,
+    #[serde(default, deserialize_with = "deserialize_tls_version")]
+    min_tls_version: Option<TlsVersion>,
 }
 
 fn deserialize_hosts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
@@ -211,6 +216,20 @@ fn deserialize_hosts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<S
     Ok(hosts)
 }
 
+fn deserialize_tls_version<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<TlsVersion>, D::Error> {
+    let version = Cow::<str>::deserialize(deserializer)?;
+    match version.as_ref() {
+        "1.2" => Ok(Some(TlsVersion::Tls12)),
+        "1.3" => Ok(Some(TlsVersion::Tls13)),
+        _ => Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Str(&version),
+            &"a TLS version: \"1.2\" or \"1.3\"",
+        )),
+    }
+}
+
 #[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 struct OutboundNetworkingToml {
@@ -315,6 +334,7 @@ mod tests {
         assert_eq!(tls_configs[0].components, ["test-component"]);
         assert_eq!(tls_configs[0].hosts[0].as_str(), "test-host");
         assert!(tls_configs[0].use_webpki_roots);
+        assert_eq!(tls_configs[0].min_tls_version, None);
         Ok(())
     }
 
@@ -331,6 +351,7 @@ mod tests {
                 ca_roots_file = "valid-cert.pem"
                 client_cert_file = "valid-cert.pem"
                 client_private_key_file = "valid-private-key.pem"
+                min_tls_version = "1.3"
             })?
             .context("missing config section")?;
         assert_eq!(tls_configs.len(), 1);
@@ -338,9 +359,32 @@ mod tests {
         assert!(tls_configs[0].use_webpki_roots);
         assert_eq!(tls_configs[0].root_certificates.len(), 2);
         assert!(tls_configs[0].client_cert.is_some());
+        assert_eq!(tls_configs[0].min_tls_version, Some(TlsVersion::Tls13));
         Ok(())
     }
 
+    #[test]
+    fn test_invalid_min_tls_version() {
+        let config = SpinRuntimeConfig::new("/doesnt-matter");
+
+        for table in &[
+            toml::toml! {
+                [[client_tls]]
+                component_ids = ["test-component"]
+                hosts = ["test-host"]
+                min_tls_version = "1.1"
+            },
+            toml::toml! {
+                [[client_tls]]
+                component_ids = ["test-component"]
+                hosts = ["test-host"]
+                min_tls_version = 1.3
+            },
+        ] {
+            config.tls_configs_from_table(table).unwrap_err();
+        }
+    }
+
     #[test]
     fn test_use_webpki_roots_default_with_explicit_roots() -> anyhow::Result<()> {
         let config = SpinRuntimeConfig::new(TESTDATA_DIR);
@@ -2,7 +2,7 @@ use std::{collections::HashMap, ops::Deref, sync::Arc};
 
 use anyhow::{ensure, Context};
 
-use crate::runtime_config::{ClientCertRuntimeConfig, ClientTlsRuntimeConfig};
+use crate::runtime_config::{ClientCertRuntimeConfig, ClientTlsRuntimeConfig, TlsVersion};
 
 /// TLS client configs
 #[derive(Default)]
@@ -25,6 +25,7 @@ impl TlsClientConfigs {
             root_certificates,
             use_webpki_roots,
             client_cert,
+            min_tls_version,
         } in client_tls_configs
         {
             ensure!(
@@ -35,9 +36,13 @@ impl TlsClientConfigs {
                 !hosts.is_empty(),
                 "client TLS 'hosts' list may not be empty"
             );
-            let tls_client_config =
-                TlsClientConfig::new(root_certificates, use_webpki_roots, client_cert)
-                    .context("error building TLS client config")?;
+            let tls_client_config = TlsClientConfig::new(TlsSettings {
+                root_certificates,
+                use_webpki_roots,
+                client_cert,
+                min_tls_version,
+            })
+            .context("error building TLS client config")?;
             for component in components {
                 let host_configs = component_host_tls_client_configs
                     .entry(component.clone())
@@ -90,43 +95,82 @@ impl ComponentTlsClientConfigs {
             .and_then(|configs| configs.get(host))
             .unwrap_or(&self.default_client_config)
     }
+
+    /// Returns the [`TlsClientConfig`] configured for the given host
+    /// authority, or `None` if the host uses the default configuration.
+    ///
+    /// Clients which can't use a [`rustls::ClientConfig`] can use this to
+    /// apply the configuration's [`TlsSettings`] only where it was asked for.
+    pub fn get_configured_client_config(&self, host: &str) -> Option<&TlsClientConfig> {
+        self.host_client_configs
+            .as_ref()
+            .and_then(|configs| configs.get(host))
+    }
 }
 
 /// Shared TLS client configuration
 #[derive(Clone)]
-pub struct TlsClientConfig(Arc<rustls::ClientConfig>);
+pub struct TlsClientConfig {
+    config: Arc<rustls::ClientConfig>,
+    settings: Arc<TlsSettings>,
+}
+
+/// The settings a [`TlsClientConfig`] was built from.
+#[derive(Debug)]
+pub struct TlsSettings {
+    /// A set of CA certs that should be considered valid roots.
+    pub root_certificates: Vec<rustls_pki_types::CertificateDer<'static>>,
+    /// If true, the "standard" CA certs will be considered valid roots in
+    /// addition to `root_certificates`.
+    pub use_webpki_roots: bool,
+    /// A certificate and private key to be used as the client certificate.
+    pub client_cert: Option<ClientCertRuntimeConfig>,
+    /// The minimum TLS version to negotiate.
+    pub min_tls_version: Option<TlsVersion>,
+}
 
 impl TlsClientConfig {
-    fn new(
-        root_certificates: Vec<rustls_pki_types::CertificateDer<'static>>,
-        use_webpki_roots: bool,
-        client_cert: Option<ClientCertRuntimeConfig>,
-    ) -> anyhow::Result<Self> {
+    fn new(settings: TlsSettings) -> anyhow::Result<Self> {
         let mut root_store = rustls::RootCertStore::empty();
-        if use_webpki_roots {
+        if settings.use_webpki_roots {
             root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
         }
-        for cert in root_certificates {
-            root_store.add(cert)?;
+        for cert in &settings.root_certificates {
+            root_store.add(cert.clone())?;
         }
 
-        let builder = rustls::ClientConfig::builder().with_root_certificates(root_store);
+        let builder = match settings.min_tls_version {
+            None | Some(TlsVersion::Tls12) => rustls::ClientConfig::builder(),
+            Some(TlsVersion::Tls13) => {
+                rustls::ClientConfig::builder_with_protocol_versions(&[&rustls::version::TLS13])
+            }
+        }
+        .with_root_certificates(root_store);
 
         let client_config = if let Some(ClientCertRuntimeConfig {
             cert_chain,
             key_der,
-        }) = client_cert
+        }) = &settings.client_cert
         {
-            builder.with_client_auth_cert(cert_chain, key_der)?
+            builder.with_client_auth_cert(cert_chain.clone(), key_der.clone_key())?
         } else {
             builder.with_no_client_auth()
         };
-        Ok(Self(client_config.into()))
+        Ok(Self {
+            config: client_config.into(),
+            settings: settings.into(),
+        })
     }
 
     /// Returns the inner [`rustls::ClientConfig`] for consumption by rustls APIs.
     pub fn inner(&self) -> Arc<rustls::ClientConfig> {
-        self.0.clone()
+        self.config.clone()
+    }
+
+    /// Returns the settings this configuration was built from, for clients
+    /// which use a TLS library other than rustls.
+    pub fn settings(&self) -> &TlsSettings {
+        &self.settings
     }
 }
 
@@ -134,13 +178,19 @@ impl Deref for TlsClientConfig {
     type Target = rustls::ClientConfig;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.config
     }
 }
 
 impl Default for TlsClientConfig {
     fn default() -> Self {
-        Self::new(vec![], true, None).expect("default client config should be valid")
+        Self::new(TlsSettings {
+            root_certificates: vec![],
+            use_webpki_roots: true,
+            client_cert: None,
+            min_tls_version: None,
+        })
+        .expect("default client config should be valid")
     }
 }
 
@@ -181,11 +231,12 @@ mod tests {
             root_certificates: vec![],
             use_webpki_roots: false,
             client_cert: None,
+            min_tls_version: None,
         }])?;
         let config = configs.get_tls_client_config("test-component", "test-host");
         // Check that we didn't just get the default
         let default_config = configs.get_tls_client_config("other_component", "test-host");
-        assert!(!Arc::ptr_eq(&config.0, &default_config.0));
+        assert!(!Arc::ptr_eq(&config.config, &default_config.config));
         Ok(())
     }
 
@@ -202,9 +253,30 @@ mod tests {
                 cert_chain: test_certs,
                 key_der: test_key,
             }),
+            min_tls_version: Some(TlsVersion::Tls13),
         }])?;
         let config = configs.get_tls_client_config("test-component", "test-host");
         assert!(config.client_auth_cert_resolver.has_certs());
+        assert_eq!(config.settings().min_tls_version, Some(TlsVersion::Tls13));
+        Ok(())
+    }
+
+    #[test]
+    fn test_configured_client_config() -> anyhow::Result<()> {
+        let configs = TlsClientConfigs::new([ClientTlsRuntimeConfig {
+            components: vec!["test-component".into()],
+            hosts: vec!["test-host".into()],
+            min_tls_version: Some(TlsVersion::Tls13),
+            ..Default::default()
+        }])?;
+        let component_configs = configs.get_component_tls_configs("test-component");
+        let config = component_configs
+            .get_configured_client_config("test-host")
+            .context("expected configured config")?;
+        assert_eq!(config.settings().min_tls_version, Some(TlsVersion::Tls13));
+        assert!(component_configs
+            .get_configured_client_config("other-host")
+            .is_none());
         Ok(())
     }
 
@@ -66,6 +66,41 @@ async fn configures_wasi_socket_addr_check() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn allows_socket_connections_to_resolved_host_names() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        wasi: WasiFactor::new(DummyFilesMounter),
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        allowed_outbound_hosts = ["tcp://localhost:3000"]
+    });
+    let mut state = env.build_instance_state().await?;
+    let mut wasi = WasiFactor::get_wasi_impl(&mut state).unwrap();
+
+    let network_resource = wasi.instance_network()?;
+    let network = wasi.table().get(&network_resource)?;
+
+    assert!(network.allow_ip_name_lookup);
+    network
+        .check_socket_addr("127.0.0.1:3000".parse().unwrap(), SocketAddrUse::TcpConnect)
+        .await?;
+    for not_allowed in ["127.0.0.1:3001", "127.0.0.2:3000"] {
+        assert_eq!(
+            network
+                .check_socket_addr(not_allowed.parse().unwrap(), SocketAddrUse::TcpConnect)
+                .await
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+    }
+    Ok(())
+}
+
 #[tokio::test]
 async fn wasi_factor_is_optional() -> anyhow::Result<()> {
     #[derive(RuntimeFactors)]
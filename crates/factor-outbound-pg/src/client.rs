@@ -1,6 +1,11 @@
-use anyhow::{anyhow, Result};
-use native_tls::TlsConnector;
+use anyhow::{anyhow, bail, Result};
+use base64::Engine;
+use native_tls::{Certificate, Identity, Protocol, TlsConnector};
 use postgres_native_tls::MakeTlsConnector;
+use spin_factor_outbound_networking::runtime_config::{
+    ClientCertRuntimeConfig, PrivateKeyDer, TlsVersion,
+};
+use spin_factor_outbound_networking::TlsClientConfig;
 use spin_world::async_trait;
 use spin_world::spin::postgres::postgres::{
     self as v3, Column, DbDataType, DbValue, ParameterValue, RowSet,
@@ -11,7 +16,9 @@ use tokio_postgres::{Client as TokioClient, NoTls, Socket};
 
 #[async_trait]
 pub trait Client {
-    async fn build_client(address: &str) -> Result<Self>
+    /// Connects to the given address, using the given TLS configuration if
+    /// one has been configured for the host.
+    async fn build_client(address: &str, tls: Option<TlsClientConfig>) -> Result<Self>
     where
         Self: Sized;
 
@@ -30,7 +37,7 @@ pub trait Client {
 
 #[async_trait]
 impl Client for TokioClient {
-    async fn build_client(address: &str) -> Result<Self>
+    async fn build_client(address: &str, tls: Option<TlsClientConfig>) -> Result<Self>
     where
         Self: Sized,
     {
@@ -43,8 +50,7 @@ impl Client for TokioClient {
             spawn_connection(connection);
             Ok(client)
         } else {
-            let builder = TlsConnector::builder();
-            let connector = MakeTlsConnector::new(builder.build()?);
+            let connector = MakeTlsConnector::new(tls_connector(tls.as_ref())?);
             let (client, connection) = config.connect(connector).await?;
             spawn_connection(connection);
             Ok(client)
@@ -56,6 +62,7 @@ impl Client for TokioClient {
         statement: String,
         params: Vec<ParameterValue>,
     ) -> Result<u64, v3::Error> {
+        let statement = spin_telemetry::inject_trace_context_sql(statement);
         let params = params
             .iter()
             .map(to_sql_parameter)
@@ -77,6 +84,7 @@ impl Client for TokioClient {
         statement: String,
         params: Vec<ParameterValue>,
     ) -> Result<RowSet, v3::Error> {
+        let statement = spin_telemetry::inject_trace_context_sql(statement);
         let params = params
             .iter()
             .map(to_sql_parameter)
@@ -111,6 +119,58 @@ impl Client for TokioClient {
     }
 }
 
+/// Builds a native-tls connector which applies the given configuration.
+fn tls_connector(tls: Option<&TlsClientConfig>) -> Result<TlsConnector> {
+    let mut builder = TlsConnector::builder();
+    if let Some(tls) = tls {
+        let settings = tls.settings();
+        // native-tls's built-in roots are the system's, rather than webpki's
+        builder.disable_built_in_roots(!settings.use_webpki_roots);
+        for cert in &settings.root_certificates {
+            builder.add_root_certificate(Certificate::from_der(cert)?);
+        }
+        if let Some(client_cert) = &settings.client_cert {
+            builder.identity(client_identity(client_cert)?);
+        }
+        match settings.min_tls_version {
+            None => {}
+            Some(TlsVersion::Tls12) => {
+                builder.min_protocol_version(Some(Protocol::Tlsv12));
+            }
+            Some(TlsVersion::Tls13) => {
+                bail!("a minimum TLS version of 1.3 is not supported for PostgreSQL")
+            }
+        }
+    }
+    Ok(builder.build()?)
+}
+
+/// native-tls only loads client certificates from PEM, so the certificate
+/// chain and key are converted back.
+fn client_identity(client_cert: &ClientCertRuntimeConfig) -> Result<Identity> {
+    let PrivateKeyDer::Pkcs8(key) = &client_cert.key_der else {
+        bail!("PostgreSQL client private keys must be in PKCS #8 format");
+    };
+    let cert_chain = client_cert
+        .cert_chain
+        .iter()
+        .map(|cert| pem("CERTIFICATE", cert))
+        .collect::<String>();
+    let key = pem("PRIVATE KEY", key.secret_pkcs8_der());
+    Ok(Identity::from_pkcs8(cert_chain.as_bytes(), key.as_bytes())?)
+}
+
+fn pem(label: &str, der: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+    let lines = encoded
+        .as_bytes()
+        .chunks(64)
+        .map(|line| std::str::from_utf8(line).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("-----BEGIN {label}-----\n{lines}\n-----END {label}-----\n")
+}
+
 fn spawn_connection<T>(connection: tokio_postgres::Connection<Socket, T>)
 where
     T: tokio_postgres::tls::TlsStream + std::marker::Unpin + std::marker::Send + 'static,
@@ -373,3 +433,22 @@ impl std::fmt::Debug for PgNull {
         f.debug_struct("NULL").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_CERT: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../factor-outbound-networking/testdata/valid-cert.pem"
+    );
+
+    #[test]
+    fn pem_round_trips() -> Result<()> {
+        let cert = Certificate::from_pem(&std::fs::read(TEST_CERT)?)?;
+        let der = cert.to_der()?;
+        let round_tripped = Certificate::from_pem(pem("CERTIFICATE", &der).as_bytes())?;
+        assert_eq!(round_tripped.to_der()?, der);
+        Ok(())
+    }
+}
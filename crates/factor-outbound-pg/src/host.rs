@@ -1,5 +1,6 @@
 use anyhow::Result;
 use spin_core::wasmtime::component::Resource;
+use spin_factor_outbound_networking::TlsClientConfig;
 use spin_world::spin::postgres::postgres::{self as v3};
 use spin_world::v1::postgres as v1;
 use spin_world::v1::rdbms_types as v1_types;
@@ -17,9 +18,10 @@ impl<C: Client> InstanceState<C> {
         &mut self,
         address: &str,
     ) -> Result<Resource<Conn>, v3::Error> {
+        let tls = self.tls_client_config(address);
         self.connections
             .push(
-                C::build_client(address)
+                C::build_client(address, tls)
                     .await
                     .map_err(|e| v3::Error::ConnectionFailed(format!("{e:?}")))?,
             )
@@ -36,6 +38,20 @@ impl<C: Client> InstanceState<C> {
             .ok_or_else(|| v3::Error::ConnectionFailed("no connection found".into()))
     }
 
+    /// Returns the TLS configuration for the first host of the address, if
+    /// one has been configured for this component.
+    fn tls_client_config(&self, address: &str) -> Option<TlsClientConfig> {
+        let config = address.parse::<tokio_postgres::Config>().ok()?;
+        let host = config.get_hosts().iter().find_map(|host| match host {
+            tokio_postgres::config::Host::Tcp(host) => Some(host),
+            #[cfg(unix)]
+            tokio_postgres::config::Host::Unix(_) => None,
+        })?;
+        self.component_tls_configs
+            .get_configured_client_config(host)
+            .cloned()
+    }
+
     async fn is_address_allowed(&self, address: &str) -> Result<bool> {
         let Ok(config) = address.parse::<tokio_postgres::Config>() else {
             return Ok(false);
@@ -3,7 +3,8 @@ mod host;
 
 use client::Client;
 use spin_factor_outbound_networking::{
-    config::allowed_hosts::OutboundAllowedHosts, OutboundNetworkingFactor,
+    config::allowed_hosts::OutboundAllowedHosts, ComponentTlsClientConfigs,
+    OutboundNetworkingFactor,
 };
 use spin_factors::{
     anyhow, ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors,
@@ -40,11 +41,12 @@ impl<C: Send + Sync + Client + 'static> Factor for OutboundPgFactor<C> {
         &self,
         mut ctx: PrepareContext<T, Self>,
     ) -> anyhow::Result<Self::InstanceBuilder> {
-        let allowed_hosts = ctx
-            .instance_builder::<OutboundNetworkingFactor>()?
-            .allowed_hosts();
+        let outbound_networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        let allowed_hosts = outbound_networking.allowed_hosts();
+        let component_tls_configs = outbound_networking.component_tls_configs();
         Ok(InstanceState {
             allowed_hosts,
+            component_tls_configs,
             connections: Default::default(),
         })
     }
@@ -66,6 +68,7 @@ impl<C> OutboundPgFactor<C> {
 
 pub struct InstanceState<C> {
     allowed_hosts: OutboundAllowedHosts,
+    component_tls_configs: ComponentTlsClientConfigs,
     connections: spin_resource_table::Table<C>,
 }
 
@@ -1,5 +1,5 @@
 use anyhow::{bail, Result};
-use spin_factor_outbound_networking::OutboundNetworkingFactor;
+use spin_factor_outbound_networking::{OutboundNetworkingFactor, TlsClientConfig};
 use spin_factor_outbound_pg::client::Client;
 use spin_factor_outbound_pg::OutboundPgFactor;
 use spin_factor_variables::VariablesFactor;
@@ -108,7 +108,7 @@ pub struct MockClient {}
 
 #[async_trait]
 impl Client for MockClient {
-    async fn build_client(_address: &str) -> anyhow::Result<Self>
+    async fn build_client(_address: &str, _tls: Option<TlsClientConfig>) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
@@ -1,7 +1,12 @@
 use anyhow::Result;
-use redis::{aio::MultiplexedConnection, AsyncCommands, FromRedisValue, Value};
+use redis::{
+    aio::{MultiplexedConnection, PubSub},
+    AsyncCommands, FromRedisValue, Value,
+};
 use spin_core::wasmtime::component::Resource;
-use spin_factor_outbound_networking::config::allowed_hosts::OutboundAllowedHosts;
+use spin_factor_outbound_networking::{
+    config::allowed_hosts::OutboundAllowedHosts, ComponentTlsClientConfigs,
+};
 use spin_world::v1::{redis as v1, redis_types};
 use spin_world::v2::redis::{
     self as v2, Connection as RedisConnection, Error, RedisParameter, RedisResult,
@@ -11,20 +16,41 @@ use tracing::{instrument, Level};
 
 pub struct InstanceState {
     pub allowed_hosts: OutboundAllowedHosts,
+    pub component_tls_configs: ComponentTlsClientConfigs,
     pub connections: spin_resource_table::Table<MultiplexedConnection>,
+    pub subscribers: spin_resource_table::Table<PubSub>,
 }
 
 impl InstanceState {
-    async fn is_address_allowed(&self, address: &str) -> Result<bool> {
+    pub(crate) async fn is_address_allowed(&self, address: &str) -> Result<bool> {
         self.allowed_hosts.check_url(address, "redis").await
     }
 
+    /// Returns a client for the address, which must already be allowed.
+    pub(crate) fn open_client(&self, address: &str) -> Result<redis::Client, Error> {
+        let client = redis::Client::open(address).map_err(|_| Error::InvalidAddress)?;
+        // The Redis client's TLS connector can't be given custom roots or
+        // client certificates, so connecting without them is refused.
+        if let redis::ConnectionAddr::TcpTls { host, .. } = &client.get_connection_info().addr {
+            if self
+                .component_tls_configs
+                .get_configured_client_config(host)
+                .is_some()
+            {
+                return Err(Error::Other(format!(
+                    "client TLS configuration for {host:?} is not supported for Redis"
+                )));
+            }
+        }
+        Ok(client)
+    }
+
     async fn establish_connection(
         &mut self,
         address: String,
     ) -> Result<Resource<RedisConnection>, Error> {
-        let conn = redis::Client::open(address.as_str())
-            .map_err(|_| Error::InvalidAddress)?
+        let conn = self
+            .open_client(&address)?
             .get_multiplexed_async_connection()
             .await
             .map_err(other_error)?;
@@ -203,7 +229,7 @@ impl v2::HostConnection for crate::InstanceState {
     }
 }
 
-fn other_error(e: impl std::fmt::Display) -> Error {
+pub(crate) fn other_error(e: impl std::fmt::Display) -> Error {
     Error::Other(e.to_string())
 }
 
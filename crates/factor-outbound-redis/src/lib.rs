@@ -1,4 +1,5 @@
 mod host;
+mod pubsub;
 
 use host::InstanceState;
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
@@ -27,6 +28,7 @@ impl Factor for OutboundRedisFactor {
     fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
         ctx.link_bindings(spin_world::v1::redis::add_to_linker::<_, FactorData<Self>>)?;
         ctx.link_bindings(spin_world::v2::redis::add_to_linker::<_, FactorData<Self>>)?;
+        ctx.link_bindings(spin_world::spin::redis::pubsub::add_to_linker::<_, FactorData<Self>>)?;
         Ok(())
     }
 
@@ -41,12 +43,14 @@ impl Factor for OutboundRedisFactor {
         &self,
         mut ctx: PrepareContext<T, Self>,
     ) -> anyhow::Result<Self::InstanceBuilder> {
-        let allowed_hosts = ctx
-            .instance_builder::<OutboundNetworkingFactor>()?
-            .allowed_hosts();
+        let outbound_networking = ctx.instance_builder::<OutboundNetworkingFactor>()?;
+        let allowed_hosts = outbound_networking.allowed_hosts();
+        let component_tls_configs = outbound_networking.component_tls_configs();
         Ok(InstanceState {
             allowed_hosts,
+            component_tls_configs,
             connections: spin_resource_table::Table::new(1024),
+            subscribers: spin_resource_table::Table::new(pubsub::MAX_SUBSCRIBERS),
         })
     }
 }
@@ -0,0 +1,121 @@
+use futures::StreamExt;
+use spin_core::wasmtime::component::Resource;
+use spin_world::spin::redis::pubsub::{self, Message, Subscriber};
+use spin_world::v2::redis::Error;
+use tracing::field::Empty;
+use tracing::{instrument, Level};
+
+use crate::host::{other_error, InstanceState};
+
+/// The maximum number of subscriber connections an instance may have open.
+///
+/// Each subscriber holds its own connection for as long as it exists, so
+/// these are much more limited than other connections.
+pub(crate) const MAX_SUBSCRIBERS: u32 = 16;
+
+impl InstanceState {
+    fn get_subscriber(
+        &mut self,
+        subscriber: &Resource<Subscriber>,
+    ) -> Result<&mut redis::aio::PubSub, Error> {
+        self.subscribers
+            .get_mut(subscriber.rep())
+            .ok_or(Error::Other(
+                "could not find subscriber for resource".into(),
+            ))
+    }
+}
+
+impl pubsub::Host for InstanceState {}
+
+impl pubsub::HostSubscriber for InstanceState {
+    #[instrument(name = "spin_outbound_redis.open_subscriber", skip(self, address), err(level = Level::INFO), fields(otel.kind = "client", db.system = "redis", db.address = Empty, server.port = Empty, db.namespace = Empty))]
+    async fn open(&mut self, address: String) -> Result<Resource<Subscriber>, Error> {
+        spin_factor_outbound_networking::record_address_fields(&address);
+
+        if !self
+            .is_address_allowed(&address)
+            .await
+            .map_err(other_error)?
+        {
+            return Err(Error::InvalidAddress);
+        }
+
+        let pubsub = self
+            .open_client(&address)?
+            .get_async_pubsub()
+            .await
+            .map_err(other_error)?;
+        self.subscribers
+            .push(pubsub)
+            .map(Resource::new_own)
+            .map_err(|_| Error::TooManyConnections)
+    }
+
+    #[instrument(name = "spin_outbound_redis.subscribe", skip(self, subscriber), err(level = Level::INFO), fields(otel.kind = "client", db.system = "redis", otel.name = format!("SUBSCRIBE {}", channels.join(" "))))]
+    async fn subscribe(
+        &mut self,
+        subscriber: Resource<Subscriber>,
+        channels: Vec<String>,
+    ) -> Result<(), Error> {
+        let pubsub = self.get_subscriber(&subscriber)?;
+        pubsub.subscribe(channels).await.map_err(other_error)
+    }
+
+    #[instrument(name = "spin_outbound_redis.psubscribe", skip(self, subscriber), err(level = Level::INFO), fields(otel.kind = "client", db.system = "redis", otel.name = format!("PSUBSCRIBE {}", patterns.join(" "))))]
+    async fn psubscribe(
+        &mut self,
+        subscriber: Resource<Subscriber>,
+        patterns: Vec<String>,
+    ) -> Result<(), Error> {
+        let pubsub = self.get_subscriber(&subscriber)?;
+        pubsub.psubscribe(patterns).await.map_err(other_error)
+    }
+
+    #[instrument(name = "spin_outbound_redis.unsubscribe", skip(self, subscriber), err(level = Level::INFO), fields(otel.kind = "client", db.system = "redis", otel.name = format!("UNSUBSCRIBE {}", channels.join(" "))))]
+    async fn unsubscribe(
+        &mut self,
+        subscriber: Resource<Subscriber>,
+        channels: Vec<String>,
+    ) -> Result<(), Error> {
+        let pubsub = self.get_subscriber(&subscriber)?;
+        pubsub.unsubscribe(channels).await.map_err(other_error)
+    }
+
+    #[instrument(name = "spin_outbound_redis.punsubscribe", skip(self, subscriber), err(level = Level::INFO), fields(otel.kind = "client", db.system = "redis", otel.name = format!("PUNSUBSCRIBE {}", patterns.join(" "))))]
+    async fn punsubscribe(
+        &mut self,
+        subscriber: Resource<Subscriber>,
+        patterns: Vec<String>,
+    ) -> Result<(), Error> {
+        let pubsub = self.get_subscriber(&subscriber)?;
+        pubsub.punsubscribe(patterns).await.map_err(other_error)
+    }
+
+    #[instrument(name = "spin_outbound_redis.next_message", skip(self, subscriber), err(level = Level::INFO), fields(otel.kind = "client", db.system = "redis"))]
+    async fn next_message(
+        &mut self,
+        subscriber: Resource<Subscriber>,
+    ) -> Result<Option<Message>, Error> {
+        let pubsub = self.get_subscriber(&subscriber)?;
+        // The stream ends when the connection is closed
+        let Some(msg) = pubsub.on_message().next().await else {
+            return Ok(None);
+        };
+        let pattern = if msg.from_pattern() {
+            Some(msg.get_pattern().map_err(other_error)?)
+        } else {
+            None
+        };
+        Ok(Some(Message {
+            channel: msg.get_channel_name().to_owned(),
+            pattern,
+            payload: msg.get_payload_bytes().to_vec(),
+        }))
+    }
+
+    async fn drop(&mut self, subscriber: Resource<Subscriber>) -> anyhow::Result<()> {
+        self.subscribers.remove(subscriber.rep());
+        Ok(())
+    }
+}
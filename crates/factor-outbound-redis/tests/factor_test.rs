@@ -1,4 +1,5 @@
 use anyhow::bail;
+use spin_factor_outbound_networking::runtime_config::spin::SpinRuntimeConfig;
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
 use spin_factor_outbound_redis::OutboundRedisFactor;
 use spin_factor_variables::VariablesFactor;
@@ -41,3 +42,73 @@ async fn no_outbound_hosts_fails() -> anyhow::Result<()> {
     assert!(matches!(err, Error::InvalidAddress));
     Ok(())
 }
+
+#[tokio::test]
+async fn subscriber_no_outbound_hosts_fails() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        redis: OutboundRedisFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        spin_manifest_version = 2
+        application.name = "test-app"
+        [[trigger.test]]
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+    let mut state = env.build_instance_state().await?;
+    let subscriber = spin_world::spin::redis::pubsub::HostSubscriber::open(
+        &mut state.redis,
+        "redis://redis.test:8080".to_string(),
+    )
+    .await;
+
+    let Err(err) = subscriber else {
+        bail!("expected Error, got Ok");
+    };
+
+    assert!(matches!(err, Error::InvalidAddress));
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_tls_config_is_not_ignored() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+        networking: OutboundNetworkingFactor::new(),
+        redis: OutboundRedisFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            spin_manifest_version = 2
+            application.name = "test-app"
+            [[trigger.test]]
+
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            allowed_outbound_hosts = ["rediss://redis.test:6379"]
+        })
+        .runtime_config(TestFactorsRuntimeConfig {
+            networking: SpinRuntimeConfig::new("").config_from_table(&toml! {
+                [[client_tls]]
+                component_ids = ["test-component"]
+                hosts = ["redis.test"]
+                min_tls_version = "1.3"
+            })?,
+            ..Default::default()
+        })?;
+    let mut state = env.build_instance_state().await?;
+    let connection = state
+        .redis
+        .open("rediss://redis.test:6379".to_string())
+        .await;
+
+    let Err(err) = connection else {
+        bail!("expected Error, got Ok");
+    };
+
+    assert!(matches!(err, Error::Other(msg) if msg.contains("not supported")));
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Session cookies, which hold a session ID signed by the host.
+//!
+//! A cookie's value is `<id>.<signature>`, where the signature is the
+//! unpadded URL-safe Base64 of the ID's HMAC-SHA256.
+
+use std::fmt::Write;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::hmac;
+use spin_world::spin::sessions::types::{CookieOptions, SameSite};
+
+/// Signs a session ID as a cookie value.
+pub fn sign(key: &hmac::Key, id: &str) -> String {
+    let signature = hmac::sign(key, id.as_bytes());
+    format!("{id}.{}", URL_SAFE_NO_PAD.encode(signature.as_ref()))
+}
+
+/// Returns the session ID in a cookie value, if its signature is valid.
+pub fn verify(key: &hmac::Key, value: &str) -> Option<String> {
+    let (id, signature) = value.rsplit_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    hmac::verify(key, id.as_bytes(), &signature).ok()?;
+    Some(id.to_owned())
+}
+
+/// Returns the value of the cookie with the given name in a `cookie` header.
+pub fn find<'a>(cookie_header: &'a str, name: &str) -> Option<&'a str> {
+    cookie_header.split(';').find_map(|pair| {
+        let (pair_name, value) = pair.trim().split_once('=')?;
+        (pair_name.trim() == name).then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Returns the value of a `set-cookie` header which sets a cookie, or an
+/// error message if the name or options can't be in the header.
+pub fn set_cookie(name: &str, value: &str, options: &CookieOptions) -> Result<String, String> {
+    if name.is_empty() || !name.bytes().all(is_token_byte) {
+        return Err(format!("invalid cookie name {name:?}"));
+    }
+    let path = options.path.as_deref().unwrap_or("/");
+    let mut header = format!("{name}={value}; Path={}", attribute_value("path", path)?);
+    if let Some(domain) = &options.domain {
+        write!(header, "; Domain={}", attribute_value("domain", domain)?).unwrap();
+    }
+    if let Some(max_age_ms) = options.max_age_ms {
+        write!(header, "; Max-Age={}", max_age_ms.div_ceil(1000)).unwrap();
+    }
+    if options.secure {
+        header.push_str("; Secure");
+    }
+    if options.http_only {
+        header.push_str("; HttpOnly");
+    }
+    match options.same_site {
+        Some(SameSite::Strict) => header.push_str("; SameSite=Strict"),
+        Some(SameSite::Lax) => header.push_str("; SameSite=Lax"),
+        Some(SameSite::None) if !options.secure => {
+            return Err("a cookie with `same-site` none must be secure".to_owned())
+        }
+        Some(SameSite::None) => header.push_str("; SameSite=None"),
+        None => {}
+    }
+    Ok(header)
+}
+
+/// Checks that a value can be a cookie attribute.
+fn attribute_value<'a>(attribute: &str, value: &'a str) -> Result<&'a str, String> {
+    if value.bytes().all(|b| b.is_ascii_graphic() && b != b';') {
+        Ok(value)
+    } else {
+        Err(format!("invalid cookie {attribute} {value:?}"))
+    }
+}
+
+/// Whether a byte may be in a cookie name, which is an HTTP token.
+fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> CookieOptions {
+        CookieOptions {
+            path: None,
+            domain: None,
+            secure: true,
+            http_only: true,
+            same_site: Some(SameSite::Lax),
+            max_age_ms: Some(3_600_500),
+        }
+    }
+
+    #[test]
+    fn signed_ids_are_verified() {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let value = sign(&key, "abc");
+        assert_eq!(verify(&key, &value).as_deref(), Some("abc"));
+
+        let other_key = hmac::Key::new(hmac::HMAC_SHA256, b"other");
+        assert_eq!(verify(&other_key, &value), None);
+        let (_, signature) = value.split_once('.').unwrap();
+        assert_eq!(verify(&key, &format!("abd.{signature}")), None);
+        assert_eq!(verify(&key, "abc"), None);
+    }
+
+    #[test]
+    fn cookies_are_found_by_name() {
+        let header = "theme=dark; session=abc.def;other=\"x\"";
+        assert_eq!(find(header, "session"), Some("abc.def"));
+        assert_eq!(find(header, "other"), Some("x"));
+        assert_eq!(find(header, "sess"), None);
+    }
+
+    #[test]
+    fn set_cookie_has_the_options() {
+        assert_eq!(
+            set_cookie("session", "abc.def", &options()).unwrap(),
+            "session=abc.def; Path=/; Max-Age=3601; Secure; HttpOnly; SameSite=Lax"
+        );
+        let options = CookieOptions {
+            path: Some("/app".into()),
+            domain: Some("example.com".into()),
+            secure: false,
+            http_only: false,
+            same_site: None,
+            max_age_ms: Some(0),
+        };
+        assert_eq!(
+            set_cookie("session", "", &options).unwrap(),
+            "session=; Path=/app; Domain=example.com; Max-Age=0"
+        );
+
+        assert!(set_cookie("my session", "", &options).is_err());
+        let insecure_none = CookieOptions {
+            same_site: Some(SameSite::None),
+            ..options.clone()
+        };
+        assert!(set_cookie("session", "", &insecure_none).is_err());
+        let injected = CookieOptions {
+            path: Some("/; Domain=evil.example".into()),
+            ..options
+        };
+        assert!(set_cookie("session", "", &injected).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use spin_serde::Base64Bytes;
+use spin_world::spin::sessions::sessions;
+use spin_world::spin::sessions::types::{self, CookieOptions, Error};
+use tracing::{instrument, Level};
+
+use crate::cookie;
+use crate::store::{Session, SessionStore};
+
+pub struct InstanceState {
+    sessions: SessionStore,
+}
+
+impl InstanceState {
+    pub fn new(sessions: SessionStore) -> Self {
+        Self { sessions }
+    }
+
+    async fn load(&self, id: &str) -> Result<Session, Error> {
+        self.sessions
+            .load(id)
+            .await
+            .map_err(store_error)?
+            .ok_or(Error::NoSuchSession)
+    }
+
+    async fn save(&self, id: &str, session: &Session) -> Result<(), Error> {
+        self.sessions.save(id, session).await.map_err(store_error)
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl sessions::Host for InstanceState {
+    #[instrument(name = "spin_sessions.create", skip(self), err(level = Level::INFO))]
+    async fn create(&mut self, ttl_ms: u64) -> Result<String, Error> {
+        if ttl_ms == 0 {
+            return Err(Error::InvalidArgument(
+                "ttl must be greater than zero".to_owned(),
+            ));
+        }
+        self.sessions
+            .create(Duration::from_millis(ttl_ms))
+            .await
+            .map_err(store_error)
+    }
+
+    #[instrument(name = "spin_sessions.get", skip_all, err(level = Level::INFO))]
+    async fn get(&mut self, id: String, key: String) -> Result<Option<Vec<u8>>, Error> {
+        let mut session = self.load(&id).await?;
+        Ok(session.values.remove(&key).map(Base64Bytes::into_vec))
+    }
+
+    #[instrument(name = "spin_sessions.set", skip_all, err(level = Level::INFO))]
+    async fn set(&mut self, id: String, key: String, value: Vec<u8>) -> Result<(), Error> {
+        let mut session = self.load(&id).await?;
+        session.values.insert(key, value.into());
+        self.save(&id, &session).await
+    }
+
+    #[instrument(name = "spin_sessions.delete", skip_all, err(level = Level::INFO))]
+    async fn delete(&mut self, id: String, key: String) -> Result<(), Error> {
+        let mut session = self.load(&id).await?;
+        session.values.remove(&key);
+        self.save(&id, &session).await
+    }
+
+    #[instrument(name = "spin_sessions.touch", skip_all, err(level = Level::INFO))]
+    async fn touch(&mut self, id: String) -> Result<(), Error> {
+        let session = self.load(&id).await?;
+        self.save(&id, &session).await
+    }
+
+    #[instrument(name = "spin_sessions.destroy", skip_all, err(level = Level::INFO))]
+    async fn destroy(&mut self, id: String) -> Result<(), Error> {
+        self.sessions.destroy(&id).await.map_err(store_error)
+    }
+
+    async fn cookie(
+        &mut self,
+        name: String,
+        id: String,
+        options: CookieOptions,
+    ) -> Result<String, Error> {
+        let key = self.sessions.signing_key().await.map_err(store_error)?;
+        cookie::set_cookie(&name, &cookie::sign(key, &id), &options).map_err(Error::InvalidArgument)
+    }
+
+    async fn clear_cookie(
+        &mut self,
+        name: String,
+        options: CookieOptions,
+    ) -> Result<String, Error> {
+        let options = CookieOptions {
+            max_age_ms: Some(0),
+            ..options
+        };
+        cookie::set_cookie(&name, "", &options).map_err(Error::InvalidArgument)
+    }
+
+    async fn id_from_cookie(
+        &mut self,
+        name: String,
+        cookie_header: String,
+    ) -> Result<Option<String>, Error> {
+        let Some(value) = cookie::find(&cookie_header, &name) else {
+            return Ok(None);
+        };
+        let key = self.sessions.signing_key().await.map_err(store_error)?;
+        Ok(cookie::verify(key, value))
+    }
+}
+
+fn store_error(err: anyhow::Error) -> Error {
+    tracing::error!("Failed to use sessions store: {err:?}");
+    Error::StoreUnavailable(format!("{err:#}"))
+}
@@ -0,0 +1,73 @@
+mod cookie;
+mod host;
+pub mod runtime_config;
+pub mod store;
+
+use anyhow::{ensure, Context as _};
+use host::InstanceState;
+use runtime_config::RuntimeConfig;
+use spin_factor_key_value::KeyValueFactor;
+use spin_factors::{
+    ConfigureAppContext, Factor, FactorData, PrepareContext, RuntimeFactors, SelfInstanceBuilder,
+};
+use store::SessionStore;
+
+/// A factor that lets components keep server-side sessions, identified by
+/// signed cookies. Sessions are kept in a key-value store.
+#[derive(Default)]
+pub struct SessionsFactor {
+    _priv: (),
+}
+
+impl SessionsFactor {
+    /// Create a new SessionsFactor.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for SessionsFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(
+            spin_world::spin::sessions::sessions::add_to_linker::<_, FactorData<Self>>,
+        )?;
+        Ok(())
+    }
+
+    fn configure_app<T: RuntimeFactors>(
+        &self,
+        mut ctx: ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let config = ctx.take_runtime_config().unwrap_or_default();
+        let store_manager = ctx
+            .app_state::<KeyValueFactor>()
+            .context("SessionsFactor depends on KeyValueFactor")?
+            .store_manager();
+        ensure!(
+            store_manager.is_defined(&config.store),
+            "sessions store {:?} is not a configured key-value store",
+            config.store
+        );
+        Ok(AppState {
+            sessions: SessionStore::new(store_manager, &config.store, config.secret.as_deref()),
+        })
+    }
+
+    fn prepare<T: RuntimeFactors>(
+        &self,
+        ctx: PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        Ok(InstanceState::new(ctx.app_state().sessions.clone()))
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+/// The sessions of an app.
+pub struct AppState {
+    sessions: SessionStore,
+}
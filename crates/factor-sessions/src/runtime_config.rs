@@ -0,0 +1,34 @@
+pub mod spin;
+
+use serde::Deserialize;
+
+/// The label of the key-value store which holds sessions if none is
+/// configured.
+pub const DEFAULT_STORE_LABEL: &str = "default";
+
+/// Runtime configuration for sessions.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// The label of the key-value store which holds sessions.
+    #[serde(default = "default_store_label")]
+    pub store: String,
+    /// The secret with which session cookies are signed. If not set, a
+    /// random secret is generated and kept in the store, so that every
+    /// process sharing the store signs cookies alike.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            store: default_store_label(),
+            secret: None,
+        }
+    }
+}
+
+fn default_store_label() -> String {
+    DEFAULT_STORE_LABEL.to_owned()
+}
@@ -0,0 +1,95 @@
+use anyhow::{ensure, Context};
+use spin_factors::runtime_config::toml::GetTomlValue;
+
+use super::RuntimeConfig;
+
+/// The shortest secret which may sign session cookies, in bytes.
+const MIN_SECRET_LEN: usize = 32;
+
+/// Get the runtime configuration for sessions from a TOML table.
+///
+/// Expects table to be in the format:
+/// ```toml
+/// [sessions]
+/// store = "sessions"
+/// secret = "a secret of at least 32 bytes"
+/// ```
+pub fn runtime_config_from_toml(
+    table: &impl GetTomlValue,
+) -> anyhow::Result<Option<RuntimeConfig>> {
+    let Some(value) = table.get("sessions") else {
+        return Ok(None);
+    };
+    let config: RuntimeConfig = value
+        .clone()
+        .try_into()
+        .context("failed to parse [sessions] table")?;
+    ensure!(
+        !config.store.is_empty(),
+        "[sessions] store must not be empty"
+    );
+    if let Some(secret) = &config.secret {
+        ensure!(
+            secret.len() >= MIN_SECRET_LEN,
+            "[sessions] secret must be at least {MIN_SECRET_LEN} bytes"
+        );
+    }
+    Ok(Some(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_config() -> anyhow::Result<()> {
+        let maybe_config = runtime_config_from_toml(&toml::toml! {
+            [some_other_config]
+            relevant = false
+        })?;
+        assert!(maybe_config.is_none(), "{maybe_config:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_and_secret() -> anyhow::Result<()> {
+        let config = runtime_config_from_toml(&toml::toml! {
+            [sessions]
+            store = "sessions"
+            secret = "0123456789abcdef0123456789abcdef"
+        })?
+        .context("expected config, got None")?;
+        assert_eq!(config.store, "sessions");
+        assert_eq!(
+            config.secret.as_deref(),
+            Some("0123456789abcdef0123456789abcdef")
+        );
+
+        let config = runtime_config_from_toml(&toml::toml! {
+            [sessions]
+        })?
+        .context("expected config, got None")?;
+        assert_eq!(config, RuntimeConfig::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        for table in [
+            toml::toml! {
+                [sessions]
+                store = ""
+            },
+            toml::toml! {
+                [sessions]
+                secret = "too short"
+            },
+            toml::toml! {
+                [sessions]
+                stor = "sessions"
+            },
+        ] {
+            runtime_config_from_toml(&table).unwrap_err();
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Sessions kept in a key-value store.
+//!
+//! Each session is a JSON value under the key `spin-sessions/<digest>`, where
+//! the digest is the SHA-256 of the session's ID, so that the store's keys
+//! don't reveal the IDs of live sessions. The store expires a session's key
+//! once its TTL has passed since it was last written.
+//!
+//! Unless the runtime config sets one, the secret which signs session
+//! cookies is generated when first needed and kept under the key
+//! `spin-sessions/secret`.
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use spin_factor_key_value::{Store, StoreManager, SwapError};
+use spin_serde::Base64Bytes;
+use tokio::sync::OnceCell;
+
+const KEY_PREFIX: &str = "spin-sessions/";
+const SECRET_KEY: &str = "spin-sessions/secret";
+
+/// The length of session IDs and generated secrets, in random bytes.
+const RANDOM_LEN: usize = 32;
+
+/// A session's values.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// How long the session lasts after it is written, in milliseconds.
+    pub ttl_ms: u64,
+    pub values: BTreeMap<String, Base64Bytes>,
+}
+
+impl Session {
+    fn ttl(&self) -> Duration {
+        Duration::from_millis(self.ttl_ms)
+    }
+}
+
+/// The sessions in a key-value store. The store is opened when first used.
+#[derive(Clone)]
+pub struct SessionStore {
+    store_manager: Arc<dyn StoreManager>,
+    label: Arc<str>,
+    store: Arc<OnceCell<Arc<dyn Store>>>,
+    /// The secret set by the runtime config, if any.
+    secret: Option<Arc<str>>,
+    signing_key: Arc<OnceCell<hmac::Key>>,
+}
+
+impl SessionStore {
+    /// Creates a store of the sessions in the key-value store with the given
+    /// label, whose cookies are signed with the given secret or else with
+    /// one kept in the store.
+    pub fn new(store_manager: Arc<dyn StoreManager>, label: &str, secret: Option<&str>) -> Self {
+        Self {
+            store_manager,
+            label: label.into(),
+            store: Default::default(),
+            secret: secret.map(Into::into),
+            signing_key: Default::default(),
+        }
+    }
+
+    /// The label of the key-value store holding the sessions.
+    pub fn store_label(&self) -> &str {
+        &self.label
+    }
+
+    async fn store(&self) -> anyhow::Result<&dyn Store> {
+        let store = self
+            .store
+            .get_or_try_init(|| self.store_manager.get(&self.label))
+            .await
+            .with_context(|| format!("failed to open key-value store {:?}", self.label))?;
+        Ok(store.as_ref())
+    }
+
+    /// Creates an empty session with the given TTL. Returns its ID.
+    pub async fn create(&self, ttl: Duration) -> anyhow::Result<String> {
+        let id = URL_SAFE_NO_PAD.encode(random_bytes()?);
+        let session = Session {
+            ttl_ms: ttl.as_millis().try_into().unwrap_or(u64::MAX),
+            values: Default::default(),
+        };
+        self.save(&id, &session).await?;
+        Ok(id)
+    }
+
+    /// Returns a session, or `None` if it doesn't exist or has expired.
+    pub async fn load(&self, id: &str) -> anyhow::Result<Option<Session>> {
+        let Some(value) = self
+            .store()
+            .await?
+            .get(&session_key(id))
+            .await
+            .context("failed to get session")?
+        else {
+            return Ok(None);
+        };
+        let session = serde_json::from_slice(&value).context("invalid session")?;
+        Ok(Some(session))
+    }
+
+    /// Writes a session, which then lasts for its TTL.
+    pub async fn save(&self, id: &str, session: &Session) -> anyhow::Result<()> {
+        self.store()
+            .await?
+            .set_with_ttl(
+                &session_key(id),
+                &serde_json::to_vec(session)?,
+                session.ttl(),
+            )
+            .await
+            .context("failed to store session")
+    }
+
+    /// Removes a session.
+    pub async fn destroy(&self, id: &str) -> anyhow::Result<()> {
+        self.store()
+            .await?
+            .delete(&session_key(id))
+            .await
+            .context("failed to delete session")
+    }
+
+    /// Returns the key which signs session cookies.
+    pub async fn signing_key(&self) -> anyhow::Result<&hmac::Key> {
+        self.signing_key
+            .get_or_try_init(|| async {
+                let secret = match &self.secret {
+                    Some(secret) => secret.as_bytes().to_vec(),
+                    None => self.stored_secret().await?,
+                };
+                anyhow::Ok(hmac::Key::new(hmac::HMAC_SHA256, &secret))
+            })
+            .await
+    }
+
+    /// Returns the secret kept in the store, generating it if there is none.
+    /// Processes which generate one at once agree on the first to be stored.
+    async fn stored_secret(&self) -> anyhow::Result<Vec<u8>> {
+        let store = self.store().await?;
+        let cas = store
+            .new_compare_and_swap(0, SECRET_KEY)
+            .await
+            .context("failed to get session secret")?;
+        if let Some(secret) = cas
+            .current()
+            .await
+            .context("failed to get session secret")?
+        {
+            return Ok(secret);
+        }
+        let secret = random_bytes()?.to_vec();
+        match cas.swap(secret.clone()).await {
+            Ok(()) => Ok(secret),
+            Err(SwapError::CasFailed(_)) => store
+                .get(SECRET_KEY)
+                .await
+                .context("failed to get session secret")?
+                .context("session secret was removed while it was generated"),
+            Err(SwapError::Other(err)) => {
+                Err(anyhow::anyhow!(err).context("failed to store session secret"))
+            }
+        }
+    }
+}
+
+fn session_key(id: &str) -> String {
+    format!(
+        "{KEY_PREFIX}{}",
+        spin_common::sha256::hex_digest_from_bytes(id)
+    )
+}
+
+fn random_bytes() -> anyhow::Result<[u8; RANDOM_LEN]> {
+    let mut bytes = [0; RANDOM_LEN];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow::anyhow!("failed to generate random bytes"))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
+    use spin_key_value_memory::MemoryKeyValueStore;
+
+    use super::*;
+
+    fn sessions(secret: Option<&str>) -> SessionStore {
+        let store_manager = MemoryKeyValueStore::new()
+            .make_store(Default::default())
+            .unwrap();
+        SessionStore::new(Arc::new(store_manager), "default", secret)
+    }
+
+    #[tokio::test]
+    async fn sessions_are_created_saved_and_destroyed() -> anyhow::Result<()> {
+        let sessions = sessions(None);
+        let id = sessions.create(Duration::from_secs(60)).await?;
+        let mut session = sessions.load(&id).await?.context("session not found")?;
+        assert_eq!(session.ttl_ms, 60_000);
+        assert!(session.values.is_empty());
+
+        session
+            .values
+            .insert("user".into(), b"alice".to_vec().into());
+        sessions.save(&id, &session).await?;
+        assert_eq!(sessions.load(&id).await?, Some(session));
+        // The store's keys don't give away session IDs
+        let keys = sessions.store().await?.get_keys().await?;
+        assert!(keys.iter().all(|key| !key.contains(&id)));
+
+        sessions.destroy(&id).await?;
+        assert_eq!(sessions.load(&id).await?, None);
+        assert_eq!(sessions.load("unknown").await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn generated_secret_is_kept_in_the_store() -> anyhow::Result<()> {
+        let sessions = sessions(None);
+        let key = sessions.signing_key().await?;
+        let tag = hmac::sign(key, b"id");
+        let secret = sessions.store().await?.get(SECRET_KEY).await?.unwrap();
+        assert_eq!(secret.len(), RANDOM_LEN);
+
+        // Another process sharing the store signs alike
+        let other = SessionStore {
+            signing_key: Default::default(),
+            ..sessions.clone()
+        };
+        hmac::verify(other.signing_key().await?, b"id", tag.as_ref()).unwrap();
+        Ok(())
+    }
+}
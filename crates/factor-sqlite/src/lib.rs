@@ -173,7 +173,9 @@ impl AppState {
 pub trait ConnectionCreator: Send + Sync {
     /// Get a *new* [`Connection`]
     ///
-    /// The connection should be a new connection, not a reused one.
+    /// The connection may wrap one taken from a pool, but it must not share
+    /// state, such as an open transaction or the result of `changes`, with
+    /// other connections.
     async fn create_connection(
         &self,
         label: &str,
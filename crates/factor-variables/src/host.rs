@@ -78,13 +78,15 @@ fn expressions_to_variables_err(err: spin_expressions::Error) -> variables::Erro
     use spin_expressions::Error;
     let blame = match err {
         Error::InvalidName(_) | Error::InvalidTemplate(_) | Error::Undefined(_) => Blame::Guest,
-        Error::Provider(_) => Blame::Host,
+        Error::Provider(_) | Error::InvalidValue(_) | Error::InvalidDefinition(_) => Blame::Host,
     };
     traces::mark_as_error(&err, Some(blame));
     match err {
         Error::InvalidName(msg) => variables::Error::InvalidName(msg),
         Error::Undefined(msg) => variables::Error::Undefined(msg),
-        Error::InvalidTemplate(_) => variables::Error::Other(format!("{err}")),
+        Error::InvalidTemplate(_) | Error::InvalidValue(_) | Error::InvalidDefinition(_) => {
+            variables::Error::Other(format!("{err}"))
+        }
         Error::Provider(err) => variables::Error::Provider(err.to_string()),
     }
 }
@@ -1,26 +1,59 @@
 mod host;
 pub mod runtime_config;
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
+use futures::stream::BoxStream;
 use runtime_config::RuntimeConfig;
-use spin_expressions::{ProviderResolver as ExpressionResolver, Template};
+use spin_expressions::{
+    Filter, Filters, ProviderResolver as ExpressionResolver, RotationHook, Template,
+};
 use spin_factors::{
-    anyhow, ConfigureAppContext, Factor, FactorData, InitContext, PrepareContext, RuntimeFactors,
+    anyhow::{self, Context as _},
+    serde::de::DeserializeOwned,
+    App, ConfigureAppContext, Factor, FactorData, InitContext, PrepareContext, RuntimeFactors,
     SelfInstanceBuilder,
 };
+use spin_locked_app::{MetadataKey, Variable};
 
 /// A factor for providing variables to components.
 #[derive(Default)]
 pub struct VariablesFactor {
-    _priv: (),
+    filters: Filters,
+    rotation_hooks: Vec<RotationHook>,
+    profile: Option<String>,
 }
 
+/// Profile name -> variables declared by the profile.
+const VARIABLE_PROFILES_KEY: MetadataKey<BTreeMap<String, BTreeMap<String, Variable>>> =
+    MetadataKey::new("variable_profiles");
+
+/// The profile to use if none is selected at startup, such as that of the
+/// manifest environment the application was loaded with.
+const DEFAULT_PROFILE_KEY: MetadataKey<String> = MetadataKey::new("variables_profile");
+
 impl VariablesFactor {
     /// Creates a new `VariablesFactor`.
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Registers a filter which variable templates can use, e.g. `{{ key | name }}`.
+    pub fn add_filter(&mut self, name: impl Into<String>, filter: impl Filter + 'static) {
+        self.filters.register(name, filter);
+    }
+
+    /// Selects the configuration profile whose variables override the
+    /// application variables.
+    pub fn set_profile(&mut self, profile: impl Into<String>) {
+        self.profile = Some(profile.into());
+    }
+
+    /// Registers a hook which is called with a variable's name and new version
+    /// when a provider reports that its value has been rotated.
+    pub fn on_rotation(&mut self, hook: impl Fn(&str, &str) + Send + Sync + 'static) {
+        self.rotation_hooks.push(Arc::new(hook));
+    }
 }
 
 impl Factor for VariablesFactor {
@@ -40,8 +73,32 @@ impl Factor for VariablesFactor {
         mut ctx: ConfigureAppContext<T, Self>,
     ) -> anyhow::Result<Self::AppState> {
         let app = ctx.app();
-        let mut expression_resolver =
-            ExpressionResolver::new(app.variables().map(|(key, val)| (key.clone(), val.clone())))?;
+        let variables = app.variables().map(|(key, val)| (key.clone(), val.clone()));
+        let profile = match &self.profile {
+            Some(profile) => Some(profile.clone()),
+            None => app.get_metadata(DEFAULT_PROFILE_KEY)?,
+        };
+        let mut expression_resolver = match profile {
+            Some(profile) => {
+                let mut profiles = app.get_metadata(VARIABLE_PROFILES_KEY)?.unwrap_or_default();
+                let Some(overlay) = profiles.remove(&profile) else {
+                    let known = profiles.keys().cloned().collect::<Vec<_>>();
+                    anyhow::bail!(
+                        "unknown variables profile {profile:?}; the application declares [{}]",
+                        known.join(", ")
+                    );
+                };
+                tracing::info!("Using variables profile {profile:?}");
+                ExpressionResolver::with_profile(variables, profile.clone(), overlay)
+                    .with_context(|| format!("invalid variables profile {profile:?}"))?
+            }
+            None => ExpressionResolver::new(variables)?,
+        };
+        expression_resolver.set_filters(self.filters.clone());
+        for hook in &self.rotation_hooks {
+            let hook = hook.clone();
+            expression_resolver.on_rotation(move |key, version| hook(key, version));
+        }
 
         for component in app.components() {
             expression_resolver.add_component_variables(
@@ -50,10 +107,16 @@ impl Factor for VariablesFactor {
             )?;
         }
 
-        let providers = ctx.take_runtime_config().unwrap_or_default();
-        for provider in providers {
+        let runtime_config = ctx.take_runtime_config().unwrap_or_default();
+        for component_id in runtime_config.trusted_components {
+            expression_resolver.trust_component(component_id);
+        }
+        for provider in runtime_config.providers {
             expression_resolver.add_provider(provider);
         }
+        for warning in expression_resolver.provider_warnings() {
+            tracing::warn!("{warning}");
+        }
 
         Ok(AppState {
             expression_resolver: Arc::new(expression_resolver),
@@ -85,6 +148,105 @@ impl AppState {
         let template = Template::new(expr)?;
         self.expression_resolver.resolve_template(&template).await
     }
+
+    /// Returns the IDs and configs of the app's triggers of the given type,
+    /// as [`App::trigger_configs`], with variable templates resolved in every
+    /// string value of the configs.
+    ///
+    /// Secret values are handled as by [`Self::resolve_expression`]; triggers
+    /// should avoid logging configs which may contain them.
+    pub async fn resolve_trigger_configs<T: DeserializeOwned>(
+        &self,
+        app: &App,
+        trigger_type: &str,
+    ) -> anyhow::Result<Vec<(String, T)>> {
+        let mut configs = vec![];
+        for trigger in app.triggers_with_type(trigger_type) {
+            let mut config: serde_json::Value = trigger.typed_config()?;
+            for value in string_values(&mut config) {
+                if value.contains("{{") {
+                    *value = self
+                        .resolve_expression(value.as_str())
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "failed to resolve {trigger_type} trigger config for trigger {:?}",
+                                trigger.id()
+                            )
+                        })?;
+                }
+            }
+            configs.push((trigger.id().to_owned(), serde_json::from_value(config)?));
+        }
+        Ok(configs)
+    }
+
+    /// Fetches the values of all variables from the providers in bulk, so
+    /// that slow providers don't delay the first request.
+    pub async fn prefetch(&self) -> spin_expressions::Result<()> {
+        self.expression_resolver.prefetch().await
+    }
+
+    /// Returns a handle to counters describing how variables have been
+    /// resolved, such as the latency and failures of each provider.
+    pub fn metrics(&self) -> spin_expressions::ResolverMetrics {
+        self.expression_resolver.metrics()
+    }
+
+    /// Returns the name of the active configuration profile, if any.
+    pub fn profile(&self) -> Option<&str> {
+        self.expression_resolver.profile()
+    }
+
+    /// Returns the version of the variable's most recently resolved value, if
+    /// its provider reports versions.
+    ///
+    /// A change in version means the value has been rotated.
+    pub fn version(&self, key: &str) -> Option<String> {
+        self.expression_resolver.version(key)
+    }
+
+    /// Resolves all variables into a [`Snapshot`](spin_expressions::Snapshot),
+    /// with secret values redacted.
+    pub async fn snapshot(&self) -> spin_expressions::Snapshot {
+        self.expression_resolver.snapshot().await
+    }
+
+    /// Resolves all variables which declare a type or value constraints,
+    /// returning an error naming the first variable which is invalid.
+    pub async fn validate_constraints(&self) -> spin_expressions::Result<()> {
+        self.expression_resolver.validate_constraints().await
+    }
+
+    /// Returns a stream of the names of variables whose values may have
+    /// changed, e.g. because a secret was rotated.
+    ///
+    /// Long-running triggers can use this to pick up new values without
+    /// restarting the app.
+    pub fn changes(&self) -> BoxStream<'static, String> {
+        self.expression_resolver.changes()
+    }
+
+    /// Returns the name of the provider (or `default`) that each variable
+    /// resolved so far got its value from.
+    pub fn resolved_sources(&self) -> Vec<(String, spin_expressions::Source)> {
+        self.expression_resolver.resolved_sources()
+    }
+}
+
+// Returns all the strings in the given JSON value, including nested ones.
+fn string_values(value: &mut serde_json::Value) -> Vec<&mut String> {
+    let mut strings = vec![];
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            serde_json::Value::String(s) => strings.push(s),
+            serde_json::Value::Array(values) => stack.extend(values.iter_mut()),
+            serde_json::Value::Object(values) => stack.extend(values.values_mut()),
+            _ => (),
+        }
+    }
+    strings
 }
 
 pub struct InstanceState {
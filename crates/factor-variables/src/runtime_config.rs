@@ -4,6 +4,9 @@ use spin_expressions::Provider;
 #[derive(Default)]
 pub struct RuntimeConfig {
     pub providers: Vec<Box<dyn Provider>>,
+    /// Components which may read application variables directly, rather than
+    /// only the variables in their own `variables` table.
+    pub trusted_components: Vec<String>,
 }
 
 impl IntoIterator for RuntimeConfig {
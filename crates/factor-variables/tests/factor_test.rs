@@ -1,6 +1,6 @@
 use spin_expressions::{Key, Provider};
 use spin_factor_variables::{runtime_config::RuntimeConfig, VariablesFactor};
-use spin_factors::{anyhow, RuntimeFactors};
+use spin_factors::{anyhow, App, RuntimeFactors};
 use spin_factors_test::{toml, TestEnvironment};
 use spin_world::v2::variables::Host;
 
@@ -16,7 +16,10 @@ async fn provider_works() -> anyhow::Result<()> {
     };
     let providers = vec![Box::new(MockProvider) as _];
     let runtime_config = TestFactorsRuntimeConfig {
-        variables: Some(RuntimeConfig { providers }),
+        variables: Some(RuntimeConfig {
+            providers,
+            ..Default::default()
+        }),
     };
     let env = TestEnvironment::new(factors)
         .extend_manifest(toml! {
@@ -35,6 +38,59 @@ async fn provider_works() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn profile_overrides_variables() -> anyhow::Result<()> {
+    let mut variables = VariablesFactor::default();
+    variables.set_profile("prod");
+    let factors = TestFactors { variables };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [variables]
+        log_level = { default = "debug" }
+
+        [profiles.prod.variables]
+        log_level = { default = "warn" }
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        variables = { level = "{{ log_level }}" }
+    });
+
+    let mut state = env.build_instance_state().await?;
+    let val = state.variables.get("level".into()).await?;
+    assert_eq!(val, "warn");
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn environment_profile_is_the_default() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        variables: VariablesFactor::default(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [variables]
+        log_level = { default = "debug" }
+
+        [profiles.prod.variables]
+        log_level = { default = "warn" }
+
+        [component.test-component]
+        source = "does-not-exist.wasm"
+    });
+
+    // As recorded by the loader when the app is loaded for the `prod` environment
+    let mut locked_app = env.build_locked_app().await?;
+    locked_app
+        .metadata
+        .insert("variables_profile".into(), "prod".into());
+
+    let app = App::new("test-app", locked_app);
+    let configured_app = env.factors.configure_app(app, env.runtime_config)?;
+    let state = configured_app.app_state::<VariablesFactor>()?;
+    assert_eq!(state.profile(), Some("prod"));
+    assert_eq!(state.resolve_expression("{{ log_level }}").await?, "warn");
+    Ok(())
+}
+
 #[derive(Debug)]
 struct MockProvider;
 
@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use spin_factors::wasmtime::component::Resource;
+use spin_factors::{anyhow, SelfInstanceBuilder};
+use spin_world::spin::vector::store::{self, Store};
+use spin_world::spin::vector::types::{self, Error, Metadata, Point, ScoredPoint};
+use tracing::field::Empty;
+use tracing::{instrument, Level};
+
+use crate::VectorStore;
+
+pub struct InstanceState {
+    allowed_stores: Arc<HashSet<String>>,
+    /// A resource table of open stores.
+    open_stores: spin_resource_table::Table<Arc<dyn VectorStore>>,
+    /// A map from store label to store.
+    stores: HashMap<String, Arc<dyn VectorStore>>,
+}
+
+impl InstanceState {
+    /// Create a new `InstanceState`
+    pub fn new(
+        allowed_stores: Arc<HashSet<String>>,
+        stores: HashMap<String, Arc<dyn VectorStore>>,
+    ) -> Self {
+        Self {
+            allowed_stores,
+            open_stores: spin_resource_table::Table::new(256),
+            stores,
+        }
+    }
+
+    fn get_store(&self, store: Resource<Store>) -> Result<&dyn VectorStore, Error> {
+        let store = self
+            .open_stores
+            .get(store.rep())
+            .map(|store| store.as_ref())
+            .ok_or_else(|| Error::Other("could not find store for resource".into()))?;
+        tracing::Span::current().record(
+            "vector.backend",
+            store.summary().as_deref().unwrap_or("unknown"),
+        );
+        Ok(store)
+    }
+}
+
+impl SelfInstanceBuilder for InstanceState {}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> anyhow::Result<Error> {
+        Ok(error)
+    }
+}
+
+impl store::Host for InstanceState {}
+
+impl store::HostStore for InstanceState {
+    #[instrument(name = "spin_vector.open", skip(self), err(level = Level::INFO), fields(otel.kind = "client", vector.backend = Empty))]
+    async fn open(&mut self, label: String) -> Result<Resource<Store>, Error> {
+        if !self.allowed_stores.contains(&label) {
+            return Err(Error::AccessDenied);
+        }
+        let store = self.stores.get(&label).ok_or(Error::NoSuchStore)?.clone();
+        tracing::Span::current().record(
+            "vector.backend",
+            store.summary().as_deref().unwrap_or("unknown"),
+        );
+        self.open_stores
+            .push(store)
+            .map_err(|()| Error::Other("too many stores opened".into()))
+            .map(Resource::new_own)
+    }
+
+    #[instrument(name = "spin_vector.upsert", skip(self, store, points), err(level = Level::INFO), fields(otel.kind = "client", vector.backend = Empty, vector.points = points.len()))]
+    async fn upsert(&mut self, store: Resource<Store>, points: Vec<Point>) -> Result<(), Error> {
+        if let Some(point) = points.iter().find(|point| point.vector.is_empty()) {
+            return Err(Error::InvalidInput(format!(
+                "the vector of point {:?} is empty",
+                point.id
+            )));
+        }
+        if points.is_empty() {
+            return Ok(());
+        }
+        self.get_store(store)?.upsert(points).await
+    }
+
+    #[instrument(name = "spin_vector.query", skip(self, store, vector, filter), err(level = Level::INFO), fields(otel.kind = "client", vector.backend = Empty))]
+    async fn query(
+        &mut self,
+        store: Resource<Store>,
+        vector: Vec<f32>,
+        limit: u32,
+        filter: Metadata,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        if vector.is_empty() {
+            return Err(Error::InvalidInput("the query vector is empty".into()));
+        }
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+        self.get_store(store)?.query(vector, limit, filter).await
+    }
+
+    #[instrument(name = "spin_vector.delete", skip(self, store, ids), err(level = Level::INFO), fields(otel.kind = "client", vector.backend = Empty))]
+    async fn delete(&mut self, store: Resource<Store>, ids: Vec<String>) -> Result<(), Error> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.get_store(store)?.delete(ids).await
+    }
+
+    async fn drop(&mut self, store: Resource<Store>) -> anyhow::Result<()> {
+        self.open_stores.remove(store.rep());
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+mod host;
+pub mod runtime_config;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use host::InstanceState;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use spin_factors::{anyhow, Factor, FactorData};
+use spin_locked_app::MetadataKey;
+use spin_world::spin::vector::store;
+use spin_world::spin::vector::types::{Error, Metadata, MetadataValue, Point, ScoredPoint};
+
+pub use runtime_config::RuntimeConfig;
+
+/// Metadata key for a list of allowed vector stores for a component.
+pub const ALLOWED_STORES_KEY: MetadataKey<Vec<String>> = MetadataKey::new("vector_stores");
+
+/// A factor that lets components store vectors, such as embeddings, and
+/// search them by similarity.
+#[derive(Default)]
+pub struct VectorFactor {
+    _priv: (),
+}
+
+impl VectorFactor {
+    /// Create a new `VectorFactor`
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl Factor for VectorFactor {
+    type RuntimeConfig = RuntimeConfig;
+    type AppState = AppState;
+    type InstanceBuilder = InstanceState;
+
+    fn init(&mut self, ctx: &mut impl spin_factors::InitContext<Self>) -> anyhow::Result<()> {
+        ctx.link_bindings(store::add_to_linker::<_, FactorData<Self>>)?;
+        Ok(())
+    }
+
+    fn configure_app<T: spin_factors::RuntimeFactors>(
+        &self,
+        mut ctx: spin_factors::ConfigureAppContext<T, Self>,
+    ) -> anyhow::Result<Self::AppState> {
+        let stores = ctx.take_runtime_config().unwrap_or_default().stores;
+
+        let allowed_stores = ctx
+            .app()
+            .components()
+            .map(|component| {
+                Ok((
+                    component.id().to_string(),
+                    Arc::new(
+                        component
+                            .get_metadata(ALLOWED_STORES_KEY)?
+                            .unwrap_or_default()
+                            .into_iter()
+                            .collect::<HashSet<_>>(),
+                    ),
+                ))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+        ensure_allowed_stores_are_configured(&allowed_stores, |label| stores.contains_key(label))?;
+
+        Ok(AppState {
+            allowed_stores,
+            stores,
+        })
+    }
+
+    fn prepare<T: spin_factors::RuntimeFactors>(
+        &self,
+        ctx: spin_factors::PrepareContext<T, Self>,
+    ) -> anyhow::Result<Self::InstanceBuilder> {
+        let allowed_stores = ctx
+            .app_state()
+            .allowed_stores
+            .get(ctx.app_component().id())
+            .cloned()
+            .unwrap_or_default();
+        Ok(InstanceState::new(
+            allowed_stores,
+            ctx.app_state().stores.clone(),
+        ))
+    }
+}
+
+/// Ensure that all the stores in the allowed stores list for each component are configured
+fn ensure_allowed_stores_are_configured(
+    allowed_stores: &HashMap<String, Arc<HashSet<String>>>,
+    is_configured: impl Fn(&str) -> bool,
+) -> anyhow::Result<()> {
+    let mut errors = Vec::new();
+    for (component_id, stores) in allowed_stores {
+        for allowed in stores.iter() {
+            if !is_configured(allowed) {
+                errors.push(format!(
+                    "- Component {component_id} uses vector store '{allowed}'"
+                ));
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        let prologue = vec![
+            "One or more components use vector stores which are not defined.",
+            "Check the spelling, or pass a runtime configuration file that defines these stores.",
+            "Details:",
+        ];
+        let lines: Vec<_> = prologue
+            .into_iter()
+            .map(|s| s.to_owned())
+            .chain(errors)
+            .collect();
+        return Err(anyhow::anyhow!(lines.join("\n")));
+    }
+    Ok(())
+}
+
+pub struct AppState {
+    /// A map from component id to a set of allowed store labels.
+    allowed_stores: HashMap<String, Arc<HashSet<String>>>,
+    /// A map from store label to store.
+    stores: HashMap<String, Arc<dyn VectorStore>>,
+}
+
+/// How the similarity of vectors is measured.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Distance {
+    /// The cosine of the angle between the vectors, from -1 to 1.
+    #[default]
+    Cosine,
+    /// The dot product of the vectors, which is their cosine similarity
+    /// when they are normalized.
+    DotProduct,
+}
+
+impl Distance {
+    /// The similarity score of two vectors of the same dimension. Higher
+    /// scores are more similar.
+    pub fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        match self {
+            Distance::DotProduct => dot,
+            Distance::Cosine => {
+                let norms = norm(a) * norm(b);
+                if norms == 0.0 {
+                    0.0
+                } else {
+                    dot / norms
+                }
+            }
+        }
+    }
+}
+
+fn norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Converts metadata to a JSON object, for stores which keep it as JSON.
+pub fn metadata_to_json(metadata: Metadata) -> Map<String, Value> {
+    metadata
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                MetadataValue::Text(s) => Value::from(s),
+                MetadataValue::Integer(i) => Value::from(i),
+                MetadataValue::Float(f) => Value::from(f),
+                MetadataValue::Boolean(b) => Value::from(b),
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// Converts a JSON object made by [`metadata_to_json`] back to metadata.
+/// Values which metadata can't hold, such as arrays, are left out.
+pub fn metadata_from_json(object: Map<String, Value>) -> Metadata {
+    object
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => MetadataValue::Text(s),
+                Value::Bool(b) => MetadataValue::Boolean(b),
+                Value::Number(n) => match n.as_i64() {
+                    Some(i) => MetadataValue::Integer(i),
+                    None => MetadataValue::Float(n.as_f64()?),
+                },
+                _ => return None,
+            };
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// A store of vectors, searched by similarity.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Insert the points, replacing any points with the same IDs.
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error>;
+
+    /// Find at most `limit` points most similar to `vector`, most similar
+    /// first, among those whose metadata has every key-value pair of
+    /// `filter`.
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        limit: u32,
+        filter: Metadata,
+    ) -> Result<Vec<ScoredPoint>, Error>;
+
+    /// Delete the points with the given IDs.
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error>;
+
+    /// A human-readable summary of the store's configuration
+    ///
+    /// Example: "pgvector table documents"
+    fn summary(&self) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_vectors_by_distance() {
+        let a = [1.0, 0.0];
+        let b = [2.0, 2.0];
+        assert_eq!(Distance::DotProduct.score(&a, &b), 2.0);
+        assert!((Distance::Cosine.score(&a, &b) - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(Distance::Cosine.score(&a, &[0.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn metadata_round_trips_through_json() {
+        let json = metadata_to_json(vec![
+            ("title".into(), MetadataValue::Text("Spin".into())),
+            ("page".into(), MetadataValue::Integer(3)),
+            ("weight".into(), MetadataValue::Float(1.0)),
+            ("draft".into(), MetadataValue::Boolean(false)),
+        ]);
+        assert_eq!(
+            Value::Object(json.clone()).to_string(),
+            r#"{"draft":false,"page":3,"title":"Spin","weight":1.0}"#
+        );
+        let metadata = metadata_from_json(json);
+        assert!(matches!(
+            metadata.iter().find(|(k, _)| k == "weight"),
+            Some((_, MetadataValue::Float(f))) if *f == 1.0
+        ));
+        assert!(matches!(
+            metadata.iter().find(|(k, _)| k == "page"),
+            Some((_, MetadataValue::Integer(3)))
+        ));
+    }
+}
@@ -0,0 +1,11 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::VectorStore;
+
+/// A runtime configuration for vector stores.
+///
+/// Maps store labels to stores.
+#[derive(Default)]
+pub struct RuntimeConfig {
+    pub stores: HashMap<String, Arc<dyn VectorStore>>,
+}
@@ -0,0 +1,134 @@
+use std::{collections::HashMap, sync::Arc};
+
+use spin_factor_vector::{RuntimeConfig, VectorFactor, VectorStore};
+use spin_factors::{
+    anyhow::{self, bail, Context as _},
+    RuntimeFactors,
+};
+use spin_factors_test::{toml, TestEnvironment};
+use spin_world::{
+    async_trait,
+    spin::vector::store::HostStore as _,
+    spin::vector::types::{Error, Metadata, Point, ScoredPoint},
+};
+
+#[derive(RuntimeFactors)]
+struct TestFactors {
+    vector: VectorFactor,
+}
+
+#[tokio::test]
+async fn errors_when_non_configured_store_used() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        vector: VectorFactor::new(),
+    };
+    let env = TestEnvironment::new(factors).extend_manifest(toml! {
+        [component.test-component]
+        source = "does-not-exist.wasm"
+        vector_stores = ["foo"]
+    });
+    let Err(err) = env.build_instance_state().await else {
+        bail!("Expected build_instance_state to error but it did not");
+    };
+
+    assert!(err
+        .to_string()
+        .contains("One or more components use vector stores which are not defined."));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_when_store_not_allowed() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        vector: VectorFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+        })
+        .runtime_config(runtime_config())?;
+    let mut state = env
+        .build_instance_state()
+        .await
+        .context("build_instance_state failed")?;
+
+    assert!(matches!(
+        state.vector.open("foo".into()).await,
+        Err(Error::AccessDenied)
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn queries_configured_store() -> anyhow::Result<()> {
+    let factors = TestFactors {
+        vector: VectorFactor::new(),
+    };
+    let env = TestEnvironment::new(factors)
+        .extend_manifest(toml! {
+            [component.test-component]
+            source = "does-not-exist.wasm"
+            vector_stores = ["foo"]
+        })
+        .runtime_config(runtime_config())?;
+    let mut state = env
+        .build_instance_state()
+        .await
+        .context("build_instance_state failed")?;
+
+    assert!(matches!(
+        state.vector.open("bar".into()).await,
+        Err(Error::AccessDenied)
+    ));
+    let store = state.vector.open("foo".into()).await?;
+    let matches = state
+        .vector
+        .query(
+            spin_factors::wasmtime::component::Resource::new_borrow(store.rep()),
+            vec![1.0, 0.0],
+            1,
+            vec![],
+        )
+        .await?;
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].id, "doc-1");
+    Ok(())
+}
+
+fn runtime_config() -> TestFactorsRuntimeConfig {
+    let mut stores = HashMap::new();
+    stores.insert("foo".to_owned(), Arc::new(MockStore) as _);
+    TestFactorsRuntimeConfig {
+        vector: Some(RuntimeConfig { stores }),
+    }
+}
+
+/// A store that finds a single point for every query.
+struct MockStore;
+
+#[async_trait]
+impl VectorStore for MockStore {
+    async fn upsert(&self, _points: Vec<Point>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        _vector: Vec<f32>,
+        _limit: u32,
+        _filter: Metadata,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        Ok(vec![ScoredPoint {
+            id: "doc-1".into(),
+            score: 1.0,
+            metadata: vec![],
+        }])
+    }
+
+    async fn delete(&self, _ids: Vec<String>) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -305,6 +305,12 @@ impl FactorInstanceBuilder for InstanceBuilder {
 }
 
 impl InstanceBuilder {
+    /// Sets whether the instance may resolve host names with
+    /// `wasi:sockets/ip-name-lookup`, which it may not by default.
+    pub fn allow_ip_name_lookup(&mut self, enable: bool) {
+        self.ctx.allow_ip_name_lookup(enable);
+    }
+
     pub fn outbound_socket_addr_check<F, Fut>(&mut self, check: F)
     where
         F: Fn(SocketAddr, SocketAddrUse) -> Fut + Send + Sync + Clone + 'static,
@@ -41,7 +41,8 @@ impl FilesMounter for SpinFilesMounter {
             let guest_path = guest_path
                 .to_str()
                 .with_context(|| format!("guest path {guest_path:?} not valid UTF-8"))?;
-            ctx.preopened_dir(source_path, guest_path, self.allow_transient_writes)?;
+            let allow_writes = self.allow_transient_writes && !content_dir.read_only;
+            ctx.preopened_dir(source_path, guest_path, allow_writes)?;
         }
         Ok(())
     }
@@ -0,0 +1,53 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// Fields which describe an invocation, such as the ID of the request which
+/// a trigger is handling.
+///
+/// Triggers set fields on an instance's context, and executor hooks attach
+/// them to what they capture of the instance, such as its output. Clones
+/// share the same fields, so a hook can keep a clone from when it prepares an
+/// instance and see the fields a trigger sets later.
+#[derive(Clone, Debug, Default)]
+pub struct InvocationContext {
+    fields: Arc<Mutex<BTreeMap<String, String>>>,
+}
+
+impl InvocationContext {
+    /// Sets a field, replacing any previous value.
+    pub fn set(&self, key: impl Into<String>, value: impl Into<String>) {
+        self.fields.lock().unwrap().insert(key.into(), value.into());
+    }
+
+    /// Returns the fields set so far.
+    pub fn fields(&self) -> BTreeMap<String, String> {
+        self.fields.lock().unwrap().clone()
+    }
+
+    /// Replaces the fields with those of another context, for an instance
+    /// made before the invocation it serves.
+    pub(crate) fn replace_with(&self, other: &Self) {
+        let fields = other.fields();
+        *self.fields.lock().unwrap() = fields;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_fields() {
+        let context = InvocationContext::default();
+        let clone = context.clone();
+        context.set("request_id", "1");
+        assert_eq!(clone.fields()["request_id"], "1");
+
+        let other = InvocationContext::default();
+        other.set("request_id", "2");
+        clone.replace_with(&other);
+        assert_eq!(context.fields()["request_id"], "2");
+    }
+}
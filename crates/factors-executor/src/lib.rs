@@ -1,12 +1,52 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
 
-use anyhow::Context;
-use spin_app::{App, AppComponent};
+use anyhow::{bail, Context};
+use spin_app::{App, AppComponent, MetadataKey};
 use spin_core::{async_trait, Component};
 use spin_factors::{
     AsInstanceState, ConfiguredApp, Factor, HasInstanceBuilder, RuntimeFactors,
     RuntimeFactorsInstanceState,
 };
+use tokio::{sync::OwnedSemaphorePermit, task::AbortHandle};
+
+mod context;
+mod limit;
+mod usage;
+mod warm;
+
+pub use context::InvocationContext;
+pub use limit::{ConcurrencyLimit, Overloaded};
+pub use usage::ComponentUsage;
+use usage::UsageTotals;
+use warm::{WarmPool, DEFAULT_WARM_INSTANCE_IDLE_TIMEOUT};
+
+/// The maximum memory, in bytes, of each instance of a component.
+pub const MEMORY_LIMIT_KEY: MetadataKey<u64> = MetadataKey::new("memory_limit");
+/// The maximum execution time, in milliseconds, of each instance of a component.
+pub const EXECUTION_TIMEOUT_KEY: MetadataKey<u64> = MetadataKey::new("execution_timeout_ms");
+/// The maximum number of concurrent instances of a component.
+pub const INSTANCE_POOL_SIZE_KEY: MetadataKey<u32> = MetadataKey::new("instance_pool_size");
+/// The maximum number of executions which may wait for an instance of a component.
+pub const INSTANCE_POOL_QUEUE_KEY: MetadataKey<u32> = MetadataKey::new("instance_pool_queue");
+/// The maximum time, in milliseconds, an execution may wait for an instance of a component.
+pub const INSTANCE_POOL_TIMEOUT_KEY: MetadataKey<u64> =
+    MetadataKey::new("instance_pool_timeout_ms");
+/// The number of instances of a component to keep ready ahead of requests.
+pub const WARM_INSTANCES_KEY: MetadataKey<u32> = MetadataKey::new("warm_instances");
+/// How long, in milliseconds, warm instances of a component are kept while it is idle.
+pub const WARM_INSTANCE_IDLE_TIMEOUT_KEY: MetadataKey<u64> =
+    MetadataKey::new("warm_instance_idle_timeout_ms");
+
+/// Returns whether the given error is from an instance which was interrupted
+/// at its execution deadline, such as its component's execution timeout.
+pub fn is_execution_timeout(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.downcast_ref::<spin_core::Trap>() == Some(&spin_core::Trap::Interrupt))
+}
 
 /// A FactorsExecutor manages execution of a Spin app.
 ///
@@ -65,18 +105,34 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutor<T, U> {
 
         let components = configured_app.app().components();
         let mut component_instance_pres = HashMap::with_capacity(components.len());
+        let mut component_instance_pools = HashMap::new();
 
         for component in components {
             let instance_pre = component_loader
                 .load_instance_pre(&self.core_engine, &component)
                 .await?;
             component_instance_pres.insert(component.id().to_string(), instance_pre);
+
+            if let Some(pool_size) = component.get_metadata(INSTANCE_POOL_SIZE_KEY)? {
+                let queue = component.get_metadata(INSTANCE_POOL_QUEUE_KEY)?;
+                let timeout = component.get_metadata(INSTANCE_POOL_TIMEOUT_KEY)?;
+                let pool = Arc::new(ConcurrencyLimit::new(
+                    pool_size as usize,
+                    queue.map(|queue| queue as usize),
+                    timeout.map(Duration::from_millis),
+                ));
+                component_instance_pools.insert(component.id().to_string(), pool);
+            }
         }
 
+        let usage = Arc::new(UsageTotals::new(configured_app.app().id()));
         Ok(FactorsExecutorApp {
             executor: self.clone(),
-            configured_app,
+            configured_app: Arc::new(configured_app),
             component_instance_pres,
+            component_instance_pools,
+            warm_instances: OnceLock::new(),
+            usage,
         })
     }
 }
@@ -123,15 +179,40 @@ pub trait ComponentLoader<T: RuntimeFactors, U>: Sync {
 type InstancePre<T, U> =
     spin_core::InstancePre<InstanceState<<T as RuntimeFactors>::InstanceState, U>>;
 
+type Instance<T, U> = (
+    spin_core::Instance,
+    spin_core::Store<InstanceState<<T as RuntimeFactors>::InstanceState, U>>,
+);
+
+/// Configures an instance builder, as [`ExecutorHooks::prepare_instance`]
+/// does, on behalf of the caller of [`FactorsExecutorApp::prepare`].
+pub type ConfigureInstance<T, U> =
+    Arc<dyn Fn(&mut FactorsInstanceBuilder<T, U>) -> anyhow::Result<()> + Send + Sync>;
+
 /// A FactorsExecutorApp represents a loaded Spin app, ready for instantiation.
 ///
 /// It is generic over the executor's [`RuntimeFactors`] and any ad-hoc additional
 /// per-instance state needed by the caller.
 pub struct FactorsExecutorApp<T: RuntimeFactors, U: 'static> {
     executor: Arc<FactorsExecutor<T, U>>,
-    configured_app: ConfiguredApp<T>,
+    configured_app: Arc<ConfiguredApp<T>>,
     // Maps component IDs -> InstancePres
     component_instance_pres: HashMap<String, InstancePre<T, U>>,
+    // Maps component IDs -> permits for concurrent instances, for components
+    // with an instance pool size
+    component_instance_pools: HashMap<String, Arc<ConcurrencyLimit>>,
+    // Set once warm instances have been started
+    warm_instances: OnceLock<WarmInstances<T, U>>,
+    // The resource usage of the app's invocations
+    usage: Arc<UsageTotals>,
+}
+
+struct WarmInstances<T: RuntimeFactors, U: 'static> {
+    configure: ConfigureInstance<T, U>,
+    // Maps component IDs -> warm instances, for components which keep them
+    pools: HashMap<String, Arc<WarmPool<Instance<T, U>>>>,
+    // The tasks which keep the pools full
+    tasks: Vec<AbortHandle>,
 }
 
 impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
@@ -157,39 +238,300 @@ impl<T: RuntimeFactors, U: Send + 'static> FactorsExecutorApp<T, U> {
             .with_context(|| format!("no such component {component_id:?}"))
     }
 
-    /// Returns an instance builder for the given component ID.
-    pub fn prepare(&self, component_id: &str) -> anyhow::Result<FactorsInstanceBuilder<T, U>> {
-        let app_component = self
-            .configured_app
+    /// Records an event for an instance of the given component, run by the
+    /// given trigger, being interrupted by its execution timeout.
+    pub fn record_execution_timeout(&self, trigger_type: &str, component_id: &str) {
+        let timeout_ms = self
             .app()
             .get_component(component_id)
-            .with_context(|| format!("no such component {component_id:?}"))?;
+            .and_then(|component| component.get_metadata(EXECUTION_TIMEOUT_KEY).ok())
+            .flatten();
+        tracing::warn!(
+            trigger_type,
+            app_id = self.app().id(),
+            component_id,
+            timeout_ms,
+            "Component {component_id} exceeded its execution timeout"
+        );
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.execution_timeouts = 1,
+            trigger_type = trigger_type,
+            app_id = self.app().id(),
+            component_id = component_id
+        );
+    }
 
-        let instance_pre = self.component_instance_pres.get(component_id).unwrap();
+    /// Returns the resource usage of the invocations of each component so
+    /// far, by component ID.
+    ///
+    /// An invocation is an instance from [`FactorsInstanceBuilder::instantiate`];
+    /// its usage is recorded when its store is dropped.
+    pub fn resource_usage(&self) -> HashMap<String, ComponentUsage> {
+        self.usage.snapshot()
+    }
 
-        let factor_builders = self
-            .executor
-            .factors
-            .prepare(&self.configured_app, component_id)?;
+    /// Returns an instance builder for the given component ID.
+    pub fn prepare(&self, component_id: &str) -> anyhow::Result<FactorsInstanceBuilder<T, U>> {
+        let instance_pre = self.get_instance_pre(component_id)?;
+        let warm_instances = self.warm_instances.get();
+        let mut builder = prepare_instance(
+            &self.executor,
+            &self.configured_app,
+            instance_pre,
+            warm_instances.map(|warm| &warm.configure),
+            component_id,
+        )?;
+        builder.instance_pool = self.component_instance_pools.get(component_id).cloned();
+        builder.warm_pool = warm_instances.and_then(|warm| warm.pools.get(component_id).cloned());
+        builder.usage = Some(self.usage.clone());
+        Ok(builder)
+    }
 
-        let store_builder = self.executor.core_engine.store_builder();
+    /// Returns a [`ComponentPreparer`] for the given component ID.
+    pub fn component_preparer(
+        &self,
+        component_id: &str,
+    ) -> anyhow::Result<ComponentPreparer<T, U>> {
+        Ok(ComponentPreparer {
+            executor: self.executor.clone(),
+            configured_app: self.configured_app.clone(),
+            instance_pre: self.get_instance_pre(component_id)?.clone(),
+            component_id: component_id.to_owned(),
+        })
+    }
 
-        let mut builder = FactorsInstanceBuilder {
-            store_builder,
-            factor_builders,
-            instance_pre,
-            app_component,
-            factors: &self.executor.factors,
-        };
+    /// Returns an [`AppHandle`] to the app's configuration and resource usage.
+    pub fn handle(&self) -> AppHandle<T> {
+        AppHandle {
+            configured_app: self.configured_app.clone(),
+            usage: self.usage.clone(),
+        }
+    }
+}
+
+/// A handle to a [`FactorsExecutorApp`]'s configuration and resource usage,
+/// for inspecting the app after handing it to a trigger.
+pub struct AppHandle<T: RuntimeFactors> {
+    configured_app: Arc<ConfiguredApp<T>>,
+    usage: Arc<UsageTotals>,
+}
 
-        for hooks in &self.executor.hooks {
-            hooks.prepare_instance(&mut builder)?;
+impl<T: RuntimeFactors> Clone for AppHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            configured_app: self.configured_app.clone(),
+            usage: self.usage.clone(),
         }
+    }
+}
 
-        Ok(builder)
+impl<T: RuntimeFactors> AppHandle<T> {
+    pub fn configured_app(&self) -> &ConfiguredApp<T> {
+        &self.configured_app
+    }
+
+    pub fn app(&self) -> &App {
+        self.configured_app.app()
+    }
+
+    /// Returns the resource usage of the invocations of each component so
+    /// far, by component ID, as [`FactorsExecutorApp::resource_usage`] does.
+    pub fn resource_usage(&self) -> HashMap<String, ComponentUsage> {
+        self.usage.snapshot()
     }
 }
 
+/// Prepares instances of a component apart from its [`FactorsExecutorApp`],
+/// for work which outlives the caller's hold on the app, such as health
+/// checks.
+///
+/// Instances get the executor's hooks and the component's limits, but are
+/// never taken from warm instances, do not wait on the component's instance
+/// pool and are not counted in the app's resource usage.
+pub struct ComponentPreparer<T: RuntimeFactors, U: 'static> {
+    executor: Arc<FactorsExecutor<T, U>>,
+    configured_app: Arc<ConfiguredApp<T>>,
+    instance_pre: InstancePre<T, U>,
+    component_id: String,
+}
+
+impl<T: RuntimeFactors, U: Send + 'static> ComponentPreparer<T, U> {
+    /// The ID of the component.
+    pub fn component_id(&self) -> &str {
+        &self.component_id
+    }
+
+    /// Returns the component's [`spin_core::InstancePre`].
+    pub fn instance_pre(&self) -> &InstancePre<T, U> {
+        &self.instance_pre
+    }
+
+    /// Returns an instance builder for the component.
+    pub fn prepare(&self) -> anyhow::Result<FactorsInstanceBuilder<T, U>> {
+        prepare_instance(
+            &self.executor,
+            &self.configured_app,
+            &self.instance_pre,
+            None,
+            &self.component_id,
+        )
+    }
+}
+
+impl<T: RuntimeFactors, U: Default + Send + 'static> FactorsExecutorApp<T, U> {
+    /// Starts keeping warm instances of the components which set a number of
+    /// warm instances, so that requests need not wait for instantiation.
+    ///
+    /// From now on, `configure` is applied to every instance builder this
+    /// prepares, after any hooks. A builder which is not otherwise configured
+    /// is then instantiated by taking one of the component's warm instances,
+    /// if one is ready.
+    pub fn start_warm_instances(&self, configure: ConfigureInstance<T, U>) -> anyhow::Result<()> {
+        if self.warm_instances.get().is_some() {
+            bail!("warm instances have already been started");
+        }
+        let mut pools = HashMap::new();
+        let mut tasks = Vec::new();
+        for component in self.app().components() {
+            let Some(size) = component.get_metadata(WARM_INSTANCES_KEY)? else {
+                continue;
+            };
+            let idle_timeout = component
+                .get_metadata(WARM_INSTANCE_IDLE_TIMEOUT_KEY)?
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_WARM_INSTANCE_IDLE_TIMEOUT);
+            let pool = Arc::new(WarmPool::new(
+                self.app().id(),
+                component.id(),
+                size as usize,
+                idle_timeout,
+            ));
+            let executor = self.executor.clone();
+            let configured_app = self.configured_app.clone();
+            let instance_pre = self.get_instance_pre(component.id())?.clone();
+            let configure = configure.clone();
+            let component_id = component.id().to_owned();
+            let task = tokio::spawn({
+                let pool = pool.clone();
+                async move {
+                    let (executor, configured_app, instance_pre, configure, component_id) = (
+                        &executor,
+                        &configured_app,
+                        &instance_pre,
+                        &configure,
+                        &component_id,
+                    );
+                    pool.run(|| async move {
+                        prepare_instance(
+                            executor,
+                            configured_app,
+                            instance_pre,
+                            Some(configure),
+                            component_id,
+                        )?
+                        .instantiate(U::default())
+                        .await
+                    })
+                    .await
+                }
+            });
+            tasks.push(task.abort_handle());
+            pools.insert(component.id().to_owned(), pool);
+        }
+        let _ = self.warm_instances.set(WarmInstances {
+            configure,
+            pools,
+            tasks,
+        });
+        Ok(())
+    }
+
+    /// Stops keeping warm instances, and drops those which are ready, such as
+    /// once the app has been replaced by a new version. Instances prepared
+    /// from now on are made afresh.
+    pub fn stop_warm_instances(&self) {
+        if let Some(warm_instances) = self.warm_instances.get() {
+            for task in &warm_instances.tasks {
+                task.abort();
+            }
+            for pool in warm_instances.pools.values() {
+                pool.clear();
+            }
+        }
+    }
+}
+
+impl<T: RuntimeFactors, U: 'static> Drop for FactorsExecutorApp<T, U> {
+    fn drop(&mut self) {
+        if let Some(warm_instances) = self.warm_instances.get() {
+            for task in &warm_instances.tasks {
+                task.abort();
+            }
+        }
+    }
+}
+
+/// Returns an instance builder for the given component, with the executor's
+/// hooks, the given configuration and the component's limits applied.
+fn prepare_instance<'a, T: RuntimeFactors, U: 'static>(
+    executor: &'a FactorsExecutor<T, U>,
+    configured_app: &'a ConfiguredApp<T>,
+    instance_pre: &'a InstancePre<T, U>,
+    configure: Option<&ConfigureInstance<T, U>>,
+    component_id: &str,
+) -> anyhow::Result<FactorsInstanceBuilder<'a, T, U>> {
+    let app_component = configured_app
+        .app()
+        .get_component(component_id)
+        .with_context(|| format!("no such component {component_id:?}"))?;
+
+    let factor_builders = executor.factors.prepare(configured_app, component_id)?;
+
+    let memory_limit = app_component.get_metadata(MEMORY_LIMIT_KEY)?;
+    let execution_timeout = app_component.get_metadata(EXECUTION_TIMEOUT_KEY)?;
+
+    let store_builder = executor.core_engine.store_builder();
+
+    let mut builder = FactorsInstanceBuilder {
+        store_builder,
+        factor_builders,
+        instance_pre,
+        app_component,
+        instance_pool: None,
+        warm_pool: None,
+        usage: None,
+        invocation_context: Default::default(),
+        configured: false,
+        factors: &executor.factors,
+    };
+
+    for hooks in &executor.hooks {
+        hooks.prepare_instance(&mut builder)?;
+    }
+    if let Some(configure) = configure {
+        configure(&mut builder)?;
+    }
+
+    // Component limits apply after hooks, so that they can only tighten
+    // any limits the hooks set
+    if let Some(memory_limit) = memory_limit {
+        let memory_limit = memory_limit
+            .try_into()
+            .context("component memory limit is too large")?;
+        builder.store_builder.limit_memory_size(memory_limit);
+    }
+    if let Some(execution_timeout) = execution_timeout {
+        builder
+            .store_builder
+            .execution_timeout(Duration::from_millis(execution_timeout));
+    }
+
+    // Only configuration by the caller makes the instance differ from warm
+    // instances
+    builder.configured = false;
+    Ok(builder)
+}
+
 /// A FactorsInstanceBuilder manages the instantiation of a Spin component instance.
 ///
 /// It is generic over the executor's [`RuntimeFactors`] and any ad-hoc additional
@@ -199,6 +541,12 @@ pub struct FactorsInstanceBuilder<'a, F: RuntimeFactors, U: 'static> {
     store_builder: spin_core::StoreBuilder,
     factor_builders: F::InstanceBuilders,
     instance_pre: &'a InstancePre<F, U>,
+    instance_pool: Option<Arc<ConcurrencyLimit>>,
+    warm_pool: Option<Arc<WarmPool<Instance<F, U>>>>,
+    usage: Option<Arc<UsageTotals>>,
+    invocation_context: InvocationContext,
+    // Whether the caller has configured the builder since it was prepared
+    configured: bool,
     factors: &'a F,
 }
 
@@ -209,12 +557,20 @@ impl<T: RuntimeFactors, U: 'static> FactorsInstanceBuilder<'_, T, U> {
     }
 
     /// Returns the store builder for the instance.
+    ///
+    /// An instance whose builder is configured is never taken from the
+    /// component's warm instances.
     pub fn store_builder(&mut self) -> &mut spin_core::StoreBuilder {
+        self.configured = true;
         &mut self.store_builder
     }
 
     /// Returns the factor instance builders for the instance.
+    ///
+    /// An instance whose builder is configured is never taken from the
+    /// component's warm instances.
     pub fn factor_builders(&mut self) -> &mut T::InstanceBuilders {
+        self.configured = true;
         &mut self.factor_builders
     }
 
@@ -223,6 +579,15 @@ impl<T: RuntimeFactors, U: 'static> FactorsInstanceBuilder<'_, T, U> {
         self.factor_builders().for_factor::<F>()
     }
 
+    /// Returns the invocation context of the instance.
+    ///
+    /// Unlike other configuration, setting context fields does not keep the
+    /// instance from being taken from the component's warm instances: a warm
+    /// instance adopts the fields set before it is instantiated.
+    pub fn invocation_context(&self) -> &InvocationContext {
+        &self.invocation_context
+    }
+
     /// Returns the underlying wasmtime engine for the instance.
     pub fn wasmtime_engine(&self) -> &spin_core::WasmtimeEngine {
         self.instance_pre.engine()
@@ -235,21 +600,83 @@ impl<T: RuntimeFactors, U: 'static> FactorsInstanceBuilder<'_, T, U> {
 }
 
 impl<T: RuntimeFactors, U: Send> FactorsInstanceBuilder<'_, T, U> {
-    /// Instantiates the instance with the given executor instance state
-    pub async fn instantiate(
-        self,
-        executor_instance_state: U,
-    ) -> anyhow::Result<(
-        spin_core::Instance,
-        spin_core::Store<InstanceState<T::InstanceState, U>>,
-    )> {
-        let instance_state = InstanceState {
-            core: Default::default(),
-            factors: self.factors.build_instance_state(self.factor_builders)?,
-            executor: executor_instance_state,
+    /// Instantiates the instance with the given executor instance state.
+    ///
+    /// If the component has an instance pool size, this waits until fewer
+    /// than that many of its instances exist. It fails with [`Overloaded`]
+    /// if the component's instance pool queue is full, or the wait exceeds
+    /// its instance pool timeout.
+    ///
+    /// If the component keeps warm instances and the builder has not been
+    /// configured since it was prepared, this takes a warm instance if one
+    /// is ready.
+    ///
+    /// The instance's resource usage is recorded when its store is dropped.
+    pub async fn instantiate(self, executor_instance_state: U) -> anyhow::Result<Instance<T, U>> {
+        let app_id = self.app_component.app.id();
+        let component_id = self.app_component.id();
+        let instance_permit = match self.instance_pool {
+            Some(pool) => {
+                let waited = Instant::now();
+                let permit = pool.acquire().await;
+                spin_telemetry::metrics::histogram!(
+                    spin.instance_pool_wait_ms = waited.elapsed().as_secs_f64() * 1000.0,
+                    app_id = app_id,
+                    component_id = component_id
+                );
+                if permit.is_err() {
+                    spin_telemetry::metrics::monotonic_counter!(
+                        spin.instance_pool_rejections = 1,
+                        app_id = app_id,
+                        component_id = component_id
+                    );
+                }
+                Some(permit.with_context(|| {
+                    format!("no instance of component {component_id:?} is available")
+                })?)
+            }
+            None => None,
+        };
+        let warm_instance = match &self.warm_pool {
+            Some(pool) if !self.configured => pool.take(),
+            _ => None,
+        };
+        let (instance, mut store) = match warm_instance {
+            Some((instance, mut store)) => {
+                let state = store.data_mut();
+                state.executor = executor_instance_state;
+                state._instance_permit = instance_permit;
+                state
+                    .invocation_context
+                    .replace_with(&self.invocation_context);
+                store.restart_deadline();
+                (instance, store)
+            }
+            None => {
+                let instance_state = InstanceState {
+                    core: Default::default(),
+                    factors: self.factors.build_instance_state(self.factor_builders)?,
+                    executor: executor_instance_state,
+                    invocation_context: self.invocation_context,
+                    _instance_permit: instance_permit,
+                };
+                let started = Instant::now();
+                let mut store = self.store_builder.build(instance_state)?;
+                let instance = self.instance_pre.instantiate_async(&mut store).await?;
+                spin_telemetry::metrics::histogram!(
+                    spin.instantiation_duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+                    app_id = app_id,
+                    component_id = component_id
+                );
+                (instance, store)
+            }
         };
-        let mut store = self.store_builder.build(instance_state)?;
-        let instance = self.instance_pre.instantiate_async(&mut store).await?;
+        if let Some(usage) = self.usage {
+            let component_id = component_id.to_owned();
+            store.report_usage_on_drop(move |resource_usage| {
+                usage.record(&component_id, resource_usage)
+            });
+        }
         Ok((instance, store))
     }
 }
@@ -262,6 +689,10 @@ pub struct InstanceState<T, U> {
     core: spin_core::State,
     factors: T,
     executor: U,
+    invocation_context: InvocationContext,
+    // Held for the lifetime of the instance, for components with an instance
+    // pool size
+    _instance_permit: Option<OwnedSemaphorePermit>,
 }
 
 impl<T, U> InstanceState<T, U> {
@@ -285,6 +716,12 @@ impl<T, U> InstanceState<T, U> {
         &mut self.factors
     }
 
+    /// Provides access to the invocation context, for fields set after
+    /// instantiation.
+    pub fn invocation_context(&self) -> &InvocationContext {
+        &self.invocation_context
+    }
+
     /// Provides access to the ad-hoc executor instance state.
     pub fn executor_instance_state(&self) -> &U {
         &self.executor
@@ -312,7 +749,7 @@ impl<T: RuntimeFactorsInstanceState, U> AsInstanceState<T> for InstanceState<T,
 mod tests {
     use spin_factor_wasi::{DummyFilesMounter, WasiFactor};
     use spin_factors::RuntimeFactors;
-    use spin_factors_test::TestEnvironment;
+    use spin_factors_test::{toml, TestEnvironment};
 
     use super::*;
 
@@ -352,6 +789,182 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn resource_usage_is_recorded_per_invocation() -> anyhow::Result<()> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors);
+        let locked = env.build_locked_app().await?;
+        let app = App::new("test-app", locked);
+
+        let mut config = spin_core::Config::default();
+        config.enable_fuel_metering();
+        let engine_builder = spin_core::Engine::builder(&config)?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+        let factors_app = executor
+            .load_app(app, Default::default(), &DummyComponentLoader)
+            .await?;
+
+        let (_instance, store) = factors_app.prepare("empty")?.instantiate(()).await?;
+        assert!(factors_app.resource_usage().is_empty());
+
+        drop(store);
+        let usage = &factors_app.resource_usage()["empty"];
+        assert_eq!(usage.invocations, 1);
+        assert!(usage.fuel_consumed.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn instance_pool_size_limits_concurrent_instances() -> anyhow::Result<()> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors).extend_manifest(toml! {
+            [component.empty]
+            source = "does-not-exist.wasm"
+            instance_pool_size = 1
+        });
+        let locked = env.build_locked_app().await?;
+        let app = App::new("test-app", locked);
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+        let factors_app = executor
+            .load_app(app, Default::default(), &DummyComponentLoader)
+            .await?;
+
+        let first = factors_app.prepare("empty")?.instantiate(()).await?;
+        let second = factors_app.prepare("empty")?.instantiate(());
+        tokio::pin!(second);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut second)
+                .await
+                .is_err(),
+            "second instance should wait for the first to be dropped"
+        );
+
+        drop(first);
+        second.await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn instance_pool_queue_refuses_excess_instances() -> anyhow::Result<()> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors).extend_manifest(toml! {
+            [component.empty]
+            source = "does-not-exist.wasm"
+            instance_pool_size = 1
+            instance_pool_queue = 0
+        });
+        let locked = env.build_locked_app().await?;
+        let app = App::new("test-app", locked);
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+        let factors_app = executor
+            .load_app(app, Default::default(), &DummyComponentLoader)
+            .await?;
+
+        let _first = factors_app.prepare("empty")?.instantiate(()).await?;
+        let Err(err) = factors_app.prepare("empty")?.instantiate(()).await else {
+            panic!("second instance should be refused");
+        };
+        assert_eq!(
+            err.downcast_ref::<Overloaded>(),
+            Some(&Overloaded::QueueFull)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn warm_instances_are_taken_unless_configured() -> anyhow::Result<()> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors).extend_manifest(toml! {
+            [component.empty]
+            source = "does-not-exist.wasm"
+            warm_instances = 1
+        });
+        let locked = env.build_locked_app().await?;
+        let app = App::new("test-app", locked);
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+        let factors_app = executor
+            .load_app(app, Default::default(), &DummyComponentLoader)
+            .await?;
+        factors_app.start_warm_instances(Arc::new(|_| Ok(())))?;
+
+        let pool = factors_app.warm_instances.get().unwrap().pools["empty"].clone();
+        while pool.len() < 1 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        // The pool is not refilled meanwhile, since the test runtime has a
+        // single thread and taking a warm instance does not yield
+        let mut configured = factors_app.prepare("empty")?;
+        configured.store_builder().max_memory_size(1_000_000);
+        configured.instantiate(()).await?;
+        assert_eq!(
+            pool.len(),
+            1,
+            "a configured builder should not take a warm instance"
+        );
+
+        factors_app.prepare("empty")?.instantiate(()).await?;
+        assert_eq!(pool.len(), 0, "the warm instance should be taken");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stopped_warm_instances_are_dropped() -> anyhow::Result<()> {
+        let factors = TestFactors {
+            wasi: WasiFactor::new(DummyFilesMounter),
+        };
+        let env = TestEnvironment::new(factors).extend_manifest(toml! {
+            [component.empty]
+            source = "does-not-exist.wasm"
+            warm_instances = 2
+        });
+        let locked = env.build_locked_app().await?;
+        let app = App::new("test-app", locked);
+
+        let engine_builder = spin_core::Engine::builder(&Default::default())?;
+        let executor = Arc::new(FactorsExecutor::new(engine_builder, env.factors)?);
+        let factors_app = executor
+            .load_app(app, Default::default(), &DummyComponentLoader)
+            .await?;
+        factors_app.start_warm_instances(Arc::new(|_| Ok(())))?;
+
+        let pool = factors_app.warm_instances.get().unwrap().pools["empty"].clone();
+        while pool.len() < 2 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        factors_app.stop_warm_instances();
+        assert_eq!(pool.len(), 0);
+        factors_app.prepare("empty")?.instantiate(()).await?;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(pool.len(), 0, "a stopped pool should not be refilled");
+        Ok(())
+    }
+
+    #[test]
+    fn execution_timeouts_are_recognized() {
+        let timeout = anyhow::Error::new(spin_core::Trap::Interrupt).context("handler failed");
+        assert!(is_execution_timeout(&timeout));
+
+        let trap = anyhow::Error::new(spin_core::Trap::UnreachableCodeReached);
+        assert!(!is_execution_timeout(&trap));
+        assert!(!is_execution_timeout(&anyhow::anyhow!("handler failed")));
+    }
+
     struct DummyComponentLoader;
 
     #[async_trait]
@@ -0,0 +1,128 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// A limit on how many executions may run at once.
+///
+/// Executions beyond the limit wait for a running one to finish. If the wait
+/// queue is bounded, executions which find it full are refused at once, and
+/// if the wait is bounded, executions which wait too long are refused then;
+/// either way they fail with [`Overloaded`].
+#[derive(Debug)]
+pub struct ConcurrencyLimit {
+    permits: Arc<Semaphore>,
+    waiting: AtomicUsize,
+    max_waiting: Option<usize>,
+    wait_timeout: Option<Duration>,
+}
+
+impl ConcurrencyLimit {
+    /// Creates a limit of `max_running` concurrent executions, of which at
+    /// most `max_waiting` may wait, each for at most `wait_timeout`. If these
+    /// are not set, any number may wait indefinitely.
+    pub fn new(
+        max_running: usize,
+        max_waiting: Option<usize>,
+        wait_timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_running)),
+            waiting: AtomicUsize::new(0),
+            max_waiting,
+            wait_timeout,
+        }
+    }
+
+    /// Waits until an execution may run. It counts as running until the
+    /// returned permit is dropped.
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, Overloaded> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        let waiting = self.waiting.fetch_add(1, Ordering::AcqRel);
+        // Counts this execution as waiting until it stops, including if the
+        // caller stops waiting
+        let _waiter = Waiter(&self.waiting);
+        if self.max_waiting.is_some_and(|max| waiting >= max) {
+            return Err(Overloaded::QueueFull);
+        }
+
+        let acquire = self.permits.clone().acquire_owned();
+        let permit = match self.wait_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire)
+                .await
+                .map_err(|_| Overloaded::TimedOut)?,
+            None => acquire.await,
+        };
+        Ok(permit.expect("the semaphore is never closed"))
+    }
+}
+
+struct Waiter<'a>(&'a AtomicUsize);
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// The error when a [`ConcurrencyLimit`] refuses an execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overloaded {
+    /// As many executions as may wait were already waiting.
+    QueueFull,
+    /// The execution waited too long for a running one to finish.
+    TimedOut,
+}
+
+impl Display for Overloaded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::QueueFull => f.write_str("too many executions are already waiting to run"),
+            Self::TimedOut => f.write_str("timed out waiting for a running execution to finish"),
+        }
+    }
+}
+
+impl std::error::Error for Overloaded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn full_queue_is_refused() {
+        let limit = Arc::new(ConcurrencyLimit::new(1, Some(1), None));
+        let running = limit.acquire().await.unwrap();
+
+        let waiting = tokio::spawn({
+            let limit = limit.clone();
+            async move { limit.acquire().await.map(drop) }
+        });
+        while limit.waiting.load(Ordering::Acquire) == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(limit.acquire().await.unwrap_err(), Overloaded::QueueFull);
+
+        drop(running);
+        waiting.await.unwrap().unwrap();
+        assert_eq!(limit.waiting.load(Ordering::Acquire), 0);
+        let _running = limit.acquire().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn long_wait_is_refused() {
+        let limit = ConcurrencyLimit::new(1, None, Some(Duration::from_millis(10)));
+        let _running = limit.acquire().await.unwrap();
+        assert_eq!(limit.acquire().await.unwrap_err(), Overloaded::TimedOut);
+        assert_eq!(limit.waiting.load(Ordering::Acquire), 0);
+    }
+}
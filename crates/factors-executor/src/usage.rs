@@ -0,0 +1,100 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use spin_core::ResourceUsage;
+
+/// The resources used by the invocations of a component so far.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComponentUsage {
+    /// The number of invocations.
+    pub invocations: u64,
+    /// The total fuel consumed, if fuel metering is enabled.
+    pub fuel_consumed: Option<u64>,
+    /// The highest peak memory, in bytes, of any invocation.
+    pub peak_memory: u64,
+}
+
+/// Records the resource usage of each invocation, and totals it by component.
+pub(crate) struct UsageTotals {
+    app_id: String,
+    components: Mutex<HashMap<String, ComponentUsage>>,
+}
+
+impl UsageTotals {
+    pub fn new(app_id: &str) -> Self {
+        Self {
+            app_id: app_id.to_owned(),
+            components: Default::default(),
+        }
+    }
+
+    pub fn record(&self, component_id: &str, usage: ResourceUsage) {
+        tracing::info!(
+            app_id = self.app_id,
+            component_id,
+            fuel_consumed = usage.fuel_consumed,
+            peak_memory = usage.peak_memory,
+            "Component {component_id} invocation finished"
+        );
+        if let Some(fuel) = usage.fuel_consumed {
+            // Fuel starts at i64::MAX, so what is consumed fits
+            spin_telemetry::metrics::histogram!(
+                spin.invocation_fuel_consumed = fuel as i64,
+                app_id = self.app_id,
+                component_id = component_id
+            );
+        }
+        spin_telemetry::metrics::histogram!(
+            spin.invocation_peak_memory = usage.peak_memory as i64,
+            app_id = self.app_id,
+            component_id = component_id
+        );
+
+        let mut components = self.components.lock().unwrap();
+        let totals = components.entry(component_id.to_owned()).or_default();
+        totals.invocations += 1;
+        if let Some(fuel) = usage.fuel_consumed {
+            let total = totals.fuel_consumed.get_or_insert(0);
+            *total = total.saturating_add(fuel);
+        }
+        totals.peak_memory = totals.peak_memory.max(usage.peak_memory);
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, ComponentUsage> {
+        self.components.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_is_totalled_by_component() {
+        let totals = UsageTotals::new("app");
+        let usage = |fuel_consumed, peak_memory| ResourceUsage {
+            fuel_consumed,
+            peak_memory,
+        };
+        totals.record("a", usage(Some(10), 100));
+        totals.record("a", usage(Some(5), 300));
+        totals.record("b", usage(None, 200));
+
+        let snapshot = totals.snapshot();
+        assert_eq!(
+            snapshot["a"],
+            ComponentUsage {
+                invocations: 2,
+                fuel_consumed: Some(15),
+                peak_memory: 300,
+            }
+        );
+        assert_eq!(
+            snapshot["b"],
+            ComponentUsage {
+                invocations: 1,
+                fuel_consumed: None,
+                peak_memory: 200,
+            }
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::{future::Future, sync::Mutex, time::Duration};
+
+use tokio::{sync::Notify, time::Instant};
+
+/// How long warm instances are kept while their component receives no
+/// requests, if the component does not set it.
+pub(crate) const DEFAULT_WARM_INSTANCE_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Instances of a component made ahead of the requests which use them.
+///
+/// A task runs [`WarmPool::run`] to keep the pool full. If no instance is
+/// taken for the idle timeout, the pool's instances are evicted, and the pool
+/// is refilled only once an instance is next wanted.
+pub(crate) struct WarmPool<I> {
+    app_id: String,
+    component_id: String,
+    size: usize,
+    idle_timeout: Duration,
+    state: Mutex<PoolState<I>>,
+    wanted: Notify,
+}
+
+struct PoolState<I> {
+    instances: Vec<I>,
+    last_wanted: Instant,
+}
+
+impl<I: Send> WarmPool<I> {
+    pub fn new(app_id: &str, component_id: &str, size: usize, idle_timeout: Duration) -> Self {
+        Self {
+            app_id: app_id.to_owned(),
+            component_id: component_id.to_owned(),
+            size,
+            idle_timeout,
+            state: Mutex::new(PoolState {
+                instances: Vec::with_capacity(size),
+                last_wanted: Instant::now(),
+            }),
+            wanted: Notify::new(),
+        }
+    }
+
+    /// Takes a warm instance, if one is ready, and has the pool replace it.
+    pub fn take(&self) -> Option<I> {
+        let instance = {
+            let mut state = self.state.lock().unwrap();
+            state.last_wanted = Instant::now();
+            state.instances.pop()
+        };
+        self.wanted.notify_one();
+
+        if instance.is_some() {
+            spin_telemetry::metrics::monotonic_counter!(
+                spin.warm_instance_hits = 1,
+                app_id = self.app_id,
+                component_id = self.component_id
+            );
+            spin_telemetry::metrics::counter!(
+                spin.warm_instances = -1,
+                app_id = self.app_id,
+                component_id = self.component_id
+            );
+        } else {
+            spin_telemetry::metrics::monotonic_counter!(
+                spin.warm_instance_misses = 1,
+                app_id = self.app_id,
+                component_id = self.component_id
+            );
+        }
+        instance
+    }
+
+    /// Keeps the pool full with instances from `make_instance`, and evicts
+    /// them once they go unused for the idle timeout. This never returns.
+    pub async fn run<F, Fut>(&self, make_instance: F)
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = anyhow::Result<I>>,
+    {
+        loop {
+            while self.len() < self.size {
+                match make_instance().await {
+                    Ok(instance) => {
+                        self.state.lock().unwrap().instances.push(instance);
+                        spin_telemetry::metrics::counter!(
+                            spin.warm_instances = 1,
+                            app_id = self.app_id,
+                            component_id = self.component_id
+                        );
+                    }
+                    Err(err) => {
+                        // Retrying at once would likely fail the same way, so
+                        // wait until an instance is next wanted
+                        tracing::warn!(
+                            "Failed to make a warm instance of component {}: {err:?}",
+                            self.component_id
+                        );
+                        break;
+                    }
+                }
+            }
+
+            let idle_deadline = self.state.lock().unwrap().last_wanted + self.idle_timeout;
+            tokio::select! {
+                _ = self.wanted.notified() => continue,
+                _ = tokio::time::sleep_until(idle_deadline) => {}
+            }
+
+            // An instance may have been wanted just as the timeout elapsed
+            if self.state.lock().unwrap().last_wanted + self.idle_timeout > Instant::now() {
+                continue;
+            }
+            let evicted = std::mem::take(&mut self.state.lock().unwrap().instances);
+            if !evicted.is_empty() {
+                tracing::debug!(
+                    "Evicting {} idle warm instances of component {}",
+                    evicted.len(),
+                    self.component_id
+                );
+                spin_telemetry::metrics::monotonic_counter!(
+                    spin.warm_instance_evictions = evicted.len() as i64,
+                    app_id = self.app_id,
+                    component_id = self.component_id
+                );
+                spin_telemetry::metrics::counter!(
+                    spin.warm_instances = -(evicted.len() as i64),
+                    app_id = self.app_id,
+                    component_id = self.component_id
+                );
+            }
+            drop(evicted);
+            self.wanted.notified().await;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().instances.len()
+    }
+
+    /// Drops the pool's ready instances.
+    pub fn clear(&self) {
+        let cleared = std::mem::take(&mut self.state.lock().unwrap().instances);
+        if !cleared.is_empty() {
+            spin_telemetry::metrics::counter!(
+                spin.warm_instances = -(cleared.len() as i64),
+                app_id = self.app_id,
+                component_id = self.component_id
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    fn spawn_pool(size: usize, idle_timeout: Duration) -> (Arc<WarmPool<u32>>, Arc<AtomicU32>) {
+        let pool = Arc::new(WarmPool::new("app", "component", size, idle_timeout));
+        let made = Arc::new(AtomicU32::new(0));
+        tokio::spawn({
+            let pool = pool.clone();
+            let made = made.clone();
+            async move {
+                pool.run(|| async { Ok(made.fetch_add(1, Ordering::SeqCst)) })
+                    .await
+            }
+        });
+        (pool, made)
+    }
+
+    async fn wait_for_len(pool: &WarmPool<u32>, len: usize) {
+        while pool.len() != len {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn taken_instances_are_replaced() {
+        let (pool, made) = spawn_pool(2, Duration::from_secs(60));
+        wait_for_len(&pool, 2).await;
+
+        assert!(pool.take().is_some());
+        wait_for_len(&pool, 2).await;
+        assert_eq!(made.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn idle_instances_are_evicted_until_wanted() {
+        let (pool, made) = spawn_pool(2, Duration::from_millis(50));
+        wait_for_len(&pool, 2).await;
+        wait_for_len(&pool, 0).await;
+
+        assert!(pool.take().is_none());
+        wait_for_len(&pool, 2).await;
+        assert_eq!(made.load(Ordering::SeqCst), 4);
+    }
+}
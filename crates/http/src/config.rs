@@ -1,5 +1,8 @@
+use std::{num::NonZeroU32, path::PathBuf};
+
 use serde::{Deserialize, Serialize};
 use spin_http_routes::HttpTriggerRouteConfig;
+use spin_serde::HumanDuration;
 
 /// Configuration for the HTTP trigger
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -12,6 +15,283 @@ pub struct HttpTriggerConfig {
     /// The HTTP executor the component requires
     #[serde(default)]
     pub executor: Option<HttpExecutorType>,
+    /// How long a streamed response may go without sending data before it is
+    /// abandoned. If not set, a response may stay open indefinitely.
+    #[serde(default)]
+    pub response_idle_timeout: Option<HumanDuration>,
+    /// The maximum number of requests to the route which may run at once.
+    /// Further requests wait until one finishes.
+    #[serde(default)]
+    pub max_concurrent_requests: Option<NonZeroU32>,
+    /// The maximum number of requests which may wait to run. Further requests
+    /// are refused. If not set, any number of requests may wait.
+    #[serde(default)]
+    pub max_queued_requests: Option<u32>,
+    /// How long a request may wait to run before it is refused. If not set, a
+    /// request may wait indefinitely.
+    #[serde(default)]
+    pub queue_timeout: Option<HumanDuration>,
+    /// The status of the response to a request refused because the route, or
+    /// the component's instance pool, is at capacity: 429 or 503. If not set,
+    /// it is 503.
+    #[serde(default)]
+    pub overload_status: Option<u16>,
+    /// The largest request body the route accepts, in bytes. Larger requests
+    /// are refused with 413. If not set, a request body may be any size.
+    #[serde(default, with = "spin_serde::bytes::option")]
+    pub max_request_body_size: Option<u64>,
+    /// The largest response body the component may send, in bytes. A larger
+    /// response is cut off. If not set, a response body may be any size.
+    #[serde(default, with = "spin_serde::bytes::option")]
+    pub max_response_body_size: Option<u64>,
+    /// The largest total size of a request's headers, in bytes. Requests with
+    /// larger headers are refused with 431. If not set, only the server's own
+    /// limit applies.
+    #[serde(default, with = "spin_serde::bytes::option")]
+    pub max_header_size: Option<u64>,
+    /// The request methods the route accepts. Requests with other methods are
+    /// refused with 405. If not set, any method is accepted.
+    #[serde(default)]
+    pub allowed_methods: Option<Vec<String>>,
+    /// Whether the route's responses are cached, and for how long. If not
+    /// set, they are cached if the application enables the response cache.
+    #[serde(default)]
+    pub cache: Option<RouteCacheConfig>,
+    /// The client IP addresses, or CIDR ranges of them, whose requests the
+    /// route accepts. Requests from other clients are refused with 403. If
+    /// not set, requests from any client are accepted.
+    #[serde(default)]
+    pub allowed_ips: Option<Vec<String>>,
+    /// The client IP addresses, or CIDR ranges of them, whose requests are
+    /// refused with 403, even if they are in `allowed_ips`.
+    #[serde(default)]
+    pub denied_ips: Option<Vec<String>>,
+    /// A limit on the rate of each client's requests to the route. Requests
+    /// over the limit are refused with 429.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// The route's cross-origin resource sharing (CORS) policy. Preflight
+    /// requests are answered without invoking the component. If not set,
+    /// the component handles CORS itself.
+    #[serde(default)]
+    pub cors: Option<CorsConfig>,
+    /// The route's authentication: requests must bear a JSON Web Token
+    /// (JWT) signed by one of the given keys. Other requests are refused
+    /// with 401 without invoking the component.
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+}
+
+/// The JSON Web Tokens (JWTs) a route accepts as authentication.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    /// The URL of a JSON Web Key Set (JWKS) of the keys which sign tokens,
+    /// such as an OpenID Connect provider's `jwks_uri`.
+    #[serde(default)]
+    pub jwks_url: Option<String>,
+    /// Keys which sign tokens, as JSON Web Keys (JWKs).
+    #[serde(default)]
+    pub keys: Vec<Jwk>,
+    /// The issuer tokens must have in their `iss` claim.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// The audiences, one of which tokens must have in their `aud` claim.
+    /// May be a single audience or a list of them.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub audience: Vec<String>,
+    /// The claim holding a token's roles, as a list or a space-separated
+    /// string, such as `roles` or `realm_access.roles`.
+    #[serde(default)]
+    pub roles_claim: Option<String>,
+    /// The roles, one of which tokens must have. Tokens without any of them
+    /// are refused with 403.
+    #[serde(default)]
+    pub required_roles: Vec<String>,
+    /// How far a token's expiry and not-before times may be passed, to allow
+    /// for clock skew. If not set, it is one minute.
+    #[serde(default)]
+    pub leeway: Option<HumanDuration>,
+}
+
+/// A JSON Web Key (JWK), as defined by RFC 7517.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Jwk {
+    /// The key type: `RSA`, `EC`, `OKP` or `oct`.
+    pub kty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// The algorithm the key is used with, such as `RS256`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    /// The curve of an `EC` or `OKP` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// The modulus of an `RSA` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// The exponent of an `RSA` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    /// The x coordinate of an `EC` key, or the public key of an `OKP` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    /// The y coordinate of an `EC` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+    /// The secret of an `oct` (HMAC) key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k: Option<String>,
+}
+
+/// A route's cross-origin resource sharing (CORS) policy.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsConfig {
+    /// The origins allowed to make cross-origin requests, such as
+    /// `https://example.com`, or `*` for any origin.
+    pub allowed_origins: Vec<String>,
+    /// The methods cross-origin requests may use. If not set, they may use
+    /// `GET`, `HEAD` and `POST`.
+    #[serde(default)]
+    pub allowed_methods: Option<Vec<String>>,
+    /// The request headers cross-origin requests may send, or `*` for any.
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    /// The response headers which scripts making cross-origin requests may
+    /// read, beyond the CORS-safelisted ones.
+    #[serde(default)]
+    pub exposed_headers: Vec<String>,
+    /// How long browsers may cache the answer to a preflight request. If not
+    /// set, each browser's default applies.
+    #[serde(default)]
+    pub max_age: Option<HumanDuration>,
+    /// Whether cross-origin requests may include credentials such as
+    /// cookies. An `*` origin may not be combined with credentials.
+    #[serde(default)]
+    pub allow_credentials: bool,
+}
+
+/// A token bucket limit on the rate of each client's requests to a route.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// The number of requests a client may make in each `per`.
+    pub requests: NonZeroU32,
+    /// The period over which a client may make `requests`.
+    pub per: HumanDuration,
+    /// The most requests a client may make at once, after making none for a
+    /// while. If not set, it is `requests`.
+    #[serde(default)]
+    pub burst: Option<NonZeroU32>,
+    /// What identifies a client: `"ip"`, its IP address, or `"header:<name>"`,
+    /// the value of a request header such as an API key. Requests without
+    /// the header are identified by IP address. If not set, it is `"ip"`.
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+/// Whether a route's responses are cached, and for how long.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum RouteCacheConfig {
+    /// `cache = true` caches responses for as long as their `Cache-Control`
+    /// header allows; `cache = false` never caches them.
+    Enabled(bool),
+    /// `cache = { ttl = "5m" }` caches responses for the given time, in
+    /// place of any lifetime in their `Cache-Control` header.
+    Options(RouteCacheOptions),
+}
+
+/// Options for caching a route's responses.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteCacheOptions {
+    /// How long a response is cached for.
+    pub ttl: HumanDuration,
+}
+
+/// Configuration for all of an application's HTTP triggers, from its
+/// `[application.trigger.http]` table.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpAppTriggerConfig {
+    /// The base path of all routes. Deprecated: it may only be `/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Routes which serve files from a directory on the host, without
+    /// invoking a component. May be a single table or a list of them.
+    #[serde(
+        default,
+        rename = "static",
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub static_routes: Vec<StaticRouteConfig>,
+    /// The response cache, which applies to every route unless the route
+    /// sets `cache = false`. If not set, only the responses of routes which
+    /// set `cache` are cached, in memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<ResponseCacheConfig>,
+    /// The label of the key-value store in which routes' rate limits are
+    /// counted, so that every process serving the application shares them.
+    /// If not set, each process counts them in memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_store: Option<String>,
+    /// The IP addresses or CIDR ranges of the proxies in front of the
+    /// server. A request from one of them is taken to be from the client
+    /// its `Forwarded` or `X-Forwarded-For` header names, for route IP
+    /// filters and rate limits and the client IP given to components.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_proxies: Vec<String>,
+}
+
+/// Configuration for the cache of HTTP responses.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResponseCacheConfig {
+    /// The label of the key-value store in which responses are cached. If
+    /// not set, responses are cached in memory.
+    #[serde(default)]
+    pub key_value_store: Option<String>,
+    /// The most responses cached in memory. If not set, it is 1000.
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+    /// The largest response body which is cached, in bytes. If not set, it
+    /// is 1 MiB.
+    #[serde(default, with = "spin_serde::bytes::option")]
+    pub max_entry_size: Option<u64>,
+}
+
+/// A route which serves files from a directory on the host.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StaticRouteConfig {
+    /// The wildcard route under which the files are served, such as
+    /// `/assets/...`.
+    pub route: String,
+    /// The directory of the files, relative to the application's directory.
+    pub dir: PathBuf,
+    /// The `Cache-Control` header of the responses. If not set, none is sent.
+    #[serde(default)]
+    pub cache_control: Option<String>,
+}
+
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
 }
 
 /// The executor for the HTTP component.
@@ -78,4 +358,203 @@ mod tests {
         assert_eq!(config.entrypoint, "_start");
         assert_eq!(config.argv, "${SCRIPT_NAME} ${ARGS}");
     }
+
+    #[test]
+    fn response_idle_timeout_is_parsed() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "events"
+            route = "/events"
+            response_idle_timeout = "30s"
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(
+            config.response_idle_timeout.unwrap().duration(),
+            std::time::Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn concurrency_limits_are_parsed() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "api"
+            route = "/api/..."
+            max_concurrent_requests = 10
+            max_queued_requests = 0
+            queue_timeout = "5s"
+            overload_status = 429
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(config.max_concurrent_requests.unwrap().get(), 10);
+        assert_eq!(config.max_queued_requests, Some(0));
+        assert_eq!(
+            config.queue_timeout.unwrap().duration(),
+            std::time::Duration::from_secs(5)
+        );
+        assert_eq!(config.overload_status, Some(429));
+    }
+
+    #[test]
+    fn request_policies_are_parsed() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "upload"
+            route = "/upload"
+            max_request_body_size = "1MiB"
+            max_response_body_size = 4096
+            max_header_size = "8KB"
+            allowed_methods = ["POST", "PUT"]
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(config.max_request_body_size, Some(1 << 20));
+        assert_eq!(config.max_response_body_size, Some(4096));
+        assert_eq!(config.max_header_size, Some(8000));
+        assert_eq!(
+            config.allowed_methods.unwrap(),
+            vec!["POST".to_owned(), "PUT".to_owned()]
+        );
+    }
+
+    #[test]
+    fn static_routes_are_parsed() {
+        let config: HttpAppTriggerConfig = toml::toml! {
+            static = { route = "/assets/...", dir = "public", cache_control = "max-age=3600" }
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(config.static_routes.len(), 1);
+        assert_eq!(config.static_routes[0].route, "/assets/...");
+        assert_eq!(
+            config.static_routes[0].cache_control.as_deref(),
+            Some("max-age=3600")
+        );
+
+        let config: HttpAppTriggerConfig = toml::toml! {
+            base = "/"
+            static = [
+                { route = "/assets/...", dir = "public" },
+                { route = "/docs/...", dir = "site/docs" },
+            ]
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(config.base.as_deref(), Some("/"));
+        assert_eq!(config.static_routes[1].dir, PathBuf::from("site/docs"));
+    }
+
+    #[test]
+    fn response_cache_is_parsed() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "api"
+            route = "/api/..."
+            cache = { ttl = "5m" }
+        }
+        .try_into()
+        .unwrap();
+        let Some(RouteCacheConfig::Options(options)) = config.cache else {
+            panic!("wrong cache config");
+        };
+        assert_eq!(options.ttl.duration(), std::time::Duration::from_secs(300));
+
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "api"
+            route = "/api/..."
+            cache = false
+        }
+        .try_into()
+        .unwrap();
+        assert!(matches!(
+            config.cache,
+            Some(RouteCacheConfig::Enabled(false))
+        ));
+
+        let config: HttpAppTriggerConfig = toml::toml! {
+            cache = { key_value_store = "cache", max_entry_size = "64KiB" }
+        }
+        .try_into()
+        .unwrap();
+        let cache = config.cache.unwrap();
+        assert_eq!(cache.key_value_store.as_deref(), Some("cache"));
+        assert_eq!(cache.max_entry_size, Some(64 << 10));
+    }
+
+    #[test]
+    fn access_controls_are_parsed() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "api"
+            route = "/api/..."
+            allowed_ips = ["10.0.0.0/8", "192.168.1.20"]
+            denied_ips = ["10.1.0.0/16"]
+            rate_limit = { requests = 100, per = "1m", burst = 20, key = "header:x-api-key" }
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(config.allowed_ips.unwrap().len(), 2);
+        assert_eq!(config.denied_ips.unwrap(), vec!["10.1.0.0/16".to_owned()]);
+        let rate_limit = config.rate_limit.unwrap();
+        assert_eq!(rate_limit.requests.get(), 100);
+        assert_eq!(
+            rate_limit.per.duration(),
+            std::time::Duration::from_secs(60)
+        );
+        assert_eq!(rate_limit.burst.unwrap().get(), 20);
+        assert_eq!(rate_limit.key.as_deref(), Some("header:x-api-key"));
+
+        let config: HttpAppTriggerConfig = toml::toml! {
+            rate_limit_store = "limits"
+            trusted_proxies = ["10.0.0.0/8"]
+        }
+        .try_into()
+        .unwrap();
+        assert_eq!(config.rate_limit_store.as_deref(), Some("limits"));
+        assert_eq!(config.trusted_proxies, vec!["10.0.0.0/8".to_owned()]);
+    }
+
+    #[test]
+    fn cors_is_parsed() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "api"
+            route = "/api/..."
+            cors = { allowed_origins = ["https://example.com"], allowed_headers = ["*"], max_age = "1h", allow_credentials = true }
+        }
+        .try_into()
+        .unwrap();
+        let cors = config.cors.unwrap();
+        assert_eq!(cors.allowed_origins, vec!["https://example.com".to_owned()]);
+        assert!(cors.allowed_methods.is_none());
+        assert_eq!(cors.allowed_headers, vec!["*".to_owned()]);
+        assert!(cors.exposed_headers.is_empty());
+        assert_eq!(
+            cors.max_age.unwrap().duration(),
+            std::time::Duration::from_secs(3600)
+        );
+        assert!(cors.allow_credentials);
+    }
+
+    #[test]
+    fn auth_is_parsed() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "api"
+            route = "/api/..."
+            [auth]
+            jwks_url = "https://auth.example.com/.well-known/jwks.json"
+            issuer = "https://auth.example.com/"
+            audience = "api"
+            roles_claim = "realm_access.roles"
+            required_roles = ["admin"]
+            [[auth.keys]]
+            kty = "oct"
+            kid = "local"
+            k = "c2VjcmV0"
+        }
+        .try_into()
+        .unwrap();
+        let auth = config.auth.unwrap();
+        assert_eq!(auth.audience, vec!["api".to_owned()]);
+        assert_eq!(auth.keys[0].kty, "oct");
+        assert_eq!(auth.keys[0].k.as_deref(), Some("c2VjcmV0"));
+        assert_eq!(auth.roles_claim.as_deref(), Some("realm_access.roles"));
+        assert!(auth.leeway.is_none());
+    }
 }
@@ -2,6 +2,7 @@ use core::str;
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 
 use anyhow::Result;
@@ -154,6 +155,8 @@ enum CasState {
     Unversioned(Blob),
     // Item was missing when fetched during `current`, expected to be new
     Unset,
+    // Item had expired when fetched during `current`, but not yet been deleted
+    Expired(String),
     // Potentially new item -- `current` was never called to fetch version
     Unknown,
 }
@@ -172,6 +175,26 @@ const PK: &str = "PK";
 const VAL: &str = "VAL";
 /// Version key in DynamoDB items used for atomic operations
 const VER: &str = "VER";
+/// Expiry key in DynamoDB items, as Unix seconds. This should be configured as the table's TTL
+/// attribute, so that DynamoDB deletes expired items.
+///
+/// `TTL` is a DynamoDB reserved word, so expressions must refer to it as `#TTL`.
+const TTL: &str = "TTL";
+
+/// Returns the item's expiry if it has passed. DynamoDB deletes expired items only some time
+/// after they expire, so reads must skip them.
+fn expired_ttl(item: &HashMap<String, AttributeValue>) -> Option<&String> {
+    let Some(AttributeValue::N(ttl)) = item.get(TTL) else {
+        return None;
+    };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    ttl.parse::<u64>()
+        .is_ok_and(|ttl| ttl <= now)
+        .then_some(ttl)
+}
 
 #[async_trait]
 impl Store for AwsDynamoStore {
@@ -185,12 +208,17 @@ impl Store for AwsDynamoStore {
                 PK,
                 aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
             )
-            .projection_expression(VAL)
+            .projection_expression("#VAL, #TTL")
+            .expression_attribute_names("#VAL", VAL)
+            .expression_attribute_names("#TTL", TTL)
             .send()
             .await
             .map_err(log_error)?;
 
         let item = response.item.and_then(|mut item| {
+            if expired_ttl(&item).is_some() {
+                return None;
+            }
             if let Some(AttributeValue::B(val)) = item.remove(VAL) {
                 Some(val.into_inner())
             } else {
@@ -213,6 +241,25 @@ impl Store for AwsDynamoStore {
         Ok(())
     }
 
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        // DynamoDB expiry is in whole seconds, so round up
+        let expires = SystemTime::now() + ttl + Duration::from_nanos(999_999_999);
+        let expires = expires
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.client
+            .put_item()
+            .table_name(self.table.as_str())
+            .item(PK, AttributeValue::S(key.to_string()))
+            .item(VAL, AttributeValue::B(Blob::new(value)))
+            .item(TTL, AttributeValue::N(expires.to_string()))
+            .send()
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
     async fn delete(&self, key: &str) -> Result<(), Error> {
         self.client
             .delete_item()
@@ -234,12 +281,16 @@ impl Store for AwsDynamoStore {
                 PK,
                 aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
             )
-            .projection_expression(PK)
+            .projection_expression("#PK, #TTL")
+            .expression_attribute_names("#PK", PK)
+            .expression_attribute_names("#TTL", TTL)
             .send()
             .await
             .map_err(log_error)?;
 
-        Ok(item.map(|item| item.contains_key(PK)).unwrap_or(false))
+        Ok(item
+            .map(|item| item.contains_key(PK) && expired_ttl(&item).is_none())
+            .unwrap_or(false))
     }
 
     async fn get_keys(&self) -> Result<Vec<String>, Error> {
@@ -249,7 +300,9 @@ impl Store for AwsDynamoStore {
             .client
             .scan()
             .table_name(self.table.as_str())
-            .projection_expression(PK)
+            .projection_expression("#PK, #TTL")
+            .expression_attribute_names("#PK", PK)
+            .expression_attribute_names("#TTL", TTL)
             .into_paginator()
             .send();
 
@@ -257,6 +310,9 @@ impl Store for AwsDynamoStore {
             let scan_output = output.map_err(log_error)?;
             if let Some(items) = scan_output.items {
                 for mut item in items {
+                    if expired_ttl(&item).is_some() {
+                        continue;
+                    }
                     if let Some(AttributeValue::S(pk)) = item.remove(PK) {
                         primary_keys.push(pk);
                     }
@@ -270,7 +326,8 @@ impl Store for AwsDynamoStore {
     async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
         let mut results = Vec::with_capacity(keys.len());
         let mut keys_and_attributes_builder = KeysAndAttributes::builder()
-            .projection_expression(format!("{PK},{VAL}"))
+            .projection_expression(format!("{PK},{VAL},#TTL"))
+            .expression_attribute_names("#TTL", TTL)
             .consistent_read(self.consistent_read);
         for key in keys {
             keys_and_attributes_builder = keys_and_attributes_builder.keys(HashMap::from_iter([(
@@ -300,6 +357,9 @@ impl Store for AwsDynamoStore {
                 responses.and_then(|mut responses| responses.remove(self.table.as_str()))
             {
                 for mut item in items {
+                    if expired_ttl(&item).is_some() {
+                        continue;
+                    }
                     match (item.remove(PK), item.remove(VAL)) {
                         (Some(AttributeValue::S(pk)), Some(AttributeValue::B(val))) => {
                             results.push((pk, Some(val.into_inner())));
@@ -394,12 +454,16 @@ impl Store for AwsDynamoStore {
             .consistent_read(true)
             .table_name(self.table.as_str())
             .key(PK, AttributeValue::S(key.clone()))
-            .projection_expression(VAL)
+            .projection_expression("#VAL, #TTL")
+            .expression_attribute_names("#VAL", VAL)
+            .expression_attribute_names("#TTL", TTL)
             .send()
             .await
             .map_err(log_error)?;
 
-        let old_val = match item {
+        // An expired value counts as missing, and must not pass its expiry on to the new one.
+        let expired = item.as_ref().and_then(expired_ttl).cloned();
+        let old_val = match item.filter(|_| expired.is_none()) {
             Some(mut current_item) => match current_item.remove(VAL) {
                 // We're expecting i64, so technically we could transmute but seems risky...
                 Some(AttributeValue::B(val)) => Some(
@@ -425,7 +489,13 @@ impl Store for AwsDynamoStore {
                 AttributeValue::B(Blob::new(new_val.to_string().as_bytes())),
             );
 
-        if let Some(old_val) = old_val {
+        if let Some(expired) = expired {
+            update = update
+                .update_expression("SET #VAL = :new_val REMOVE #TTL")
+                .condition_expression("#TTL = :old_ttl")
+                .expression_attribute_names("#TTL", TTL)
+                .expression_attribute_values(":old_ttl", AttributeValue::N(expired))
+        } else if let Some(old_val) = old_val {
             update = update
                 .condition_expression("#VAL = :old_val")
                 .expression_attribute_values(
@@ -474,11 +544,20 @@ impl Cas for CompareAndSwap {
             .consistent_read(true)
             .table_name(self.table.as_str())
             .key(PK, AttributeValue::S(self.key.clone()))
-            .projection_expression(format!("{VAL},{VER}"))
+            .projection_expression(format!("{VAL},{VER},#TTL"))
+            .expression_attribute_names("#TTL", TTL)
             .send()
             .await
             .map_err(log_error)?;
 
+        if let Some(expired) = item.as_ref().and_then(expired_ttl) {
+            self.state
+                .lock()
+                .unwrap()
+                .clone_from(&CasState::Expired(expired.clone()));
+            return Ok(None);
+        }
+
         match item {
             Some(mut current_item) => match (current_item.remove(VAL), current_item.remove(VER)) {
                 (Some(AttributeValue::B(val)), Some(AttributeValue::N(ver))) => {
@@ -515,9 +594,10 @@ impl Cas for CompareAndSwap {
         let mut update = Update::builder()
             .table_name(self.table.as_str())
             .key(PK, AttributeValue::S(self.key.clone()))
-            .update_expression("SET #VAL = :val ADD #VER :increment")
+            .update_expression("SET #VAL = :val ADD #VER :increment REMOVE #TTL")
             .expression_attribute_names("#VAL", VAL)
             .expression_attribute_names("#VER", VER)
+            .expression_attribute_names("#TTL", TTL)
             .expression_attribute_values(":val", AttributeValue::B(Blob::new(value)))
             .expression_attribute_values(":increment", AttributeValue::N("1".to_owned()));
 
@@ -536,6 +616,11 @@ impl Cas for CompareAndSwap {
             CasState::Unset => {
                 update = update.condition_expression("attribute_not_exists (#VAL)");
             }
+            CasState::Expired(ttl) => {
+                update = update
+                    .condition_expression("#TTL = :ttl")
+                    .expression_attribute_values(":ttl", AttributeValue::N(ttl));
+            }
             CasState::Unknown => (),
         };
 
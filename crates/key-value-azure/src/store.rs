@@ -9,7 +9,10 @@ use azure_data_cosmos::{
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use spin_factor_key_value::{log_cas_error, log_error, Cas, Error, Store, StoreManager, SwapError};
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 pub struct KeyValueAzureCosmos {
     client: CollectionClient,
@@ -154,26 +157,16 @@ impl Store for AzureCosmosStore {
     }
 
     async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
-        let illegal_chars = ['/', '\\', '?', '#'];
-
-        if key.contains(|c| illegal_chars.contains(&c)) {
-            return Err(Error::Other(format!(
-                "Key contains an illegal character. Keys must not include any of: {}",
-                illegal_chars.iter().collect::<String>()
-            )));
-        }
+        self.upsert(key, value, None).await
+    }
 
-        let pair = Pair {
-            id: key.to_string(),
-            value: value.to_vec(),
-            store_id: self.store_id.clone(),
-        };
-        self.client
-            .create_document(pair)
-            .is_upsert(true)
+    /// Cosmos DB expires items itself, in whole seconds, so the TTL is
+    /// rounded up. This requires time to live to be enabled on the
+    /// container, e.g. with a default of -1 (no expiry).
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let secs = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+        self.upsert(key, value, Some(i64::try_from(secs).unwrap_or(i64::MAX)))
             .await
-            .map_err(log_error)?;
-        Ok(())
     }
 
     async fn delete(&self, key: &str) -> Result<(), Error> {
@@ -363,6 +356,7 @@ impl Cas for CompareAndSwap {
             id: self.key.clone(),
             value,
             store_id: self.store_id.clone(),
+            ttl: None,
         };
 
         let doc_client = self
@@ -402,6 +396,30 @@ impl Cas for CompareAndSwap {
 }
 
 impl AzureCosmosStore {
+    async fn upsert(&self, key: &str, value: &[u8], ttl: Option<i64>) -> Result<(), Error> {
+        let illegal_chars = ['/', '\\', '?', '#'];
+
+        if key.contains(|c| illegal_chars.contains(&c)) {
+            return Err(Error::Other(format!(
+                "Key contains an illegal character. Keys must not include any of: {}",
+                illegal_chars.iter().collect::<String>()
+            )));
+        }
+
+        let pair = Pair {
+            id: key.to_string(),
+            value: value.to_vec(),
+            store_id: self.store_id.clone(),
+            ttl,
+        };
+        self.client
+            .create_document(pair)
+            .is_upsert(true)
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
     async fn get_entity<F>(&self, key: &str) -> Result<Option<F>, Error>
     where
         F: CosmosEntity + Send + Sync + serde::de::DeserializeOwned + Clone,
@@ -494,6 +512,9 @@ pub struct Pair {
     pub value: Vec<u8>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub store_id: Option<String>,
+    /// Seconds until the item expires, counted from its last write.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<i64>,
 }
 
 impl CosmosEntity for Pair {
@@ -0,0 +1,39 @@
+mod store;
+
+use serde::{Deserialize, Serialize};
+use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
+use store::KeyValueMemory;
+
+/// A key-value store which keeps its data in memory, for as long as the
+/// application runs.
+#[derive(Default)]
+pub struct MemoryKeyValueStore {
+    _priv: (),
+}
+
+impl MemoryKeyValueStore {
+    /// Creates a new `MemoryKeyValueStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Runtime configuration for the in-memory key-value store.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryKeyValueRuntimeConfig {}
+
+impl MakeKeyValueStore for MemoryKeyValueStore {
+    const RUNTIME_CONFIG_TYPE: &'static str = "memory";
+
+    type RuntimeConfig = MemoryKeyValueRuntimeConfig;
+
+    type StoreManager = KeyValueMemory;
+
+    fn make_store(
+        &self,
+        _runtime_config: Self::RuntimeConfig,
+    ) -> anyhow::Result<Self::StoreManager> {
+        Ok(KeyValueMemory::new())
+    }
+}
@@ -0,0 +1,430 @@
+use spin_core::async_trait;
+use spin_factor_key_value::{Cas, Error, Store, StoreManager, SwapError};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+type Entries = Arc<Mutex<BTreeMap<String, Entry>>>;
+
+struct Entry {
+    value: Vec<u8>,
+    expires: Option<Instant>,
+}
+
+impl Entry {
+    fn new(value: Vec<u8>) -> Self {
+        Self {
+            value,
+            expires: None,
+        }
+    }
+
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires.is_none_or(|expires| expires > now)
+    }
+}
+
+/// The unexpired value of the given key, if any.
+fn live_value<'a>(entries: &'a BTreeMap<String, Entry>, key: &str) -> Option<&'a Vec<u8>> {
+    let now = Instant::now();
+    entries
+        .get(key)
+        .filter(|entry| entry.is_live(now))
+        .map(|entry| &entry.value)
+}
+
+#[derive(Default)]
+pub struct KeyValueMemory {
+    stores: Mutex<HashMap<String, Entries>>,
+}
+
+impl KeyValueMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl StoreManager for KeyValueMemory {
+    async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
+        let entries = self
+            .stores
+            .lock()
+            .unwrap()
+            .entry(name.to_owned())
+            .or_default()
+            .clone();
+        Ok(Arc::new(MemoryStore { entries }))
+    }
+
+    fn is_defined(&self, _store_name: &str) -> bool {
+        true
+    }
+
+    fn summary(&self, _store_name: &str) -> Option<String> {
+        Some("a temporary in-memory store".into())
+    }
+}
+
+struct MemoryStore {
+    entries: Entries,
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(live_value(&self.entries.lock().unwrap(), key).cloned())
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), Entry::new(value.to_vec()));
+        Ok(())
+    }
+
+    // Expired entries are swept here, so that a store used as a cache does
+    // not grow without bound.
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.is_live(now));
+        entries.insert(
+            key.to_owned(),
+            Entry {
+                value: value.to_vec(),
+                expires: now.checked_add(ttl),
+            },
+        );
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(live_value(&self.entries.lock().unwrap(), key).is_some())
+    }
+
+    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+        let now = Instant::now();
+        Ok(self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry)| entry.is_live(now))
+            .map(|(key, _)| key.clone())
+            .collect())
+    }
+
+    async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Option<Vec<u8>>)>, Error> {
+        let entries = self.entries.lock().unwrap();
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| {
+                let value = live_value(&entries, &key).cloned()?;
+                Some((key, Some(value)))
+            })
+            .collect())
+    }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        self.entries.lock().unwrap().extend(
+            key_values
+                .into_iter()
+                .map(|(key, value)| (key, Entry::new(value))),
+        );
+        Ok(())
+    }
+
+    async fn delete_many(&self, keys: Vec<String>) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys {
+            entries.remove(&key);
+        }
+        Ok(())
+    }
+
+    // As with the other stores, a missing value counts as zero, and values
+    // are stored as little-endian i64s. An unexpired value keeps its expiry.
+    async fn increment(&self, key: String, delta: i64) -> Result<i64, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let (numeric, expires) = match entries.get(&key).filter(|e| e.is_live(Instant::now())) {
+            Some(entry) => (
+                i64::from_le_bytes(entry.value.as_slice().try_into().map_err(|_| {
+                    Error::Other(format!("value of key {key:?} is not a 64-bit integer"))
+                })?),
+                entry.expires,
+            ),
+            None => (0, None),
+        };
+        let new_value = numeric
+            .checked_add(delta)
+            .ok_or_else(|| Error::Other(format!("incrementing key {key:?} overflowed")))?;
+        entries.insert(
+            key,
+            Entry {
+                value: new_value.to_le_bytes().to_vec(),
+                expires,
+            },
+        );
+        Ok(new_value)
+    }
+
+    async fn new_compare_and_swap(
+        &self,
+        bucket_rep: u32,
+        key: &str,
+    ) -> Result<Arc<dyn Cas>, Error> {
+        Ok(Arc::new(CompareAndSwap {
+            entries: self.entries.clone(),
+            key: key.to_owned(),
+            value: Mutex::new(None),
+            bucket_rep,
+        }))
+    }
+}
+
+struct CompareAndSwap {
+    entries: Entries,
+    key: String,
+    /// The value read by `current`, which must be unchanged for `swap` to
+    /// succeed.
+    value: Mutex<Option<Vec<u8>>>,
+    bucket_rep: u32,
+}
+
+#[async_trait]
+impl Cas for CompareAndSwap {
+    async fn current(&self) -> Result<Option<Vec<u8>>, Error> {
+        let value = live_value(&self.entries.lock().unwrap(), &self.key).cloned();
+        self.value.lock().unwrap().clone_from(&value);
+        Ok(value)
+    }
+
+    async fn swap(&self, value: Vec<u8>) -> Result<(), SwapError> {
+        let expected = self.value.lock().unwrap();
+        let mut entries = self.entries.lock().unwrap();
+        if live_value(&entries, &self.key) != expected.as_ref() {
+            return Err(SwapError::CasFailed(format!(
+                "value of key {:?} has changed",
+                self.key
+            )));
+        }
+        entries.insert(self.key.clone(), Entry::new(value));
+        Ok(())
+    }
+
+    async fn bucket_rep(&self) -> u32 {
+        self.bucket_rep
+    }
+
+    async fn key(&self) -> String {
+        self.key.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use spin_core::wasmtime::component::Resource;
+    use spin_factor_key_value::{DelegatingStoreManager, KeyValueDispatch};
+    use spin_world::spin::key_value::key_value::Host as _;
+    use spin_world::v2::key_value::HostStore;
+    use spin_world::wasi::keyvalue::atomics::{CasError, Host, HostCas};
+
+    fn dispatch(manager: Arc<KeyValueMemory>) -> KeyValueDispatch {
+        KeyValueDispatch::new(
+            ["default", "other"]
+                .into_iter()
+                .map(ToOwned::to_owned)
+                .collect(),
+            Arc::new(DelegatingStoreManager::new([
+                ("default".to_owned(), manager.clone() as _),
+                ("other".to_owned(), manager as _),
+            ])),
+        )
+    }
+
+    #[tokio::test]
+    async fn values_persist_across_instances() -> anyhow::Result<()> {
+        let manager = Arc::new(KeyValueMemory::new());
+
+        let mut kv = dispatch(manager.clone());
+        let store = kv.open("default".to_owned()).await??;
+        kv.set(store, "bar".to_owned(), b"baz".to_vec()).await??;
+
+        let mut kv = dispatch(manager);
+        let store = kv.open("default".to_owned()).await??;
+        let rep = store.rep();
+        assert_eq!(
+            Some(b"baz".to_vec()),
+            kv.get(Resource::new_own(rep), "bar".to_owned()).await??
+        );
+        assert_eq!(
+            vec!["bar".to_owned()],
+            kv.get_keys(Resource::new_own(rep)).await??
+        );
+
+        // Stores with different labels are independent
+        let other = kv.open("other".to_owned()).await??;
+        assert!(!kv.exists(other, "bar".to_owned()).await??);
+
+        kv.delete(Resource::new_own(rep), "bar".to_owned())
+            .await??;
+        assert!(
+            !kv.exists(Resource::new_own(rep), "bar".to_owned())
+                .await??
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn increment_and_compare_and_swap() -> anyhow::Result<()> {
+        let mut kv = dispatch(Arc::new(KeyValueMemory::new()));
+        let store = kv.open("default".to_owned()).await??;
+        let rep = store.rep();
+
+        assert_eq!(
+            3,
+            kv.increment(Resource::new_own(rep), "n".to_owned(), 3)
+                .await?
+        );
+        assert_eq!(
+            1,
+            kv.increment(Resource::new_own(rep), "n".to_owned(), -2)
+                .await?
+        );
+
+        kv.set(Resource::new_own(rep), "s".to_owned(), b"text".to_vec())
+            .await??;
+        assert!(kv
+            .increment(Resource::new_own(rep), "s".to_owned(), 1)
+            .await
+            .is_err());
+
+        let cas = kv.new(Resource::new_own(rep), "key".to_owned()).await?;
+        let cas_rep = cas.rep();
+        assert_eq!(None, kv.current(Resource::new_own(cas_rep)).await?);
+        kv.set(
+            Resource::new_own(rep),
+            "key".to_owned(),
+            b"changed".to_vec(),
+        )
+        .await??;
+        assert!(matches!(
+            kv.swap(Resource::new_own(cas_rep), b"swapped".to_vec())
+                .await,
+            Err(CasError::CasFailed(_))
+        ));
+
+        let cas = kv.new(Resource::new_own(rep), "key".to_owned()).await?;
+        let cas_rep = cas.rep();
+        assert_eq!(
+            Some(b"changed".to_vec()),
+            kv.current(Resource::new_own(cas_rep)).await?
+        );
+        kv.swap(Resource::new_own(cas_rep), b"swapped".to_vec())
+            .await
+            .expect("value should be unchanged");
+        assert_eq!(
+            Some(b"swapped".to_vec()),
+            kv.get(Resource::new_own(rep), "key".to_owned()).await??
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn values_expire_after_their_ttl() -> anyhow::Result<()> {
+        let mut kv = dispatch(Arc::new(KeyValueMemory::new()));
+        let store = kv.open("default".to_owned()).await??;
+        let rep = store.rep();
+
+        kv.set_with_ttl(
+            Resource::new_own(rep),
+            "short".to_owned(),
+            b"a".to_vec(),
+            20,
+        )
+        .await?;
+        kv.set_with_ttl(
+            Resource::new_own(rep),
+            "long".to_owned(),
+            b"b".to_vec(),
+            60_000,
+        )
+        .await?;
+        assert!(kv
+            .set_with_ttl(Resource::new_own(rep), "zero".to_owned(), vec![], 0)
+            .await
+            .is_err());
+        assert_eq!(2, kv.get_keys(Resource::new_own(rep)).await??.len());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(
+            None,
+            kv.get(Resource::new_own(rep), "short".to_owned()).await??
+        );
+        assert_eq!(
+            vec!["long".to_owned()],
+            kv.get_keys(Resource::new_own(rep)).await??
+        );
+
+        // An expired counter starts again from zero
+        kv.set_with_ttl(
+            Resource::new_own(rep),
+            "n".to_owned(),
+            5i64.to_le_bytes().to_vec(),
+            20,
+        )
+        .await?;
+        assert_eq!(
+            6,
+            kv.increment(Resource::new_own(rep), "n".to_owned(), 1)
+                .await?
+        );
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(
+            1,
+            kv.increment(Resource::new_own(rep), "n".to_owned(), 1)
+                .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn compare_and_swap_checks_the_expected_value() -> anyhow::Result<()> {
+        let mut kv = dispatch(Arc::new(KeyValueMemory::new()));
+        let store = kv.open("default".to_owned()).await??;
+        let rep = store.rep();
+
+        assert!(
+            kv.compare_and_swap(Resource::new_own(rep), "k".to_owned(), None, b"1".to_vec())
+                .await?
+        );
+        assert!(
+            !kv.compare_and_swap(Resource::new_own(rep), "k".to_owned(), None, b"2".to_vec())
+                .await?
+        );
+        assert!(
+            kv.compare_and_swap(
+                Resource::new_own(rep),
+                "k".to_owned(),
+                Some(b"1".to_vec()),
+                b"2".to_vec()
+            )
+            .await?
+        );
+        assert_eq!(
+            Some(b"2".to_vec()),
+            kv.get(Resource::new_own(rep), "k".to_owned()).await??
+        );
+        Ok(())
+    }
+}
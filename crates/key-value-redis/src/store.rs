@@ -2,7 +2,7 @@ use anyhow::{Context, Result};
 use redis::{aio::ConnectionManager, parse_redis_url, AsyncCommands, Client, RedisError};
 use spin_core::async_trait;
 use spin_factor_key_value::{log_error, Cas, Error, Store, StoreManager, SwapError};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::OnceCell;
 use url::Url;
 
@@ -84,6 +84,14 @@ impl Store for RedisStore {
             .map_err(log_error)
     }
 
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        self.connection
+            .clone()
+            .pset_ex(key, value, millis(ttl))
+            .await
+            .map_err(log_error)
+    }
+
     async fn delete(&self, key: &str) -> Result<(), Error> {
         self.connection.clone().del(key).await.map_err(log_error)
     }
@@ -140,6 +148,62 @@ impl Store for RedisStore {
             bucket_rep,
         }))
     }
+
+    /// Locks are keys holding the owner's token, set with `SET NX` and expired
+    /// by Redis itself.
+    async fn acquire_lock(&self, key: &str, token: &str, ttl: Duration) -> Result<bool, Error> {
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(millis(ttl))
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(log_error)?;
+        Ok(reply.is_some())
+    }
+
+    async fn renew_lock(&self, key: &str, token: &str, ttl: Duration) -> Result<bool, Error> {
+        let renewed: i64 = redis::Script::new(RENEW_LOCK_SCRIPT)
+            .key(key)
+            .arg(token)
+            .arg(millis(ttl))
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(log_error)?;
+        Ok(renewed == 1)
+    }
+
+    async fn release_lock(&self, key: &str, token: &str) -> Result<bool, Error> {
+        let released: i64 = redis::Script::new(RELEASE_LOCK_SCRIPT)
+            .key(key)
+            .arg(token)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(log_error)?;
+        Ok(released == 1)
+    }
+}
+
+/// Extends a lock's expiry if it is held by the given token.
+const RENEW_LOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+/// Deletes a lock if it is held by the given token.
+const RELEASE_LOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
 }
 
 #[async_trait]
@@ -7,6 +7,7 @@ use std::{
     path::PathBuf,
     sync::OnceLock,
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 use tokio::task;
 
@@ -47,12 +48,15 @@ impl KeyValueSqlite {
                            store TEXT NOT NULL,
                            key   TEXT NOT NULL,
                            value BLOB NOT NULL,
+                           expires_at INTEGER,
 
                            PRIMARY KEY (store, key)
                         )",
                 [],
             )
             .map_err(log_error)?;
+        migrate_expiry(&connection).map_err(log_error)?;
+        sweep_expired(&connection).map_err(log_error)?;
 
         // the array module is needed for `rarray` usage in queries.
         rusqlite::vtab::array::load_module(&connection).map_err(log_error)?;
@@ -61,6 +65,44 @@ impl KeyValueSqlite {
     }
 }
 
+/// Adds the `expires_at` column, in Unix milliseconds, to databases created
+/// before values could expire.
+fn migrate_expiry(connection: &Connection) -> rusqlite::Result<()> {
+    let has_expiry = connection
+        .prepare("SELECT 1 FROM pragma_table_info('spin_key_value') WHERE name='expires_at'")?
+        .exists([])?;
+    if !has_expiry {
+        connection.execute(
+            "ALTER TABLE spin_key_value ADD COLUMN expires_at INTEGER",
+            [],
+        )?;
+    }
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS spin_key_value_expires_at
+         ON spin_key_value (expires_at) WHERE expires_at IS NOT NULL",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Deletes expired values from all stores. Queries also skip expired values,
+/// so this only reclaims space.
+fn sweep_expired(connection: &Connection) -> rusqlite::Result<()> {
+    connection
+        .prepare_cached("DELETE FROM spin_key_value WHERE expires_at <= $1")?
+        .execute([now_millis()])
+        .map(drop)
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+        .try_into()
+        .unwrap_or(i64::MAX)
+}
+
 #[async_trait]
 impl StoreManager for KeyValueSqlite {
     async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error> {
@@ -104,9 +146,14 @@ impl Store for SqliteStore {
             self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
+                .prepare_cached(
+                    "SELECT value FROM spin_key_value WHERE store=$1 AND key=$2
+                     AND (expires_at IS NULL OR expires_at > $3)",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name, key], |row| row.get(0))
+                .query_map(rusqlite::params![&self.name, key, now_millis()], |row| {
+                    row.get(0)
+                })
                 .map_err(log_error)?
                 .next()
                 .transpose()
@@ -121,7 +168,7 @@ impl Store for SqliteStore {
                 .unwrap()
                 .prepare_cached(
                     "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=NULL",
                 )
                 .map_err(log_error)?
                 .execute(rusqlite::params![&self.name, key, value])
@@ -130,6 +177,23 @@ impl Store for SqliteStore {
         })
     }
 
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        task::block_in_place(|| {
+            let connection = self.connection.lock().unwrap();
+            let ttl_millis = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+            let expires_at = now_millis().saturating_add(ttl_millis);
+            connection
+                .prepare_cached(
+                    "INSERT INTO spin_key_value (store, key, value, expires_at) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=$4",
+                )
+                .map_err(log_error)?
+                .execute(rusqlite::params![&self.name, key, value, expires_at])
+                .map_err(log_error)?;
+            sweep_expired(&connection).map_err(log_error)
+        })
+    }
+
     async fn delete(&self, key: &str) -> Result<(), Error> {
         task::block_in_place(|| {
             self.connection
@@ -152,9 +216,14 @@ impl Store for SqliteStore {
             self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT key FROM spin_key_value WHERE store=$1")
+                .prepare_cached(
+                    "SELECT key FROM spin_key_value WHERE store=$1
+                     AND (expires_at IS NULL OR expires_at > $2)",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name], |row| row.get(0))
+                .query_map(rusqlite::params![&self.name, now_millis()], |row| {
+                    row.get(0)
+                })
                 .map_err(log_error)?
                 .map(|r| r.map_err(log_error))
                 .collect()
@@ -169,9 +238,9 @@ impl Store for SqliteStore {
             let row_iter: Vec<Result<(String, Option<Vec<u8>>), Error>> = self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT key, value FROM spin_key_value WHERE store=:name AND key IN rarray(:keys)")
+                .prepare_cached("SELECT key, value FROM spin_key_value WHERE store=:name AND key IN rarray(:keys) AND (expires_at IS NULL OR expires_at > :now)")
                 .map_err(log_error)?
-                .query_map(named_params! {":name": &self.name, ":keys": ptr}, |row| {
+                .query_map(named_params! {":name": &self.name, ":keys": ptr, ":now": now_millis()}, |row| {
                     <(String, Option<Vec<u8>>)>::try_from(row)
                 })
                 .map_err(log_error)?
@@ -194,7 +263,7 @@ impl Store for SqliteStore {
             for kv in key_values {
                 tx.prepare_cached(
                     "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=NULL",
                 )
                 .map_err(log_error)?
                 .execute(rusqlite::params![&self.name, kv.0, kv.1])
@@ -225,12 +294,21 @@ impl Store for SqliteStore {
 
     // The assumption with increment is that if the value for the key does not exist, it will be
     // assumed to be zero. In the case that we are unable to unmarshal the value into an i64 an error will be returned.
+    // An unexpired value keeps its expiry.
     async fn increment(&self, key: String, delta: i64) -> Result<i64, Error> {
         task::block_in_place(|| {
             let mut binding = self.connection.lock().unwrap();
 
             let tx = binding.transaction().map_err(log_error)?;
 
+            // An expired value counts as missing, and must not pass its expiry on to the new one.
+            tx.prepare_cached(
+                "DELETE FROM spin_key_value WHERE store=$1 AND key=$2 AND expires_at <= $3",
+            )
+            .map_err(log_error)?
+            .execute(rusqlite::params![&self.name, &key, now_millis()])
+            .map_err(log_error)?;
+
             let value: Option<Vec<u8>> = tx
                 .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
                 .map_err(log_error)?
@@ -291,9 +369,15 @@ impl Cas for CompareAndSwap {
                 .connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
+                .prepare_cached(
+                    "SELECT value FROM spin_key_value WHERE store=$1 AND key=$2
+                     AND (expires_at IS NULL OR expires_at > $3)",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name, &self.key], |row| row.get(0))
+                .query_map(
+                    rusqlite::params![&self.name, &self.key, now_millis()],
+                    |row| row.get(0),
+                )
                 .map_err(log_error)?
                 .next()
                 .transpose()
@@ -312,25 +396,29 @@ impl Cas for CompareAndSwap {
                 Some(old_val) => {
                     conn
                         .prepare_cached(
-                             "UPDATE spin_key_value SET value=:new_value WHERE store=:name and key=:key and value=:old_value")
+                             "UPDATE spin_key_value SET value=:new_value, expires_at=NULL WHERE store=:name and key=:key and value=:old_value and (expires_at IS NULL OR expires_at > :now)")
                         .map_err(log_cas_error)?
                         .execute(named_params! {
                             ":name": &self.name,
                             ":key": self.key,
                             ":old_value": old_val,
                             ":new_value": value,
+                            ":now": now_millis(),
                         })
                         .map_err(log_cas_error)?
                 }
                 None => {
+                    // Only an expired value may be replaced: a live one was
+                    // set since `current`, so the swap has lost a race.
                     let tx = conn.transaction().map_err(log_cas_error)?;
                     let rows = tx
                         .prepare_cached(
                             "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=NULL
+                     WHERE expires_at IS NOT NULL AND expires_at <= $4",
                         )
                         .map_err(log_cas_error)?
-                        .execute(rusqlite::params![&self.name, self.key, value])
+                        .execute(rusqlite::params![&self.name, self.key, value, now_millis()])
                         .map_err(log_cas_error)?;
                     tx.commit().map_err(log_cas_error)?;
                     rows
@@ -360,6 +448,7 @@ mod test {
     use super::*;
     use spin_core::wasmtime::component::Resource;
     use spin_factor_key_value::{DelegatingStoreManager, KeyValueDispatch};
+    use spin_world::spin::key_value::key_value::Host as spin_kv_host;
     use spin_world::v2::key_value::HostStore;
     use spin_world::wasi::keyvalue::atomics::HostCas as wasi_cas_host;
     use spin_world::wasi::keyvalue::atomics::{CasError, Host};
@@ -498,6 +587,112 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn expiry() -> Result<()> {
+        let mut kv = KeyValueDispatch::new(
+            ["default".to_owned()].into_iter().collect(),
+            Arc::new(DelegatingStoreManager::new([(
+                "default".to_owned(),
+                Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)) as _,
+            )])),
+        );
+        let rep = kv.open("default".to_owned()).await??.rep();
+
+        kv.set_with_ttl(
+            Resource::new_own(rep),
+            "short".to_owned(),
+            b"a".to_vec(),
+            20,
+        )
+        .await?;
+        kv.set_with_ttl(
+            Resource::new_own(rep),
+            "counter".to_owned(),
+            5i64.to_le_bytes().to_vec(),
+            60_000,
+        )
+        .await?;
+        assert_eq!(kv_incr(&mut kv, rep, 1).await, 6);
+        std::thread::sleep(Duration::from_millis(30));
+
+        assert!(
+            !kv.exists(Resource::new_own(rep), "short".to_owned())
+                .await??
+        );
+        assert_eq!(
+            &["counter".to_owned()] as &[_],
+            &kv.get_keys(Resource::new_own(rep)).await??
+        );
+
+        // Setting a value clears its expiry
+        kv.set_with_ttl(
+            Resource::new_own(rep),
+            "short".to_owned(),
+            b"a".to_vec(),
+            20,
+        )
+        .await?;
+        kv.set(Resource::new_own(rep), "short".to_owned(), b"b".to_vec())
+            .await??;
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(
+            Some(b"b".to_vec()),
+            kv.get(Resource::new_own(rep), "short".to_owned()).await??
+        );
+
+        assert!(
+            kv.compare_and_swap(
+                Resource::new_own(rep),
+                "short".to_owned(),
+                Some(b"b".to_vec()),
+                b"c".to_vec()
+            )
+            .await?
+        );
+        assert!(
+            !kv.compare_and_swap(
+                Resource::new_own(rep),
+                "short".to_owned(),
+                Some(b"b".to_vec()),
+                b"d".to_vec()
+            )
+            .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn only_one_insert_of_a_missing_key_wins() -> Result<()> {
+        let store = KeyValueSqlite::new(DatabaseLocation::InMemory)
+            .get("default")
+            .await?;
+
+        // Both swaps see the key as missing before either is made
+        let first = store.new_compare_and_swap(0, "claimed").await?;
+        let second = store.new_compare_and_swap(0, "claimed").await?;
+        assert_eq!(None, first.current().await?);
+        assert_eq!(None, second.current().await?);
+        let (first, second) = tokio::join!(first.swap(b"a".to_vec()), second.swap(b"b".to_vec()));
+        assert!(
+            first.is_ok() != second.is_ok(),
+            "expected exactly one swap to succeed: {first:?}, {second:?}"
+        );
+        let winner = if first.is_ok() { b"a" } else { b"b" };
+        assert_eq!(Some(winner.to_vec()), store.get("claimed").await?);
+        assert!(matches!(first.and(second), Err(SwapError::CasFailed(_))));
+
+        // An expired value counts as missing
+        store
+            .set_with_ttl("expiring", b"old", Duration::from_millis(20))
+            .await?;
+        std::thread::sleep(Duration::from_millis(30));
+        let cas = store.new_compare_and_swap(0, "expiring").await?;
+        assert_eq!(None, cas.current().await?);
+        cas.swap(b"new".to_vec()).await?;
+        assert_eq!(Some(b"new".to_vec()), store.get("expiring").await?);
+        Ok(())
+    }
+
     async fn cas_failed(kv: &mut KeyValueDispatch, rep: u32) -> Result<()> {
         let cas_key = "fail".to_owned();
         let cas_orig_value = b"baz".to_vec();
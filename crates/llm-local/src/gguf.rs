@@ -0,0 +1,184 @@
+use crate::{GgufOptions, InferencingModel};
+use anyhow::{anyhow, bail, Context, Result};
+use llama_cpp_2::{
+    context::params::LlamaContextParams,
+    llama_backend::LlamaBackend,
+    llama_batch::LlamaBatch,
+    model::{params::LlamaModelParams, AddBos, LlamaModel, Special},
+    sampling::LlamaSampler,
+};
+use spin_common::ui::quoted_path;
+use spin_core::async_trait;
+use spin_world::v2::llm::{self as wasi_llm, InferencingUsage};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+const MODEL_FILE_EXTENSION: &str = "gguf";
+
+/// Whether Spin was built to run models on a GPU.
+const GPU_ENABLED: bool = cfg!(any(feature = "metal", feature = "cublas"));
+
+/// More layers than any model has, so that every layer is offloaded.
+const ALL_LAYERS: u32 = 999;
+
+/// The llama.cpp backend, which may only be initialized once per process.
+static BACKEND: OnceLock<Result<LlamaBackend, String>> = OnceLock::new();
+
+fn backend() -> Result<&'static LlamaBackend> {
+    BACKEND
+        .get_or_init(|| LlamaBackend::init().map_err(|e| e.to_string()))
+        .as_ref()
+        .map_err(|e| anyhow!("Failed to initialize llama.cpp: {e}"))
+}
+
+/// A model in the GGUF format, run by llama.cpp.
+pub(crate) struct GgufModel {
+    model: Arc<LlamaModel>,
+    options: GgufOptions,
+}
+
+impl GgufModel {
+    pub async fn new(model_dir: &Path, options: GgufOptions) -> Result<Self> {
+        let model_file = find_model_file(model_dir)?;
+        let gpu_layers = options
+            .gpu_layers
+            .unwrap_or(if GPU_ENABLED { ALL_LAYERS } else { 0 });
+        let model = tokio::task::spawn_blocking(move || {
+            let params = LlamaModelParams::default().with_n_gpu_layers(gpu_layers);
+            LlamaModel::load_from_file(backend()?, &model_file, &params)
+                .with_context(|| format!("Failed to load GGUF model {}", quoted_path(&model_file)))
+        })
+        .await??;
+
+        Ok(Self {
+            model: Arc::new(model),
+            options,
+        })
+    }
+}
+
+#[async_trait]
+impl InferencingModel for GgufModel {
+    async fn infer(
+        &self,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+    ) -> anyhow::Result<wasi_llm::InferencingResult> {
+        let model = Arc::clone(&self.model);
+        let options = self.options;
+        tokio::task::spawn_blocking(move || generate(&model, options, &prompt, params)).await?
+    }
+}
+
+/// Generates text following the prompt, in a context created for the
+/// request.
+fn generate(
+    model: &LlamaModel,
+    options: GgufOptions,
+    prompt: &str,
+    params: wasi_llm::InferencingParams,
+) -> Result<wasi_llm::InferencingResult> {
+    let context_params = LlamaContextParams::default().with_n_ctx(options.context_length);
+    let mut ctx = model.new_context(backend()?, context_params)?;
+    let context_length = ctx.n_ctx() as usize;
+
+    let prompt_tokens = model.str_to_token(prompt, AddBos::Always)?;
+    if prompt_tokens.len() >= context_length {
+        bail!(
+            "The prompt is {} tokens long, which does not fit the context length of {context_length} tokens",
+            prompt_tokens.len()
+        );
+    }
+
+    // Evaluate the prompt in batches, asking for the logits of its last token
+    let mut batch = LlamaBatch::new(ctx.n_batch() as usize, 1);
+    let mut position = 0;
+    for chunk in prompt_tokens.chunks(ctx.n_batch() as usize) {
+        batch.clear();
+        for &token in chunk {
+            let is_last = position as usize == prompt_tokens.len() - 1;
+            batch.add(token, position, &[0], is_last)?;
+            position += 1;
+        }
+        ctx.decode(&mut batch)?;
+    }
+
+    let mut sampler = sampler(&params);
+    let mut output = Vec::new();
+    let mut tokens_generated = 0;
+    while tokens_generated < params.max_tokens && (position as usize) < context_length {
+        let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+        // Stop at the end of generation token(s)
+        if model.is_eog_token(token) {
+            break;
+        }
+        tokens_generated += 1;
+        output.extend(model.token_to_bytes(token, Special::Tokenize)?);
+
+        batch.clear();
+        batch.add(token, position, &[0], true)?;
+        position += 1;
+        ctx.decode(&mut batch)?;
+    }
+
+    Ok(wasi_llm::InferencingResult {
+        // Tokens may split multi-byte characters, so the text is only decoded
+        // once it's complete
+        text: String::from_utf8_lossy(&output).into_owned(),
+        usage: InferencingUsage {
+            prompt_token_count: prompt_tokens.len() as u32,
+            generated_token_count: tokens_generated,
+        },
+    })
+}
+
+/// Builds a sampler choosing tokens as the params ask.
+fn sampler(params: &wasi_llm::InferencingParams) -> LlamaSampler {
+    let penalties = LlamaSampler::penalties(
+        params.repeat_penalty_last_n_token_count as i32,
+        params.repeat_penalty,
+        0.0,
+        0.0,
+    );
+    if params.temperature <= 0. {
+        LlamaSampler::chain_simple([penalties, LlamaSampler::greedy()])
+    } else {
+        LlamaSampler::chain_simple([
+            penalties,
+            LlamaSampler::top_k(params.top_k as i32),
+            LlamaSampler::top_p(params.top_p, 1),
+            LlamaSampler::temp(params.temperature),
+            LlamaSampler::dist(rand::random()),
+        ])
+    }
+}
+
+/// Finds the GGUF file in a model directory, which must contain exactly one.
+fn find_model_file(model_dir: &Path) -> Result<PathBuf> {
+    let entries = std::fs::read_dir(model_dir)
+        .with_context(|| format!("Could not read model directory {}", quoted_path(model_dir)))?;
+    let mut model_files = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext == MODEL_FILE_EXTENSION)
+        {
+            model_files.push(path);
+        }
+    }
+    match model_files.len() {
+        1 => Ok(model_files.remove(0)),
+        0 => bail!(
+            "No .{MODEL_FILE_EXTENSION} file found in model directory {}",
+            quoted_path(model_dir)
+        ),
+        _ => bail!(
+            "More than one .{MODEL_FILE_EXTENSION} file found in model directory {}",
+            quoted_path(model_dir)
+        ),
+    }
+}
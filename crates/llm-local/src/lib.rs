@@ -1,4 +1,6 @@
 mod bert;
+#[cfg(feature = "gguf")]
+mod gguf;
 mod llama;
 
 use anyhow::Context;
@@ -10,6 +12,7 @@ use spin_core::async_trait;
 use spin_world::v2::llm::{self as wasi_llm};
 use std::{
     collections::{hash_map::Entry, HashMap},
+    num::NonZeroU32,
     path::{Path, PathBuf},
     str::FromStr,
     sync::Arc,
@@ -19,9 +22,21 @@ use tokenizers::PaddingParams;
 const MODEL_ALL_MINILM_L6_V2: &str = "all-minilm-l6-v2";
 type ModelName = String;
 
+/// Options for running GGUF models.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GgufOptions {
+    /// The number of tokens in the context of a request, including the
+    /// prompt. Defaults to the context length the model was trained with.
+    pub context_length: Option<NonZeroU32>,
+    /// The number of the model's layers offloaded to the GPU. Defaults to
+    /// all of them if Spin was built with GPU support, and none otherwise.
+    pub gpu_layers: Option<u32>,
+}
+
 #[derive(Clone)]
 pub struct LocalLlmEngine {
     registry: PathBuf,
+    gguf_options: GgufOptions,
     inferencing_models: HashMap<ModelName, Arc<dyn InferencingModel>>,
     embeddings_models: HashMap<String, Arc<(tokenizers::Tokenizer, BertModel)>>,
 }
@@ -29,6 +44,8 @@ pub struct LocalLlmEngine {
 #[derive(Debug)]
 enum InferencingModelArch {
     Llama,
+    /// Models of any architecture llama.cpp supports, in a GGUF file.
+    Gguf,
 }
 
 impl FromStr for InferencingModelArch {
@@ -37,6 +54,7 @@ impl FromStr for InferencingModelArch {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "llama" => Ok(InferencingModelArch::Llama),
+            "gguf" => Ok(InferencingModelArch::Gguf),
             _ => Err(()),
         }
     }
@@ -85,11 +103,18 @@ impl LocalLlmEngine {
     pub fn new(registry: PathBuf) -> Self {
         Self {
             registry,
+            gguf_options: Default::default(),
             inferencing_models: Default::default(),
             embeddings_models: Default::default(),
         }
     }
 
+    /// Sets the options used to run GGUF models.
+    pub fn with_gguf_options(mut self, gguf_options: GgufOptions) -> Self {
+        self.gguf_options = gguf_options;
+        self
+    }
+
     /// Get embeddings model from cache or load from disk
     async fn embeddings_model(
         &mut self,
@@ -148,12 +173,24 @@ impl LocalLlmEngine {
             Entry::Vacant(v) => {
                 let (model_dir, arch) =
                     walk_registry_for_model(&self.registry, model.clone()).await?;
-                let model = match arch {
+                let model: Arc<dyn InferencingModel> = match arch {
                     InferencingModelArch::Llama => Arc::new(
                         llama::LlamaModels::new(&model_dir)
                             .await
                             .map_err(|e| wasi_llm::Error::RuntimeError(e.to_string()))?,
                     ),
+                    #[cfg(feature = "gguf")]
+                    InferencingModelArch::Gguf => Arc::new(
+                        gguf::GgufModel::new(&model_dir, self.gguf_options)
+                            .await
+                            .map_err(|e| wasi_llm::Error::RuntimeError(e.to_string()))?,
+                    ),
+                    #[cfg(not(feature = "gguf"))]
+                    InferencingModelArch::Gguf => {
+                        return Err(wasi_llm::Error::RuntimeError(
+                            "GGUF models are not supported in this version of Spin.".into(),
+                        ))
+                    }
                 };
 
                 v.insert(model.clone());
@@ -1,55 +1,49 @@
+mod default;
+mod open_ai;
+
+use std::collections::HashMap;
+
 use anyhow::Result;
+use futures::stream::{self, BoxStream, StreamExt};
 use reqwest::{
     header::{HeaderMap, HeaderValue},
     Client, Url,
 };
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde::Deserialize;
 use spin_world::v2::llm::{self as wasi_llm};
 
+/// The API served by a remote LLM endpoint.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiType {
+    /// The API of the Spin LLM proxy, with `/infer` and `/embed` routes.
+    #[default]
+    Default,
+    /// An OpenAI-compatible API, with `/chat/completions` and `/embeddings`
+    /// routes under the endpoint URL.
+    OpenAi,
+}
+
+/// Text generated by a model, received as the model generates it.
+#[derive(Debug, PartialEq)]
+pub struct InferencingDelta {
+    /// The text generated since the previous delta.
+    pub text: String,
+    /// Usage information about the request, which comes with the last delta.
+    pub usage: Option<wasi_llm::InferencingUsage>,
+}
+
 #[derive(Clone)]
 pub struct RemoteHttpLlmEngine {
     auth_token: String,
     url: Url,
+    api_type: ApiType,
+    /// Maps the names of models used by apps to the names the API knows them
+    /// by. Models which aren't mapped are passed through as they are.
+    models: HashMap<String, String>,
     client: Option<Client>,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all(serialize = "camelCase"))]
-struct InferRequestBodyParams {
-    max_tokens: u32,
-    repeat_penalty: f32,
-    repeat_penalty_last_n_token_count: u32,
-    temperature: f32,
-    top_k: u32,
-    top_p: f32,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all(deserialize = "camelCase"))]
-struct InferUsage {
-    prompt_token_count: u32,
-    generated_token_count: u32,
-}
-
-#[derive(Deserialize)]
-struct InferResponseBody {
-    text: String,
-    usage: InferUsage,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all(deserialize = "camelCase"))]
-struct EmbeddingUsage {
-    prompt_token_count: u32,
-}
-
-#[derive(Deserialize)]
-struct EmbeddingResponseBody {
-    embeddings: Vec<Vec<f32>>,
-    usage: EmbeddingUsage,
-}
-
 impl RemoteHttpLlmEngine {
     pub async fn infer(
         &mut self,
@@ -57,59 +51,46 @@ impl RemoteHttpLlmEngine {
         prompt: String,
         params: wasi_llm::InferencingParams,
     ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
+        let model = self.api_model(model);
+        let headers = self.headers()?;
         let client = self.client.get_or_insert_with(Default::default);
+        match self.api_type {
+            ApiType::Default => {
+                default::infer(client, &self.url, headers, model, prompt, params).await
+            }
+            ApiType::OpenAi => {
+                open_ai::infer(client, &self.url, headers, model, prompt, params).await
+            }
+        }
+    }
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "authorization",
-            HeaderValue::from_str(&format!("bearer {}", self.auth_token)).map_err(|_| {
-                wasi_llm::Error::RuntimeError("Failed to create authorization header".to_string())
-            })?,
-        );
-        spin_telemetry::inject_trace_context(&mut headers);
-
-        let inference_options = InferRequestBodyParams {
-            max_tokens: params.max_tokens,
-            repeat_penalty: params.repeat_penalty,
-            repeat_penalty_last_n_token_count: params.repeat_penalty_last_n_token_count,
-            temperature: params.temperature,
-            top_k: params.top_k,
-            top_p: params.top_p,
-        };
-        let body = serde_json::to_string(&json!({
-            "model": model,
-            "prompt": prompt,
-            "options": inference_options
-        }))
-        .map_err(|_| wasi_llm::Error::RuntimeError("Failed to serialize JSON".to_string()))?;
-
-        let infer_url = self
-            .url
-            .join("/infer")
-            .map_err(|_| wasi_llm::Error::RuntimeError("Failed to create URL".to_string()))?;
-        tracing::info!("Sending remote inference request to {infer_url}");
-
-        let resp = client
-            .request(reqwest::Method::POST, infer_url)
-            .headers(headers)
-            .body(body)
-            .send()
-            .await
-            .map_err(|err| {
-                wasi_llm::Error::RuntimeError(format!("POST /infer request error: {err}"))
-            })?;
-
-        match resp.json::<InferResponseBody>().await {
-            Ok(val) => Ok(wasi_llm::InferencingResult {
-                text: val.text,
-                usage: wasi_llm::InferencingUsage {
-                    prompt_token_count: val.usage.prompt_token_count,
-                    generated_token_count: val.usage.generated_token_count,
-                },
-            }),
-            Err(err) => Err(wasi_llm::Error::RuntimeError(format!(
-                "Failed to deserialize response for \"POST  /index\": {err}"
-            ))),
+    /// Performs inferencing as [`Self::infer`] does, returning the generated
+    /// text as it is generated. The Spin LLM proxy API doesn't stream, so
+    /// the text comes all at once.
+    pub async fn infer_stream(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+    ) -> Result<BoxStream<'static, Result<InferencingDelta, wasi_llm::Error>>, wasi_llm::Error>
+    {
+        match self.api_type {
+            ApiType::Default => {
+                let result = self.infer(model, prompt, params).await?;
+                Ok(stream::once(async move {
+                    Ok(InferencingDelta {
+                        text: result.text,
+                        usage: Some(result.usage),
+                    })
+                })
+                .boxed())
+            }
+            ApiType::OpenAi => {
+                let model = self.api_model(model);
+                let headers = self.headers()?;
+                let client = self.client.get_or_insert_with(Default::default);
+                open_ai::infer_stream(client, &self.url, headers, model, prompt, params).await
+            }
         }
     }
 
@@ -118,53 +99,47 @@ impl RemoteHttpLlmEngine {
         model: wasi_llm::EmbeddingModel,
         data: Vec<String>,
     ) -> Result<wasi_llm::EmbeddingsResult, wasi_llm::Error> {
+        let model = self.api_model(model);
+        let headers = self.headers()?;
         let client = self.client.get_or_insert_with(Default::default);
+        match self.api_type {
+            ApiType::Default => {
+                default::generate_embeddings(client, &self.url, headers, model, data).await
+            }
+            ApiType::OpenAi => {
+                open_ai::generate_embeddings(client, &self.url, headers, model, data).await
+            }
+        }
+    }
+
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    pub fn api_type(&self) -> ApiType {
+        self.api_type
+    }
+
+    /// The name the API knows the app's model by.
+    fn api_model(&self, model: String) -> String {
+        self.models.get(&model).cloned().unwrap_or(model)
+    }
 
+    /// The headers of a request to the API.
+    fn headers(&self) -> Result<HeaderMap, wasi_llm::Error> {
+        let scheme = match self.api_type {
+            ApiType::Default => "bearer",
+            ApiType::OpenAi => "Bearer",
+        };
         let mut headers = HeaderMap::new();
         headers.insert(
             "authorization",
-            HeaderValue::from_str(&format!("bearer {}", self.auth_token)).map_err(|_| {
+            HeaderValue::from_str(&format!("{scheme} {}", self.auth_token)).map_err(|_| {
                 wasi_llm::Error::RuntimeError("Failed to create authorization header".to_string())
             })?,
         );
         spin_telemetry::inject_trace_context(&mut headers);
-
-        let body = serde_json::to_string(&json!({
-            "model": model,
-            "input": data
-        }))
-        .map_err(|_| wasi_llm::Error::RuntimeError("Failed to serialize JSON".to_string()))?;
-
-        let resp = client
-            .request(
-                reqwest::Method::POST,
-                self.url.join("/embed").map_err(|_| {
-                    wasi_llm::Error::RuntimeError("Failed to create URL".to_string())
-                })?,
-            )
-            .headers(headers)
-            .body(body)
-            .send()
-            .await
-            .map_err(|err| {
-                wasi_llm::Error::RuntimeError(format!("POST /embed request error: {err}"))
-            })?;
-
-        match resp.json::<EmbeddingResponseBody>().await {
-            Ok(val) => Ok(wasi_llm::EmbeddingsResult {
-                embeddings: val.embeddings,
-                usage: wasi_llm::EmbeddingsUsage {
-                    prompt_token_count: val.usage.prompt_token_count,
-                },
-            }),
-            Err(err) => Err(wasi_llm::Error::RuntimeError(format!(
-                "Failed to deserialize response  for \"POST  /embed\": {err}"
-            ))),
-        }
-    }
-
-    pub fn url(&self) -> Url {
-        self.url.clone()
+        Ok(headers)
     }
 }
 
@@ -173,7 +148,21 @@ impl RemoteHttpLlmEngine {
         RemoteHttpLlmEngine {
             url,
             auth_token,
+            api_type: ApiType::default(),
+            models: HashMap::new(),
             client: None,
         }
     }
+
+    /// Sets the API served at the engine's URL.
+    pub fn with_api_type(mut self, api_type: ApiType) -> Self {
+        self.api_type = api_type;
+        self
+    }
+
+    /// Sets the names which the API knows the app's models by.
+    pub fn with_models(mut self, models: HashMap<String, String>) -> Self {
+        self.models = models;
+        self
+    }
 }
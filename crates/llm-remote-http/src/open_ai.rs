@@ -0,0 +1,413 @@
+//! Requests to OpenAI-compatible APIs.
+//!
+//! Inferencing uses the chat completions API, with the prompt as a single
+//! user message. The API has no equivalent of the `top-k` and repeat penalty
+//! params, so they aren't sent.
+
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+use futures::stream::{BoxStream, Stream, StreamExt, TryStreamExt};
+use reqwest::{header::HeaderMap, Client, Response, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use spin_world::v2::llm::{self as wasi_llm};
+
+use crate::InferencingDelta;
+
+#[derive(Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: [ChatMessage<'a>; 1],
+    max_tokens: u32,
+    temperature: f32,
+    top_p: f32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+impl<'a> ChatCompletionRequest<'a> {
+    fn new(model: &'a str, prompt: &'a str, params: &wasi_llm::InferencingParams) -> Self {
+        Self {
+            model,
+            messages: [ChatMessage {
+                role: "user",
+                content: prompt,
+            }],
+            max_tokens: params.max_tokens,
+            temperature: params.temperature,
+            top_p: params.top_p,
+            stream: false,
+            stream_options: None,
+        }
+    }
+
+    /// Asks for the completion to be streamed, ending with its usage.
+    fn streamed(self) -> Self {
+        Self {
+            stream: true,
+            stream_options: Some(StreamOptions {
+                include_usage: true,
+            }),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletion {
+    choices: Vec<ChatChoice>,
+    usage: Usage,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatChoiceMessage {
+    content: Option<String>,
+}
+
+/// An item of a streamed chat completion.
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChatChunkChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct ChatChunkChoice {
+    delta: ChatChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+impl From<Usage> for wasi_llm::InferencingUsage {
+    fn from(usage: Usage) -> Self {
+        Self {
+            prompt_token_count: usage.prompt_tokens,
+            generated_token_count: usage.completion_tokens,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct Embeddings {
+    data: Vec<Embedding>,
+    usage: Usage,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// The body of an error response.
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ApiError,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+    code: Option<String>,
+}
+
+pub(crate) async fn infer(
+    client: &Client,
+    url: &Url,
+    headers: HeaderMap,
+    model: wasi_llm::InferencingModel,
+    prompt: String,
+    params: wasi_llm::InferencingParams,
+) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
+    let request = ChatCompletionRequest::new(&model, &prompt, &params);
+    let resp = post(client, url, "chat/completions", headers, &request).await?;
+    let completion: ChatCompletion = json(resp, "chat/completions").await?;
+    let text = completion
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.message.content)
+        .unwrap_or_default();
+    Ok(wasi_llm::InferencingResult {
+        text,
+        usage: completion.usage.into(),
+    })
+}
+
+pub(crate) async fn infer_stream(
+    client: &Client,
+    url: &Url,
+    headers: HeaderMap,
+    model: wasi_llm::InferencingModel,
+    prompt: String,
+    params: wasi_llm::InferencingParams,
+) -> Result<BoxStream<'static, Result<InferencingDelta, wasi_llm::Error>>, wasi_llm::Error> {
+    let request = ChatCompletionRequest::new(&model, &prompt, &params).streamed();
+    let resp = post(client, url, "chat/completions", headers, &request).await?;
+    Ok(deltas(resp.bytes_stream()).boxed())
+}
+
+pub(crate) async fn generate_embeddings(
+    client: &Client,
+    url: &Url,
+    headers: HeaderMap,
+    model: wasi_llm::EmbeddingModel,
+    data: Vec<String>,
+) -> Result<wasi_llm::EmbeddingsResult, wasi_llm::Error> {
+    let request = EmbeddingsRequest {
+        model: &model,
+        input: &data,
+    };
+    let resp = post(client, url, "embeddings", headers, &request).await?;
+    let mut embeddings: Embeddings = json(resp, "embeddings").await?;
+    embeddings.data.sort_by_key(|embedding| embedding.index);
+    Ok(wasi_llm::EmbeddingsResult {
+        embeddings: embeddings
+            .data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect(),
+        usage: wasi_llm::EmbeddingsUsage {
+            prompt_token_count: embeddings.usage.prompt_tokens,
+        },
+    })
+}
+
+/// Sends a request to the given route of the API, returning the response if
+/// it succeeded.
+async fn post(
+    client: &Client,
+    url: &Url,
+    route: &str,
+    headers: HeaderMap,
+    body: &impl Serialize,
+) -> Result<Response, wasi_llm::Error> {
+    let url = route_url(url, route)?;
+    tracing::info!("Sending remote inference request to {url}");
+    let resp = client
+        .post(url)
+        .headers(headers)
+        .json(body)
+        .send()
+        .await
+        .map_err(|err| {
+            wasi_llm::Error::RuntimeError(format!("POST /{route} request error: {err}"))
+        })?;
+
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.bytes().await.unwrap_or_default();
+    match serde_json::from_slice::<ErrorResponse>(&body) {
+        Ok(ErrorResponse { error }) if error.code.as_deref() == Some("model_not_found") => {
+            Err(wasi_llm::Error::ModelNotSupported)
+        }
+        Ok(ErrorResponse { error }) if status == StatusCode::BAD_REQUEST => {
+            Err(wasi_llm::Error::InvalidInput(error.message))
+        }
+        Ok(ErrorResponse { error }) => Err(wasi_llm::Error::RuntimeError(format!(
+            "POST /{route} failed with {status}: {}",
+            error.message
+        ))),
+        Err(_) => Err(wasi_llm::Error::RuntimeError(format!(
+            "POST /{route} failed with {status}"
+        ))),
+    }
+}
+
+async fn json<T: DeserializeOwned>(resp: Response, route: &str) -> Result<T, wasi_llm::Error> {
+    resp.json().await.map_err(|err| {
+        wasi_llm::Error::RuntimeError(format!(
+            "Failed to deserialize response for \"POST /{route}\": {err}"
+        ))
+    })
+}
+
+/// The URL of a route of the API, relative to the API's URL, whose path
+/// usually includes a version, e.g. `https://api.openai.com/v1`.
+fn route_url(url: &Url, route: &str) -> Result<Url, wasi_llm::Error> {
+    let mut base = url.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(route)
+        .map_err(|_| wasi_llm::Error::RuntimeError("Failed to create URL".to_string()))
+}
+
+/// Parses the server-sent events of a streamed chat completion into the
+/// deltas of the generated text.
+fn deltas(
+    body: impl Stream<Item = reqwest::Result<Bytes>> + Send + 'static,
+) -> impl Stream<Item = Result<InferencingDelta, wasi_llm::Error>> + Send + 'static {
+    struct State {
+        body: BoxStream<'static, Result<Bytes, wasi_llm::Error>>,
+        /// Received bytes not yet making up a whole line.
+        partial: Vec<u8>,
+        /// The data of events received but not yet parsed.
+        events: VecDeque<String>,
+    }
+
+    let state = State {
+        body: body
+            .map_err(|err| {
+                wasi_llm::Error::RuntimeError(format!("Failed to read streamed response: {err}"))
+            })
+            .boxed(),
+        partial: Vec::new(),
+        events: VecDeque::new(),
+    };
+    futures::stream::try_unfold(state, |mut state| async move {
+        loop {
+            if let Some(data) = state.events.pop_front() {
+                if data == "[DONE]" {
+                    return Ok(None);
+                }
+                let chunk: ChatCompletionChunk = serde_json::from_str(&data).map_err(|err| {
+                    wasi_llm::Error::RuntimeError(format!(
+                        "Failed to deserialize streamed response: {err}"
+                    ))
+                })?;
+                let delta = InferencingDelta {
+                    text: chunk
+                        .choices
+                        .into_iter()
+                        .filter_map(|choice| choice.delta.content)
+                        .collect(),
+                    usage: chunk.usage.map(Into::into),
+                };
+                if delta.text.is_empty() && delta.usage.is_none() {
+                    continue;
+                }
+                return Ok(Some((delta, state)));
+            }
+            let Some(bytes) = state.body.next().await.transpose()? else {
+                return Ok(None);
+            };
+            state.partial.extend_from_slice(&bytes);
+            state.events.extend(take_event_data(&mut state.partial));
+        }
+    })
+}
+
+/// Takes the whole lines from the start of the buffer, returning the data of
+/// the `data:` lines among them. Each event of a streamed completion has a
+/// single data line.
+fn take_event_data(buffer: &mut Vec<u8>) -> Vec<String> {
+    let Some(end) = buffer.iter().rposition(|&b| b == b'\n') else {
+        return Vec::new();
+    };
+    let lines: Vec<u8> = buffer.drain(..=end).collect();
+    String::from_utf8_lossy(&lines)
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.trim().to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_are_relative_to_the_api_url() {
+        for url in ["https://api.openai.com/v1", "https://api.openai.com/v1/"] {
+            let url = Url::parse(url).unwrap();
+            assert_eq!(
+                route_url(&url, "chat/completions").unwrap().as_str(),
+                "https://api.openai.com/v1/chat/completions"
+            );
+        }
+    }
+
+    #[test]
+    fn chat_completion_requests_have_a_user_message() {
+        let params = wasi_llm::InferencingParams {
+            max_tokens: 100,
+            repeat_penalty: 1.1,
+            repeat_penalty_last_n_token_count: 64,
+            temperature: 0.5,
+            top_k: 40,
+            top_p: 0.75,
+        };
+        let request = ChatCompletionRequest::new("gpt-4o-mini", "Hello", &params);
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "model": "gpt-4o-mini",
+                "messages": [{ "role": "user", "content": "Hello" }],
+                "max_tokens": 100,
+                "temperature": 0.5,
+                "top_p": 0.75,
+            })
+        );
+        let streamed = serde_json::to_value(request.streamed()).unwrap();
+        assert_eq!(streamed["stream"], true);
+        assert_eq!(streamed["stream_options"]["include_usage"], true);
+    }
+
+    #[tokio::test]
+    async fn streamed_completions_are_parsed_into_deltas() {
+        let events = [
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":\"\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n",
+            "\ndata: {\"choices\":[{\"delta\":{\"cont",
+            "ent\":\"lo!\"}}]}\r\n\r\n: keep-alive\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":2}}\n\n",
+            "data: [DONE]\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"ignored\"}}]}\n\n",
+        ];
+        let body = futures::stream::iter(events.map(|event| Ok(Bytes::from(event))));
+        let deltas: Vec<_> = deltas(body).try_collect().await.unwrap();
+        assert_eq!(
+            deltas,
+            [
+                InferencingDelta {
+                    text: "Hel".into(),
+                    usage: None
+                },
+                InferencingDelta {
+                    text: "lo!".into(),
+                    usage: None
+                },
+                InferencingDelta {
+                    text: String::new(),
+                    usage: Some(wasi_llm::InferencingUsage {
+                        prompt_token_count: 5,
+                        generated_token_count: 2,
+                    })
+                },
+            ]
+        );
+    }
+}
@@ -14,6 +14,8 @@ const REGISTRY_CACHE_DIR: &str = "registry";
 const MANIFESTS_DIR: &str = "manifests";
 const WASM_DIR: &str = "wasm";
 const DATA_DIR: &str = "data";
+const COMPOSED_DIR: &str = "composed";
+const PARTIAL_DIR: &str = "partial";
 
 /// Cache for registry entities.
 #[derive(Debug)]
@@ -75,6 +77,13 @@ impl Cache {
         Ok(path)
     }
 
+    /// Return the path to a composed component given the key of its
+    /// composition, if it has been cached.
+    pub fn composed_file(&self, key: impl AsRef<str>) -> Option<PathBuf> {
+        let path = self.composed_path(key);
+        path.exists().then_some(path)
+    }
+
     /// Return the path to a data file given its digest.
     pub fn data_file(&self, digest: impl AsRef<str>) -> Result<PathBuf> {
         let path = self.data_path(&digest);
@@ -105,11 +114,34 @@ impl Cache {
         self.wasm_dir().join(safe_name(digest).as_ref())
     }
 
-    /// The path of contents in the cache's wasm directory, which may or may not exist.
+    /// Write a composed component in the cache's composed directory.
+    pub async fn write_composed(
+        &self,
+        bytes: impl AsRef<[u8]>,
+        key: impl AsRef<str>,
+    ) -> Result<()> {
+        self.ensure_dirs().await?;
+        write_file(&self.composed_path(key), bytes.as_ref()).await?;
+        Ok(())
+    }
+
+    /// The path of contents in the cache's data directory, which may or may not exist.
     pub fn data_path(&self, digest: impl AsRef<str>) -> PathBuf {
         self.data_dir().join(safe_name(digest).as_ref())
     }
 
+    /// The path of a composed component in the cache's composed directory,
+    /// which may or may not exist.
+    pub fn composed_path(&self, key: impl AsRef<str>) -> PathBuf {
+        self.root.join(COMPOSED_DIR).join(safe_name(key).as_ref())
+    }
+
+    /// The path to which content is downloaded before it is complete and
+    /// verified, which may or may not exist.
+    pub fn partial_path(&self, digest: impl AsRef<str>) -> PathBuf {
+        self.root.join(PARTIAL_DIR).join(safe_name(digest).as_ref())
+    }
+
     /// Ensure the expected configuration directories are found in the root.
     ///
     /// ```text
@@ -118,6 +150,8 @@ impl Cache {
     ///             └──manifests
     ///             └──wasm
     ///             └──data
+    ///             └──composed
+    ///             └──partial
     /// ```
     pub async fn ensure_dirs(&self) -> Result<()> {
         tracing::debug!("using cache root directory {}", self.root.display());
@@ -151,6 +185,15 @@ impl Cache {
                 .with_context(|| format!("failed to create assets directory `{}`", p.display()))?;
         }
 
+        for dir in [COMPOSED_DIR, PARTIAL_DIR] {
+            let p = root.join(dir);
+            if !p.is_dir() {
+                create_dir_all(&p).await.with_context(|| {
+                    format!("failed to create {dir} directory `{}`", p.display())
+                })?;
+            }
+        }
+
         self.dirs_ensured_once.store(true, Ordering::Relaxed);
 
         Ok(())
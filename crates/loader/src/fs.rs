@@ -18,10 +18,6 @@ mod io {
     pub async fn copy(from: &Path, to: &Path) -> Result<u64> {
         tokio::fs::copy(from, to).await.map_err(Into::into)
     }
-
-    pub async fn metadata(path: &Path) -> Result<std::fs::Metadata> {
-        tokio::fs::metadata(path).await.map_err(Into::into)
-    }
 }
 
 #[cfg(not(feature = "async-io"))]
@@ -41,10 +37,6 @@ mod io {
     pub async fn copy(from: &Path, to: &Path) -> Result<u64> {
         Ok(std::fs::copy(from, to)?)
     }
-
-    pub async fn metadata(path: &Path) -> Result<std::fs::Metadata> {
-        Ok(std::fs::metadata(path)?)
-    }
 }
 
 pub use io::*;
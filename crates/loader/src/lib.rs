@@ -22,6 +22,9 @@ mod fs;
 #[cfg(feature = "async-io")]
 mod http;
 mod local;
+pub mod lockfile;
+
+pub use lockfile::LockfileMode;
 
 /// Maximum number of files to copy (or download) concurrently
 pub(crate) const MAX_FILE_LOADING_CONCURRENCY: usize = 16;
@@ -33,10 +36,30 @@ pub async fn from_file(
     manifest_path: impl AsRef<Path>,
     files_mount_strategy: FilesMountStrategy,
     cache_root: Option<PathBuf>,
+) -> Result<LockedApp> {
+    from_file_with_options(
+        manifest_path,
+        files_mount_strategy,
+        cache_root,
+        LoadOptions::default(),
+    )
+    .await
+}
+
+/// Load a Spin locked app from a spin.toml manifest file, as [`from_file`],
+/// with the given options.
+pub async fn from_file_with_options(
+    manifest_path: impl AsRef<Path>,
+    files_mount_strategy: FilesMountStrategy,
+    cache_root: Option<PathBuf>,
+    options: LoadOptions,
 ) -> Result<LockedApp> {
     let path = manifest_path.as_ref();
     let app_root = parent_dir(path).context("manifest path has no parent directory")?;
-    let loader = LocalLoader::new(&app_root, files_mount_strategy, cache_root).await?;
+    let mut loader = LocalLoader::new(&app_root, files_mount_strategy, cache_root).await?;
+    loader.set_lockfile_mode(options.lockfile_mode)?;
+    loader.set_environment(options.environment);
+    loader.set_deny_unknown_fields(options.deny_unknown_fields);
     loader.load_file(path).await
 }
 
@@ -48,6 +71,19 @@ pub async fn from_wasm_file(wasm_path: impl AsRef<Path>) -> Result<LockedApp> {
     loader.load_manifest(manifest).await
 }
 
+/// Options for loading a Spin app from a manifest file.
+#[derive(Debug, Default)]
+pub struct LoadOptions {
+    /// How to treat the `spin.lock` file alongside the manifest.
+    pub lockfile_mode: LockfileMode,
+    /// The manifest environment (`[environments.<name>]`) to apply, if any.
+    /// The app uses the variables profile of the same name by default.
+    pub environment: Option<String>,
+    /// Whether manifest fields which Spin does not recognise, or which are
+    /// deprecated, are errors. Otherwise they are reported as warnings.
+    pub deny_unknown_fields: bool,
+}
+
 /// The strategy to use for mounting WASI files into a guest.
 #[derive(Debug)]
 pub enum FilesMountStrategy {
@@ -1,25 +1,35 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use futures::{future::try_join_all, StreamExt};
+use futures::{
+    future::{try_join_all, BoxFuture},
+    FutureExt, StreamExt,
+};
 use reqwest::Url;
 use spin_common::{paths::parent_dir, sloth, ui::quoted_path};
 use spin_locked_app::{
     locked::{
         self, ContentPath, ContentRef, LockedApp, LockedComponent, LockedComponentDependency,
-        LockedComponentSource, LockedTrigger,
+        LockedComponentSource, LockedMap, LockedTrigger,
     },
     values::{ValuesMap, ValuesMapBuilder},
 };
-use spin_manifest::schema::v2::{self, AppManifest, KebabId, WasiFilesMount};
+use spin_manifest::{
+    schema::v2::{self, AppManifest, KebabId, SymlinkPolicy, WasiFilesMount},
+    validate::{Diagnostic, Severity},
+};
 use spin_outbound_networking_config::allowed_hosts::{
     AllowedHostsConfig, SERVICE_CHAINING_DOMAIN_SUFFIX,
 };
-use spin_serde::DependencyName;
+use spin_serde::{aliases::collect_deprecations, DependencyName, LowerSnakeId};
 use std::collections::BTreeMap;
 use tokio::{io::AsyncWriteExt, sync::Semaphore};
 
-use crate::{cache::Cache, FilesMountStrategy};
+use crate::{
+    cache::Cache,
+    lockfile::{LockedPackage, LockedSource, Lockfile, LockfileMode, LOCKFILE_NAME},
+    FilesMountStrategy,
+};
 
 #[derive(Debug)]
 pub struct LocalLoader {
@@ -27,6 +37,15 @@ pub struct LocalLoader {
     files_mount_strategy: FilesMountStrategy,
     cache: Cache,
     file_loading_permits: Semaphore,
+    lockfile_mode: LockfileMode,
+    // The lockfile as read from the app root
+    pinned: Lockfile,
+    // The sources resolved while loading
+    resolved: std::sync::Mutex<Lockfile>,
+    // The manifest environment to apply, if any
+    environment: Option<String>,
+    // Whether unknown or deprecated manifest fields are errors
+    deny_unknown_fields: bool,
 }
 
 impl LocalLoader {
@@ -43,25 +62,111 @@ impl LocalLoader {
             cache: Cache::new(cache_root).await?,
             // Limit concurrency to avoid hitting system resource limits
             file_loading_permits: Semaphore::new(crate::MAX_FILE_LOADING_CONCURRENCY),
+            lockfile_mode: LockfileMode::Ignore,
+            pinned: Default::default(),
+            resolved: Default::default(),
+            environment: None,
+            deny_unknown_fields: false,
         })
     }
 
+    // Sets how the lockfile in the app root is used, reading it if needed.
+    pub fn set_lockfile_mode(&mut self, mode: LockfileMode) -> Result<()> {
+        self.pinned = match mode {
+            LockfileMode::Ignore => Default::default(),
+            LockfileMode::Update | LockfileMode::Locked => {
+                Lockfile::from_file(self.lockfile_path())?.unwrap_or_default()
+            }
+        };
+        self.lockfile_mode = mode;
+        Ok(())
+    }
+
+    // Sets the manifest environment (`[environments.<name>]`) to apply.
+    pub fn set_environment(&mut self, environment: Option<String>) {
+        self.environment = environment;
+    }
+
+    // Sets whether unknown or deprecated manifest fields are errors, rather
+    // than warnings.
+    pub fn set_deny_unknown_fields(&mut self, deny: bool) {
+        self.deny_unknown_fields = deny;
+    }
+
+    fn lockfile_path(&self) -> PathBuf {
+        self.app_root.join(LOCKFILE_NAME)
+    }
+
     // Load the manifest file (spin.toml) at the given path into a LockedApp,
     // preparing all its content for execution.
     pub async fn load_file(&self, path: impl AsRef<Path>) -> Result<LockedApp> {
-        // Parse manifest
         let path = path.as_ref();
-        let manifest = spin_manifest::manifest_from_file(path).with_context(|| {
+
+        // Check fields before parsing, as an unknown field may be why the
+        // manifest fails to parse. Parsing reports any TOML syntax errors.
+        let field_diagnostics =
+            spin_manifest::validate::validate_file_fields(path).unwrap_or_default();
+        if !field_diagnostics.is_empty() {
+            let described = field_diagnostics
+                .iter()
+                .map(|diagnostic| describe_diagnostic(path, diagnostic))
+                .collect::<Vec<_>>();
+            if self.deny_unknown_fields {
+                bail!(
+                    "Spin app manifest {} has unknown or deprecated fields:\n{}",
+                    quoted_path(path),
+                    described.join("\n")
+                );
+            }
+            for description in described {
+                terminal::warn!("{description}");
+            }
+        }
+
+        // Parse manifest
+        let (manifest, deprecations) =
+            collect_deprecations(|| spin_manifest::manifest_from_file(path));
+        let manifest = manifest.with_context(|| {
             format!(
                 "Failed to read Spin app manifest from {}",
                 quoted_path(path)
             )
         })?;
+        for deprecation in deprecations {
+            terminal::warn!("{deprecation}");
+        }
+
+        // Report every problem in the manifest, rather than only the first
+        // one loading runs into
+        let diagnostics = spin_manifest::validate::validate_parsed_file(path, &manifest)
+            .with_context(|| {
+                format!("Failed to validate Spin app manifest {}", quoted_path(path))
+            })?;
+        let (errors, warnings): (Vec<_>, Vec<_>) = diagnostics
+            .iter()
+            .partition(|diagnostic| diagnostic.severity == Severity::Error);
+        for warning in warnings {
+            terminal::warn!("{}", describe_diagnostic(path, warning));
+        }
+        if !errors.is_empty() {
+            bail!(
+                "Spin app manifest {} is invalid:\n{}",
+                quoted_path(path),
+                errors
+                    .iter()
+                    .map(|error| describe_diagnostic(path, error))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+
         let mut locked = self
             .load_manifest(manifest)
             .await
             .with_context(|| format!("Failed to load Spin app from {}", quoted_path(path)))?;
 
+        self.finish_lockfile()?;
+
         // Set origin metadata
         locked
             .metadata
@@ -72,24 +177,52 @@ impl LocalLoader {
 
     // Load the given manifest into a LockedApp, ready for execution.
     pub(crate) async fn load_manifest(&self, mut manifest: AppManifest) -> Result<LockedApp> {
-        spin_manifest::normalize::normalize_manifest(&mut manifest);
+        let normalization = spin_manifest::normalize::normalize_manifest(&mut manifest);
+
+        if let Some(environment) = &self.environment {
+            manifest
+                .apply_environment(environment)
+                .with_context(|| format!("Failed to apply environment {environment:?}"))?;
+        }
+
+        spin_manifest::normalize::normalize_chains(&mut manifest)?;
 
         manifest.validate_dependencies()?;
+        manifest.validate_http_routes()?;
+        manifest.validate_file_destinations()?;
 
         let AppManifest {
             spin_manifest_version: _,
             application,
             variables,
+            profiles,
+            environments: _,
             triggers,
             components,
         } = manifest;
 
-        let metadata = locked_metadata(application, triggers.keys().cloned())?;
+        let mut metadata = locked_metadata(application, triggers.keys().cloned())?;
 
-        let variables = variables
-            .into_iter()
-            .map(|(name, v)| Ok((name.to_string(), locked_variable(v)?)))
-            .collect::<Result<_>>()?;
+        let variables = locked_variables(variables)?;
+
+        if !profiles.is_empty() {
+            // An environment's variables are those of the profile of the same
+            // name, which the app uses unless another is selected at startup.
+            if let Some(environment) = &self.environment {
+                if profiles.keys().any(|name| name.as_ref() == environment) {
+                    metadata.insert("variables_profile".into(), environment.clone().into());
+                }
+            }
+            let profiles = profiles
+                .into_iter()
+                .map(|(name, profile)| {
+                    let variables = locked_variables(profile.variables)
+                        .with_context(|| format!("invalid profile {name:?}"))?;
+                    Ok((name.to_string(), variables))
+                })
+                .collect::<Result<LockedMap<_>>>()?;
+            metadata.insert("variable_profiles".into(), serde_json::to_value(profiles)?);
+        }
 
         let triggers = triggers
             .into_iter()
@@ -104,10 +237,15 @@ impl LocalLoader {
         let sloth_guard = warn_if_component_load_slothful();
 
         // Load all components concurrently
-        let components = try_join_all(components.into_iter().map(|(id, c)| async move {
-            self.load_component(&id, c)
+        let inline_origins = &normalization.inline_components;
+        let all_components = &components;
+        let components = try_join_all(components.iter().map(|(id, c)| async move {
+            self.load_component(id, c.clone(), all_components)
                 .await
-                .with_context(|| format!("Failed to load component `{id}`"))
+                .with_context(|| match inline_origins.get(id) {
+                    Some(origin) => format!("Failed to load component `{id}` ({origin})"),
+                    None => format!("Failed to load component `{id}`"),
+                })
         }))
         .await?;
 
@@ -140,12 +278,18 @@ impl LocalLoader {
         &self,
         id: &KebabId,
         component: v2::Component,
+        components: &v2::Map<KebabId, v2::Component>,
     ) -> Result<LockedComponent> {
-        let allowed_outbound_hosts = component
-            .normalized_allowed_outbound_hosts()
-            .context("`allowed_http_hosts` is malformed")?;
-        AllowedHostsConfig::validate(&allowed_outbound_hosts)
-            .context("`allowed_outbound_hosts` is malformed")?;
+        let (allowed_outbound_hosts, deprecations) =
+            collect_deprecations(|| component.normalized_allowed_outbound_hosts());
+        let allowed_outbound_hosts =
+            allowed_outbound_hosts.context("`allowed_http_hosts` is malformed")?;
+        for deprecation in deprecations {
+            terminal::warn!("Component {id}: {deprecation}");
+        }
+        AllowedHostsConfig::validate(&allowed_outbound_hosts).with_context(|| {
+            format!("Component {id} has a malformed `allowed_outbound_hosts` entry")
+        })?;
 
         let component_requires_service_chaining = requires_service_chaining(&component);
 
@@ -155,7 +299,48 @@ impl LocalLoader {
             .string_array("key_value_stores", component.key_value_stores)
             .string_array("databases", component.sqlite_databases)
             .string_array("ai_models", component.ai_models)
+            .string_array(
+                "allowed_email_recipients",
+                component.allowed_email_recipients,
+            )
+            .string_array("vector_stores", component.vector_stores)
+            .string_array("blob_stores", component.blob_stores)
             .serializable("build", component.build)?
+            .serializable(
+                "memory_limit",
+                component.memory_limit.as_ref().map(|limit| limit.bytes()),
+            )?
+            .serializable(
+                "execution_timeout_ms",
+                component.execution_timeout.as_ref().map(|timeout| {
+                    u64::try_from(timeout.duration().as_millis()).unwrap_or(u64::MAX)
+                }),
+            )?
+            .serializable("instance_pool_size", component.instance_pool_size)?
+            .serializable("instance_pool_queue", component.instance_pool_queue)?
+            .serializable(
+                "instance_pool_timeout_ms",
+                component.instance_pool_timeout.as_ref().map(|timeout| {
+                    u64::try_from(timeout.duration().as_millis()).unwrap_or(u64::MAX)
+                }),
+            )?
+            .serializable("warm_instances", component.warm_instances)?
+            .serializable(
+                "warm_instance_idle_timeout_ms",
+                component
+                    .warm_instance_idle_timeout
+                    .as_ref()
+                    .map(|timeout| {
+                        u64::try_from(timeout.duration().as_millis()).unwrap_or(u64::MAX)
+                    }),
+            )?
+            .serializable("health_check", component.health_check.then_some(true))?
+            .serializable(
+                "health_check_timeout_ms",
+                component.health_check_timeout.as_ref().map(|timeout| {
+                    u64::try_from(timeout.duration().as_millis()).unwrap_or(u64::MAX)
+                }),
+            )?
             .take();
 
         let source = self
@@ -168,6 +353,7 @@ impl LocalLoader {
                 id,
                 component.dependencies_inherit_configuration,
                 &component.dependencies,
+                components,
             )
             .await?;
 
@@ -178,18 +364,36 @@ impl LocalLoader {
         } else {
             match &self.files_mount_strategy {
                 FilesMountStrategy::Copy(files_mount_root) => {
-                    let component_mount_root = files_mount_root.join(id.as_ref());
-                    // Copy mounted files into component mount root, concurrently
-                    try_join_all(component.files.iter().map(|f| {
-                        self.copy_file_mounts(f, &component_mount_root, &component.exclude_files)
-                    }))
+                    // Resolve globs up front, so that every load of the app
+                    // lays out the same files in the same mounts
+                    let mounts =
+                        self.resolve_file_mounts(&component.files, &component.exclude_files)?;
+                    let mount_roots = (0..mounts.len())
+                        .map(|index| match index {
+                            0 => files_mount_root.join(id.as_ref()),
+                            _ => files_mount_root.join(format!("{id}.mount{index}")),
+                        })
+                        .collect::<Vec<_>>();
+                    // Copy mounted files into the mount roots, concurrently
+                    try_join_all(
+                        mounts
+                            .iter()
+                            .zip(&mount_roots)
+                            .map(|(mount, root)| self.copy_resolved_mount(mount, root)),
+                    )
                     .await?;
 
-                    // All component files (copies) are in `component_mount_root` now
-                    vec![ContentPath {
-                        content: file_content_ref(component_mount_root)?,
-                        path: "/".into(),
-                    }]
+                    mounts
+                        .into_iter()
+                        .zip(mount_roots)
+                        .map(|(mount, root)| {
+                            Ok(ContentPath {
+                                content: file_content_ref(root)?,
+                                path: mount.destination.into(),
+                                read_only: mount.read_only,
+                            })
+                        })
+                        .collect::<Result<_>>()?
                 }
                 FilesMountStrategy::Direct => {
                     ensure!(
@@ -233,33 +437,40 @@ impl LocalLoader {
         })
     }
 
-    async fn load_component_dependencies(
-        &self,
-        id: &KebabId,
+    // Boxed because a dependency on another component loads that component's
+    // dependencies in turn.
+    fn load_component_dependencies<'a>(
+        &'a self,
+        id: &'a KebabId,
         inherit_configuration: bool,
-        dependencies: &v2::ComponentDependencies,
-    ) -> Result<BTreeMap<DependencyName, LockedComponentDependency>> {
-        Ok(try_join_all(dependencies.inner.iter().map(
-            |(dependency_name, dependency)| async move {
-                let locked_dependency = self
-                    .load_component_dependency(
-                        inherit_configuration,
-                        dependency_name.clone(),
-                        dependency.clone(),
-                    )
-                    .await
-                    .with_context(|| {
-                        format!(
+        dependencies: &'a v2::ComponentDependencies,
+        components: &'a v2::Map<KebabId, v2::Component>,
+    ) -> BoxFuture<'a, Result<BTreeMap<DependencyName, LockedComponentDependency>>> {
+        async move {
+            Ok(try_join_all(dependencies.inner.iter().map(
+                |(dependency_name, dependency)| async move {
+                    let locked_dependency = self
+                        .load_component_dependency(
+                            inherit_configuration,
+                            dependency_name.clone(),
+                            dependency.clone(),
+                            components,
+                        )
+                        .await
+                        .with_context(|| {
+                            format!(
                             "Failed to load component dependency `{dependency_name}` for `{id}`"
                         )
-                    })?;
+                        })?;
 
-                anyhow::Ok((dependency_name.clone(), locked_dependency))
-            },
-        ))
-        .await?
-        .into_iter()
-        .collect())
+                    anyhow::Ok((dependency_name.clone(), locked_dependency))
+                },
+            ))
+            .await?
+            .into_iter()
+            .collect())
+        }
+        .boxed()
     }
 
     async fn load_component_dependency(
@@ -267,6 +478,7 @@ impl LocalLoader {
         inherit_configuration: bool,
         dependency_name: DependencyName,
         dependency: v2::ComponentDependency,
+        components: &v2::Map<KebabId, v2::Component>,
     ) -> Result<LockedComponentDependency> {
         let (content, export) = match dependency {
             v2::ComponentDependency::Version(version) => {
@@ -330,6 +542,33 @@ impl LocalLoader {
                 let content = self.load_http_source(&url, &digest).await?;
                 (content, export)
             }
+            v2::ComponentDependency::Component { component, export } => {
+                // The component runs as part of its dependent, so inherits
+                // its configuration, but its own dependencies are composed
+                // into it as for any component.
+                let id = component;
+                let component = components.get(&id).with_context(|| {
+                    format!("Component dependency {dependency_name:?} refers to undefined component {id:?}")
+                })?;
+                let source = self
+                    .load_component_source(&id, component.source.clone())
+                    .await
+                    .with_context(|| format!("Failed to load Wasm source {}", component.source))?;
+                let dependencies = self
+                    .load_component_dependencies(
+                        &id,
+                        component.dependencies_inherit_configuration,
+                        &component.dependencies,
+                        components,
+                    )
+                    .await?;
+                return Ok(LockedComponentDependency {
+                    source,
+                    export,
+                    inherit: locked::InheritConfiguration::All,
+                    dependencies,
+                });
+            }
         };
 
         Ok(LockedComponentDependency {
@@ -343,6 +582,7 @@ impl LocalLoader {
             } else {
                 locked::InheritConfiguration::Some(vec![])
             },
+            dependencies: Default::default(),
         })
     }
 
@@ -379,10 +619,9 @@ impl LocalLoader {
     // Load a Wasm source from the given HTTP ContentRef source URL and
     // return a ContentRef an absolute path to the local copy.
     async fn load_http_source(&self, url: &str, digest: &str) -> Result<ContentRef> {
-        ensure!(
-            digest.starts_with("sha256:"),
-            "invalid `digest` {digest:?}; must start with 'sha256:'"
-        );
+        spin_serde::DigestRef::validate(digest)
+            .map_err(|e| anyhow!("invalid `digest` {digest:?}: {e}"))?;
+        self.lock_source(url, digest)?;
         let path = if let Ok(cached_path) = self.cache.wasm_file(digest) {
             cached_path
         } else {
@@ -417,30 +656,46 @@ impl LocalLoader {
         }
         let pkg_loader = wasm_pkg_client::Client::new(client_config);
 
-        let mut releases = pkg_loader.list_all_versions(package).await.map_err(|e| {
-            if matches!(e, wasm_pkg_client::Error::NoRegistryForNamespace(_)) && registry.is_none() {
-                anyhow!("No default registry specified for wasm-pkg-loader. Create a default config, or set `registry` for package {package:?}")
-            } else {
-                e.into()
-            }
-        })?;
+        let pinned = self.pinned_package(registry, package, version)?;
 
-        releases.sort();
+        let release_version = match &pinned {
+            Some(pinned) => pinned.version.parse()?,
+            None => {
+                let mut releases = pkg_loader.list_all_versions(package).await.map_err(|e| {
+                    if matches!(e, wasm_pkg_client::Error::NoRegistryForNamespace(_)) && registry.is_none() {
+                        anyhow!("No default registry specified for wasm-pkg-loader. Create a default config, or set `registry` for package {package:?}")
+                    } else {
+                        e.into()
+                    }
+                })?;
 
-        let release_version = releases
-            .iter()
-            .rev()
-            .find(|release| version.matches(&release.version) && !release.yanked)
-            .with_context(|| format!("No matching version found for {package} {version}",))?;
+                releases.sort();
 
-        let release = pkg_loader
-            .get_release(package, &release_version.version)
-            .await?;
+                releases
+                    .iter()
+                    .rev()
+                    .find(|release| version.matches(&release.version) && !release.yanked)
+                    .with_context(|| format!("No matching version found for {package} {version}",))?
+                    .version
+                    .clone()
+            }
+        };
+
+        let release = pkg_loader.get_release(package, &release_version).await?;
 
         let digest = match &release.content_digest {
             wasm_pkg_client::ContentDigest::Sha256 { hex } => format!("sha256:{hex}"),
         };
 
+        if let Some(pinned) = &pinned {
+            ensure!(
+                pinned.digest == digest,
+                "Content of {package}@{release_version} has changed since it was pinned in {LOCKFILE_NAME}: expected digest {:?} but the registry has {digest:?}",
+                pinned.digest,
+            );
+        }
+        self.lock_package(registry, package, version, &release_version, &digest);
+
         let path = if let Ok(cached_path) = self.cache.wasm_file(&digest) {
             cached_path
         } else {
@@ -463,36 +718,197 @@ impl LocalLoader {
         file_content_ref(path)
     }
 
-    // Copy content(s) from the given `mount`
-    async fn copy_file_mounts(
+    // Returns the lockfile entry for a registry package, if the lockfile is
+    // in use and pins a version matching the requirement.
+    fn pinned_package(
         &self,
-        mount: &WasiFilesMount,
-        dest_root: &Path,
-        exclude_files: &[String],
-    ) -> Result<()> {
-        match mount {
-            WasiFilesMount::Pattern(pattern) => {
-                self.copy_glob_or_path(pattern, dest_root, exclude_files)
-                    .await
+        registry: Option<&wasm_pkg_client::Registry>,
+        package: &wasm_pkg_client::PackageRef,
+        version: &semver::VersionReq,
+    ) -> Result<Option<LockedPackage>> {
+        if self.lockfile_mode == LockfileMode::Ignore {
+            return Ok(None);
+        }
+        let registry = registry.map(|r| r.to_string());
+        let pinned = self
+            .pinned
+            .package(
+                &package.to_string(),
+                registry.as_deref(),
+                &version.to_string(),
+            )
+            .filter(|pinned| {
+                semver::Version::parse(&pinned.version)
+                    .is_ok_and(|pinned_version| version.matches(&pinned_version))
+            });
+        if pinned.is_none() && self.lockfile_mode == LockfileMode::Locked {
+            bail!("{LOCKFILE_NAME} is out of date: package {package} {version} is not pinned");
+        }
+        Ok(pinned.cloned())
+    }
+
+    fn lock_package(
+        &self,
+        registry: Option<&wasm_pkg_client::Registry>,
+        package: &wasm_pkg_client::PackageRef,
+        requirement: &semver::VersionReq,
+        version: &semver::Version,
+        digest: &str,
+    ) {
+        if self.lockfile_mode == LockfileMode::Ignore {
+            return;
+        }
+        self.resolved
+            .lock()
+            .unwrap()
+            .packages
+            .insert(LockedPackage {
+                name: package.to_string(),
+                registry: registry.map(|r| r.to_string()),
+                requirement: requirement.to_string(),
+                version: version.to_string(),
+                digest: digest.to_owned(),
+            });
+    }
+
+    fn lock_source(&self, url: &str, digest: &str) -> Result<()> {
+        if self.lockfile_mode == LockfileMode::Ignore {
+            return Ok(());
+        }
+        if self.lockfile_mode == LockfileMode::Locked {
+            ensure!(
+                self.pinned.contains_source(url, digest),
+                "{LOCKFILE_NAME} is out of date: {url:?} with digest {digest:?} is not pinned"
+            );
+        }
+        self.resolved.lock().unwrap().sources.insert(LockedSource {
+            url: url.to_owned(),
+            digest: digest.to_owned(),
+        });
+        Ok(())
+    }
+
+    // Compares the sources resolved while loading with the lockfile, then
+    // updates it or fails on drift according to the lockfile mode.
+    fn finish_lockfile(&self) -> Result<()> {
+        let resolved = std::mem::take(&mut *self.resolved.lock().unwrap());
+        if resolved == self.pinned {
+            return Ok(());
+        }
+        match self.lockfile_mode {
+            LockfileMode::Ignore => Ok(()),
+            LockfileMode::Update => {
+                let path = self.lockfile_path();
+                resolved.write(&path)?;
+                terminal::einfo!(
+                    "Updated",
+                    "{} to pin the app's remote sources",
+                    quoted_path(&path)
+                );
+                Ok(())
             }
-            WasiFilesMount::Placement {
-                source,
-                destination,
-            } => {
-                let src = Path::new(source);
-                let dest = dest_root.join(destination.trim_start_matches('/'));
-                self.copy_file_or_directory(src, &dest, destination, exclude_files)
-                    .await
+            LockfileMode::Locked => {
+                bail!("{LOCKFILE_NAME} is out of date: it pins sources the app no longer uses")
             }
         }
     }
 
-    // Copy files matching glob pattern or single file/directory path.
-    async fn copy_glob_or_path(
+    // Resolve the component's `files` entries to an explicit set of files for
+    // each mount. Entries share a single mount at the guest root, except for
+    // read-only placements, which are mounted at their destinations.
+    fn resolve_file_mounts(
         &self,
-        glob_or_path: &str,
-        dest_root: &Path,
+        mounts: &[WasiFilesMount],
         exclude_files: &[String],
+    ) -> Result<Vec<ResolvedMount>> {
+        let exclude_files = glob_patterns(exclude_files, "exclude_files")?;
+        let mut root_mount = ResolvedMount::new("/", false);
+        let mut read_only_mounts = BTreeMap::<String, ResolvedMount>::new();
+
+        for mount in mounts {
+            match mount {
+                WasiFilesMount::Pattern(pattern) => {
+                    let filter = FileFilter {
+                        exclude_files: &exclude_files,
+                        exclude: &[],
+                        symlinks: SymlinkPolicy::Follow,
+                    };
+                    self.resolve_glob_or_path(pattern, &filter, &mut root_mount)?;
+                }
+                WasiFilesMount::Placement {
+                    source,
+                    destination,
+                    exclude,
+                    read_only,
+                    symlinks,
+                } => {
+                    let exclude = glob_patterns(exclude, "exclude")?;
+                    let filter = FileFilter {
+                        exclude_files: &exclude_files,
+                        exclude: &exclude,
+                        symlinks: *symlinks,
+                    };
+                    let src = self.app_root.join(source);
+                    let meta = std::fs::metadata(&src).map_err(|e| {
+                        explain_file_mount_source_error(e.into(), Path::new(source))
+                    })?;
+                    if meta.is_dir() {
+                        // { source = "host/dir", destination = "guest/dir" }
+                        let (mount, dest_prefix) = if *read_only {
+                            let mount = read_only_mounts
+                                .entry(destination.clone())
+                                .or_insert_with(|| ResolvedMount::new(destination, true));
+                            (mount, PathBuf::new())
+                        } else {
+                            let dest_prefix = PathBuf::from(destination.trim_start_matches('/'));
+                            (&mut root_mount, dest_prefix)
+                        };
+                        self.resolve_glob(&src.join("**/*"), &src, &dest_prefix, &filter, mount)?;
+                    } else {
+                        // { source = "host/file.txt", destination = "guest/file.txt" }
+                        if Self::is_directory_like(destination) {
+                            bail!(r#""{destination}" is not a valid destination file name"#);
+                        }
+                        if !check_symlinks(&src, &src, *symlinks)? {
+                            continue;
+                        }
+                        if *read_only {
+                            // Directories can be mounted but files cannot, so
+                            // mount the directory containing the file
+                            let (parent, file_name) =
+                                destination.rsplit_once('/').unwrap_or(("", destination));
+                            let parent = if parent.is_empty() { "/" } else { parent };
+                            read_only_mounts
+                                .entry(parent.to_owned())
+                                .or_insert_with(|| ResolvedMount::new(parent, true))
+                                .insert(file_name.into(), src);
+                        } else {
+                            let guest_path = destination.trim_start_matches('/');
+                            root_mount.insert(guest_path.into(), src);
+                        }
+                    }
+                }
+            }
+        }
+
+        if read_only_mounts.contains_key("/") && !root_mount.files.is_empty() {
+            bail!("Files mounted read-only at \"/\" cannot be combined with other files. Mount them at a subdirectory instead.");
+        }
+        // Only mount the guest root if something will be there
+        let mut resolved = vec![];
+        if read_only_mounts.is_empty() || !root_mount.files.is_empty() {
+            resolved.push(root_mount);
+        }
+        resolved.extend(read_only_mounts.into_values());
+        Ok(resolved)
+    }
+
+    // Resolve files matching glob pattern or single file/directory path.
+    fn resolve_glob_or_path(
+        &self,
+        glob_or_path: &str,
+        filter: &FileFilter,
+        mount: &mut ResolvedMount,
     ) -> Result<()> {
         if glob_or_path == ".." || glob_or_path.ends_with("/..") {
             bail!("A file pattern can't end in a parent directory path (..)\nIf you want to include a directory, use source-destination form, or a glob pattern ending in **/*.\nLearn more: https://spinframework.dev/writing-apps#including-files-with-components");
@@ -510,57 +926,32 @@ impl LocalLoader {
 
         let path = self.app_root.join(glob_or_path);
         if path.exists() {
-            let dest = dest_root.join(glob_or_path);
             if path.is_dir() {
                 // "single/dir"
                 let pattern = path.join("**/*");
-                self.copy_glob(&pattern, &self.app_root, &dest, exclude_files)
-                    .await?;
+                self.resolve_glob(&pattern, &self.app_root, Path::new(""), filter, mount)?;
             } else {
                 // "single/file.txt"
-                self.copy_single_file(&path, &dest, glob_or_path).await?;
+                mount.insert(glob_or_path.into(), path);
             }
         } else if looks_like_glob_pattern(glob_or_path) {
             // "glob/pattern/*"
-            self.copy_glob(&path, &self.app_root, dest_root, exclude_files)
-                .await?;
+            self.resolve_glob(&path, &self.app_root, Path::new(""), filter, mount)?;
         } else {
             bail!("{glob_or_path:?} does not exist and doesn't appear to be a glob pattern");
         }
         Ok(())
     }
 
-    // Copy a single file or entire directory from `src` to `dest`
-    async fn copy_file_or_directory(
-        &self,
-        src: &Path,
-        dest: &Path,
-        guest_dest: &str,
-        exclude_files: &[String],
-    ) -> Result<()> {
-        let src_path = self.app_root.join(src);
-        let meta = crate::fs::metadata(&src_path)
-            .await
-            .map_err(|e| explain_file_mount_source_error(e, src))?;
-        if meta.is_dir() {
-            // { source = "host/dir", destination = "guest/dir" }
-            let pattern = src_path.join("**/*");
-            self.copy_glob(&pattern, &src_path, dest, exclude_files)
-                .await?;
-        } else {
-            // { source = "host/file.txt", destination = "guest/file.txt" }
-            self.copy_single_file(&src_path, dest, guest_dest).await?;
-        }
-        Ok(())
-    }
-
-    // Copy files matching glob `pattern` into `dest_root`.
-    async fn copy_glob(
+    // Resolve files matching glob `pattern`, placing each at its path relative
+    // to `src_prefix` under `dest_prefix`.
+    fn resolve_glob(
         &self,
         pattern: &Path,
         src_prefix: &Path,
-        dest_root: &Path,
-        exclude_files: &[String],
+        dest_prefix: &Path,
+        filter: &FileFilter,
+        mount: &mut ResolvedMount,
     ) -> Result<()> {
         let pattern = pattern
             .to_str()
@@ -569,23 +960,6 @@ impl LocalLoader {
         let paths = glob::glob(pattern)
             .with_context(|| format!("Failed to resolve glob pattern {pattern:?}"))?;
 
-        let exclude_patterns = exclude_files
-            .iter()
-            .map(|pattern| {
-                glob::Pattern::new(pattern)
-                    .with_context(|| format!("Invalid exclude_files glob pattern {pattern:?}"))
-            })
-            .collect::<Result<Vec<_>>>()?;
-
-        crate::fs::create_dir_all(dest_root)
-            .await
-            .with_context(|| {
-                format!(
-                    "Failed to create parent directory {}",
-                    quoted_path(&dest_root)
-                )
-            })?;
-
         for path_res in paths {
             let src = path_res?;
             if !src.is_file() {
@@ -595,25 +969,56 @@ impl LocalLoader {
             let Ok(app_root_path) = src.strip_prefix(&self.app_root) else {
                 bail!("{pattern} cannot be mapped because it is outside the application directory. Files must be within the application directory.");
             };
+            let relative_path = src.strip_prefix(src_prefix)?;
 
-            if exclude_patterns
+            if filter
+                .exclude_files
                 .iter()
                 .any(|pattern| pattern.matches_path(app_root_path))
             {
-                tracing::debug!(
-                    "File {app_root_path:?} excluded by exclude_files {exclude_files:?}"
-                );
+                tracing::debug!("File {app_root_path:?} excluded by exclude_files");
+                continue;
+            }
+            if filter
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches_path(relative_path))
+            {
+                tracing::debug!("File {app_root_path:?} excluded by mount exclude");
+                continue;
+            }
+            if !check_symlinks(&src, src_prefix, filter.symlinks)? {
                 continue;
             }
 
-            let relative_path = src.strip_prefix(src_prefix)?;
-            let dest = dest_root.join(relative_path);
-            self.copy_single_file(&src, &dest, &relative_path.to_string_lossy())
-                .await?;
+            let guest_path = dest_prefix.join(relative_path);
+            mount.insert(guest_path, src);
         }
         Ok(())
     }
 
+    // Copy the files of a resolved mount into `dest_root`.
+    async fn copy_resolved_mount(&self, mount: &ResolvedMount, dest_root: &Path) -> Result<()> {
+        crate::fs::create_dir_all(dest_root)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to create parent directory {}",
+                    quoted_path(&dest_root)
+                )
+            })?;
+        // Copy files concurrently
+        try_join_all(mount.files.iter().map(|(guest_path, src)| {
+            let dest = dest_root.join(guest_path);
+            async move {
+                self.copy_single_file(src, &dest, &guest_path.to_string_lossy())
+                    .await
+            }
+        }))
+        .await?;
+        Ok(())
+    }
+
     // Copy a single file from `src` to `dest`, creating parent directories.
     async fn copy_single_file(&self, src: &Path, dest: &Path, guest_dest: &str) -> Result<()> {
         // Sanity checks: src is in app_root...
@@ -675,12 +1080,25 @@ impl LocalLoader {
     // Resolve the given direct mount directory, checking that it is valid for
     // direct mounting and returning its canonicalized source path.
     async fn resolve_direct_mount(&self, mount: &WasiFilesMount) -> Result<ContentPath> {
-        let (src, dest) = match mount {
-            WasiFilesMount::Pattern(pattern) => (pattern, pattern),
+        let (src, dest, read_only) = match mount {
+            WasiFilesMount::Pattern(pattern) => (pattern, pattern, false),
             WasiFilesMount::Placement {
                 source,
                 destination,
-            } => (source, destination),
+                exclude,
+                read_only,
+                symlinks,
+            } => {
+                ensure!(
+                    exclude.is_empty(),
+                    "Cannot load a files mount with `exclude` using --direct-mounts"
+                );
+                ensure!(
+                    symlinks.is_follow(),
+                    "Cannot load a files mount with a `symlinks` policy using --direct-mounts"
+                );
+                (source, destination, *read_only)
+            }
         };
         let path = self.app_root.join(src);
         if !path.is_dir() {
@@ -689,10 +1107,88 @@ impl LocalLoader {
         Ok(ContentPath {
             content: file_content_ref(src)?,
             path: dest.into(),
+            read_only,
         })
     }
 }
 
+/// The files of one WASI mount, resolved from a component's `files` entries.
+#[derive(Debug)]
+struct ResolvedMount {
+    /// The guest path at which the files are mounted
+    destination: String,
+    read_only: bool,
+    /// Host source paths, keyed by guest path relative to `destination`
+    files: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl ResolvedMount {
+    fn new(destination: &str, read_only: bool) -> Self {
+        Self {
+            destination: destination.to_owned(),
+            read_only,
+            files: Default::default(),
+        }
+    }
+
+    // Where more than one entry places a file at the same guest path, the
+    // last one wins.
+    fn insert(&mut self, guest_path: PathBuf, src: PathBuf) {
+        if let Some(previous) = self.files.get(&guest_path) {
+            if previous != &src {
+                tracing::debug!("File {src:?} replaces {previous:?} at {guest_path:?}");
+            }
+        }
+        self.files.insert(guest_path, src);
+    }
+}
+
+/// Which of the files matched by a `files` entry are included.
+struct FileFilter<'a> {
+    exclude_files: &'a [glob::Pattern],
+    exclude: &'a [glob::Pattern],
+    symlinks: SymlinkPolicy,
+}
+
+fn glob_patterns(patterns: &[String], field: &str) -> Result<Vec<glob::Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern)
+                .with_context(|| format!("Invalid {field} glob pattern {pattern:?}"))
+        })
+        .collect()
+}
+
+// Check `path`, and the directories between `root` and `path`, against the
+// symlink policy. Returns false if the file should be left out.
+fn check_symlinks(path: &Path, root: &Path, policy: SymlinkPolicy) -> Result<bool> {
+    if policy.is_follow() {
+        return Ok(true);
+    }
+    let mut current = path;
+    loop {
+        let meta = std::fs::symlink_metadata(current)
+            .with_context(|| format!("Failed to read metadata of {}", quoted_path(current)))?;
+        if meta.is_symlink() {
+            match policy {
+                SymlinkPolicy::Skip => {
+                    tracing::debug!("File {path:?} skipped because {current:?} is a symlink");
+                    return Ok(false);
+                }
+                SymlinkPolicy::Error | SymlinkPolicy::Follow => bail!(
+                    "{} is a symbolic link, but the files mount sets `symlinks = \"error\"`",
+                    quoted_path(current)
+                ),
+            }
+        }
+        match current.parent() {
+            Some(parent) if current != root && parent.starts_with(root) => current = parent,
+            _ => return Ok(true),
+        }
+    }
+}
+
 fn explain_file_mount_source_error(e: anyhow::Error, src: &Path) -> anyhow::Error {
     if let Some(io_error) = e.downcast_ref::<std::io::Error>() {
         if io_error.kind() == std::io::ErrorKind::NotFound {
@@ -758,17 +1254,53 @@ fn locked_metadata(
     Ok(builder.build())
 }
 
+fn locked_variables(
+    variables: impl IntoIterator<Item = (LowerSnakeId, v2::Variable)>,
+) -> Result<LockedMap<locked::Variable>> {
+    variables
+        .into_iter()
+        .map(|(name, v)| {
+            let variable = locked_variable(v)
+                .with_context(|| format!("invalid variable definition for {name:?}"))?;
+            Ok((name.to_string(), variable))
+        })
+        .collect()
+}
+
 fn locked_variable(variable: v2::Variable) -> Result<locked::Variable> {
     ensure!(
         variable.required ^ variable.default.is_some(),
         "must be `required` OR have a `default`"
     );
+    ensure!(
+        variable.variable_type.is_numeric() || (variable.min.is_none() && variable.max.is_none()),
+        "`min` and `max` can only be used with `int` or `float` variables"
+    );
+    if let (Some(min), Some(max)) = (variable.min, variable.max) {
+        ensure!(min <= max, "`min` must not be greater than `max`");
+    }
     Ok(locked::Variable {
         default: variable.default.clone(),
         secret: variable.secret,
+        variable_type: locked_variable_type(variable.variable_type),
+        pattern: variable.pattern,
+        allowed_values: variable.allowed_values,
+        min: variable.min,
+        max: variable.max,
     })
 }
 
+fn locked_variable_type(variable_type: v2::VariableType) -> locked::VariableType {
+    match variable_type {
+        v2::VariableType::String => locked::VariableType::String,
+        v2::VariableType::Int => locked::VariableType::Int,
+        v2::VariableType::Float => locked::VariableType::Float,
+        v2::VariableType::Bool => locked::VariableType::Bool,
+        v2::VariableType::Duration => locked::VariableType::Duration,
+        v2::VariableType::StringList => locked::VariableType::StringList,
+    }
+}
+
 fn locked_trigger(trigger_type: String, trigger: v2::Trigger) -> Result<LockedTrigger> {
     fn reference_id(spec: v2::ComponentSpec) -> toml::Value {
         let v2::ComponentSpec::Reference(id) = spec else {
@@ -782,12 +1314,14 @@ fn locked_trigger(trigger_type: String, trigger: v2::Trigger) -> Result<LockedTr
         config.insert("component".into(), id);
     }
     if !trigger.components.is_empty() {
+        let v2::TriggerComponents::Roles(roles) = trigger.components else {
+            unreachable!("chains should have already been normalized");
+        };
         // Flatten trigger config `components` `OneOrManyComponentSpecs` into
         // lists of component references.
         config.insert(
             "components".into(),
-            trigger
-                .components
+            roles
                 .into_iter()
                 .map(|(key, specs)| {
                     (
@@ -812,6 +1346,24 @@ fn locked_trigger(trigger_type: String, trigger: v2::Trigger) -> Result<LockedTr
     })
 }
 
+// Describes a manifest diagnostic, prefixed with where it is in the
+// manifest file.
+fn describe_diagnostic(path: &Path, diagnostic: &Diagnostic) -> String {
+    let file = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let location = match &diagnostic.location {
+        Some(location) => format!("{file}:{}:{}", location.line, location.column),
+        None => file.into_owned(),
+    };
+    format!(
+        "{location}: {} (at `{}`)",
+        diagnostic.message,
+        diagnostic.key.join(".")
+    )
+}
+
 fn looks_like_glob_pattern(s: impl AsRef<str>) -> bool {
     let s = s.as_ref();
     glob::Pattern::escape(s) != s
@@ -890,4 +1442,112 @@ mod test {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn read_only_placements_get_their_own_mounts() -> anyhow::Result<()> {
+        let app_root = tempfile::tempdir()?;
+        for file in ["static/index.html", "static/draft.bak", "config/app.json"] {
+            let path = app_root.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, file)?;
+        }
+        std::fs::write(app_root.path().join("app.wasm"), b"")?;
+        let manifest_path = app_root.path().join("spin.toml");
+        std::fs::write(
+            &manifest_path,
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "mounts"
+            [[trigger.http]]
+            route = "/..."
+            component = "web"
+            [component.web]
+            source = "app.wasm"
+            files = [
+                { source = "static", destination = "/", exclude = ["*.bak"] },
+                { source = "config", destination = "/config", read_only = true },
+            ]
+            "#,
+        )?;
+
+        let wd = tempfile::tempdir()?;
+        let loader = LocalLoader::new(
+            app_root.path(),
+            FilesMountStrategy::Copy(wd.path().to_owned()),
+            None,
+        )
+        .await?;
+        let locked = loader.load_file(&manifest_path).await?;
+
+        let files = &locked.components[0].files;
+        assert_eq!(2, files.len());
+        assert_eq!(
+            (Path::new("/"), false),
+            (files[0].path.as_path(), files[0].read_only)
+        );
+        assert_eq!(
+            (Path::new("/config"), true),
+            (files[1].path.as_path(), files[1].read_only)
+        );
+        let root = wd.path().join("web");
+        assert!(root.join("index.html").is_file());
+        assert!(!root.join("draft.bak").exists());
+        assert!(!root.join("config").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lockfile_pins_remote_sources() -> anyhow::Result<()> {
+        const DIGEST: &str =
+            "sha256:0000000000000000000000000000000000000000000000000000000000000000";
+
+        let app_root = tempfile::tempdir()?;
+        let cache_root = tempfile::tempdir()?;
+        Cache::new(Some(cache_root.path().to_owned()))
+            .await?
+            .write_wasm(b"", DIGEST)
+            .await?;
+
+        let manifest_path = app_root.path().join("spin.toml");
+        std::fs::write(
+            &manifest_path,
+            format!(
+                r#"
+                spin_manifest_version = 2
+                [application]
+                name = "locked"
+                [[trigger.http]]
+                route = "/..."
+                component = {{ source = {{ url = "https://example.com/a.wasm", digest = "{DIGEST}" }} }}
+                "#
+            ),
+        )?;
+
+        let load = |mode| {
+            let app_root = app_root.path().to_owned();
+            let cache_root = cache_root.path().to_owned();
+            let manifest_path = manifest_path.clone();
+            async move {
+                let mut loader =
+                    LocalLoader::new(&app_root, FilesMountStrategy::Direct, Some(cache_root))
+                        .await?;
+                loader.set_lockfile_mode(mode)?;
+                loader.load_file(manifest_path).await
+            }
+        };
+
+        let err = load(LockfileMode::Locked)
+            .await
+            .expect_err("loading without a lockfile should fail in locked mode");
+        assert!(format!("{err:#}").contains("is not pinned"), "{err:#}");
+
+        load(LockfileMode::Update).await?;
+        let lockfile = Lockfile::from_file(app_root.path().join(LOCKFILE_NAME))?
+            .expect("lockfile should have been written");
+        assert!(lockfile.contains_source("https://example.com/a.wasm", DIGEST));
+
+        load(LockfileMode::Locked).await?;
+        Ok(())
+    }
 }
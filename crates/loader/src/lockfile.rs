@@ -0,0 +1,195 @@
+//! The `spin.lock` file, which pins the content of an application's remote
+//! component sources and registry packages.
+
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use spin_common::ui::quoted_path;
+
+/// The name of the lockfile, which lives alongside the manifest.
+pub const LOCKFILE_NAME: &str = "spin.lock";
+
+const LOCKFILE_VERSION: u32 = 1;
+
+const LOCKFILE_HEADER: &str =
+    "# This file is generated by Spin. It is not intended for manual editing.\n";
+
+/// How the loader treats the application's lockfile.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LockfileMode {
+    /// Neither read nor write a lockfile.
+    #[default]
+    Ignore,
+    /// Use versions pinned by an existing lockfile, and write any newly
+    /// resolved sources back to it.
+    Update,
+    /// Require every remote source to be pinned by the existing lockfile,
+    /// and fail if loading would change it.
+    Locked,
+}
+
+/// The content of a `spin.lock` file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// The lockfile format version.
+    pub version: u32,
+    /// Components and dependencies loaded from URLs.
+    #[serde(default, rename = "source", skip_serializing_if = "BTreeSet::is_empty")]
+    pub sources: BTreeSet<LockedSource>,
+    /// Components and dependencies loaded from registries.
+    #[serde(
+        default,
+        rename = "package",
+        skip_serializing_if = "BTreeSet::is_empty"
+    )]
+    pub packages: BTreeSet<LockedPackage>,
+}
+
+/// A component or dependency loaded from a URL.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LockedSource {
+    /// The source URL.
+    pub url: String,
+    /// The content digest, e.g. `sha256:...`.
+    pub digest: String,
+}
+
+/// A component or dependency loaded from a registry.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// The package name, e.g. `example:component`.
+    pub name: String,
+    /// The registry, if not the default for the package's namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// The version requirement from the manifest.
+    pub requirement: String,
+    /// The exact version the requirement resolved to.
+    pub version: String,
+    /// The content digest of the resolved version, e.g. `sha256:...`.
+    pub digest: String,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            sources: Default::default(),
+            packages: Default::default(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Reads the lockfile at the given path, returning `None` if it does
+    /// not exist.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", quoted_path(path)))
+            }
+        };
+        let lockfile: Self = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse lockfile {}", quoted_path(path)))?;
+        anyhow::ensure!(
+            lockfile.version == LOCKFILE_VERSION,
+            "Lockfile {} has unsupported version {}; expected {LOCKFILE_VERSION}",
+            quoted_path(path),
+            lockfile.version,
+        );
+        Ok(Some(lockfile))
+    }
+
+    /// Writes the lockfile to the given path.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = format!("{LOCKFILE_HEADER}{}", toml::to_string(self)?);
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write {}", quoted_path(path)))
+    }
+
+    /// Returns true if the lockfile pins nothing.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty() && self.packages.is_empty()
+    }
+
+    /// Returns the pinned resolution of the given package requirement, if
+    /// any.
+    pub fn package(
+        &self,
+        name: &str,
+        registry: Option<&str>,
+        requirement: &str,
+    ) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| {
+            p.name == name && p.registry.as_deref() == registry && p.requirement == requirement
+        })
+    }
+
+    /// Returns true if the lockfile pins the given URL to the given digest.
+    pub fn contains_source(&self, url: &str, digest: &str) -> bool {
+        self.sources
+            .iter()
+            .any(|s| s.url == url && s.digest == digest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_file() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join(LOCKFILE_NAME);
+        assert_eq!(None, Lockfile::from_file(&path)?);
+
+        let mut lockfile = Lockfile::default();
+        lockfile.sources.insert(LockedSource {
+            url: "https://example.com/a.wasm".into(),
+            digest: "sha256:abcd".into(),
+        });
+        lockfile.packages.insert(LockedPackage {
+            name: "example:component".into(),
+            registry: None,
+            requirement: "^1.2".into(),
+            version: "1.2.3".into(),
+            digest: "sha256:1234".into(),
+        });
+        lockfile.write(&path)?;
+
+        let contents = std::fs::read_to_string(&path)?;
+        assert!(contents.starts_with(LOCKFILE_HEADER), "{contents}");
+        let read = Lockfile::from_file(&path)?.expect("lockfile should exist");
+        assert_eq!(lockfile, read);
+        assert!(read.contains_source("https://example.com/a.wasm", "sha256:abcd"));
+        assert!(!read.contains_source("https://example.com/a.wasm", "sha256:ef01"));
+        assert_eq!(
+            "1.2.3",
+            read.package("example:component", None, "^1.2")
+                .expect("package should be pinned")
+                .version
+        );
+        assert!(read
+            .package("example:component", Some("example.com"), "^1.2")
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_version() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join(LOCKFILE_NAME);
+        std::fs::write(&path, "version = 99\n")?;
+        let err = Lockfile::from_file(&path).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("unsupported version 99"),
+            "{err:#}"
+        );
+        Ok(())
+    }
+}
@@ -11,7 +11,7 @@ mod metadata;
 pub mod values;
 
 pub use async_trait::async_trait;
-pub use locked::Variable;
+pub use locked::{Variable, VariableType};
 pub use metadata::{MetadataExt, MetadataKey};
 
 /// MetadataKey for extracting the application name.
@@ -6,6 +6,8 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use spin_serde::{DependencyName, FixedVersionBackwardCompatible};
+
+pub use spin_serde::{Base64Bytes, DigestRef};
 use std::collections::BTreeMap;
 
 use crate::{
@@ -259,6 +261,10 @@ pub struct LockedComponentDependency {
     /// Which configurations to inherit from parent
     #[serde(default, skip_serializing_if = "InheritConfiguration::is_none")]
     pub inherit: InheritConfiguration,
+    /// The dependencies to compose into this dependency before it is
+    /// composed into its parent.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<DependencyName, LockedComponentDependency>,
 }
 
 /// InheritConfiguration specifies which configurations to inherit from parent.
@@ -301,6 +307,9 @@ pub struct ContentPath {
     pub content: ContentRef,
     /// WASI mount path
     pub path: PathBuf,
+    /// If true, the guest must not be allowed to write to this content
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
 }
 
 /// A ContentRef represents content used by an application.
@@ -317,15 +326,11 @@ pub struct ContentRef {
     ///
     /// NOTE: This is both an optimization for small content and a workaround
     /// for certain OCI implementations that don't support 0 or 1 byte blobs.
-    #[serde(
-        default,
-        skip_serializing_if = "Option::is_none",
-        with = "spin_serde::base64"
-    )]
-    pub inline: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline: Option<Base64Bytes>,
     /// If set, the content must have the given SHA-256 digest.
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub digest: Option<String>,
+    pub digest: Option<DigestRef>,
 }
 
 /// A LockedTrigger specifies configuration for an application trigger.
@@ -340,7 +345,7 @@ pub struct LockedTrigger {
 }
 
 /// A Variable specifies a custom configuration variable.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Variable {
     /// The variable's default value. If unset, the variable is required.
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -348,6 +353,104 @@ pub struct Variable {
     /// If set, the variable's value may be sensitive and e.g. shouldn't be logged.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub secret: bool,
+    /// The type the variable's value must parse as.
+    #[serde(
+        default,
+        rename = "type",
+        skip_serializing_if = "VariableType::is_string"
+    )]
+    pub variable_type: VariableType,
+    /// A regular expression that the whole value must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The values that the variable may take. If empty, any value is allowed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_values: Vec<String>,
+    /// The minimum allowed value for numeric variables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// The maximum allowed value for numeric variables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+impl std::fmt::Debug for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't leak the default or allowed values of secrets into logs
+        let default = match &self.default {
+            Some(_) if self.secret => Some("<redacted>"),
+            default => default.as_deref(),
+        };
+        let allowed_values: &dyn std::fmt::Debug = if self.secret && !self.allowed_values.is_empty()
+        {
+            &["<redacted>"]
+        } else {
+            &self.allowed_values
+        };
+        f.debug_struct("Variable")
+            .field("default", &default)
+            .field("secret", &self.secret)
+            .field("variable_type", &self.variable_type)
+            .field("pattern", &self.pattern)
+            .field("allowed_values", allowed_values)
+            .field("min", &self.min)
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+impl Variable {
+    /// Returns true if the variable declares a non-string type or any value
+    /// constraints which should be checked.
+    pub fn has_constraints(&self) -> bool {
+        !self.variable_type.is_string()
+            || self.pattern.is_some()
+            || !self.allowed_values.is_empty()
+            || self.min.is_some()
+            || self.max.is_some()
+    }
+}
+
+/// The type of a [`Variable`]'s value.
+///
+/// Values are always provided as strings; the type determines how they
+/// are validated and parsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    /// Any string.
+    #[default]
+    String,
+    /// A signed 64-bit integer, e.g. `8080`.
+    Int,
+    /// A 64-bit floating point number, e.g. `0.5`.
+    Float,
+    /// `true` or `false`.
+    Bool,
+    /// A duration with units, e.g. `1h30m` or `250ms`.
+    Duration,
+    /// A comma-separated list of strings, e.g. `a,b,c`.
+    StringList,
+}
+
+impl VariableType {
+    /// Returns true if this is the default `String` type.
+    pub fn is_string(&self) -> bool {
+        matches!(self, Self::String)
+    }
+}
+
+impl std::fmt::Display for VariableType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::String => "string",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::Duration => "duration",
+            Self::StringList => "string_list",
+        })
+    }
 }
 
 #[cfg(test)]
@@ -356,6 +459,25 @@ mod test {
 
     use crate::values::ValuesMapBuilder;
 
+    #[test]
+    fn variable_debug_redacts_secrets() {
+        let variable = Variable {
+            default: Some("hunter2".into()),
+            secret: true,
+            allowed_values: vec!["hunter2".into(), "swordfish".into()],
+            ..Default::default()
+        };
+        let debug = format!("{variable:?}");
+        assert!(!debug.contains("hunter2"), "{debug}");
+        assert!(!debug.contains("swordfish"), "{debug}");
+
+        let variable = Variable {
+            secret: false,
+            ..variable
+        };
+        assert!(format!("{variable:?}").contains("hunter2"));
+    }
+
     #[test]
     fn locked_app_with_no_host_reqs_serialises_as_v0_and_v0_deserialises_as_v1() {
         let locked_app = LockedApp {
@@ -494,4 +616,21 @@ mod test {
         assert_eq!(1, locked.must_understand.len());
         assert_eq!(1, locked.host_requirements.len());
     }
+
+    #[test]
+    fn content_refs_validate_digests() {
+        use serde_json::json;
+        let content: ContentRef = serde_json::from_value(json!({
+            "inline": "aGVsbG8",
+            "digest": "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+        }))
+        .unwrap();
+        let inline = content.inline.as_deref().unwrap();
+        assert_eq!(b"hello", inline);
+        content.digest.unwrap().verify(inline).unwrap();
+
+        let err = serde_json::from_value::<ContentRef>(json!({ "digest": "sha256:abc" }))
+            .expect_err("should have rejected short digest");
+        assert!(err.to_string().contains("64 hex digits"), "{err}");
+    }
 }
@@ -2,6 +2,8 @@
 
 mod allowed_http_hosts;
 
+#[cfg(feature = "unstable-v3")]
+use crate::schema::v3;
 use crate::{
     error::Error,
     schema::{v1, v2},
@@ -9,8 +11,55 @@ use crate::{
 use allowed_http_hosts::{parse_allowed_http_hosts, AllowedHttpHosts};
 
 /// Converts a V1 app manifest to V2.
+///
+/// Use [`convert_v1_to_v2`] to also find out what could not be converted
+/// exactly.
 pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Error> {
+    convert_v1_to_v2(manifest).map(|(manifest, _)| manifest)
+}
+
+/// Something in a V1 manifest which [`convert_v1_to_v2`] could not carry
+/// over exactly, or which the user should review.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    /// The V1 ID of the component concerned, if any.
+    pub component: Option<String>,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.component {
+            Some(id) => write!(f, "component `{id}`: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Converts a V1 app manifest to V2, returning warnings about anything
+/// which could not be represented exactly, such as component IDs which had
+/// to be changed or permissions which V1 granted implicitly.
+///
+/// Comments and formatting are not preserved.
+pub fn convert_v1_to_v2(
+    manifest: v1::AppManifestV1,
+) -> Result<(v2::AppManifest, Vec<Warning>), Error> {
+    let mut warnings = vec![];
     let trigger_type = manifest.trigger.trigger_type.clone();
+    if trigger_type == "http" {
+        if let Some(base) = manifest.trigger.config.get("base").and_then(|b| b.as_str()) {
+            if base != "/" {
+                warnings.push(Warning {
+                    component: None,
+                    message: format!(
+                        "the HTTP trigger `base` ({base:?}) is not supported in version 2 \
+                         manifests; prepend it to each route and remove it"
+                    ),
+                });
+            }
+        }
+    }
     let trigger_global_configs = [(trigger_type.clone(), manifest.trigger.config)]
         .into_iter()
         .collect();
@@ -33,7 +82,17 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
     let mut triggers = v2::Map::<String, Vec<v2::Trigger>>::default();
     let mut components = v2::Map::default();
     for component in manifest.components {
-        let component_id = component_id_from_string(component.id)?;
+        let v1_id = component.id;
+        let component_id = component_id_from_string(v1_id.clone())?;
+        if component_id.as_ref() != v1_id {
+            warnings.push(Warning {
+                component: Some(v1_id.clone()),
+                message: format!(
+                    "the component ID was changed to `{component_id}`, \
+                     as version 2 IDs must be kebab-case"
+                ),
+            });
+        }
 
         let variables = component
             .config
@@ -56,7 +115,16 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
                 hs.extend(allowed_http);
                 hs
             }
-            None => allowed_http,
+            None => {
+                warnings.push(Warning {
+                    component: Some(v1_id),
+                    message: "access to all Redis, MySQL and PostgreSQL hosts was granted, \
+                              as version 1 manifests without `allowed_outbound_hosts` allowed it; \
+                              restrict `allowed_outbound_hosts` to the hosts the component uses"
+                        .into(),
+                });
+                allowed_http
+            }
         };
         components.insert(
             component_id.clone(),
@@ -71,6 +139,18 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
                 key_value_stores: component.key_value_stores,
                 sqlite_databases: component.sqlite_databases,
                 ai_models,
+                allowed_email_recipients: Vec::new(),
+                vector_stores: Vec::new(),
+                blob_stores: Vec::new(),
+                memory_limit: None,
+                execution_timeout: None,
+                instance_pool_size: None,
+                instance_pool_queue: None,
+                instance_pool_timeout: None,
+                warm_instances: None,
+                warm_instance_idle_timeout: None,
+                health_check: false,
+                health_check_timeout: None,
                 build: component.build,
                 tool: Default::default(),
                 allowed_outbound_hosts,
@@ -86,18 +166,168 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
                 id: format!("trigger-{component_id}"),
                 component: Some(v2::ComponentSpec::Reference(component_id)),
                 components: Default::default(),
+                mode: None,
                 config: component.trigger,
             });
     }
-    Ok(v2::AppManifest {
+    let manifest = v2::AppManifest {
         spin_manifest_version: Default::default(),
         application,
         variables: app_variables,
+        profiles: Default::default(),
+        environments: Default::default(),
         triggers,
         components,
+    };
+    Ok((manifest, warnings))
+}
+
+/// Converts a V2 app manifest to the draft V3 format.
+///
+/// Deprecated `allowed_http_hosts` are converted to outbound hosts, and
+/// dependencies given by version alone name the package of their import.
+#[cfg(feature = "unstable-v3")]
+pub fn v2_to_v3_app(manifest: v2::AppManifest) -> Result<v3::AppManifest, Error> {
+    let components = manifest
+        .components
+        .into_iter()
+        .map(|(id, component)| {
+            let component = v2_to_v3_component(component)
+                .map_err(|e| Error::ValidationError(e.context(format!("component {id:?}"))))?;
+            Ok((id, component))
+        })
+        .collect::<Result<_, Error>>()?;
+
+    let environments = manifest
+        .environments
+        .into_iter()
+        .map(|(name, environment)| {
+            let components = environment
+                .components
+                .into_iter()
+                .map(|(id, component)| {
+                    (
+                        id,
+                        v3::EnvironmentComponent {
+                            build_command: component.build_command,
+                            capabilities: v3::CapabilitiesOverlay {
+                                outbound_hosts: component.allowed_outbound_hosts,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect();
+            (name, v3::Environment { components })
+        })
+        .collect();
+
+    Ok(v3::AppManifest {
+        spin_manifest_version: Default::default(),
+        application: manifest.application,
+        variables: manifest.variables,
+        profiles: manifest.profiles,
+        environments,
+        triggers: manifest.triggers,
+        components,
+    })
+}
+
+#[cfg(feature = "unstable-v3")]
+fn v2_to_v3_component(component: v2::Component) -> anyhow::Result<v3::Component> {
+    let outbound_hosts = component.normalized_allowed_outbound_hosts()?;
+    let imports = component
+        .dependencies
+        .inner
+        .into_iter()
+        .map(|(name, dependency)| {
+            let import = match dependency {
+                v2::ComponentDependency::Version(version) => v3::Import::Package {
+                    package: import_package(&name, None)?,
+                    version,
+                    registry: None,
+                    export: None,
+                },
+                v2::ComponentDependency::Package {
+                    version,
+                    registry,
+                    package,
+                    export,
+                } => v3::Import::Package {
+                    package: import_package(&name, package)?,
+                    version,
+                    registry,
+                    export,
+                },
+                v2::ComponentDependency::Local { path, export } => {
+                    v3::Import::Local { path, export }
+                }
+                v2::ComponentDependency::HTTP {
+                    url,
+                    digest,
+                    export,
+                } => v3::Import::Http {
+                    url,
+                    digest,
+                    export,
+                },
+                v2::ComponentDependency::Component { component, export } => {
+                    v3::Import::Component { component, export }
+                }
+            };
+            Ok((name, import))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(v3::Component {
+        source: component.source,
+        description: component.description,
+        variables: component.variables,
+        environment: component.environment,
+        files: component.files,
+        exclude_files: component.exclude_files,
+        capabilities: v3::Capabilities {
+            outbound_hosts,
+            key_value_stores: component.key_value_stores,
+            sqlite_databases: component.sqlite_databases,
+            ai_models: component.ai_models,
+            email_recipients: component.allowed_email_recipients,
+            vector_stores: component.vector_stores,
+            blob_stores: component.blob_stores,
+        },
+        memory_limit: component.memory_limit,
+        execution_timeout: component.execution_timeout,
+        instance_pool_size: component.instance_pool_size,
+        instance_pool_queue: component.instance_pool_queue,
+        instance_pool_timeout: component.instance_pool_timeout,
+        warm_instances: component.warm_instances,
+        warm_instance_idle_timeout: component.warm_instance_idle_timeout,
+        health_check: component.health_check,
+        health_check_timeout: component.health_check_timeout,
+        build: component.build,
+        tool: component.tool,
+        imports,
+        imports_inherit_capabilities: component.dependencies_inherit_configuration,
     })
 }
 
+// Returns the package which satisfies a V2 dependency: the one it names, or
+// else the package of the import.
+#[cfg(feature = "unstable-v3")]
+fn import_package(
+    name: &spin_serde::DependencyName,
+    package: Option<String>,
+) -> anyhow::Result<String> {
+    match (package, name) {
+        (Some(package), _) => Ok(package),
+        (None, spin_serde::DependencyName::Package(name)) => Ok(name.package.to_string()),
+        (None, spin_serde::DependencyName::Plain(name)) => {
+            anyhow::bail!("dependency {name:?} must specify a package")
+        }
+    }
+}
+
 /// Converts the old `allowed_http_hosts` field to the new `allowed_outbound_hosts` field.
 ///
 /// If `allow_database_access` is `true`, the function will also allow access to all redis,
@@ -151,10 +381,50 @@ fn component_id_from_string(id: String) -> Result<v2::KebabId, Error> {
         .map_err(|err: String| Error::InvalidID { id, reason: err })
 }
 
-fn id_from_string<const DELIM: char, const LOWER: bool>(
-    id: String,
-) -> Result<spin_serde::id::Id<DELIM, LOWER>, Error> {
+fn id_from_string<T: TryFrom<String, Error = String>>(id: String) -> Result<T, Error> {
     id.clone()
         .try_into()
         .map_err(|err: String| Error::InvalidID { id, reason: err })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_reports_what_changed() {
+        let v1: v1::AppManifestV1 = toml::from_str(
+            r#"
+            spin_manifest_version = "1"
+            name = "legacy"
+            trigger = { type = "http", base = "/api" }
+
+            [[component]]
+            id = "cart_service"
+            source = "cart.wasm"
+            allowed_outbound_hosts = ["https://example.com"]
+            [component.trigger]
+            route = "/cart/..."
+
+            [[component]]
+            id = "orders"
+            source = "orders.wasm"
+            [component.trigger]
+            route = "/orders/..."
+            "#,
+        )
+        .unwrap();
+        let (v2, warnings) = convert_v1_to_v2(v1).unwrap();
+        assert_eq!(
+            "cart-service",
+            v2.components.keys().next().unwrap().as_ref()
+        );
+
+        let warnings: Vec<_> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(3, warnings.len(), "{warnings:?}");
+        assert!(warnings[0].contains(r#"`base` ("/api")"#));
+        assert!(warnings[1].starts_with("component `cart_service`: "));
+        assert!(warnings[1].contains("`cart-service`"));
+        assert!(warnings[2].starts_with("component `orders`: access to all Redis"));
+    }
+}
@@ -0,0 +1,295 @@
+//! Programmatic editing of manifest files.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+use toml_edit::{DocumentMut, InlineTable, Item, Table, Value};
+
+use crate::{
+    schema::v2::{Component, ComponentDependency, Trigger},
+    Error, ManifestVersion,
+};
+
+/// Edits a V2 manifest in place, preserving its comments and layout.
+///
+/// New tables are added after the last table of the same kind: for example,
+/// a new component follows the last existing component. Values are written
+/// in the style `spin new` and `spin add` use, with nested values inline.
+///
+/// ```
+/// # use spin_manifest::edit::ManifestEditor;
+/// let mut editor = ManifestEditor::new(r#"
+/// spin_manifest_version = 2
+/// [application]
+/// name = "example"
+/// "#)?;
+/// editor.set_variable_default("log_level", "info")?;
+/// assert!(editor.to_string().contains(r#"log_level = { default = "info" }"#));
+/// # Ok::<(), spin_manifest::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct ManifestEditor {
+    document: DocumentMut,
+}
+
+impl ManifestEditor {
+    /// Parses a manifest for editing. Only V2 manifests can be edited.
+    pub fn new(manifest: &str) -> Result<Self, Error> {
+        if ManifestVersion::detect(manifest)? != ManifestVersion::V2 {
+            return Err(Error::InvalidEdit(
+                "only spin_manifest_version 2 manifests can be edited".into(),
+            ));
+        }
+        Ok(Self {
+            document: manifest.parse()?,
+        })
+    }
+
+    /// Adds a `[component.<id>]` table. Fails if the component already exists.
+    pub fn add_component(&mut self, id: &str, component: &Component) -> Result<(), Error> {
+        let components = implicit_table(self.document.as_table_mut(), "component")?;
+        if components.contains_key(id) {
+            return Err(Error::InvalidEdit(format!(
+                "component {id:?} already exists"
+            )));
+        }
+        let mut table = toml_edit::ser::to_document(component)?.as_table().clone();
+        table.decor_mut().set_prefix("\n");
+        components.insert(id, Item::Table(table));
+        Ok(())
+    }
+
+    /// Adds a `[[trigger.<trigger_type>]]` entry.
+    pub fn add_trigger(&mut self, trigger_type: &str, trigger: &Trigger) -> Result<(), Error> {
+        let triggers = implicit_table(self.document.as_table_mut(), "trigger")?;
+        let entries = triggers
+            .entry(trigger_type)
+            .or_insert_with(|| Item::ArrayOfTables(Default::default()))
+            .as_array_of_tables_mut()
+            .ok_or_else(|| {
+                Error::InvalidEdit(format!(
+                    "`trigger.{trigger_type}` is not an array of tables"
+                ))
+            })?;
+        let mut table = toml_edit::ser::to_document(trigger)?.as_table().clone();
+        table.decor_mut().set_prefix("\n");
+        entries.push(table);
+        Ok(())
+    }
+
+    /// Sets the default value of an application variable, adding the
+    /// variable if it is not already declared. A variable which was
+    /// `required` becomes optional.
+    pub fn set_variable_default(&mut self, name: &str, default: &str) -> Result<(), Error> {
+        let root = self.document.as_table_mut();
+        if !root.contains_key("variables") {
+            let mut variables = Table::new();
+            variables.decor_mut().set_prefix("\n");
+            root.insert("variables", Item::Table(variables));
+        }
+        let variables = root["variables"]
+            .as_table_like_mut()
+            .ok_or_else(|| Error::InvalidEdit("`variables` is not a table".into()))?;
+
+        let Some(variable) = variables.get_mut(name) else {
+            let mut variable = InlineTable::new();
+            variable.insert("default", default.into());
+            variables.insert(name, Item::Value(Value::InlineTable(variable)));
+            return Ok(());
+        };
+        let variable = variable
+            .as_table_like_mut()
+            .ok_or_else(|| Error::InvalidEdit(format!("variable {name:?} is not a table")))?;
+        variable.remove("required");
+        match variable.get_mut("default") {
+            // Replace the value but keep its decor, e.g. a trailing comment.
+            Some(Item::Value(value)) => {
+                let decor = value.decor().clone();
+                *value = default.into();
+                *value.decor_mut() = decor;
+            }
+            _ => {
+                variable.insert("default", Item::Value(default.into()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a dependency satisfying the named import of a component.
+    /// Fails if the component does not exist or already has a dependency
+    /// for the import.
+    pub fn add_import(
+        &mut self,
+        component_id: &str,
+        import_name: &str,
+        dependency: &ComponentDependency,
+    ) -> Result<(), Error> {
+        let component = self
+            .document
+            .get_mut("component")
+            .and_then(|components| components.get_mut(component_id))
+            .and_then(Item::as_table_mut)
+            .ok_or_else(|| {
+                Error::InvalidEdit(format!("component {component_id:?} does not exist"))
+            })?;
+        if !component.contains_key("dependencies") {
+            let mut dependencies = Table::new();
+            dependencies.decor_mut().set_prefix("\n");
+            component.insert("dependencies", Item::Table(dependencies));
+        }
+        let dependencies = component["dependencies"]
+            .as_table_like_mut()
+            .ok_or_else(|| {
+                Error::InvalidEdit(format!(
+                    "`component.{component_id}.dependencies` is not a table"
+                ))
+            })?;
+        if dependencies.contains_key(import_name) {
+            return Err(Error::InvalidEdit(format!(
+                "component {component_id:?} already has a dependency for {import_name:?}"
+            )));
+        }
+        let value = to_value(dependency)?;
+        dependencies.insert(import_name, Item::Value(value));
+        Ok(())
+    }
+
+    /// Returns the edited manifest.
+    pub fn document(&self) -> &DocumentMut {
+        &self.document
+    }
+}
+
+impl Display for ManifestEditor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.document.fmt(f)
+    }
+}
+
+// Returns the named table, creating an implicit (header-less) one if needed.
+fn implicit_table<'a>(parent: &'a mut Table, key: &str) -> Result<&'a mut Table, Error> {
+    parent
+        .entry(key)
+        .or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        })
+        .as_table_mut()
+        .ok_or_else(|| Error::InvalidEdit(format!("`{key}` is not a table")))
+}
+
+fn to_value(value: &impl Serialize) -> Result<Value, Error> {
+    let mut value = value.serialize(toml_edit::ser::ValueSerializer::new())?;
+    value.decor_mut().set_prefix(" ");
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"# The cart app
+spin_manifest_version = 2
+
+[application]
+name = "cart"
+
+[variables]
+currency = { required = true } # ISO 4217
+
+[[trigger.http]]
+route = "/api/..."
+component = "api"
+
+# The API
+[component.api]
+source = "api.wasm"
+"#;
+
+    fn component(source: &str) -> Component {
+        toml::from_str(&format!("source = {source:?}")).unwrap()
+    }
+
+    #[test]
+    fn add_component_and_trigger() {
+        let mut editor = ManifestEditor::new(MANIFEST).unwrap();
+        let trigger: Trigger = toml::from_str(
+            r#"
+            route = "/static/..."
+            component = "static"
+            "#,
+        )
+        .unwrap();
+        editor.add_trigger("http", &trigger).unwrap();
+        let mut component = component("static.wasm");
+        component.files = vec![crate::schema::v2::WasiFilesMount::Pattern(
+            "assets/**".into(),
+        )];
+        editor.add_component("static", &component).unwrap();
+
+        let expected = MANIFEST.replace(
+            "\n# The API",
+            "\n[[trigger.http]]\ncomponent = \"static\"\nroute = \"/static/...\"\n\n# The API",
+        )
+            + "\n[component.static]\nsource = \"static.wasm\"\nfiles = [\"assets/**\"]\n";
+        assert_eq!(expected, editor.to_string());
+        crate::manifest_from_str(&editor.to_string()).unwrap();
+
+        let err = editor.add_component("api", &component).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+    }
+
+    #[test]
+    fn set_variable_default_preserves_comments() {
+        let mut editor = ManifestEditor::new(MANIFEST).unwrap();
+        editor.set_variable_default("currency", "EUR").unwrap();
+        editor.set_variable_default("region", "eu").unwrap();
+        let expected = MANIFEST.replace(
+            "currency = { required = true } # ISO 4217",
+            "currency = { default = \"EUR\" } # ISO 4217\nregion = { default = \"eu\" }",
+        );
+        assert_eq!(expected, editor.to_string());
+
+        editor.set_variable_default("currency", "USD").unwrap();
+        assert!(editor
+            .to_string()
+            .contains("currency = { default = \"USD\" } # ISO 4217"));
+    }
+
+    #[test]
+    fn add_import_creates_dependencies_table() {
+        let mut editor = ManifestEditor::new(MANIFEST).unwrap();
+        let dependency = ComponentDependency::Version(">= 0.1.0".into());
+        editor
+            .add_import("api", "example:logging/log", &dependency)
+            .unwrap();
+        let dependency = ComponentDependency::Local {
+            path: "auth.wasm".into(),
+            export: None,
+        };
+        editor
+            .add_import("api", "example:auth/check", &dependency)
+            .unwrap();
+
+        let expected = format!(
+            "{MANIFEST}\n[component.api.dependencies]\n\
+             \"example:logging/log\" = \">= 0.1.0\"\n\
+             \"example:auth/check\" = {{ path = \"auth.wasm\" }}\n"
+        );
+        assert_eq!(expected, editor.to_string());
+        let manifest = crate::manifest_from_str(&editor.to_string()).unwrap();
+        assert_eq!(2, manifest.components[0].dependencies.inner.len());
+
+        let err = editor
+            .add_import("missing", "example:auth/check", &dependency)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{err}");
+    }
+
+    #[test]
+    fn rejects_v1_manifests() {
+        let err = ManifestEditor::new("spin_manifest_version = \"1\"").unwrap_err();
+        assert!(err.to_string().contains("version 2"), "{err}");
+    }
+}
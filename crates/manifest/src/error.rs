@@ -7,6 +7,17 @@ pub enum Error {
     #[error("invalid digest {0:?}: {1}")]
     InvalidDigest(String, String),
 
+    /// Conflicting definitions in included manifest files.
+    #[error("`{key}` in {path:?} is already defined in {existing:?}")]
+    IncludeConflict {
+        /// The conflicting key, e.g. `component.api.source`
+        key: String,
+        /// The file which attempted to redefine the key
+        path: std::path::PathBuf,
+        /// The file which first defined the key
+        existing: std::path::PathBuf,
+    },
+
     /// Invalid ID format.
     #[error("invalid ID `{id}`: {reason}")]
     InvalidID {
@@ -34,6 +45,19 @@ pub enum Error {
         reason: String,
     },
 
+    /// A manifest edit which could not be applied
+    #[error("cannot edit manifest: {0}")]
+    InvalidEdit(String),
+
+    /// Invalid `include`, or an included file which could not be used
+    #[error("invalid include in {path:?}: {reason}")]
+    InvalidInclude {
+        /// The manifest or included file
+        path: std::path::PathBuf,
+        /// The reason why the include is invalid
+        reason: String,
+    },
+
     /// Invalid manifest version
     #[error("invalid manifest version: {0}")]
     InvalidVersion(String),
@@ -50,6 +74,14 @@ pub enum Error {
     #[error(transparent)]
     TomlParse(#[from] toml::de::Error),
 
+    /// Error parsing TOML for editing
+    #[error(transparent)]
+    TomlEditParse(#[from] toml_edit::TomlError),
+
+    /// Error serializing TOML for editing
+    #[error(transparent)]
+    TomlEditSerialize(#[from] toml_edit::ser::Error),
+
     /// Validation error
     #[error(transparent)]
     ValidationError(anyhow::Error),
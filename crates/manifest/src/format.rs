@@ -0,0 +1,190 @@
+//! Canonical formatting of manifest files.
+
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+use crate::Error;
+
+const ROOT_ORDER: &[&str] = &[
+    "spin_manifest_version",
+    "include",
+    "application",
+    "variables",
+    "profiles",
+    "environments",
+    "trigger",
+    "component",
+];
+
+const APPLICATION_ORDER: &[&str] = &[
+    "name",
+    "version",
+    "description",
+    "authors",
+    "trigger",
+    "tool",
+];
+
+const TRIGGER_ORDER: &[&str] = &["id", "component", "components"];
+
+const COMPONENT_ORDER: &[&str] = &[
+    "source",
+    "description",
+    "variables",
+    "environment",
+    "files",
+    "exclude_files",
+    "allowed_outbound_hosts",
+    "allowed_http_hosts",
+    "key_value_stores",
+    "sqlite_databases",
+    "ai_models",
+    "allowed_email_recipients",
+    "vector_stores",
+    "blob_stores",
+    "memory_limit",
+    "execution_timeout",
+    "instance_pool_size",
+    "instance_pool_queue",
+    "instance_pool_timeout",
+    "warm_instances",
+    "warm_instance_idle_timeout",
+    "health_check",
+    "health_check_timeout",
+    "build",
+    "tool",
+    "dependencies_inherit_configuration",
+    "dependencies",
+];
+
+/// Re-emits a V2 manifest in canonical style, preserving comments.
+///
+/// Well-known keys are put in a stable order (for example `source` first in
+/// each component), with unknown keys after them in their original order,
+/// and tables are separated by a single blank line. The manifest is not
+/// otherwise restructured: for example, inline components stay inline.
+/// Formatting is idempotent.
+pub fn format_manifest(manifest: &str) -> Result<String, Error> {
+    let mut document: DocumentMut = manifest.parse()?;
+    let root = document.as_table_mut();
+
+    sort_table(root, ROOT_ORDER);
+    if let Some(application) = root.get_mut("application") {
+        sort_item(application, APPLICATION_ORDER);
+    }
+    if let Some(Item::Table(components)) = root.get_mut("component") {
+        for (_, component) in components.iter_mut() {
+            sort_item(component, COMPONENT_ORDER);
+        }
+    }
+    if let Some(Item::Table(triggers)) = root.get_mut("trigger") {
+        for (_, triggers) in triggers.iter_mut() {
+            if let Item::ArrayOfTables(triggers) = triggers {
+                for trigger in triggers.iter_mut() {
+                    sort_table(trigger, TRIGGER_ORDER);
+                    if let Some(component) = trigger.get_mut("component") {
+                        sort_item(component, COMPONENT_ORDER);
+                    }
+                }
+            }
+        }
+    }
+
+    let root_has_values = root.iter().any(|(_, item)| item.is_value());
+    let mut position = 0;
+    renumber_tables(root, &mut position, !root_has_values);
+
+    let mut formatted = document.to_string();
+    formatted.truncate(formatted.trim_end().len());
+    formatted.push('\n');
+    Ok(formatted)
+}
+
+fn rank(order: &[&str], key: &str) -> usize {
+    order.iter().position(|k| *k == key).unwrap_or(order.len())
+}
+
+fn sort_table(table: &mut Table, order: &[&str]) {
+    table.sort_values_by(|k1, _, k2, _| rank(order, k1).cmp(&rank(order, k2)));
+}
+
+fn sort_item(item: &mut Item, order: &[&str]) {
+    match item {
+        Item::Table(table) => sort_table(table, order),
+        Item::Value(Value::InlineTable(table)) => {
+            table.sort_values_by(|k1, _, k2, _| rank(order, k1).cmp(&rank(order, k2)));
+            // Inline tables cannot contain comments, so nothing is lost by
+            // resetting the whitespace that sorting has moved around.
+            table.fmt();
+        }
+        _ => (),
+    }
+}
+
+// Assigns table positions in (sorted) key order, so that headers are emitted
+// in that order, and puts exactly one blank line before each header.
+fn renumber_tables(table: &mut Table, position: &mut usize, mut first: bool) {
+    for (_, item) in table.iter_mut() {
+        let tables: Vec<&mut Table> = match item {
+            Item::Table(table) => vec![table],
+            Item::ArrayOfTables(array) => array.iter_mut().collect(),
+            _ => continue,
+        };
+        for table in tables {
+            *position += 1;
+            table.set_position(*position);
+            if !table.is_implicit() && !table.is_dotted() {
+                let decor = table.decor_mut();
+                let prefix = decor
+                    .prefix()
+                    .and_then(|prefix| prefix.as_str())
+                    .unwrap_or_default()
+                    .trim_start()
+                    .to_owned();
+                let separator = if first { "" } else { "\n" };
+                decor.set_prefix(format!("{separator}{prefix}"));
+                first = false;
+            }
+            renumber_tables(table, position, first);
+            first = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::{gen::SchemaGenerator, JsonSchema};
+
+    use super::*;
+    use crate::schema::v2::{AppDetails, Component};
+
+    fn schema_keys<T: JsonSchema>() -> Vec<String> {
+        let schema = SchemaGenerator::default().into_root_schema_for::<T>();
+        schema
+            .schema
+            .object
+            .unwrap()
+            .properties
+            .into_keys()
+            .collect()
+    }
+
+    fn assert_ordered(order: &[&str], keys: Vec<String>) {
+        for key in keys {
+            assert!(
+                order.contains(&key.as_str()),
+                "`{key}` has no place in {order:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn orders_cover_every_schema_key() {
+        let root = crate::json_schema::app_manifest_schema();
+        assert_ordered(
+            ROOT_ORDER,
+            root.schema.object.unwrap().properties.into_keys().collect(),
+        );
+        assert_ordered(APPLICATION_ORDER, schema_keys::<AppDetails>());
+        assert_ordered(COMPONENT_ORDER, schema_keys::<Component>());
+    }
+}
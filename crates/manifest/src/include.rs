@@ -0,0 +1,209 @@
+//! Support for splitting a manifest across files with `include = [...]`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use toml::{Table, Value};
+
+use crate::Error;
+
+const INCLUDE_KEY: &str = "include";
+
+/// Returns true if the manifest has an `include` array.
+pub(crate) fn has_includes(manifest: &Table) -> bool {
+    manifest.contains_key(INCLUDE_KEY)
+}
+
+/// Merges the files listed in the manifest's `include` array into the
+/// manifest, removing the `include` key.
+///
+/// Each entry is a path or glob pattern relative to the manifest directory.
+/// Tables are merged recursively, and `[[trigger.<type>]]` entries from all
+/// files are concatenated; any other key defined in more than one file is an
+/// error. Paths within included files (such as component sources) remain
+/// relative to the manifest directory.
+pub(crate) fn resolve_includes(manifest: &mut Table, manifest_path: &Path) -> Result<(), Error> {
+    let files = included_files(manifest, manifest_path)?;
+    manifest.remove(INCLUDE_KEY);
+
+    let mut origins = Origins::new(manifest_path);
+    for file in files {
+        let included = read_included(&file)?;
+        merge_table(manifest, included, "", &file, &mut origins)?;
+    }
+    Ok(())
+}
+
+/// Returns the files matched by the manifest's `include` array, in the order
+/// they are merged, each only once.
+pub(crate) fn included_files(
+    manifest: &Table,
+    manifest_path: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    let Some(include) = manifest.get(INCLUDE_KEY) else {
+        return Ok(vec![]);
+    };
+    let patterns: Vec<String> = include
+        .clone()
+        .try_into()
+        .map_err(|_| Error::InvalidInclude {
+            path: manifest_path.to_owned(),
+            reason: "`include` must be an array of file paths or glob patterns".into(),
+        })?;
+
+    let base_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let mut seen = vec![manifest_path.canonicalize()?];
+    let mut files = vec![];
+
+    for pattern in patterns {
+        for file in expand_pattern(base_dir, &pattern, manifest_path)? {
+            let canonical = file.canonicalize()?;
+            if seen.contains(&canonical) {
+                continue;
+            }
+            seen.push(canonical);
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+fn expand_pattern(
+    base_dir: &Path,
+    pattern: &str,
+    manifest_path: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    let invalid = |reason: String| Error::InvalidInclude {
+        path: manifest_path.to_owned(),
+        reason,
+    };
+
+    let full_pattern = base_dir.join(pattern);
+    let full_pattern = full_pattern
+        .to_str()
+        .ok_or_else(|| invalid(format!("include {pattern:?} is not valid UTF-8")))?;
+    let mut files = glob::glob(full_pattern)
+        .map_err(|e| invalid(format!("include {pattern:?} is not a valid pattern: {e}")))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(format!("could not read include {pattern:?}: {e}")))?;
+    files.retain(|f| f.is_file());
+    if files.is_empty() {
+        return Err(invalid(format!("include {pattern:?} matched no files")));
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_included(file: &Path) -> Result<Table, Error> {
+    let invalid = |reason: String| Error::InvalidInclude {
+        path: file.to_owned(),
+        reason,
+    };
+
+    let contents = std::fs::read_to_string(file)?;
+    let mut table: Table = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+    if table.contains_key(INCLUDE_KEY) {
+        return Err(invalid(
+            "included files may not themselves use `include`".into(),
+        ));
+    }
+    if let Some(version) = table.remove("spin_manifest_version") {
+        if version.as_integer() != Some(2) {
+            return Err(invalid(format!(
+                "included files must use spin_manifest_version 2, not {version}"
+            )));
+        }
+    }
+    Ok(table)
+}
+
+fn merge_table(
+    into: &mut Table,
+    from: Table,
+    prefix: &str,
+    file: &Path,
+    origins: &mut Origins,
+) -> Result<(), Error> {
+    for (name, value) in from {
+        let key = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}.{name}")
+        };
+        match (into.get_mut(&name), value) {
+            (None, value) => {
+                origins.insert(&key, file);
+                into.insert(name, value);
+            }
+            (Some(Value::Table(existing)), Value::Table(value)) => {
+                merge_table(existing, value, &key, file, origins)?;
+            }
+            (Some(Value::Array(existing)), Value::Array(value)) if prefix == "trigger" => {
+                existing.extend(value);
+            }
+            (Some(_), _) => {
+                let existing = origins.get(&key).to_owned();
+                return Err(Error::IncludeConflict {
+                    key,
+                    path: file.to_owned(),
+                    existing,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Tracks which file defined each key added by an include.
+struct Origins<'a> {
+    manifest_path: &'a Path,
+    files: HashMap<String, PathBuf>,
+}
+
+impl<'a> Origins<'a> {
+    fn new(manifest_path: &'a Path) -> Self {
+        Self {
+            manifest_path,
+            files: Default::default(),
+        }
+    }
+
+    fn insert(&mut self, key: &str, file: &Path) {
+        self.files.insert(key.to_owned(), file.to_owned());
+    }
+
+    // Returns the file defining the key or its closest defined parent table.
+    fn get(&self, key: &str) -> &Path {
+        let mut key = key;
+        loop {
+            if let Some(file) = self.files.get(key) {
+                return file;
+            }
+            match key.rsplit_once('.') {
+                Some((parent, _)) => key = parent,
+                None => return self.manifest_path,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn included_files_are_listed_in_merge_order() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/include");
+        let files = crate::included_files(dir.join("spin.toml")).unwrap();
+        assert_eq!(
+            vec![
+                dir.join("components/api.toml"),
+                dir.join("components/worker.toml"),
+                dir.join("overrides/prod.toml"),
+            ],
+            files
+        );
+    }
+}
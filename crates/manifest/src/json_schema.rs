@@ -0,0 +1,97 @@
+//! JSON Schema for the Spin manifest, for editors and external validators.
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{RootSchema, Schema},
+};
+
+use crate::schema::v2::AppManifest;
+
+/// Returns the JSON Schema of the V2 manifest (`spin.toml`).
+///
+/// The schema is derived from the serde types in [`crate::schema::v2`], so
+/// it stays in sync with what the manifest parser accepts. Keys which are
+/// handled before deserialization, such as `include`, are added here.
+pub fn app_manifest_schema() -> RootSchema {
+    let mut gen = SchemaGenerator::default();
+    let mut root = gen.root_schema_for::<AppManifest>();
+
+    let mut include = gen.subschema_for::<Vec<String>>();
+    if let Schema::Object(include) = &mut include {
+        include.metadata().description = Some(
+            "Other files whose definitions are merged into this manifest. Each entry is a path \
+             or glob pattern relative to the manifest.\n\nExample: `include = [\"components/*.toml\"]`"
+                .into(),
+        );
+    }
+    root.schema
+        .object()
+        .properties
+        .insert("include".into(), include);
+
+    root.definitions.extend(gen.take_definitions());
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    // Follows `$ref`s (including those wrapped in `allOf` to carry a
+    // description) to the schema they refer to.
+    fn resolve<'a>(schema: &'a Value, definitions: &'a Value) -> &'a Value {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/definitions/");
+            return resolve(&definitions[name], definitions);
+        }
+        match schema["allOf"].as_array() {
+            Some(all_of) if all_of.len() == 1 => resolve(&all_of[0], definitions),
+            _ => schema,
+        }
+    }
+
+    fn assert_keys_in_schema(value: &Value, schema: &Value, definitions: &Value, path: &str) {
+        let schema = resolve(schema, definitions);
+        let Some(object) = value.as_object() else {
+            return;
+        };
+        if let Some(properties) = schema["properties"].as_object() {
+            let closed = schema["additionalProperties"] == Value::Bool(false);
+            for (key, value) in object {
+                match properties.get(key) {
+                    Some(property) => assert_keys_in_schema(
+                        value,
+                        property,
+                        definitions,
+                        &format!("{path}.{key}"),
+                    ),
+                    None if closed => panic!("`{path}.{key}` is rejected by the schema"),
+                    None => (),
+                }
+            }
+        } else if schema["additionalProperties"].is_object() {
+            for (key, value) in object {
+                assert_keys_in_schema(
+                    value,
+                    &schema["additionalProperties"],
+                    definitions,
+                    &format!("{path}.{key}"),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn schema_covers_maximal_manifest() {
+        let manifest_path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ui/maximal.toml");
+        let manifest = crate::manifest_from_file(manifest_path).unwrap();
+        let manifest = serde_json::to_value(manifest).unwrap();
+
+        let schema = serde_json::to_value(app_manifest_schema()).unwrap();
+        assert_keys_in_schema(&manifest, &schema, &schema["definitions"], "");
+        assert!(schema["properties"]["include"].is_object());
+    }
+}
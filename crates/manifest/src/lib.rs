@@ -3,22 +3,65 @@
 #![deny(missing_docs)]
 
 pub mod compat;
+pub mod edit;
 pub mod error;
+pub mod format;
+mod include;
+pub mod json_schema;
 pub mod normalize;
 pub mod schema;
+pub mod validate;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use schema::v2::AppManifest;
 
 pub use error::Error;
 
 /// Parses a V1 or V2 app manifest file into a [`AppManifest`].
+///
+/// A V2 manifest may split its definitions across files with an `include`
+/// array of paths or glob patterns, relative to the manifest. Included files
+/// are merged into the manifest before it is parsed.
 pub fn manifest_from_file(path: impl AsRef<Path>) -> Result<AppManifest, Error> {
+    let path = path.as_ref();
     let manifest_str = std::fs::read_to_string(path)?;
+    if ManifestVersion::detect(&manifest_str)? == ManifestVersion::V2 {
+        let mut manifest: toml::Table = toml::from_str(&manifest_str)?;
+        if include::has_includes(&manifest) {
+            include::resolve_includes(&mut manifest, path)?;
+            return Ok(manifest.try_into()?);
+        }
+    }
     manifest_from_str(&manifest_str)
 }
 
+/// Returns the files included by the V2 app manifest file at the given path
+/// through its `include` array. A V1 manifest includes no files.
+pub fn included_files(path: impl AsRef<Path>) -> Result<Vec<PathBuf>, Error> {
+    let path = path.as_ref();
+    let manifest_str = std::fs::read_to_string(path)?;
+    if ManifestVersion::detect(&manifest_str)? != ManifestVersion::V2 {
+        return Ok(vec![]);
+    }
+    let manifest: toml::Table = toml::from_str(&manifest_str)?;
+    include::included_files(&manifest, path)
+}
+
+// Parses a V2 app manifest file into a TOML table, merging in any included
+// files, or returns `None` for a V1 manifest.
+fn v2_table_from_file(path: &Path) -> Result<Option<toml::Table>, Error> {
+    let manifest_str = std::fs::read_to_string(path)?;
+    if ManifestVersion::detect(&manifest_str)? != ManifestVersion::V2 {
+        return Ok(None);
+    }
+    let mut manifest: toml::Table = toml::from_str(&manifest_str)?;
+    if include::has_includes(&manifest) {
+        include::resolve_includes(&mut manifest, path)?;
+    }
+    Ok(Some(manifest))
+}
+
 /// Parses a V1 or V2 app manifest into a [`AppManifest`].
 pub fn manifest_from_str(v1_or_v2_toml: &str) -> Result<AppManifest, Error> {
     // TODO: would it be faster to parse into a toml::Table rather than parse twice?
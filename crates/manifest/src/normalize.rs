@@ -1,21 +1,143 @@
 //! Manifest normalization functions.
 
-use std::collections::HashSet;
+use std::{collections::HashSet, fmt::Display};
 
-use crate::schema::v2::{AppManifest, ComponentSpec, KebabId};
+use indexmap::IndexMap;
+
+use anyhow::Context;
+use spin_serde::DependencyName;
+
+use crate::schema::v2::{
+    AppManifest, Component, ComponentDependency, ComponentSpec, KebabId, TriggerComponents,
+    TriggerMode,
+};
 
 /// Normalizes some optional [`AppManifest`] features into a canonical form:
 /// - Inline components in trigger configs are moved into top-level
 ///   components and replaced with a reference.
 /// - Any triggers without an ID are assigned a generated ID.
-pub fn normalize_manifest(manifest: &mut AppManifest) {
-    normalize_trigger_ids(manifest);
-    normalize_inline_components(manifest);
+///
+/// Returns what was changed, so that diagnostics can refer to inline
+/// components as the user wrote them, and so that the changes can be undone
+/// with [`denormalize_manifest`].
+pub fn normalize_manifest(manifest: &mut AppManifest) -> Normalization {
+    let generated_trigger_ids = normalize_trigger_ids(manifest);
+    let inline_components = normalize_inline_components(manifest);
+    Normalization {
+        inline_components,
+        generated_trigger_ids,
+    }
+}
+
+/// The changes made by [`normalize_manifest`].
+#[derive(Clone, Debug, Default)]
+pub struct Normalization {
+    /// Where each inline component moved into the top-level components was
+    /// defined, keyed by its generated component ID.
+    pub inline_components: IndexMap<KebabId, InlineComponentOrigin>,
+    /// The IDs generated for triggers which did not have one.
+    pub generated_trigger_ids: HashSet<String>,
+}
+
+/// Undoes [`normalize_manifest`], so that a manifest which was loaded,
+/// modified and saved keeps the terse form the user wrote:
+/// - Generated inline components are moved back into their triggers.
+/// - Generated trigger IDs are removed.
+///
+/// Changes made to the manifest since it was normalized are respected: a
+/// generated component is only moved back if it is still referenced once,
+/// from where it was defined, and not by any environment.
+pub fn denormalize_manifest(manifest: &mut AppManifest, normalization: &Normalization) {
+    for (id, origin) in &normalization.inline_components {
+        let referenced_elsewhere = component_references(manifest, id) != 1
+            || manifest
+                .environments
+                .values()
+                .any(|environment| environment.components.contains_key(id));
+        if referenced_elsewhere {
+            continue;
+        }
+        let Some(trigger) = manifest
+            .triggers
+            .values_mut()
+            .flatten()
+            .find(|trigger| trigger.id == origin.trigger_id)
+        else {
+            continue;
+        };
+        let spec = match &origin.role {
+            None => trigger.component.as_mut(),
+            Some(role) => trigger
+                .components
+                .specs_mut()
+                .find(|(r, spec)| r == role && is_reference_to(spec, id))
+                .map(|(_, spec)| spec),
+        };
+        let Some(spec) = spec.filter(|spec| is_reference_to(spec, id)) else {
+            continue;
+        };
+        if let Some(component) = manifest.components.shift_remove(id) {
+            *spec = ComponentSpec::Inline(Box::new(component));
+        }
+    }
+
+    for trigger in manifest.triggers.values_mut().flatten() {
+        if normalization.generated_trigger_ids.contains(&trigger.id) {
+            trigger.id.clear();
+        }
+    }
+}
+
+fn is_reference_to(spec: &ComponentSpec, id: &KebabId) -> bool {
+    matches!(spec, ComponentSpec::Reference(reference) if reference == id)
 }
 
-fn normalize_inline_components(manifest: &mut AppManifest) {
-    // Normalize inline components
+// Counts the trigger component specs which refer to the given component.
+fn component_references(manifest: &AppManifest, id: &KebabId) -> usize {
+    manifest
+        .triggers
+        .values()
+        .flatten()
+        .flat_map(|trigger| {
+            trigger
+                .component
+                .iter()
+                .chain(trigger.components.specs().map(|(_, spec)| spec))
+        })
+        .filter(|spec| is_reference_to(spec, id))
+        .count()
+}
+
+/// Where an inline component was defined before [`normalize_manifest`]
+/// moved it into the top-level components.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlineComponentOrigin {
+    /// The ID of the trigger which defined the component.
+    pub trigger_id: String,
+    /// The key of the trigger's `components` map which defined the
+    /// component, [`CHAIN_ROLE`](crate::schema::v2::CHAIN_ROLE) if it was defined in the trigger's chain, or
+    /// `None` if it was defined by the trigger's `component`.
+    pub role: Option<String>,
+}
+
+impl Display for InlineComponentOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.role {
+            Some(role) => write!(
+                f,
+                "inline component for `{role}` of trigger `{}`",
+                self.trigger_id
+            ),
+            None => write!(f, "inline component of trigger `{}`", self.trigger_id),
+        }
+    }
+}
+
+fn normalize_inline_components(
+    manifest: &mut AppManifest,
+) -> IndexMap<KebabId, InlineComponentOrigin> {
     let components = &mut manifest.components;
+    let mut origins = IndexMap::new();
 
     for trigger in manifest.triggers.values_mut().flatten() {
         let trigger_id = &trigger.id;
@@ -23,39 +145,24 @@ fn normalize_inline_components(manifest: &mut AppManifest) {
         let component_specs = trigger
             .component
             .iter_mut()
+            .map(|spec| (None, spec))
             .chain(
                 trigger
                     .components
-                    .values_mut()
-                    .flat_map(|specs| specs.0.iter_mut()),
+                    .specs_mut()
+                    .map(|(role, spec)| (Some(role), spec)),
             )
             .collect::<Vec<_>>();
-        let multiple_components = component_specs.len() > 1;
 
-        let mut counter = 1;
-        for spec in component_specs {
+        for (role, spec) in component_specs {
             if !matches!(spec, ComponentSpec::Inline(_)) {
                 continue;
             };
 
-            let inline_id = {
-                // Try a "natural" component ID...
-                let mut id = KebabId::try_from(format!("{trigger_id}-component"));
-                // ...falling back to a counter-based component ID
-                if multiple_components
-                    || id.is_err()
-                    || components.contains_key(id.as_ref().unwrap())
-                {
-                    id = Ok(loop {
-                        let id = KebabId::try_from(format!("inline-component{counter}")).unwrap();
-                        if !components.contains_key(&id) {
-                            break id;
-                        }
-                        counter += 1;
-                    });
-                }
-                id.unwrap()
-            };
+            // Name the component after the trigger and the role it plays,
+            // e.g. `admin-handler-component` or `my-trigger-cache`.
+            let suffix = role.map_or("component".to_owned(), |role| role.replace('_', "-"));
+            let inline_id = generated_id(components, &format!("{trigger_id}-{suffix}"));
 
             // Replace the inline component with a reference...
             let inline_spec = std::mem::replace(spec, ComponentSpec::Reference(inline_id.clone()));
@@ -64,11 +171,137 @@ fn normalize_inline_components(manifest: &mut AppManifest) {
             };
             // ...moving the inline component into the top-level components map.
             components.insert(inline_id.clone(), *component);
+            origins.insert(
+                inline_id,
+                InlineComponentOrigin {
+                    trigger_id: trigger_id.clone(),
+                    role: role.map(str::to_owned),
+                },
+            );
         }
     }
+    origins
 }
 
-fn normalize_trigger_ids(manifest: &mut AppManifest) {
+/// Composes each trigger with `mode = "chain"` into a trigger of a single
+/// component: each component of the chain but the last gets a dependency on
+/// the next through its handler import, and the trigger refers to the first.
+/// Components which are used elsewhere are copied, rather than changed in
+/// place, with the copy named after the trigger, e.g. `api-route-auth`.
+///
+/// Unlike [`normalize_manifest`], this cannot be undone. Inline components
+/// should be normalized first, and any environment applied, so that the
+/// copies include the environment's settings.
+pub fn normalize_chains(manifest: &mut AppManifest) -> anyhow::Result<()> {
+    manifest.validate_chains()?;
+
+    // Count every use of each component before any chain is composed, so
+    // that wrapping a component for one chain never changes another.
+    let shared = manifest
+        .components
+        .keys()
+        .filter(|id| {
+            let dependents = manifest
+                .components
+                .values()
+                .flat_map(|component| component.dependencies.inner.values())
+                .filter(|dependency| {
+                    matches!(dependency, ComponentDependency::Component { component, .. } if component == *id)
+                })
+                .count();
+            component_references(manifest, id) + dependents > 1
+        })
+        .cloned()
+        .collect::<HashSet<_>>();
+
+    let components = &mut manifest.components;
+    for (trigger_type, triggers) in &mut manifest.triggers {
+        for trigger in triggers {
+            if trigger.mode != Some(TriggerMode::Chain) {
+                continue;
+            }
+            let TriggerComponents::Chain(chain) = std::mem::take(&mut trigger.components) else {
+                unreachable!("chains should have been validated");
+            };
+            let handler =
+                chain_handler_import(trigger_type).expect("chains should have been validated");
+            let chain = chain
+                .into_iter()
+                .map(|spec| match spec {
+                    ComponentSpec::Reference(id) => Ok(id),
+                    ComponentSpec::Inline(_) => Err(anyhow::anyhow!(
+                        "inline components of trigger `{}` should have been normalized",
+                        trigger.id
+                    )),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            // Wrap each component around the next, from the innermost out.
+            let (last, wrappers) = chain.split_last().context("chain is empty")?;
+            let mut next = last.clone();
+            for id in wrappers.iter().rev() {
+                let component = components.get(id).with_context(|| {
+                    format!("trigger `{}` chains undefined component {id:?}", trigger.id)
+                })?;
+                let wrapper_id = if shared.contains(id) {
+                    let wrapper_id = generated_id(components, &format!("{}-{id}", trigger.id));
+                    components.insert(wrapper_id.clone(), component.clone());
+                    wrapper_id
+                } else {
+                    id.clone()
+                };
+                components[&wrapper_id].dependencies.inner.insert(
+                    handler.clone(),
+                    ComponentDependency::Component {
+                        component: next,
+                        export: None,
+                    },
+                );
+                next = wrapper_id;
+            }
+            trigger.component = Some(ComponentSpec::Reference(next));
+            trigger.mode = None;
+        }
+    }
+    Ok(())
+}
+
+/// The import through which a component of a chained trigger of the given
+/// type calls the next component, or `None` if the trigger type does not
+/// support chaining.
+pub fn chain_handler_import(trigger_type: &str) -> Option<DependencyName> {
+    match trigger_type {
+        "http" => Some("wasi:http/incoming-handler".parse().unwrap()),
+        _ => None,
+    }
+}
+
+// Returns `base`, adding `2`, `3`, ... if that ID is already taken, or a
+// counter-based ID if `base` is not a valid component ID.
+fn generated_id(components: &IndexMap<KebabId, Component>, base: &str) -> KebabId {
+    (1..)
+        .map(|n| match n {
+            1 => base.to_owned(),
+            n => format!("{base}{n}"),
+        })
+        .map(KebabId::try_from)
+        .find(|id| !matches!(id, Ok(id) if components.contains_key(id)))
+        .unwrap()
+        .unwrap_or_else(|_| fallback_id(components))
+}
+
+// Returns a counter-based component ID, for generated components whose
+// trigger ID and role do not make a valid component ID.
+fn fallback_id(components: &IndexMap<KebabId, Component>) -> KebabId {
+    (1..)
+        .map(|n| KebabId::try_from(format!("inline-component{n}")).unwrap())
+        .find(|id| !components.contains_key(id))
+        .unwrap()
+}
+
+// Returns the generated trigger IDs.
+fn normalize_trigger_ids(manifest: &mut AppManifest) -> HashSet<String> {
+    let mut generated = HashSet::new();
     let mut trigger_ids = manifest
         .triggers
         .values()
@@ -87,7 +320,8 @@ fn normalize_trigger_ids(manifest: &mut AppManifest) {
                 let candidate_id = format!("{component_id}-{trigger_type}-trigger");
                 if !trigger_ids.contains(&candidate_id) {
                     trigger.id.clone_from(&candidate_id);
-                    trigger_ids.insert(candidate_id);
+                    trigger_ids.insert(candidate_id.clone());
+                    generated.insert(candidate_id);
                     continue;
                 }
             }
@@ -96,10 +330,61 @@ fn normalize_trigger_ids(manifest: &mut AppManifest) {
                 let id = format!("{trigger_type}-trigger{counter}");
                 if !trigger_ids.contains(&id) {
                     trigger_ids.insert(id.clone());
+                    generated.insert(id.clone());
                     break id;
                 }
                 counter += 1;
             }
         }
     }
+    generated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denormalize_restores_terse_manifest() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/ui/normalization.toml");
+        let original = crate::manifest_from_file(path).unwrap();
+
+        let mut manifest = original.clone();
+        let normalization = normalize_manifest(&mut manifest);
+        let id = KebabId::try_from("jobs-workers2".to_owned()).unwrap();
+        assert_eq!(
+            "inline component for `workers` of trigger `jobs`",
+            normalization.inline_components[&id].to_string()
+        );
+        denormalize_manifest(&mut manifest, &normalization);
+        assert_eq!(
+            toml::to_string(&original).unwrap(),
+            toml::to_string(&manifest).unwrap()
+        );
+    }
+
+    #[test]
+    fn denormalize_keeps_components_referenced_elsewhere() {
+        let mut manifest: AppManifest = toml::from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "app"
+            [[trigger.http]]
+            route = "/..."
+            component = { source = "app.wasm" }
+            "#,
+        )
+        .unwrap();
+        let normalization = normalize_manifest(&mut manifest);
+        let id = KebabId::try_from("http-trigger1-component".to_owned()).unwrap();
+        let mut trigger = manifest.triggers["http"][0].clone();
+        trigger.id = "second".into();
+        manifest.triggers.get_mut("http").unwrap().push(trigger);
+
+        denormalize_manifest(&mut manifest, &normalization);
+        assert!(manifest.components.contains_key(&id));
+        assert!(manifest.triggers["http"][0].id.is_empty());
+        assert_eq!("second", manifest.triggers["http"][1].id);
+    }
 }
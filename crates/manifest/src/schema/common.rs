@@ -1,7 +1,9 @@
 use std::fmt::Display;
 
+use indexmap::IndexMap;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use spin_serde::KebabId;
 
 use wasm_pkg_common::{package::PackageRef, registry::Registry};
 
@@ -21,9 +23,13 @@ pub struct Variable {
     pub required: bool,
     /// The value of the variable if no value is supplied at runtime. If specified,
     /// the value must be a string. If not specified, `required`` must be `true`.
+    /// The default may refer to other application variables using `{{ ... }}`
+    /// syntax, as long as the references do not form a cycle.
     ///
     /// Example: `default = "default value"`
     ///
+    /// Example: `default = "{{ api_host }}/users"`
+    ///
     /// Learn more: https://spinframework.dev/variables#adding-variables-to-your-applications
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub default: Option<String>,
@@ -34,6 +40,91 @@ pub struct Variable {
     /// Learn more: https://spinframework.dev/variables#adding-variables-to-your-applications
     #[serde(default, skip_serializing_if = "is_false")]
     pub secret: bool,
+    /// The type of the variable's value. Values (including the default) are
+    /// checked against this type when they are resolved. If not specified,
+    /// the type is `"string"`.
+    ///
+    /// Example: `type = "int"`
+    ///
+    /// Learn more: https://spinframework.dev/variables#adding-variables-to-your-applications
+    #[serde(
+        default,
+        rename = "type",
+        skip_serializing_if = "VariableType::is_string",
+        with = "spin_serde::aliases"
+    )]
+    #[schemars(with = "VariableType")]
+    pub variable_type: VariableType,
+    /// A regular expression that the whole value must match.
+    ///
+    /// Example: `pattern = "^[a-z]+://"`
+    ///
+    /// Learn more: https://spinframework.dev/variables#adding-variables-to-your-applications
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The values that the variable may take. If empty, any value is allowed.
+    ///
+    /// Example: `allowed_values = ["debug", "info", "warn"]`
+    ///
+    /// Learn more: https://spinframework.dev/variables#adding-variables-to-your-applications
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_values: Vec<String>,
+    /// The minimum allowed value. Only valid for `int` and `float` variables.
+    ///
+    /// Example: `min = 1024`
+    ///
+    /// Learn more: https://spinframework.dev/variables#adding-variables-to-your-applications
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// The maximum allowed value. Only valid for `int` and `float` variables.
+    ///
+    /// Example: `max = 65535`
+    ///
+    /// Learn more: https://spinframework.dev/variables#adding-variables-to-your-applications
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// The type of a variable's value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    /// `type = "string"`: any string. This is the default.
+    #[default]
+    String,
+    /// `type = "int"`: a signed 64-bit integer, e.g. `"8080"`.
+    Int,
+    /// `type = "float"`: a floating point number, e.g. `"0.5"`.
+    Float,
+    /// `type = "bool"`: `"true"` or `"false"`.
+    Bool,
+    /// `type = "duration"`: a duration with units, e.g. `"1h30m"` or `"250ms"`.
+    Duration,
+    /// `type = "string_list"`: a comma-separated list of strings, e.g. `"a,b,c"`.
+    StringList,
+}
+
+impl spin_serde::aliases::Aliased for VariableType {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("string", Self::String),
+        ("int", Self::Int),
+        ("float", Self::Float),
+        ("bool", Self::Bool),
+        ("duration", Self::Duration),
+        ("string_list", Self::StringList),
+    ];
+}
+
+impl VariableType {
+    /// Returns true if this is the default `String` type.
+    pub fn is_string(&self) -> bool {
+        matches!(self, Self::String)
+    }
+
+    /// Returns true if values of this type are numbers.
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Self::Int | Self::Float)
+    }
 }
 
 /// The file, package, or URL containing the component Wasm binary. This may be:
@@ -147,9 +238,59 @@ pub enum WasiFilesMount {
         ///
         /// Learn more: https://spinframework.dev/writing-apps#including-files-with-components
         destination: String,
+        /// Glob patterns, relative to `source`, for files which are not made
+        /// available in the guest.
+        ///
+        /// Example: `exclude = ["**/*.bak", "drafts/**/*"]`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        exclude: Vec<String>,
+        /// If true, the guest can never write to these files, even if Spin is
+        /// run with transient writes allowed.
+        ///
+        /// Example: `read_only = true`
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        read_only: bool,
+        /// How to treat symbolic links under `source`. The default is to follow them.
+        ///
+        /// Example: `symlinks = "skip"`
+        #[serde(
+            default,
+            skip_serializing_if = "SymlinkPolicy::is_follow",
+            with = "spin_serde::aliases"
+        )]
+        #[schemars(with = "SymlinkPolicy")]
+        symlinks: SymlinkPolicy,
     },
 }
 
+/// How to treat symbolic links when collecting the files for a mount.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// `symlinks = "follow"`: include the file which the link points to. This is the default.
+    #[default]
+    Follow,
+    /// `symlinks = "skip"`: leave out links, and everything under linked directories.
+    Skip,
+    /// `symlinks = "error"`: fail to load the application if the mount contains a link.
+    Error,
+}
+
+impl spin_serde::aliases::Aliased for SymlinkPolicy {
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("follow", Self::Follow),
+        ("skip", Self::Skip),
+        ("error", Self::Error),
+    ];
+}
+
+impl SymlinkPolicy {
+    /// Returns true if this is the default `Follow` policy.
+    pub fn is_follow(&self) -> bool {
+        matches!(self, Self::Follow)
+    }
+}
+
 /// Component build configuration
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -179,6 +320,25 @@ pub struct ComponentBuildConfig {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     #[schemars(with = "Vec<json_schema::WatchCommand>")]
     pub watch: Vec<String>,
+    /// Environment variables to set for the build commands.
+    ///
+    /// Example: `environment = { CARGO_TARGET_DIR = "target" }`
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub environment: IndexMap<String, String>,
+    /// Feature flags for the build. The placeholder `{{ features }}` in a
+    /// build command is replaced with the features, separated by commas.
+    ///
+    /// Example: `features = ["metrics"]`, `command = "cargo build --features '{{ features }}'"`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Named build profiles, such as `dev` or `release`. A profile is selected
+    /// with `spin build --profile <name>` or `spin watch --profile <name>`;
+    /// its settings replace those of the component build, except that its
+    /// environment variables are added to the component build's.
+    ///
+    /// Example: `[component.cart.build.profiles.release]`
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub profiles: IndexMap<KebabId, BuildProfile>,
 }
 
 impl ComponentBuildConfig {
@@ -190,6 +350,75 @@ impl ComponentBuildConfig {
         };
         as_vec.into_iter()
     }
+
+    /// Returns the build configuration for the named profile, or for no
+    /// profile if `profile` is `None` or the component does not define it.
+    /// The `{{ features }}` placeholder in the returned commands is replaced,
+    /// and the returned configuration has no profiles.
+    pub fn with_profile(&self, profile: Option<&str>) -> Self {
+        let mut config = Self {
+            profiles: Default::default(),
+            ..self.clone()
+        };
+        if let Some(overrides) = profile.and_then(|p| self.profile(p)) {
+            if let Some(command) = &overrides.command {
+                config.command = command.clone();
+            }
+            if let Some(watch) = &overrides.watch {
+                config.watch = watch.clone();
+            }
+            if let Some(features) = &overrides.features {
+                config.features = features.clone();
+            }
+            config.environment.extend(overrides.environment.clone());
+        }
+
+        let features = config.features.join(",");
+        let expand = |command: &String| command.replace("{{ features }}", &features);
+        config.command = match &config.command {
+            Commands::Single(command) => Commands::Single(expand(command)),
+            Commands::Multiple(commands) => {
+                Commands::Multiple(commands.iter().map(expand).collect())
+            }
+        };
+        config
+    }
+
+    /// The named build profile, if the component defines it.
+    pub fn profile(&self, name: &str) -> Option<&BuildProfile> {
+        self.profiles
+            .iter()
+            .find_map(|(id, profile)| (id.as_ref() == name).then_some(profile))
+    }
+}
+
+/// A named set of build settings. Settings which are omitted are taken from
+/// the component build.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BuildProfile {
+    /// The command or commands to build the component with this profile.
+    ///
+    /// Example: `command = "cargo build --release"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<Commands>,
+    /// Environment variables to set for the build commands, in addition to
+    /// those of the component build.
+    ///
+    /// Example: `environment = { RUSTFLAGS = "-C opt-level=3" }`
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub environment: IndexMap<String, String>,
+    /// Feature flags for the build, replacing those of the component build.
+    ///
+    /// Example: `features = ["metrics", "tracing"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+    /// Source files to use in `spin watch` with this profile.
+    ///
+    /// Example: `watch = ["src/**/*.rs", "release.toml"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<Vec<json_schema::WatchCommand>>")]
+    pub watch: Option<Vec<String>>,
 }
 
 /// The command or commands to build the application. If multiple commands
@@ -6,6 +6,9 @@ use serde::Deserialize;
 pub mod v1;
 /// Serialization types for the Spin manifest V2.
 pub mod v2;
+/// Serialization types for the draft Spin manifest V3.
+#[cfg(feature = "unstable-v3")]
+pub mod v3;
 
 // Types common between manifest versions. Re-exported from versioned modules
 // to make them easier to split if necessary.
@@ -1,14 +1,20 @@
 use anyhow::Context;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use spin_serde::{DependencyName, DependencyPackageName, FixedVersion, LowerSnakeId};
-pub use spin_serde::{KebabId, SnakeId};
-use std::path::PathBuf;
-
-pub use super::common::{ComponentBuildConfig, ComponentSource, Variable, WasiFilesMount};
+use spin_serde::{
+    aliases::Deprecation, DependencyName, DependencyPackageName, FixedVersion, LowerSnakeId,
+};
+pub use spin_serde::{ByteSize, HumanDuration, KebabId, SnakeId};
+use std::{num::NonZeroU32, path::PathBuf};
+
+pub use super::common::{
+    BuildProfile, Commands, ComponentBuildConfig, ComponentSource, SymlinkPolicy, Variable,
+    VariableType, WasiFilesMount,
+};
 use super::json_schema;
 
-pub(crate) type Map<K, V> = indexmap::IndexMap<K, V>;
+/// The map type used for tables in the manifest, which preserves their order.
+pub type Map<K, V> = indexmap::IndexMap<K, V>;
 
 /// App manifest
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
@@ -26,6 +32,22 @@ pub struct AppManifest {
     /// Learn more: https://spinframework.dev/variables, https://spinframework.dev/dynamic-configuration#application-variables-runtime-configuration
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub variables: Map<LowerSnakeId, Variable>,
+    /// Configuration profiles, such as `dev` or `prod`. Each profile declares
+    /// variables which override or add to the application variables when the
+    /// profile is selected at startup, e.g. with `SPIN_VARIABLES_PROFILE=prod`.
+    /// Applying an environment selects the profile of the same name by default.
+    ///
+    /// Example: `[profiles.prod.variables]`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub profiles: Map<KebabId, Profile>,
+    /// Target environments, such as `staging` or `prod`. Each environment can
+    /// override component settings; the environment to use is chosen when the
+    /// application is loaded. Variables for an environment are declared in the
+    /// profile of the same name.
+    ///
+    /// Example: `[environments.prod.component.api]`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub environments: Map<KebabId, Environment>,
     /// The triggers to which the application responds. Most triggers can appear
     /// multiple times with different parameters: for example, the `http` trigger may
     /// appear multiple times with different routes, or the `redis` trigger with
@@ -42,7 +64,9 @@ pub struct AppManifest {
 }
 
 impl AppManifest {
-    /// This method ensures that the dependencies of each component are valid.
+    /// This method ensures that the dependencies of each component are valid,
+    /// and that dependencies on other components refer to defined components
+    /// without forming a cycle.
     pub fn validate_dependencies(&self) -> anyhow::Result<()> {
         for (component_id, component) in &self.components {
             component
@@ -50,10 +74,141 @@ impl AppManifest {
                 .validate()
                 .with_context(|| format!("component {component_id:?} has invalid dependencies"))?;
         }
+        let mut diagnostics = vec![];
+        crate::validate::validate_component_dependencies(self, &mut diagnostics);
+        if let Some(diagnostic) = diagnostics.first() {
+            anyhow::bail!("`{}`: {}", diagnostic.key.join("."), diagnostic.message);
+        }
+        Ok(())
+    }
+
+    /// Validates that triggers with `mode = "chain"` are well-formed. See
+    /// [`crate::normalize::normalize_chains`].
+    pub fn validate_chains(&self) -> anyhow::Result<()> {
+        let mut diagnostics = vec![];
+        crate::validate::validate_chains(self, &mut diagnostics);
+        if let Some(diagnostic) = diagnostics.first() {
+            anyhow::bail!("`{}`: {}", diagnostic.key.join("."), diagnostic.message);
+        }
+        Ok(())
+    }
+
+    /// This method ensures that HTTP trigger routes are well-formed, and
+    /// that no two routes differ only in the names of their parameters
+    /// (e.g. `/users/:id` and `/users/:name`). Exact duplicates are allowed
+    /// here; the router reports them when the application starts.
+    pub fn validate_http_routes(&self) -> anyhow::Result<()> {
+        let mut diagnostics = vec![];
+        crate::validate::validate_routes(self, false, &mut diagnostics);
+        if let Some(diagnostic) = diagnostics.first() {
+            anyhow::bail!("`{}`: {}", diagnostic.key.join("."), diagnostic.message);
+        }
+        Ok(())
+    }
+
+    /// Validates that file mount destinations do not refer to variables,
+    /// which are not known when the files are laid out.
+    pub fn validate_file_destinations(&self) -> anyhow::Result<()> {
+        let mut diagnostics = vec![];
+        crate::validate::validate_file_destinations(self, &mut diagnostics);
+        if let Some(diagnostic) = diagnostics.first() {
+            anyhow::bail!("`{}`: {}", diagnostic.key.join("."), diagnostic.message);
+        }
+        Ok(())
+    }
+
+    /// Applies the overrides of the named target environment, and removes
+    /// all environments from the manifest. Inline components should be
+    /// normalized first so that the environment can refer to them by ID.
+    ///
+    /// The environment's variables are those of the profile of the same name,
+    /// which is left in place to be selected when the application starts. An
+    /// environment may be declared by either or both of these.
+    pub fn apply_environment(&mut self, name: &str) -> anyhow::Result<()> {
+        let environments = std::mem::take(&mut self.environments);
+        let environment = environments
+            .into_iter()
+            .find_map(|(id, env)| (id.as_ref() == name).then_some(env));
+        let Some(environment) = environment.or_else(|| {
+            self.profiles
+                .keys()
+                .any(|id| id.as_ref() == name)
+                .then(Environment::default)
+        }) else {
+            anyhow::bail!("the manifest does not define an environment or profile named {name:?}");
+        };
+
+        for (component_id, overrides) in environment.components {
+            let component = self.components.get_mut(&component_id).with_context(|| {
+                format!("environment {name:?} configures unknown component {component_id:?}")
+            })?;
+            if let Some(command) = overrides.build_command {
+                match &mut component.build {
+                    Some(build) => build.command = command,
+                    None => {
+                        component.build = Some(ComponentBuildConfig {
+                            command,
+                            workdir: None,
+                            watch: vec![],
+                            environment: Default::default(),
+                            features: vec![],
+                            profiles: Default::default(),
+                        })
+                    }
+                }
+            }
+            if let Some(hosts) = overrides.allowed_outbound_hosts {
+                component.allowed_outbound_hosts = hosts;
+                #[allow(deprecated)]
+                component.allowed_http_hosts.clear();
+            }
+        }
         Ok(())
     }
 }
 
+/// A configuration profile
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Variables which replace the application variables of the same name, or
+    /// add new ones, when the profile is selected.
+    ///
+    /// Example: `log_level = { default = "warn" }`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub variables: Map<LowerSnakeId, Variable>,
+}
+
+/// A target environment
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Environment {
+    /// Settings which replace those of the component of the same ID in this
+    /// environment.
+    ///
+    /// Example: `[environments.prod.component.api]`
+    #[serde(rename = "component")]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub components: Map<KebabId, EnvironmentComponent>,
+}
+
+/// Component settings for a target environment
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentComponent {
+    /// The command or commands to build the component in this environment.
+    ///
+    /// Example: `build_command = "cargo build --release"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<Commands>,
+    /// The network destinations which the component is allowed to access in
+    /// this environment.
+    ///
+    /// Example: `allowed_outbound_hosts = ["https://api.example.com"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_outbound_hosts: Option<Vec<String>>,
+}
+
 /// App details
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -125,16 +280,95 @@ pub struct Trigger {
     /// Learn more: https://spinframework.dev/triggers#triggers-and-components
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub component: Option<ComponentSpec>,
-    /// Reserved for future use.
+    /// With `mode = "chain"`, the ordered list of components which handle
+    /// the trigger, each calling the next through its imported handler.
+    /// Otherwise reserved for future use.
     ///
-    /// `components = { ... }`
-    #[serde(default, skip_serializing_if = "Map::is_empty")]
-    pub components: Map<String, OneOrManyComponentSpecs>,
+    /// Example: `components = ["auth", "ratelimit", "api"]`
+    #[serde(default, skip_serializing_if = "TriggerComponents::is_empty")]
+    pub components: TriggerComponents,
+    /// How the trigger's `components` handle the trigger.
+    ///
+    /// Example: `mode = "chain"`
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "spin_serde::aliases::option"
+    )]
+    pub mode: Option<TriggerMode>,
     /// Opaque trigger-type-specific config
     #[serde(flatten)]
     pub config: toml::Table,
 }
 
+/// The components of a trigger other than its `component`.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum TriggerComponents {
+    /// `components = { role = "component-id", ... }`
+    Roles(Map<String, OneOrManyComponentSpecs>),
+    /// `components = ["auth", "api"]`
+    Chain(Vec<ComponentSpec>),
+}
+
+/// The role which the components of a [`TriggerComponents::Chain`] play.
+pub const CHAIN_ROLE: &str = "chain";
+
+impl TriggerComponents {
+    /// Whether there are no components.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Roles(roles) => roles.is_empty(),
+            Self::Chain(chain) => chain.is_empty(),
+        }
+    }
+
+    /// The component specs, with the role each plays. The components of a
+    /// chain play the [`CHAIN_ROLE`].
+    pub fn specs(&self) -> Box<dyn Iterator<Item = (&str, &ComponentSpec)> + '_> {
+        match self {
+            Self::Roles(roles) => {
+                Box::new(roles.iter().flat_map(|(role, specs)| {
+                    specs.0.iter().map(move |spec| (role.as_str(), spec))
+                }))
+            }
+            Self::Chain(chain) => Box::new(chain.iter().map(|spec| (CHAIN_ROLE, spec))),
+        }
+    }
+
+    /// As [`Self::specs`], but mutable.
+    pub fn specs_mut(&mut self) -> Box<dyn Iterator<Item = (&str, &mut ComponentSpec)> + '_> {
+        match self {
+            Self::Roles(roles) => Box::new(roles.iter_mut().flat_map(|(role, specs)| {
+                specs.0.iter_mut().map(move |spec| (role.as_str(), spec))
+            })),
+            Self::Chain(chain) => Box::new(chain.iter_mut().map(|spec| (CHAIN_ROLE, spec))),
+        }
+    }
+}
+
+impl Default for TriggerComponents {
+    fn default() -> Self {
+        Self::Roles(Map::default())
+    }
+}
+
+/// How a trigger's `components` handle the trigger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerMode {
+    /// The first of the `components` handles the trigger, and each component
+    /// satisfies the handler import of the one before it, so that the
+    /// components before the last act as middleware. Chains are composed
+    /// when the application is loaded, and all of the chained components
+    /// run with the settings of the first.
+    Chain,
+}
+
+impl spin_serde::aliases::Aliased for TriggerMode {
+    const NAMES: &'static [(&'static str, Self)] = &[("chain", Self::Chain)];
+}
+
 /// One or many `ComponentSpec`(s)
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(transparent)]
@@ -186,6 +420,10 @@ impl TryFrom<toml::Value> for ComponentSpec {
 ///
 /// Example: `"my:import" = { url = "https://example.com/component.wasm", sha256 = "sha256:..." }`
 ///
+/// - Another component of the application.
+///
+/// Example: `"wasi:http/incoming-handler" = { component = "api" }`
+///
 /// Learn more: https://spinframework.dev/v3/writing-apps#using-component-dependencies
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged, deny_unknown_fields)]
@@ -263,6 +501,20 @@ pub enum ComponentDependency {
         /// Learn more: https://spinframework.dev/writing-apps#dependencies-from-a-url
         export: Option<String>,
     },
+    /// `... = { component = "component-id" }`
+    #[schemars(description = "")] // schema docs are on the parent
+    Component {
+        /// The ID of the application component that implements the dependency.
+        /// The component's own dependencies are composed into it, and it runs
+        /// with the settings of the component that depends on it.
+        ///
+        /// Example: `"wasi:http/incoming-handler" = { component = "api" }`
+        component: KebabId,
+        /// The name of the export in the component. If omitted, this defaults to the name of the import.
+        ///
+        /// Example: `"my:dep/import" = { export = "your:impl/export", component = "api" }`
+        export: Option<String>,
+    },
 }
 
 /// A Spin component.
@@ -282,10 +534,16 @@ pub struct Component {
     pub description: String,
     /// Configuration variables available to the component. Names must be
     /// in `lower_snake_case`. Values are strings, and may refer
-    /// to application variables using `{{ ... }}` syntax.
+    /// to application variables using `{{ ... }}` syntax. Values can be
+    /// transformed with filters such as `upper`, `trim_suffix:'/'` or `base64`.
+    /// A backslash before `{{` makes it literal text.
     ///
     /// `variables = { users_endpoint = "https://{{ api_host }}/users"}`
     ///
+    /// `variables = { api_base = "{{ api_url | trim_suffix:'/' }}"}`
+    ///
+    /// `variables = { greeting = '\{{ not_a_variable }}'}`
+    ///
     /// Learn more: https://spinframework.dev/variables#adding-variables-to-your-applications
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub variables: Map<LowerSnakeId, String>,
@@ -322,8 +580,10 @@ pub struct Component {
     /// allows * as a wildcard e.g. "https://\*" (HTTPS on the default port
     /// to any destination) or "\*://localhost:\*" (any protocol to any port on
     /// localhost). The host part allows segment wildcards for subdomains
-    /// e.g. "https://\*.example.com". Application variables are allowed using
-    /// `{{ my_var }}`` syntax.
+    /// e.g. "https://\*.example.com". The host may be a CIDR block e.g.
+    /// "https://10.0.0.0/8", and the port may be a range e.g. "\*://\*.internal:8000-8999".
+    /// With a "\*" scheme and no port, each scheme's default port is allowed.
+    /// Application variables are allowed using `{{ my_var }}`` syntax.
     ///
     /// Example: `allowed_outbound_hosts = ["redis://myredishost.com:6379"]`
     ///
@@ -335,12 +595,14 @@ pub struct Component {
     /// by label e.g. "default" or "customer". Stores other than "default" must be mapped
     /// to a backing store in the runtime config.
     ///
-    /// Example: `key_value_stores = ["default", "my-store"]`
+    /// Labels may contain variable templates, which are resolved at runtime.
+    ///
+    /// Example: `key_value_stores = ["default", "my-store", "{{ tenant }}-cache"]`
     ///
     /// Learn more: https://spinframework.dev/kv-store-api-guide#custom-key-value-stores
     #[serde(
         default,
-        with = "kebab_or_snake_case",
+        with = "kebab_or_snake_case_or_template",
         skip_serializing_if = "Vec::is_empty"
     )]
     #[schemars(with = "Vec<json_schema::KeyValueStore>")]
@@ -369,6 +631,87 @@ pub struct Component {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     #[schemars(with = "Vec<json_schema::AIModel>")]
     pub ai_models: Vec<KebabId>,
+    /// The email addresses to which the component is allowed to send mail.
+    /// An entry may be an address, `*@` followed by a domain to allow any
+    /// address at the domain, or `*` to allow any address.
+    ///
+    /// Example: `allowed_email_recipients = ["ops@example.com", "*@customers.example.com"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_email_recipients: Vec<String>,
+    /// The vector stores which the component is allowed to access. Stores are
+    /// identified by label e.g. "default" or "docs". Stores other than "default"
+    /// must be defined in the runtime config.
+    ///
+    /// Example: `vector_stores = ["default", "docs"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vector_stores: Vec<String>,
+    /// The blob stores which the component is allowed to access. Stores are
+    /// identified by label e.g. "default" or "uploads". Stores other than
+    /// "default" must be defined in the runtime config.
+    ///
+    /// Example: `blob_stores = ["default", "uploads"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blob_stores: Vec<String>,
+    /// The maximum memory which each instance of the component may use. If
+    /// the runtime also sets a limit, the lower of the two applies.
+    ///
+    /// Example: `memory_limit = "128MiB"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<ByteSize>,
+    /// The maximum time for which each instance of the component may run
+    /// before it is interrupted. An interrupted HTTP request receives a 504
+    /// response, and an interrupted message is handled as a failure.
+    ///
+    /// Example: `execution_timeout = "30s"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_timeout: Option<HumanDuration>,
+    /// The maximum number of instances of the component which may exist at
+    /// once. Further requests wait until an instance finishes.
+    ///
+    /// Example: `instance_pool_size = 10`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_pool_size: Option<NonZeroU32>,
+    /// The maximum number of requests which may wait for an instance when
+    /// `instance_pool_size` instances exist. Further requests are refused;
+    /// HTTP requests receive the trigger's `overload_status`. If not set, any
+    /// number of requests may wait.
+    ///
+    /// Example: `instance_pool_queue = 100`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_pool_queue: Option<u32>,
+    /// How long a request may wait for an instance before it is refused. If
+    /// not set, a request may wait indefinitely.
+    ///
+    /// Example: `instance_pool_timeout = "5s"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_pool_timeout: Option<HumanDuration>,
+    /// The number of instances of the component to keep instantiated ahead of
+    /// requests, so that requests need not wait for instantiation. This
+    /// applies to components handling HTTP requests.
+    ///
+    /// Example: `warm_instances = 4`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_instances: Option<NonZeroU32>,
+    /// How long warm instances are kept while the component receives no
+    /// requests. They are then dropped, and made again once the component
+    /// next receives a request. The default is 5 minutes.
+    ///
+    /// Example: `warm_instance_idle_timeout = "10m"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_instance_idle_timeout: Option<HumanDuration>,
+    /// If true, the component exports the `spin:health/health-check`
+    /// interface, and the application is only ready, as reported by the
+    /// trigger's admin listener, while the component's check succeeds.
+    ///
+    /// Example: `health_check = true`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub health_check: bool,
+    /// How long the component's health check may run before it is treated
+    /// as failed. The default is 5 seconds.
+    ///
+    /// Example: `health_check_timeout = "2s"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_timeout: Option<HumanDuration>,
     /// The component build configuration.
     ///
     /// Learn more: https://spinframework.dev/build
@@ -439,6 +782,7 @@ impl ComponentDependencies {
                     let export = match dependency {
                         ComponentDependency::Package { export, .. } => export,
                         ComponentDependency::Local { export, .. } => export,
+                        ComponentDependency::Component { export, .. } => export,
                         _ => continue,
                     };
 
@@ -532,9 +876,7 @@ mod kebab_or_snake_case {
     where
         S: serde::ser::Serializer,
     {
-        if value.iter().all(|s| {
-            KebabId::try_from(s.clone()).is_ok() || SnakeId::try_from(s.to_owned()).is_ok()
-        }) {
+        if value.iter().all(|s| is_valid(s)) {
             value.serialize(serializer)
         } else {
             Err(serde::ser::Error::custom(
@@ -549,9 +891,7 @@ mod kebab_or_snake_case {
     {
         let value = toml::Value::deserialize(deserializer)?;
         let list: Vec<String> = Vec::deserialize(value).map_err(serde::de::Error::custom)?;
-        if list.iter().all(|s| {
-            KebabId::try_from(s.clone()).is_ok() || SnakeId::try_from(s.to_owned()).is_ok()
-        }) {
+        if list.iter().all(|s| is_valid(s)) {
             Ok(list)
         } else {
             Err(serde::de::Error::custom(
@@ -559,20 +899,66 @@ mod kebab_or_snake_case {
             ))
         }
     }
+
+    pub(super) fn is_valid(s: &str) -> bool {
+        KebabId::validate(s).is_ok() || SnakeId::validate(s).is_ok()
+    }
+}
+
+/// As [`kebab_or_snake_case`], but also allows labels containing variable
+/// templates, which are resolved at runtime.
+mod kebab_or_snake_case_or_template {
+    use serde::{Deserialize, Serialize};
+
+    fn is_valid(s: &str) -> bool {
+        s.contains("{{") || super::kebab_or_snake_case::is_valid(s)
+    }
+
+    pub fn serialize<S>(value: &[String], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        if value.iter().all(|s| is_valid(s)) {
+            value.serialize(serializer)
+        } else {
+            Err(serde::ser::Error::custom(
+                "expected kebab-case, snake_case or a variable template",
+            ))
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = toml::Value::deserialize(deserializer)?;
+        let list: Vec<String> = Vec::deserialize(value).map_err(serde::de::Error::custom)?;
+        if list.iter().all(|s| is_valid(s)) {
+            Ok(list)
+        } else {
+            Err(serde::de::Error::custom(
+                "expected kebab-case, snake_case or a variable template",
+            ))
+        }
+    }
 }
 
 impl Component {
     /// Combine `allowed_outbound_hosts` with the deprecated `allowed_http_hosts` into
     /// one array all normalized to the syntax of `allowed_outbound_hosts`.
+    ///
+    /// Use of `allowed_http_hosts` is reported as a
+    /// [`Deprecation`](spin_serde::aliases::Deprecation); see
+    /// [`collect_deprecations`](spin_serde::aliases::collect_deprecations).
     pub fn normalized_allowed_outbound_hosts(&self) -> anyhow::Result<Vec<String>> {
         #[allow(deprecated)]
         let normalized =
             crate::compat::convert_allowed_http_to_allowed_hosts(&self.allowed_http_hosts, false)?;
         if !normalized.is_empty() {
-            terminal::warn!(
-                "Use of the deprecated field `allowed_http_hosts` - to fix, \
-            replace `allowed_http_hosts` with `allowed_outbound_hosts = {normalized:?}`",
-            )
+            spin_serde::aliases::report_deprecation(
+                Deprecation::new("allowed_http_hosts", "allowed_outbound_hosts")
+                    .with_advice(format!("`allowed_outbound_hosts = {normalized:?}`")),
+            );
         }
 
         Ok(self
@@ -733,6 +1119,18 @@ mod tests {
             key_value_stores: labels.clone(),
             sqlite_databases: labels,
             ai_models: vec![],
+            allowed_email_recipients: vec![],
+            vector_stores: vec![],
+            blob_stores: vec![],
+            memory_limit: None,
+            execution_timeout: None,
+            instance_pool_size: None,
+            instance_pool_queue: None,
+            instance_pool_timeout: None,
+            warm_instances: None,
+            warm_instance_idle_timeout: None,
+            health_check: false,
+            health_check_timeout: None,
             build: None,
             tool: Map::new(),
             dependencies_inherit_configuration: false,
@@ -820,6 +1218,73 @@ mod tests {
         }
     }
 
+    #[test]
+    fn apply_environment_overrides() {
+        let mut manifest = AppManifest::deserialize(toml! {
+            spin_manifest_version = 2
+            [application]
+            name = "environments"
+            [variables]
+            api_url = { required = true }
+            [[trigger.fake]]
+            component = "api"
+            [component.api]
+            source = "api.wasm"
+            allowed_outbound_hosts = ["http://localhost:3000"]
+            build = { command = "cargo build", workdir = "api" }
+            [profiles.prod.variables]
+            api_url = { default = "https://api.example.com" }
+            [environments.prod.component.api]
+            build_command = "cargo build --release"
+            allowed_outbound_hosts = ["https://api.example.com"]
+        })
+        .unwrap();
+
+        manifest.apply_environment("prod").unwrap();
+        assert!(manifest.environments.is_empty());
+        // Variables are left to the profile, which is selected at startup
+        assert!(manifest.variables[0].required);
+        assert_eq!(1, manifest.profiles.len());
+
+        let component = &manifest.components[0];
+        assert_eq!(
+            vec!["https://api.example.com"],
+            component.allowed_outbound_hosts
+        );
+        let build = component.build.as_ref().unwrap();
+        assert_eq!(
+            vec!["cargo build --release"],
+            build.commands().collect::<Vec<_>>()
+        );
+        assert_eq!(Some("api"), build.workdir.as_deref());
+    }
+
+    #[test]
+    fn apply_unknown_environment_fails() {
+        let mut manifest = AppManifest::deserialize(toml! {
+            spin_manifest_version = 2
+            [application]
+            name = "environments"
+            [[trigger.fake]]
+            component = { source = "inline.wasm" }
+            [environments.staging.component.missing]
+            build_command = "make"
+            [profiles.dev.variables]
+            log_level = { default = "debug" }
+        })
+        .unwrap();
+
+        let err = manifest.clone().apply_environment("prod").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("environment or profile named \"prod\""),
+            "{err}"
+        );
+        manifest.clone().apply_environment("dev").unwrap();
+        let err = manifest.apply_environment("staging").unwrap_err();
+        assert!(err.to_string().contains("unknown component"), "{err}");
+    }
+
     #[test]
     fn test_validate_dependencies() {
         // Specifying a dependency name as a plain-name without a package is an error
@@ -929,4 +1394,72 @@ mod tests {
         .validate()
         .is_err());
     }
+
+    #[test]
+    fn build_profiles_override_component_build() {
+        let build = ComponentBuildConfig::deserialize(toml! {
+            command = ["cargo build --features '{{ features }}'", "wasm-opt"]
+            environment = { CARGO_TARGET_DIR = "target" }
+            features = ["metrics"]
+            watch = ["src/**/*.rs"]
+            [profiles.release]
+            command = "cargo build --release --features '{{ features }}'"
+            environment = { RUSTFLAGS = "-C opt-level=3" }
+            features = ["metrics", "lto"]
+        })
+        .unwrap();
+
+        let dev = build.with_profile(None);
+        assert_eq!(
+            vec!["cargo build --features 'metrics'", "wasm-opt"],
+            dev.commands().collect::<Vec<_>>()
+        );
+        assert!(dev.profiles.is_empty());
+
+        let release = build.with_profile(Some("release"));
+        assert_eq!(
+            vec!["cargo build --release --features 'metrics,lto'"],
+            release.commands().collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["src/**/*.rs"], release.watch);
+        assert_eq!(2, release.environment.len());
+
+        // A profile the component doesn't define leaves its build unchanged
+        let other = build.with_profile(Some("other"));
+        assert_eq!(2, other.commands().len());
+    }
+
+    #[test]
+    fn test_validate_http_routes() {
+        let manifest = |second_route: &str| {
+            AppManifest::deserialize(toml! {
+                spin_manifest_version = 2
+                [application]
+                name = "routes"
+                [[trigger.http]]
+                route = "/users/:id/..."
+                component = { source = "a.wasm" }
+                [[trigger.http]]
+                route = second_route
+                component = { source = "b.wasm" }
+            })
+            .unwrap()
+        };
+
+        // Exact duplicates are left to the router
+        manifest("/users/:id/...").validate_http_routes().unwrap();
+        manifest("/users/:id/orders")
+            .validate_http_routes()
+            .unwrap();
+
+        let err = manifest("/users/:name/...")
+            .validate_http_routes()
+            .unwrap_err();
+        assert!(err.to_string().contains("conflicts"), "{err}");
+
+        let err = manifest("/users/.../orders")
+            .validate_http_routes()
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid route"), "{err}");
+    }
 }
@@ -0,0 +1,345 @@
+//! The draft V3 manifest schema.
+//!
+//! This format is unstable: it may change in any release, and Spin does not
+//! yet load V3 manifests. Compared to V2:
+//! - Component permissions are grouped under `capabilities`.
+//! - Component imports always name the package which satisfies them.
+//! - Environments can overlay more component settings, including any
+//!   capability.
+
+use serde::{Deserialize, Serialize};
+pub use spin_serde::{ByteSize, HumanDuration, KebabId, SnakeId};
+use spin_serde::{DependencyName, FixedVersion, LowerSnakeId};
+use std::{num::NonZeroU32, path::PathBuf};
+
+pub use super::common::{
+    Commands, ComponentBuildConfig, ComponentSource, SymlinkPolicy, Variable, VariableType,
+    WasiFilesMount,
+};
+pub use super::v2::{AppDetails, Profile, Trigger};
+
+pub(crate) type Map<K, V> = indexmap::IndexMap<K, V>;
+
+/// App manifest
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppManifest {
+    /// `spin_manifest_version = 3`
+    pub spin_manifest_version: FixedVersion<3>,
+    /// `[application]`
+    pub application: AppDetails,
+    /// Application configuration variables.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub variables: Map<LowerSnakeId, Variable>,
+    /// Configuration profiles, such as `dev` or `prod`.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub profiles: Map<KebabId, Profile>,
+    /// Target environments, such as `staging` or `prod`, each of which can
+    /// overlay component settings. Variables for an environment are declared
+    /// in the profile of the same name.
+    ///
+    /// Example: `[environments.prod.component.api.capabilities]`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub environments: Map<KebabId, Environment>,
+    /// The triggers to which the application responds.
+    #[serde(rename = "trigger")]
+    pub triggers: Map<String, Vec<Trigger>>,
+    /// `[component.<id>]`
+    #[serde(rename = "component")]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub components: Map<KebabId, Component>,
+}
+
+/// A Spin component.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Component {
+    /// The file, package, or URL containing the component Wasm binary.
+    pub source: ComponentSource,
+    /// A human-readable description of the component.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Configuration variables available to the component.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub variables: Map<LowerSnakeId, String>,
+    /// Environment variables to be set for the Wasm module.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub environment: Map<String, String>,
+    /// The files the component is allowed to read.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<WasiFilesMount>,
+    /// Files or glob patterns which are not available to the component,
+    /// even though they match a `files` entry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_files: Vec<String>,
+    /// The resources which the component is allowed to access.
+    ///
+    /// Example: `capabilities = { outbound_hosts = ["https://example.com"], key_value_stores = ["default"] }`
+    #[serde(default, skip_serializing_if = "Capabilities::is_empty")]
+    pub capabilities: Capabilities,
+    /// The maximum memory which each instance of the component may use.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<ByteSize>,
+    /// The maximum time for which each instance of the component may run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_timeout: Option<HumanDuration>,
+    /// The maximum number of instances of the component which may exist at once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_pool_size: Option<NonZeroU32>,
+    /// The maximum number of requests which may wait for an instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_pool_queue: Option<u32>,
+    /// How long a request may wait for an instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_pool_timeout: Option<HumanDuration>,
+    /// The number of instances of the component to keep ready ahead of requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_instances: Option<NonZeroU32>,
+    /// How long warm instances are kept while the component is idle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_instance_idle_timeout: Option<HumanDuration>,
+    /// If true, the component exports a health check which readiness depends on.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub health_check: bool,
+    /// How long the component's health check may run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_timeout: Option<HumanDuration>,
+    /// The component build configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<ComponentBuildConfig>,
+    /// Settings for custom tools or plugins. Spin ignores this field.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub tool: Map<String, toml::Table>,
+    /// How to satisfy Wasm Component Model imports of this component.
+    ///
+    /// Example: `"wasi:keyvalue/store" = { package = "example:kv", version = "0.1.0" }`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub imports: Map<DependencyName, Import>,
+    /// If true, the components which satisfy imports have the same
+    /// capabilities as this component; otherwise they have none.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub imports_inherit_capabilities: bool,
+}
+
+/// The resources which a component is allowed to access.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Capabilities {
+    /// The network destinations which the component is allowed to access,
+    /// in the form "(scheme)://(host)[:port]".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outbound_hosts: Vec<String>,
+    /// The labels of the key-value stores which the component is allowed to
+    /// access.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_value_stores: Vec<String>,
+    /// The labels of the SQLite databases which the component is allowed to
+    /// access.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sqlite_databases: Vec<String>,
+    /// The AI models which the component is allowed to access.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ai_models: Vec<KebabId>,
+    /// The email addresses to which the component is allowed to send mail.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub email_recipients: Vec<String>,
+    /// The labels of the vector stores which the component is allowed to
+    /// access.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vector_stores: Vec<String>,
+    /// The labels of the blob stores which the component is allowed to
+    /// access.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blob_stores: Vec<String>,
+}
+
+impl Capabilities {
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A package which satisfies a component import. Unlike V2 dependencies,
+/// registry imports always name their package.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum Import {
+    /// `... = { package = "example:kv", version = "0.1.0" }`
+    Package {
+        /// The name of the package to use.
+        package: String,
+        /// A semantic versioning constraint for the package version.
+        version: String,
+        /// The registry that hosts the package, if not the default registry.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        registry: Option<String>,
+        /// The name of the export in the package, if not the import name.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        export: Option<String>,
+    },
+    /// `... = { path = "path/to/component.wasm" }`
+    Local {
+        /// The path to the Wasm file that implements the import.
+        path: PathBuf,
+        /// The name of the export in the package, if not the import name.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        export: Option<String>,
+    },
+    /// `... = { url = "https://example.com/component.wasm", digest = "sha256:..." }`
+    Http {
+        /// The URL of the Wasm component that implements the import.
+        url: String,
+        /// The SHA256 digest of the Wasm file, beginning with `sha256:`.
+        digest: String,
+        /// The name of the export in the package, if not the import name.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        export: Option<String>,
+    },
+    /// `... = { component = "component-id" }`
+    Component {
+        /// The ID of the application component that implements the import.
+        component: KebabId,
+        /// The name of the export in the component, if not the import name.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        export: Option<String>,
+    },
+}
+
+/// A target environment
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Environment {
+    /// Settings which overlay those of the component of the same ID in this
+    /// environment.
+    #[serde(rename = "component")]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub components: Map<KebabId, EnvironmentComponent>,
+}
+
+/// Component settings for a target environment. Each setting which is
+/// present replaces the component's own.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentComponent {
+    /// The command or commands to build the component in this environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<Commands>,
+    /// Component variables which replace those of the same name.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub variables: Map<LowerSnakeId, String>,
+    /// Environment variables which replace those of the same name.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub environment: Map<String, String>,
+    /// Capabilities which replace the component's.
+    #[serde(default, skip_serializing_if = "CapabilitiesOverlay::is_empty")]
+    pub capabilities: CapabilitiesOverlay,
+}
+
+/// Capabilities for a target environment. Each capability which is present
+/// replaces the component's own.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapabilitiesOverlay {
+    /// Replaces [`Capabilities::outbound_hosts`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbound_hosts: Option<Vec<String>>,
+    /// Replaces [`Capabilities::key_value_stores`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_value_stores: Option<Vec<String>>,
+    /// Replaces [`Capabilities::sqlite_databases`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sqlite_databases: Option<Vec<String>>,
+    /// Replaces [`Capabilities::ai_models`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_models: Option<Vec<KebabId>>,
+    /// Replaces [`Capabilities::email_recipients`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_recipients: Option<Vec<String>>,
+    /// Replaces [`Capabilities::vector_stores`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_stores: Option<Vec<String>>,
+    /// Replaces [`Capabilities::blob_stores`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_stores: Option<Vec<String>>,
+}
+
+impl CapabilitiesOverlay {
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_maximal_v2_manifest() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/ui/maximal.toml");
+        let v2 = crate::manifest_from_file(path).unwrap();
+        let v3 = crate::compat::v2_to_v3_app(v2.clone()).unwrap();
+
+        let (id, v2_component) = v2.components.first().unwrap();
+        let v3_component = &v3.components[id];
+        assert_eq!(
+            v2_component.normalized_allowed_outbound_hosts().unwrap(),
+            v3_component.capabilities.outbound_hosts
+        );
+        assert_eq!(
+            v2_component.key_value_stores,
+            v3_component.capabilities.key_value_stores
+        );
+        assert_eq!(
+            v2_component.dependencies.inner.len(),
+            v3_component.imports.len()
+        );
+        assert!(v3_component.imports.values().all(|import| match import {
+            Import::Package { package, .. } => !package.is_empty(),
+            _ => true,
+        }));
+
+        // The upgraded manifest round-trips through TOML
+        let toml = toml::to_string(&v3).unwrap();
+        let reparsed: AppManifest = toml::from_str(&toml).unwrap();
+        assert_eq!(toml, toml::to_string(&reparsed).unwrap());
+    }
+
+    #[test]
+    fn parses_capabilities_and_overlays() {
+        let manifest: AppManifest = toml::from_str(
+            r#"
+            spin_manifest_version = 3
+            [application]
+            name = "app"
+            [[trigger.http]]
+            route = "/..."
+            component = "api"
+            [component.api]
+            source = "api.wasm"
+            capabilities = { outbound_hosts = ["https://example.com"], key_value_stores = ["default"] }
+            [component.api.imports]
+            "example:cache/store" = { package = "example:redis-cache", version = "0.1.0" }
+            [environments.prod.component.api.capabilities]
+            key_value_stores = ["prod"]
+            "#,
+        )
+        .unwrap();
+        let api = &manifest.components[0];
+        assert_eq!(vec!["default"], api.capabilities.key_value_stores);
+        let overlay = &manifest.environments[0].components[0].capabilities;
+        assert_eq!(Some(vec!["prod".to_owned()]), overlay.key_value_stores);
+        assert_eq!(None, overlay.outbound_hosts);
+
+        assert!(toml::from_str::<AppManifest>(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "app"
+            [[trigger.http]]
+            route = "/..."
+            component = "api"
+            "#,
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,1062 @@
+//! Manifest validation beyond what deserialization checks.
+
+use std::{collections::HashSet, fmt::Display, ops::Range, path::Path};
+
+use schemars::schema::{Schema, SchemaObject, SingleOrVec};
+
+use spin_http_routes::RoutePattern;
+use spin_serde::DependencyName;
+
+use crate::{
+    normalize::chain_handler_import,
+    schema::v2::{
+        AppManifest, ComponentDependency, ComponentSpec, KebabId, TriggerComponents, TriggerMode,
+        WasiFilesMount,
+    },
+    Error, ManifestVersion,
+};
+
+/// A problem found by [`validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// A description of the problem.
+    pub message: String,
+    /// The path of the offending key, e.g. `["trigger", "http", "0", "component"]`.
+    pub key: Vec<String>,
+    /// Where the offending key is in the manifest file, if known.
+    pub location: Option<Location>,
+}
+
+/// The seriousness of a [`Diagnostic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The manifest may not work as intended.
+    Warning,
+    /// The manifest cannot be loaded.
+    Error,
+}
+
+/// A position in a manifest file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// The 1-based line number.
+    pub line: usize,
+    /// The 1-based column number, in characters.
+    pub column: usize,
+    /// The byte range of the offending item.
+    pub span: Range<usize>,
+}
+
+impl Diagnostic {
+    fn error(key: Vec<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            key,
+            location: None,
+        }
+    }
+
+    fn warning(key: Vec<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(key, message)
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{}:{}: ", location.line, location.column)?;
+        }
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}: {}", self.message)?;
+        if !self.key.is_empty() {
+            write!(f, " (at `{}`)", self.key.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks cross-references within the manifest, returning every problem
+/// found rather than stopping at the first:
+/// - trigger components refer to defined components
+/// - chained triggers list their components, and support chaining
+/// - component dependencies on other components refer to defined components,
+///   and do not form a cycle
+/// - components only set an instance pool queue or timeout along with an
+///   instance pool size, a non-zero warm instance idle timeout along with a
+///   number of warm instances, and a non-zero health check timeout along
+///   with a health check
+/// - allowed email recipients are addresses, `*@` followed by a domain, or
+///   `*`
+/// - variable templates (in component variables, allowed outbound hosts,
+///   key-value store labels and trigger configs) refer to declared
+///   application variables, and are not used in file mount destinations
+/// - HTTP routes are well-formed, and neither duplicated nor in conflict
+///   across triggers
+/// - environments refer to defined components
+///
+/// Other trigger settings are checked by each trigger type when the app
+/// starts, as it deserializes them into its own config.
+///
+/// Diagnostics from this function have no [`Location`]; use
+/// [`validate_file`] to get locations.
+pub fn validate(manifest: &AppManifest) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    validate_trigger_components(manifest, &mut diagnostics);
+    validate_chains(manifest, &mut diagnostics);
+    validate_component_dependencies(manifest, &mut diagnostics);
+    validate_instance_pools(manifest, &mut diagnostics);
+    validate_email_recipients(manifest, &mut diagnostics);
+    validate_templates(manifest, &mut diagnostics);
+    validate_file_destinations(manifest, &mut diagnostics);
+    validate_routes(manifest, true, &mut diagnostics);
+    validate_environments(manifest, &mut diagnostics);
+    diagnostics
+}
+
+/// Validates the manifest file at the given path, as [`validate`] and
+/// [`validate_fields`], and also checks that local dependency files exist.
+/// Diagnostics are located in the manifest file where possible, and sorted
+/// by location.
+pub fn validate_file(path: impl AsRef<Path>) -> Result<Vec<Diagnostic>, Error> {
+    let path = path.as_ref();
+    let mut diagnostics = match crate::v2_table_from_file(path)? {
+        Some(manifest) => validate_fields(&manifest),
+        None => vec![],
+    };
+
+    let manifest = crate::manifest_from_file(path)?;
+    validate_with_files(path, &manifest, &mut diagnostics);
+
+    locate_all(path, &mut diagnostics)?;
+    Ok(diagnostics)
+}
+
+/// Validates a manifest already parsed from the file at the given path, as
+/// [`validate`], and also checks that local dependency files exist.
+/// Diagnostics are located in the manifest file where possible, and sorted
+/// by location.
+///
+/// Unlike [`validate_file`], this does not check fields; use
+/// [`validate_file_fields`] for that. Like [`validate_file_fields`], it does
+/// not check V1 manifests, whose keys do not match their converted form.
+pub fn validate_parsed_file(
+    path: impl AsRef<Path>,
+    manifest: &AppManifest,
+) -> Result<Vec<Diagnostic>, Error> {
+    let path = path.as_ref();
+    if ManifestVersion::detect(&std::fs::read_to_string(path)?)? != ManifestVersion::V2 {
+        return Ok(vec![]);
+    }
+    let mut diagnostics = vec![];
+    validate_with_files(path, manifest, &mut diagnostics);
+    locate_all(path, &mut diagnostics)?;
+    Ok(diagnostics)
+}
+
+// Validates the manifest, and checks the files it refers to relative to its
+// path.
+fn validate_with_files(path: &Path, manifest: &AppManifest, diagnostics: &mut Vec<Diagnostic>) {
+    diagnostics.extend(validate(manifest));
+    let app_root = path.parent().unwrap_or(Path::new("."));
+    validate_dependency_paths(manifest, app_root, diagnostics);
+}
+
+/// Checks the fields of the manifest file at the given path, as
+/// [`validate_fields`]. Diagnostics are located in the manifest file where
+/// possible, and sorted by location. V1 manifests are not checked.
+///
+/// Unlike [`validate_file`], this does not need the manifest to be valid,
+/// so its diagnostics can explain why a manifest fails to parse.
+pub fn validate_file_fields(path: impl AsRef<Path>) -> Result<Vec<Diagnostic>, Error> {
+    let path = path.as_ref();
+    let Some(manifest) = crate::v2_table_from_file(path)? else {
+        return Ok(vec![]);
+    };
+    let mut diagnostics = validate_fields(&manifest);
+    locate_all(path, &mut diagnostics)?;
+    Ok(diagnostics)
+}
+
+// Locates the diagnostics in the manifest file, and sorts them by location.
+fn locate_all(path: &Path, diagnostics: &mut [Diagnostic]) -> Result<(), Error> {
+    let contents = std::fs::read_to_string(path)?;
+    let document = toml_edit::ImDocument::parse(contents.as_str())
+        .map_err(|e| Error::ValidationError(e.into()))?;
+    for diagnostic in diagnostics.iter_mut() {
+        diagnostic.location = locate(&document, &contents, &diagnostic.key);
+    }
+    diagnostics.sort_by_key(|d| d.location.as_ref().map(|l| l.span.start));
+    Ok(())
+}
+
+/// Checks the fields of a V2 manifest, before it is parsed, against the
+/// manifest's JSON schema (see [`crate::json_schema`]). Returns a warning
+/// for each deprecated field, and for each field which Spin does not
+/// recognise, suggesting the closest known field.
+///
+/// Many unknown fields also fail parsing, but not trigger settings, which
+/// would otherwise be silently ignored. Fields of tables which Spin does not
+/// interpret, such as `tool` settings or the settings of trigger types other
+/// than `http`, `redis`, `cron`, `kafka`, `nats`, `amqp`, `grpc`, `queue`,
+/// `job` and `tcp`, are not checked.
+pub fn validate_fields(manifest: &toml::Table) -> Vec<Diagnostic> {
+    let schema = crate::json_schema::app_manifest_schema();
+    let mut checker = KeyChecker {
+        definitions: &schema.definitions,
+        diagnostics: vec![],
+    };
+    checker.check_table(manifest, &schema.schema, &mut vec![]);
+    checker.diagnostics
+}
+
+struct KeyChecker<'a> {
+    definitions: &'a schemars::Map<String, Schema>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> KeyChecker<'a> {
+    // Follows references, including those wrapped in an `allOf` to carry a
+    // description, to the schema they refer to.
+    fn resolve(&self, schema: &'a Schema) -> Option<&'a SchemaObject> {
+        let Schema::Object(object) = schema else {
+            return None;
+        };
+        if let Some(reference) = &object.reference {
+            let name = reference.trim_start_matches("#/definitions/");
+            return self.resolve(self.definitions.get(name)?);
+        }
+        match object
+            .subschemas
+            .as_deref()
+            .and_then(|s| s.all_of.as_deref())
+        {
+            Some([schema]) => self.resolve(schema),
+            _ => Some(object),
+        }
+    }
+
+    // Returns the alternatives of an untagged enum, or else the schema itself.
+    fn variants(&self, object: &'a SchemaObject) -> Vec<&'a SchemaObject> {
+        let alternatives = object
+            .subschemas
+            .as_deref()
+            .and_then(|s| s.any_of.as_ref().or(s.one_of.as_ref()));
+        match alternatives {
+            Some(alternatives) => alternatives
+                .iter()
+                .filter_map(|schema| self.resolve(schema))
+                .flat_map(|object| self.variants(object))
+                .collect(),
+            None => vec![object],
+        }
+    }
+
+    fn check(&mut self, value: &toml::Value, object: &'a SchemaObject, key: &mut Vec<String>) {
+        match value {
+            toml::Value::Table(table) => self.check_table(table, object, key),
+            toml::Value::Array(items) => {
+                let item_schema =
+                    self.variants(object).into_iter().find_map(|variant| {
+                        match variant.array.as_deref()?.items.as_ref()? {
+                            SingleOrVec::Single(item) => self.resolve(item),
+                            SingleOrVec::Vec(_) => None,
+                        }
+                    });
+                let Some(item_schema) = item_schema else {
+                    return;
+                };
+                for (index, item) in items.iter().enumerate() {
+                    key.push(index.to_string());
+                    self.check(item, item_schema, key);
+                    key.pop();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn check_table(
+        &mut self,
+        table: &toml::Table,
+        object: &'a SchemaObject,
+        key: &mut Vec<String>,
+    ) {
+        let objects = self
+            .variants(object)
+            .into_iter()
+            .filter_map(|variant| variant.object.as_deref())
+            .collect::<Vec<_>>();
+        if objects.is_empty() {
+            return;
+        }
+        for (name, value) in table {
+            key.push(name.clone());
+            if let Some(property) = objects.iter().find_map(|o| o.properties.get(name)) {
+                if is_deprecated(property) {
+                    self.diagnostics.push(Diagnostic::warning(
+                        key.clone(),
+                        deprecation_message(name, property),
+                    ));
+                }
+                if let Some(property) = self.resolve(property) {
+                    self.check(value, property, key);
+                }
+            } else {
+                // Fields which are not properties are checked against the
+                // schema of additional properties, unless any is open.
+                let additional = objects
+                    .iter()
+                    .map(|o| o.additional_properties.as_deref())
+                    .collect::<Vec<_>>();
+                if additional
+                    .iter()
+                    .any(|a| matches!(a, None | Some(Schema::Bool(true))))
+                {
+                    key.pop();
+                    continue;
+                }
+                match additional
+                    .into_iter()
+                    .flatten()
+                    .find_map(|a| self.resolve(a))
+                {
+                    Some(additional) => self.check(value, additional, key),
+                    None => {
+                        let known = objects
+                            .iter()
+                            .flat_map(|o| &o.properties)
+                            .filter(|(_, property)| !is_deprecated(property))
+                            .map(|(name, _)| name.as_str());
+                        let message = match closest(name, known) {
+                            Some(suggestion) => {
+                                format!("unknown field `{name}`; did you mean `{suggestion}`?")
+                            }
+                            None => format!("unknown field `{name}`"),
+                        };
+                        self.diagnostics
+                            .push(Diagnostic::warning(key.clone(), message));
+                    }
+                }
+            }
+            key.pop();
+        }
+    }
+}
+
+fn is_deprecated(schema: &Schema) -> bool {
+    matches!(schema, Schema::Object(object) if object.metadata.as_ref().is_some_and(|m| m.deprecated))
+}
+
+// Uses the field's documentation, if it says what to do instead, e.g.
+// "Deprecated. Use `allowed_outbound_hosts` instead."
+fn deprecation_message(name: &str, schema: &Schema) -> String {
+    let advice = match schema {
+        Schema::Object(object) => object
+            .metadata
+            .as_ref()
+            .and_then(|m| m.description.as_deref())
+            .and_then(|d| d.lines().next()?.strip_prefix("Deprecated."))
+            .map(str::trim)
+            .filter(|advice| !advice.is_empty()),
+        Schema::Bool(_) => None,
+    };
+    match advice {
+        Some(advice) => format!("`{name}` is deprecated. {advice}"),
+        None => format!("`{name}` is deprecated"),
+    }
+}
+
+// Returns the known field closest to the given unknown one, if any is close
+// enough to be a plausible misspelling or abbreviation.
+fn closest<'k>(name: &str, known: impl Iterator<Item = &'k str>) -> Option<&'k str> {
+    known
+        .map(|candidate| (levenshtein::levenshtein(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= name.len().max(candidate.len()) / 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn validate_trigger_components(manifest: &AppManifest, diagnostics: &mut Vec<Diagnostic>) {
+    for (trigger_type, triggers) in &manifest.triggers {
+        for (index, trigger) in triggers.iter().enumerate() {
+            let trigger_key = |field: &str| {
+                vec![
+                    "trigger".to_owned(),
+                    trigger_type.clone(),
+                    index.to_string(),
+                    field.to_owned(),
+                ]
+            };
+            if let Some(ComponentSpec::Reference(id)) = &trigger.component {
+                if !manifest.components.contains_key(id) {
+                    diagnostics.push(Diagnostic::error(
+                        trigger_key("component"),
+                        format!("trigger refers to undefined component {:?}", id.as_ref()),
+                    ));
+                }
+            }
+            let specs: Vec<(String, &ComponentSpec)> = match &trigger.components {
+                TriggerComponents::Roles(roles) => roles
+                    .iter()
+                    .flat_map(|(role, specs)| specs.0.iter().map(|spec| (role.clone(), spec)))
+                    .collect(),
+                TriggerComponents::Chain(chain) => chain
+                    .iter()
+                    .enumerate()
+                    .map(|(position, spec)| (position.to_string(), spec))
+                    .collect(),
+            };
+            for (item, spec) in specs {
+                if let ComponentSpec::Reference(id) = spec {
+                    if !manifest.components.contains_key(id) {
+                        let mut key = trigger_key("components");
+                        key.push(item);
+                        diagnostics.push(Diagnostic::error(
+                            key,
+                            format!("trigger refers to undefined component {:?}", id.as_ref()),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Checks that triggers with `mode = "chain"` list at least two components
+/// instead of setting `component`, that only they list components, and that
+/// their trigger type supports chaining.
+pub(crate) fn validate_chains(manifest: &AppManifest, diagnostics: &mut Vec<Diagnostic>) {
+    for (trigger_type, triggers) in &manifest.triggers {
+        for (index, trigger) in triggers.iter().enumerate() {
+            let trigger_key = |field: &str| {
+                vec![
+                    "trigger".to_owned(),
+                    trigger_type.clone(),
+                    index.to_string(),
+                    field.to_owned(),
+                ]
+            };
+            let chain = match &trigger.components {
+                TriggerComponents::Chain(chain) => Some(chain),
+                TriggerComponents::Roles(_) => None,
+            };
+            match (trigger.mode, chain) {
+                (None, None) => continue,
+                (None, Some(_)) => {
+                    diagnostics.push(Diagnostic::error(
+                        trigger_key("components"),
+                        "a list of components requires `mode = \"chain\"`",
+                    ));
+                }
+                (Some(TriggerMode::Chain), None) => {
+                    diagnostics.push(Diagnostic::error(
+                        trigger_key("mode"),
+                        "a chained trigger must list its components, e.g. \
+                         `components = [\"auth\", \"api\"]`",
+                    ));
+                }
+                (Some(TriggerMode::Chain), Some(chain)) => {
+                    if chain.len() < 2 {
+                        diagnostics.push(Diagnostic::error(
+                            trigger_key("components"),
+                            "a chained trigger must list at least two components",
+                        ));
+                    }
+                    if trigger.component.is_some() {
+                        diagnostics.push(Diagnostic::error(
+                            trigger_key("component"),
+                            "a chained trigger is handled by the first of its `components`, \
+                             so must not set `component`",
+                        ));
+                    }
+                    let Some(handler) = chain_handler_import(trigger_type) else {
+                        diagnostics.push(Diagnostic::error(
+                            trigger_key("mode"),
+                            format!("{trigger_type:?} triggers do not support chaining"),
+                        ));
+                        continue;
+                    };
+                    // Each component but the last gets a dependency on the
+                    // handler, which must not clash with its own.
+                    let wrappers = chain.iter().take(chain.len().saturating_sub(1));
+                    for (position, spec) in wrappers.enumerate() {
+                        let ComponentSpec::Reference(id) = spec else {
+                            continue;
+                        };
+                        let Some(component) = manifest.components.get(id) else {
+                            continue;
+                        };
+                        let clashes = component.dependencies.inner.keys().any(|name| {
+                            match (name, &handler) {
+                                (
+                                    DependencyName::Package(name),
+                                    DependencyName::Package(handler),
+                                ) => name.package == handler.package,
+                                _ => false,
+                            }
+                        });
+                        if clashes {
+                            let mut key = trigger_key("components");
+                            key.push(position.to_string());
+                            diagnostics.push(Diagnostic::error(
+                                key,
+                                format!(
+                                    "component {:?} cannot wrap the next component of the chain, \
+                                     because it already has a dependency on `{handler}`",
+                                    id.as_ref()
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Checks that component dependencies on other components of the app refer
+/// to defined components, and that no component depends on itself, either
+/// directly or through other components.
+pub(crate) fn validate_component_dependencies(
+    manifest: &AppManifest,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let dependency_components = |id: &KebabId| -> Vec<&KebabId> {
+        manifest
+            .components
+            .get(id)
+            .into_iter()
+            .flat_map(|component| component.dependencies.inner.values())
+            .filter_map(|dependency| match dependency {
+                ComponentDependency::Component { component, .. } => Some(component),
+                _ => None,
+            })
+            .collect()
+    };
+
+    for (id, component) in &manifest.components {
+        for (name, dependency) in &component.dependencies.inner {
+            let ComponentDependency::Component {
+                component: dependency_id,
+                ..
+            } = dependency
+            else {
+                continue;
+            };
+            let key = vec![
+                "component".to_owned(),
+                id.to_string(),
+                "dependencies".to_owned(),
+                name.to_string(),
+            ];
+            if !manifest.components.contains_key(dependency_id) {
+                diagnostics.push(Diagnostic::error(
+                    key,
+                    format!(
+                        "dependency refers to undefined component {:?}",
+                        dependency_id.as_ref()
+                    ),
+                ));
+                continue;
+            }
+            // Search the components which this dependency depends on.
+            let mut visited = HashSet::new();
+            let mut pending = vec![dependency_id];
+            while let Some(next) = pending.pop() {
+                if next == id {
+                    diagnostics.push(Diagnostic::error(
+                        key,
+                        format!(
+                            "component {:?} depends on itself through component {:?}",
+                            id.as_ref(),
+                            dependency_id.as_ref()
+                        ),
+                    ));
+                    break;
+                }
+                if visited.insert(next) {
+                    pending.extend(dependency_components(next));
+                }
+            }
+        }
+    }
+}
+
+/// Checks that components which set an instance pool queue or timeout also
+/// set an instance pool size, that components which set a warm instance
+/// idle timeout also set a number of warm instances, and that components
+/// which set a health check timeout also have a health check, without which
+/// they have no effect.
+fn validate_instance_pools(manifest: &AppManifest, diagnostics: &mut Vec<Diagnostic>) {
+    for (id, component) in &manifest.components {
+        let key = |field: &str| vec!["component".to_owned(), id.to_string(), field.to_owned()];
+        let fields = [
+            (
+                "instance_pool_queue",
+                component.instance_pool_queue.is_some(),
+                "instance_pool_size",
+                component.instance_pool_size.is_some(),
+            ),
+            (
+                "instance_pool_timeout",
+                component.instance_pool_timeout.is_some(),
+                "instance_pool_size",
+                component.instance_pool_size.is_some(),
+            ),
+            (
+                "warm_instance_idle_timeout",
+                component.warm_instance_idle_timeout.is_some(),
+                "warm_instances",
+                component.warm_instances.is_some(),
+            ),
+            (
+                "health_check_timeout",
+                component.health_check_timeout.is_some(),
+                "health_check",
+                component.health_check,
+            ),
+        ];
+        for (field, set, needed, needed_set) in fields {
+            if set && !needed_set {
+                diagnostics.push(Diagnostic::warning(
+                    key(field),
+                    format!("`{field}` has no effect without `{needed}`"),
+                ));
+            }
+        }
+        let timeouts = [
+            (
+                "warm_instance_idle_timeout",
+                &component.warm_instance_idle_timeout,
+            ),
+            ("health_check_timeout", &component.health_check_timeout),
+        ];
+        for (field, timeout) in timeouts {
+            if timeout
+                .as_ref()
+                .is_some_and(|timeout| timeout.duration().is_zero())
+            {
+                diagnostics.push(Diagnostic::error(
+                    key(field),
+                    format!("{field} must be greater than zero"),
+                ));
+            }
+        }
+    }
+}
+
+fn validate_email_recipients(manifest: &AppManifest, diagnostics: &mut Vec<Diagnostic>) {
+    for (id, component) in &manifest.components {
+        for (index, pattern) in component.allowed_email_recipients.iter().enumerate() {
+            if !is_email_recipient_pattern(pattern) {
+                diagnostics.push(Diagnostic::error(
+                    vec![
+                        "component".to_owned(),
+                        id.to_string(),
+                        "allowed_email_recipients".to_owned(),
+                        index.to_string(),
+                    ],
+                    format!(
+                        "{pattern:?} is not an email address, `*@` followed by a domain, or `*`"
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn is_email_recipient_pattern(pattern: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    let Some((local, domain)) = pattern.rsplit_once('@') else {
+        return false;
+    };
+    let is_part = |part: &str| {
+        !part.is_empty()
+            && !part.contains(|c: char| {
+                c.is_whitespace() || c.is_control() || matches!(c, '@' | '<' | '>' | ',' | '*')
+            })
+    };
+    (local == "*" || is_part(local)) && is_part(domain)
+}
+
+fn validate_templates(manifest: &AppManifest, diagnostics: &mut Vec<Diagnostic>) {
+    let declared: HashSet<&str> = manifest.variables.keys().map(|k| k.as_ref()).collect();
+    let mut check = |key: Vec<String>, template: &str| {
+        for name in template_references(template) {
+            if !declared.contains(name) {
+                diagnostics.push(Diagnostic::error(
+                    key.clone(),
+                    format!("template refers to undeclared variable {name:?}"),
+                ));
+            }
+        }
+    };
+    for (id, component) in &manifest.components {
+        let component_key =
+            |field: &str| vec!["component".to_owned(), id.to_string(), field.to_owned()];
+        for (name, template) in &component.variables {
+            let mut key = component_key("variables");
+            key.push(name.to_string());
+            check(key, template);
+        }
+        for (index, host) in component.allowed_outbound_hosts.iter().enumerate() {
+            let mut key = component_key("allowed_outbound_hosts");
+            key.push(index.to_string());
+            check(key, host);
+        }
+        for (index, label) in component.key_value_stores.iter().enumerate() {
+            let mut key = component_key("key_value_stores");
+            key.push(index.to_string());
+            check(key, label);
+        }
+    }
+    for (trigger_type, triggers) in &manifest.triggers {
+        for (index, trigger) in triggers.iter().enumerate() {
+            for (field, value) in &trigger.config {
+                let key = vec![
+                    "trigger".to_owned(),
+                    trigger_type.clone(),
+                    index.to_string(),
+                    field.clone(),
+                ];
+                for template in toml_strings(value) {
+                    check(key.clone(), template);
+                }
+            }
+        }
+    }
+}
+
+/// Checks that file mount destinations do not contain variable templates:
+/// files are laid out when the app is loaded, before variables are resolved.
+pub(crate) fn validate_file_destinations(
+    manifest: &AppManifest,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (id, component) in &manifest.components {
+        for (index, mount) in component.files.iter().enumerate() {
+            let WasiFilesMount::Placement { destination, .. } = mount else {
+                continue;
+            };
+            if !template_references(destination).is_empty() {
+                diagnostics.push(Diagnostic::error(
+                    vec![
+                        "component".to_owned(),
+                        id.to_string(),
+                        "files".to_owned(),
+                        index.to_string(),
+                        "destination".to_owned(),
+                    ],
+                    "file mount destinations are fixed when the app is loaded, \
+                     so cannot refer to variables",
+                ));
+            }
+        }
+    }
+}
+
+// Returns all the strings in the given TOML value, including nested ones.
+fn toml_strings(value: &toml::Value) -> Vec<&str> {
+    match value {
+        toml::Value::String(s) => vec![s],
+        toml::Value::Array(values) => values.iter().flat_map(toml_strings).collect(),
+        toml::Value::Table(table) => table.values().flat_map(toml_strings).collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns the variable names referenced by `{{ name }}` expressions in a
+/// template, skipping escaped `\{{` sequences.
+fn template_references(template: &str) -> Vec<&str> {
+    let mut names = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("}}") else {
+            break;
+        };
+        names.push(after_open[..end].trim());
+        rest = &after_open[end + 2..];
+    }
+    names
+}
+
+/// Checks that HTTP routes parse, and that no two routes match exactly the
+/// same paths. Routes which differ only in parameter names are always
+/// reported, since the router cannot prefer either; exact duplicates (where
+/// the last one wins at runtime) are reported only if `report_duplicates`.
+pub(crate) fn validate_routes(
+    manifest: &AppManifest,
+    report_duplicates: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Some(triggers) = manifest.triggers.get("http") else {
+        return;
+    };
+    let mut seen: Vec<(usize, &str, RoutePattern)> = vec![];
+    for (index, trigger) in triggers.iter().enumerate() {
+        let Some(route) = trigger.config.get("route").and_then(|r| r.as_str()) else {
+            continue;
+        };
+        let key = vec![
+            "trigger".to_owned(),
+            "http".to_owned(),
+            index.to_string(),
+            "route".to_owned(),
+        ];
+        let pattern = match RoutePattern::parse(route) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                diagnostics.push(Diagnostic::error(
+                    key,
+                    format!("invalid route {route:?}: {e}"),
+                ));
+                continue;
+            }
+        };
+        match seen.iter().find(|(_, _, p)| p.is_equivalent(&pattern)) {
+            Some((first, _, first_pattern)) if *first_pattern == pattern => {
+                if report_duplicates {
+                    diagnostics.push(Diagnostic::error(
+                        key,
+                        format!(
+                            "route {route:?} is already used by HTTP trigger {}",
+                            first + 1
+                        ),
+                    ));
+                }
+            }
+            Some((first, first_route, _)) => {
+                diagnostics.push(Diagnostic::error(
+                    key,
+                    format!(
+                        "route {route:?} conflicts with route {first_route:?} of HTTP trigger {}: \
+                         both match the same paths",
+                        first + 1
+                    ),
+                ));
+            }
+            None => seen.push((index, route, pattern)),
+        }
+    }
+}
+
+fn validate_environments(manifest: &AppManifest, diagnostics: &mut Vec<Diagnostic>) {
+    for (name, environment) in &manifest.environments {
+        for id in environment.components.keys() {
+            if !manifest.components.contains_key(id) {
+                diagnostics.push(Diagnostic::error(
+                    vec![
+                        "environments".to_owned(),
+                        name.to_string(),
+                        "component".to_owned(),
+                        id.to_string(),
+                    ],
+                    format!(
+                        "environment configures undefined component {:?}",
+                        id.as_ref()
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn validate_dependency_paths(
+    manifest: &AppManifest,
+    app_root: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (id, component) in &manifest.components {
+        for (name, dependency) in &component.dependencies.inner {
+            let ComponentDependency::Local { path, .. } = dependency else {
+                continue;
+            };
+            if !app_root.join(path).exists() {
+                diagnostics.push(Diagnostic::warning(
+                    vec![
+                        "component".to_owned(),
+                        id.to_string(),
+                        "dependencies".to_owned(),
+                        name.to_string(),
+                    ],
+                    format!(
+                        "dependency file {} does not exist; it may need to be built",
+                        path.display()
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+// Finds the span of the item at the given key, falling back to the closest
+// parent which has one. Keys that come from included files are not found.
+fn locate(
+    document: &toml_edit::ImDocument<&str>,
+    contents: &str,
+    key: &[String],
+) -> Option<Location> {
+    let mut item = document.as_item();
+    let mut span = None;
+    for part in key {
+        let next = match part.parse::<usize>() {
+            Ok(index) => item.get(index),
+            Err(_) => item.get(part.as_str()),
+        }?;
+        if let Some(next_span) = next.span() {
+            span = Some(next_span);
+        } else if let Some(key_span) = item
+            .as_table_like()
+            .and_then(|table| table.get_key_value(part))
+            .and_then(|(key, _)| key.span())
+        {
+            span = Some(key_span);
+        }
+        item = next;
+    }
+    let span = span?;
+    let before = &contents[..span.start];
+    let line = before.matches('\n').count() + 1;
+    let column = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+        .chars()
+        .count()
+        + 1;
+    Some(Location { line, column, span })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_references_skip_escapes() {
+        assert_eq!(
+            vec!["a", "b"],
+            template_references(r"{{ a }}-\{{ literal }}-{{b}}")
+        );
+        assert!(template_references("no templates").is_empty());
+    }
+
+    #[test]
+    fn unknown_and_deprecated_fields_are_reported() {
+        let manifest: toml::Table = toml::from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "app"
+            [application.trigger.http]
+            base = "/"
+            [[trigger.http]]
+            rout = "/..."
+            component = "api"
+            [[trigger.sqs]]
+            queue_url = "https://sqs.example.com/queue"
+            component = "api"
+            [component.api]
+            source = { url = "https://example.com/api.wasm", digets = "sha256:abc" }
+            allowed_hosts = ["https://example.com"]
+            allowed_http_hosts = ["example.com"]
+            files = [{ source = "assets", destination = "/", read_only = true }]
+            [component.api.tool.my-tool]
+            anything = "goes"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "warning: unknown field `rout`; did you mean `route`? (at `trigger.http.0.rout`)",
+                "warning: unknown field `digets`; did you mean `digest`? (at `component.api.source.digets`)",
+                "warning: unknown field `allowed_hosts`; did you mean `allowed_outbound_hosts`? (at `component.api.allowed_hosts`)",
+                "warning: `allowed_http_hosts` is deprecated. Use `allowed_outbound_hosts` instead. (at `component.api.allowed_http_hosts`)",
+            ],
+            validate_fields(&manifest)
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn maximal_manifest_fields_are_known() {
+        for name in ["maximal.toml", "chain.toml", "normalization.toml"] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/ui")
+                .join(name);
+            let manifest = crate::v2_table_from_file(&path).unwrap().unwrap();
+            assert_eq!(
+                Vec::<Diagnostic>::new(),
+                validate_fields(&manifest),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn component_dependency_cycles_are_reported() {
+        let manifest: AppManifest = toml::from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "app"
+            [[trigger.http]]
+            route = "/..."
+            component = "a"
+            [component.a]
+            source = "a.wasm"
+            dependencies = { "example:b/run" = { component = "b" } }
+            [component.b]
+            source = "b.wasm"
+            dependencies = { "example:a/run" = { component = "a" } }
+            "#,
+        )
+        .unwrap();
+        let mut diagnostics = vec![];
+        validate_component_dependencies(&manifest, &mut diagnostics);
+        assert_eq!(
+            vec![
+                r#"error: component "a" depends on itself through component "b" (at `component.a.dependencies.example:b/run`)"#,
+                r#"error: component "b" depends on itself through component "a" (at `component.b.dependencies.example:a/run`)"#,
+            ],
+            diagnostics
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parsed_file_diagnostics_are_located() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/validate/spin.toml");
+        let manifest = crate::manifest_from_file(&path).unwrap();
+        let diagnostics = validate_parsed_file(&path, &manifest).unwrap();
+        assert!(!diagnostics.is_empty());
+        let file_diagnostics = validate_file(&path).unwrap();
+        for diagnostic in diagnostics {
+            assert!(diagnostic.location.is_some(), "{diagnostic}");
+            assert!(file_diagnostics.contains(&diagnostic), "{diagnostic}");
+        }
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ui/v1/maximal.toml");
+        let manifest = crate::manifest_from_file(&path).unwrap();
+        assert_eq!(
+            Vec::<Diagnostic>::new(),
+            validate_parsed_file(&path, &manifest).unwrap()
+        );
+    }
+}
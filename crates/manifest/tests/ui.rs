@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use spin_manifest::normalize::normalize_manifest;
+use spin_manifest::normalize::{normalize_chains, normalize_manifest};
 use ui_testing::{Failed, UiTestsRunner};
 
 fn main() -> anyhow::Result<()> {
@@ -25,6 +25,29 @@ fn main() -> anyhow::Result<()> {
         "tests/ui/normalization.toml.norm",
         |_| run_normalization_test("tests/ui/normalization.toml"),
     );
+    runner.add_test("ui::chain".into(), "tests/ui/chain.toml.norm", |_| {
+        run_chain_test("tests/ui/chain.toml")
+    });
+    // Included files aren't manifests in their own right, so these live
+    // outside tests/ui
+    for name in ["spin", "conflict"] {
+        let path = Path::new("tests/include")
+            .join(name)
+            .with_extension("toml")
+            .canonicalize()?;
+        let snapshot_path = path.with_extension("toml.norm");
+        runner.add_test(format!("ui::include::{name}"), snapshot_path, move |_| {
+            run_normalization_test(&path)
+        });
+    }
+    runner.add_test("ui::format".into(), "tests/format/spin.toml.fmt", |_| {
+        run_format_test("tests/format/spin.toml")
+    });
+    runner.add_test(
+        "ui::validate".into(),
+        "tests/validate/spin.toml.validate",
+        |_| run_validate_test("tests/validate/spin.toml"),
+    );
 
     runner.run_tests()
 }
@@ -43,8 +66,28 @@ fn run_v1_to_v2_test(input: &Path) -> Result<String, Failed> {
     Ok(toml::to_string(&v2_manifest).expect("serialization should work"))
 }
 
+fn run_format_test(input: impl AsRef<Path>) -> Result<String, Failed> {
+    let manifest_str = std::fs::read_to_string(input)?;
+    let formatted = spin_manifest::format::format_manifest(&manifest_str)?;
+    let reformatted = spin_manifest::format::format_manifest(&formatted)?;
+    assert_eq!(formatted, reformatted, "formatting should be idempotent");
+    Ok(formatted)
+}
+
+fn run_validate_test(input: impl AsRef<Path>) -> Result<String, Failed> {
+    let diagnostics = spin_manifest::validate::validate_file(input)?;
+    Ok(diagnostics.iter().map(|d| format!("{d}\n")).collect())
+}
+
 fn run_normalization_test(input: impl AsRef<Path>) -> Result<String, Failed> {
     let mut manifest = spin_manifest::manifest_from_file(input)?;
     normalize_manifest(&mut manifest);
     Ok(toml::to_string(&manifest).expect("serialization should work"))
 }
+
+fn run_chain_test(input: impl AsRef<Path>) -> Result<String, Failed> {
+    let mut manifest = spin_manifest::manifest_from_file(input)?;
+    normalize_manifest(&mut manifest);
+    normalize_chains(&mut manifest)?;
+    Ok(toml::to_string(&manifest).expect("serialization should work"))
+}
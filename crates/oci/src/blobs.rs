@@ -0,0 +1,146 @@
+//! Bookkeeping for the content-addressed blobs of Spin applications.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use spin_common::sha256;
+
+/// Checks that the given bytes have the given `sha256:` digest.
+pub(crate) fn verify_digest(expected: &str, bytes: &[u8]) -> Result<()> {
+    let Some(expected_hex) = expected.strip_prefix("sha256:") else {
+        bail!("unsupported digest {expected:?}: only sha256 digests are supported");
+    };
+    let actual_hex = sha256::hex_digest_from_bytes(bytes);
+    if actual_hex != expected_hex {
+        bail!("content does not match its digest: expected {expected}, got sha256:{actual_hex}");
+    }
+    Ok(())
+}
+
+/// Computes the `sha256:` digest of a pulled manifest, checking that it is
+/// the digest the manifest was pulled by, if any.
+///
+/// The digest reported by the registry is not used: it is only a claim about
+/// the manifest, which the registry could get wrong.
+pub(crate) fn manifest_digest(bytes: &[u8], expected: Option<&str>) -> Result<String> {
+    let digest = format!("sha256:{}", sha256::hex_digest_from_bytes(bytes));
+    if let Some(expected) = expected {
+        verify_digest(expected, bytes).context("invalid manifest")?;
+    }
+    Ok(digest)
+}
+
+/// The repositories of a registry to which blobs have been pushed, so that a
+/// later push of the same blob to another repository can mount it from one
+/// of them instead of uploading it again.
+///
+/// The index is only a hint: registries may delete blobs, or refuse to mount
+/// them, in which case they are uploaded as usual.
+#[derive(Debug, Default)]
+pub(crate) struct PushedBlobs {
+    path: PathBuf,
+    repositories: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl PushedBlobs {
+    /// Loads the index from the given file. A missing or unreadable index is
+    /// treated as empty.
+    pub async fn load(path: PathBuf) -> Self {
+        let repositories = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::debug!("Ignoring invalid pushed blobs index {path:?}: {e}");
+                Default::default()
+            }),
+            Err(_) => Default::default(),
+        };
+        Self { path, repositories }
+    }
+
+    /// The repositories the blob with the given digest has been pushed to.
+    pub fn repositories(&self, digest: &str) -> impl Iterator<Item = &str> {
+        self.repositories
+            .get(digest)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Records that the given blobs are in the given repository.
+    pub fn record<'a>(&mut self, repository: &str, digests: impl IntoIterator<Item = &'a str>) {
+        for digest in digests {
+            self.repositories
+                .entry(digest.to_owned())
+                .or_default()
+                .insert(repository.to_owned());
+        }
+    }
+
+    /// Saves the index to the file it was loaded from.
+    pub async fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let json = serde_json::to_vec(&self.repositories)?;
+        write_atomically(&self.path, &json)
+            .await
+            .with_context(|| format!("cannot write pushed blobs index {:?}", self.path))
+    }
+}
+
+/// Writes the file via a temporary file, so that concurrent readers never see
+/// it partly written.
+async fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, contents).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn digests_are_verified() {
+        let digest = format!("sha256:{}", sha256::hex_digest_from_bytes("spin"));
+        verify_digest(&digest, b"spin").unwrap();
+        verify_digest(&digest, b"spun").unwrap_err();
+        verify_digest("sha512:abc", b"spin").unwrap_err();
+    }
+
+    #[test]
+    fn manifest_digests_are_computed_and_verified() {
+        let manifest = br#"{"schemaVersion":2}"#;
+        let digest = format!("sha256:{}", sha256::hex_digest_from_bytes(manifest));
+        assert_eq!(manifest_digest(manifest, None).unwrap(), digest);
+        assert_eq!(manifest_digest(manifest, Some(&digest)).unwrap(), digest);
+
+        let other = br#"{"schemaVersion":2,"layers":[]}"#;
+        let err = manifest_digest(other, Some(&digest)).unwrap_err();
+        assert!(format!("{err:#}").contains("does not match"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn pushed_blobs_are_remembered() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("ghcr.io").join("pushed-blobs.json");
+
+        let mut pushed = PushedBlobs::load(path.clone()).await;
+        assert_eq!(pushed.repositories("sha256:aaa").count(), 0);
+        pushed.record("spin/app1", ["sha256:aaa", "sha256:bbb"]);
+        pushed.record("spin/app2", ["sha256:aaa"]);
+        pushed.save().await?;
+
+        let pushed = PushedBlobs::load(path).await;
+        assert_eq!(
+            pushed.repositories("sha256:aaa").collect::<Vec<_>>(),
+            ["spin/app1", "spin/app2"]
+        );
+        assert_eq!(
+            pushed.repositories("sha256:bbb").collect::<Vec<_>>(),
+            ["spin/app1"]
+        );
+        Ok(())
+    }
+}
@@ -9,8 +9,15 @@ use futures_util::future;
 use futures_util::stream::{self, StreamExt, TryStreamExt};
 use itertools::Itertools;
 use oci_distribution::{
-    client::ImageLayer, config::ConfigFile, manifest::OciImageManifest, secrets::RegistryAuth,
-    token_cache::RegistryTokenType, Reference, RegistryOperation,
+    client::{BlobResponse, ImageLayer},
+    config::ConfigFile,
+    manifest::{
+        OciDescriptor, OciImageManifest, OciManifest, IMAGE_MANIFEST_MEDIA_TYPE,
+        OCI_IMAGE_MEDIA_TYPE,
+    },
+    secrets::RegistryAuth,
+    token_cache::RegistryTokenType,
+    Reference, RegistryOperation,
 };
 use reqwest::Url;
 use spin_common::sha256;
@@ -18,12 +25,17 @@ use spin_common::ui::quoted_path;
 use spin_common::url::parse_file_url;
 use spin_compose::ComponentSourceLoaderFs;
 use spin_loader::cache::Cache;
-use spin_loader::FilesMountStrategy;
-use spin_locked_app::locked::{ContentPath, ContentRef, LockedApp, LockedComponent};
+use spin_loader::{FilesMountStrategy, LoadOptions};
+use spin_locked_app::locked::{
+    ContentPath, ContentRef, DigestRef, LockedApp, LockedComponent, LockedComponentDependency,
+};
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 use walkdir::WalkDir;
 
 use crate::auth::AuthConfig;
+use crate::blobs::{manifest_digest, verify_digest, PushedBlobs};
+use crate::signature::{SignatureLayer, SignatureVerifier, TrustPolicy, SIMPLE_SIGNING_MEDIA_TYPE};
 
 // TODO: the media types for application, data and archive layer are not final
 /// Media type for a layer representing a locked Spin application configuration
@@ -34,15 +46,20 @@ pub const DATA_MEDIATYPE: &str = "application/vnd.wasm.content.layer.v1+data";
 pub const ARCHIVE_MEDIATYPE: &str = "application/vnd.wasm.content.bundle.v1.tar+gzip";
 // Note: this will be updated with a canonical value once defined upstream
 const WASM_LAYER_MEDIA_TYPE: &str = "application/vnd.wasm.content.layer.v1+wasm";
+/// The manifest media types of Spin apps. Image indexes are not accepted, as
+/// Spin apps are not platform-specific.
+const IMAGE_MANIFEST_MEDIA_TYPES: &[&str] = &[OCI_IMAGE_MEDIA_TYPE, IMAGE_MANIFEST_MEDIA_TYPE];
 
 const CONFIG_FILE: &str = "config.json";
 const LATEST_TAG: &str = "latest";
 const MANIFEST_FILE: &str = "manifest.json";
+const PUSHED_BLOBS_FILE: &str = "pushed-blobs.json";
 
 /// Env var to force use of archive layers when publishing a Spin app
 const SPIN_OCI_ARCHIVE_LAYERS_OPT: &str = "SPIN_OCI_ARCHIVE_LAYERS";
 
 const MAX_PARALLEL_PULL: usize = 16;
+const MAX_PARALLEL_PUSH: usize = 8;
 /// Maximum layer count allowed per app, set in accordance to the lowest
 /// known maximum per image in well-known OCI registry implementations.
 /// (500 appears to be the limit for Elastic Container Registry)
@@ -86,6 +103,8 @@ pub struct Client {
     oci: oci_distribution::Client,
     /// Client options
     pub opts: ClientOpts,
+    /// The signatures pulled apps must have, if any.
+    signatures: Option<SignatureVerifier>,
 }
 
 #[derive(Clone)]
@@ -119,14 +138,31 @@ impl Client {
             oci: client,
             cache,
             opts,
+            signatures: None,
         })
     }
 
+    /// Require apps pulled by this client to be signed as the given policy
+    /// requires. Pulls of apps which are not fail before any of their
+    /// content is pulled.
+    pub fn set_trust_policy(&mut self, policy: &TrustPolicy) -> Result<()> {
+        self.signatures = Some(
+            policy
+                .load()
+                .context("cannot load signature trust policy")?,
+        );
+        Ok(())
+    }
+
     /// Push a Spin application to an OCI registry and return the digest (or None
     /// if the digest cannot be determined).
+    ///
+    /// The application is loaded from its manifest with the given options,
+    /// which say how its `spin.lock` is used.
     pub async fn push(
         &mut self,
         manifest_path: &Path,
+        load_options: LoadOptions,
         reference: impl AsRef<str>,
         annotations: Option<BTreeMap<String, String>>,
         infer_annotations: InferPredefinedAnnotations,
@@ -142,10 +178,11 @@ impl Client {
         // Create a locked application from the application manifest.
         // TODO: We don't need an extra copy here for each asset to prepare the application.
         // We should be able to use assets::collect instead when constructing the locked app.
-        let locked = spin_loader::from_file(
+        let locked = spin_loader::from_file_with_options(
             manifest_path,
             FilesMountStrategy::Copy(working_dir.path().into()),
             None,
+            load_options,
         )
         .await?;
 
@@ -255,12 +292,28 @@ impl Client {
             oci_distribution::client::Config::oci_v1_from_config_file(oci_config_file, None)?;
         let manifest = OciImageManifest::build(&layers, &oci_config, annotations);
 
+        self.oci
+            .store_auth_if_needed(reference.resolve_registry(), &auth)
+            .await;
+        self.oci
+            .auth(&reference, &auth, RegistryOperation::Push)
+            .await
+            .context("cannot authenticate to push Spin application")?;
+
+        let config_digest = format!("sha256:{}", sha256::hex_digest_from_bytes(&oci_config.data));
+        let blobs = layers
+            .iter()
+            .map(|layer| (&layer.data[..], layer.sha256_digest()))
+            .chain([(&oci_config.data[..], config_digest)]);
+        self.push_blobs(&reference, blobs)
+            .await
+            .context("cannot push Spin application")?;
+
         let response = self
             .oci
-            .push(&reference, &layers, oci_config, &auth, Some(manifest))
+            .push_manifest(&reference, &OciManifest::Image(manifest))
             .await
-            .map(|push_response| push_response.manifest_url)
-            .context("cannot push Spin application")?;
+            .context("cannot push Spin application manifest")?;
 
         tracing::info!("Pushed {:?}", response);
 
@@ -268,6 +321,80 @@ impl Client {
         Ok(digest)
     }
 
+    /// Push the given blobs, as pairs of contents and digest, to the
+    /// repository of the given reference.
+    ///
+    /// Blobs are content-addressed, so a blob the registry already holds is
+    /// not uploaded again: it is either already in the repository, or is
+    /// mounted from another repository of the registry which Spin has pushed
+    /// it to, where the registry allows.
+    async fn push_blobs(
+        &self,
+        reference: &Reference,
+        blobs: impl Iterator<Item = (&[u8], String)>,
+    ) -> Result<()> {
+        let mut pushed = PushedBlobs::load(
+            self.cache
+                .manifests_dir()
+                .join(fs_safe_segment(reference.registry()))
+                .join(PUSHED_BLOBS_FILE),
+        )
+        .await;
+
+        let digests = stream::iter(blobs)
+            .map(|(data, digest)| {
+                let pushed = &pushed;
+                async move {
+                    if self.mount_existing_blob(reference, &digest, pushed).await {
+                        tracing::debug!("Blob {digest} already exists in registry");
+                    } else {
+                        tracing::debug!("Pushing blob {digest}");
+                        self.oci
+                            .push_blob(reference, data, &digest)
+                            .await
+                            .with_context(|| format!("cannot push blob {digest}"))?;
+                    }
+                    anyhow::Ok(digest)
+                }
+            })
+            .buffer_unordered(MAX_PARALLEL_PUSH)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        pushed.record(reference.repository(), digests.iter().map(String::as_str));
+        if let Err(e) = pushed.save().await {
+            tracing::warn!("Cannot record pushed blobs: {e:?}");
+        }
+        Ok(())
+    }
+
+    /// Mounts the blob with the given digest into the repository of the
+    /// given reference, from the repository itself or from another to which
+    /// it has been pushed. Returns false if the blob could not be mounted,
+    /// and must be uploaded.
+    async fn mount_existing_blob(
+        &self,
+        reference: &Reference,
+        digest: &str,
+        pushed: &PushedBlobs,
+    ) -> bool {
+        let target = reference.repository();
+        let sources = std::iter::once(target)
+            .chain(pushed.repositories(digest).filter(|repo| *repo != target));
+        for repository in sources {
+            let source = Reference::with_digest(
+                reference.registry().to_owned(),
+                repository.to_owned(),
+                digest.to_owned(),
+            );
+            match self.oci.mount_blob(reference, &source, digest).await {
+                Ok(()) => return true,
+                Err(e) => tracing::trace!("Cannot mount blob {digest} from {repository}: {e}"),
+            }
+        }
+        false
+    }
+
     /// Assemble ImageLayers for a locked application using the provided
     /// AssemblyMode and return the resulting Vec<ImageLayer>.
     async fn assemble_layers(
@@ -321,23 +448,9 @@ impl Client {
 
             layers.push(layer);
 
-            let mut deps = BTreeMap::default();
-            for (dep_name, mut dep) in c.dependencies {
-                let source = dep
-                    .source
-                    .content
-                    .source
-                    .context("dependency loaded from disk should contain a file source")?;
-                let source = parse_file_url(source.as_str())?;
-
-                let layer = Self::wasm_layer(&source).await?;
-
-                dep.source.content = self.content_ref_for_layer(&layer);
-                deps.insert(dep_name, dep);
-
-                layers.push(layer);
+            for dep in c.dependencies.values_mut() {
+                self.assemble_dependency_layers(&mut layers, dep).await?;
             }
-            c.dependencies = deps;
 
             c.files = self
                 .assemble_content_layers(assembly_mode, &mut layers, c.files.as_slice())
@@ -348,6 +461,33 @@ impl Client {
         Ok((layers, components))
     }
 
+    // Adds the Wasm source of the given dependency, and of its own
+    // dependencies, as layers.
+    async fn assemble_dependency_layers(
+        &self,
+        layers: &mut Vec<ImageLayer>,
+        dep: &mut LockedComponentDependency,
+    ) -> Result<()> {
+        let source = dep
+            .source
+            .content
+            .source
+            .as_ref()
+            .context("dependency loaded from disk should contain a file source")?;
+        let source = parse_file_url(source.as_str())?;
+
+        let layer = Self::wasm_layer(&source).await?;
+
+        dep.source.content = self.content_ref_for_layer(&layer);
+
+        layers.push(layer);
+
+        for nested in dep.dependencies.values_mut() {
+            Box::pin(self.assemble_dependency_layers(layers, nested)).await?;
+        }
+        Ok(())
+    }
+
     async fn assemble_layers_composed(
         &mut self,
         assembly_mode: AssemblyMode,
@@ -393,14 +533,14 @@ impl Client {
 
             match assembly_mode {
                 AssemblyMode::Archive => self
-                    .push_archive_layer(&source, &mut files, layers)
+                    .push_archive_layer(&source, f, &mut files, layers)
                     .await
                     .context(format!(
                         "cannot push archive layer for source {}",
                         quoted_path(&source)
                     ))?,
                 AssemblyMode::Simple => self
-                    .push_file_layers(&source, &mut files, layers)
+                    .push_file_layers(&source, f, &mut files, layers)
                     .await
                     .context(format!(
                         "cannot push file layers for source {}",
@@ -416,6 +556,7 @@ impl Client {
     async fn push_archive_layer(
         &mut self,
         source: &PathBuf,
+        mount: &ContentPath,
         files: &mut Vec<ContentPath>,
         layers: &mut Vec<ImageLayer>,
     ) -> Result<()> {
@@ -433,7 +574,8 @@ impl Client {
             let content = self.content_ref_for_layer(&layer);
             files.push(ContentPath {
                 content,
-                path: rel_path.into(),
+                path: mounted_path(mount, rel_path),
+                read_only: mount.read_only,
             });
         }
 
@@ -454,6 +596,7 @@ impl Client {
     async fn push_file_layers(
         &mut self,
         source: &PathBuf,
+        mount: &ContentPath,
         files: &mut Vec<ContentPath>,
         layers: &mut Vec<ImageLayer>,
     ) -> Result<()> {
@@ -469,7 +612,7 @@ impl Client {
             let rel_path = entry.path().strip_prefix(source).unwrap();
             // Paths must be in portable (forward slash) format in the registry,
             // so that they can be placed correctly on any host system
-            let rel_path = portable_path(rel_path);
+            let rel_path = portable_path(&mounted_path(mount, rel_path));
 
             tracing::trace!("Adding new layer for asset {rel_path:?}");
             // Construct and push layer, adding its digest to the locked component files Vec
@@ -479,6 +622,7 @@ impl Client {
             files.push(ContentPath {
                 content,
                 path: rel_path,
+                read_only: mount.read_only,
             });
             // As a workaround for OCI implementations that don't support very small blobs,
             // don't push very small content that has been inlined into the manifest:
@@ -496,9 +640,30 @@ impl Client {
         let reference: Reference = reference.parse().context("cannot parse reference")?;
         let auth = Self::auth(&reference).await?;
 
-        // Pull the manifest from the registry.
-        let (manifest, digest) = self.oci.pull_image_manifest(&reference, &auth).await?;
+        // Pull the manifest from the registry, and check it is the one the
+        // reference asks for before trusting anything it says.
+        let (manifest_bytes, _) = self
+            .oci
+            .pull_manifest_raw(&reference, &auth, IMAGE_MANIFEST_MEDIA_TYPES)
+            .await?;
+        let digest = manifest_digest(&manifest_bytes, reference.digest())
+            .with_context(|| format!("registry returned the wrong manifest for {reference}"))?;
+        if let Some(verifier) = &self.signatures {
+            let signatures = self
+                .pull_signatures(&reference, &auth, &digest)
+                .await
+                .with_context(|| {
+                    format!("cannot pull signatures of {reference} ({digest}): is it signed?")
+                })?;
+            verifier
+                .verify_manifest(&manifest_bytes, &signatures)
+                .with_context(|| {
+                    format!("{reference} ({digest}) is not signed as the trust policy requires")
+                })?;
+        }
 
+        let manifest: OciImageManifest =
+            serde_json::from_slice(&manifest_bytes).context("cannot parse manifest")?;
         let manifest_json = serde_json::to_string(&manifest)?;
         tracing::debug!("Pulled manifest: {}", manifest_json);
 
@@ -513,6 +678,7 @@ impl Client {
         self.oci
             .pull_blob(&reference, &manifest.config, &mut cfg_bytes)
             .await?;
+        verify_digest(&manifest.config.digest, &cfg_bytes).context("invalid config blob")?;
         self.write_locked_app_config(&reference.to_string(), &cfg_bytes)
             .await
             .context("unable to write locked app config to cache")?;
@@ -533,8 +699,10 @@ impl Client {
                     }
 
                     tracing::debug!("Pulling layer {}", &layer.digest);
-                    let mut bytes = Vec::with_capacity(layer.size.try_into()?);
-                    this.oci.pull_blob(&reference, &layer, &mut bytes).await?;
+                    let bytes = this
+                        .pull_layer(&reference, &layer)
+                        .await
+                        .with_context(|| format!("cannot pull layer {}", layer.digest))?;
                     match layer.media_type.as_str() {
                         SPIN_APPLICATION_MEDIA_TYPE => {
                             this.write_locked_app_config(&reference.to_string(), &bytes)
@@ -562,6 +730,95 @@ impl Client {
         Ok(())
     }
 
+    /// Pull the cosign signatures of the manifest with the given digest, which
+    /// are kept under the `sha256-<digest>.sig` tag of its repository.
+    async fn pull_signatures(
+        &self,
+        reference: &Reference,
+        auth: &RegistryAuth,
+        digest: &str,
+    ) -> Result<Vec<SignatureLayer>> {
+        let signatures_reference = Reference::with_tag(
+            reference.registry().to_owned(),
+            reference.repository().to_owned(),
+            format!("{}.sig", digest.replace(':', "-")),
+        );
+        let (manifest, _) = self
+            .oci
+            .pull_image_manifest(&signatures_reference, auth)
+            .await?;
+
+        let mut signatures = vec![];
+        for layer in manifest.layers {
+            if layer.media_type != SIMPLE_SIGNING_MEDIA_TYPE {
+                continue;
+            }
+            let mut payload = Vec::new();
+            self.oci
+                .pull_blob(&signatures_reference, &layer, &mut payload)
+                .await?;
+            verify_digest(&layer.digest, &payload).context("invalid signature payload")?;
+            signatures.push(SignatureLayer {
+                payload,
+                annotations: layer.annotations.into_iter().flatten().collect(),
+            });
+        }
+        Ok(signatures)
+    }
+
+    /// Pull the blob of a layer and verify its digest.
+    ///
+    /// The blob is downloaded to a partial file in the cache, so that if the
+    /// download is interrupted, the next pull of the layer resumes it from
+    /// where it stopped rather than starting again.
+    async fn pull_layer(&self, reference: &Reference, layer: &OciDescriptor) -> Result<Vec<u8>> {
+        self.cache.ensure_dirs().await?;
+        let path = self.cache.partial_path(&layer.digest);
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("cannot open {}", quoted_path(&path)))?;
+        let mut offset = file.metadata().await?.len();
+        let size = u64::try_from(layer.size).unwrap_or_default();
+        if offset > size {
+            // Left by a different, broken download
+            file.set_len(0).await?;
+            offset = 0;
+        }
+
+        if offset < size {
+            if offset > 0 {
+                tracing::debug!("Resuming pull of layer {} at byte {offset}", layer.digest);
+            }
+            let mut stream = match self
+                .oci
+                .pull_blob_stream_partial(reference, layer, offset, None)
+                .await?
+            {
+                BlobResponse::Partial(stream) => stream,
+                BlobResponse::Full(stream) => {
+                    // The registry does not support resuming
+                    file.set_len(0).await?;
+                    stream
+                }
+            };
+            while let Some(chunk) = stream.try_next().await? {
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+        }
+        drop(file);
+
+        let bytes = fs::read(&path).await?;
+        // A blob which doesn't match its digest can't be resumed
+        let verified = verify_digest(&layer.digest, &bytes);
+        fs::remove_file(&path).await?;
+        verified?;
+        Ok(bytes)
+    }
+
     /// Get the file path to an OCI manifest given a reference.
     /// If the directory for the manifest does not exist, this will create it.
     async fn manifest_path(&self, reference: impl AsRef<str>) -> Result<PathBuf> {
@@ -649,8 +906,8 @@ impl Client {
             // Inline small content as an optimization and to work around issues
             // with OCI implementations that don't support very small blobs.
             inline: (layer.data.len() <= self.opts.content_ref_inline_max_size)
-                .then(|| layer.data.to_vec()),
-            digest: Some(layer.sha256_digest()),
+                .then(|| layer.data.to_vec().into()),
+            digest: Some(DigestRef::sha256(&layer.data)),
             ..Default::default()
         }
     }
@@ -893,6 +1150,13 @@ fn add_inferred(map: &mut BTreeMap<String, String>, key: &str, value: Option<Str
     }
 }
 
+/// Returns the path in the guest, relative to the guest root, of a file
+/// at `rel_path` within the given mount.
+fn mounted_path(mount: &ContentPath, rel_path: &Path) -> PathBuf {
+    let mount_path = mount.path.strip_prefix("/").unwrap_or(&mount.path);
+    mount_path.join(rel_path)
+}
+
 /// Takes a relative path and turns it into a format that is safe
 /// for putting into a registry where it might end up on any host.
 #[cfg(target_os = "windows")]
@@ -971,6 +1235,11 @@ mod test {
         }
     }
 
+    // A well-formed digest for locked components whose content is read
+    // from their `source`
+    const TEST_DIGEST: &str =
+        "sha256:0000000000000000000000000000000000000000000000000000000000000000";
+
     // Convenience wrapper for deserializing from literal JSON
     #[macro_export]
     #[allow(missing_docs)] // it's test-only, but rust-analyzer gets mad
@@ -1082,14 +1351,14 @@ mod test {
                     "source": {
                         "content_type": "application/wasm",
                         "source": format!("file://{}", working_dir.path().join("component1.wasm").to_str().unwrap()),
-                        "digest": "digest",
+                        "digest": TEST_DIGEST,
                 }},
                 {
                     "id": "component2",
                     "source": {
                         "content_type": "application/wasm",
                         "source": format!("file://{}", working_dir.path().join("component2.wasm").to_str().unwrap()),
-                        "digest": "digest",
+                        "digest": TEST_DIGEST,
                 }}]),
                 expected_layer_count: 2,
                 expected_error: None,
@@ -1103,7 +1372,7 @@ mod test {
                 "source": {
                     "content_type": "application/wasm",
                     "source": format!("file://{}", working_dir.path().join("component1.wasm").to_str().unwrap()),
-                    "digest": "digest",
+                    "digest": TEST_DIGEST,
                 },
                 "files": [
                     {
@@ -1128,7 +1397,7 @@ mod test {
                 "source": {
                     "content_type": "application/wasm",
                     "source": format!("file://{}", working_dir.path().join("component1.wasm").to_str().unwrap()),
-                    "digest": "digest",
+                    "digest": TEST_DIGEST,
                 },
                 "files": [
                     {
@@ -1149,14 +1418,14 @@ mod test {
                 "source": {
                     "content_type": "application/wasm",
                     "source": format!("file://{}", working_dir.path().join("component1.wasm").to_str().unwrap()),
-                    "digest": "digest",
+                    "digest": TEST_DIGEST,
                 },
                 "dependencies": {
                     "test:comp2": {
                         "source": {
                             "content_type": "application/wasm",
                             "source": format!("file://{}", working_dir.path().join("component2.wasm").to_str().unwrap()),
-                            "digest": "digest",
+                            "digest": TEST_DIGEST,
                         },
                         "export": null,
                     }
@@ -1174,7 +1443,7 @@ mod test {
                 "source": {
                     "content_type": "application/wasm",
                     "source": "",
-                    "digest": "digest",
+                    "digest": TEST_DIGEST,
                 }
                 }]),
                 expected_layer_count: 0,
@@ -1189,14 +1458,14 @@ mod test {
                     "source": {
                         "content_type": "application/wasm",
                         "source": format!("file://{}", working_dir.path().join("component1.wasm").to_str().unwrap()),
-                        "digest": "digest",
+                        "digest": TEST_DIGEST,
                 }},
                 {
                     "id": "component2",
                     "source": {
                         "content_type": "application/wasm",
                         "source": format!("file://{}", working_dir.path().join("component1.wasm").to_str().unwrap()),
-                        "digest": "digest",
+                        "digest": TEST_DIGEST,
                 }}]),
                 expected_layer_count: 1,
                 expected_error: None,
@@ -1210,7 +1479,7 @@ mod test {
                 "source": {
                     "content_type": "application/wasm",
                     "source": format!("file://{}", working_dir.path().join("component1.wasm").to_str().unwrap()),
-                    "digest": "digest",
+                    "digest": TEST_DIGEST,
                 },
                 "files": [
                     {
@@ -1227,7 +1496,7 @@ mod test {
                     "source": {
                         "content_type": "application/wasm",
                         "source": format!("file://{}", working_dir.path().join("component2.wasm").to_str().unwrap()),
-                        "digest": "digest",
+                        "digest": TEST_DIGEST,
                 },
                 "files": [
                     {
@@ -1248,14 +1517,14 @@ mod test {
                 "source": {
                     "content_type": "application/wasm",
                     "source": format!("file://{}", working_dir.path().join("root.wasm").to_str().unwrap()),
-                    "digest": "digest",
+                    "digest": TEST_DIGEST,
                 },
                 "dependencies": {
                     "test:test/a": {
                         "source": {
                             "content_type": "application/wasm",
                             "source": format!("file://{}", working_dir.path().join("dep_a.wasm").to_str().unwrap()),
-                            "digest": "digest",
+                            "digest": TEST_DIGEST,
                         },
                         "export": null,
                     }
@@ -2,8 +2,10 @@
 #![deny(missing_docs)]
 
 mod auth;
+mod blobs;
 pub mod client;
 mod loader;
+pub mod signature;
 pub mod utils;
 
 pub use client::{Client, ComposeMode};
@@ -3,9 +3,12 @@ use std::path::{Path, PathBuf};
 use anyhow::{anyhow, ensure, Context, Result};
 use oci_distribution::Reference;
 use reqwest::Url;
-use spin_common::ui::quoted_path;
+use spin_common::{sha256, ui::quoted_path};
+use spin_compose::ComponentSourceLoaderFs;
 use spin_loader::cache::Cache;
-use spin_locked_app::locked::{ContentPath, ContentRef, LockedApp, LockedComponent};
+use spin_locked_app::locked::{
+    ContentPath, ContentRef, DigestRef, LockedApp, LockedComponent, LockedComponentDependency,
+};
 
 use crate::{Client, ORIGIN_URL_SCHEME};
 
@@ -77,15 +80,34 @@ impl OciLoader {
         component: &mut LockedComponent,
         cache: &Cache,
     ) -> Result<()> {
+        // Components published uncomposed are composed once, when first
+        // loaded, and the result cached by the digests of their parts
+        let composition_key = (!component.dependencies.is_empty())
+            .then(|| composition_key(component))
+            .transpose()?;
+
         // Update wasm content path
         let wasm_digest = content_digest(&component.source.content)?;
         let wasm_path = cache.wasm_file(wasm_digest)?;
         component.source.content = content_ref(wasm_path)?;
 
         for dep in &mut component.dependencies.values_mut() {
-            let dep_wasm_digest = content_digest(&dep.source.content)?;
-            let dep_wasm_path = cache.wasm_file(dep_wasm_digest)?;
-            dep.source.content = content_ref(dep_wasm_path)?;
+            resolve_dependency_content_refs(dep, cache)?;
+        }
+
+        if let Some(key) = composition_key {
+            let composed_path = match cache.composed_file(&key) {
+                Some(path) => path,
+                None => {
+                    let composed = spin_compose::compose(&ComponentSourceLoaderFs, component)
+                        .await
+                        .context("failed to resolve dependencies")?;
+                    cache.write_composed(&composed, &key).await?;
+                    cache.composed_path(&key)
+                }
+            };
+            component.source.content = content_ref(composed_path)?;
+            component.dependencies.clear();
         }
 
         if !component.files.is_empty() {
@@ -127,9 +149,13 @@ impl OciLoader {
                 }
             }
 
+            // Files are merged into a single mount, which can only be read-only
+            // if all of them are
+            let read_only = component.files.iter().all(|file| file.read_only);
             component.files = vec![ContentPath {
                 content: content_ref(mount_dir)?,
                 path: "/".into(),
+                read_only,
             }]
         }
 
@@ -137,10 +163,39 @@ impl OciLoader {
     }
 }
 
+// Points the Wasm source of the given dependency, and of its own
+// dependencies, at the cached files.
+fn resolve_dependency_content_refs(
+    dep: &mut LockedComponentDependency,
+    cache: &Cache,
+) -> Result<()> {
+    let dep_wasm_digest = content_digest(&dep.source.content)?;
+    let dep_wasm_path = cache.wasm_file(dep_wasm_digest)?;
+    dep.source.content = content_ref(dep_wasm_path)?;
+    for nested in dep.dependencies.values_mut() {
+        resolve_dependency_content_refs(nested, cache)?;
+    }
+    Ok(())
+}
+
+/// Returns a key identifying the composition of a component with its
+/// dependencies, whose sources must still be content refs with digests.
+fn composition_key(component: &LockedComponent) -> Result<String> {
+    // Composition may change between versions of Spin
+    let inputs = serde_json::to_vec(&(
+        env!("CARGO_PKG_VERSION"),
+        &component.id,
+        &component.source,
+        &component.dependencies,
+    ))?;
+    Ok(format!("sha256:{}", sha256::hex_digest_from_bytes(inputs)))
+}
+
 fn content_digest(content_ref: &ContentRef) -> Result<&str> {
     content_ref
         .digest
-        .as_deref()
+        .as_ref()
+        .map(DigestRef::as_str)
         .with_context(|| format!("content missing expected digest: {content_ref:?}"))
 }
 
@@ -0,0 +1,562 @@
+//! Verification of the signatures of Spin applications pulled from
+//! registries, as made by `cosign sign`.
+//!
+//! Cosign keeps the signatures of a manifest as the layers of another
+//! artifact in the same repository, tagged `sha256-<digest>.sig`. Each layer
+//! is a "simple signing" payload naming the digest of the signed manifest,
+//! with the signature of the payload in its annotations. Signatures made
+//! without a key also carry a short-lived certificate for the signer's
+//! identity, issued by a Fulcio certificate authority, and signatures which
+//! were recorded in a Rekor transparency log carry a bundle proving it.
+
+mod certificate;
+mod rekor;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::Deserialize;
+use spin_common::{sha256, ui::quoted_path};
+use x509_cert::{der::DecodePem, Certificate};
+
+use certificate::{PublicKey, SigningCertificate};
+use rekor::RekorBundle;
+
+/// The key of the runtime config table which sets the trust policy for apps
+/// pulled from registries.
+pub const TRUST_POLICY_RUNTIME_CONFIG_KEY: &str = "signatures";
+
+/// The media type of the layers of signature artifacts.
+pub(crate) const SIMPLE_SIGNING_MEDIA_TYPE: &str =
+    "application/vnd.dev.cosign.simplesigning.v1+json";
+
+const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+const CERTIFICATE_ANNOTATION: &str = "dev.sigstore.cosign/certificate";
+const CHAIN_ANNOTATION: &str = "dev.sigstore.cosign/chain";
+const BUNDLE_ANNOTATION: &str = "dev.sigstore.cosign/bundle";
+
+const SIMPLE_SIGNING_TYPE: &str = "cosign container image signature";
+
+/// The signatures which apps pulled from registries must have, from the
+/// `[signatures]` table of a runtime config file. An app is trusted if any
+/// of its signatures is made by one of the trusted keys or identities.
+///
+/// ```toml
+/// [signatures]
+/// keys = ["cosign.pub"]
+/// identities = [
+///   { issuer = "https://token.actions.githubusercontent.com", subject = "https://github.com/acme/app/.github/workflows/release.yml@refs/heads/main" },
+/// ]
+/// fulcio_roots = ["fulcio.crt.pem"]
+/// rekor_keys = ["rekor.pub"]
+/// require_rekor = true
+/// ```
+///
+/// Signatures made without a key ("keyless") must always be recorded in a
+/// trusted transparency log, as their certificates are only valid for the
+/// few minutes around signing.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustPolicy {
+    /// Paths to the public keys, in PEM format, whose signatures are trusted.
+    #[serde(default)]
+    keys: Vec<PathBuf>,
+    /// The identities whose keyless signatures are trusted.
+    #[serde(default)]
+    identities: Vec<TrustedIdentity>,
+    /// Paths to the certificates, in PEM format, of the certificate
+    /// authorities which issue the certificates of keyless signatures.
+    #[serde(default)]
+    fulcio_roots: Vec<PathBuf>,
+    /// Paths to the public keys, in PEM format, of the trusted transparency
+    /// logs.
+    #[serde(default)]
+    rekor_keys: Vec<PathBuf>,
+    /// Whether signatures made with keys must also be recorded in a trusted
+    /// transparency log.
+    #[serde(default)]
+    require_rekor: bool,
+}
+
+/// An identity whose keyless signatures are trusted.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrustedIdentity {
+    /// The OIDC issuer which vouched for the identity, such as
+    /// `https://accounts.google.com`.
+    issuer: String,
+    /// The email address or URI, such as that of a CI workflow, of the
+    /// identity.
+    subject: String,
+}
+
+impl TrustPolicy {
+    /// Parses the `[signatures]` table, if any, of the given runtime config.
+    pub fn from_toml(table: &toml::Table) -> Result<Option<Self>> {
+        table
+            .get(TRUST_POLICY_RUNTIME_CONFIG_KEY)
+            .map(Self::from_toml_value)
+            .transpose()
+    }
+
+    /// Parses and validates the value of a `[signatures]` table.
+    pub fn from_toml_value(value: &toml::Value) -> Result<Self> {
+        let policy: Self = value.clone().try_into().with_context(|| {
+            format!("invalid [{TRUST_POLICY_RUNTIME_CONFIG_KEY}] runtime config")
+        })?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.keys.is_empty() || !self.identities.is_empty(),
+            "[signatures] must list trusted `keys` or `identities`"
+        );
+        if !self.identities.is_empty() {
+            ensure!(
+                !self.fulcio_roots.is_empty() && !self.rekor_keys.is_empty(),
+                "[signatures] must list `fulcio_roots` and `rekor_keys` to trust `identities`"
+            );
+        }
+        if self.require_rekor {
+            ensure!(
+                !self.rekor_keys.is_empty(),
+                "[signatures] must list `rekor_keys` to `require_rekor`"
+            );
+        }
+        Ok(())
+    }
+
+    /// Resolves relative paths against the given directory, which should be
+    /// that of the runtime config file.
+    pub fn resolve_paths(&mut self, base: &Path) {
+        for path in self
+            .keys
+            .iter_mut()
+            .chain(&mut self.fulcio_roots)
+            .chain(&mut self.rekor_keys)
+        {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        }
+    }
+
+    /// Reads the keys and certificates of the policy.
+    pub(crate) fn load(&self) -> Result<SignatureVerifier> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|path| Ok((path.clone(), read_public_key(path)?)))
+            .collect::<Result<_>>()?;
+        let fulcio_roots = self
+            .fulcio_roots
+            .iter()
+            .map(|path| {
+                let pem = read_pem(path)?;
+                Certificate::from_pem(pem)
+                    .with_context(|| format!("invalid certificate {}", quoted_path(path)))
+            })
+            .collect::<Result<_>>()?;
+        let rekor_keys = self
+            .rekor_keys
+            .iter()
+            .map(|path| read_public_key(path))
+            .collect::<Result<_>>()?;
+        Ok(SignatureVerifier {
+            keys,
+            identities: self.identities.clone(),
+            fulcio_roots,
+            rekor_keys,
+            require_rekor: self.require_rekor,
+        })
+    }
+}
+
+fn read_pem(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("cannot read {}", quoted_path(path)))
+}
+
+fn read_public_key(path: &Path) -> Result<PublicKey> {
+    PublicKey::from_pem(&read_pem(path)?)
+        .with_context(|| format!("invalid public key {}", quoted_path(path)))
+}
+
+/// A layer of a signature artifact.
+pub(crate) struct SignatureLayer {
+    /// The signed simple signing payload.
+    pub payload: Vec<u8>,
+    /// The annotations holding the signature and any certificate and
+    /// transparency log bundle.
+    pub annotations: HashMap<String, String>,
+}
+
+/// A trust policy, with its keys and certificates loaded.
+pub(crate) struct SignatureVerifier {
+    keys: Vec<(PathBuf, PublicKey)>,
+    identities: Vec<TrustedIdentity>,
+    fulcio_roots: Vec<Certificate>,
+    rekor_keys: Vec<PublicKey>,
+    require_rekor: bool,
+}
+
+impl SignatureVerifier {
+    /// Checks that at least one of the given signatures is of the given
+    /// manifest, as pulled, and satisfies the policy. The manifest's digest is
+    /// computed here rather than taken from the registry, so that a signature
+    /// can only vouch for the exact manifest that is used.
+    pub fn verify_manifest(&self, manifest: &[u8], signatures: &[SignatureLayer]) -> Result<()> {
+        let digest = format!("sha256:{}", sha256::hex_digest_from_bytes(manifest));
+        self.verify(&digest, signatures)
+    }
+
+    /// Checks that at least one of the given signatures of the manifest with
+    /// the given digest satisfies the policy.
+    pub fn verify(&self, digest: &str, signatures: &[SignatureLayer]) -> Result<()> {
+        ensure!(!signatures.is_empty(), "the app has no signatures");
+        let mut rejections = vec![];
+        for (index, signature) in signatures.iter().enumerate() {
+            match self.verify_signature(digest, signature) {
+                Ok(signer) => {
+                    tracing::info!("Verified signature of {digest} by {signer}");
+                    return Ok(());
+                }
+                Err(e) => rejections.push(format!("signature {}: {e:#}", index + 1)),
+            }
+        }
+        bail!(
+            "no signature of the app satisfies the trust policy:\n{}",
+            rejections.join("\n")
+        )
+    }
+
+    /// Verifies a signature, returning a description of its signer.
+    fn verify_signature(&self, digest: &str, layer: &SignatureLayer) -> Result<String> {
+        check_payload(digest, &layer.payload)?;
+        let signature = layer
+            .annotations
+            .get(SIGNATURE_ANNOTATION)
+            .context("the signature layer has no signature")?;
+        let signature = BASE64
+            .decode(signature)
+            .context("the signature is not valid base64")?;
+
+        // When the signature was recorded in a trusted transparency log
+        let logged_at = match layer.annotations.get(BUNDLE_ANNOTATION) {
+            Some(bundle) if !self.rekor_keys.is_empty() => Some(
+                RekorBundle::parse(bundle)?.verify(&self.rekor_keys, &layer.payload, &signature)?,
+            ),
+            _ => None,
+        };
+        if self.require_rekor {
+            ensure!(
+                logged_at.is_some(),
+                "the signature is not recorded in a transparency log"
+            );
+        }
+
+        if let Some((path, _)) = self
+            .keys
+            .iter()
+            .find(|(_, key)| key.verify(&layer.payload, &signature).is_ok())
+        {
+            return Ok(format!("key {}", quoted_path(path)));
+        }
+        match layer.annotations.get(CERTIFICATE_ANNOTATION) {
+            Some(certificate) => self.verify_keyless(layer, certificate, &signature, logged_at),
+            None => bail!("the signature is not made by a trusted key"),
+        }
+    }
+
+    fn verify_keyless(
+        &self,
+        layer: &SignatureLayer,
+        certificate: &str,
+        signature: &[u8],
+        logged_at: Option<u64>,
+    ) -> Result<String> {
+        ensure!(
+            !self.identities.is_empty(),
+            "the signature is keyless, and no keyless identities are trusted"
+        );
+        let logged_at = logged_at
+            .context("the keyless signature is not recorded in a trusted transparency log")?;
+
+        let certificate = SigningCertificate::parse(
+            certificate,
+            layer.annotations.get(CHAIN_ANNOTATION).map(String::as_str),
+        )?;
+        certificate.verify_chain(&self.fulcio_roots, logged_at)?;
+        certificate
+            .public_key()?
+            .verify(&layer.payload, signature)
+            .context("the signature does not match its certificate")?;
+
+        let (issuer, subject) = certificate.identity()?;
+        ensure!(
+            self.identities
+                .iter()
+                .any(|identity| identity.issuer == issuer && identity.subject == subject),
+            "the signer {subject} (issued by {issuer}) is not a trusted identity"
+        );
+        Ok(format!("{subject} (issued by {issuer})"))
+    }
+}
+
+#[derive(Deserialize)]
+struct SimpleSigning {
+    critical: SimpleSigningCritical,
+}
+
+#[derive(Deserialize)]
+struct SimpleSigningCritical {
+    image: SimpleSigningImage,
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+#[derive(Deserialize)]
+struct SimpleSigningImage {
+    #[serde(rename = "docker-manifest-digest")]
+    docker_manifest_digest: String,
+}
+
+/// Checks that a simple signing payload is for the manifest with the given
+/// digest.
+fn check_payload(digest: &str, payload: &[u8]) -> Result<()> {
+    let payload: SimpleSigning =
+        serde_json::from_slice(payload).context("the signed payload is not a cosign payload")?;
+    ensure!(
+        payload.critical.ty == SIMPLE_SIGNING_TYPE,
+        "unsupported signed payload type {:?}",
+        payload.critical.ty
+    );
+    let signed_digest = payload.critical.image.docker_manifest_digest;
+    ensure!(
+        signed_digest == digest,
+        "the signature is for manifest {signed_digest}"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use p256::ecdsa::{signature::Signer, DerSignature, SigningKey};
+    use p256::pkcs8::{EncodePublicKey, LineEnding};
+
+    use super::certificate::test::{ChainOptions, TestChain, ISSUER, SUBJECT};
+    use super::*;
+
+    const DIGEST: &str = "sha256:4e07408562bedb8b60ce05c1decfe3ad16b72230967de01f640b7e4729b49fce";
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn payload(digest: &str) -> Vec<u8> {
+        payload_for("ghcr.io/spin/app", digest)
+    }
+
+    fn payload_for(repository: &str, digest: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "critical": {
+                "identity": { "docker-reference": repository },
+                "image": { "docker-manifest-digest": digest },
+                "type": SIMPLE_SIGNING_TYPE,
+            },
+            "optional": null,
+        }))
+        .unwrap()
+    }
+
+    fn signature_layer(key: &SigningKey, payload: Vec<u8>) -> SignatureLayer {
+        let signature: DerSignature = key.sign(&payload);
+        let annotations = [(
+            SIGNATURE_ANNOTATION.to_owned(),
+            BASE64.encode(signature.as_bytes()),
+        )];
+        SignatureLayer {
+            payload,
+            annotations: annotations.into_iter().collect(),
+        }
+    }
+
+    fn policy(dir: &Path, toml: toml::Table) -> Result<SignatureVerifier> {
+        let mut policy = TrustPolicy::from_toml_value(&toml.into())?;
+        policy.resolve_paths(dir);
+        policy.load()
+    }
+
+    fn write_public_key(dir: &Path, name: &str, key: &SigningKey) -> Result<()> {
+        let pem = key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        std::fs::write(dir.join(name), pem)?;
+        Ok(())
+    }
+
+    #[test]
+    fn policies_must_trust_someone() {
+        let invalid = [
+            toml::toml! {
+                require_rekor = false
+            },
+            toml::toml! {
+                identities = [{ issuer = "https://accounts.google.com", subject = "me@example.com" }]
+            },
+            toml::toml! {
+                keys = ["cosign.pub"]
+                require_rekor = true
+            },
+        ];
+        for table in invalid {
+            TrustPolicy::from_toml_value(&table.into()).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn signatures_by_trusted_keys_are_accepted() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_public_key(dir.path(), "cosign.pub", &signing_key(1))?;
+        let verifier = policy(dir.path(), toml::toml! { keys = ["cosign.pub"] })?;
+
+        let trusted = signature_layer(&signing_key(1), payload(DIGEST));
+        let untrusted = signature_layer(&signing_key(2), payload(DIGEST));
+        verifier.verify(DIGEST, &[untrusted, trusted])?;
+        Ok(())
+    }
+
+    #[test]
+    fn signatures_must_be_trusted_and_for_the_app() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_public_key(dir.path(), "cosign.pub", &signing_key(1))?;
+        let verifier = policy(dir.path(), toml::toml! { keys = ["cosign.pub"] })?;
+
+        verifier.verify(DIGEST, &[]).unwrap_err();
+
+        let untrusted = signature_layer(&signing_key(2), payload(DIGEST));
+        let err = verifier.verify(DIGEST, &[untrusted]).unwrap_err();
+        assert!(
+            err.to_string().contains("not made by a trusted key"),
+            "{err}"
+        );
+
+        let other_app = signature_layer(&signing_key(1), payload("sha256:0000"));
+        let err = verifier.verify(DIGEST, &[other_app]).unwrap_err();
+        assert!(
+            err.to_string().contains("for manifest sha256:0000"),
+            "{err}"
+        );
+
+        let mut tampered = signature_layer(&signing_key(1), payload(DIGEST));
+        tampered.payload = payload_for("ghcr.io/mallory/app", DIGEST);
+        verifier.verify(DIGEST, &[tampered]).unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn signatures_must_be_of_the_pulled_manifest() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_public_key(dir.path(), "cosign.pub", &signing_key(1))?;
+        let verifier = policy(dir.path(), toml::toml! { keys = ["cosign.pub"] })?;
+
+        let manifest = br#"{"schemaVersion":2,"layers":[]}"#;
+        let digest = format!("sha256:{}", sha256::hex_digest_from_bytes(manifest));
+        let signature = || signature_layer(&signing_key(1), payload(&digest));
+        verifier.verify_manifest(manifest, &[signature()])?;
+
+        // A registry which returns another manifest, claiming the signed digest
+        let mismatched = br#"{"schemaVersion":2,"layers":[{}]}"#;
+        let err = verifier
+            .verify_manifest(mismatched, &[signature()])
+            .unwrap_err();
+        assert!(
+            err.to_string().contains(&format!("for manifest {digest}")),
+            "{err}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn signatures_must_be_logged_if_required() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_public_key(dir.path(), "cosign.pub", &signing_key(1))?;
+        write_public_key(dir.path(), "rekor.pub", &signing_key(3))?;
+        let verifier = policy(
+            dir.path(),
+            toml::toml! {
+                keys = ["cosign.pub"]
+                rekor_keys = ["rekor.pub"]
+                require_rekor = true
+            },
+        )?;
+
+        let mut layer = signature_layer(&signing_key(1), payload(DIGEST));
+        let err = verifier
+            .verify(DIGEST, std::slice::from_ref(&layer))
+            .unwrap_err();
+        assert!(err.to_string().contains("not recorded"), "{err}");
+
+        let signature = BASE64.decode(&layer.annotations[SIGNATURE_ANNOTATION])?;
+        let bundle = rekor::test::bundle(&signing_key(3), &layer.payload, &signature);
+        layer
+            .annotations
+            .insert(BUNDLE_ANNOTATION.to_owned(), bundle);
+        verifier.verify(DIGEST, &[layer])?;
+        Ok(())
+    }
+
+    #[test]
+    fn keyless_signatures_by_trusted_identities_are_accepted() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let chain = TestChain::issue(&signing_key(1), ChainOptions::default());
+        std::fs::write(dir.path().join("fulcio.crt.pem"), chain.root_pem())?;
+        write_public_key(dir.path(), "rekor.pub", &signing_key(3))?;
+        let keyless_policy = |subject: &str| {
+            policy(
+                dir.path(),
+                toml::toml! {
+                    identities = [{ issuer = ISSUER, subject = subject }]
+                    fulcio_roots = ["fulcio.crt.pem"]
+                    rekor_keys = ["rekor.pub"]
+                },
+            )
+        };
+
+        let mut layer = signature_layer(&signing_key(1), payload(DIGEST));
+        layer
+            .annotations
+            .insert(CERTIFICATE_ANNOTATION.to_owned(), chain.leaf_pem());
+        layer
+            .annotations
+            .insert(CHAIN_ANNOTATION.to_owned(), chain.chain_pem());
+        let err = keyless_policy(SUBJECT)?
+            .verify(DIGEST, std::slice::from_ref(&layer))
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("not recorded in a trusted transparency log"),
+            "{err}"
+        );
+
+        let signature = BASE64.decode(&layer.annotations[SIGNATURE_ANNOTATION])?;
+        let bundle = rekor::test::bundle(&signing_key(3), &layer.payload, &signature);
+        layer
+            .annotations
+            .insert(BUNDLE_ANNOTATION.to_owned(), bundle);
+        keyless_policy(SUBJECT)?.verify(DIGEST, std::slice::from_ref(&layer))?;
+
+        let err = keyless_policy("mallory@example.com")?
+            .verify(DIGEST, &[layer])
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("is not a trusted identity"),
+            "{err}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,486 @@
+//! The public keys and certificates by which signatures are verified.
+
+use anyhow::{bail, ensure, Context, Result};
+use p256::pkcs8::DecodePublicKey;
+use x509_cert::der::oid::{AssociatedOid, ObjectIdentifier};
+use x509_cert::der::{Decode, DecodePem, Encode};
+use x509_cert::ext::pkix::{name::GeneralName, BasicConstraints, ExtendedKeyUsage, SubjectAltName};
+use x509_cert::Certificate;
+
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const ECDSA_WITH_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.3");
+
+/// The extended key usage of certificates for code signing.
+const CODE_SIGNING: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.3.3");
+
+/// The extension of Fulcio certificates naming the OIDC issuer of their
+/// identity, as raw UTF-8.
+const FULCIO_ISSUER_V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.1");
+/// The extension of Fulcio certificates naming the OIDC issuer of their
+/// identity, as a DER-encoded UTF8String.
+const FULCIO_ISSUER_V2: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.57264.1.8");
+
+/// An ECDSA public key. Signatures by P-256 keys are of SHA-256 digests,
+/// and by P-384 keys of SHA-384 digests.
+pub(crate) enum PublicKey {
+    P256(p256::ecdsa::VerifyingKey),
+    P384(p384::ecdsa::VerifyingKey),
+}
+
+impl PublicKey {
+    /// Parses a public key in PEM format.
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        if let Ok(key) = p256::ecdsa::VerifyingKey::from_public_key_pem(pem) {
+            return Ok(Self::P256(key));
+        }
+        if let Ok(key) = p384::ecdsa::VerifyingKey::from_public_key_pem(pem) {
+            return Ok(Self::P384(key));
+        }
+        bail!("unsupported public key: only ECDSA P-256 and P-384 keys are supported")
+    }
+
+    fn from_certificate(certificate: &Certificate) -> Result<Self> {
+        let der = certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()?;
+        if let Ok(key) = p256::ecdsa::VerifyingKey::from_public_key_der(&der) {
+            return Ok(Self::P256(key));
+        }
+        if let Ok(key) = p384::ecdsa::VerifyingKey::from_public_key_der(&der) {
+            return Ok(Self::P384(key));
+        }
+        bail!("unsupported certificate key: only ECDSA P-256 and P-384 keys are supported")
+    }
+
+    /// Verifies a DER-encoded signature of the message.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<()> {
+        use p256::ecdsa::signature::Verifier;
+        match self {
+            Self::P256(key) => {
+                key.verify(message, &p256::ecdsa::Signature::from_der(signature)?)?
+            }
+            Self::P384(key) => {
+                key.verify(message, &p384::ecdsa::Signature::from_der(signature)?)?
+            }
+        }
+        Ok(())
+    }
+
+    /// The algorithm of the key's signatures in certificates.
+    fn signature_algorithm(&self) -> ObjectIdentifier {
+        match self {
+            Self::P256(_) => ECDSA_WITH_SHA256,
+            Self::P384(_) => ECDSA_WITH_SHA384,
+        }
+    }
+}
+
+/// The certificate of a keyless signature, with the chain of certificates
+/// which issued it.
+pub(crate) struct SigningCertificate {
+    leaf: Certificate,
+    chain: Vec<Certificate>,
+}
+
+impl SigningCertificate {
+    /// Parses a certificate, and any chain of its issuers, in PEM format.
+    pub fn parse(leaf: &str, chain: Option<&str>) -> Result<Self> {
+        let leaf = Certificate::from_pem(leaf).context("invalid signing certificate")?;
+        let chain = match chain {
+            Some(chain) => Certificate::load_pem_chain(chain.as_bytes())
+                .context("invalid signing certificate chain")?,
+            None => vec![],
+        };
+        Ok(Self { leaf, chain })
+    }
+
+    /// The key of the certificate's subject.
+    pub fn public_key(&self) -> Result<PublicKey> {
+        PublicKey::from_certificate(&self.leaf)
+    }
+
+    /// Checks that the certificate is for code signing, that it was issued
+    /// by one of the given roots, directly or through its chain, and that
+    /// each certificate on the way was valid at the given time, in seconds
+    /// since the Unix epoch.
+    pub fn verify_chain(&self, roots: &[Certificate], time: u64) -> Result<()> {
+        ensure!(
+            is_for_code_signing(&self.leaf),
+            "the signing certificate is not for code signing"
+        );
+        let mut certificate = &self.leaf;
+        let mut chain = self.chain.iter();
+        loop {
+            ensure!(
+                is_valid_at(certificate, time),
+                "certificate {} was not valid when the signature was made",
+                certificate.tbs_certificate.subject
+            );
+            if roots
+                .iter()
+                .any(|root| verify_issued_by(certificate, root).is_ok())
+            {
+                return Ok(());
+            }
+            let Some(issuer) = chain.next() else {
+                bail!("the signing certificate is not issued by a trusted certificate authority");
+            };
+            ensure!(
+                is_ca(issuer),
+                "certificate {} is not a certificate authority",
+                issuer.tbs_certificate.subject
+            );
+            verify_issued_by(certificate, issuer).with_context(|| {
+                format!(
+                    "certificate {} is not issued by the next in its chain",
+                    certificate.tbs_certificate.subject
+                )
+            })?;
+            certificate = issuer;
+        }
+    }
+
+    /// The identity the certificate was issued for: the OIDC issuer which
+    /// vouched for it, and its email address or URI.
+    pub fn identity(&self) -> Result<(String, String)> {
+        let issuer = if let Some(value) = extension(&self.leaf, FULCIO_ISSUER_V2) {
+            String::from_der(value).context("invalid OIDC issuer extension")?
+        } else if let Some(value) = extension(&self.leaf, FULCIO_ISSUER_V1) {
+            std::str::from_utf8(value)
+                .context("invalid OIDC issuer extension")?
+                .to_owned()
+        } else {
+            bail!("the signing certificate names no OIDC issuer");
+        };
+
+        let names = extension(&self.leaf, SubjectAltName::OID)
+            .context("the signing certificate names no subject")?;
+        let names = SubjectAltName::from_der(names).context("invalid subject alternative name")?;
+        let subject = names
+            .0
+            .iter()
+            .find_map(|name| match name {
+                GeneralName::Rfc822Name(email) => Some(email.to_string()),
+                GeneralName::UniformResourceIdentifier(uri) => Some(uri.to_string()),
+                _ => None,
+            })
+            .context("the signing certificate names no email address or URI")?;
+        Ok((issuer, subject))
+    }
+}
+
+fn extension(certificate: &Certificate, oid: ObjectIdentifier) -> Option<&[u8]> {
+    certificate
+        .tbs_certificate
+        .extensions
+        .as_deref()?
+        .iter()
+        .find(|extension| extension.extn_id == oid)
+        .map(|extension| extension.extn_value.as_bytes())
+}
+
+fn is_valid_at(certificate: &Certificate, time: u64) -> bool {
+    let validity = &certificate.tbs_certificate.validity;
+    validity.not_before.to_unix_duration().as_secs() <= time
+        && time <= validity.not_after.to_unix_duration().as_secs()
+}
+
+fn is_for_code_signing(certificate: &Certificate) -> bool {
+    extension(certificate, ExtendedKeyUsage::OID)
+        .and_then(|value| ExtendedKeyUsage::from_der(value).ok())
+        .is_some_and(|usage| usage.0.contains(&CODE_SIGNING))
+}
+
+fn is_ca(certificate: &Certificate) -> bool {
+    extension(certificate, BasicConstraints::OID)
+        .and_then(|value| BasicConstraints::from_der(value).ok())
+        .is_some_and(|constraints| constraints.ca)
+}
+
+fn verify_issued_by(certificate: &Certificate, issuer: &Certificate) -> Result<()> {
+    ensure!(
+        certificate.tbs_certificate.issuer == issuer.tbs_certificate.subject,
+        "issuer names differ"
+    );
+    let key = PublicKey::from_certificate(issuer)?;
+    ensure!(
+        certificate.signature_algorithm.oid == key.signature_algorithm(),
+        "unsupported signature algorithm {}",
+        certificate.signature_algorithm.oid
+    );
+    let signature = certificate
+        .signature
+        .as_bytes()
+        .context("malformed certificate signature")?;
+    key.verify(&certificate.tbs_certificate.to_der()?, signature)
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use p256::ecdsa::{DerSignature, SigningKey};
+    use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+    use x509_cert::der::asn1::{Ia5String, UtcTime, Utf8StringRef};
+    use x509_cert::der::pem::LineEnding;
+    use x509_cert::der::{EncodePem, Length, Writer};
+    use x509_cert::ext::AsExtension;
+    use x509_cert::name::Name;
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::spki::SubjectPublicKeyInfoOwned;
+    use x509_cert::time::{Time, Validity};
+
+    use super::*;
+
+    /// When the test transparency log records signatures.
+    pub const LOGGED_AT: u64 = 1_700_000_000;
+    pub const ISSUER: &str = "https://token.actions.githubusercontent.com";
+    pub const SUBJECT: &str =
+        "https://github.com/acme/app/.github/workflows/release.yml@refs/heads/main";
+
+    /// How a test chain's certificates are issued.
+    pub struct ChainOptions {
+        /// Whether the intermediate certificate is of a certificate authority.
+        pub intermediate_is_ca: bool,
+        /// When the leaf certificate is valid, in seconds since the Unix epoch.
+        pub leaf_validity: (u64, u64),
+        /// Whether the leaf certificate is for code signing.
+        pub code_signing: bool,
+    }
+
+    impl Default for ChainOptions {
+        fn default() -> Self {
+            Self {
+                intermediate_is_ca: true,
+                leaf_validity: (LOGGED_AT - 300, LOGGED_AT + 300),
+                code_signing: true,
+            }
+        }
+    }
+
+    /// A Fulcio-like chain: a root, an intermediate, and a leaf certificate
+    /// for the given key, with the identity of [`ISSUER`] and [`SUBJECT`].
+    pub struct TestChain {
+        pub root: Certificate,
+        pub intermediate: Certificate,
+        pub leaf: Certificate,
+    }
+
+    impl TestChain {
+        pub fn issue(leaf_key: &SigningKey, options: ChainOptions) -> Self {
+            let root_key = SigningKey::from_slice(&[10; 32]).unwrap();
+            let intermediate_key = SigningKey::from_slice(&[11; 32]).unwrap();
+            let (root_name, intermediate_name) = (name("fulcio-root"), name("fulcio-intermediate"));
+            let ca_validity = (LOGGED_AT - 86_400, LOGGED_AT + 86_400);
+
+            let root = issue(
+                Profile::Root,
+                "fulcio-root",
+                &root_key,
+                &root_key,
+                ca_validity,
+                |_| {},
+            );
+            let intermediate_profile = if options.intermediate_is_ca {
+                Profile::SubCA {
+                    issuer: root_name,
+                    path_len_constraint: Some(0),
+                }
+            } else {
+                Profile::Leaf {
+                    issuer: root_name,
+                    enable_key_agreement: false,
+                    enable_key_encipherment: false,
+                }
+            };
+            let intermediate = issue(
+                intermediate_profile,
+                "fulcio-intermediate",
+                &intermediate_key,
+                &root_key,
+                ca_validity,
+                |_| {},
+            );
+            let leaf_profile = Profile::Leaf {
+                issuer: intermediate_name,
+                enable_key_agreement: false,
+                enable_key_encipherment: false,
+            };
+            let leaf = issue(
+                leaf_profile,
+                "",
+                leaf_key,
+                &intermediate_key,
+                options.leaf_validity,
+                |builder| {
+                    let subject = Ia5String::new(SUBJECT).unwrap();
+                    let names =
+                        SubjectAltName(vec![GeneralName::UniformResourceIdentifier(subject)]);
+                    builder.add_extension(&names).unwrap();
+                    builder.add_extension(&FulcioIssuer(ISSUER)).unwrap();
+                    if options.code_signing {
+                        let usage = ExtendedKeyUsage(vec![CODE_SIGNING]);
+                        builder.add_extension(&usage).unwrap();
+                    }
+                },
+            );
+            Self {
+                root,
+                intermediate,
+                leaf,
+            }
+        }
+
+        pub fn signing_certificate(&self) -> SigningCertificate {
+            SigningCertificate::parse(&self.leaf_pem(), Some(&self.chain_pem())).unwrap()
+        }
+
+        pub fn root_pem(&self) -> String {
+            self.root.to_pem(LineEnding::LF).unwrap()
+        }
+
+        pub fn leaf_pem(&self) -> String {
+            self.leaf.to_pem(LineEnding::LF).unwrap()
+        }
+
+        /// The chain of issuers of the leaf, as cosign annotates it.
+        pub fn chain_pem(&self) -> String {
+            [&self.intermediate, &self.root]
+                .map(|certificate| certificate.to_pem(LineEnding::LF).unwrap())
+                .concat()
+        }
+    }
+
+    fn name(common_name: &str) -> Name {
+        Name::from_str(&format!("CN={common_name},O=sigstore.dev")).unwrap()
+    }
+
+    fn issue(
+        profile: Profile,
+        common_name: &str,
+        key: &SigningKey,
+        issuer_key: &SigningKey,
+        (not_before, not_after): (u64, u64),
+        extend: impl FnOnce(&mut CertificateBuilder<SigningKey>),
+    ) -> Certificate {
+        let time =
+            |secs| Time::UtcTime(UtcTime::from_unix_duration(Duration::from_secs(secs)).unwrap());
+        let validity = Validity {
+            not_before: time(not_before),
+            not_after: time(not_after),
+        };
+        let subject = if common_name.is_empty() {
+            Name::default()
+        } else {
+            name(common_name)
+        };
+        let mut builder = CertificateBuilder::new(
+            profile,
+            SerialNumber::from(u32::from(key.to_bytes()[0])),
+            validity,
+            subject,
+            SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).unwrap(),
+            issuer_key,
+        )
+        .unwrap();
+        extend(&mut builder);
+        builder.build::<DerSignature>().unwrap()
+    }
+
+    /// The Fulcio extension naming the OIDC issuer, as a UTF8String.
+    struct FulcioIssuer(&'static str);
+
+    impl AssociatedOid for FulcioIssuer {
+        const OID: ObjectIdentifier = FULCIO_ISSUER_V2;
+    }
+
+    impl Encode for FulcioIssuer {
+        fn encoded_len(&self) -> x509_cert::der::Result<Length> {
+            Utf8StringRef::new(self.0)?.encoded_len()
+        }
+
+        fn encode(&self, writer: &mut impl Writer) -> x509_cert::der::Result<()> {
+            Utf8StringRef::new(self.0)?.encode(writer)
+        }
+    }
+
+    impl AsExtension for FulcioIssuer {
+        fn critical(&self, _: &Name, _: &[x509_cert::ext::Extension]) -> bool {
+            false
+        }
+    }
+
+    fn leaf_key() -> SigningKey {
+        SigningKey::from_slice(&[12; 32]).unwrap()
+    }
+
+    #[test]
+    fn chains_to_trusted_roots_are_accepted() -> Result<()> {
+        let chain = TestChain::issue(&leaf_key(), ChainOptions::default());
+        let certificate = chain.signing_certificate();
+        certificate.verify_chain(std::slice::from_ref(&chain.root), LOGGED_AT)?;
+        assert_eq!(
+            (ISSUER.to_owned(), SUBJECT.to_owned()),
+            certificate.identity()?
+        );
+
+        let err = certificate
+            .verify_chain(std::slice::from_ref(&chain.leaf), LOGGED_AT)
+            .unwrap_err();
+        assert!(err.to_string().contains("not issued by a trusted"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn issuers_must_be_certificate_authorities() {
+        let chain = TestChain::issue(
+            &leaf_key(),
+            ChainOptions {
+                intermediate_is_ca: false,
+                ..Default::default()
+            },
+        );
+        let err = chain
+            .signing_certificate()
+            .verify_chain(std::slice::from_ref(&chain.root), LOGGED_AT)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("is not a certificate authority"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn certificates_must_be_valid_when_logged() {
+        let chain = TestChain::issue(
+            &leaf_key(),
+            ChainOptions {
+                leaf_validity: (LOGGED_AT - 1200, LOGGED_AT - 600),
+                ..Default::default()
+            },
+        );
+        let err = chain
+            .signing_certificate()
+            .verify_chain(std::slice::from_ref(&chain.root), LOGGED_AT)
+            .unwrap_err();
+        assert!(err.to_string().contains("was not valid"), "{err}");
+    }
+
+    #[test]
+    fn certificates_must_be_for_code_signing() {
+        let chain = TestChain::issue(
+            &leaf_key(),
+            ChainOptions {
+                code_signing: false,
+                ..Default::default()
+            },
+        );
+        let err = chain
+            .signing_certificate()
+            .verify_chain(std::slice::from_ref(&chain.root), LOGGED_AT)
+            .unwrap_err();
+        assert!(err.to_string().contains("not for code signing"), "{err}");
+    }
+}
@@ -0,0 +1,140 @@
+//! Proofs that signatures were recorded in a Rekor transparency log.
+
+use anyhow::{ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use spin_common::sha256;
+
+use super::certificate::PublicKey;
+
+/// The bundle with which cosign annotates a signature recorded in a
+/// transparency log: the log's entry for the signature, and the log's
+/// signed promise to include it.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct RekorBundle {
+    signed_entry_timestamp: String,
+    payload: BundlePayload,
+}
+
+/// The log entry of a bundle, which the log signs in canonical JSON form:
+/// compact, with the fields in this order.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundlePayload {
+    body: String,
+    integrated_time: i64,
+    #[serde(rename = "logID")]
+    log_id: String,
+    log_index: i64,
+}
+
+#[derive(Deserialize)]
+struct HashedRekord {
+    kind: String,
+    spec: HashedRekordSpec,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordSpec {
+    data: HashedRekordData,
+    signature: HashedRekordSignature,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordData {
+    hash: HashedRekordHash,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordHash {
+    algorithm: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct HashedRekordSignature {
+    content: String,
+}
+
+impl RekorBundle {
+    /// Parses the JSON of a bundle.
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("invalid transparency log bundle")
+    }
+
+    /// Checks that the bundle is signed by one of the logs with the given
+    /// keys, and records the given signature of the given payload. Returns
+    /// when the log recorded the signature, in seconds since the Unix epoch.
+    pub fn verify(&self, log_keys: &[PublicKey], payload: &[u8], signature: &[u8]) -> Result<u64> {
+        let signed = serde_json::to_vec(&self.payload)?;
+        let timestamp = BASE64
+            .decode(&self.signed_entry_timestamp)
+            .context("invalid transparency log bundle timestamp")?;
+        ensure!(
+            log_keys
+                .iter()
+                .any(|key| key.verify(&signed, &timestamp).is_ok()),
+            "the transparency log bundle is not signed by a trusted log"
+        );
+
+        let entry = BASE64
+            .decode(&self.payload.body)
+            .context("invalid transparency log entry")?;
+        let entry: HashedRekord =
+            serde_json::from_slice(&entry).context("invalid transparency log entry")?;
+        ensure!(
+            entry.kind == "hashedrekord",
+            "unsupported transparency log entry kind {:?}",
+            entry.kind
+        );
+        let hash = &entry.spec.data.hash;
+        ensure!(
+            hash.algorithm == "sha256" && hash.value == sha256::hex_digest_from_bytes(payload),
+            "the transparency log entry is for a different payload"
+        );
+        let logged_signature = BASE64
+            .decode(&entry.spec.signature.content)
+            .context("invalid transparency log entry signature")?;
+        ensure!(
+            logged_signature == signature,
+            "the transparency log entry is for a different signature"
+        );
+        self.payload
+            .integrated_time
+            .try_into()
+            .context("invalid transparency log entry time")
+    }
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use p256::ecdsa::{signature::Signer, DerSignature, SigningKey};
+
+    use super::*;
+
+    /// A bundle for the given signature of the given payload, signed by a log
+    /// with the given key.
+    pub fn bundle(log_key: &SigningKey, payload: &[u8], signature: &[u8]) -> String {
+        let entry = serde_json::json!({
+            "apiVersion": "0.0.1",
+            "kind": "hashedrekord",
+            "spec": {
+                "data": { "hash": { "algorithm": "sha256", "value": sha256::hex_digest_from_bytes(payload) } },
+                "signature": { "content": BASE64.encode(signature) },
+            },
+        });
+        let payload = BundlePayload {
+            body: BASE64.encode(serde_json::to_vec(&entry).unwrap()),
+            integrated_time: 1_700_000_000,
+            log_id: "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d".into(),
+            log_index: 42,
+        };
+        let timestamp: DerSignature = log_key.sign(&serde_json::to_vec(&payload).unwrap());
+        serde_json::json!({
+            "SignedEntryTimestamp": BASE64.encode(timestamp.as_bytes()),
+            "Payload": payload,
+        })
+        .to_string()
+    }
+}
@@ -1,5 +1,8 @@
-use std::ops::Range;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, ensure, Context as _};
 use futures_util::future::{BoxFuture, Shared};
@@ -9,14 +12,23 @@ pub const SERVICE_CHAINING_DOMAIN: &str = "spin.internal";
 /// The domain suffix used for service chaining.
 pub const SERVICE_CHAINING_DOMAIN_SUFFIX: &str = ".spin.internal";
 
+/// How long the addresses an allowed host name resolves to are reused for
+/// checking socket addresses.
+const RESOLVED_ADDRESSES_TTL: Duration = Duration::from_secs(30);
+
 /// An easily cloneable, shared, boxed future of result
 pub type SharedFutureResult<T> = Shared<BoxFuture<'static, Result<Arc<T>, Arc<anyhow::Error>>>>;
 
+/// Allowed host names, with when they were resolved and the addresses they
+/// resolved to.
+type ResolvedAddresses = HashMap<String, (Instant, Arc<[IpAddr]>)>;
+
 /// A check for whether a URL is allowed by the outbound networking configuration.
 #[derive(Clone)]
 pub struct OutboundAllowedHosts {
     allowed_hosts_future: SharedFutureResult<AllowedHostsConfig>,
     disallowed_host_handler: Option<Arc<dyn DisallowedHostHandler>>,
+    resolved_addresses: Arc<Mutex<ResolvedAddresses>>,
 }
 
 impl OutboundAllowedHosts {
@@ -28,6 +40,7 @@ impl OutboundAllowedHosts {
         Self {
             allowed_hosts_future,
             disallowed_host_handler,
+            resolved_addresses: Default::default(),
         }
     }
 
@@ -56,6 +69,31 @@ impl OutboundAllowedHosts {
         Ok(is_allowed)
     }
 
+    /// Checks a socket address against allowed hosts. An address which isn't
+    /// allowed itself is allowed if a host name allowed for its scheme and
+    /// port resolves to it, so that a component may connect to the addresses
+    /// it looks up for an allowed host. Wildcard host names can't be resolved,
+    /// so don't allow any addresses.
+    ///
+    /// Calls the [`DisallowedHostHandler`] if set and the address is disallowed.
+    pub async fn check_socket_addr(&self, addr: SocketAddr, scheme: &str) -> anyhow::Result<bool> {
+        tracing::debug!("Checking outbound socket connection to '{addr}'");
+        let url = OutboundUrl::parse(addr.to_string(), scheme)?;
+        let allowed_hosts = self.resolve().await?;
+        if allowed_hosts.allows(&url) {
+            return Ok(true);
+        }
+        let ip = addr.ip().to_canonical();
+        for domain in allowed_hosts.allowed_domains(scheme, addr.port()) {
+            if self.resolve_domain(domain).await.contains(&ip) {
+                return Ok(true);
+            }
+        }
+        tracing::debug!("Disallowed outbound socket connection to '{addr}'");
+        self.report_disallowed_host(scheme, &url.authority());
+        Ok(false)
+    }
+
     /// Checks if allowed hosts permit relative requests
     ///
     /// Calls the [`DisallowedHostHandler`] if set and relative requests are
@@ -81,6 +119,33 @@ impl OutboundAllowedHosts {
             .map_err(anyhow::Error::msg)
     }
 
+    /// The addresses an allowed host name resolves to, reusing recent
+    /// lookups. A name which can't be resolved has no addresses.
+    async fn resolve_domain(&self, domain: &str) -> Arc<[IpAddr]> {
+        let cached = self
+            .resolved_addresses
+            .lock()
+            .unwrap()
+            .get(domain)
+            .filter(|(resolved_at, _)| resolved_at.elapsed() < RESOLVED_ADDRESSES_TTL)
+            .map(|(_, addresses)| addresses.clone());
+        if let Some(addresses) = cached {
+            return addresses;
+        }
+        let addresses: Arc<[IpAddr]> = match tokio::net::lookup_host((domain, 0)).await {
+            Ok(addresses) => addresses.map(|addr| addr.ip().to_canonical()).collect(),
+            Err(err) => {
+                tracing::debug!(%err, "Failed to resolve allowed host '{domain}'");
+                Arc::new([])
+            }
+        };
+        self.resolved_addresses
+            .lock()
+            .unwrap()
+            .insert(domain.to_owned(), (Instant::now(), addresses.clone()));
+        addresses
+    }
+
     fn report_disallowed_host(&self, scheme: &str, authority: &str) {
         if let Some(handler) = &self.disallowed_host_handler {
             handler.handle_disallowed_host(scheme, authority);
@@ -112,34 +177,51 @@ pub struct AllowedHostConfig {
 impl AllowedHostConfig {
     /// Try to parse the address.
     ///
-    /// If the parsing fails, the address is prepended with the scheme and parsing
-    /// is tried again.
+    /// The host may be a CIDR block, such as `10.0.0.0/8`, and the port may be
+    /// a range, such as `8000-8999`. If the port is omitted, the scheme's
+    /// default port is used; with a `*` scheme, each scheme's default port is
+    /// allowed.
+    ///
+    /// Errors name the offending entry and, where possible, suggest a fix.
     pub fn parse(url: impl Into<String>) -> anyhow::Result<Self> {
         let original = url.into();
         let url = original.trim();
+        let (scheme, host, port) = Self::parse_parts(url)
+            .with_context(|| format!("{url:?} is not a valid allowed outbound host"))?;
+        Ok(Self {
+            scheme,
+            host,
+            port,
+            original,
+        })
+    }
+
+    fn parse_parts(url: &str) -> anyhow::Result<(SchemeConfig, HostConfig, PortConfig)> {
         let Some((scheme, rest)) = url.split_once("://") else {
             match url {
-                "*" | ":" | "" | "?" => bail!("{url:?} is not an allowed outbound host format.\nHosts must be in the form <scheme>://<host>[:<port>], with '*' wildcards allowed for each.\nIf you intended to allow all outbound networking, you can use '*://*:*' - this will obviate all network sandboxing.\nLearn more: https://spinframework.dev/v3/http-outbound#granting-http-permissions-to-components"),
-                _ => bail!("{url:?} does not contain a scheme (e.g., 'http://' or '*://')\nLearn more: https://spinframework.dev/v3/http-outbound#granting-http-permissions-to-components"),
+                "*" | ":" | "" | "?" => bail!("Hosts must be in the form <scheme>://<host>[:<port>], with '*' wildcards allowed for each.\nIf you intended to allow all outbound networking, you can use '*://*:*' - this will obviate all network sandboxing.\nLearn more: https://spinframework.dev/v3/http-outbound#granting-http-permissions-to-components"),
+                _ => bail!("it does not contain a scheme (e.g., 'http://' or '*://'). Did you mean \"*://{url}\"?\nLearn more: https://spinframework.dev/v3/http-outbound#granting-http-permissions-to-components"),
             }
         };
-        let (host, rest) = rest.rsplit_once(':').unwrap_or((rest, ""));
+        let (host, rest) =
+            split_cidr(rest).unwrap_or_else(|| rest.rsplit_once(':').unwrap_or((rest, "")));
         let port = match rest.split_once('/') {
             Some((port, path)) => {
                 if !path.is_empty() {
-                    bail!("{url:?} has a path but is not allowed to");
+                    bail!(
+                        "it has a path but is not allowed to. Did you mean \"{scheme}://{host}\"?"
+                    );
                 }
                 port
             }
             None => rest,
         };
 
-        Ok(Self {
-            scheme: SchemeConfig::parse(scheme)?,
-            host: HostConfig::parse(host)?,
-            port: PortConfig::parse(port, scheme)?,
-            original,
-        })
+        Ok((
+            SchemeConfig::parse(scheme)?,
+            HostConfig::parse(host)?,
+            PortConfig::parse(port, scheme)?,
+        ))
     }
 
     pub fn scheme(&self) -> &SchemeConfig {
@@ -195,7 +277,7 @@ impl SchemeConfig {
         }
 
         if scheme.chars().any(|c| !c.is_alphabetic()) {
-            anyhow::bail!(" scheme {scheme:?} contains non alphabetic character");
+            anyhow::bail!("scheme {scheme:?} contains non alphabetic character");
         }
 
         Ok(Self::List(vec![scheme.into()]))
@@ -270,7 +352,9 @@ impl HostConfig {
             HostConfig::List(l) => l.iter().any(|h| h.as_str() == host),
             HostConfig::ToSelf => false,
             HostConfig::Cidr(c) => {
-                let Ok(ip) = host.parse::<std::net::IpAddr>() else {
+                // IPv6 hosts in URLs are bracketed
+                let host = host.trim_start_matches('[').trim_end_matches(']');
+                let Ok(ip) = host.parse::<IpAddr>() else {
                     return false;
                 };
                 c.contains(ip)
@@ -286,15 +370,20 @@ impl HostConfig {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum PortConfig {
     Any,
+    /// The default port of whichever scheme is used
+    SchemeDefault,
     List(Vec<IndividualPortConfig>),
 }
 
 impl PortConfig {
     fn parse(port: &str, scheme: &str) -> anyhow::Result<PortConfig> {
         if port.is_empty() {
+            if scheme == "*" {
+                return Ok(PortConfig::SchemeDefault);
+            }
             return well_known_port(scheme)
                 .map(|p| PortConfig::List(vec![IndividualPortConfig::Port(p)]))
-                .with_context(|| format!("no port was provided and the scheme {scheme:?} does not have a known default port number"));
+                .with_context(|| format!("no port was provided and the scheme {scheme:?} does not have a known default port number. Add a port (e.g. ':5000'), a port range (e.g. ':8000-8999') or ':*' for any port"));
         }
         if port == "*" {
             return Ok(PortConfig::Any);
@@ -313,6 +402,10 @@ impl PortConfig {
     fn allows(&self, port: Option<u16>, scheme: &str) -> bool {
         match self {
             PortConfig::Any => true,
+            PortConfig::SchemeDefault => match well_known_port(scheme) {
+                Some(default_port) => port.unwrap_or(default_port) == default_port,
+                None => false,
+            },
             PortConfig::List(l) => {
                 let port = match port.or_else(|| well_known_port(scheme)) {
                     Some(p) => p,
@@ -327,23 +420,46 @@ impl PortConfig {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum IndividualPortConfig {
     Port(u16),
-    Range(Range<u16>),
+    Range(RangeInclusive<u16>),
 }
 
 impl IndividualPortConfig {
+    /// Parses a port, or a range of ports. Ranges are either inclusive, as in
+    /// `8000-8999` or `8000..=8999`, or exclusive, as in `8000..9000`.
     fn parse(port: &str) -> anyhow::Result<Self> {
-        if let Some((start, end)) = port.split_once("..") {
-            let start = start
-                .parse()
-                .with_context(|| format!("port range {port:?} contains non-number"))?;
-            let end = end
-                .parse()
-                .with_context(|| format!("port range {port:?} contains non-number"))?;
-            return Ok(Self::Range(start..end));
+        let range = if let Some((start, end)) = port.split_once("..") {
+            match end.strip_prefix('=') {
+                Some(end) => Some((start, end, true)),
+                None => Some((start, end, false)),
+            }
+        } else {
+            port.split_once('-').map(|(start, end)| (start, end, true))
+        };
+        let Some((start, end, inclusive)) = range else {
+            return Ok(Self::Port(
+                port.parse()
+                    .with_context(|| format!("port {port:?} is not a number"))?,
+            ));
+        };
+
+        let start: u16 = start
+            .parse()
+            .with_context(|| format!("port range {port:?} contains non-number"))?;
+        let end: u16 = end
+            .parse()
+            .with_context(|| format!("port range {port:?} contains non-number"))?;
+        let last = if inclusive {
+            Some(end)
+        } else {
+            end.checked_sub(1)
+        };
+        match last {
+            Some(last) if start <= last => Ok(Self::Range(start..=last)),
+            _ if start > end => {
+                bail!("port range {port:?} is empty. Did you mean \"{end}-{start}\"?")
+            }
+            _ => bail!("port range {port:?} is empty"),
         }
-        Ok(Self::Port(port.parse().with_context(|| {
-            format!("port {port:?} is not a number")
-        })?))
     }
 
     fn allows(&self, port: u16) -> bool {
@@ -354,12 +470,28 @@ impl IndividualPortConfig {
     }
 }
 
+// Splits a CIDR block host, such as `10.0.0.0/8` or `ff00::/8`, from the rest
+// of the entry. Returns None if the entry's host is not a CIDR block.
+fn split_cidr(rest: &str) -> Option<(&str, &str)> {
+    let (addr, after) = rest.split_once('/')?;
+    addr.parse::<IpAddr>().ok()?;
+    let prefix_len = after
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(after.len());
+    let (host, rest) = rest.split_at(addr.len() + 1 + prefix_len);
+    Some((host, rest.strip_prefix(':').unwrap_or(rest)))
+}
+
 fn well_known_port(scheme: &str) -> Option<u16> {
     match scheme {
         "postgres" => Some(5432),
         "mysql" => Some(3306),
         "redis" => Some(6379),
         "mqtt" => Some(1883),
+        "kafka" => Some(9092),
+        "nats" => Some(4222),
+        "amqp" => Some(5672),
+        "amqps" => Some(5671),
         "http" => Some(80),
         "https" => Some(443),
         _ => None,
@@ -443,6 +575,28 @@ impl AllowedHostsConfig {
             }
         }
     }
+
+    /// The host names, other than wildcards and addresses, which are allowed
+    /// for the scheme and port.
+    pub fn allowed_domains(&self, scheme: &str, port: u16) -> Vec<&str> {
+        match self {
+            AllowedHostsConfig::All => vec![],
+            AllowedHostsConfig::SpecificHosts(hosts) => hosts
+                .iter()
+                .filter(|h| h.scheme.allows(scheme) && h.port.allows(Some(port), scheme))
+                .flat_map(|h| match &h.host {
+                    HostConfig::List(names) => names.as_slice(),
+                    _ => &[],
+                })
+                .map(String::as_str)
+                // Addresses are allowed as they are, without resolving
+                .filter(|host| {
+                    let host = host.trim_start_matches('[').trim_end_matches(']');
+                    host.parse::<IpAddr>().is_err()
+                })
+                .collect(),
+        }
+    }
 }
 
 impl Default for AllowedHostsConfig {
@@ -588,7 +742,7 @@ mod test {
             Self::List(vec![IndividualPortConfig::Port(port)])
         }
 
-        fn range(port: Range<u16>) -> Self {
+        fn range(port: RangeInclusive<u16>) -> Self {
             Self::List(vec![IndividualPortConfig::Range(port)])
         }
     }
@@ -686,10 +840,47 @@ mod test {
             AllowedHostConfig::new(
                 SchemeConfig::new("http"),
                 HostConfig::new("spin.fermyon.dev"),
-                PortConfig::range(4444..5555)
+                PortConfig::range(4444..=5554)
             ),
             AllowedHostConfig::parse("http://spin.fermyon.dev:4444..5555").unwrap()
         );
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::Any,
+                HostConfig::subdomain("internal"),
+                PortConfig::range(8000..=8999)
+            ),
+            AllowedHostConfig::parse("*://*.internal:8000-8999").unwrap()
+        );
+        assert_eq!(
+            PortConfig::range(8000..=8999),
+            AllowedHostConfig::parse("*://*.internal:8000..=8999")
+                .unwrap()
+                .port
+        );
+        assert_eq!(
+            PortConfig::range(65535..=65535),
+            AllowedHostConfig::parse("*://*.internal:65535-65535")
+                .unwrap()
+                .port
+        );
+
+        let err = AllowedHostConfig::parse("*://*.internal:8999-8000").unwrap_err();
+        assert!(
+            format!("{err:#}").contains("Did you mean \"8000-8999\"?"),
+            "{err:#}"
+        );
+        assert!(AllowedHostConfig::parse("*://*.internal:8000..8000").is_err());
+    }
+
+    #[test]
+    fn test_allowed_hosts_port_ranges_are_inclusive() {
+        let allowed =
+            AllowedHostsConfig::parse(&["*://*.internal:8000-8999"], &dummy_resolver()).unwrap();
+        assert!(allowed.allows(&OutboundUrl::parse("http://a.internal:8000", "http").unwrap()));
+        assert!(allowed.allows(&OutboundUrl::parse("http://a.internal:8999", "http").unwrap()));
+        assert!(!allowed.allows(&OutboundUrl::parse("http://a.internal:9000", "http").unwrap()));
+        assert!(!allowed.allows(&OutboundUrl::parse("http://a.internal", "http").unwrap()));
     }
 
     #[test]
@@ -802,7 +993,16 @@ mod test {
             ),
             AllowedHostConfig::parse("*://127.0.0.0/24:80").unwrap()
         );
-        assert!(AllowedHostConfig::parse("*://127.0.0.0/24").is_err());
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::Any,
+                HostConfig::Cidr(IpNetwork::V4(
+                    Ipv4Network::new(Ipv4Addr::new(127, 0, 0, 0), 24).unwrap()
+                )),
+                PortConfig::SchemeDefault
+            ),
+            AllowedHostConfig::parse("*://127.0.0.0/24").unwrap()
+        );
         assert_eq!(
             AllowedHostConfig::new(
                 SchemeConfig::Any,
@@ -917,4 +1117,99 @@ mod test {
             AllowedHostsConfig::parse(&["*://127.0.0.1/24:63551"], &dummy_resolver()).unwrap();
         assert!(allowed.allows(&OutboundUrl::parse("tcp://127.0.0.1:63551", "tcp").unwrap()));
     }
+
+    #[test]
+    fn test_cidr_without_port() {
+        let allowed =
+            AllowedHostsConfig::parse(&["https://10.0.0.0/8", "*://ff00::/8/"], &dummy_resolver())
+                .unwrap();
+        assert!(allowed.allows(&OutboundUrl::parse("https://10.1.2.3", "https").unwrap()));
+        assert!(!allowed.allows(&OutboundUrl::parse("https://10.1.2.3:8443", "https").unwrap()));
+        assert!(!allowed.allows(&OutboundUrl::parse("https://11.1.2.3", "https").unwrap()));
+        assert!(allowed.allows(&OutboundUrl::parse("http://[ff00::1]", "http").unwrap()));
+
+        assert!(AllowedHostConfig::parse("https://10.0.0.0/8/path").is_err());
+    }
+
+    #[test]
+    fn test_scheme_wildcard_allows_each_default_port() {
+        let allowed =
+            AllowedHostsConfig::parse(&["*://db.example.com"], &dummy_resolver()).unwrap();
+        assert!(
+            allowed.allows(&OutboundUrl::parse("postgres://db.example.com", "postgres").unwrap())
+        );
+        assert!(
+            allowed.allows(&OutboundUrl::parse("redis://db.example.com:6379", "redis").unwrap())
+        );
+        assert!(
+            !allowed.allows(&OutboundUrl::parse("redis://db.example.com:5432", "redis").unwrap())
+        );
+        assert!(!allowed.allows(&OutboundUrl::parse("tcp://db.example.com:5432", "tcp").unwrap()));
+    }
+
+    #[test]
+    fn test_errors_name_entry_and_suggest_fixes() {
+        let err = AllowedHostConfig::parse("*.internal:8000-8999").unwrap_err();
+        let err = format!("{err:#}");
+        assert!(
+            err.contains(r#""*.internal:8000-8999" is not a valid"#),
+            "{err}"
+        );
+        assert!(
+            err.contains(r#"Did you mean "*://*.internal:8000-8999"?"#),
+            "{err}"
+        );
+
+        let err = AllowedHostConfig::parse("tcp://example.com").unwrap_err();
+        assert!(format!("{err:#}").contains("':*' for any port"), "{err:#}");
+    }
+
+    #[test]
+    fn test_allowed_domains_for_scheme_and_port() {
+        let allowed = AllowedHostsConfig::parse(
+            &[
+                "tcp://db.example.com:5000-5999",
+                "*://cache.example.com:6379",
+                "tcp://*.example.com:5432",
+                "tcp://10.0.0.1:5432",
+                "udp://dns.example.com:53",
+            ],
+            &dummy_resolver(),
+        )
+        .unwrap();
+        assert_eq!(vec!["db.example.com"], allowed.allowed_domains("tcp", 5432));
+        assert_eq!(
+            vec!["cache.example.com"],
+            allowed.allowed_domains("tcp", 6379)
+        );
+        assert_eq!(vec!["dns.example.com"], allowed.allowed_domains("udp", 53));
+        assert!(allowed.allowed_domains("udp", 5432).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_socket_addrs_are_allowed_by_resolved_names() {
+        use futures_util::FutureExt as _;
+
+        let allowed = AllowedHostsConfig::parse(
+            &["tcp://localhost:5432", "tcp://10.0.0.1:6379"],
+            &dummy_resolver(),
+        )
+        .unwrap();
+        let allowed = OutboundAllowedHosts::new(
+            futures_util::future::ready(Ok(Arc::new(allowed)))
+                .boxed()
+                .shared(),
+            None,
+        );
+        let check = |addr: &str, scheme: &'static str| {
+            let allowed = allowed.clone();
+            let addr = addr.parse().unwrap();
+            async move { allowed.check_socket_addr(addr, scheme).await.unwrap() }
+        };
+        assert!(check("127.0.0.1:5432", "tcp").await);
+        assert!(check("10.0.0.1:6379", "tcp").await);
+        assert!(!check("127.0.0.1:5433", "tcp").await);
+        assert!(!check("127.0.0.1:5432", "udp").await);
+        assert!(!check("10.0.0.2:5432", "tcp").await);
+    }
 }
@@ -0,0 +1,42 @@
+//! Connections to PostgreSQL databases, shared by the stores which Spin
+//! backs with PostgreSQL.
+
+use anyhow::Context;
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use tokio_postgres::config::SslMode;
+use tokio_postgres::{Client, Config, NoTls};
+
+/// Connects to the database, using TLS unless the config's SSL mode is
+/// `disable`.
+///
+/// The connection is driven by a spawned task, which logs the error if the
+/// connection fails.
+pub async fn connect(config: &Config) -> anyhow::Result<Client> {
+    let client = if config.get_ssl_mode() == SslMode::Disable {
+        let (client, connection) = config.connect(NoTls).await?;
+        spawn_connection(connection);
+        client
+    } else {
+        let connector = MakeTlsConnector::new(
+            TlsConnector::builder()
+                .build()
+                .context("failed to create TLS connector")?,
+        );
+        let (client, connection) = config.connect(connector).await?;
+        spawn_connection(connection);
+        client
+    };
+    Ok(client)
+}
+
+fn spawn_connection<T>(connection: tokio_postgres::Connection<tokio_postgres::Socket, T>)
+where
+    T: tokio_postgres::tls::TlsStream + std::marker::Unpin + std::marker::Send + 'static,
+{
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::error!("PostgreSQL connection error: {e}");
+        }
+    });
+}
@@ -7,6 +7,10 @@ use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, collections::HashMap, fmt};
 
+mod pattern;
+
+pub use pattern::{RoutePattern, RouteSegment};
+
 /// The prefix for well-known routes.
 pub const WELL_KNOWN_PREFIX: &str = "/.well-known/spin/";
 
@@ -30,6 +34,8 @@ struct RouteHandler {
     /// The route, including any application base and capturing information about whether it has a trailing wildcard.
     /// (This avoids re-parsing the route string.)
     parsed_based_route: ParsedRoute,
+    /// The route, including any application base, parsed into segments.
+    pattern: RoutePattern,
 }
 
 /// A detected duplicate route.
@@ -110,12 +116,20 @@ impl Router {
                     re.component_id
                 )
             })?;
+            let pattern = RoutePattern::parse(&re.based_route).map_err(|e| {
+                anyhow!(
+                    "Error parsing route {} associated with component {}: {e}",
+                    re.based_route,
+                    re.component_id
+                )
+            })?;
 
             let handler = RouteHandler {
                 component_id: re.component_id.to_string(),
                 based_route: re.based_route.into(),
                 raw_route: re.raw_route.to_string().into(),
                 parsed_based_route: parsed,
+                pattern,
             };
 
             rf.add(rfroute, handler).map_err(|e| anyhow!("{e}"))?;
@@ -151,6 +165,13 @@ impl Router {
             .map(|(_spec, handler)| (&handler.parsed_based_route, &handler.component_id))
     }
 
+    /// Returns the parsed routes, with the components they map to.
+    pub fn patterns(&self) -> impl Iterator<Item = (&RoutePattern, &str)> {
+        self.router
+            .iter()
+            .map(|(_spec, handler)| (&handler.pattern, handler.component_id.as_str()))
+    }
+
     /// true if one or more routes is under the reserved `/.well-known/spin/*`
     /// prefix; otherwise false.
     pub fn contains_reserved_route(&self) -> bool {
@@ -239,6 +260,7 @@ impl RouteMatch<'_, '_> {
                     based_route: "/...".into(),
                     raw_route: "/...".into(),
                     parsed_based_route: ParsedRoute::TrailingWildcard(String::new()),
+                    pattern: RoutePattern::trailing_wildcard(),
                 },
                 trailing_wildcard: path,
             },
@@ -278,6 +300,11 @@ impl RouteMatch<'_, '_> {
             .unwrap_or(&self.inner.route_handler().raw_route)
     }
 
+    /// The matched route, combined with the base, parsed into segments.
+    pub fn pattern(&self) -> &RoutePattern {
+        &self.inner.route_handler().pattern
+    }
+
     /// The named wildcards captured from the path, if any
     pub fn named_wildcards(&self) -> HashMap<&str, &str> {
         self.inner.named_wildcards()
@@ -710,6 +737,17 @@ mod route_tests {
         assert_eq!("2", m.named_wildcards()["two"]);
     }
 
+    #[test]
+    fn matched_pattern_is_exposed() {
+        let routes = Router::build("/api", vec![("comp", &"/users/:id/...".into())], None).unwrap();
+        let m = routes
+            .route("/api/users/42/orders")
+            .expect("/api/users/42/orders should have matched");
+        assert_eq!("/api/users/:id/...", m.pattern().to_string());
+        assert_eq!(vec!["id"], m.pattern().parameters().collect::<Vec<_>>());
+        assert_eq!("42", m.named_wildcards()["id"]);
+    }
+
     #[test]
     fn reserved_routes_are_reserved() {
         let routes =
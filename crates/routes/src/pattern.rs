@@ -0,0 +1,168 @@
+//! Parsed HTTP trigger routes.
+
+use std::fmt;
+
+use anyhow::{bail, Result};
+
+/// A segment of a [`RoutePattern`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RouteSegment {
+    /// A segment which must match exactly, e.g. `users`.
+    Literal(String),
+    /// A named parameter matching any single segment, e.g. `:id`.
+    Parameter(String),
+    /// A trailing wildcard matching any remaining segments, written `...`
+    /// (or `*`).
+    TrailingWildcard,
+}
+
+/// A parsed HTTP trigger route, such as `/users/:id/orders/...`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutePattern {
+    segments: Vec<RouteSegment>,
+}
+
+impl RoutePattern {
+    /// Parses a route as written in the manifest. A single trailing slash is
+    /// ignored, as it is by the router.
+    pub fn parse(route: &str) -> Result<Self> {
+        let trimmed = route.strip_prefix('/').unwrap_or(route);
+        let trimmed = trimmed.strip_suffix('/').unwrap_or(trimmed);
+        if trimmed.is_empty() {
+            return Ok(Self { segments: vec![] });
+        }
+
+        let mut segments = vec![];
+        let mut parts = trimmed.split('/').peekable();
+        while let Some(part) = parts.next() {
+            let segment = match part {
+                "..." | "*" => {
+                    if parts.peek().is_some() {
+                        bail!("`{part}` is only allowed at the end of a route");
+                    }
+                    RouteSegment::TrailingWildcard
+                }
+                _ => match part.strip_prefix(':') {
+                    Some(name) => {
+                        if name.is_empty() {
+                            bail!("parameters must be named, e.g. `:id`");
+                        }
+                        if segments.contains(&RouteSegment::Parameter(name.to_owned())) {
+                            bail!("parameter `{part}` appears more than once");
+                        }
+                        RouteSegment::Parameter(name.to_owned())
+                    }
+                    None => RouteSegment::Literal(part.to_owned()),
+                },
+            };
+            segments.push(segment);
+        }
+        Ok(Self { segments })
+    }
+
+    /// The route `/...`, which matches all paths.
+    pub(crate) fn trailing_wildcard() -> Self {
+        Self {
+            segments: vec![RouteSegment::TrailingWildcard],
+        }
+    }
+
+    /// The segments of the route.
+    pub fn segments(&self) -> &[RouteSegment] {
+        &self.segments
+    }
+
+    /// The names of the route's parameters, in order.
+    pub fn parameters(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            RouteSegment::Parameter(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// true if the route ends with a wildcard; otherwise false.
+    pub fn has_trailing_wildcard(&self) -> bool {
+        self.segments.last() == Some(&RouteSegment::TrailingWildcard)
+    }
+
+    /// true if the two routes match exactly the same paths, that is, they
+    /// differ at most in the names of their parameters. The router cannot
+    /// prefer either of two such routes.
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        self.segments.len() == other.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(&other.segments)
+                .all(|pair| match pair {
+                    (RouteSegment::Literal(a), RouteSegment::Literal(b)) => a == b,
+                    (RouteSegment::Parameter(_), RouteSegment::Parameter(_)) => true,
+                    (RouteSegment::TrailingWildcard, RouteSegment::TrailingWildcard) => true,
+                    _ => false,
+                })
+    }
+}
+
+impl fmt::Display for RoutePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.segments.is_empty() {
+            return f.write_str("/");
+        }
+        for segment in &self.segments {
+            match segment {
+                RouteSegment::Literal(literal) => write!(f, "/{literal}")?,
+                RouteSegment::Parameter(name) => write!(f, "/:{name}")?,
+                RouteSegment::TrailingWildcard => f.write_str("/...")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_parameters_and_wildcards() {
+        let pattern = RoutePattern::parse("/users/:id/orders/...").unwrap();
+        assert_eq!(
+            &[
+                RouteSegment::Literal("users".into()),
+                RouteSegment::Parameter("id".into()),
+                RouteSegment::Literal("orders".into()),
+                RouteSegment::TrailingWildcard,
+            ],
+            pattern.segments()
+        );
+        assert_eq!(vec!["id"], pattern.parameters().collect::<Vec<_>>());
+        assert!(pattern.has_trailing_wildcard());
+        assert_eq!("/users/:id/orders/...", pattern.to_string());
+
+        assert_eq!("/", RoutePattern::parse("/").unwrap().to_string());
+        assert_eq!("/foo", RoutePattern::parse("foo/").unwrap().to_string());
+        assert_eq!(
+            "/foo/...",
+            RoutePattern::parse("/foo/*").unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_routes() {
+        for route in ["/a/.../b", "/a/:", "/a/:id/b/:id"] {
+            assert!(
+                RoutePattern::parse(route).is_err(),
+                "{route} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn equivalence_ignores_parameter_names() {
+        let parse = |route| RoutePattern::parse(route).unwrap();
+        assert!(parse("/users/:id/...").is_equivalent(&parse("/users/:name/...")));
+        assert!(parse("/users/").is_equivalent(&parse("/users")));
+        assert!(!parse("/users/:id").is_equivalent(&parse("/users/:id/...")));
+        assert!(!parse("/users/:id").is_equivalent(&parse("/users/me")));
+    }
+}
@@ -6,9 +6,10 @@ use anyhow::Context as _;
 use spin_factors_executor::FactorsExecutor;
 use spin_runtime_config::ResolvedRuntimeConfig;
 use spin_trigger::cli::{
-    FactorsConfig, InitialKvSetterHook, KeyValueDefaultStoreSummaryHook, MaxInstanceMemoryHook,
-    RuntimeFactorsBuilder, SqlStatementExecutorHook, SqliteDefaultStoreSummaryHook,
-    StdioLoggingExecutorHooks,
+    FactorsConfig, InitialKvSetterHook, InvocationContextHook, KeyValueDefaultStoreSummaryHook,
+    MaxInstanceMemoryHook, RuntimeFactorsBuilder, SqlStatementExecutorHook,
+    SqliteDefaultStoreSummaryHook, StdioLoggingExecutorHooks, VariablesSnapshotHook,
+    VariablesValidationHook,
 };
 
 /// A [`RuntimeFactorsBuilder`] for [`TriggerFactors`].
@@ -32,12 +33,15 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
 
         runtime_config.summarize(config.runtime_config_file.as_deref());
 
-        let factors = TriggerFactors::new(
+        let mut factors = TriggerFactors::new(
             runtime_config.state_dir(),
             config.working_dir.clone(),
             args.allow_transient_write,
         )
         .context("failed to create factors")?;
+        if let Some(profile) = &args.variables_profile {
+            factors.variables.set_profile(profile);
+        }
         Ok((factors, runtime_config))
     }
 
@@ -50,13 +54,19 @@ impl RuntimeFactorsBuilder for FactorsBuilder {
         executor.add_hooks(StdioLoggingExecutorHooks::new(
             config.follow_components.clone(),
             runtime_config.log_dir(),
+            runtime_config.component_logs(),
         ));
         executor.add_hooks(SqlStatementExecutorHook::new(
             args.sqlite_statements.clone(),
         ));
         executor.add_hooks(InitialKvSetterHook::new(args.key_values.clone()));
+        executor.add_hooks(InvocationContextHook);
         executor.add_hooks(SqliteDefaultStoreSummaryHook);
         executor.add_hooks(KeyValueDefaultStoreSummaryHook);
+        executor.add_hooks(VariablesValidationHook);
+        if let Some(path) = &args.variables_snapshot {
+            executor.add_hooks(VariablesSnapshotHook::new(path.clone()));
+        }
 
         let max_instance_memory = args
             .max_instance_memory
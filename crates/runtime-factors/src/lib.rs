@@ -6,16 +6,27 @@ use std::path::PathBuf;
 
 use anyhow::Context as _;
 use spin_common::arg_parser::parse_kv;
+use spin_factor_blob_store::BlobStoreFactor;
+use spin_factor_context::ContextFactor;
+use spin_factor_jobs::JobsFactor;
 use spin_factor_key_value::KeyValueFactor;
 use spin_factor_llm::LlmFactor;
+use spin_factor_locks::LocksFactor;
+use spin_factor_observe::ObserveFactor;
+use spin_factor_outbound_amqp::{NetworkedAmqpConnection, OutboundAmqpFactor};
+use spin_factor_outbound_email::OutboundEmailFactor;
 use spin_factor_outbound_http::OutboundHttpFactor;
+use spin_factor_outbound_kafka::{NetworkedKafkaProducer, OutboundKafkaFactor};
 use spin_factor_outbound_mqtt::{NetworkedMqttClient, OutboundMqttFactor};
 use spin_factor_outbound_mysql::OutboundMysqlFactor;
+use spin_factor_outbound_nats::{NetworkedNatsClient, OutboundNatsFactor};
 use spin_factor_outbound_networking::OutboundNetworkingFactor;
 use spin_factor_outbound_pg::OutboundPgFactor;
 use spin_factor_outbound_redis::OutboundRedisFactor;
+use spin_factor_sessions::SessionsFactor;
 use spin_factor_sqlite::SqliteFactor;
 use spin_factor_variables::VariablesFactor;
+use spin_factor_vector::VectorFactor;
 use spin_factor_wasi::{spin::SpinFilesMounter, WasiFactor};
 use spin_factors::RuntimeFactors;
 use spin_runtime_config::{ResolvedRuntimeConfig, TomlRuntimeConfigSource};
@@ -25,14 +36,25 @@ pub struct TriggerFactors {
     pub wasi: WasiFactor,
     pub variables: VariablesFactor,
     pub key_value: KeyValueFactor,
+    pub jobs: JobsFactor,
+    pub locks: LocksFactor,
+    pub sessions: SessionsFactor,
     pub outbound_networking: OutboundNetworkingFactor,
     pub outbound_http: OutboundHttpFactor,
     pub sqlite: SqliteFactor,
+    pub vector: VectorFactor,
+    pub blob_store: BlobStoreFactor,
     pub redis: OutboundRedisFactor,
     pub mqtt: OutboundMqttFactor,
+    pub kafka: OutboundKafkaFactor,
+    pub nats: OutboundNatsFactor,
+    pub amqp: OutboundAmqpFactor,
     pub pg: OutboundPgFactor,
     pub mysql: OutboundMysqlFactor,
+    pub email: OutboundEmailFactor,
     pub llm: LlmFactor,
+    pub observe: ObserveFactor,
+    pub context: ContextFactor,
 }
 
 impl TriggerFactors {
@@ -45,17 +67,28 @@ impl TriggerFactors {
             wasi: wasi_factor(working_dir, allow_transient_writes),
             variables: VariablesFactor::default(),
             key_value: KeyValueFactor::new(),
+            jobs: JobsFactor::new(),
+            locks: LocksFactor::new(),
+            sessions: SessionsFactor::new(),
             outbound_networking: outbound_networking_factor(),
             outbound_http: OutboundHttpFactor::default(),
             sqlite: SqliteFactor::new(),
+            vector: VectorFactor::new(),
+            blob_store: BlobStoreFactor::new(),
             redis: OutboundRedisFactor::new(),
             mqtt: OutboundMqttFactor::new(NetworkedMqttClient::creator()),
+            kafka: OutboundKafkaFactor::new(NetworkedKafkaProducer::creator()),
+            nats: OutboundNatsFactor::new(NetworkedNatsClient::creator()),
+            amqp: OutboundAmqpFactor::new(NetworkedAmqpConnection::creator()),
             pg: OutboundPgFactor::new(),
             mysql: OutboundMysqlFactor::new(),
+            email: OutboundEmailFactor::new(),
             llm: LlmFactor::new(
                 spin_factor_llm::spin::default_engine_creator(state_dir)
                     .context("failed to configure LLM factor")?,
             ),
+            observe: ObserveFactor::new(),
+            context: ContextFactor::new(),
         })
     }
 }
@@ -104,6 +137,17 @@ pub struct TriggerAppArgs {
     /// Sets the maxmimum memory allocation limit for an instance in bytes.
     #[clap(long, env = "SPIN_MAX_INSTANCE_MEMORY")]
     pub max_instance_memory: Option<usize>,
+
+    /// The configuration profile whose variables override the application
+    /// variables, e.g. `dev` or `prod`.
+    #[clap(long = "variables-profile", env = "SPIN_VARIABLES_PROFILE")]
+    pub variables_profile: Option<String>,
+
+    /// Write the resolved values of the application's variables to a JSON file
+    /// at startup, for debugging. Secret values are not written. The file can be
+    /// read back with a `snapshot` variables provider.
+    #[clap(long = "variables-snapshot", value_name = "FILE")]
+    pub variables_snapshot: Option<PathBuf>,
 }
 
 impl From<ResolvedRuntimeConfig<TriggerFactorsRuntimeConfig>> for TriggerFactorsRuntimeConfig {
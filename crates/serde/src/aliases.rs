@@ -0,0 +1,250 @@
+//! Case-insensitive, alias-aware (de)serialization of unit enums, and
+//! structured notices for deprecated spellings.
+//!
+//! Use with `#[serde(with = "spin_serde::aliases")]` on a field whose type
+//! implements [`Aliased`], or `#[serde(with = "spin_serde::aliases::option")]`
+//! for an `Option` of one. Values match names without regard to ASCII case;
+//! a value which matches one of the type's aliases is accepted, and reported
+//! as a [`Deprecation`] to any active [`collect_deprecations`] call.
+
+use std::cell::RefCell;
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Schema, SchemaObject},
+};
+use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+/// A unit enum which is written as one of a fixed set of names.
+pub trait Aliased: Copy + PartialEq + 'static {
+    /// The canonical name of each variant. Serialization uses these names.
+    const NAMES: &'static [(&'static str, Self)];
+
+    /// Historical alternate names, which are accepted but deprecated.
+    const ALIASES: &'static [(&'static str, Self)] = &[];
+
+    /// The canonical name of this variant.
+    fn name(&self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, value)| value == self)
+            .map(|(name, _)| *name)
+            .expect("every variant should have a name")
+    }
+}
+
+/// A deprecated spelling which was accepted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deprecation {
+    /// The spelling which was used.
+    pub used: String,
+    /// The spelling which should be used instead.
+    pub replacement: String,
+    /// Further advice on how to replace the deprecated spelling, if any.
+    pub advice: Option<String>,
+}
+
+impl Deprecation {
+    /// A deprecation of `used` in favour of `replacement`.
+    pub fn new(used: impl Into<String>, replacement: impl Into<String>) -> Self {
+        Self {
+            used: used.into(),
+            replacement: replacement.into(),
+            advice: None,
+        }
+    }
+
+    /// Adds advice on how to replace the deprecated spelling.
+    pub fn with_advice(self, advice: impl Into<String>) -> Self {
+        Self {
+            advice: Some(advice.into()),
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` is deprecated; use `{}` instead",
+            self.used, self.replacement
+        )?;
+        if let Some(advice) = &self.advice {
+            write!(f, ": {advice}")?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static DEPRECATIONS: RefCell<Option<Vec<Deprecation>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, returning its result together with the deprecations reported
+/// while it ran on this thread. Calls may be nested; each deprecation is
+/// returned by the innermost call only.
+pub fn collect_deprecations<T>(f: impl FnOnce() -> T) -> (T, Vec<Deprecation>) {
+    let outer = DEPRECATIONS.with(|d| d.replace(Some(vec![])));
+    let result = f();
+    let deprecations = DEPRECATIONS.with(|d| d.replace(outer)).unwrap_or_default();
+    (result, deprecations)
+}
+
+/// Reports a deprecation to the active [`collect_deprecations`] call, if
+/// any. Otherwise the deprecation is ignored.
+pub fn report_deprecation(deprecation: Deprecation) {
+    DEPRECATIONS.with(|d| {
+        if let Some(deprecations) = d.borrow_mut().as_mut() {
+            deprecations.push(deprecation);
+        }
+    });
+}
+
+/// Parses a name or alias of `T`, without regard to ASCII case. Aliases are
+/// reported as deprecations.
+pub fn parse<T: Aliased>(name: &str) -> Result<T, String> {
+    let matching = |(candidate, _): &&(&str, T)| candidate.eq_ignore_ascii_case(name);
+    if let Some((_, value)) = T::NAMES.iter().find(matching) {
+        return Ok(*value);
+    }
+    if let Some((alias, value)) = T::ALIASES.iter().find(matching) {
+        report_deprecation(Deprecation::new(*alias, value.name()));
+        return Ok(*value);
+    }
+    let expected = T::NAMES
+        .iter()
+        .map(|(name, _)| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(format!(
+        "unknown value `{name}`; expected one of {expected}"
+    ))
+}
+
+/// Serializes a value as its canonical name.
+pub fn serialize<T: Aliased, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(value.name())
+}
+
+/// Deserializes a name or alias of `T`. See [`parse`].
+pub fn deserialize<'de, T: Aliased, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    parse(&name).map_err(D::Error::custom)
+}
+
+/// A JSON schema listing the canonical names of `T`, for use with
+/// `#[schemars(schema_with = "spin_serde::aliases::json_schema::<T>")]`.
+pub fn json_schema<T: Aliased>(_gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        enum_values: Some(T::NAMES.iter().map(|(name, _)| (*name).into()).collect()),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// As the parent module, for `Option` fields.
+pub mod option {
+    use super::*;
+
+    /// Serializes a value, if any, as its canonical name.
+    pub fn serialize<T: Aliased, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(value.name()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an optional name or alias of `T`. See [`parse`].
+    pub fn deserialize<'de, T: Aliased, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|name| parse(&name).map_err(D::Error::custom))
+            .transpose()
+    }
+
+    /// A JSON schema listing the canonical names of `T`. The field should
+    /// also have `#[serde(default)]`.
+    pub fn json_schema<T: Aliased>(gen: &mut SchemaGenerator) -> Schema {
+        super::json_schema::<T>(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Colour {
+        Red,
+        Grey,
+    }
+
+    impl Aliased for Colour {
+        const NAMES: &'static [(&'static str, Self)] = &[("red", Self::Red), ("grey", Self::Grey)];
+        const ALIASES: &'static [(&'static str, Self)] = &[("gray", Self::Grey)];
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Paint {
+        #[serde(with = "crate::aliases")]
+        colour: Colour,
+        #[serde(default, with = "crate::aliases::option")]
+        trim: Option<Colour>,
+    }
+
+    fn paint(json: &str) -> (Result<Paint, String>, Vec<Deprecation>) {
+        collect_deprecations(|| serde_json::from_str(json).map_err(|e| e.to_string()))
+    }
+
+    #[test]
+    fn names_match_without_case() {
+        let (paint, deprecations) = paint(r#"{ "colour": "RED", "trim": "Grey" }"#);
+        let paint = paint.unwrap();
+        assert_eq!(Colour::Red, paint.colour);
+        assert_eq!(Some(Colour::Grey), paint.trim);
+        assert!(deprecations.is_empty());
+    }
+
+    #[test]
+    fn aliases_are_reported() {
+        let (paint, deprecations) = paint(r#"{ "colour": "gray" }"#);
+        assert_eq!(Colour::Grey, paint.unwrap().colour);
+        assert_eq!(vec![Deprecation::new("gray", "grey")], deprecations);
+        assert_eq!(
+            "`gray` is deprecated; use `grey` instead",
+            deprecations[0].to_string()
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        let (paint, _) = paint(r#"{ "colour": "blue" }"#);
+        assert!(paint
+            .unwrap_err()
+            .contains("unknown value `blue`; expected one of `red`, `grey`"));
+    }
+
+    #[test]
+    fn canonical_names_are_serialized() {
+        let (paint, _) = paint(r#"{ "colour": "Gray", "trim": "RED" }"#);
+        assert_eq!(
+            r#"{"colour":"grey","trim":"red"}"#,
+            serde_json::to_string(&paint.unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn deprecations_outside_collection_are_ignored() {
+        report_deprecation(Deprecation::new("old", "new"));
+        let ((), deprecations) = collect_deprecations(|| ());
+        assert!(deprecations.is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+//! Byte size (de)serialization
+//!
+//! Use with `#[serde(with = "spin_serde::bytes")]` on a `u64` field, or
+//! `#[serde(with = "spin_serde::bytes::option")]` on an `Option<u64>`. Sizes
+//! are written as an integer number of bytes or as a string with an optional
+//! unit, such as `"512 KiB"` or `"2MB"`. See [`parse`].
+//!
+//! Where a size should be kept as it was written, use [`ByteSize`] instead.
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject},
+    JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A number of bytes, with an optional unit, e.g. `"512"`, `"64KB"` or
+/// `"128MiB"`. Supported units are `B`, `KB`, `MB`, `GB` (powers of 1000) and
+/// `KiB`, `MiB`, `GiB` (powers of 1024).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(into = "String", try_from = "String")]
+pub struct ByteSize(String);
+
+impl ByteSize {
+    /// The number of bytes.
+    pub fn bytes(&self) -> u64 {
+        parse(&self.0).expect("ByteSize should have been validated")
+    }
+}
+
+impl std::fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<ByteSize> for String {
+    fn from(value: ByteSize) -> Self {
+        value.0
+    }
+}
+
+impl TryFrom<String> for ByteSize {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse(&value)?;
+        Ok(Self(value))
+    }
+}
+
+// Largest first, so that `format` picks the shortest spelling.
+const UNITS: &[(&str, u64)] = &[
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("KiB", 1 << 10),
+    ("KB", 1_000),
+    ("B", 1),
+];
+
+/// Parses a number of bytes, with an optional unit which may be separated
+/// from the number by whitespace. See [`ByteSize`].
+pub fn parse(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let digits_end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    if digits_end == 0 {
+        return Err("expected a size such as `512`, `64KB` or `128MiB`".into());
+    }
+    let (number, unit) = value.split_at(digits_end);
+    let number: u64 = number.parse().map_err(|e| format!("{e}"))?;
+    let multiplier = match unit.trim_start() {
+        "" => 1,
+        unit => UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, multiplier)| *multiplier)
+            .ok_or_else(|| {
+                format!(
+                    "unknown unit `{unit}`; expected one of `B`, `KB`, `MB`, `GB`, `KiB`, `MiB`, `GiB`"
+                )
+            })?,
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| "size is too large".into())
+}
+
+/// Formats a number of bytes using the largest unit which divides it
+/// exactly, e.g. `"512KiB"`. The result can be read back with [`parse`].
+pub fn format(bytes: u64) -> String {
+    let (unit, multiplier) = UNITS
+        .iter()
+        .find(|(_, multiplier)| bytes != 0 && bytes.checked_rem(*multiplier) == Some(0))
+        .unwrap_or(&("B", 1));
+    format!("{}{unit}", bytes / multiplier)
+}
+
+/// Serializes a number of bytes as a string. See [`format`].
+pub fn serialize<S: Serializer>(bytes: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(*bytes))
+}
+
+/// Deserializes a number of bytes from an integer or a string. See [`parse`].
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    deserializer.deserialize_any(Visitor)
+}
+
+/// A JSON schema for a byte size, for use with
+/// `#[schemars(schema_with = "spin_serde::bytes::json_schema")]`.
+pub fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(vec![InstanceType::Integer, InstanceType::String].into()),
+        metadata: Some(Box::new(Metadata {
+            description: Some(
+                "A number of bytes, or a size with a unit such as `512KiB` or `2MB`".into(),
+            ),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+struct Visitor;
+
+impl de::Visitor<'_> for Visitor {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a number of bytes, or a size such as `512KiB` or `2MB`")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<u64, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<u64, E> {
+        u64::try_from(v).map_err(|_| E::custom("size must not be negative"))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<u64, E> {
+        parse(v).map_err(E::custom)
+    }
+}
+
+/// As the parent module, for `Option` fields.
+pub mod option {
+    use super::*;
+
+    /// Serializes a number of bytes, if any, as a string.
+    pub fn serialize<S: Serializer>(bytes: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_some(&format(*bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an optional number of bytes. See [`parse`].
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        #[derive(Deserialize)]
+        struct Bytes(#[serde(deserialize_with = "super::deserialize")] u64);
+
+        Ok(Option::<Bytes>::deserialize(deserializer)?.map(|Bytes(bytes)| bytes))
+    }
+
+    /// A JSON schema for a byte size. The field should also have
+    /// `#[serde(default)]`.
+    pub fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        super::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Limits {
+        #[serde(with = "crate::bytes")]
+        memory: u64,
+        #[serde(default, with = "crate::bytes::option")]
+        cache: Option<u64>,
+    }
+
+    #[test]
+    fn sizes_are_parsed() {
+        assert_eq!(Ok(512), parse("512"));
+        assert_eq!(Ok(64_000), parse("64KB"));
+        assert_eq!(Ok(2_000_000), parse("2MB"));
+        assert_eq!(Ok(512 << 10), parse("512 KiB"));
+        assert_eq!(Ok(128 << 20), parse("128 MiB"));
+        assert!(parse("MiB").is_err());
+        assert!(parse("99999999999GiB").is_err());
+        assert_eq!(
+            Err(
+                "unknown unit `mb`; expected one of `B`, `KB`, `MB`, `GB`, `KiB`, `MiB`, `GiB`"
+                    .into()
+            ),
+            parse("12mb")
+        );
+
+        assert_eq!(
+            1 << 30,
+            ByteSize::try_from("1GiB".to_owned()).unwrap().bytes()
+        );
+    }
+
+    #[test]
+    fn sizes_are_formatted_with_the_largest_exact_unit() {
+        assert_eq!("0B", format(0));
+        assert_eq!("1500B", format(1500));
+        assert_eq!("512KiB", format(512 << 10));
+        assert_eq!("2MB", format(2_000_000));
+        assert_eq!("3GiB", format(3 << 30));
+        for bytes in [0, 1, 999, 1024, 1_000_000, u64::MAX] {
+            assert_eq!(Ok(bytes), parse(&format(bytes)));
+        }
+    }
+
+    #[test]
+    fn sizes_deserialize_from_integers_and_strings() {
+        let limits: Limits = serde_json::from_str(r#"{ "memory": 1024, "cache": "2MB" }"#).unwrap();
+        assert_eq!(1024, limits.memory);
+        assert_eq!(Some(2_000_000), limits.cache);
+        assert_eq!(
+            r#"{"memory":"1KiB","cache":"2MB"}"#,
+            serde_json::to_string(&limits).unwrap()
+        );
+
+        let limits: Limits = serde_json::from_str(r#"{ "memory": "512 KiB" }"#).unwrap();
+        assert_eq!(None, limits.cache);
+
+        let err = serde_json::from_str::<Limits>(r#"{ "memory": -1 }"#).unwrap_err();
+        assert!(err.to_string().contains("must not be negative"), "{err}");
+        let err = serde_json::from_str::<Limits>(r#"{ "memory": "1TB" }"#).unwrap_err();
+        assert!(err.to_string().contains("unknown unit `TB`"), "{err}");
+    }
+}
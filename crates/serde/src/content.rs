@@ -0,0 +1,258 @@
+//! Content (de)serialization: inline bytes and content digests
+//!
+//! These types represent the ways an application refers to a piece of
+//! content, such as a component's Wasm binary: the bytes themselves, or the
+//! digest of bytes stored elsewhere.
+
+use base64::{
+    alphabet,
+    display::Base64Display,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject},
+    JsonSchema,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+// Writes unpadded base64, as `crate::base64` does, but also reads padded
+// base64.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Bytes which (de)serialize as a base64 string.
+///
+/// Serializers which support it write the encoding directly, and
+/// deserializers decode from their input, without building an intermediate
+/// string. The `Debug` representation shows only the length, so that large
+/// content is not written to logs.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Base64Bytes(Vec<u8>);
+
+impl Base64Bytes {
+    /// Returns the bytes.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl std::fmt::Debug for Base64Bytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Base64Bytes(<{} bytes>)", self.0.len())
+    }
+}
+
+impl std::ops::Deref for Base64Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Base64Bytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Base64Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&[u8]> for Base64Bytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl From<Base64Bytes> for Vec<u8> {
+    fn from(value: Base64Bytes) -> Self {
+        value.0
+    }
+}
+
+impl Serialize for Base64Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&Base64Display::new(&self.0, &BASE64))
+    }
+}
+
+impl<'de> Deserialize<'de> for Base64Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(Base64Visitor)
+    }
+}
+
+// Decodes from the deserializer's string, without copying it first.
+struct Base64Visitor;
+
+impl de::Visitor<'_> for Base64Visitor {
+    type Value = Base64Bytes;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a base64 string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Base64Bytes, E> {
+        BASE64
+            .decode(v)
+            .map(Base64Bytes)
+            .map_err(|e| E::custom(format!("invalid base64: {e}")))
+    }
+}
+
+impl JsonSchema for Base64Bytes {
+    fn schema_name() -> String {
+        "Base64Bytes".to_owned()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some("Base64-encoded bytes".into()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+crate::constrained_id! {
+    /// A content digest, of the form `sha256:<hex>`, where `<hex>` is the 64
+    /// lowercase hexadecimal digits of the content's SHA-256 hash.
+    pub struct DigestRef;
+    validate = validate_digest;
+}
+
+impl DigestRef {
+    /// The digest of the given content.
+    pub fn sha256(content: impl AsRef<[u8]>) -> Self {
+        Self(format!("sha256:{:x}", Sha256::digest(content)))
+    }
+
+    /// The name of the hash algorithm, e.g. `sha256`.
+    pub fn algorithm(&self) -> &str {
+        self.split().0
+    }
+
+    /// The hexadecimal hash.
+    pub fn hex(&self) -> &str {
+        self.split().1
+    }
+
+    /// Checks that the given content has this digest.
+    pub fn verify(&self, content: impl AsRef<[u8]>) -> Result<(), String> {
+        let actual = Self::sha256(content);
+        if actual == *self {
+            Ok(())
+        } else {
+            Err(format!(
+                "content digest mismatch; expected {self}, found {actual}"
+            ))
+        }
+    }
+
+    fn split(&self) -> (&str, &str) {
+        self.0
+            .split_once(':')
+            .expect("DigestRef should have been validated")
+    }
+}
+
+fn validate_digest(digest: &str) -> Result<(), String> {
+    let Some((algorithm, hex)) = digest.split_once(':') else {
+        return Err("expected a digest of the form `sha256:<hex>`".into());
+    };
+    if algorithm != "sha256" {
+        return Err(format!(
+            "unsupported digest algorithm {algorithm:?}; expected \"sha256\""
+        ));
+    }
+    if hex.len() != 64 {
+        return Err(format!(
+            "SHA-256 digests must have 64 hex digits; got {}",
+            hex.len()
+        ));
+    }
+    if let Some(ch) = hex.chars().find(|ch| !matches!(ch, '0'..='9' | 'a'..='f')) {
+        return Err(format!(
+            "SHA-256 digests may only contain lowercase hex digits; got {ch:?}"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SHA-256 of "hello"
+    const HELLO: &str = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn bytes_round_trip_as_base64() {
+        let bytes = Base64Bytes::from(b"hello".as_slice());
+        let json = serde_json::to_string(&bytes).unwrap();
+        assert_eq!(r#""aGVsbG8""#, json);
+        assert_eq!(bytes, serde_json::from_str(&json).unwrap());
+        assert_eq!(
+            bytes,
+            serde_json::from_str::<Base64Bytes>(r#""aGVsbG8=""#).unwrap()
+        );
+        let err = serde_json::from_str::<Base64Bytes>(r#""not base64!""#).unwrap_err();
+        assert!(err.to_string().contains("invalid base64"), "{err}");
+    }
+
+    #[test]
+    fn bytes_debug_omits_content() {
+        let bytes = Base64Bytes::from(vec![0; 4096]);
+        assert_eq!("Base64Bytes(<4096 bytes>)", format!("{bytes:?}"));
+    }
+
+    #[test]
+    fn digests_are_validated() {
+        let digest = DigestRef::try_from(HELLO).unwrap();
+        assert_eq!("sha256", digest.algorithm());
+        assert_eq!(&HELLO[7..], digest.hex());
+        assert_eq!(digest, DigestRef::sha256("hello"));
+
+        for (bad, reason) in [
+            ("digest", "of the form"),
+            ("sha512:abcd", "unsupported digest algorithm"),
+            ("sha256:abcd", "64 hex digits"),
+            (&HELLO.to_uppercase().replace("SHA", "sha"), "lowercase"),
+        ] {
+            let err = DigestRef::try_from(bad).unwrap_err();
+            assert!(err.contains(reason), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn digests_verify_content() {
+        let digest = DigestRef::sha256("hello");
+        digest.verify("hello").unwrap();
+        let err = digest.verify("goodbye").unwrap_err();
+        assert!(err.starts_with("content digest mismatch"), "{err}");
+    }
+
+    #[test]
+    fn digests_deserialize_with_reasons() {
+        let json = format!("{HELLO:?}");
+        let digest: DigestRef = serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&digest).unwrap());
+        let err = serde_json::from_str::<DigestRef>(r#""digest""#).unwrap_err();
+        assert!(err.to_string().contains("sha256:<hex>"), "{err}");
+    }
+}
@@ -0,0 +1,246 @@
+//! Duration (de)serialization
+//!
+//! Use with `#[serde(with = "spin_serde::duration")]` on a
+//! [`Duration`](std::time::Duration) field, or
+//! `#[serde(with = "spin_serde::duration::option")]` on an `Option` of one.
+//! Durations are written as strings such as `"30s"`, `"5m"` or `"1h30m"`.
+//! See [`parse`].
+//!
+//! Where a duration should be kept as it was written, use [`HumanDuration`]
+//! instead.
+
+use std::time::Duration;
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject},
+    JsonSchema,
+};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A duration made up of one or more `<integer><unit>` pairs, e.g. `"30s"` or
+/// `"1h30m"`. Supported units are `ms`, `s`, `m`, `h` and `d`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(into = "String", try_from = "String")]
+pub struct HumanDuration(String);
+
+impl HumanDuration {
+    /// The duration.
+    pub fn duration(&self) -> Duration {
+        parse(&self.0).expect("HumanDuration should have been validated")
+    }
+}
+
+impl std::fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<HumanDuration> for String {
+    fn from(value: HumanDuration) -> Self {
+        value.0
+    }
+}
+
+impl TryFrom<String> for HumanDuration {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse(&value)?;
+        Ok(Self(value))
+    }
+}
+
+// Largest first, so that `format` writes the most significant unit first.
+const UNITS: &[(&str, u64)] = &[
+    ("d", 86_400_000),
+    ("h", 3_600_000),
+    ("m", 60_000),
+    ("s", 1_000),
+    ("ms", 1),
+];
+
+/// Parses a duration made up of one or more `<integer><unit>` pairs, which
+/// may be separated by whitespace, as may each number and unit. See
+/// [`HumanDuration`].
+pub fn parse(value: &str) -> Result<Duration, String> {
+    let mut remainder = value.trim();
+    if remainder.is_empty() {
+        return Err("expected a duration such as `30s` or `1h30m`".into());
+    }
+    let mut total = Duration::ZERO;
+    while !remainder.is_empty() {
+        let digits_end = remainder
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or("missing unit; expected one of `ms`, `s`, `m`, `h`, `d`")?;
+        if digits_end == 0 {
+            return Err("expected a number before each unit".into());
+        }
+        let (number, rest) = remainder.split_at(digits_end);
+        let number: u64 = number.parse().map_err(|e| format!("{e}"))?;
+        let rest = rest.trim_start();
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let (unit, rest) = rest.split_at(unit_end);
+        if unit.is_empty() {
+            return Err("missing unit; expected one of `ms`, `s`, `m`, `h`, `d`".into());
+        }
+        let unit_millis = UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, millis)| *millis)
+            .ok_or_else(|| {
+                format!("unknown unit `{unit}`; expected one of `ms`, `s`, `m`, `h`, `d`")
+            })?;
+        let millis = number
+            .checked_mul(unit_millis)
+            .ok_or("duration is too large")?;
+        total = total
+            .checked_add(Duration::from_millis(millis))
+            .ok_or("duration is too large")?;
+        remainder = rest.trim_start();
+    }
+    Ok(total)
+}
+
+/// Formats a duration, rounded down to the millisecond, as one or more
+/// `<integer><unit>` pairs, e.g. `"1h30m"`. The result can be read back with
+/// [`parse`].
+pub fn format(duration: Duration) -> String {
+    let mut millis = duration.as_millis();
+    if millis == 0 {
+        return "0s".into();
+    }
+    let mut formatted = String::new();
+    for (unit, unit_millis) in UNITS {
+        let unit_millis = u128::from(*unit_millis);
+        if millis >= unit_millis {
+            formatted.push_str(&format!("{}{unit}", millis / unit_millis));
+            millis %= unit_millis;
+        }
+    }
+    formatted
+}
+
+/// Serializes a duration as a string. See [`format`].
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(*duration))
+}
+
+/// Deserializes a duration from a string. See [`parse`].
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse(&value).map_err(D::Error::custom)
+}
+
+/// A JSON schema for a duration, for use with
+/// `#[schemars(schema_with = "spin_serde::duration::json_schema")]`.
+pub fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata {
+            description: Some("A duration such as `30s`, `5m` or `1h30m`".into()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// As the parent module, for `Option` fields.
+pub mod option {
+    use super::*;
+
+    /// Serializes a duration, if any, as a string.
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&format(*duration)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an optional duration. See [`parse`].
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| parse(&value).map_err(D::Error::custom))
+            .transpose()
+    }
+
+    /// A JSON schema for a duration. The field should also have
+    /// `#[serde(default)]`.
+    pub fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        super::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Timeouts {
+        #[serde(with = "crate::duration")]
+        request: Duration,
+        #[serde(default, with = "crate::duration::option")]
+        ttl: Option<Duration>,
+    }
+
+    #[test]
+    fn durations_are_parsed() {
+        assert_eq!(Ok(Duration::from_secs(30)), parse("30s"));
+        assert_eq!(Ok(Duration::from_secs(300)), parse("5m"));
+        assert_eq!(Ok(Duration::from_secs(5400)), parse("1h30m"));
+        assert_eq!(Ok(Duration::from_secs(5400)), parse("1h 30 m"));
+        assert!(parse("30").is_err());
+        assert!(parse("s").is_err());
+        assert_eq!(
+            Err("unknown unit `sec`; expected one of `ms`, `s`, `m`, `h`, `d`".into()),
+            parse("30 sec")
+        );
+        assert_eq!(
+            Duration::from_millis(250),
+            HumanDuration::try_from("250ms".to_owned())
+                .unwrap()
+                .duration()
+        );
+    }
+
+    #[test]
+    fn durations_are_formatted_by_unit() {
+        assert_eq!("0s", format(Duration::ZERO));
+        assert_eq!("250ms", format(Duration::from_micros(250_999)));
+        assert_eq!("1h30m", format(Duration::from_secs(5400)));
+        assert_eq!("1d1s5ms", format(Duration::from_millis(86_401_005)));
+        for millis in [0, 1, 59_999, 90_061_001] {
+            let duration = Duration::from_millis(millis);
+            assert_eq!(Ok(duration), parse(&format(duration)));
+        }
+    }
+
+    #[test]
+    fn durations_round_trip() {
+        let timeouts: Timeouts =
+            serde_json::from_str(r#"{ "request": "30s", "ttl": "5m" }"#).unwrap();
+        assert_eq!(Duration::from_secs(30), timeouts.request);
+        assert_eq!(Some(Duration::from_secs(300)), timeouts.ttl);
+        assert_eq!(
+            r#"{"request":"30s","ttl":"5m"}"#,
+            serde_json::to_string(&timeouts).unwrap()
+        );
+
+        let timeouts: Timeouts = serde_json::from_str(r#"{ "request": "1m30s" }"#).unwrap();
+        assert_eq!(None, timeouts.ttl);
+
+        let err = serde_json::from_str::<Timeouts>(r#"{ "request": 30 }"#).unwrap_err();
+        assert!(err.to_string().contains("expected a string"), "{err}");
+        let err = serde_json::from_str::<Timeouts>(r#"{ "request": "30" }"#).unwrap_err();
+        assert!(err.to_string().contains("missing unit"), "{err}");
+    }
+}
@@ -1,85 +1,380 @@
 //! ID (de)serialization
 
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-
-/// An ID is a non-empty string containing one or more component model
-/// `word`s separated by a delimiter char.
-#[derive(
-    Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize, JsonSchema,
-)]
-#[serde(into = "String", try_from = "String")]
-pub struct Id<const DELIM: char, const LOWER: bool>(String);
-
-impl<const DELIM: char, const LOWER: bool> std::fmt::Display for Id<DELIM, LOWER> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+/// Defines a newtype around `String` whose values are checked by a
+/// validation function, `fn(&str) -> Result<(), String>`. The type
+/// (de)serializes as a string, failing if the string is not valid.
+///
+/// ```ignore
+/// spin_serde::constrained_id! {
+///     /// A lowercase region name, such as `eu-west`.
+///     pub struct RegionId;
+///     validate = |id| spin_serde::id::validate_words(id, '-', true);
+/// }
+/// ```
+#[macro_export]
+macro_rules! constrained_id {
+    (
+        $(#[doc = $doc:literal])*
+        $vis:vis struct $name:ident;
+        validate = $validate:expr;
+    ) => {
+        $(#[doc = $doc])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
+        $vis struct $name(String);
+
+        impl $name {
+            /// Checks whether the given string is a valid ID of this type.
+            pub fn validate(id: &str) -> Result<(), String> {
+                let validate: fn(&str) -> Result<(), String> = $validate;
+                validate(id)
+            }
+
+            /// Returns the ID as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = String;
+
+            fn try_from(id: String) -> Result<Self, Self::Error> {
+                Self::validate(&id)?;
+                Ok(Self(id))
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = String;
+
+            fn try_from(id: &str) -> Result<Self, Self::Error> {
+                Self::validate(id)?;
+                Ok(Self(id.to_owned()))
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(id: &str) -> Result<Self, Self::Err> {
+                Self::try_from(id)
+            }
+        }
+
+        impl $crate::__private::serde::Serialize for $name {
+            fn serialize<S: $crate::__private::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> $crate::__private::serde::Deserialize<'de> for $name {
+            fn deserialize<D: $crate::__private::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                let id = <String as $crate::__private::serde::Deserialize>::deserialize(
+                    deserializer,
+                )?;
+                Self::try_from(id).map_err(<D::Error as $crate::__private::serde::de::Error>::custom)
+            }
+        }
+
+        impl $crate::__private::schemars::JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_owned()
+            }
+
+            fn json_schema(
+                _gen: &mut $crate::__private::schemars::gen::SchemaGenerator,
+            ) -> $crate::__private::schemars::schema::Schema {
+                let doc: &[&str] = &[$($doc),*];
+                $crate::id::string_schema(doc)
+            }
+        }
+    };
+}
+
+crate::constrained_id! {
+    /// A "kebab-case" identifier: one or more component model `word`s
+    /// separated by `-`.
+    pub struct KebabId;
+    validate = |id| validate_words(id, '-', false);
+}
+
+crate::constrained_id! {
+    /// A "snake_case" identifier: one or more component model `word`s
+    /// separated by `_`.
+    pub struct SnakeId;
+    validate = |id| validate_words(id, '_', false);
+}
+
+crate::constrained_id! {
+    /// A lower-case "snake_case" identifier: one or more lowercase component
+    /// model `word`s separated by `_`.
+    pub struct LowerSnakeId;
+    validate = |id| validate_words(id, '_', true);
+}
+
+impl KebabId {
+    /// The same words, separated by `_`.
+    pub fn to_snake_id(&self) -> SnakeId {
+        SnakeId(self.0.replace('-', "_"))
     }
 }
 
-impl<const DELIM: char, const LOWER: bool> AsRef<str> for Id<DELIM, LOWER> {
-    fn as_ref(&self) -> &str {
-        &self.0
+impl SnakeId {
+    /// The same words, separated by `-`.
+    pub fn to_kebab_id(&self) -> KebabId {
+        KebabId(self.0.replace('_', "-"))
     }
 }
 
-impl<const DELIM: char, const LOWER: bool> From<Id<DELIM, LOWER>> for String {
-    fn from(value: Id<DELIM, LOWER>) -> Self {
-        value.0
+impl LowerSnakeId {
+    /// The same words, separated by `-`.
+    pub fn to_kebab_id(&self) -> KebabId {
+        KebabId(self.0.replace('_', "-"))
     }
 }
 
-impl<const DELIM: char, const LOWER: bool> TryFrom<String> for Id<DELIM, LOWER> {
-    type Error = String;
+impl From<LowerSnakeId> for SnakeId {
+    fn from(value: LowerSnakeId) -> Self {
+        Self(value.0)
+    }
+}
 
-    fn try_from(id: String) -> Result<Self, Self::Error> {
-        if id.is_empty() {
-            return Err("empty".into());
+/// Checks that `id` is a non-empty string of one or more component model
+/// `word`s separated by `delim`. A word is an ASCII letter followed by ASCII
+/// letters and digits, with its letters either all lowercase or all
+/// uppercase. If `lower`, every word must be lowercase.
+pub fn validate_words(id: &str, delim: char, lower: bool) -> Result<(), String> {
+    if id.is_empty() {
+        return Err("empty".into());
+    }
+    // Special-case common "wrong separator" errors
+    if let Some(wrong) = wrong_delim(delim) {
+        if id.contains(wrong) {
+            return Err(format!(
+                "words must be separated with {delim:?}, not {wrong:?}"
+            ));
         }
-        // Special-case common "wrong separator" errors
-        if let Some(wrong) = wrong_delim::<DELIM>() {
-            if id.contains(wrong) {
-                return Err(format!(
-                    "words must be separated with {DELIM:?}, not {wrong:?}"
-                ));
-            }
+    }
+    for word in id.split(delim) {
+        if word.is_empty() {
+            return Err(format!("{delim:?}-separated words must not be empty"));
         }
-        for word in id.split(DELIM) {
-            if word.is_empty() {
-                return Err(format!("{DELIM:?}-separated words must not be empty"));
-            }
-            let mut chars = word.chars();
-            let first = chars.next().unwrap();
-            if !first.is_ascii_alphabetic() {
-                return Err(format!(
-                    "{DELIM:?}-separated words must start with an ASCII letter; got {first:?}"
-                ));
-            }
-            let word_is_uppercase = first.is_ascii_uppercase();
-            for ch in chars {
-                if ch.is_ascii_digit() {
-                } else if !ch.is_ascii_alphanumeric() {
-                    return Err(format!(
-                        "{DELIM:?}-separated words may only contain alphanumeric ASCII; got {ch:?}"
-                    ));
-                } else if ch.is_ascii_uppercase() != word_is_uppercase {
-                    return Err(format!("{DELIM:?}-separated words must be all lowercase or all UPPERCASE; got {word:?}"));
-                }
-            }
-            if LOWER && word_is_uppercase {
+        let mut chars = word.chars();
+        let first = chars.next().unwrap();
+        if !first.is_ascii_alphabetic() {
+            return Err(format!(
+                "{delim:?}-separated words must start with an ASCII letter; got {first:?}"
+            ));
+        }
+        let word_is_uppercase = first.is_ascii_uppercase();
+        for ch in chars {
+            if ch.is_ascii_digit() {
+            } else if !ch.is_ascii_alphanumeric() {
                 return Err(format!(
-                    "Lower-case identifiers must be all lowercase; got {id:?}"
+                    "{delim:?}-separated words may only contain alphanumeric ASCII; got {ch:?}"
                 ));
+            } else if ch.is_ascii_uppercase() != word_is_uppercase {
+                return Err(format!("{delim:?}-separated words must be all lowercase or all UPPERCASE; got {word:?}"));
             }
         }
-        Ok(Self(id))
+        if lower && word_is_uppercase {
+            return Err(format!(
+                "Lower-case identifiers must be all lowercase; got {id:?}"
+            ));
+        }
     }
+    Ok(())
 }
 
-const fn wrong_delim<const DELIM: char>() -> Option<char> {
-    match DELIM {
+fn wrong_delim(delim: char) -> Option<char> {
+    match delim {
         '_' => Some('-'),
         '-' => Some('_'),
         _ => None,
     }
 }
+
+/// The JSON schema of a [`constrained_id`] type with the given doc comment
+/// lines.
+#[doc(hidden)]
+pub fn string_schema(doc: &[&str]) -> schemars::schema::Schema {
+    use schemars::schema::{InstanceType, Metadata, SchemaObject};
+
+    let description = doc
+        .iter()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join(" ");
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: (!description.is_empty()).then(|| {
+            Box::new(Metadata {
+                description: Some(description),
+                ..Default::default()
+            })
+        }),
+        ..Default::default()
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_validated() {
+        for good in ["a", "abc-def", "a1-b2", "HTTP-server"] {
+            KebabId::try_from(good).expect(good);
+        }
+        for bad in ["", "-a", "a-", "a--b", "1a", "a-1", "Ab", "a_b", "a.b"] {
+            KebabId::try_from(bad).expect_err(bad);
+        }
+        assert_eq!(
+            Err("words must be separated with '_', not '-'".to_owned()),
+            SnakeId::validate("a-b")
+        );
+        LowerSnakeId::try_from("HTTP_port").expect_err("uppercase");
+        SnakeId::try_from("HTTP_port").expect("uppercase");
+    }
+
+    #[test]
+    fn ids_convert_between_separators() {
+        let kebab = KebabId::try_from("HTTP-server2").unwrap();
+        assert_eq!("HTTP_server2", kebab.to_snake_id().as_str());
+        assert_eq!(kebab, kebab.to_snake_id().to_kebab_id());
+        let lower = LowerSnakeId::try_from("api_key").unwrap();
+        assert_eq!("api-key", lower.to_kebab_id().as_str());
+        assert_eq!("api_key", SnakeId::from(lower).as_str());
+    }
+
+    #[test]
+    fn ids_deserialize_with_reasons() {
+        let id: KebabId = serde_json::from_str(r#""my-component""#).unwrap();
+        assert_eq!("\"my-component\"", serde_json::to_string(&id).unwrap());
+        let err = serde_json::from_str::<KebabId>(r#""my_component""#).unwrap_err();
+        assert!(err.to_string().contains("separated with '-'"), "{err}");
+    }
+
+    // A small, seeded generator, so that fuzz failures are reproducible.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+
+        fn string(&mut self, alphabet: &[u8], max_len: usize) -> String {
+            let len = self.next(max_len + 1);
+            (0..len)
+                .map(|_| alphabet[self.next(alphabet.len())] as char)
+                .collect()
+        }
+
+        fn word(&mut self, upper: bool) -> String {
+            let (letters, digits) = if upper {
+                (b"ABCXYZ".as_slice(), b"0189".as_slice())
+            } else {
+                (b"abcxyz".as_slice(), b"0189".as_slice())
+            };
+            let mut word = String::from(letters[self.next(letters.len())] as char);
+            for _ in 0..self.next(4) {
+                let pool = if self.next(3) == 0 { digits } else { letters };
+                word.push(pool[self.next(pool.len())] as char);
+            }
+            word
+        }
+    }
+
+    #[test]
+    fn fuzz_arbitrary_strings() {
+        let mut rng = XorShift(0x05ee_d1d5);
+        for _ in 0..20_000 {
+            let id = rng.string(b"aZ9-_.x Y", 10);
+            let kebab = KebabId::validate(&id);
+            let snake = SnakeId::validate(&id);
+            let lower = LowerSnakeId::validate(&id);
+
+            // Kebab and snake differ only in their separator
+            if !id.contains('_') {
+                assert_eq!(
+                    kebab.is_ok(),
+                    SnakeId::validate(&id.replace('-', "_")).is_ok(),
+                    "{id:?}"
+                );
+            }
+            // Lower snake IDs are exactly the lowercase snake IDs
+            assert_eq!(
+                lower.is_ok(),
+                snake.is_ok() && !id.chars().any(|c| c.is_ascii_uppercase()),
+                "{id:?}"
+            );
+            if let Ok(()) = kebab {
+                let parsed = KebabId::try_from(id.as_str()).unwrap();
+                assert_eq!(id, parsed.to_string());
+                assert_eq!(parsed, parsed.to_snake_id().to_kebab_id());
+                SnakeId::validate(parsed.to_snake_id().as_str()).unwrap();
+            }
+            if let Ok(()) = lower {
+                KebabId::validate(
+                    LowerSnakeId::try_from(id.as_str())
+                        .unwrap()
+                        .to_kebab_id()
+                        .as_str(),
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn fuzz_generated_ids() {
+        let mut rng = XorShift(0xc0ffee);
+        for _ in 0..5_000 {
+            let words = (0..=rng.next(3))
+                .map(|_| {
+                    let upper = rng.next(4) == 0;
+                    rng.word(upper)
+                })
+                .collect::<Vec<_>>();
+            let kebab = words.join("-");
+            let snake = words.join("_");
+            KebabId::validate(&kebab).unwrap();
+            SnakeId::validate(&snake).unwrap();
+            assert_eq!(
+                LowerSnakeId::validate(&snake).is_ok(),
+                snake == snake.to_lowercase(),
+                "{snake:?}"
+            );
+            let json = serde_json::to_string(&kebab).unwrap();
+            let id: KebabId = serde_json::from_str(&json).unwrap();
+            assert_eq!(kebab, id.as_str());
+        }
+    }
+}
@@ -2,20 +2,29 @@
 
 #![deny(missing_docs)]
 
+pub mod aliases;
 pub mod base64;
+pub mod bytes;
+pub mod content;
 pub mod dependencies;
+pub mod duration;
 pub mod id;
 mod version;
 
-pub use version::{FixedStringVersion, FixedVersion, FixedVersionBackwardCompatible};
+pub use bytes::ByteSize;
+pub use content::{Base64Bytes, DigestRef};
+pub use duration::HumanDuration;
+pub use version::{
+    FixedStringVersion, FixedVersion, FixedVersionBackwardCompatible, SemverCompat, VersionRange,
+};
 
 pub use dependencies::{DependencyName, DependencyPackageName};
 
-/// A "kebab-case" identifier.
-pub type KebabId = id::Id<'-', false>;
+pub use id::{KebabId, LowerSnakeId, SnakeId};
 
-/// A "snake_case" identifier.
-pub type SnakeId = id::Id<'_', false>;
-
-/// A lower-case "snake_case" identifier.
-pub type LowerSnakeId = id::Id<'_', true>;
+/// Re-exports for use by [`constrained_id`].
+#[doc(hidden)]
+pub mod __private {
+    pub use schemars;
+    pub use serde;
+}
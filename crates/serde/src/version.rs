@@ -1,4 +1,10 @@
-use schemars::JsonSchema;
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{
+        InstanceType, NumberValidation, Schema, SchemaObject, StringValidation, SubschemaValidation,
+    },
+    JsonSchema,
+};
 use serde::{Deserialize, Serialize};
 
 /// FixedVersion represents a version integer field with a const value.
@@ -69,3 +75,248 @@ impl<const V: usize> TryFrom<String> for FixedStringVersion<V> {
         Ok(Self)
     }
 }
+
+/// SemverCompat represents a version field which accepts any version with
+/// the major version `MAJOR`, so that minor revisions of a format can be read
+/// by older code. The version may be an integer (`2`) or a string of one to
+/// three parts (`"2"`, `"2.1"`, `"2.1.3"`); missing parts are zero.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "VersionRepr", try_from = "VersionRepr")]
+pub struct SemverCompat<const MAJOR: usize> {
+    version: semver::Version,
+}
+
+impl<const MAJOR: usize> SemverCompat<MAJOR> {
+    /// The version which was deserialized.
+    pub fn version(&self) -> &semver::Version {
+        &self.version
+    }
+}
+
+impl<const MAJOR: usize> Default for SemverCompat<MAJOR> {
+    fn default() -> Self {
+        Self {
+            version: semver::Version::new(MAJOR as u64, 0, 0),
+        }
+    }
+}
+
+impl<const MAJOR: usize> From<SemverCompat<MAJOR>> for VersionRepr {
+    fn from(value: SemverCompat<MAJOR>) -> Self {
+        VersionRepr::from_version(value.version)
+    }
+}
+
+impl<const MAJOR: usize> TryFrom<VersionRepr> for SemverCompat<MAJOR> {
+    type Error = String;
+
+    fn try_from(value: VersionRepr) -> Result<Self, Self::Error> {
+        let version = value.parse()?;
+        if version.major != MAJOR as u64 {
+            return Err(format!(
+                "invalid version {version}: expected major version {MAJOR}"
+            ));
+        }
+        Ok(Self { version })
+    }
+}
+
+impl<const MAJOR: usize> JsonSchema for SemverCompat<MAJOR> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        format!("SemverCompat{MAJOR}")
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        major_versions_schema(MAJOR, MAJOR)
+    }
+}
+
+/// VersionRange represents a version field which accepts any version whose
+/// major version is between `MIN` and `MAX` inclusive, in the same forms as
+/// [`SemverCompat`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "VersionRepr", try_from = "VersionRepr")]
+pub struct VersionRange<const MIN: usize, const MAX: usize> {
+    version: semver::Version,
+}
+
+impl<const MIN: usize, const MAX: usize> VersionRange<MIN, MAX> {
+    /// The version which was deserialized.
+    pub fn version(&self) -> &semver::Version {
+        &self.version
+    }
+}
+
+impl<const MIN: usize, const MAX: usize> Default for VersionRange<MIN, MAX> {
+    fn default() -> Self {
+        Self {
+            version: semver::Version::new(MAX as u64, 0, 0),
+        }
+    }
+}
+
+impl<const MIN: usize, const MAX: usize> From<VersionRange<MIN, MAX>> for VersionRepr {
+    fn from(value: VersionRange<MIN, MAX>) -> Self {
+        VersionRepr::from_version(value.version)
+    }
+}
+
+impl<const MIN: usize, const MAX: usize> TryFrom<VersionRepr> for VersionRange<MIN, MAX> {
+    type Error = String;
+
+    fn try_from(value: VersionRepr) -> Result<Self, Self::Error> {
+        let version = value.parse()?;
+        if !(MIN as u64..=MAX as u64).contains(&version.major) {
+            return Err(format!(
+                "invalid version {version}: expected major version {MIN} to {MAX}"
+            ));
+        }
+        Ok(Self { version })
+    }
+}
+
+impl<const MIN: usize, const MAX: usize> JsonSchema for VersionRange<MIN, MAX> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        format!("VersionRange{MIN}To{MAX}")
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        major_versions_schema(MIN, MAX)
+    }
+}
+
+/// The serialized form of a [`SemverCompat`] or [`VersionRange`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VersionRepr {
+    Integer(u64),
+    String(String),
+}
+
+impl VersionRepr {
+    // Versions with no minor or patch version round-trip as integers, which
+    // is how manifests have always written them.
+    fn from_version(version: semver::Version) -> Self {
+        if version.minor == 0 && version.patch == 0 && version.pre.is_empty() {
+            Self::Integer(version.major)
+        } else {
+            Self::String(version.to_string())
+        }
+    }
+
+    fn parse(&self) -> Result<semver::Version, String> {
+        match self {
+            Self::Integer(major) => Ok(semver::Version::new(*major, 0, 0)),
+            Self::String(version) => parse_partial_version(version),
+        }
+    }
+}
+
+/// Parses a version of one to three parts, such as `"2"`, `"2.1"` or
+/// `"2.1.3-beta"`, treating missing parts as zero.
+fn parse_partial_version(version: &str) -> Result<semver::Version, String> {
+    let core_len = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, suffix) = version.split_at(core_len);
+    let padding = match core.matches('.').count() {
+        0 => ".0.0",
+        1 => ".0",
+        _ => "",
+    };
+    semver::Version::parse(&format!("{core}{padding}{suffix}"))
+        .map_err(|e| format!("invalid version {version:?}: {e}"))
+}
+
+/// A schema accepting an integer from `min` to `max`, or a string version
+/// of one to three parts with a major version in that range.
+fn major_versions_schema(min: usize, max: usize) -> Schema {
+    let majors = (min..=max)
+        .map(|major| major.to_string())
+        .collect::<Vec<_>>()
+        .join("|");
+    let integer = SchemaObject {
+        instance_type: Some(InstanceType::Integer.into()),
+        number: Some(Box::new(NumberValidation {
+            minimum: Some(min as f64),
+            maximum: Some(max as f64),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    let string = SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(format!(r"^({majors})(\.[0-9]+){{0,2}}([-+].*)?$")),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(vec![integer.into(), string.into()]),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Manifest<V> {
+        version: V,
+    }
+
+    fn parse<V: for<'de> Deserialize<'de>>(version: &str) -> Result<V, String> {
+        let json = format!(r#"{{ "version": {version} }}"#);
+        serde_json::from_str::<Manifest<V>>(&json)
+            .map(|m| m.version)
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn semver_compat_accepts_same_major() {
+        let v: SemverCompat<2> = parse("2").unwrap();
+        assert_eq!(&semver::Version::new(2, 0, 0), v.version());
+        let v: SemverCompat<2> = parse(r#""2.1""#).unwrap();
+        assert_eq!(&semver::Version::new(2, 1, 0), v.version());
+        let v: SemverCompat<2> = parse(r#""2.1.3""#).unwrap();
+        assert_eq!(&semver::Version::new(2, 1, 3), v.version());
+
+        assert!(parse::<SemverCompat<2>>("3").is_err());
+        assert!(parse::<SemverCompat<2>>(r#""1.9""#).is_err());
+        assert!(parse::<SemverCompat<2>>(r#""two""#).is_err());
+    }
+
+    #[test]
+    fn version_range_accepts_majors_in_range() {
+        let v: VersionRange<1, 3> = parse(r#""3.2""#).unwrap();
+        assert_eq!(&semver::Version::new(3, 2, 0), v.version());
+        assert!(parse::<VersionRange<1, 3>>("1").is_ok());
+        assert!(parse::<VersionRange<1, 3>>("0").is_err());
+        assert!(parse::<VersionRange<1, 3>>(r#""4.0""#).is_err());
+    }
+
+    #[test]
+    fn versions_round_trip() {
+        let json = |version| serde_json::to_string(&Manifest { version }).unwrap();
+        assert_eq!(
+            r#"{"version":2}"#,
+            json(parse::<SemverCompat<2>>(r#""2""#).unwrap())
+        );
+        assert_eq!(
+            r#"{"version":"2.1.0"}"#,
+            json(parse::<SemverCompat<2>>(r#""2.1""#).unwrap())
+        );
+    }
+}
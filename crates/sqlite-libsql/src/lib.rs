@@ -3,23 +3,183 @@ use async_trait::async_trait;
 use spin_factor_sqlite::Connection;
 use spin_world::spin::sqlite::sqlite as v3;
 use spin_world::spin::sqlite::sqlite::{self, RowResult};
-use tokio::sync::OnceCell;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OnceCell, OwnedSemaphorePermit, Semaphore};
 
-/// A lazy wrapper around a [`LibSqlConnection`] that implements the [`Connection`] trait.
-pub struct LazyLibSqlConnection {
+/// The most idle connections a pool keeps if it has no connection limit.
+const DEFAULT_MAX_IDLE_CONNECTIONS: usize = 16;
+
+/// How to retry operations which fail because the libSQL server could not be
+/// reached.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The number of retries after the first attempt.
+    ///
+    /// A statement whose request reached the server but whose response was
+    /// lost may be applied more than once, so this defaults to 0.
+    pub max_retries: u32,
+    /// The delay before the first retry, which doubles for each further retry.
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RetryPolicy {
+    async fn run<T, F, Fut>(&self, mut operation: F) -> libsql::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = libsql::Result<T>>,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match operation().await {
+                Err(e) if retries < self.max_retries && is_transport_error(&e) => {
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+// libSQL does not expose its remote protocol errors, so transport failures
+// are recognized by their message.
+fn is_transport_error(e: &libsql::Error) -> bool {
+    match e {
+        libsql::Error::ConnectionFailed(_) => true,
+        libsql::Error::Hrana(e) => e.to_string().starts_with("http error"),
+        _ => false,
+    }
+}
+
+/// A pool of connections to a libSQL server, shared by all the instances
+/// using a database.
+pub struct LibSqlPool {
     url: String,
     token: String,
-    // Since the libSQL client can only be created asynchronously, we wait until
-    // we're in the `Connection` implementation to create. Since we only want to do
-    // this once, we use a `OnceCell` to store it.
-    inner: OnceCell<LibSqlConnection>,
+    retry: RetryPolicy,
+    max_idle: usize,
+    /// Limits the number of open connections, if there is a limit.
+    permits: Option<Arc<Semaphore>>,
+    // The libSQL client can only be created asynchronously, so it is created
+    // when the first connection is needed.
+    database: OnceCell<libsql::Database>,
+    idle: Mutex<Vec<libsql::Connection>>,
 }
 
-impl LazyLibSqlConnection {
-    pub fn new(url: String, token: String) -> Self {
+impl LibSqlPool {
+    /// Create a pool of connections to the libSQL server at `url`.
+    ///
+    /// If `max_connections` is set, acquiring a connection waits while that
+    /// many are in use.
+    pub fn new(
+        url: String,
+        token: String,
+        max_connections: Option<usize>,
+        retry: RetryPolicy,
+    ) -> Self {
         Self {
             url,
             token,
+            retry,
+            max_idle: max_connections.unwrap_or(DEFAULT_MAX_IDLE_CONNECTIONS),
+            permits: max_connections.map(|max| Arc::new(Semaphore::new(max))),
+            database: OnceCell::new(),
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The URL of the libSQL server.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Take an idle connection, or open a new one.
+    pub async fn get(self: &Arc<Self>) -> anyhow::Result<PooledLibSqlConnection> {
+        let permit = match &self.permits {
+            Some(permits) => Some(permits.clone().acquire_owned().await?),
+            None => None,
+        };
+        let idle = self.idle.lock().unwrap().pop();
+        let inner = match idle {
+            Some(inner) => inner,
+            None => {
+                let database = self
+                    .database
+                    .get_or_try_init(|| {
+                        libsql::Builder::new_remote(self.url.clone(), self.token.clone()).build()
+                    })
+                    .await?;
+                database.connect()?
+            }
+        };
+        Ok(PooledLibSqlConnection {
+            connection: Some(LibSqlConnection {
+                inner,
+                retry: self.retry.clone(),
+            }),
+            pool: self.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+/// A connection taken from a [`LibSqlPool`], which returns it to the pool
+/// when dropped.
+pub struct PooledLibSqlConnection {
+    connection: Option<LibSqlConnection>,
+    pool: Arc<LibSqlPool>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl std::ops::Deref for PooledLibSqlConnection {
+    type Target = LibSqlConnection;
+
+    fn deref(&self) -> &LibSqlConnection {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledLibSqlConnection {
+    fn drop(&mut self) {
+        let Some(connection) = self.connection.take() else {
+            return;
+        };
+        // A connection left inside a transaction must not be reused.
+        if !connection.inner.is_autocommit() {
+            return;
+        }
+        let mut idle = self.pool.idle.lock().unwrap();
+        if idle.len() < self.pool.max_idle {
+            idle.push(connection.inner);
+        }
+    }
+}
+
+/// A lazy wrapper around a pooled [`LibSqlConnection`] that implements the [`Connection`] trait.
+pub struct LazyLibSqlConnection {
+    pool: Arc<LibSqlPool>,
+    // Connections are only taken from the pool when a statement is run, so
+    // that instances which do not use the database do not hold one.
+    inner: OnceCell<PooledLibSqlConnection>,
+}
+
+impl LazyLibSqlConnection {
+    pub fn new(pool: Arc<LibSqlPool>) -> Self {
+        Self {
+            pool,
             inner: OnceCell::new(),
         }
     }
@@ -27,11 +187,13 @@ impl LazyLibSqlConnection {
     pub async fn get_or_create_connection(&self) -> Result<&LibSqlConnection, v3::Error> {
         self.inner
             .get_or_try_init(|| async {
-                LibSqlConnection::create(self.url.clone(), self.token.clone())
+                self.pool
+                    .get()
                     .await
                     .context("failed to create SQLite client")
             })
             .await
+            .map(|connection| &**connection)
             .map_err(|_| v3::Error::InvalidConnection)
     }
 }
@@ -52,18 +214,21 @@ impl Connection for LazyLibSqlConnection {
         client.execute_batch(statements).await
     }
 
+    // A pooled connection reports the changes of its previous user's last
+    // statement, so report none until a statement has been run.
     async fn changes(&self) -> Result<u64, sqlite::Error> {
-        let client = self.get_or_create_connection().await?;
-        Ok(client.changes())
+        Ok(self.inner.get().map_or(0, |client| client.changes()))
     }
 
     async fn last_insert_rowid(&self) -> Result<i64, sqlite::Error> {
-        let client = self.get_or_create_connection().await?;
-        Ok(client.last_insert_rowid())
+        Ok(self
+            .inner
+            .get()
+            .map_or(0, |client| client.last_insert_rowid()))
     }
 
     fn summary(&self) -> Option<String> {
-        Some(format!("libSQL at {}", self.url))
+        Some(format!("libSQL at {}", self.pool.url()))
     }
 }
 
@@ -71,13 +236,17 @@ impl Connection for LazyLibSqlConnection {
 #[derive(Clone)]
 pub struct LibSqlConnection {
     inner: libsql::Connection,
+    retry: RetryPolicy,
 }
 
 impl LibSqlConnection {
     pub async fn create(url: String, token: String) -> anyhow::Result<Self> {
         let db = libsql::Builder::new_remote(url, token).build().await?;
         let inner = db.connect()?;
-        Ok(Self { inner })
+        Ok(Self {
+            inner,
+            retry: RetryPolicy::default(),
+        })
     }
 }
 
@@ -87,9 +256,10 @@ impl LibSqlConnection {
         query: &str,
         parameters: Vec<sqlite::Value>,
     ) -> Result<sqlite::QueryResult, sqlite::Error> {
+        let parameters = convert_parameters(&parameters);
         let result = self
-            .inner
-            .query(query, convert_parameters(&parameters))
+            .retry
+            .run(|| self.inner.query(query, parameters.clone()))
             .await
             .map_err(|e| sqlite::Error::Io(e.to_string()))?;
 
@@ -102,7 +272,9 @@ impl LibSqlConnection {
     }
 
     pub async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
-        self.inner.execute_batch(statements).await?;
+        self.retry
+            .run(|| self.inner.execute_batch(statements))
+            .await?;
 
         Ok(())
     }
@@ -0,0 +1,233 @@
+//! A PostgreSQL database behind Spin's SQLite interface.
+//!
+//! Statements are passed to Postgres as written, except that SQLite-style
+//! `?` parameters are renumbered as Postgres's `$N`, so applications must use
+//! SQL which both databases accept.
+
+mod placeholders;
+
+use std::error::Error as StdError;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::BytesMut;
+use spin_factor_sqlite::Connection;
+use spin_world::spin::sqlite::sqlite::{self as v3, RowResult};
+use tokio::sync::OnceCell;
+use tokio_postgres::config::Host;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::{to_sql_checked, IsNull, ToSql, Type};
+use tokio_postgres::{Client, Row};
+
+pub use tokio_postgres::Config;
+
+/// A lazy connection to a PostgreSQL database that implements the [`Connection`] trait.
+pub struct LazyPostgresConnection {
+    config: Arc<Config>,
+    // The client can only be created asynchronously, so it is created the
+    // first time a statement is run.
+    client: OnceCell<Client>,
+    /// The number of rows changed by the most recent statement.
+    changes: AtomicU64,
+}
+
+impl LazyPostgresConnection {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self {
+            config,
+            client: OnceCell::new(),
+            changes: AtomicU64::new(0),
+        }
+    }
+
+    async fn get_or_create_client(&self) -> Result<&Client, v3::Error> {
+        self.client
+            .get_or_try_init(|| spin_postgres_client::connect(&self.config))
+            .await
+            .map_err(|_| v3::Error::InvalidConnection)
+    }
+}
+
+#[async_trait]
+impl Connection for LazyPostgresConnection {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<v3::Value>,
+    ) -> Result<v3::QueryResult, v3::Error> {
+        let statement = placeholders::translate(query).map_err(v3::Error::Io)?;
+        let client = self.get_or_create_client().await?;
+        let statement = client.prepare(&statement).await.map_err(query_failed)?;
+        let parameters = parameters.iter().map(Parameter).collect::<Vec<_>>();
+        let parameters = parameters
+            .iter()
+            .map(|p| p as &(dyn ToSql + Sync))
+            .collect::<Vec<_>>();
+        let columns = statement
+            .columns()
+            .iter()
+            .map(|column| column.name().to_owned())
+            .collect::<Vec<_>>();
+        // Like SQLite's, the count of changed rows is left alone by statements
+        // which return rows.
+        if columns.is_empty() {
+            let changes = client
+                .execute(&statement, &parameters)
+                .await
+                .map_err(query_failed)?;
+            self.changes.store(changes, Ordering::Relaxed);
+            return Ok(v3::QueryResult {
+                columns,
+                rows: vec![],
+            });
+        }
+        let rows = client
+            .query(&statement, &parameters)
+            .await
+            .map_err(query_failed)?
+            .iter()
+            .map(convert_row)
+            .collect::<Result<_, _>>()
+            .map_err(query_failed)?;
+        Ok(v3::QueryResult { columns, rows })
+    }
+
+    async fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
+        let client = self.get_or_create_client().await?;
+        client
+            .batch_execute(statements)
+            .await
+            .context("failed to execute statements")
+    }
+
+    async fn changes(&self) -> Result<u64, v3::Error> {
+        Ok(self.changes.load(Ordering::Relaxed))
+    }
+
+    // Postgres has no row IDs. The nearest equivalent is the value most
+    // recently taken from a sequence, such as that of a `SERIAL` column.
+    async fn last_insert_rowid(&self) -> Result<i64, v3::Error> {
+        let Some(client) = self.client.get() else {
+            return Ok(0);
+        };
+        match client.query_one("SELECT lastval()", &[]).await {
+            Ok(row) => row.try_get(0).map_err(query_failed),
+            Err(e) if e.code() == Some(&SqlState::OBJECT_NOT_IN_PREREQUISITE_STATE) => Ok(0),
+            Err(e) => Err(query_failed(e)),
+        }
+    }
+
+    fn summary(&self) -> Option<String> {
+        let host = match self.config.get_hosts().first() {
+            Some(Host::Tcp(host)) => host.clone(),
+            #[cfg(unix)]
+            Some(Host::Unix(path)) => path.display().to_string(),
+            None => "localhost".to_owned(),
+        };
+        let database = self.config.get_dbname().unwrap_or_default();
+        Some(format!("PostgreSQL at {host}/{database}"))
+    }
+}
+
+fn query_failed(e: tokio_postgres::Error) -> v3::Error {
+    match e.as_db_error() {
+        Some(db_error) => v3::Error::Io(db_error.message().to_owned()),
+        None => v3::Error::Io(e.to_string()),
+    }
+}
+
+/// A statement parameter, which is converted to the type Postgres expects
+/// for it. Integers, for example, may be passed for any integer, floating
+/// point or boolean parameter.
+#[derive(Debug)]
+struct Parameter<'a>(&'a v3::Value);
+
+impl ToSql for Parameter<'_> {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+        match self.0 {
+            v3::Value::Null => Ok(IsNull::Yes),
+            v3::Value::Integer(i) => match *ty {
+                Type::BOOL => (*i != 0).to_sql(ty, out),
+                Type::INT2 => i16::try_from(*i)?.to_sql(ty, out),
+                Type::INT4 => i32::try_from(*i)?.to_sql(ty, out),
+                Type::INT8 => i.to_sql(ty, out),
+                Type::FLOAT4 => (*i as f32).to_sql(ty, out),
+                Type::FLOAT8 => (*i as f64).to_sql(ty, out),
+                _ => as_text(i.to_string(), "an integer", ty, out),
+            },
+            v3::Value::Real(f) => match *ty {
+                Type::FLOAT4 => (*f as f32).to_sql(ty, out),
+                Type::FLOAT8 => f.to_sql(ty, out),
+                _ => as_text(f.to_string(), "a real number", ty, out),
+            },
+            v3::Value::Text(text) => as_text(text.clone(), "text", ty, out),
+            v3::Value::Blob(blob) if *ty == Type::BYTEA => blob.as_slice().to_sql(ty, out),
+            v3::Value::Blob(_) => Err(format!("cannot pass a blob as {ty}").into()),
+        }
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+fn as_text(
+    text: String,
+    kind: &str,
+    ty: &Type,
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+    if !<String as ToSql>::accepts(ty) {
+        return Err(format!("cannot pass {kind} as {ty}").into());
+    }
+    text.to_sql(ty, out)
+}
+
+/// Converts a row to SQLite values. Booleans become integers, as in SQLite.
+fn convert_row(row: &Row) -> Result<RowResult, tokio_postgres::Error> {
+    let values = row
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let value = match *column.type_() {
+                Type::BOOL => row
+                    .try_get::<_, Option<bool>>(index)?
+                    .map(|b| v3::Value::Integer(b.into())),
+                Type::INT2 => row
+                    .try_get::<_, Option<i16>>(index)?
+                    .map(|i| v3::Value::Integer(i.into())),
+                Type::INT4 => row
+                    .try_get::<_, Option<i32>>(index)?
+                    .map(|i| v3::Value::Integer(i.into())),
+                Type::INT8 => row
+                    .try_get::<_, Option<i64>>(index)?
+                    .map(v3::Value::Integer),
+                Type::OID => row
+                    .try_get::<_, Option<u32>>(index)?
+                    .map(|i| v3::Value::Integer(i.into())),
+                Type::FLOAT4 => row
+                    .try_get::<_, Option<f32>>(index)?
+                    .map(|f| v3::Value::Real(f.into())),
+                Type::FLOAT8 => row.try_get::<_, Option<f64>>(index)?.map(v3::Value::Real),
+                Type::BYTEA => row
+                    .try_get::<_, Option<Vec<u8>>>(index)?
+                    .map(v3::Value::Blob),
+                // Text-like types, or an error naming the unsupported type
+                _ => row
+                    .try_get::<_, Option<String>>(index)?
+                    .map(v3::Value::Text),
+            };
+            Ok(value.unwrap_or(v3::Value::Null))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(RowResult { values })
+}
@@ -0,0 +1,121 @@
+//! Translation of SQLite statement parameters to Postgres
+
+/// Rewrites the SQLite-style `?` and `?NNN` parameters in `statement` as
+/// Postgres's `$NNN`, numbering them as SQLite does: `?` is one more than the
+/// largest number so far.
+///
+/// Parameters inside string literals, quoted identifiers and comments are
+/// left alone. Named parameters (`:name`, `@name` and `$name`) are not
+/// supported, because Postgres has no equivalent.
+pub fn translate(statement: &str) -> Result<String, String> {
+    let mut translated = String::with_capacity(statement.len());
+    let mut chars = statement.chars().peekable();
+    let mut largest = 0u32;
+    while let Some(ch) = chars.next() {
+        translated.push(ch);
+        match ch {
+            '\'' | '"' => {
+                // A doubled quote is an escaped quote, which this treats as
+                // the end of one quoted run and the start of another.
+                for inner in chars.by_ref() {
+                    translated.push(inner);
+                    if inner == ch {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.next_if_eq(&'-').is_some() => {
+                translated.push('-');
+                for inner in chars.by_ref() {
+                    translated.push(inner);
+                    if inner == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                translated.push('*');
+                let mut previous = None;
+                for inner in chars.by_ref() {
+                    translated.push(inner);
+                    if previous == Some('*') && inner == '/' {
+                        break;
+                    }
+                    previous = Some(inner);
+                }
+            }
+            // A Postgres cast, such as `?::text`
+            ':' if chars.next_if_eq(&':').is_some() => translated.push(':'),
+            '?' => {
+                translated.pop();
+                let mut digits = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit);
+                }
+                let number = if digits.is_empty() {
+                    largest
+                        .checked_add(1)
+                        .ok_or("too many statement parameters")?
+                } else {
+                    match digits.parse() {
+                        Ok(number) if number > 0 => number,
+                        _ => return Err(format!("invalid statement parameter `?{digits}`")),
+                    }
+                };
+                largest = largest.max(number);
+                translated.push('$');
+                translated.push_str(&number.to_string());
+            }
+            ':' | '@' | '$'
+                if chars
+                    .peek()
+                    .is_some_and(|next| next.is_ascii_alphabetic() || *next == '_') =>
+            {
+                return Err(format!(
+                    "named statement parameters such as `{ch}name` are not supported by PostgreSQL databases; use `?` or `?NNN`"
+                ));
+            }
+            _ => {}
+        }
+    }
+    Ok(translated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_are_numbered_as_sqlite_does() {
+        assert_eq!(
+            Ok("SELECT * FROM t WHERE a = $1 AND b = $2".to_owned()),
+            translate("SELECT * FROM t WHERE a = ? AND b = ?")
+        );
+        assert_eq!(
+            Ok("INSERT INTO t VALUES ($2, $1, $3)".to_owned()),
+            translate("INSERT INTO t VALUES (?2, ?1, ?)")
+        );
+        assert_eq!(
+            Ok("SELECT $1::text".to_owned()),
+            translate("SELECT ?::text")
+        );
+    }
+
+    #[test]
+    fn quoted_and_commented_text_is_unchanged() {
+        let statement = "SELECT '?', 'it''s ?', \"?\" FROM t -- ?\nWHERE a = ? /* ? */";
+        assert_eq!(
+            Ok("SELECT '?', 'it''s ?', \"?\" FROM t -- ?\nWHERE a = $1 /* ? */".to_owned()),
+            translate(statement)
+        );
+    }
+
+    #[test]
+    fn named_and_invalid_parameters_are_rejected() {
+        assert!(translate("SELECT * FROM t WHERE a = :a").is_err());
+        assert!(translate("SELECT * FROM t WHERE a = @a").is_err());
+        assert!(translate("SELECT * FROM t WHERE a = $a").is_err());
+        assert!(translate("SELECT ?0").is_err());
+        assert!(translate("SELECT '@a', ':a'").is_ok());
+    }
+}
@@ -1,4 +1,6 @@
-use std::env::VarError;
+use std::{env::VarError, net::SocketAddr};
+
+use anyhow::Context as _;
 
 use opentelemetry_otlp::{
     OTEL_EXPORTER_OTLP_ENDPOINT, OTEL_EXPORTER_OTLP_LOGS_ENDPOINT,
@@ -11,6 +13,7 @@ const OTEL_EXPORTER_OTLP_TRACES_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_TRACES_PROT
 const OTEL_EXPORTER_OTLP_METRICS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_METRICS_PROTOCOL";
 const OTEL_EXPORTER_OTLP_LOGS_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_LOGS_PROTOCOL";
 const SPIN_DISABLE_LOG_TO_TRACING: &str = "SPIN_DISABLE_LOG_TO_TRACING";
+pub(crate) const SPIN_PROMETHEUS_LISTEN: &str = "SPIN_PROMETHEUS_LISTEN";
 
 /// Returns a boolean indicating if the OTEL tracing layer should be enabled.
 ///
@@ -63,6 +66,20 @@ pub(crate) fn spin_disable_log_to_tracing() -> bool {
     any_vars_set(&[SPIN_DISABLE_LOG_TO_TRACING])
 }
 
+/// Returns the address on which to serve Prometheus metrics scrapes, if any.
+///
+/// It is set by the environment variable `SPIN_PROMETHEUS_LISTEN`. Unlike the OTEL layers this is
+/// not overridden by OTEL_SDK_DISABLED, as it exports nowhere unless scraped.
+pub(crate) fn prometheus_listen_addr() -> anyhow::Result<Option<SocketAddr>> {
+    match std::env::var(SPIN_PROMETHEUS_LISTEN) {
+        Ok(addr) if !addr.is_empty() => addr
+            .parse()
+            .map(Some)
+            .with_context(|| format!("invalid {SPIN_PROMETHEUS_LISTEN} address '{addr}'")),
+        _ => Ok(None),
+    }
+}
+
 fn any_vars_set(enabling_vars: &[&str]) -> bool {
     enabling_vars
         .iter()
@@ -0,0 +1,202 @@
+//! Telemetry recorded by guest components through host interfaces.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+
+use opentelemetry::{
+    global,
+    metrics::{Counter, Histogram},
+    trace::TraceContextExt as _,
+    KeyValue,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+/// The name of the meter which records guest metrics.
+const GUEST_METER_NAME: &str = "spin_guest";
+
+/// The target of guest trace events.
+pub(crate) const GUEST_EVENT_TARGET: &str = "spin_guest";
+
+/// The prefix of the names of Spin's own metrics, which guests may not use.
+const RESERVED_PREFIX: &str = "spin.";
+
+/// Why a guest could not record a metric.
+#[derive(Debug, PartialEq, Eq)]
+pub enum GuestMetricError {
+    /// The name is not a valid instrument name, or is reserved.
+    InvalidName(String),
+    /// The name was already used for a different kind of instrument.
+    KindMismatch(String),
+}
+
+impl std::fmt::Display for GuestMetricError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidName(msg) | Self::KindMismatch(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for GuestMetricError {}
+
+/// The metric instruments guests of an app have recorded to, by name.
+///
+/// Instruments are created on first use, from the global meter provider.
+#[derive(Default)]
+pub struct GuestMetrics {
+    instruments: Mutex<HashMap<String, Instrument>>,
+}
+
+enum Instrument {
+    Counter(Counter<u64>),
+    Histogram(Histogram<f64>),
+}
+
+impl GuestMetrics {
+    /// Adds to the named monotonic counter.
+    pub fn counter_add(
+        &self,
+        name: &str,
+        value: u64,
+        attributes: Vec<(String, String)>,
+    ) -> Result<(), GuestMetricError> {
+        validate_name(name)?;
+        let mut instruments = self
+            .instruments
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let instrument = instruments.entry(name.to_owned()).or_insert_with(|| {
+            Instrument::Counter(
+                global::meter(GUEST_METER_NAME)
+                    .u64_counter(name.to_owned())
+                    .build(),
+            )
+        });
+        match instrument {
+            Instrument::Counter(counter) => {
+                counter.add(value, &key_values(attributes));
+                Ok(())
+            }
+            Instrument::Histogram(_) => Err(GuestMetricError::KindMismatch(format!(
+                "metric {name:?} is a histogram, not a counter"
+            ))),
+        }
+    }
+
+    /// Records a value in the named histogram.
+    pub fn histogram_record(
+        &self,
+        name: &str,
+        value: f64,
+        attributes: Vec<(String, String)>,
+    ) -> Result<(), GuestMetricError> {
+        validate_name(name)?;
+        let mut instruments = self
+            .instruments
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let instrument = instruments.entry(name.to_owned()).or_insert_with(|| {
+            Instrument::Histogram(
+                global::meter(GUEST_METER_NAME)
+                    .f64_histogram(name.to_owned())
+                    .build(),
+            )
+        });
+        match instrument {
+            Instrument::Histogram(histogram) => {
+                histogram.record(value, &key_values(attributes));
+                Ok(())
+            }
+            Instrument::Counter(_) => Err(GuestMetricError::KindMismatch(format!(
+                "metric {name:?} is a counter, not a histogram"
+            ))),
+        }
+    }
+}
+
+/// Checks a guest metric name against the OpenTelemetry instrument name
+/// syntax, and that it is not one of Spin's own.
+fn validate_name(name: &str) -> Result<(), GuestMetricError> {
+    let valid_syntax = name.len() <= 255
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '/'));
+    if !valid_syntax {
+        return Err(GuestMetricError::InvalidName(format!(
+            "{name:?} is not a valid metric name"
+        )));
+    }
+    if name.starts_with(RESERVED_PREFIX) {
+        return Err(GuestMetricError::InvalidName(format!(
+            "metric names starting {RESERVED_PREFIX:?} are reserved"
+        )));
+    }
+    Ok(())
+}
+
+fn key_values(attributes: Vec<(String, String)>) -> Vec<KeyValue> {
+    attributes
+        .into_iter()
+        .map(|(key, value)| KeyValue::new(key, value))
+        .collect()
+}
+
+/// Adds an event to the current span.
+///
+/// The event is named by its message, as for any [tracing] event; its
+/// attributes are recorded as a single `attributes` field of `key=value`
+/// pairs, since event fields are fixed at compile time.
+pub fn add_span_event(name: &str, attributes: Vec<(String, String)>) {
+    let attributes = attributes
+        .into_iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    tracing::info!(target: GUEST_EVENT_TARGET, attributes = %attributes, "{name}");
+}
+
+/// Sets an attribute of the current span.
+pub fn set_span_attribute(key: String, value: String) {
+    tracing::Span::current().set_attribute(key, value);
+}
+
+/// Returns the ID, in hex, of the current span's trace, or `None` if it is
+/// not being traced.
+pub fn current_trace_id() -> Option<String> {
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_validated() {
+        assert!(validate_name("orders.placed").is_ok());
+        assert!(validate_name("http/client-latency_ms").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("9lives").is_err());
+        assert!(validate_name("orders placed").is_err());
+        assert!(validate_name(&"a".repeat(256)).is_err());
+        assert!(validate_name("spin.request_count").is_err());
+    }
+
+    #[test]
+    fn names_keep_their_instrument_kind() {
+        let metrics = GuestMetrics::default();
+        metrics.counter_add("orders", 1, vec![]).unwrap();
+        metrics.counter_add("orders", 2, vec![]).unwrap();
+        assert!(matches!(
+            metrics.histogram_record("orders", 1.0, vec![]),
+            Err(GuestMetricError::KindMismatch(_))
+        ));
+    }
+}
@@ -1,18 +1,22 @@
-use std::io::IsTerminal;
+use std::{io::IsTerminal, sync::OnceLock};
 
 use anyhow::Context;
 use env::otel_logs_enabled;
 use env::otel_metrics_enabled;
 use env::otel_tracing_enabled;
+use env::prometheus_listen_addr;
 use opentelemetry_sdk::propagation::TraceContextPropagator;
-use tracing_subscriber::{fmt, prelude::*, registry, EnvFilter, Layer};
+use tracing_subscriber::{fmt, prelude::*, registry, reload, EnvFilter, Layer};
 
 mod alert_in_dev;
 pub mod detector;
 mod env;
+pub mod guest;
 pub mod logs;
 pub mod metrics;
+mod metrics_listener;
 mod propagation;
+mod runtime_config;
 pub mod traces;
 
 #[cfg(feature = "testing")]
@@ -20,14 +24,21 @@ pub mod testing;
 
 pub use propagation::extract_trace_context;
 pub use propagation::inject_trace_context;
+pub use propagation::inject_trace_context_sql;
+pub use runtime_config::{
+    OtelRuntimeConfig, PrometheusRuntimeConfig, OTEL_RUNTIME_CONFIG_KEY,
+    PROMETHEUS_RUNTIME_CONFIG_KEY,
+};
 
 /// Initializes telemetry for Spin using the [tracing] library.
 ///
 /// Under the hood this involves initializing a [tracing::Subscriber] with multiple [Layer]s. One
 /// [Layer] emits [tracing] events to stderr, another sends spans to an OTel collector, and another
-/// sends metrics to an OTel collector.
+/// sends metrics to an OTel collector and/or serves them to Prometheus scrapes.
 ///
-/// Configuration for the OTel layers is pulled from the environment.
+/// Configuration for the OTel layers is pulled from the environment. The `[otel]` table of a
+/// runtime config file is applied by passing it as environment variables, see
+/// [OtelRuntimeConfig].
 ///
 /// Examples of emitting traces from Spin:
 ///
@@ -54,20 +65,16 @@ pub use propagation::inject_trace_context;
 /// ```
 pub fn init(spin_version: String) -> anyhow::Result<()> {
     // This layer will print all tracing library log messages to stderr.
+    let (stderr_filter, stderr_filter_handle) = reload::Layer::new(stderr_filter(None)?);
     let fmt_layer = fmt::layer()
         .with_writer(std::io::stderr)
         .with_ansi(std::io::stderr().is_terminal())
-        .with_filter(
-            // Filter directives explained here https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html#directives
-            EnvFilter::from_default_env()
-                // Wasmtime is too noisy
-                .add_directive("wasmtime_wasi_http=warn".parse()?)
-                // Watchexec is too noisy
-                .add_directive("watchexec=off".parse()?)
-                // We don't want to duplicate application logs
-                .add_directive("[{app_log}]=off".parse()?)
-                .add_directive("[{app_log_non_utf8}]=off".parse()?),
-        );
+        .with_filter(stderr_filter);
+    let _ = STDERR_FILTER_RELOAD.set(Box::new(move |filter| {
+        stderr_filter_handle
+            .reload(filter)
+            .context("failed to replace log filter")
+    }));
 
     let otel_tracing_layer = if otel_tracing_enabled() {
         Some(
@@ -78,9 +85,10 @@ pub fn init(spin_version: String) -> anyhow::Result<()> {
         None
     };
 
-    let otel_metrics_layer = if otel_metrics_enabled() {
+    let prometheus_listen = prometheus_listen_addr()?;
+    let otel_metrics_layer = if otel_metrics_enabled() || prometheus_listen.is_some() {
         Some(
-            metrics::otel_metrics_layer(spin_version.clone())
+            metrics::otel_metrics_layer(spin_version.clone(), prometheus_listen)
                 .context("failed to initialize otel metrics")?,
         )
     } else {
@@ -108,3 +116,39 @@ pub fn init(spin_version: String) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Replaces the filter of the log messages printed to stderr, once [init] has run.
+type ReloadFilter = Box<dyn Fn(EnvFilter) -> anyhow::Result<()> + Send + Sync>;
+
+static STDERR_FILTER_RELOAD: OnceLock<ReloadFilter> = OnceLock::new();
+
+/// Sets which log messages are printed to stderr from now on, with filter
+/// directives as `RUST_LOG` takes them, or restores those of `RUST_LOG` if
+/// `directives` is `None`.
+pub fn set_log_directives(directives: Option<&str>) -> anyhow::Result<()> {
+    let reload = STDERR_FILTER_RELOAD
+        .get()
+        .context("telemetry has not been initialized")?;
+    reload(stderr_filter(directives)?)
+}
+
+/// Returns the filter of the log messages printed to stderr, from the given
+/// directives or else `RUST_LOG`.
+fn stderr_filter(directives: Option<&str>) -> anyhow::Result<EnvFilter> {
+    // Filter directives explained here https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html#directives
+    let filter = match directives {
+        Some(directives) => EnvFilter::try_new(directives)
+            .with_context(|| format!("invalid log filter {directives:?}"))?,
+        None => EnvFilter::from_default_env(),
+    };
+    Ok(filter
+        // Wasmtime is too noisy
+        .add_directive("wasmtime_wasi_http=warn".parse()?)
+        // Watchexec is too noisy
+        .add_directive("watchexec=off".parse()?)
+        // We don't want to duplicate application logs
+        .add_directive("[{app_log}]=off".parse()?)
+        .add_directive("[{app_log_non_utf8}]=off".parse()?)
+        // Guest trace events belong in the trace, not on stderr
+        .add_directive(format!("{}=off", guest::GUEST_EVENT_TARGET).parse()?))
+}
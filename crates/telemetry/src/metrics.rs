@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use anyhow::{bail, Result};
 use opentelemetry::global;
 use opentelemetry_sdk::{
@@ -9,15 +11,23 @@ use tracing::Subscriber;
 use tracing_opentelemetry::MetricsLayer;
 use tracing_subscriber::{registry::LookupSpan, Layer};
 
-use crate::{detector::SpinResourceDetector, env::OtlpProtocol};
+use crate::{
+    detector::SpinResourceDetector,
+    env::{otel_metrics_enabled, OtlpProtocol},
+    metrics_listener,
+};
 
-/// Constructs a layer for the tracing subscriber that sends metrics to an OTEL collector.
+/// Constructs a layer for the tracing subscriber that sends metrics to an OTEL collector, serves
+/// them to Prometheus scrapes, or both.
 ///
 /// It pulls OTEL configuration from the environment based on the variables defined
 /// [here](https://opentelemetry.io/docs/specs/otel/protocol/exporter/) and
 /// [here](https://opentelemetry.io/docs/specs/otel/configuration/sdk-environment-variables/#general-sdk-configuration).
+/// Metrics are only sent to an OTEL collector if [otel_metrics_enabled] is true, and are only
+/// served to Prometheus scrapes if `prometheus_listen` is set.
 pub(crate) fn otel_metrics_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
     spin_version: String,
+    prometheus_listen: Option<SocketAddr>,
 ) -> Result<impl Layer<S>> {
     let resource = Resource::builder()
         .with_detectors(&[
@@ -31,25 +41,36 @@ pub(crate) fn otel_metrics_layer<S: Subscriber + for<'span> LookupSpan<'span>>(
         ])
         .build();
 
-    // This will configure the exporter based on the OTEL_EXPORTER_* environment variables. We
-    // currently default to using the HTTP exporter but in the future we could select off of the
-    // combination of OTEL_EXPORTER_OTLP_PROTOCOL and OTEL_EXPORTER_OTLP_TRACES_PROTOCOL to
-    // determine whether we should use http/protobuf or grpc.
-    let exporter = match OtlpProtocol::metrics_protocol_from_env() {
-        OtlpProtocol::Grpc => opentelemetry_otlp::MetricExporter::builder()
-            .with_tonic()
-            .build()?,
-        OtlpProtocol::HttpProtobuf => opentelemetry_otlp::MetricExporter::builder()
-            .with_http()
-            .build()?,
-        OtlpProtocol::HttpJson => bail!("http/json OTLP protocol is not supported"),
-    };
+    let mut meter_provider = SdkMeterProvider::builder().with_resource(resource);
 
-    let reader = PeriodicReader::builder(exporter).build();
-    let meter_provider = SdkMeterProvider::builder()
-        .with_reader(reader)
-        .with_resource(resource)
-        .build();
+    if otel_metrics_enabled() {
+        // This will configure the exporter based on the OTEL_EXPORTER_* environment variables. We
+        // currently default to using the HTTP exporter but in the future we could select off of
+        // the combination of OTEL_EXPORTER_OTLP_PROTOCOL and OTEL_EXPORTER_OTLP_TRACES_PROTOCOL
+        // to determine whether we should use http/protobuf or grpc.
+        let exporter = match OtlpProtocol::metrics_protocol_from_env() {
+            OtlpProtocol::Grpc => opentelemetry_otlp::MetricExporter::builder()
+                .with_tonic()
+                .build()?,
+            OtlpProtocol::HttpProtobuf => opentelemetry_otlp::MetricExporter::builder()
+                .with_http()
+                .build()?,
+            OtlpProtocol::HttpJson => bail!("http/json OTLP protocol is not supported"),
+        };
+        meter_provider = meter_provider.with_reader(PeriodicReader::builder(exporter).build());
+    }
+
+    if let Some(addr) = prometheus_listen {
+        let registry = prometheus::Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()?;
+        meter_provider = meter_provider.with_reader(exporter);
+        let addr = metrics_listener::serve(addr, registry)?;
+        terminal::einfo!("Serving Prometheus metrics", "on http://{addr}/metrics");
+    }
+
+    let meter_provider = meter_provider.build();
 
     global::set_meter_provider(meter_provider.clone());
 
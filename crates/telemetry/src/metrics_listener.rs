@@ -0,0 +1,109 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use prometheus::{Encoder, Registry, TextEncoder};
+
+/// How long a scrape may take to send its request or read its response.
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves the metrics in the registry, in the Prometheus text format, to scrapes of `/metrics` on
+/// the given address. Returns the address it listens on.
+///
+/// Scrapes are infrequent and cheap, so they are served one at a time from a dedicated thread,
+/// which keeps them apart from the runtime serving the application.
+pub(crate) fn serve(addr: SocketAddr, registry: Registry) -> Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)
+        .with_context(|| format!("failed to listen for Prometheus metrics scrapes on {addr}"))?;
+    let local_addr = listener.local_addr()?;
+    std::thread::Builder::new()
+        .name("spin-metrics-listener".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream
+                    .map_err(Into::into)
+                    .and_then(|stream| respond(stream, &registry));
+                if let Err(err) = result {
+                    tracing::debug!("Failed to serve Prometheus metrics scrape: {err:?}");
+                }
+            }
+        })?;
+    Ok(local_addr)
+}
+
+fn respond(mut stream: TcpStream, registry: &Registry) -> Result<()> {
+    stream.set_read_timeout(Some(SCRAPE_TIMEOUT))?;
+    stream.set_write_timeout(Some(SCRAPE_TIMEOUT))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Scrapes have no body, so the request ends with its headers
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => {
+            let encoder = TextEncoder::new();
+            let mut body = vec![];
+            encoder.encode(&registry.gather(), &mut body)?;
+            ("200 OK", encoder.format_type().to_owned(), body)
+        }
+        _ => (
+            "404 Not Found",
+            "text/plain".to_owned(),
+            b"Not Found".to_vec(),
+        ),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn metrics_are_served_to_scrapes() {
+        let registry = Registry::new();
+        let counter = prometheus::IntCounter::new("test_requests", "Test requests").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+
+        let addr = serve("127.0.0.1:0".parse().unwrap(), registry).unwrap();
+
+        let response = get(addr, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.contains("test_requests 1"), "{response}");
+
+        let response = get(addr, "/");
+        assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+    }
+}
@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 use opentelemetry::{
     global,
     propagation::{Extractor, Injector},
 };
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+const TRACEPARENT: &str = "traceparent";
+
 /// Injects the current W3C TraceContext into the provided request.
 pub fn inject_trace_context<'a>(req: impl Into<HeaderInjector<'a>>) {
     let mut injector = req.into();
@@ -22,6 +26,37 @@ pub fn extract_trace_context<'a>(req: impl Into<HeaderExtractor<'a>>) {
     tracing::Span::current().set_parent(parent_context);
 }
 
+/// Appends the current W3C TraceContext to the provided SQL statement as a
+/// [sqlcommenter](https://google.github.io/sqlcommenter/spec/) comment, so that the database's
+/// view of the statement can be correlated with the trace which issued it.
+///
+/// The statement is returned unchanged if there is no trace context to propagate or if it already
+/// contains a comment.
+pub fn inject_trace_context_sql(statement: String) -> String {
+    let mut carrier = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        let context = tracing::Span::current().context();
+        propagator.inject_context(&context, &mut carrier);
+    });
+    match carrier.get(TRACEPARENT) {
+        Some(traceparent) => append_sql_comment(statement, traceparent),
+        None => statement,
+    }
+}
+
+fn append_sql_comment(statement: String, traceparent: &str) -> String {
+    if statement.contains("/*") || statement.contains("--") {
+        return statement;
+    }
+    // The comment goes before any terminating semicolon, so it stays part of the statement
+    let trimmed = statement.trim_end();
+    let (body, terminator) = match trimmed.strip_suffix(';') {
+        Some(body) => (body.trim_end(), ";"),
+        None => (trimmed, ""),
+    };
+    format!("{body} /*{TRACEPARENT}='{traceparent}'*/{terminator}")
+}
+
 pub enum HeaderInjector<'a> {
     Http0(&'a mut http0::HeaderMap),
     Http1(&'a mut http1::HeaderMap),
@@ -108,3 +143,33 @@ impl<'a, T> From<&'a http1::Request<T>> for HeaderExtractor<'a> {
         Self::Http1(req.headers())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    #[test]
+    fn sql_comment_is_appended() {
+        assert_eq!(
+            append_sql_comment("SELECT 1".into(), TRACE),
+            format!("SELECT 1 /*traceparent='{TRACE}'*/")
+        );
+        assert_eq!(
+            append_sql_comment("SELECT 1;\n".into(), TRACE),
+            format!("SELECT 1 /*traceparent='{TRACE}'*/;")
+        );
+    }
+
+    #[test]
+    fn commented_sql_is_unchanged() {
+        let statement = "SELECT 1 /* mine */".to_string();
+        assert_eq!(append_sql_comment(statement.clone(), TRACE), statement);
+    }
+
+    #[test]
+    fn sql_without_trace_context_is_unchanged() {
+        assert_eq!(inject_trace_context_sql("SELECT 1".into()), "SELECT 1");
+    }
+}
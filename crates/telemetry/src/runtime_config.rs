@@ -0,0 +1,219 @@
+use std::{collections::BTreeMap, net::SocketAddr};
+
+use anyhow::Context as _;
+use opentelemetry_otlp::{
+    OTEL_EXPORTER_OTLP_ENDPOINT, OTEL_EXPORTER_OTLP_HEADERS, OTEL_EXPORTER_OTLP_LOGS_ENDPOINT,
+    OTEL_EXPORTER_OTLP_METRICS_ENDPOINT, OTEL_EXPORTER_OTLP_PROTOCOL,
+    OTEL_EXPORTER_OTLP_TRACES_ENDPOINT,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::env::SPIN_PROMETHEUS_LISTEN;
+
+const OTEL_SERVICE_NAME: &str = "OTEL_SERVICE_NAME";
+
+/// The key of the runtime config table which configures OTLP export.
+pub const OTEL_RUNTIME_CONFIG_KEY: &str = "otel";
+
+/// The key of the runtime config table which configures the Prometheus metrics listener.
+pub const PROMETHEUS_RUNTIME_CONFIG_KEY: &str = "prometheus";
+
+/// The address of the Prometheus metrics listener if the runtime config does not set one.
+const DEFAULT_PROMETHEUS_LISTEN: &str = "127.0.0.1:9464";
+
+/// OTLP export settings from the `[otel]` table of a runtime config file.
+///
+/// Telemetry is configured from the environment before any runtime config is
+/// read, so these settings take effect by being passed as `OTEL_*`
+/// environment variables to the processes which run an application's
+/// triggers.
+///
+/// ```toml
+/// [otel]
+/// endpoint = "http://localhost:4318"
+/// protocol = "http/protobuf"
+/// service_name = "my-app"
+/// headers = { authorization = "Bearer ..." }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OtelRuntimeConfig {
+    /// The endpoint to export all signals to.
+    endpoint: Option<String>,
+    /// The endpoint to export traces to, if not `endpoint`.
+    traces_endpoint: Option<String>,
+    /// The endpoint to export metrics to, if not `endpoint`.
+    metrics_endpoint: Option<String>,
+    /// The endpoint to export logs to, if not `endpoint`.
+    logs_endpoint: Option<String>,
+    /// The OTLP protocol: `grpc` or `http/protobuf`.
+    protocol: Option<String>,
+    /// Headers to send with each export request.
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// The `service.name` of exported telemetry.
+    service_name: Option<String>,
+}
+
+impl OtelRuntimeConfig {
+    /// Parses the `[otel]` table, if any, of the given runtime config.
+    pub fn from_toml(table: &toml::Table) -> anyhow::Result<Option<Self>> {
+        table
+            .get(OTEL_RUNTIME_CONFIG_KEY)
+            .map(Self::from_toml_value)
+            .transpose()
+    }
+
+    /// Parses the value of an `[otel]` table.
+    pub fn from_toml_value(value: &toml::Value) -> anyhow::Result<Self> {
+        parse_table(OTEL_RUNTIME_CONFIG_KEY, value)
+    }
+
+    /// The environment variables which configure OTLP export as these
+    /// settings do.
+    ///
+    /// Variables which are already set in the environment are left out, so
+    /// that the environment takes precedence over the runtime config.
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let headers = (!self.headers.is_empty()).then(|| {
+            self.headers
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+        [
+            (OTEL_EXPORTER_OTLP_ENDPOINT, self.endpoint.clone()),
+            (
+                OTEL_EXPORTER_OTLP_TRACES_ENDPOINT,
+                self.traces_endpoint.clone(),
+            ),
+            (
+                OTEL_EXPORTER_OTLP_METRICS_ENDPOINT,
+                self.metrics_endpoint.clone(),
+            ),
+            (OTEL_EXPORTER_OTLP_LOGS_ENDPOINT, self.logs_endpoint.clone()),
+            (OTEL_EXPORTER_OTLP_PROTOCOL, self.protocol.clone()),
+            (OTEL_EXPORTER_OTLP_HEADERS, headers),
+            (OTEL_SERVICE_NAME, self.service_name.clone()),
+        ]
+        .into_iter()
+        .filter(|(key, _)| std::env::var_os(key).is_none())
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
+}
+
+/// Prometheus metrics listener settings from the `[prometheus]` table of a runtime config file.
+///
+/// The table's presence enables the listener, which serves `/metrics` on a port apart from any
+/// the application listens on. Like [OtelRuntimeConfig], it takes effect by being passed as an
+/// environment variable to a process which runs an application's triggers.
+///
+/// ```toml
+/// [prometheus]
+/// listen = "127.0.0.1:9464"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrometheusRuntimeConfig {
+    /// The address to serve metrics scrapes on.
+    listen: Option<SocketAddr>,
+}
+
+impl PrometheusRuntimeConfig {
+    /// Parses the `[prometheus]` table, if any, of the given runtime config.
+    pub fn from_toml(table: &toml::Table) -> anyhow::Result<Option<Self>> {
+        table
+            .get(PROMETHEUS_RUNTIME_CONFIG_KEY)
+            .map(Self::from_toml_value)
+            .transpose()
+    }
+
+    /// Parses the value of a `[prometheus]` table.
+    pub fn from_toml_value(value: &toml::Value) -> anyhow::Result<Self> {
+        parse_table(PROMETHEUS_RUNTIME_CONFIG_KEY, value)
+    }
+
+    /// The environment variables which enable the Prometheus metrics listener as these settings
+    /// do. As with [OtelRuntimeConfig::env_vars], the environment takes precedence.
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        if std::env::var_os(SPIN_PROMETHEUS_LISTEN).is_some() {
+            return vec![];
+        }
+        let listen = match self.listen {
+            Some(addr) => addr.to_string(),
+            None => DEFAULT_PROMETHEUS_LISTEN.to_owned(),
+        };
+        vec![(SPIN_PROMETHEUS_LISTEN, listen)]
+    }
+}
+
+fn parse_table<T: DeserializeOwned>(key: &str, value: &toml::Value) -> anyhow::Result<T> {
+    value
+        .clone()
+        .try_into()
+        .with_context(|| format!("invalid [{key}] runtime config"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_map_to_otel_env_vars() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [otel]
+            endpoint = "http://collector:4317"
+            protocol = "grpc"
+            headers = { "x-tenant" = "a", "x-key" = "b" }
+        };
+        let config = OtelRuntimeConfig::from_toml(&table)?.unwrap();
+        let env_vars = config.env_vars();
+        let get = |key| {
+            env_vars
+                .iter()
+                .find_map(|(k, v)| (*k == key).then_some(v.as_str()))
+        };
+        assert_eq!(
+            get(OTEL_EXPORTER_OTLP_ENDPOINT),
+            Some("http://collector:4317")
+        );
+        assert_eq!(get(OTEL_EXPORTER_OTLP_PROTOCOL), Some("grpc"));
+        assert_eq!(get(OTEL_EXPORTER_OTLP_HEADERS), Some("x-key=b,x-tenant=a"));
+        assert_eq!(get(OTEL_SERVICE_NAME), None);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        let table: toml::Table = toml::toml! {
+            [otel]
+            endpont = "http://collector:4317"
+        };
+        assert!(OtelRuntimeConfig::from_toml(&table).is_err());
+    }
+
+    #[test]
+    fn prometheus_listener_defaults_to_localhost() -> anyhow::Result<()> {
+        let table: toml::Table = toml::toml! {
+            [prometheus]
+        };
+        let config = PrometheusRuntimeConfig::from_toml(&table)?.unwrap();
+        assert_eq!(
+            config.env_vars(),
+            [(SPIN_PROMETHEUS_LISTEN, DEFAULT_PROMETHEUS_LISTEN.to_owned())]
+        );
+
+        let table: toml::Table = toml::toml! {
+            [prometheus]
+            listen = "0.0.0.0:9000"
+        };
+        let config = PrometheusRuntimeConfig::from_toml(&table)?.unwrap();
+        assert_eq!(
+            config.env_vars(),
+            [(SPIN_PROMETHEUS_LISTEN, "0.0.0.0:9000".to_owned())]
+        );
+        Ok(())
+    }
+}
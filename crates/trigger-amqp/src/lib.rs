@@ -0,0 +1,303 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use futures::StreamExt;
+use lapin::message::Delivery;
+use lapin::options::{BasicAckOptions, BasicConsumeOptions, BasicQosOptions, BasicRejectOptions};
+use lapin::types::FieldTable;
+use lapin::uri::AMQPUri;
+use lapin::{Connection, ConnectionProperties};
+use serde::Deserialize;
+use spin_factor_outbound_amqp::properties_from_amqp;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{cli::NoCliArgs, is_execution_timeout, App, Trigger, TriggerApp};
+use spin_world::exports::spin::amqp::inbound_amqp::{self, Disposition, GuestIndices};
+use tracing::{instrument, Level};
+
+/// Runs components for messages delivered from AMQP queues.
+pub struct AmqpTrigger;
+
+/// AMQP trigger metadata.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerMetadata {
+    /// Broker address used by triggers which do not set their own
+    address: Option<String>,
+}
+
+/// AMQP trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Queue to consume from
+    queue: String,
+    /// Optionally override broker address for trigger
+    address: Option<String>,
+    /// How many unsettled messages the broker may deliver at once, all of
+    /// which are handled concurrently
+    prefetch: Option<u16>,
+}
+
+/// The prefetch of triggers which do not set their own, so that messages are
+/// handled one at a time and in order.
+const DEFAULT_PREFETCH: u16 = 1;
+
+impl<F: RuntimeFactors> Trigger<F> for AmqpTrigger {
+    const TYPE: &'static str = "amqp";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        // Consumers share the trigger app, which the variables are borrowed from
+        let trigger_app = Arc::new(trigger_app);
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("AmqpTrigger depends on VariablesFactor")?;
+
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+        let metadata = trigger_app
+            .app()
+            .get_trigger_metadata::<TriggerMetadata>(trigger_type)?
+            .unwrap_or_default();
+
+        // Maps <broker address> -> <consumers>
+        let mut address_consumers: HashMap<String, Vec<ConsumerConfig>> = HashMap::new();
+
+        // Resolve trigger configs before connecting to any brokers
+        for (trigger_id, config) in trigger_app
+            .app()
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+            .into_iter()
+            .collect::<Vec<_>>()
+        {
+            let component_id = config.component;
+            let resolve = |field: &'static str, expr: String| {
+                let component_id = &component_id;
+                async move {
+                    app_variables
+                        .resolve_expression(expr.clone())
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "failed to resolve amqp trigger {field} {expr:?} for component {component_id}"
+                            )
+                        })
+                }
+            };
+
+            let Some(address) = config.address.or_else(|| metadata.address.clone()) else {
+                anyhow::bail!("amqp trigger {trigger_id:?} has no address");
+            };
+            let address = resolve("address", address).await?;
+            if !address.starts_with("amqp://") && !address.starts_with("amqps://") {
+                anyhow::bail!(
+                    "amqp trigger {trigger_id:?} has an address which is not an `amqp://` or `amqps://` URL"
+                );
+            }
+            let queue = resolve("queue", config.queue).await?;
+            if queue.is_empty() {
+                anyhow::bail!("amqp trigger {trigger_id:?} has an empty queue");
+            }
+            let prefetch = config.prefetch.unwrap_or(DEFAULT_PREFETCH);
+            if prefetch == 0 {
+                anyhow::bail!("amqp trigger {trigger_id:?} must have a prefetch of at least 1");
+            }
+
+            let guest_indices = GuestIndices::new(trigger_app.get_instance_pre(&component_id)?)
+                .with_context(|| {
+                    format!(
+                        "component {component_id:?} of amqp trigger {trigger_id:?} does not export spin:amqp/inbound-amqp"
+                    )
+                })?;
+
+            address_consumers
+                .entry(address)
+                .or_default()
+                .push(ConsumerConfig {
+                    queue,
+                    prefetch,
+                    component_id,
+                    guest_indices,
+                });
+        }
+
+        // Connect and start consuming
+        println!("Active consumers:");
+        let mut consumer_tasks = Vec::new();
+        for (address, consumers) in address_consumers {
+            // Addresses may contain credentials, so only the broker's host
+            // and port appear in messages.
+            let uri = address
+                .parse::<AMQPUri>()
+                .map_err(anyhow::Error::msg)
+                .context("AMQP trigger has an invalid broker address")?;
+            let broker = format!("{}:{}", uri.authority.host, uri.authority.port);
+            tracing::info!("Connecting to AMQP broker at {broker}");
+            let connection = Connection::connect_uri(uri, ConnectionProperties::default())
+                .await
+                .with_context(|| format!("AMQP trigger failed to connect to {broker}"))?;
+
+            for config in consumers {
+                // Each consumer has its own channel, to which its prefetch
+                // applies.
+                let channel = connection
+                    .create_channel()
+                    .await
+                    .context("AMQP trigger failed to open a channel")?;
+                channel
+                    .basic_qos(config.prefetch, BasicQosOptions::default())
+                    .await
+                    .context("AMQP trigger failed to set prefetch")?;
+                let deliveries = channel
+                    .basic_consume(
+                        &config.queue,
+                        "",
+                        BasicConsumeOptions::default(),
+                        FieldTable::default(),
+                    )
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "AMQP trigger failed to consume from queue {:?}",
+                            config.queue
+                        )
+                    })?;
+                println!(
+                    "\t{broker}/{} (prefetch {}): [{}]",
+                    config.queue, config.prefetch, config.component_id
+                );
+
+                let consumer = Arc::new(Consumer {
+                    config,
+                    trigger_app: trigger_app.clone(),
+                });
+                consumer_tasks.push(tokio::spawn(consumer.run(deliveries)));
+            }
+        }
+
+        // Wait for any task to complete
+        let (res, _, _) = futures::future::select_all(consumer_tasks).await;
+        res?
+    }
+}
+
+/// The resolved settings of a single AMQP trigger.
+struct ConsumerConfig {
+    queue: String,
+    prefetch: u16,
+    component_id: String,
+    guest_indices: GuestIndices,
+}
+
+/// Handles the messages delivered to a single AMQP trigger, each in its own
+/// task. The broker limits how many are in flight to the trigger's prefetch.
+struct Consumer<F: RuntimeFactors> {
+    config: ConsumerConfig,
+    trigger_app: Arc<TriggerApp<AmqpTrigger, F>>,
+}
+
+impl<F: RuntimeFactors> Consumer<F> {
+    async fn run(self: Arc<Self>, mut deliveries: lapin::Consumer) -> anyhow::Result<()> {
+        while let Some(delivery) = deliveries.next().await {
+            let delivery = delivery.with_context(|| {
+                format!(
+                    "AMQP trigger failed to receive from queue {:?}",
+                    self.config.queue
+                )
+            })?;
+            let consumer = self.clone();
+            tokio::spawn(async move { consumer.settle(delivery).await });
+        }
+        Err(anyhow::anyhow!(
+            "consumer of queue {:?} ended",
+            self.config.queue
+        ))
+    }
+
+    /// Handles a message, then acknowledges or rejects it as the handler
+    /// directs. A failed handler rejects the message without requeueing it.
+    async fn settle(&self, delivery: Delivery) {
+        let disposition = match self.handle_message(&delivery).await {
+            Ok(disposition) => disposition,
+            Err(err) => {
+                if is_execution_timeout(&err) {
+                    self.trigger_app
+                        .record_execution_timeout("amqp", &self.config.component_id);
+                } else {
+                    tracing::info!(
+                        "Component {} handler failed: {err}",
+                        self.config.component_id
+                    );
+                }
+                Disposition::Reject
+            }
+        };
+        let acker = &delivery.acker;
+        let result = match disposition {
+            Disposition::Ack => acker.ack(BasicAckOptions::default()).await,
+            Disposition::Requeue => acker.reject(BasicRejectOptions { requeue: true }).await,
+            Disposition::Reject => acker.reject(BasicRejectOptions { requeue: false }).await,
+        };
+        if let Err(err) = result {
+            tracing::error!(
+                "Error settling message {} from queue {:?}: {err}",
+                delivery.delivery_tag,
+                self.config.queue
+            );
+        }
+    }
+
+    #[instrument(name = "spin_trigger_amqp.handle_message", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("{} receive", self.config.queue),
+        otel.kind = "consumer",
+        messaging.operation = "receive",
+        messaging.system = "rabbitmq"
+    ))]
+    async fn handle_message(&self, delivery: &Delivery) -> anyhow::Result<Disposition> {
+        let component_id = self.config.component_id.as_str();
+        tracing::trace!(
+            queue = self.config.queue,
+            delivery_tag = delivery.delivery_tag,
+            "Received message"
+        );
+
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "amqp",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let (instance, mut store) = self
+            .trigger_app
+            .prepare(component_id)?
+            .instantiate(())
+            .await?;
+        let guest = self.config.guest_indices.load(&mut store, &instance)?;
+
+        guest
+            .call_handle_message(&mut store, &to_message(delivery))
+            .await?
+            .context("AMQP handler returned an error")
+    }
+}
+
+fn to_message(delivery: &Delivery) -> inbound_amqp::Message {
+    inbound_amqp::Message {
+        exchange: delivery.exchange.as_str().to_owned(),
+        routing_key: delivery.routing_key.as_str().to_owned(),
+        redelivered: delivery.redelivered,
+        payload: delivery.data.clone(),
+        properties: properties_from_amqp(&delivery.properties),
+    }
+}
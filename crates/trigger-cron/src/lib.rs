@@ -0,0 +1,331 @@
+mod schedule;
+mod singleton;
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use singleton::RunClaims;
+use spin_factor_key_value::KeyValueFactor;
+use spin_factor_locks::LocksFactor;
+use spin_factor_variables::VariablesFactor;
+use spin_factor_wasi::WasiFactor;
+use spin_factors::RuntimeFactors;
+use spin_serde::HumanDuration;
+use spin_trigger::{cli::NoCliArgs, is_execution_timeout, App, Trigger, TriggerApp};
+use tokio::sync::{Mutex, Semaphore};
+use tracing::{instrument, Level};
+use wasmtime_wasi::p2::bindings::CommandIndices;
+
+pub use schedule::Schedule;
+
+/// Runs components on a schedule, by calling their `wasi:cli/run` export.
+pub struct CronTrigger;
+
+/// Cron trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Cron expression, evaluated in UTC
+    schedule: Option<String>,
+    /// Fixed time between runs
+    interval: Option<HumanDuration>,
+    /// What to do when a run is due while the previous run is still executing
+    #[serde(default)]
+    overlap: OverlapPolicy,
+    /// Maximum random delay to add to each run
+    jitter: Option<HumanDuration>,
+    /// Run on only one replica of the app per tick
+    #[serde(default)]
+    singleton: bool,
+}
+
+/// What to do when a run is due while the previous run of the same trigger
+/// is still executing.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum OverlapPolicy {
+    /// Skip the run.
+    #[default]
+    Skip,
+    /// Start the run when the previous run finishes. At most one run waits.
+    Queue,
+    /// Start the run alongside the previous run.
+    Allow,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for CronTrigger {
+    const TYPE: &'static str = "cron";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        // Jobs share the trigger app, which the variables are borrowed from
+        let trigger_app = Arc::new(trigger_app);
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("CronTrigger depends on VariablesFactor")?;
+
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+
+        let configs = trigger_app
+            .app()
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        // Singleton triggers claim their runs in the locks store, which
+        // replicas of the app share
+        let claims = if configs.iter().any(|(_, config)| config.singleton) {
+            let configured_app = trigger_app.configured_app();
+            let locks = configured_app
+                .app_state::<LocksFactor>()
+                .context("singleton cron triggers depend on LocksFactor")?
+                .locks()
+                .clone();
+            let store_manager = configured_app
+                .app_state::<KeyValueFactor>()
+                .context("singleton cron triggers depend on KeyValueFactor")?
+                .store_manager();
+            Some(Arc::new(RunClaims::new(locks, store_manager)))
+        } else {
+            None
+        };
+
+        // Resolve schedules before starting any jobs
+        let mut jobs = Vec::new();
+        for (trigger_id, config) in configs {
+            let expression = match &config.schedule {
+                Some(expression) => Some(
+                    app_variables
+                        .resolve_expression(expression.clone())
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "failed to resolve cron trigger schedule {expression:?} for component {}",
+                                config.component
+                            )
+                        })?,
+                ),
+                None => None,
+            };
+            let schedule = Schedule::new(
+                expression.as_deref(),
+                config.interval.as_ref().map(HumanDuration::duration),
+            )
+            .with_context(|| format!("invalid schedule for cron trigger {trigger_id:?}"))?;
+
+            let command = CommandIndices::new(trigger_app.get_instance_pre(&config.component)?)
+                .with_context(|| {
+                    format!(
+                        "component {:?} of cron trigger {trigger_id:?} does not export wasi:cli/run",
+                        config.component
+                    )
+                })?;
+
+            let mut description = match (expression, &config.interval) {
+                (Some(expression), _) => format!("`{expression}` (UTC)"),
+                (None, Some(interval)) => format!("every {interval}"),
+                (None, None) => unreachable!("schedule should have been validated"),
+            };
+            if config.singleton {
+                description.push_str(", on one replica");
+            }
+
+            jobs.push(Job {
+                trigger_id: trigger_id.to_owned(),
+                component_id: config.component,
+                description,
+                schedule,
+                jitter: config
+                    .jitter
+                    .as_ref()
+                    .map(HumanDuration::duration)
+                    .unwrap_or_default(),
+                overlap: config.overlap,
+                admission: match config.overlap {
+                    OverlapPolicy::Skip => Some(Arc::new(Semaphore::new(1))),
+                    OverlapPolicy::Queue => Some(Arc::new(Semaphore::new(2))),
+                    OverlapPolicy::Allow => None,
+                },
+                running: Mutex::new(()),
+                claims: if config.singleton {
+                    claims.clone()
+                } else {
+                    None
+                },
+                command,
+                trigger_app: trigger_app.clone(),
+            });
+        }
+
+        println!("Active schedules:");
+        for job in &jobs {
+            println!(
+                "\t{}: {} [{}]",
+                job.trigger_id, job.description, job.component_id
+            );
+        }
+
+        // Jobs run until their schedules end, which most never do
+        let tasks = jobs
+            .into_iter()
+            .map(|job| tokio::spawn(Arc::new(job).run()));
+        for result in futures::future::join_all(tasks).await {
+            result?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a single cron trigger's component on its schedule.
+struct Job<F: RuntimeFactors> {
+    trigger_id: String,
+    component_id: String,
+    description: String,
+    schedule: Schedule,
+    jitter: Duration,
+    overlap: OverlapPolicy,
+    /// Limits how many runs may be executing or waiting to execute, as set
+    /// by the overlap policy. `None` if there is no limit.
+    admission: Option<Arc<Semaphore>>,
+    /// Held while a run executes, unless runs may overlap.
+    running: Mutex<()>,
+    /// Where runs are claimed from other replicas, if the trigger is a
+    /// singleton.
+    claims: Option<Arc<RunClaims>>,
+    command: CommandIndices,
+    trigger_app: Arc<TriggerApp<CronTrigger, F>>,
+}
+
+impl<F: RuntimeFactors> Job<F> {
+    async fn run(self: Arc<Self>) {
+        let mut tick = Utc::now();
+        loop {
+            let Some(next) = self.schedule.next_tick(tick, Utc::now()) else {
+                tracing::info!(
+                    "Cron trigger {} has no further runs scheduled",
+                    self.trigger_id
+                );
+                return;
+            };
+            tick = next;
+            let delay =
+                (next - Utc::now()).to_std().unwrap_or_default() + schedule::jitter(self.jitter);
+            tokio::time::sleep(delay).await;
+            self.clone().start(tick);
+        }
+    }
+
+    /// Starts a run in the background, unless the overlap policy forbids it.
+    fn start(self: Arc<Self>, scheduled: DateTime<Utc>) {
+        let permit = match &self.admission {
+            Some(admission) => match admission.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    tracing::warn!(
+                        "Skipping run of cron trigger {} scheduled for {scheduled}: the previous run is still executing",
+                        self.trigger_id
+                    );
+                    return;
+                }
+            },
+            None => None,
+        };
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Some(claims) = &self.claims {
+                match claims
+                    .claim(&self.trigger_id, &self.schedule, scheduled)
+                    .await
+                {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        tracing::debug!(
+                            "Skipping run of cron trigger {} scheduled for {scheduled}: another replica has claimed it",
+                            self.trigger_id
+                        );
+                        return;
+                    }
+                    Err(err) => {
+                        tracing::warn!(
+                            "Skipping run of cron trigger {} scheduled for {scheduled}: failed to claim the run: {err:?}",
+                            self.trigger_id
+                        );
+                        return;
+                    }
+                }
+            }
+            let _running = match self.overlap {
+                OverlapPolicy::Allow => None,
+                OverlapPolicy::Skip | OverlapPolicy::Queue => Some(self.running.lock().await),
+            };
+            tracing::trace!("Executing cron component {}", self.component_id);
+            // Errors are recorded by `execute`'s span
+            if let Err(err) = self.execute(scheduled).await {
+                if is_execution_timeout(&err) {
+                    self.trigger_app
+                        .record_execution_timeout("cron", &self.component_id);
+                }
+            }
+        });
+    }
+
+    #[instrument(name = "spin_trigger_cron.execute", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("cron {}", self.trigger_id),
+        otel.kind = "internal",
+    ))]
+    async fn execute(&self, scheduled: DateTime<Utc>) -> anyhow::Result<()> {
+        let component_id = self.component_id.as_str();
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "cron",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let mut instance_builder = self.trigger_app.prepare(component_id)?;
+
+        let scheduled = scheduled.to_rfc3339();
+        let wasi_builder = instance_builder
+            .factor_builder::<WasiFactor>()
+            .context("The cron trigger was configured without the required wasi support")?;
+        wasi_builder.env([
+            ("SPIN_CRON_TRIGGER_ID", self.trigger_id.as_str()),
+            ("SPIN_CRON_SCHEDULED_TIME", scheduled.as_str()),
+        ]);
+
+        let (instance, mut store) = instance_builder.instantiate(()).await?;
+        let command = self.command.load(&mut store, &instance)?;
+
+        command
+            .wasi_cli_run()
+            .call_run(&mut store)
+            .await
+            .or_else(ignore_successful_proc_exit_trap)?
+            .map_err(|()| anyhow::anyhow!("component {component_id:?} returned an error"))
+    }
+}
+
+fn ignore_successful_proc_exit_trap(guest_err: anyhow::Error) -> anyhow::Result<Result<(), ()>> {
+    match guest_err
+        .root_cause()
+        .downcast_ref::<wasmtime_wasi::I32Exit>()
+    {
+        Some(trap) => match trap.0 {
+            0 => Ok(Ok(())),
+            _ => Err(guest_err),
+        },
+        None => Err(guest_err),
+    }
+}
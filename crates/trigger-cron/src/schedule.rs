@@ -0,0 +1,168 @@
+use std::{str::FromStr, time::Duration};
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// When a cron trigger runs its component.
+#[derive(Clone, Debug)]
+pub enum Schedule {
+    /// A cron expression, evaluated in UTC.
+    Cron(Box<cron::Schedule>),
+    /// A fixed interval, counted from when the trigger starts.
+    Interval(Duration),
+}
+
+impl Schedule {
+    /// Creates a schedule from a trigger's `schedule` expression or
+    /// `interval`, exactly one of which must be given.
+    pub fn new(expression: Option<&str>, interval: Option<Duration>) -> anyhow::Result<Self> {
+        match (expression, interval) {
+            (Some(expression), None) => Self::parse_cron(expression)
+                .with_context(|| format!("invalid cron expression {expression:?}")),
+            (None, Some(interval)) if interval.is_zero() => {
+                bail!("interval must be greater than zero")
+            }
+            (None, Some(interval)) => Ok(Self::Interval(interval)),
+            (Some(_), Some(_)) => bail!("only one of `schedule` and `interval` may be set"),
+            (None, None) => bail!("one of `schedule` or `interval` must be set"),
+        }
+    }
+
+    // Accepts the traditional five-field form (minute, hour, day of month,
+    // month, day of week) as well as the `cron` crate's own forms, which
+    // start with a seconds field and may end with a year field, and
+    // shorthands such as `@hourly`.
+    fn parse_cron(expression: &str) -> anyhow::Result<Self> {
+        let expression = expression.trim();
+        let expression = match expression.split_whitespace().count() {
+            5 => format!("0 {expression}"),
+            1 if expression.starts_with('@') => expression.to_owned(),
+            6 | 7 => expression.to_owned(),
+            fields => bail!("expected 5, 6 or 7 fields, found {fields}"),
+        };
+        let schedule = cron::Schedule::from_str(&expression)?;
+        Ok(Self::Cron(Box::new(schedule)))
+    }
+
+    /// Returns the first time after `time` at which the schedule fires, or
+    /// `None` if it never fires again.
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Cron(schedule) => schedule.after(&time).next(),
+            Self::Interval(interval) => {
+                time.checked_add_signed(TimeDelta::from_std(*interval).ok()?)
+            }
+        }
+    }
+
+    /// Returns the time the schedule fires next after it fired at
+    /// `previous`. Times which had already passed by `now` (for example
+    /// because the host was suspended) are skipped, rather than fired all at
+    /// once.
+    pub fn next_tick(&self, previous: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let next = self.next_after(previous)?;
+        if next < now {
+            self.next_after(now)
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Returns whether a run scheduled for `run`, by any replica, counts as
+    /// the run for `tick`. Cron ticks are the same on every replica, but
+    /// intervals are counted from when each replica started, so any run in
+    /// the interval before `tick` counts.
+    pub fn covers(&self, run: DateTime<Utc>, tick: DateTime<Utc>) -> bool {
+        match self {
+            Self::Cron(_) => run >= tick,
+            Self::Interval(interval) => match TimeDelta::from_std(*interval) {
+                Ok(interval) => run > tick - interval,
+                Err(_) => true,
+            },
+        }
+    }
+}
+
+/// Returns a random delay of up to `max`, so that triggers which share a
+/// schedule do not all run at the same moment.
+pub fn jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    let max_millis = u64::try_from(max.as_millis()).unwrap_or(u64::MAX);
+    Duration::from_millis(rand::random_range(0..=max_millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    #[test]
+    fn five_field_expressions_fire_on_the_minute() {
+        let schedule = Schedule::new(Some("*/15 * * * *"), None).unwrap();
+        assert_eq!(
+            Some(at("2024-01-01T10:15:00Z")),
+            schedule.next_after(at("2024-01-01T10:07:30Z"))
+        );
+    }
+
+    #[test]
+    fn cron_crate_expressions_are_accepted() {
+        let schedule = Schedule::new(Some("30 0 9 * * Mon-Fri"), None).unwrap();
+        assert_eq!(
+            Some(at("2024-01-08T09:00:30Z")),
+            schedule.next_after(at("2024-01-06T12:00:00Z"))
+        );
+        Schedule::new(Some("@hourly"), None).unwrap();
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected() {
+        assert!(Schedule::new(Some("* * *"), None).is_err());
+        assert!(Schedule::new(Some("61 * * * *"), None).is_err());
+        assert!(Schedule::new(None, Some(Duration::ZERO)).is_err());
+        assert!(Schedule::new(Some("@daily"), Some(Duration::from_secs(60))).is_err());
+        assert!(Schedule::new(None, None).is_err());
+    }
+
+    #[test]
+    fn intervals_count_from_the_previous_tick() {
+        let schedule = Schedule::new(None, Some(Duration::from_secs(90))).unwrap();
+        assert_eq!(
+            Some(at("2024-01-01T10:01:30Z")),
+            schedule.next_tick(at("2024-01-01T10:00:00Z"), at("2024-01-01T10:00:05Z"))
+        );
+    }
+
+    #[test]
+    fn missed_ticks_are_skipped() {
+        let schedule = Schedule::new(None, Some(Duration::from_secs(60))).unwrap();
+        assert_eq!(
+            Some(at("2024-01-01T10:10:30Z")),
+            schedule.next_tick(at("2024-01-01T10:00:00Z"), at("2024-01-01T10:09:30Z"))
+        );
+    }
+
+    #[test]
+    fn runs_cover_ticks_of_other_replicas() {
+        let cron = Schedule::new(Some("*/15 * * * *"), None).unwrap();
+        assert!(cron.covers(at("2024-01-01T10:15:00Z"), at("2024-01-01T10:15:00Z")));
+        assert!(!cron.covers(at("2024-01-01T10:00:00Z"), at("2024-01-01T10:15:00Z")));
+
+        let interval = Schedule::new(None, Some(Duration::from_secs(60))).unwrap();
+        assert!(interval.covers(at("2024-01-01T10:00:00Z"), at("2024-01-01T10:00:30Z")));
+        assert!(!interval.covers(at("2024-01-01T10:00:00Z"), at("2024-01-01T10:01:00Z")));
+    }
+
+    #[test]
+    fn jitter_is_bounded() {
+        assert_eq!(Duration::ZERO, jitter(Duration::ZERO));
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_secs(2)) <= Duration::from_secs(2));
+        }
+    }
+}
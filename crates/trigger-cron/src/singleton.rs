@@ -0,0 +1,206 @@
+//! Claims on the runs of singleton cron triggers, so that only one of the
+//! replicas of an app runs a trigger on each tick.
+//!
+//! Each trigger has a run record, under the key `spin-cron/<trigger>/last-run`
+//! of the locks store, saying which tick it last ran for. A replica claims a
+//! tick by taking the trigger's lock, `cron/<trigger>`, and recording the
+//! tick unless a run by any replica already covers it.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use spin_factor_key_value::{Store, StoreManager};
+use spin_factor_locks::locks::LockStore;
+use tokio::sync::OnceCell;
+
+use crate::Schedule;
+
+/// How long a claim may take before other replicas may claim the tick
+/// instead.
+const CLAIM_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RunRecord {
+    /// The tick the run was scheduled for.
+    scheduled: DateTime<Utc>,
+    /// When the run was claimed.
+    claimed_at: DateTime<Utc>,
+}
+
+/// The run records of singleton cron triggers, kept in the locks store.
+pub struct RunClaims {
+    locks: LockStore,
+    store_manager: Arc<dyn StoreManager>,
+    store: OnceCell<Arc<dyn Store>>,
+}
+
+impl RunClaims {
+    /// Creates claims kept in the store of the given locks, which the store
+    /// manager must be able to open.
+    pub fn new(locks: LockStore, store_manager: Arc<dyn StoreManager>) -> Self {
+        Self {
+            locks,
+            store_manager,
+            store: OnceCell::new(),
+        }
+    }
+
+    async fn store(&self) -> anyhow::Result<&dyn Store> {
+        let label = self.locks.store_label();
+        let store = self
+            .store
+            .get_or_try_init(|| self.store_manager.get(label))
+            .await
+            .with_context(|| format!("failed to open key-value store {label:?}"))?;
+        Ok(store.as_ref())
+    }
+
+    /// Claims the run of a trigger for a tick of its schedule. Returns false
+    /// if another replica is claiming a run of the trigger, or has run it for
+    /// the tick.
+    pub async fn claim(
+        &self,
+        trigger_id: &str,
+        schedule: &Schedule,
+        tick: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let Some(lock) = self
+            .locks
+            .acquire(&format!("cron/{trigger_id}"), CLAIM_TTL)
+            .await?
+        else {
+            return Ok(false);
+        };
+        let claimed = self.record(trigger_id, schedule, tick).await;
+        // The lock would expire anyway, so failing to release it only delays
+        // the next claim
+        if let Err(err) = self.locks.release(&lock).await {
+            tracing::warn!("Failed to release lock of cron trigger {trigger_id}: {err:?}");
+        }
+        claimed
+    }
+
+    async fn record(
+        &self,
+        trigger_id: &str,
+        schedule: &Schedule,
+        tick: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let store = self.store().await?;
+        let key = format!("spin-cron/{trigger_id}/last-run");
+        let value = store.get(&key).await.context("failed to read run record")?;
+        if let Some(value) = value {
+            match serde_json::from_slice::<RunRecord>(&value) {
+                Ok(record) if schedule.covers(record.scheduled, tick) => return Ok(false),
+                Ok(_) => {}
+                Err(err) => tracing::warn!("Ignoring invalid run record {key:?}: {err}"),
+            }
+        }
+        let record = RunRecord {
+            scheduled: tick,
+            claimed_at: Utc::now(),
+        };
+        store
+            .set(&key, &serde_json::to_vec(&record)?)
+            .await
+            .context("failed to write run record")?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_factor_key_value::runtime_config::spin::MakeKeyValueStore;
+    use spin_key_value_memory::MemoryKeyValueStore;
+    use spin_key_value_spin::{SpinKeyValueRuntimeConfig, SpinKeyValueStore};
+
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    fn replicas() -> (RunClaims, RunClaims) {
+        replicas_sharing(Arc::new(
+            MemoryKeyValueStore::new()
+                .make_store(Default::default())
+                .unwrap(),
+        ))
+    }
+
+    fn replicas_sharing(store_manager: Arc<dyn StoreManager>) -> (RunClaims, RunClaims) {
+        let replica = || {
+            RunClaims::new(
+                LockStore::new(store_manager.clone(), "default"),
+                store_manager.clone(),
+            )
+        };
+        (replica(), replica())
+    }
+
+    #[tokio::test]
+    async fn each_tick_is_claimed_once() -> anyhow::Result<()> {
+        let (a, b) = replicas();
+        let schedule = Schedule::new(Some("*/15 * * * *"), None)?;
+        let tick = at("2024-01-01T10:15:00Z");
+        assert!(a.claim("report", &schedule, tick).await?);
+        assert!(!b.claim("report", &schedule, tick).await?);
+        assert!(b.claim("cleanup", &schedule, tick).await?);
+
+        let next = at("2024-01-01T10:30:00Z");
+        assert!(b.claim("report", &schedule, next).await?);
+        assert!(!a.claim("report", &schedule, next).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn interval_ticks_are_claimed_once_per_interval() -> anyhow::Result<()> {
+        let (a, b) = replicas();
+        let schedule = Schedule::new(None, Some(Duration::from_secs(60)))?;
+        let (tick_a, tick_b) = (at("2024-01-01T10:01:00Z"), at("2024-01-01T10:01:30Z"));
+        assert!(a.claim("sync", &schedule, tick_a).await?);
+        assert!(!b.claim("sync", &schedule, tick_b).await?);
+        let next_a = at("2024-01-01T10:02:00Z");
+        assert!(a.claim("sync", &schedule, next_a).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ticks_are_not_claimed_while_another_replica_is_claiming() -> anyhow::Result<()> {
+        let (a, b) = replicas();
+        let schedule = Schedule::new(Some("@hourly"), None)?;
+        let tick = at("2024-01-01T11:00:00Z");
+        let lock = b.locks.acquire("cron/report", CLAIM_TTL).await?.unwrap();
+        assert!(!a.claim("report", &schedule, tick).await?);
+        b.locks.release(&lock).await?;
+        assert!(a.claim("report", &schedule, tick).await?);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_claims_of_a_tick_have_one_winner() -> anyhow::Result<()> {
+        // SQLite stores use the default, compare-and-swap based, locks
+        let (a, b) = replicas_sharing(Arc::new(
+            SpinKeyValueStore::new(None).make_store(SpinKeyValueRuntimeConfig::new(None))?,
+        ));
+        let (a, b) = (Arc::new(a), Arc::new(b));
+        let schedule = Arc::new(Schedule::new(Some("* * * * *"), None)?);
+        for minute in 0..30 {
+            let tick = at("2024-01-01T10:00:00Z") + chrono::Duration::minutes(minute);
+            let claim = |replica: &Arc<RunClaims>| {
+                let (replica, schedule) = (replica.clone(), schedule.clone());
+                tokio::spawn(async move { replica.claim("report", &schedule, tick).await })
+            };
+            let (claim_a, claim_b) = (claim(&a), claim(&b));
+            let (claimed_a, claimed_b) = (claim_a.await??, claim_b.await??);
+            assert!(
+                claimed_a != claimed_b,
+                "tick {tick} was claimed by {} replicas",
+                u8::from(claimed_a) + u8::from(claimed_b)
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Implementation for the Spin gRPC trigger.
+
+mod codec;
+mod reflection;
+mod routes;
+mod server;
+
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use clap::Args;
+use serde::Deserialize;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{App, Trigger};
+
+use reflection::Reflection;
+use server::GrpcServer;
+
+/// A [`spin_trigger::TriggerApp`] for the gRPC trigger.
+pub(crate) type TriggerApp<F> = spin_trigger::TriggerApp<GrpcTrigger, F>;
+
+#[derive(Args)]
+pub struct CliArgs {
+    /// IP address and port to listen on for gRPC calls
+    #[clap(
+        long = "grpc-listen",
+        env = "SPIN_GRPC_LISTEN_ADDR",
+        default_value = "127.0.0.1:50051"
+    )]
+    pub address: SocketAddr,
+
+    /// The path to an encoded protobuf `FileDescriptorSet` describing the app's services. If any are given, the gRPC server reflection service is served, so that tools such as grpcurl can discover them
+    #[clap(long = "grpc-descriptor-set")]
+    pub descriptor_sets: Vec<PathBuf>,
+}
+
+/// gRPC trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Fully-qualified name of the service the component handles
+    service: String,
+    /// Optionally handle only this method of the service
+    method: Option<String>,
+}
+
+/// Runs components for unary gRPC calls, routed by service and method.
+pub struct GrpcTrigger {
+    /// The address the server should listen on.
+    listen_addr: SocketAddr,
+    reflection: Option<Reflection>,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for GrpcTrigger {
+    const TYPE: &'static str = "grpc";
+
+    type CliArgs = CliArgs;
+    type InstanceState = ();
+
+    fn new(cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        let reflection = if cli_args.descriptor_sets.is_empty() {
+            None
+        } else {
+            Some(Reflection::load(&cli_args.descriptor_sets)?)
+        };
+        Ok(Self {
+            listen_addr: cli_args.address,
+            reflection,
+        })
+    }
+
+    async fn run(self, trigger_app: TriggerApp<F>) -> anyhow::Result<()> {
+        let server = GrpcServer::new(self.listen_addr, self.reflection, trigger_app).await?;
+        Arc::new(server).serve().await
+    }
+}
@@ -0,0 +1,86 @@
+//! The gRPC server reflection service, which describes an app's services to
+//! tools such as `grpcurl`.
+
+use std::{convert::Infallible, path::PathBuf};
+
+use anyhow::Context;
+use futures::future::BoxFuture;
+use http::{Request, Response};
+use hyper::body::Incoming;
+use tonic::{body::BoxBody, codegen::Service};
+
+type Handler =
+    Box<dyn Fn(Request<Incoming>) -> BoxFuture<'static, Response<BoxBody>> + Send + Sync>;
+
+/// Serves both versions of the reflection protocol in use, from the services
+/// in a set of protobuf descriptors.
+pub(crate) struct Reflection {
+    v1: Handler,
+    v1alpha: Handler,
+}
+
+impl Reflection {
+    /// Loads encoded `FileDescriptorSet`s from the given files, as produced
+    /// by `protoc --descriptor_set_out` or `buf build`.
+    pub fn load(paths: &[PathBuf]) -> anyhow::Result<Self> {
+        let descriptor_sets = paths
+            .iter()
+            .map(|path| {
+                std::fs::read(path).with_context(|| {
+                    format!("failed to read gRPC descriptor set {}", path.display())
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let builder = || {
+            descriptor_sets.iter().fold(
+                tonic_reflection::server::Builder::configure(),
+                |builder, descriptor_set| {
+                    builder.register_encoded_file_descriptor_set(descriptor_set)
+                },
+            )
+        };
+        Ok(Self {
+            v1: handler(
+                builder()
+                    .build_v1()
+                    .context("invalid gRPC descriptor set")?,
+            ),
+            v1alpha: handler(
+                builder()
+                    .build_v1alpha()
+                    .context("invalid gRPC descriptor set")?,
+            ),
+        })
+    }
+
+    /// Returns the handler for calls to the given path, if it is a method of
+    /// the reflection service.
+    pub fn handler(&self, path: &str) -> Option<&Handler> {
+        let (service, _method) = path.strip_prefix('/')?.split_once('/')?;
+        match service {
+            "grpc.reflection.v1.ServerReflection" => Some(&self.v1),
+            "grpc.reflection.v1alpha.ServerReflection" => Some(&self.v1alpha),
+            _ => None,
+        }
+    }
+}
+
+fn handler<S>(service: S) -> Handler
+where
+    S: Service<Request<Incoming>, Response = Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    Box::new(move |request| {
+        let mut service = service.clone();
+        Box::pin(async move {
+            match service.call(request).await {
+                Ok(response) => response,
+                Err(never) => match never {},
+            }
+        })
+    })
+}
@@ -0,0 +1,170 @@
+//! Routing of gRPC calls to components.
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+
+/// Routes calls, by the service and method in their paths, to the components
+/// handling them. A route for a single method takes precedence over a route
+/// for every method of its service.
+pub(crate) struct Router<T> {
+    /// Maps `<service>/<method>` -> handler
+    methods: HashMap<String, T>,
+    /// Maps `<service>` -> handler
+    services: HashMap<String, T>,
+}
+
+/// A call matched to a handler.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RouteMatch<'a, T> {
+    pub service: &'a str,
+    pub method: &'a str,
+    pub handler: &'a T,
+}
+
+// Derived impls would require `T: Clone` and `T: Copy`.
+impl<T> Clone for RouteMatch<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RouteMatch<'_, T> {}
+
+// A derived impl would require `T: Default`.
+impl<T> Default for Router<T> {
+    fn default() -> Self {
+        Self {
+            methods: HashMap::new(),
+            services: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Router<T> {
+    /// Adds a route for the given service, and the given method or every
+    /// method of the service. Fails if the route is already taken.
+    pub fn add(&mut self, service: &str, method: Option<&str>, handler: T) -> anyhow::Result<()> {
+        if service.is_empty() || service.contains('/') {
+            bail!("invalid gRPC service name {service:?}");
+        }
+        let (routes, key) = match method {
+            Some(method) if method.is_empty() || method.contains('/') => {
+                bail!("invalid gRPC method name {method:?}")
+            }
+            Some(method) => (&mut self.methods, format!("{service}/{method}")),
+            None => (&mut self.services, service.to_owned()),
+        };
+        if routes.contains_key(&key) {
+            bail!("more than one gRPC trigger handles {key}");
+        }
+        routes.insert(key, handler);
+        Ok(())
+    }
+
+    /// Matches the path of a call, of the form `/<service>/<method>`.
+    pub fn route<'a>(&'a self, path: &'a str) -> Option<RouteMatch<'a, T>> {
+        let (service, method) = path.strip_prefix('/')?.split_once('/')?;
+        if service.is_empty() || method.is_empty() || method.contains('/') {
+            return None;
+        }
+        let handler = self
+            .methods
+            .get(&path[1..])
+            .or_else(|| self.services.get(service))?;
+        Some(RouteMatch {
+            service,
+            method,
+            handler,
+        })
+    }
+
+    /// The routes, in the form `<service>/<method>` or `<service>/*`, with
+    /// their handlers, sorted by route.
+    pub fn routes(&self) -> Vec<(String, &T)> {
+        let mut routes: Vec<_> = self
+            .methods
+            .iter()
+            .map(|(route, handler)| (route.clone(), handler))
+            .chain(
+                self.services
+                    .iter()
+                    .map(|(service, handler)| (format!("{service}/*"), handler)),
+            )
+            .collect();
+        routes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        routes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> Router<&'static str> {
+        let mut router = Router::default();
+        router.add("helloworld.Greeter", None, "greeter").unwrap();
+        router
+            .add("helloworld.Greeter", Some("SayGoodbye"), "farewell")
+            .unwrap();
+        router
+    }
+
+    #[test]
+    fn method_route_takes_precedence_over_service_route() {
+        let router = router();
+        let route = router.route("/helloworld.Greeter/SayGoodbye").unwrap();
+        assert_eq!(*route.handler, "farewell");
+        let route = router.route("/helloworld.Greeter/SayHello").unwrap();
+        assert_eq!(
+            route,
+            RouteMatch {
+                service: "helloworld.Greeter",
+                method: "SayHello",
+                handler: &"greeter",
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_or_malformed_paths_do_not_match() {
+        let router = router();
+        for path in [
+            "/other.Service/SayHello",
+            "/helloworld.Greeter",
+            "/helloworld.Greeter/",
+            "/helloworld.Greeter/SayHello/extra",
+            "helloworld.Greeter/SayHello",
+        ] {
+            assert!(router.route(path).is_none(), "{path:?} should not match");
+        }
+    }
+
+    #[test]
+    fn duplicate_routes_are_refused() {
+        let mut router = router();
+        router.add("helloworld.Greeter", None, "other").unwrap_err();
+        router
+            .add("helloworld.Greeter", Some("SayGoodbye"), "other")
+            .unwrap_err();
+        router
+            .add("helloworld.Greeter", Some("SayHello"), "other")
+            .unwrap();
+    }
+
+    #[test]
+    fn routes_are_listed_in_order() {
+        let routes: Vec<_> = router()
+            .routes()
+            .into_iter()
+            .map(|(route, handler)| (route, *handler))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                ("helloworld.Greeter/*".to_owned(), "greeter"),
+                ("helloworld.Greeter/SayGoodbye".to_owned(), "farewell"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use anyhow::Context;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{Request, Response};
+use hyper::{body::Incoming, server::conn::http2, service::service_fn};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use spin_app::APP_DESCRIPTION_KEY;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_trigger::is_execution_timeout;
+use spin_world::exports::spin::grpc::inbound_grpc::{self, Code, GuestIndices, MetadataEntry};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task,
+};
+use tonic::{
+    body::BoxBody,
+    metadata::{
+        AsciiMetadataKey, AsciiMetadataValue, BinaryMetadataKey, BinaryMetadataValue,
+        KeyAndValueRef, MetadataMap,
+    },
+    server::{Grpc, UnaryService},
+    Status,
+};
+use tracing::Instrument;
+
+use crate::{
+    codec::BytesCodec,
+    reflection::Reflection,
+    routes::{RouteMatch, Router},
+    TriggerApp, TriggerConfig,
+};
+
+/// A gRPC server which runs Spin apps.
+pub(crate) struct GrpcServer<F: RuntimeFactors> {
+    /// The address the server is listening on.
+    listen_addr: SocketAddr,
+    /// Routes calls to components.
+    router: Router<Component>,
+    /// The reflection service, if descriptor sets were given.
+    reflection: Option<Reflection>,
+    /// The app being triggered.
+    trigger_app: TriggerApp<F>,
+}
+
+/// A component handling gRPC calls.
+struct Component {
+    id: String,
+    guest_indices: GuestIndices,
+}
+
+impl<F: RuntimeFactors> GrpcServer<F> {
+    /// Create a new [`GrpcServer`].
+    ///
+    /// If the app's factors include variables, variable templates in the
+    /// trigger configs are resolved.
+    pub(crate) async fn new(
+        listen_addr: SocketAddr,
+        reflection: Option<Reflection>,
+        trigger_app: TriggerApp<F>,
+    ) -> anyhow::Result<Self> {
+        let trigger_configs = match trigger_app.configured_app().app_state::<VariablesFactor>() {
+            Ok(variables) => {
+                variables
+                    .resolve_trigger_configs::<TriggerConfig>(trigger_app.app(), "grpc")
+                    .await?
+            }
+            Err(_) => trigger_app
+                .app()
+                .trigger_configs::<TriggerConfig>("grpc")?
+                .into_iter()
+                .map(|(id, config)| (id.to_owned(), config))
+                .collect(),
+        };
+
+        let mut router = Router::default();
+        for (trigger_id, config) in trigger_configs {
+            let component_id = config.component;
+            let guest_indices = GuestIndices::new(trigger_app.get_instance_pre(&component_id)?)
+                .with_context(|| {
+                    format!(
+                        "component {component_id:?} of grpc trigger {trigger_id:?} does not export spin:grpc/inbound-grpc"
+                    )
+                })?;
+            router
+                .add(
+                    &config.service,
+                    config.method.as_deref(),
+                    Component {
+                        id: component_id,
+                        guest_indices,
+                    },
+                )
+                .with_context(|| format!("invalid grpc trigger {trigger_id:?}"))?;
+        }
+
+        Ok(Self {
+            listen_addr,
+            router,
+            reflection,
+            trigger_app,
+        })
+    }
+
+    /// Serve incoming calls until the listener fails.
+    pub async fn serve(self: Arc<Self>) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(self.listen_addr).await.with_context(|| {
+            format!(
+                "Unable to listen on {listen_addr}",
+                listen_addr = self.listen_addr
+            )
+        })?;
+        self.print_startup_msgs(&listener)?;
+        loop {
+            let (stream, client_addr) = listener.accept().await?;
+            self.clone().serve_connection(stream, client_addr);
+        }
+    }
+
+    fn serve_connection(self: Arc<Self>, stream: TcpStream, client_addr: SocketAddr) {
+        task::spawn(async move {
+            if let Err(err) = http2::Builder::new(TokioExecutor::new())
+                .serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(move |request| {
+                        let server = self.clone();
+                        async move { Ok::<_, Infallible>(server.handle(request, client_addr).await) }
+                    }),
+                )
+                .await
+            {
+                tracing::warn!("Error serving gRPC connection: {err:?}");
+            }
+        });
+    }
+
+    /// Handles a call, with the reflection service or the component routed
+    /// to by the call's path.
+    async fn handle(
+        self: &Arc<Self>,
+        request: Request<Incoming>,
+        client_addr: SocketAddr,
+    ) -> Response<BoxBody> {
+        let path = request.uri().path().to_owned();
+        let span = tracing::info_span!(
+            "spin_trigger_grpc.handle_call",
+            "otel.kind" = "server",
+            "otel.name" = path.trim_start_matches('/'),
+            "rpc.system" = "grpc",
+            "network.peer.address" = %client_addr.ip(),
+            "network.peer.port" = %client_addr.port(),
+        );
+        async {
+            spin_telemetry::extract_trace_context(&request);
+            tracing::info!("Processing call to '{path}'");
+
+            if let Some(reflection) = self.reflection.as_ref().and_then(|r| r.handler(&path)) {
+                return reflection(request).await;
+            }
+            let Some(route) = self.router.route(&path) else {
+                return Status::unimplemented(format!("no component handles {path}")).into_http();
+            };
+            Grpc::new(BytesCodec)
+                .unary(
+                    Call {
+                        server: self,
+                        route,
+                    },
+                    request,
+                )
+                .await
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Runs the component handling a call.
+    async fn handle_call(
+        &self,
+        route: RouteMatch<'_, Component>,
+        request: tonic::Request<Bytes>,
+    ) -> anyhow::Result<Result<inbound_grpc::Response, inbound_grpc::Status>> {
+        let component_id = route.handler.id.as_str();
+
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "grpc",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let instance_builder = self.trigger_app.prepare(component_id)?;
+        instance_builder
+            .invocation_context()
+            .set("method", format!("{}/{}", route.service, route.method));
+        let (instance, mut store) = instance_builder.instantiate(()).await?;
+        let guest = route.handler.guest_indices.load(&mut store, &instance)?;
+
+        let (metadata, _, message) = request.into_parts();
+        let request = inbound_grpc::Request {
+            service: route.service.to_owned(),
+            method: route.method.to_owned(),
+            metadata: metadata_entries(&metadata),
+            message: message.into(),
+        };
+        guest.call_handle_call(&mut store, &request).await
+    }
+
+    fn print_startup_msgs(&self, listener: &TcpListener) -> anyhow::Result<()> {
+        let local_addr = listener.local_addr()?;
+        terminal::step!("\nServing", "gRPC on {local_addr}");
+        tracing::info!("Serving gRPC on {local_addr}");
+
+        println!("Available Methods:");
+        for (route, component) in self.router.routes() {
+            println!("  {}: {route}", component.id);
+            if let Some(component) = self.trigger_app.app().get_component(&component.id) {
+                if let Some(description) = component.get_metadata(APP_DESCRIPTION_KEY)? {
+                    println!("    {description}");
+                }
+            }
+        }
+        if self.reflection.is_some() {
+            println!("  reflection: grpc.reflection.v1.ServerReflection/*");
+        }
+        Ok(())
+    }
+}
+
+/// A unary call routed to a component.
+struct Call<'a, F: RuntimeFactors> {
+    server: &'a GrpcServer<F>,
+    route: RouteMatch<'a, Component>,
+}
+
+impl<'a, F: RuntimeFactors> UnaryService<Bytes> for Call<'a, F> {
+    type Response = Bytes;
+    type Future = BoxFuture<'a, Result<tonic::Response<Bytes>, Status>>;
+
+    fn call(&mut self, request: tonic::Request<Bytes>) -> Self::Future {
+        let server = self.server;
+        let route = self.route;
+        Box::pin(async move {
+            let component_id = route.handler.id.as_str();
+            match server.handle_call(route, request).await {
+                Ok(Ok(response)) => {
+                    let metadata = metadata_map(response.metadata).map_err(|err| {
+                        tracing::error!(
+                            "Component {component_id} returned invalid metadata: {err}"
+                        );
+                        Status::internal("invalid response metadata")
+                    })?;
+                    Ok(tonic::Response::from_parts(
+                        metadata,
+                        Bytes::from(response.message),
+                        Default::default(),
+                    ))
+                }
+                Ok(Err(status)) => Err(Status::new(code(status.code), status.message)),
+                Err(err) if is_execution_timeout(&err) => {
+                    server
+                        .trigger_app
+                        .record_execution_timeout("grpc", component_id);
+                    Err(Status::deadline_exceeded(
+                        "component exceeded its execution timeout",
+                    ))
+                }
+                Err(err) => {
+                    tracing::error!("Component {component_id} failed to handle call: {err:?}");
+                    Err(Status::internal("component failed to handle the call"))
+                }
+            }
+        })
+    }
+}
+
+/// Converts call metadata to entries for a component, decoding binary values.
+fn metadata_entries(metadata: &MetadataMap) -> Vec<MetadataEntry> {
+    metadata
+        .iter()
+        .filter_map(|entry| {
+            let (key, value) = match entry {
+                KeyAndValueRef::Ascii(key, value) => {
+                    (key.as_str(), value.as_encoded_bytes().to_vec())
+                }
+                KeyAndValueRef::Binary(key, value) => {
+                    let Ok(value) = value.to_bytes() else {
+                        tracing::warn!("Ignoring metadata {key} with invalid base64 value");
+                        return None;
+                    };
+                    (key.as_str(), value.to_vec())
+                }
+            };
+            if is_reserved(key) {
+                return None;
+            }
+            Some(MetadataEntry {
+                key: key.to_owned(),
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Converts metadata entries from a component to call metadata, encoding
+/// binary values. Reserved entries are ignored.
+fn metadata_map(entries: Vec<MetadataEntry>) -> anyhow::Result<MetadataMap> {
+    let mut metadata = MetadataMap::new();
+    for MetadataEntry { key, value } in entries {
+        let key = key.to_ascii_lowercase();
+        if is_reserved(&key) {
+            tracing::warn!("Ignoring reserved metadata {key}");
+            continue;
+        }
+        if key.ends_with("-bin") {
+            let name = BinaryMetadataKey::from_bytes(key.as_bytes())
+                .with_context(|| format!("invalid metadata key {key:?}"))?;
+            metadata.append_bin(name, BinaryMetadataValue::from_bytes(&value));
+        } else {
+            let name = AsciiMetadataKey::from_bytes(key.as_bytes())
+                .with_context(|| format!("invalid metadata key {key:?}"))?;
+            let value = AsciiMetadataValue::try_from(value)
+                .with_context(|| format!("invalid value for metadata {key:?}"))?;
+            metadata.append(name, value);
+        }
+    }
+    Ok(metadata)
+}
+
+/// Whether the metadata key is an HTTP/2 or gRPC protocol header, which the
+/// server manages rather than components.
+fn is_reserved(key: &str) -> bool {
+    matches!(
+        key,
+        "connection" | "content-length" | "content-type" | "te" | "transfer-encoding"
+    ) || key.starts_with("grpc-")
+}
+
+fn code(code: Code) -> tonic::Code {
+    match code {
+        Code::Cancelled => tonic::Code::Cancelled,
+        Code::Unknown => tonic::Code::Unknown,
+        Code::InvalidArgument => tonic::Code::InvalidArgument,
+        Code::DeadlineExceeded => tonic::Code::DeadlineExceeded,
+        Code::NotFound => tonic::Code::NotFound,
+        Code::AlreadyExists => tonic::Code::AlreadyExists,
+        Code::PermissionDenied => tonic::Code::PermissionDenied,
+        Code::ResourceExhausted => tonic::Code::ResourceExhausted,
+        Code::FailedPrecondition => tonic::Code::FailedPrecondition,
+        Code::Aborted => tonic::Code::Aborted,
+        Code::OutOfRange => tonic::Code::OutOfRange,
+        Code::Unimplemented => tonic::Code::Unimplemented,
+        Code::Internal => tonic::Code::Internal,
+        Code::Unavailable => tonic::Code::Unavailable,
+        Code::DataLoss => tonic::Code::DataLoss,
+        Code::Unauthenticated => tonic::Code::Unauthenticated,
+    }
+}
@@ -0,0 +1,661 @@
+//! Certificates obtained and renewed from an ACME certificate authority, such
+//! as Let's Encrypt.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use http::{Request, Response, StatusCode};
+use http_body_util::Full;
+use hyper::{body::Bytes, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use instant_acme::{
+    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, NewAccount,
+    NewOrder, OrderStatus,
+};
+use rcgen::{CertificateParams, CustomExtension, DistinguishedName, KeyPair};
+use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use serde::{Deserialize, Serialize};
+use spin_common::ui::quoted_path;
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::{
+    crypto::ring::sign::any_supported_type,
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    ServerConfig,
+};
+
+/// The directory of Let's Encrypt's production certificate authority.
+pub const LETS_ENCRYPT_DIRECTORY: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+/// The ALPN protocol of TLS-ALPN-01 challenge handshakes (RFC 8737).
+pub(crate) const ACME_TLS_ALPN: &[u8] = b"acme-tls/1";
+
+/// How long to wait before retrying after failing to obtain a certificate.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// The longest to sleep before checking whether the certificate needs renewal.
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+/// How many times to poll an order before giving up on it.
+const MAX_ORDER_POLLS: u32 = 10;
+
+/// Settings for obtaining certificates from an ACME certificate authority.
+#[derive(Clone, Debug)]
+pub struct AcmeConfig {
+    /// The domains the certificate is for.
+    pub domains: Vec<String>,
+    /// An email address the certificate authority may contact about the
+    /// certificates, such as before they expire.
+    pub contact: Option<String>,
+    /// The URL of the certificate authority's ACME directory.
+    pub directory_url: String,
+    /// The directory in which the ACME account and certificates are kept
+    /// between runs. If not set, a new account and certificate are obtained
+    /// each time the trigger starts.
+    pub cache_dir: Option<PathBuf>,
+    /// How the certificate authority validates control of the domains.
+    pub challenge: AcmeChallenge,
+    /// The address on which to answer HTTP-01 challenges, which the
+    /// certificate authority makes on port 80.
+    pub http_challenge_addr: SocketAddr,
+}
+
+/// How an ACME certificate authority validates control of a domain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcmeChallenge {
+    /// A request for a token over plain HTTP, on port 80.
+    Http01,
+    /// A TLS handshake for a special certificate on the HTTPS listener,
+    /// which must be on port 443.
+    TlsAlpn01,
+}
+
+impl FromStr for AcmeChallenge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "http-01" => Ok(Self::Http01),
+            "tls-alpn-01" => Ok(Self::TlsAlpn01),
+            _ => Err(format!(
+                "unknown ACME challenge {s:?}; expected \"http-01\" or \"tls-alpn-01\""
+            )),
+        }
+    }
+}
+
+impl AcmeConfig {
+    /// Starts obtaining and renewing certificates, returning the server
+    /// config of a listener which serves them and offers the given ALPN
+    /// protocols.
+    ///
+    /// Until the first certificate is obtained, TLS handshakes fail.
+    pub(crate) async fn server_config(
+        &self,
+        alpn_protocols: Vec<Vec<u8>>,
+    ) -> anyhow::Result<ServerConfig> {
+        if let Some(domain) = self.domains.iter().find(|domain| domain.contains('*')) {
+            bail!("cannot obtain a certificate for {domain:?}: wildcard domains are not supported");
+        }
+        let resolver = Arc::new(CertResolver::default());
+        let acme = Arc::new(Acme {
+            config: self.clone(),
+            resolver: resolver.clone(),
+        });
+        if let Some(cached) = acme.load_cached_cert() {
+            resolver.set_current(cached);
+        }
+        if self.challenge == AcmeChallenge::Http01 {
+            serve_http_challenges(self.http_challenge_addr, resolver.clone()).await?;
+        }
+        println!(
+            "Obtaining certificates for {} from {}",
+            self.domains.join(", "),
+            self.directory_url
+        );
+        tokio::spawn(acme.run());
+
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+        config.alpn_protocols = alpn_protocols;
+        if self.challenge == AcmeChallenge::TlsAlpn01 {
+            config.alpn_protocols.push(ACME_TLS_ALPN.to_vec());
+        }
+        Ok(config)
+    }
+}
+
+/// Obtains and renews the certificate of an [`AcmeConfig`].
+struct Acme {
+    config: AcmeConfig,
+    resolver: Arc<CertResolver>,
+}
+
+/// The account, and the certificate authority it belongs to, kept in the
+/// cache directory.
+#[derive(Serialize, Deserialize)]
+struct CachedAccount {
+    directory_url: String,
+    credentials: AccountCredentials,
+}
+
+impl Acme {
+    /// Renews the certificate whenever it is due, forever.
+    async fn run(self: Arc<Self>) {
+        loop {
+            let renew_in = self
+                .resolver
+                .renew_at()
+                .and_then(|renew_at| renew_at.duration_since(SystemTime::now()).ok())
+                .unwrap_or_default();
+            if !renew_in.is_zero() {
+                tokio::time::sleep(renew_in.min(MAX_CHECK_INTERVAL)).await;
+                continue;
+            }
+            match self.obtain_cert().await {
+                Ok(()) => println!(
+                    "Obtained a certificate for {}",
+                    self.config.domains.join(", ")
+                ),
+                Err(err) => {
+                    tracing::error!(
+                        "Failed to obtain a certificate for {}: {err:#}",
+                        self.config.domains.join(", ")
+                    );
+                    self.resolver.clear_challenges();
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    async fn obtain_cert(&self) -> anyhow::Result<()> {
+        let account = self.account().await?;
+        let identifiers: Vec<_> = self
+            .config
+            .domains
+            .iter()
+            .map(|domain| Identifier::Dns(domain.clone()))
+            .collect();
+        let mut order = account
+            .new_order(&NewOrder {
+                identifiers: &identifiers,
+            })
+            .await
+            .context("failed to place certificate order")?;
+
+        let challenge_type = match self.config.challenge {
+            AcmeChallenge::Http01 => ChallengeType::Http01,
+            AcmeChallenge::TlsAlpn01 => ChallengeType::TlsAlpn01,
+        };
+        let mut challenge_urls = vec![];
+        for authorization in order.authorizations().await? {
+            let Identifier::Dns(domain) = &authorization.identifier;
+            match authorization.status {
+                AuthorizationStatus::Pending => {}
+                AuthorizationStatus::Valid => continue,
+                status => bail!("authorization for {domain} is {status:?}"),
+            }
+            let challenge = authorization
+                .challenges
+                .iter()
+                .find(|challenge| challenge.r#type == challenge_type)
+                .with_context(|| {
+                    format!(
+                        "certificate authority offers no {challenge_type:?} challenge for {domain}"
+                    )
+                })?;
+            let key_authorization = order.key_authorization(challenge);
+            match self.config.challenge {
+                AcmeChallenge::Http01 => self
+                    .resolver
+                    .add_http_challenge(&challenge.token, key_authorization.as_str()),
+                AcmeChallenge::TlsAlpn01 => self.resolver.add_tls_alpn_challenge(
+                    domain,
+                    tls_alpn_challenge_cert(domain, key_authorization.digest().as_ref())?,
+                ),
+            }
+            challenge_urls.push(challenge.url.clone());
+        }
+        for url in &challenge_urls {
+            order.set_challenge_ready(url).await?;
+        }
+
+        let mut delay = Duration::from_secs(1);
+        let mut polls = 0;
+        let status = loop {
+            tokio::time::sleep(delay).await;
+            let state = order.refresh().await?;
+            if matches!(
+                state.status,
+                OrderStatus::Ready | OrderStatus::Valid | OrderStatus::Invalid
+            ) {
+                break state.status;
+            }
+            polls += 1;
+            if polls == MAX_ORDER_POLLS {
+                bail!("certificate order is still {:?}", state.status);
+            }
+            delay = (delay * 2).min(Duration::from_secs(30));
+        };
+        self.resolver.clear_challenges();
+        if status == OrderStatus::Invalid {
+            bail!("certificate authority could not validate control of the domains");
+        }
+
+        let key_pair = KeyPair::generate()?;
+        let mut params = CertificateParams::new(self.config.domains.clone())?;
+        params.distinguished_name = DistinguishedName::new();
+        let csr = params.serialize_request(&key_pair)?;
+        if status == OrderStatus::Ready {
+            order.finalize(csr.der()).await?;
+        }
+        let mut polls = 0;
+        let cert_chain_pem = loop {
+            if let Some(cert_chain_pem) = order.certificate().await? {
+                break cert_chain_pem;
+            }
+            polls += 1;
+            if polls == MAX_ORDER_POLLS {
+                bail!("certificate was not issued in time");
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        };
+        let key_pem = key_pair.serialize_pem();
+
+        let cert = IssuedCert::from_pem(cert_chain_pem.as_bytes(), key_pem.as_bytes())?;
+        if let Some(dir) = &self.config.cache_dir {
+            let (cert_path, key_path) = self.cert_paths(dir);
+            std::fs::create_dir_all(dir)?;
+            write_private(&key_path, key_pem.as_bytes())?;
+            std::fs::write(&cert_path, cert_chain_pem)
+                .with_context(|| format!("failed to write {}", quoted_path(&cert_path)))?;
+        }
+        self.resolver.set_current(cert);
+        Ok(())
+    }
+
+    /// Returns the cached account for the certificate authority, or else
+    /// registers a new one.
+    async fn account(&self) -> anyhow::Result<Account> {
+        let account_path = self
+            .config
+            .cache_dir
+            .as_ref()
+            .map(|dir| dir.join("account.json"));
+        if let Some(path) = &account_path {
+            let cached = std::fs::read(path)
+                .ok()
+                .and_then(|json| serde_json::from_slice::<CachedAccount>(&json).ok())
+                .filter(|cached| cached.directory_url == self.config.directory_url);
+            if let Some(cached) = cached {
+                return Account::from_credentials(cached.credentials)
+                    .await
+                    .context("failed to load cached ACME account");
+            }
+        }
+
+        let contact = self.config.contact.as_ref().map(|contact| {
+            if contact.starts_with("mailto:") {
+                contact.clone()
+            } else {
+                format!("mailto:{contact}")
+            }
+        });
+        let contact: Vec<&str> = contact.iter().map(String::as_str).collect();
+        let (account, credentials) = Account::create(
+            &NewAccount {
+                contact: &contact,
+                terms_of_service_agreed: true,
+                only_return_existing: false,
+            },
+            &self.config.directory_url,
+            None,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "failed to register an account with {}",
+                self.config.directory_url
+            )
+        })?;
+        if let Some(path) = &account_path {
+            let cached = CachedAccount {
+                directory_url: self.config.directory_url.clone(),
+                credentials,
+            };
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            write_private(path, &serde_json::to_vec_pretty(&cached)?)?;
+        }
+        Ok(account)
+    }
+
+    /// Returns the certificate kept in the cache directory, if it covers the
+    /// configured domains and has not expired.
+    fn load_cached_cert(&self) -> Option<IssuedCert> {
+        let dir = self.config.cache_dir.as_ref()?;
+        let (cert_path, key_path) = self.cert_paths(dir);
+        let (Ok(cert_chain_pem), Ok(key_pem)) =
+            (std::fs::read(&cert_path), std::fs::read(&key_path))
+        else {
+            return None;
+        };
+        match IssuedCert::from_pem(&cert_chain_pem, &key_pem) {
+            Ok(cert) if cert.not_after > SystemTime::now() => Some(cert),
+            Ok(_) => None,
+            Err(err) => {
+                tracing::warn!(
+                    "Ignoring cached certificate {}: {err:#}",
+                    quoted_path(&cert_path)
+                );
+                None
+            }
+        }
+    }
+
+    /// The paths of the certificate chain and private key for the
+    /// configured domains in the given cache directory.
+    fn cert_paths(&self, dir: &Path) -> (PathBuf, PathBuf) {
+        let stem = self.config.domains.join("+");
+        (
+            dir.join(format!("{stem}.crt.pem")),
+            dir.join(format!("{stem}.key.pem")),
+        )
+    }
+}
+
+/// An issued certificate chain and its private key.
+struct IssuedCert {
+    key: Arc<CertifiedKey>,
+    not_after: SystemTime,
+    /// When to renew: two thirds of the way through the certificate's
+    /// validity, as certificate authorities recommend.
+    renew_at: SystemTime,
+}
+
+impl IssuedCert {
+    fn from_pem(cert_chain_pem: &[u8], key_pem: &[u8]) -> anyhow::Result<Self> {
+        let cert_chain = CertificateDer::pem_slice_iter(cert_chain_pem)
+            .collect::<Result<Vec<_>, _>>()
+            .context("invalid certificate chain")?;
+        let private_key = PrivateKeyDer::from_pem_slice(key_pem).context("invalid private key")?;
+        let leaf = cert_chain.first().context("empty certificate chain")?;
+        let (_, parsed) = x509_parser::parse_x509_certificate(leaf)
+            .map_err(|err| anyhow::anyhow!("invalid certificate: {err}"))?;
+        let validity = parsed.validity();
+        let time = |timestamp: i64| UNIX_EPOCH + Duration::from_secs(timestamp.max(0) as u64);
+        let not_before = time(validity.not_before.timestamp());
+        let not_after = time(validity.not_after.timestamp());
+        let lifetime = not_after.duration_since(not_before).unwrap_or_default();
+        let signing_key = any_supported_type(&private_key)?;
+        Ok(Self {
+            key: Arc::new(CertifiedKey::new(cert_chain, signing_key)),
+            not_after,
+            renew_at: not_before + lifetime * 2 / 3,
+        })
+    }
+}
+
+/// Returns the self-signed certificate which answers a TLS-ALPN-01
+/// challenge for the given domain.
+fn tls_alpn_challenge_cert(
+    domain: &str,
+    key_authorization_digest: &[u8],
+) -> anyhow::Result<Arc<CertifiedKey>> {
+    let key_pair = KeyPair::generate()?;
+    let mut params = CertificateParams::new(vec![domain.to_owned()])?;
+    params.custom_extensions = vec![CustomExtension::new_acme_identifier(
+        key_authorization_digest,
+    )];
+    let cert = params.self_signed(&key_pair)?;
+    let private_key = PrivateKeyDer::Pkcs8(key_pair.serialize_der().into());
+    Ok(Arc::new(CertifiedKey::new(
+        vec![cert.der().clone()],
+        any_supported_type(&private_key)?,
+    )))
+}
+
+/// Picks the certificate for each TLS handshake: the one obtained, or the one
+/// answering a TLS-ALPN-01 challenge for the certificate authority.
+#[derive(Debug, Default)]
+struct CertResolver {
+    current: RwLock<Option<Current>>,
+    /// Domain -> challenge certificate
+    tls_alpn_challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+    /// Token -> key authorization
+    http_challenges: RwLock<HashMap<String, String>>,
+}
+
+#[derive(Debug)]
+struct Current {
+    key: Arc<CertifiedKey>,
+    renew_at: SystemTime,
+}
+
+impl CertResolver {
+    fn set_current(&self, cert: IssuedCert) {
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Some(Current {
+            key: cert.key,
+            renew_at: cert.renew_at,
+        });
+    }
+
+    fn renew_at(&self) -> Option<SystemTime> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(|current| current.renew_at)
+    }
+
+    fn add_tls_alpn_challenge(&self, domain: &str, key: Arc<CertifiedKey>) {
+        self.tls_alpn_challenges
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(domain.to_owned(), key);
+    }
+
+    fn add_http_challenge(&self, token: &str, key_authorization: &str) {
+        self.http_challenges
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(token.to_owned(), key_authorization.to_owned());
+    }
+
+    fn http_challenge(&self, token: &str) -> Option<String> {
+        self.http_challenges
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(token)
+            .cloned()
+    }
+
+    fn clear_challenges(&self) {
+        self.tls_alpn_challenges
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.http_challenges
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let is_challenge = client_hello
+            .alpn()
+            .is_some_and(|mut protocols| protocols.any(|protocol| protocol == ACME_TLS_ALPN));
+        if is_challenge {
+            let domain = client_hello.server_name()?;
+            return self
+                .tls_alpn_challenges
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(domain)
+                .cloned();
+        }
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(|current| current.key.clone())
+    }
+}
+
+/// Answers HTTP-01 challenges on the given address.
+async fn serve_http_challenges(
+    addr: SocketAddr,
+    resolver: Arc<CertResolver>,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Unable to listen for ACME HTTP-01 challenges on {addr}"))?;
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!("Failed to accept ACME challenge connection: {err}");
+                    continue;
+                }
+            };
+            let resolver = resolver.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| {
+                    let response = http_challenge_response(&resolver, &req);
+                    async move { response }
+                });
+                if let Err(err) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    tracing::warn!("Error serving ACME challenge connection: {err:?}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn http_challenge_response<B>(
+    resolver: &CertResolver,
+    req: &Request<B>,
+) -> Result<Response<Full<Bytes>>, http::Error> {
+    let key_authorization = req
+        .uri()
+        .path()
+        .strip_prefix("/.well-known/acme-challenge/")
+        .and_then(|token| resolver.http_challenge(token));
+    match key_authorization {
+        Some(key_authorization) => Response::builder()
+            .header("content-type", "application/octet-stream")
+            .body(Full::new(key_authorization.into())),
+        None => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Full::default()),
+    }
+}
+
+/// Writes a file readable only by its owner, where the platform allows.
+fn write_private(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .with_context(|| format!("failed to write {}", quoted_path(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    fn self_signed_pem(domain: &str) -> (String, String) {
+        let key_pair = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![domain.to_owned()])
+            .unwrap()
+            .self_signed(&key_pair)
+            .unwrap();
+        (cert.pem(), key_pair.serialize_pem())
+    }
+
+    #[test]
+    fn challenge_types_are_parsed() {
+        assert_eq!("http-01".parse(), Ok(AcmeChallenge::Http01));
+        assert_eq!("tls-alpn-01".parse(), Ok(AcmeChallenge::TlsAlpn01));
+        assert!("dns-01".parse::<AcmeChallenge>().is_err());
+    }
+
+    #[test]
+    fn issued_cert_is_renewed_two_thirds_through_its_validity() {
+        let (cert_pem, key_pem) = self_signed_pem("example.com");
+        let cert = IssuedCert::from_pem(cert_pem.as_bytes(), key_pem.as_bytes()).unwrap();
+        assert!(cert.renew_at < cert.not_after);
+        assert!(cert.renew_at > SystemTime::now());
+    }
+
+    #[tokio::test]
+    async fn http_challenges_are_answered() {
+        let resolver = CertResolver::default();
+        resolver.add_http_challenge("token", "token.thumbprint");
+        let request = |path: &str| Request::get(path).body(()).unwrap();
+
+        let response =
+            http_challenge_response(&resolver, &request("/.well-known/acme-challenge/token"))
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "token.thumbprint");
+
+        let response =
+            http_challenge_response(&resolver, &request("/.well-known/acme-challenge/other"))
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        resolver.clear_challenges();
+        let response =
+            http_challenge_response(&resolver, &request("/.well-known/acme-challenge/token"))
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn cached_cert_is_loaded_for_its_domains() {
+        let dir = tempfile::tempdir().unwrap();
+        let acme = Acme {
+            config: AcmeConfig {
+                domains: vec!["example.com".into(), "www.example.com".into()],
+                contact: None,
+                directory_url: LETS_ENCRYPT_DIRECTORY.into(),
+                cache_dir: Some(dir.path().to_owned()),
+                challenge: AcmeChallenge::TlsAlpn01,
+                http_challenge_addr: ([127, 0, 0, 1], 0).into(),
+            },
+            resolver: Default::default(),
+        };
+        assert!(acme.load_cached_cert().is_none());
+
+        let (cert_pem, key_pem) = self_signed_pem("example.com");
+        let (cert_path, key_path) = acme.cert_paths(dir.path());
+        assert!(cert_path.ends_with("example.com+www.example.com.crt.pem"));
+        std::fs::write(cert_path, cert_pem).unwrap();
+        std::fs::write(key_path, key_pem).unwrap();
+        assert!(acme.load_cached_cert().is_some());
+    }
+}
@@ -0,0 +1,676 @@
+//! Authentication of requests to routes by JSON Web Tokens (JWTs).
+//!
+//! A route with `auth` accepts only requests bearing a token signed by one of
+//! its keys, from its issuer and for its audience. The component gets the
+//! token's verified claims in headers, which are removed from every incoming
+//! request so that clients can't forge them.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use http::{header, HeaderValue, Request, Response, StatusCode};
+use ring::{hmac, signature};
+use serde::Deserialize;
+use spin_http::{
+    body,
+    config::{AuthConfig, Jwk},
+};
+use tokio::sync::RwLock;
+
+use crate::Body;
+
+/// The header holding the `sub` claim of the request's token.
+pub const SUBJECT_HEADER: &str = "spin-auth-subject";
+/// The header holding the roles of the request's token, separated by commas.
+pub const ROLES_HEADER: &str = "spin-auth-roles";
+/// The header holding the claims of the request's token, as base64url-encoded
+/// JSON.
+pub const CLAIMS_HEADER: &str = "spin-auth-claims";
+
+/// The headers set from a request's token.
+pub const AUTH_HEADERS: [&str; 3] = [SUBJECT_HEADER, ROLES_HEADER, CLAIMS_HEADER];
+
+const DEFAULT_LEEWAY: Duration = Duration::from_secs(60);
+
+/// How long a fetched key set is used before it is fetched again.
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(600);
+
+/// How soon a key set may be fetched again for a token signed by a key it
+/// lacks, such as a newly rotated one.
+const JWKS_MIN_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The authentication of a route.
+pub(crate) struct RouteAuth {
+    keys: Vec<Jwk>,
+    remote_keys: Option<RemoteKeys>,
+    issuer: Option<String>,
+    audience: Vec<String>,
+    roles_claim: Option<Vec<String>>,
+    required_roles: Vec<String>,
+    leeway: Duration,
+}
+
+/// Why a request failed authentication.
+#[derive(Debug, PartialEq)]
+pub(crate) enum AuthError {
+    /// The request has no bearer token.
+    MissingToken,
+    /// The request's token isn't valid, for the given reason.
+    InvalidToken(&'static str),
+    /// The request's token has none of the required roles.
+    MissingRole,
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingToken => f.write_str("no bearer token"),
+            Self::InvalidToken(reason) => write!(f, "invalid token: {reason}"),
+            Self::MissingRole => f.write_str("token lacks a required role"),
+        }
+    }
+}
+
+impl AuthError {
+    /// Creates the response to a request which failed authentication: an
+    /// HTTP 401 or 403 response.
+    pub fn response(&self) -> anyhow::Result<Response<Body>> {
+        let (status, challenge) = match self {
+            Self::MissingToken => (StatusCode::UNAUTHORIZED, "Bearer"),
+            Self::InvalidToken(_) => (StatusCode::UNAUTHORIZED, "Bearer error=\"invalid_token\""),
+            Self::MissingRole => (StatusCode::FORBIDDEN, "Bearer error=\"insufficient_scope\""),
+        };
+        Ok(Response::builder()
+            .status(status)
+            .header(header::WWW_AUTHENTICATE, challenge)
+            .body(body::empty())?)
+    }
+}
+
+/// The verified claims of a request's token.
+#[derive(Debug)]
+struct Verified {
+    subject: Option<String>,
+    roles: Vec<String>,
+    /// The token's claims, as encoded in the token.
+    encoded_claims: String,
+}
+
+impl RouteAuth {
+    pub fn from_config(config: &AuthConfig) -> anyhow::Result<Self> {
+        if config.keys.is_empty() && config.jwks_url.is_none() {
+            bail!("auth must set `jwks_url` or `keys`");
+        }
+        for key in &config.keys {
+            VerifyingKey::from_jwk(key).with_context(|| match &key.kid {
+                Some(kid) => format!("invalid key {kid:?}"),
+                None => "invalid key".to_owned(),
+            })?;
+        }
+        let remote_keys = config
+            .jwks_url
+            .as_deref()
+            .map(RemoteKeys::new)
+            .transpose()?;
+        if !config.required_roles.is_empty() && config.roles_claim.is_none() {
+            bail!("auth must set `roles_claim` to require roles");
+        }
+        Ok(Self {
+            keys: config.keys.clone(),
+            remote_keys,
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            roles_claim: config
+                .roles_claim
+                .as_ref()
+                .map(|claim| claim.split('.').map(str::to_owned).collect()),
+            required_roles: config.required_roles.clone(),
+            leeway: config
+                .leeway
+                .as_ref()
+                .map_or(DEFAULT_LEEWAY, |leeway| leeway.duration()),
+        })
+    }
+
+    /// Authenticates the request by its bearer token, setting the token's
+    /// claims in its headers.
+    pub async fn authenticate(&self, req: &mut Request<Body>) -> Result<(), AuthError> {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                let (scheme, token) = value.split_once(' ')?;
+                scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+            })
+            .ok_or(AuthError::MissingToken)?;
+        let verified = self.verify(token, SystemTime::now()).await?;
+
+        let headers = req.headers_mut();
+        let mut set = |name: &'static str, value: &str| {
+            if let Ok(value) = HeaderValue::from_str(value) {
+                headers.insert(name, value);
+            }
+        };
+        if let Some(subject) = &verified.subject {
+            set(SUBJECT_HEADER, subject);
+        }
+        if self.roles_claim.is_some() {
+            set(ROLES_HEADER, &verified.roles.join(","));
+        }
+        set(CLAIMS_HEADER, &verified.encoded_claims);
+        Ok(())
+    }
+
+    async fn verify(&self, token: &str, now: SystemTime) -> Result<Verified, AuthError> {
+        let invalid = AuthError::InvalidToken;
+        let mut segments = token.split('.');
+        let (Some(encoded_header), Some(encoded_claims), Some(encoded_signature), None) = (
+            segments.next(),
+            segments.next(),
+            segments.next(),
+            segments.next(),
+        ) else {
+            return Err(invalid("malformed"));
+        };
+        let header: TokenHeader = decode_json(encoded_header).ok_or(invalid("malformed header"))?;
+        let claims: serde_json::Map<String, serde_json::Value> =
+            decode_json(encoded_claims).ok_or(invalid("malformed claims"))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(encoded_signature)
+            .map_err(|_| invalid("malformed signature"))?;
+
+        // The signed part of a token is its encoded header and claims
+        let message = &token[..encoded_header.len() + 1 + encoded_claims.len()];
+        if !self
+            .signature_verifies(&header, message.as_bytes(), &signature)
+            .await
+        {
+            return Err(invalid("bad signature"));
+        }
+
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let leeway = self.leeway.as_secs_f64();
+        let time = |claim: &str| claims.get(claim).and_then(serde_json::Value::as_f64);
+        match time("exp") {
+            Some(exp) if exp + leeway > now.as_secs_f64() => {}
+            Some(_) => return Err(invalid("expired")),
+            None => return Err(invalid("no expiry")),
+        }
+        if time("nbf").is_some_and(|nbf| nbf - leeway > now.as_secs_f64()) {
+            return Err(invalid("not yet valid"));
+        }
+        if let Some(issuer) = &self.issuer {
+            if claims.get("iss").and_then(serde_json::Value::as_str) != Some(issuer) {
+                return Err(invalid("wrong issuer"));
+            }
+        }
+        if !self.audience.is_empty() {
+            let audiences = match claims.get("aud") {
+                Some(serde_json::Value::String(aud)) => vec![aud.as_str()],
+                Some(serde_json::Value::Array(auds)) => {
+                    auds.iter().filter_map(serde_json::Value::as_str).collect()
+                }
+                _ => vec![],
+            };
+            if !audiences
+                .iter()
+                .any(|aud| self.audience.iter().any(|a| a == aud))
+            {
+                return Err(invalid("wrong audience"));
+            }
+        }
+
+        let roles = self
+            .roles_claim
+            .as_ref()
+            .map(|path| roles(&claims, path))
+            .unwrap_or_default();
+        if !self.required_roles.is_empty()
+            && !roles.iter().any(|role| self.required_roles.contains(role))
+        {
+            return Err(AuthError::MissingRole);
+        }
+        Ok(Verified {
+            subject: claims
+                .get("sub")
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_owned),
+            roles,
+            encoded_claims: encoded_claims.to_owned(),
+        })
+    }
+
+    async fn signature_verifies(&self, header: &TokenHeader, message: &[u8], sig: &[u8]) -> bool {
+        if verify_with(&self.keys, header, message, sig) {
+            return true;
+        }
+        let Some(remote) = &self.remote_keys else {
+            return false;
+        };
+        let keys = remote.keys(false).await;
+        if verify_with(&keys, header, message, sig) {
+            return true;
+        }
+        // The token may be signed by a key added since the set was fetched
+        let has_key = header
+            .kid
+            .as_ref()
+            .is_some_and(|kid| keys.iter().any(|key| key.kid.as_ref() == Some(kid)));
+        if has_key {
+            return false;
+        }
+        let keys = remote.keys(true).await;
+        verify_with(&keys, header, message, sig)
+    }
+}
+
+/// The header of a token.
+#[derive(Deserialize)]
+struct TokenHeader {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(encoded: &str) -> Option<T> {
+    let json = URL_SAFE_NO_PAD.decode(encoded).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// The roles in the claim at the given path of the claims.
+fn roles(claims: &serde_json::Map<String, serde_json::Value>, path: &[String]) -> Vec<String> {
+    let Some((first, rest)) = path.split_first() else {
+        return vec![];
+    };
+    let mut value = claims.get(first);
+    for segment in rest {
+        value = value.and_then(|value| value.get(segment));
+    }
+    match value {
+        Some(serde_json::Value::String(roles)) => {
+            roles.split_whitespace().map(str::to_owned).collect()
+        }
+        Some(serde_json::Value::Array(roles)) => roles
+            .iter()
+            .filter_map(serde_json::Value::as_str)
+            .map(str::to_owned)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Whether any of the keys which could have signed a token verifies its
+/// signature.
+fn verify_with(keys: &[Jwk], header: &TokenHeader, message: &[u8], sig: &[u8]) -> bool {
+    keys.iter()
+        .filter(|key| header.kid.is_none() || key.kid == header.kid)
+        .filter(|key| key.alg.as_ref().is_none_or(|alg| *alg == header.alg))
+        .filter_map(|key| VerifyingKey::from_jwk(key).ok())
+        .any(|key| key.verify(&header.alg, message, sig))
+}
+
+/// A key which verifies token signatures.
+enum VerifyingKey {
+    Hmac(Vec<u8>),
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    Ec { curve: String, point: Vec<u8> },
+    Ed25519(Vec<u8>),
+}
+
+impl VerifyingKey {
+    fn from_jwk(jwk: &Jwk) -> anyhow::Result<Self> {
+        let param = |name: &str, value: &Option<String>| -> anyhow::Result<Vec<u8>> {
+            let value = value
+                .as_deref()
+                .with_context(|| format!("{} key has no `{name}`", jwk.kty))?;
+            URL_SAFE_NO_PAD
+                .decode(value)
+                .with_context(|| format!("`{name}` is not base64url"))
+        };
+        Ok(match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+            ("oct", _) => Self::Hmac(param("k", &jwk.k)?),
+            ("RSA", _) => Self::Rsa {
+                n: param("n", &jwk.n)?,
+                e: param("e", &jwk.e)?,
+            },
+            ("EC", Some(curve @ ("P-256" | "P-384"))) => {
+                // Uncompressed point encoding, as ring expects
+                let mut point = vec![0x04];
+                point.extend(param("x", &jwk.x)?);
+                point.extend(param("y", &jwk.y)?);
+                Self::Ec {
+                    curve: curve.to_owned(),
+                    point,
+                }
+            }
+            ("OKP", Some("Ed25519")) => Self::Ed25519(param("x", &jwk.x)?),
+            (kty, crv) => bail!(
+                "unsupported key type {kty:?}{}",
+                crv.map(|crv| format!(" with curve {crv:?}"))
+                    .unwrap_or_default()
+            ),
+        })
+    }
+
+    fn verify(&self, alg: &str, message: &[u8], sig: &[u8]) -> bool {
+        match (self, alg) {
+            (Self::Hmac(secret), "HS256" | "HS384" | "HS512") => {
+                let algorithm = match alg {
+                    "HS256" => hmac::HMAC_SHA256,
+                    "HS384" => hmac::HMAC_SHA384,
+                    _ => hmac::HMAC_SHA512,
+                };
+                hmac::verify(&hmac::Key::new(algorithm, secret), message, sig).is_ok()
+            }
+            (Self::Rsa { n, e }, _) => {
+                let params: &signature::RsaParameters = match alg {
+                    "RS256" => &signature::RSA_PKCS1_2048_8192_SHA256,
+                    "RS384" => &signature::RSA_PKCS1_2048_8192_SHA384,
+                    "RS512" => &signature::RSA_PKCS1_2048_8192_SHA512,
+                    "PS256" => &signature::RSA_PSS_2048_8192_SHA256,
+                    "PS384" => &signature::RSA_PSS_2048_8192_SHA384,
+                    "PS512" => &signature::RSA_PSS_2048_8192_SHA512,
+                    _ => return false,
+                };
+                signature::RsaPublicKeyComponents { n, e }
+                    .verify(params, message, sig)
+                    .is_ok()
+            }
+            (Self::Ec { curve, point }, _) => {
+                let algorithm = match (curve.as_str(), alg) {
+                    ("P-256", "ES256") => &signature::ECDSA_P256_SHA256_FIXED,
+                    ("P-384", "ES384") => &signature::ECDSA_P384_SHA384_FIXED,
+                    _ => return false,
+                };
+                signature::UnparsedPublicKey::new(algorithm, point)
+                    .verify(message, sig)
+                    .is_ok()
+            }
+            (Self::Ed25519(key), "EdDSA") => {
+                signature::UnparsedPublicKey::new(&signature::ED25519, key)
+                    .verify(message, sig)
+                    .is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A key set fetched from a URL, and refreshed periodically.
+struct RemoteKeys {
+    url: reqwest::Url,
+    client: reqwest::Client,
+    fetched: RwLock<Option<FetchedKeys>>,
+}
+
+struct FetchedKeys {
+    keys: Arc<[Jwk]>,
+    at: Instant,
+}
+
+#[derive(Deserialize)]
+struct KeySet {
+    keys: Vec<Jwk>,
+}
+
+impl RemoteKeys {
+    fn new(url: &str) -> anyhow::Result<Self> {
+        let url = reqwest::Url::parse(url).with_context(|| format!("invalid jwks_url {url:?}"))?;
+        Ok(Self {
+            url,
+            client: reqwest::Client::new(),
+            fetched: Default::default(),
+        })
+    }
+
+    /// The keys in the set, fetched if they are stale or if `refetch` and
+    /// they weren't fetched very recently.
+    async fn keys(&self, refetch: bool) -> Arc<[Jwk]> {
+        let fresh_for = if refetch {
+            JWKS_MIN_REFETCH_INTERVAL
+        } else {
+            JWKS_REFRESH_INTERVAL
+        };
+        if let Some(fetched) = &*self.fetched.read().await {
+            if fetched.at.elapsed() < fresh_for {
+                return fetched.keys.clone();
+            }
+        }
+        let mut fetched = self.fetched.write().await;
+        // Another request may have fetched the keys while this one waited
+        if let Some(fetched) = &*fetched {
+            if fetched.at.elapsed() < fresh_for {
+                return fetched.keys.clone();
+            }
+        }
+        match self.fetch().await {
+            Ok(keys) => {
+                let keys: Arc<[Jwk]> = keys.into();
+                *fetched = Some(FetchedKeys {
+                    keys: keys.clone(),
+                    at: Instant::now(),
+                });
+                keys
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to fetch JSON Web Key Set from {}: {err:?}",
+                    self.url
+                );
+                // The old keys, if any, are better than none; they are kept
+                // until the next attempt
+                match &mut *fetched {
+                    Some(stale) => {
+                        stale.at = Instant::now();
+                        stale.keys.clone()
+                    }
+                    None => Arc::new([]),
+                }
+            }
+        }
+    }
+
+    async fn fetch(&self) -> anyhow::Result<Vec<Jwk>> {
+        let key_set: KeySet = self
+            .client
+            .get(self.url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(key_set.keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_http::config::AuthConfig;
+
+    use super::*;
+
+    const SECRET: &[u8] = b"correct horse battery staple";
+
+    fn hs256_key(kid: &str) -> Jwk {
+        Jwk {
+            kty: "oct".into(),
+            kid: Some(kid.into()),
+            k: Some(URL_SAFE_NO_PAD.encode(SECRET)),
+            ..Default::default()
+        }
+    }
+
+    fn token(kid: &str, claims: serde_json::Value) -> String {
+        let header = serde_json::json!({ "alg": "HS256", "typ": "JWT", "kid": kid });
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let tag = hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, SECRET),
+            message.as_bytes(),
+        );
+        format!("{message}.{}", URL_SAFE_NO_PAD.encode(tag.as_ref()))
+    }
+
+    fn auth() -> RouteAuth {
+        RouteAuth::from_config(&AuthConfig {
+            keys: vec![hs256_key("k1")],
+            issuer: Some("https://auth.example.com/".into()),
+            audience: vec!["api".into()],
+            roles_claim: Some("realm_access.roles".into()),
+            required_roles: vec!["admin".into(), "editor".into()],
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn claims(roles: &[&str]) -> serde_json::Value {
+        serde_json::json!({
+            "sub": "alice",
+            "iss": "https://auth.example.com/",
+            "aud": ["other", "api"],
+            "exp": now() + 300,
+            "realm_access": { "roles": roles },
+        })
+    }
+
+    #[tokio::test]
+    async fn valid_tokens_are_accepted_and_their_claims_passed_on() {
+        let mut req = Request::get("/api")
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", token("k1", claims(&["editor"]))),
+            )
+            .header(SUBJECT_HEADER, "mallory")
+            .body(body::empty())
+            .unwrap();
+        auth().authenticate(&mut req).await.unwrap();
+        assert_eq!(req.headers()[SUBJECT_HEADER], "alice");
+        assert_eq!(req.headers()[ROLES_HEADER], "editor");
+        let claims: serde_json::Value =
+            decode_json(req.headers()[CLAIMS_HEADER].to_str().unwrap()).unwrap();
+        assert_eq!(claims["sub"], "alice");
+    }
+
+    #[tokio::test]
+    async fn invalid_tokens_are_refused() {
+        let auth = auth();
+        let verify = |token: String| {
+            let auth = &auth;
+            async move { auth.verify(&token, SystemTime::now()).await.map(drop) }
+        };
+
+        let mut expired = claims(&["admin"]);
+        expired["exp"] = (now() - 120).into();
+        let mut wrong_issuer = claims(&["admin"]);
+        wrong_issuer["iss"] = "https://evil.example/".into();
+        let mut wrong_audience = claims(&["admin"]);
+        wrong_audience["aud"] = "other".into();
+        let mut tampered = token("k1", claims(&["viewer"]));
+        tampered = {
+            let mut parts = tampered.split('.').map(str::to_owned).collect::<Vec<_>>();
+            parts[1] = URL_SAFE_NO_PAD.encode(claims(&["admin"]).to_string());
+            parts.join(".")
+        };
+
+        assert_eq!(verify(token("k1", claims(&["admin"]))).await, Ok(()));
+        assert_eq!(
+            verify(token("k1", expired)).await,
+            Err(AuthError::InvalidToken("expired"))
+        );
+        assert_eq!(
+            verify(token("k1", wrong_issuer)).await,
+            Err(AuthError::InvalidToken("wrong issuer"))
+        );
+        assert_eq!(
+            verify(token("k1", wrong_audience)).await,
+            Err(AuthError::InvalidToken("wrong audience"))
+        );
+        assert_eq!(
+            verify(token("k2", claims(&["admin"]))).await,
+            Err(AuthError::InvalidToken("bad signature"))
+        );
+        assert_eq!(
+            verify(tampered).await,
+            Err(AuthError::InvalidToken("bad signature"))
+        );
+        assert_eq!(
+            verify(token("k1", claims(&["viewer"]))).await,
+            Err(AuthError::MissingRole)
+        );
+
+        let mut req = Request::get("/api").body(body::empty()).unwrap();
+        let err = auth.authenticate(&mut req).await.unwrap_err();
+        assert_eq!(err, AuthError::MissingToken);
+        assert_eq!(err.response().unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn ec_signed_tokens_are_verified() {
+        use ring::signature::KeyPair;
+
+        let rng = ring::rand::SystemRandom::new();
+        let alg = &signature::ECDSA_P256_SHA256_FIXED_SIGNING;
+        let pkcs8 = signature::EcdsaKeyPair::generate_pkcs8(alg, &rng).unwrap();
+        let key_pair = signature::EcdsaKeyPair::from_pkcs8(alg, pkcs8.as_ref(), &rng).unwrap();
+        let point = key_pair.public_key().as_ref();
+        let auth = RouteAuth::from_config(&AuthConfig {
+            keys: vec![Jwk {
+                kty: "EC".into(),
+                crv: Some("P-256".into()),
+                x: Some(URL_SAFE_NO_PAD.encode(&point[1..33])),
+                y: Some(URL_SAFE_NO_PAD.encode(&point[33..])),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let header = serde_json::json!({ "alg": "ES256" });
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims(&[]).to_string())
+        );
+        let sig = key_pair.sign(&rng, message.as_bytes()).unwrap();
+        let token = format!("{message}.{}", URL_SAFE_NO_PAD.encode(sig.as_ref()));
+        let verified = auth.verify(&token, SystemTime::now()).await.unwrap();
+        assert_eq!(verified.subject.as_deref(), Some("alice"));
+
+        // A token can't opt out of being signed
+        let none = format!(
+            "{}.{}.",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#),
+            URL_SAFE_NO_PAD.encode(claims(&[]).to_string())
+        );
+        assert!(auth.verify(&none, SystemTime::now()).await.is_err());
+    }
+
+    #[test]
+    fn config_needs_keys() {
+        assert!(RouteAuth::from_config(&AuthConfig::default()).is_err());
+        let unsupported = AuthConfig {
+            keys: vec![Jwk {
+                kty: "EC".into(),
+                crv: Some("secp256k1".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(RouteAuth::from_config(&unsupported).is_err());
+    }
+}
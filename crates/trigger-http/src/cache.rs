@@ -0,0 +1,643 @@
+//! Caching of responses, so that a route's component need not recompute a
+//! response it has already sent.
+//!
+//! Responses are cached as a shared cache would cache them: for as long as
+//! their `Cache-Control` header allows, or for their route's `ttl`, and only
+//! for requests which send the same values of the headers the response
+//! `Vary`s on.
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode};
+use http_body_util::BodyExt;
+use hyper::body::{Bytes, Frame, SizeHint};
+use serde::{Deserialize, Serialize};
+use spin_http::{
+    body,
+    config::{ResponseCacheConfig, RouteCacheConfig},
+};
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+const DEFAULT_MAX_ENTRIES: usize = 1000;
+const DEFAULT_MAX_ENTRY_SIZE: u64 = 1 << 20;
+
+/// The prefix of the keys of cached responses in a key-value store.
+const KEY_PREFIX: &str = "spin-http-cache/";
+
+/// The response statuses which are cached.
+const CACHEABLE_STATUSES: &[u16] = &[200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Headers which apply to a single connection, and so are not cached.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// How a route's responses are cached.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RouteCaching {
+    /// How long responses are cached for, in place of the lifetime in their
+    /// `Cache-Control` header.
+    ttl: Option<Duration>,
+}
+
+impl RouteCaching {
+    /// How the route's responses are cached, if they are: by default, only
+    /// if the app enables the cache for all routes.
+    pub(crate) fn from_config(config: Option<&RouteCacheConfig>, by_default: bool) -> Option<Self> {
+        match config {
+            None if by_default => Some(Self { ttl: None }),
+            None | Some(RouteCacheConfig::Enabled(false)) => None,
+            Some(RouteCacheConfig::Enabled(true)) => Some(Self { ttl: None }),
+            Some(RouteCacheConfig::Options(options)) => Some(Self {
+                ttl: Some(options.ttl.duration()).filter(|ttl| !ttl.is_zero()),
+            }),
+        }
+    }
+}
+
+/// A cache of the responses of an app's routes.
+pub(crate) struct ResponseCache {
+    store: Arc<CacheStore>,
+    max_entry_size: u64,
+}
+
+impl ResponseCache {
+    /// Creates a cache with the given config, which stores responses in the
+    /// given key-value store if it sets one.
+    pub(crate) fn new(
+        config: &ResponseCacheConfig,
+        key_value_store: Option<Arc<dyn spin_factor_key_value::Store>>,
+    ) -> Self {
+        let store = match key_value_store {
+            Some(store) => CacheStore::KeyValue(store),
+            None => CacheStore::Memory(MemoryStore {
+                entries: Default::default(),
+                max_entries: config.max_entries.unwrap_or(DEFAULT_MAX_ENTRIES),
+            }),
+        };
+        Self {
+            store: Arc::new(store),
+            max_entry_size: config.max_entry_size.unwrap_or(DEFAULT_MAX_ENTRY_SIZE),
+        }
+    }
+
+    /// Notes what the cache needs of a request to a cached route. Returns
+    /// `None` if the request may not be answered from the cache, nor its
+    /// response cached.
+    pub(crate) fn request(
+        &self,
+        route: &str,
+        caching: &RouteCaching,
+        req: &Request<Body>,
+    ) -> Option<CachedRequest> {
+        // Responses to authorized requests are for the requester alone
+        if req.headers().contains_key(header::AUTHORIZATION) {
+            return None;
+        }
+        let directives = CacheControl::parse(req.headers());
+        if directives.has("no-store") {
+            return None;
+        }
+        let material = format!("{route}\n{}", req.uri());
+        Some(CachedRequest {
+            key: format!(
+                "{KEY_PREFIX}{}",
+                spin_common::sha256::hex_digest_from_bytes(material)
+            ),
+            method: req.method().clone(),
+            headers: req.headers().clone(),
+            revalidate: directives.has("no-cache") || directives.get("max-age") == Some(0),
+            ttl: caching.ttl,
+        })
+    }
+
+    /// Returns the cached response to the request, if there is one.
+    pub(crate) async fn lookup(&self, req: &CachedRequest) -> Option<Response<Body>> {
+        if !matches!(req.method, Method::GET | Method::HEAD) || req.revalidate {
+            return None;
+        }
+        let entry = CacheEntry::decode(&self.store.get(&req.key).await?)?;
+        let now = unix_time(SystemTime::now());
+        if now >= entry.head.expires_at || !entry.head.matches(&req.headers) {
+            return None;
+        }
+        tracing::debug!("Serving cached response to {} {}", req.method, req.key);
+        let age = now.saturating_sub(entry.head.stored_at);
+        entry.into_response(age, req.method == Method::HEAD).ok()
+    }
+
+    /// Caches the response to the request, if it may be cached, as it is
+    /// sent. A successful request with a method which may change the
+    /// resource removes its cached response instead.
+    pub(crate) fn store(&self, req: CachedRequest, res: Response<Body>) -> Response<Body> {
+        if !req.method.is_safe() {
+            if res.status().is_success() || res.status().is_redirection() {
+                let store = self.store.clone();
+                tokio::spawn(async move { store.delete(&req.key).await });
+            }
+            return res;
+        }
+        if req.method != Method::GET {
+            return res;
+        }
+        let Some(head) = EntryHead::new(&req, &res, self.max_entry_size) else {
+            return res;
+        };
+        let pending = PendingEntry {
+            store: self.store.clone(),
+            key: req.key,
+            head,
+        };
+        res.map(|inner| CachingBody::new(inner, pending, self.max_entry_size).boxed())
+    }
+}
+
+/// What the cache needs of a request to a cached route.
+pub(crate) struct CachedRequest {
+    key: String,
+    method: Method,
+    headers: HeaderMap,
+    /// Whether the request asks for a response from the component, rather
+    /// than the cache.
+    revalidate: bool,
+    ttl: Option<Duration>,
+}
+
+/// The directives of `Cache-Control` headers.
+struct CacheControl(Vec<(String, Option<String>)>);
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let directives = headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|directive| {
+                let directive = directive.trim();
+                if directive.is_empty() {
+                    return None;
+                }
+                let (name, value) = match directive.split_once('=') {
+                    Some((name, value)) => (name, Some(value.trim().trim_matches('"').to_owned())),
+                    None => (directive, None),
+                };
+                Some((name.trim().to_ascii_lowercase(), value))
+            })
+            .collect();
+        Self(directives)
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.0.iter().any(|(directive, _)| directive == name)
+    }
+
+    /// The value of a directive with a number of seconds.
+    fn get(&self, name: &str) -> Option<u64> {
+        self.0
+            .iter()
+            .find(|(directive, _)| directive == name)
+            .and_then(|(_, value)| value.as_deref()?.parse().ok())
+    }
+}
+
+/// A cached response's status, headers and freshness.
+#[derive(Debug, Deserialize, Serialize)]
+struct EntryHead {
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// The request headers the response varies on, with the values of the
+    /// request it answered.
+    vary: Vec<(String, Option<String>)>,
+    /// When the response was cached, in seconds since the Unix epoch.
+    stored_at: u64,
+    /// When the response stops being served from the cache.
+    expires_at: u64,
+}
+
+impl EntryHead {
+    /// The head of the cache entry for the response, if it may be cached.
+    fn new(req: &CachedRequest, res: &Response<Body>, max_entry_size: u64) -> Option<Self> {
+        if !CACHEABLE_STATUSES.contains(&res.status().as_u16())
+            || res.headers().contains_key(header::SET_COOKIE)
+        {
+            return None;
+        }
+        let directives = CacheControl::parse(res.headers());
+        if ["no-store", "no-cache", "private"]
+            .iter()
+            .any(|name| directives.has(name))
+        {
+            return None;
+        }
+        let ttl = req
+            .ttl
+            .map(|ttl| ttl.as_secs())
+            .or_else(|| directives.get("s-maxage"))
+            .or_else(|| directives.get("max-age"))
+            .filter(|ttl| *ttl > 0)?;
+        let declared_len = res
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+        if declared_len.is_some_and(|len| len > max_entry_size) {
+            return None;
+        }
+
+        let mut vary = vec![];
+        for value in res.headers().get_all(header::VARY) {
+            for name in value.to_str().ok()?.split(',') {
+                let name = name.trim().to_ascii_lowercase();
+                if name == "*" {
+                    return None;
+                }
+                if !name.is_empty() {
+                    let value = header_value(&req.headers, &name)?;
+                    vary.push((name, value));
+                }
+            }
+        }
+        let headers = res
+            .headers()
+            .iter()
+            .filter(|(name, _)| {
+                !HOP_BY_HOP_HEADERS.contains(&name.as_str()) && *name != header::AGE
+            })
+            .map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect::<Option<_>>()?;
+        let stored_at = unix_time(SystemTime::now());
+        Some(Self {
+            status: res.status().as_u16(),
+            headers,
+            vary,
+            stored_at,
+            expires_at: stored_at.saturating_add(ttl),
+        })
+    }
+
+    /// Whether a request has the values of the headers the response varies
+    /// on that the request it answered did.
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| header_value(headers, name).as_ref() == Some(value))
+    }
+}
+
+/// The value of a header, or `None` within if the request has none. Returns
+/// `None` if the value is not visible ASCII.
+fn header_value(headers: &HeaderMap, name: &str) -> Option<Option<String>> {
+    match headers.get(name) {
+        Some(value) => Some(Some(value.to_str().ok()?.to_owned())),
+        None => Some(None),
+    }
+}
+
+/// A cached response.
+struct CacheEntry {
+    head: EntryHead,
+    body: Bytes,
+}
+
+impl CacheEntry {
+    /// Encodes the entry as its head, as a line of JSON, followed by its body.
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = serde_json::to_vec(&self.head).expect("entry heads serialize");
+        encoded.push(b'\n');
+        encoded.extend_from_slice(&self.body);
+        encoded
+    }
+
+    fn decode(encoded: &[u8]) -> Option<Self> {
+        let newline = encoded.iter().position(|b| *b == b'\n')?;
+        let head = serde_json::from_slice(&encoded[..newline]).ok()?;
+        Some(Self {
+            head,
+            body: Bytes::copy_from_slice(&encoded[newline + 1..]),
+        })
+    }
+
+    fn into_response(self, age: u64, head_only: bool) -> anyhow::Result<Response<Body>> {
+        let mut response = Response::builder().status(StatusCode::from_u16(self.head.status)?);
+        for (name, value) in &self.head.headers {
+            response = response.header(
+                HeaderName::try_from(name.as_str())?,
+                HeaderValue::try_from(value.as_str())?,
+            );
+        }
+        let body = if head_only {
+            body::empty()
+        } else {
+            body::full(self.body)
+        };
+        Ok(response.header(header::AGE, age).body(body)?)
+    }
+}
+
+/// A response which is cached once its body has been sent.
+struct PendingEntry {
+    store: Arc<CacheStore>,
+    key: String,
+    head: EntryHead,
+}
+
+/// A response body which collects what it sends, and caches the response
+/// when it ends, unless it is too large or fails.
+struct CachingBody {
+    inner: Body,
+    pending: Option<PendingEntry>,
+    collected: Vec<u8>,
+    max_entry_size: u64,
+}
+
+impl CachingBody {
+    fn new(inner: Body, pending: PendingEntry, max_entry_size: u64) -> Self {
+        Self {
+            inner,
+            pending: Some(pending),
+            collected: vec![],
+            max_entry_size,
+        }
+    }
+}
+
+impl hyper::body::Body for CachingBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = std::task::ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        match &frame {
+            Some(Ok(frame)) => match frame.data_ref() {
+                Some(data)
+                    if this.collected.len() as u64 + data.len() as u64 <= this.max_entry_size =>
+                {
+                    this.collected.extend_from_slice(data);
+                }
+                // Too large, or with trailers, which are not cached
+                _ => this.pending = None,
+            },
+            Some(Err(_)) => this.pending = None,
+            None => {
+                if let Some(pending) = this.pending.take() {
+                    let entry = CacheEntry {
+                        head: pending.head,
+                        body: std::mem::take(&mut this.collected).into(),
+                    };
+                    let ttl = Duration::from_secs(entry.head.expires_at - entry.head.stored_at);
+                    tokio::spawn(async move {
+                        pending.store.set(&pending.key, entry.encode(), ttl).await
+                    });
+                }
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        // The end of the stream is observed to cache the response
+        self.pending.is_none() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Where cached responses are kept.
+enum CacheStore {
+    Memory(MemoryStore),
+    KeyValue(Arc<dyn spin_factor_key_value::Store>),
+}
+
+struct MemoryStore {
+    entries: Mutex<HashMap<String, MemoryEntry>>,
+    max_entries: usize,
+}
+
+struct MemoryEntry {
+    expires: SystemTime,
+    encoded: Vec<u8>,
+}
+
+impl CacheStore {
+    async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::Memory(memory) => {
+                let entries = memory.entries.lock().unwrap();
+                entries.get(key).map(|entry| entry.encoded.clone())
+            }
+            Self::KeyValue(store) => match store.get(key).await {
+                Ok(entry) => entry,
+                Err(err) => {
+                    tracing::warn!("Failed to read cached response: {err:?}");
+                    None
+                }
+            },
+        }
+    }
+
+    async fn set(&self, key: &str, entry: Vec<u8>, ttl: Duration) {
+        match self {
+            Self::Memory(memory) => {
+                let mut entries = memory.entries.lock().unwrap();
+                if entries.len() >= memory.max_entries && !entries.contains_key(key) {
+                    let now = SystemTime::now();
+                    entries.retain(|_, entry| entry.expires > now);
+                    // Make room by evicting the entry closest to expiring
+                    if entries.len() >= memory.max_entries {
+                        let soonest = entries
+                            .iter()
+                            .min_by_key(|(_, entry)| entry.expires)
+                            .map(|(key, _)| key.clone());
+                        if let Some(soonest) = soonest {
+                            entries.remove(&soonest);
+                        }
+                    }
+                }
+                if memory.max_entries > 0 {
+                    entries.insert(
+                        key.to_owned(),
+                        MemoryEntry {
+                            expires: SystemTime::now() + ttl,
+                            encoded: entry,
+                        },
+                    );
+                }
+            }
+            Self::KeyValue(store) => {
+                if let Err(err) = store.set_with_ttl(key, &entry, ttl).await {
+                    tracing::warn!("Failed to cache response: {err:?}");
+                }
+            }
+        }
+    }
+
+    async fn delete(&self, key: &str) {
+        match self {
+            Self::Memory(memory) => {
+                memory.entries.lock().unwrap().remove(key);
+            }
+            Self::KeyValue(store) => {
+                if let Err(err) = store.delete(key).await {
+                    tracing::warn!("Failed to remove cached response: {err:?}");
+                }
+            }
+        }
+    }
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_http::config::RouteCacheOptions;
+
+    use super::*;
+
+    fn cache() -> ResponseCache {
+        ResponseCache::new(&ResponseCacheConfig::default(), None)
+    }
+
+    fn request(method: Method, headers: &[(&str, &str)]) -> Request<Body> {
+        let mut req = Request::builder()
+            .method(method)
+            .uri("http://example.com/api/items?page=2");
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        req.body(body::empty()).unwrap()
+    }
+
+    fn response(headers: &[(&str, &str)], text: &'static str) -> Response<Body> {
+        let mut res = Response::builder();
+        for (name, value) in headers {
+            res = res.header(*name, *value);
+        }
+        res.body(body::full(Bytes::from_static(text.as_bytes())))
+            .unwrap()
+    }
+
+    async fn send(res: Response<Body>) -> Bytes {
+        let bytes = res.into_body().collect().await.unwrap().to_bytes();
+        // Let the response be cached
+        tokio::task::yield_now().await;
+        bytes
+    }
+
+    #[test]
+    fn route_settings_override_the_default() {
+        assert_eq!(
+            RouteCaching::from_config(None, true),
+            Some(RouteCaching { ttl: None })
+        );
+        assert_eq!(RouteCaching::from_config(None, false), None);
+        assert_eq!(
+            RouteCaching::from_config(Some(&RouteCacheConfig::Enabled(false)), true),
+            None
+        );
+        let options = RouteCacheOptions {
+            ttl: "1m".to_owned().try_into().unwrap(),
+        };
+        assert_eq!(
+            RouteCaching::from_config(Some(&RouteCacheConfig::Options(options)), false),
+            Some(RouteCaching {
+                ttl: Some(Duration::from_secs(60))
+            })
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn fresh_responses_are_served_from_the_cache() {
+        let cache = cache();
+        let caching = RouteCaching { ttl: None };
+        let req = request(Method::GET, &[("accept-language", "en")]);
+
+        let cached = cache.request("/api/...", &caching, &req).unwrap();
+        assert!(cache.lookup(&cached).await.is_none());
+        let res = response(
+            &[
+                ("cache-control", "public, max-age=60"),
+                ("vary", "Accept-Language"),
+            ],
+            "items",
+        );
+        assert_eq!(send(cache.store(cached, res)).await, "items");
+
+        let cached = cache.request("/api/...", &caching, &req).unwrap();
+        let res = cache.lookup(&cached).await.unwrap();
+        assert_eq!(res.headers()[header::AGE], "0");
+        assert_eq!(send(res).await, "items");
+
+        // Another route, or other values of the headers the response varies
+        // on, are not answered from the cache
+        let cached = cache.request("/other/...", &caching, &req).unwrap();
+        assert!(cache.lookup(&cached).await.is_none());
+        let req = request(Method::GET, &[("accept-language", "fr")]);
+        let cached = cache.request("/api/...", &caching, &req).unwrap();
+        assert!(cache.lookup(&cached).await.is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn uncacheable_responses_are_not_cached() {
+        let cache = cache();
+        let caching = RouteCaching { ttl: None };
+        for headers in [
+            &[][..],
+            &[("cache-control", "no-store, max-age=60")],
+            &[("cache-control", "private, max-age=60")],
+            &[("cache-control", "max-age=60"), ("set-cookie", "id=1")],
+            &[("cache-control", "max-age=60"), ("vary", "*")],
+        ] {
+            let req = request(Method::GET, &[]);
+            let cached = cache.request("/api/...", &caching, &req).unwrap();
+            send(cache.store(cached, response(headers, "items"))).await;
+            let cached = cache.request("/api/...", &caching, &req).unwrap();
+            assert!(cache.lookup(&cached).await.is_none(), "{headers:?}");
+        }
+        let req = request(Method::GET, &[("authorization", "Bearer token")]);
+        assert!(cache.request("/api/...", &caching, &req).is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn route_ttl_replaces_the_response_lifetime() {
+        let cache = cache();
+        let caching = RouteCaching {
+            ttl: Some(Duration::from_secs(300)),
+        };
+        let req = request(Method::GET, &[]);
+        let cached = cache.request("/api/...", &caching, &req).unwrap();
+        send(cache.store(cached, response(&[], "items"))).await;
+        let cached = cache.request("/api/...", &caching, &req).unwrap();
+        assert!(cache.lookup(&cached).await.is_some());
+
+        // A successful change to the resource removes it from the cache
+        let req = request(Method::DELETE, &[]);
+        let cached = cache.request("/api/...", &caching, &req).unwrap();
+        send(cache.store(cached, response(&[], ""))).await;
+        let req = request(Method::GET, &[]);
+        let cached = cache.request("/api/...", &caching, &req).unwrap();
+        assert!(cache.lookup(&cached).await.is_none());
+    }
+}
@@ -0,0 +1,157 @@
+//! The addresses of the clients of requests which come through proxies.
+//!
+//! A proxy in front of the server names the client it forwards a request for
+//! in the `Forwarded` or `X-Forwarded-For` header, after any addresses that
+//! earlier proxies named. Only what trusted proxies add can be believed, so
+//! the client is the last address in the chain which isn't a trusted proxy.
+
+use std::net::{IpAddr, SocketAddr};
+
+use http::{
+    header::{AsHeaderName, FORWARDED},
+    HeaderMap,
+};
+use ip_network::IpNetwork;
+
+use crate::policy::parse_networks;
+
+/// The legacy header in which proxies name the clients they forward for.
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// The networks of the proxies whose forwarding headers are trusted.
+#[derive(Debug, Default)]
+pub(crate) struct TrustedProxies {
+    networks: Vec<IpNetwork>,
+}
+
+impl TrustedProxies {
+    pub fn from_config(proxies: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            networks: parse_networks(proxies)?,
+        })
+    }
+
+    /// Returns the address of the client of a request the server received
+    /// from the given peer.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: IpAddr) -> IpAddr {
+        let mut client = peer.to_canonical();
+        if !self.is_trusted(client) {
+            return client;
+        }
+        for forwarded in forwarded_for(headers).into_iter().rev() {
+            // An address a proxy has hidden leaves nothing further to trust
+            let Some(ip) = forwarded else {
+                break;
+            };
+            client = ip.to_canonical();
+            if !self.is_trusted(client) {
+                break;
+            }
+        }
+        client
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+}
+
+/// The addresses proxies have named as the clients of a request, in the
+/// order they were added, or `None` for any which isn't an IP address.
+///
+/// The standard `Forwarded` header is used in preference to the legacy
+/// `X-Forwarded-For` header.
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    if headers.contains_key(FORWARDED) {
+        list_values(headers, FORWARDED)
+            .map(|element| {
+                let node = element.split(';').find_map(|pair| {
+                    let (name, value) = pair.trim().split_once('=')?;
+                    name.eq_ignore_ascii_case("for").then_some(value)
+                })?;
+                parse_node(node)
+            })
+            .collect()
+    } else {
+        list_values(headers, X_FORWARDED_FOR)
+            .map(parse_node)
+            .collect()
+    }
+}
+
+/// The comma-separated values of a header, over all of its lines.
+fn list_values(headers: &HeaderMap, name: impl AsHeaderName) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(name)
+        .into_iter()
+        // A line which isn't text gives a value which isn't an address
+        .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+}
+
+/// Parses the address of a node named by a proxy, which may have a port and
+/// be quoted, and if IPv6 is bracketed when it has a port.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Some(bracketed) = node.strip_prefix('[') {
+        return bracketed.split_once(']')?.0.parse().ok();
+    }
+    node.parse()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(headers: &[(&str, &str)]) -> HeaderMap {
+        headers
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn only_trusted_proxies_are_skipped() {
+        let proxies = TrustedProxies::from_config(&["10.0.0.0/8".into()]).unwrap();
+        let forwarded = headers(&[("x-forwarded-for", "198.51.100.7, 203.0.113.9, 10.0.0.2")]);
+        assert_eq!(
+            proxies.client_ip(&forwarded, ip("10.0.0.1")),
+            ip("203.0.113.9")
+        );
+        // Forwarding headers from anyone else are ignored
+        assert_eq!(
+            proxies.client_ip(&forwarded, ip("192.0.2.1")),
+            ip("192.0.2.1")
+        );
+        assert_eq!(
+            TrustedProxies::default().client_ip(&forwarded, ip("10.0.0.1")),
+            ip("10.0.0.1")
+        );
+        // The chain can't be followed past a hidden address
+        let hidden = headers(&[("x-forwarded-for", "198.51.100.7, unknown")]);
+        assert_eq!(proxies.client_ip(&hidden, ip("10.0.0.1")), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn forwarded_is_preferred() {
+        let proxies = TrustedProxies::from_config(&["10.0.0.1".into()]).unwrap();
+        let forwarded = headers(&[
+            (
+                "forwarded",
+                "for=192.0.2.60;proto=http, For=\"[2001:db8:cafe::17]:4711\"",
+            ),
+            ("x-forwarded-for", "198.51.100.7"),
+        ]);
+        assert_eq!(
+            proxies.client_ip(&forwarded, ip("::ffff:10.0.0.1")),
+            ip("2001:db8:cafe::17")
+        );
+        let ported = headers(&[("x-forwarded-for", "192.0.2.43:47011")]);
+        assert_eq!(proxies.client_ip(&ported, ip("10.0.0.1")), ip("192.0.2.43"));
+    }
+}
@@ -0,0 +1,359 @@
+//! Cross-origin resource sharing (CORS) policies of routes.
+//!
+//! A route with a CORS policy has preflight requests answered by the trigger,
+//! without invoking its component, and has the CORS headers of the policy set
+//! on the component's responses to cross-origin requests.
+
+use anyhow::{bail, Context};
+use http::{
+    header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+        ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS,
+        ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD,
+        ORIGIN, VARY,
+    },
+    HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+};
+use spin_http::{body, config::CorsConfig};
+
+use crate::Body;
+
+/// A route's CORS policy.
+#[derive(Debug)]
+pub(crate) struct RouteCors {
+    /// The allowed origins, or `None` if any origin is allowed.
+    allowed_origins: Option<Vec<String>>,
+    allowed_methods: Vec<Method>,
+    /// The allowed request headers, or `None` if any header is allowed.
+    allowed_headers: Option<Vec<HeaderName>>,
+    exposed_headers: Vec<HeaderName>,
+    max_age: Option<u64>,
+    allow_credentials: bool,
+}
+
+impl RouteCors {
+    pub fn from_config(config: &CorsConfig) -> anyhow::Result<Self> {
+        let allowed_origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+            if config.allow_credentials {
+                bail!("allowed_origins may not include \"*\" when allow_credentials is set");
+            }
+            None
+        } else {
+            if config.allowed_origins.is_empty() {
+                bail!("allowed_origins must list at least one origin");
+            }
+            if let Some(origin) = config.allowed_origins.iter().find(|o| !is_origin(o)) {
+                bail!("invalid origin {origin:?}: expected `\"*\"` or the form `<scheme>://<host>[:<port>]`");
+            }
+            Some(
+                config
+                    .allowed_origins
+                    .iter()
+                    .map(|origin| origin.trim_end_matches('/').to_ascii_lowercase())
+                    .collect(),
+            )
+        };
+        let allowed_methods = match &config.allowed_methods {
+            Some(methods) => methods
+                .iter()
+                .map(|method| {
+                    Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                        .with_context(|| format!("invalid allowed method {method:?}"))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            None => vec![Method::GET, Method::HEAD, Method::POST],
+        };
+        let allowed_headers = if config.allowed_headers.iter().any(|name| name == "*") {
+            None
+        } else {
+            Some(header_names(&config.allowed_headers)?)
+        };
+        Ok(Self {
+            allowed_origins,
+            allowed_methods,
+            allowed_headers,
+            exposed_headers: header_names(&config.exposed_headers)?,
+            max_age: config.max_age.as_ref().map(|age| age.duration().as_secs()),
+            allow_credentials: config.allow_credentials,
+        })
+    }
+
+    /// Answers the request if it is a preflight request.
+    pub fn preflight(&self, req: &Request<Body>) -> anyhow::Result<Option<Response<Body>>> {
+        let headers = req.headers();
+        if req.method() != Method::OPTIONS {
+            return Ok(None);
+        }
+        let (Some(origin), Some(method)) = (
+            headers.get(ORIGIN),
+            headers.get(ACCESS_CONTROL_REQUEST_METHOD),
+        ) else {
+            return Ok(None);
+        };
+
+        let method_allowed = Method::from_bytes(method.as_bytes())
+            .is_ok_and(|method| self.allowed_methods.contains(&method));
+        let requested_headers = requested_headers(headers);
+        let headers_allowed = match &self.allowed_headers {
+            None => true,
+            Some(allowed) => requested_headers
+                .iter()
+                .all(|name| allowed.iter().any(|allowed| allowed.as_str() == name)),
+        };
+        if !self.origin_allowed(origin) || !method_allowed || !headers_allowed {
+            return Ok(Some(
+                Response::builder()
+                    .status(StatusCode::FORBIDDEN)
+                    .header(VARY, preflight_vary())
+                    .body(body::empty())?,
+            ));
+        }
+
+        let mut res = Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(body::empty())?;
+        let res_headers = res.headers_mut();
+        self.set_origin(res_headers, origin);
+        res_headers.insert(
+            ACCESS_CONTROL_ALLOW_METHODS,
+            join(self.allowed_methods.iter().map(Method::as_str))?,
+        );
+        if !requested_headers.is_empty() {
+            // Only the requested headers are listed, which is all the browser
+            // needs, and doesn't reveal the rest of the policy
+            res_headers.insert(
+                ACCESS_CONTROL_ALLOW_HEADERS,
+                join(requested_headers.iter().map(String::as_str))?,
+            );
+        }
+        if let Some(max_age) = self.max_age {
+            res_headers.insert(ACCESS_CONTROL_MAX_AGE, max_age.into());
+        }
+        res_headers.append(VARY, preflight_vary());
+        Ok(Some(res))
+    }
+
+    /// Sets the policy's CORS headers on the response to a request from the
+    /// given origin, if the origin is allowed.
+    pub fn apply(&self, origin: Option<&HeaderValue>, mut res: Response<Body>) -> Response<Body> {
+        let headers = res.headers_mut();
+        // The route's policy takes the place of any the component applies
+        for name in [
+            ACCESS_CONTROL_ALLOW_ORIGIN,
+            ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            ACCESS_CONTROL_EXPOSE_HEADERS,
+        ] {
+            headers.remove(name);
+        }
+        if self.allowed_origins.is_some() {
+            // Whether the headers are set depends on the origin, which
+            // caches must take into account
+            headers.append(VARY, HeaderValue::from_static("origin"));
+        }
+        let Some(origin) = origin.filter(|origin| self.origin_allowed(origin)) else {
+            return res;
+        };
+        self.set_origin(headers, origin);
+        if !self.exposed_headers.is_empty() {
+            if let Ok(exposed) = join(self.exposed_headers.iter().map(HeaderName::as_str)) {
+                headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, exposed);
+            }
+        }
+        res
+    }
+
+    fn origin_allowed(&self, origin: &HeaderValue) -> bool {
+        match &self.allowed_origins {
+            None => true,
+            Some(allowed) => origin.to_str().is_ok_and(|origin| {
+                allowed
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+            }),
+        }
+    }
+
+    fn set_origin(&self, headers: &mut HeaderMap, origin: &HeaderValue) {
+        let allow_origin = match self.allowed_origins {
+            None => HeaderValue::from_static("*"),
+            Some(_) => origin.clone(),
+        };
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.allow_credentials {
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+}
+
+/// The headers a preflight request asks to send, in lower case.
+fn requested_headers(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(ACCESS_CONTROL_REQUEST_HEADERS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The `Vary` header of the answer to a preflight request, which depends on
+/// all of the request's CORS headers.
+fn preflight_vary() -> HeaderValue {
+    HeaderValue::from_static(
+        "origin, access-control-request-method, access-control-request-headers",
+    )
+}
+
+/// Whether a string is a scheme and host with an optional port.
+fn is_origin(origin: &str) -> bool {
+    let Some((scheme, authority)) = origin.trim_end_matches('/').split_once("://") else {
+        return false;
+    };
+    !scheme.is_empty()
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        && !authority.is_empty()
+        && !authority.contains(['/', '?', '#', '*', ' '])
+}
+
+fn header_names(names: &[String]) -> anyhow::Result<Vec<HeaderName>> {
+    names
+        .iter()
+        .map(|name| {
+            HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("invalid header name {name:?}"))
+        })
+        .collect()
+}
+
+fn join<'a>(values: impl Iterator<Item = &'a str>) -> anyhow::Result<HeaderValue> {
+    Ok(HeaderValue::from_str(
+        &values.collect::<Vec<_>>().join(", "),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors(origins: &[&str], credentials: bool) -> RouteCors {
+        RouteCors::from_config(&CorsConfig {
+            allowed_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            allowed_methods: Some(vec!["get".into(), "PUT".into()]),
+            allowed_headers: vec!["Content-Type".into()],
+            exposed_headers: vec!["X-Request-Id".into()],
+            max_age: Some("10m".to_owned().try_into().unwrap()),
+            allow_credentials: credentials,
+        })
+        .unwrap()
+    }
+
+    fn preflight(origin: &str, method: &str, headers: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api")
+            .header(ORIGIN, origin)
+            .header(ACCESS_CONTROL_REQUEST_METHOD, method);
+        if let Some(headers) = headers {
+            builder = builder.header(ACCESS_CONTROL_REQUEST_HEADERS, headers);
+        }
+        builder.body(body::empty()).unwrap()
+    }
+
+    #[test]
+    fn preflight_requests_are_answered() {
+        let policy = cors(&["https://Example.com/"], true);
+        let res = policy
+            .preflight(&preflight(
+                "https://example.com",
+                "PUT",
+                Some("content-type"),
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let headers = res.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, PUT");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        for req in [
+            preflight("https://evil.example", "PUT", None),
+            preflight("https://example.com", "DELETE", None),
+            preflight("https://example.com", "GET", Some("content-type, x-secret")),
+        ] {
+            let res = policy.preflight(&req).unwrap().unwrap();
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+            assert!(!res.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+        }
+
+        let not_preflight = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api")
+            .header(ORIGIN, "https://example.com")
+            .body(body::empty())
+            .unwrap();
+        assert!(policy.preflight(&not_preflight).unwrap().is_none());
+    }
+
+    #[test]
+    fn responses_to_allowed_origins_get_cors_headers() {
+        let policy = cors(&["https://example.com"], false);
+        let origin = HeaderValue::from_static("https://example.com");
+        let res = policy.apply(Some(&origin), Response::new(body::empty()));
+        let headers = res.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[ACCESS_CONTROL_EXPOSE_HEADERS], "x-request-id");
+        assert_eq!(headers[VARY], "origin");
+        assert!(!headers.contains_key(ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let other = HeaderValue::from_static("https://evil.example");
+        let component_res = Response::builder()
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .body(body::empty())
+            .unwrap();
+        let res = policy.apply(Some(&other), component_res);
+        assert!(!res.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(res.headers()[VARY], "origin");
+
+        let any = cors(&["*"], false);
+        let res = any.apply(Some(&other), Response::new(body::empty()));
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!res.headers().contains_key(VARY));
+    }
+
+    #[test]
+    fn any_origin_cannot_allow_credentials() {
+        let config = CorsConfig {
+            allowed_origins: vec!["*".into()],
+            allowed_methods: None,
+            allowed_headers: vec![],
+            exposed_headers: vec![],
+            max_age: None,
+            allow_credentials: true,
+        };
+        assert!(RouteCors::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn origins_must_be_well_formed() {
+        for origins in [vec![], vec!["example.com"], vec!["https://example.com/api"]] {
+            let config = CorsConfig {
+                allowed_origins: origins.into_iter().map(Into::into).collect(),
+                allowed_methods: None,
+                allowed_headers: vec![],
+                exposed_headers: vec![],
+                max_age: None,
+                allow_credentials: false,
+            };
+            assert!(RouteCors::from_config(&config).is_err());
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+use anyhow::Context;
+use http::{
+    header::{HeaderValue, ALT_SVC},
+    uri::Scheme,
+};
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use spin_factors::RuntimeFactors;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    task,
+};
+use tokio_rustls::{rustls, TlsAcceptor};
+
+use crate::{acme::ACME_TLS_ALPN, HttpProtocols, HttpServer, TlsConfig};
+
+/// The version of an app with which an HTTP listener serves new requests.
+///
+/// Switching to a new version leaves requests already being handled,
+/// including those on kept-alive connections, to finish on the version it
+/// replaces, which is dropped once they have.
+pub struct Deployment<F: RuntimeFactors> {
+    current: RwLock<Arc<HttpServer<F>>>,
+    /// The URL of the listener, once it is listening.
+    base_url: OnceLock<String>,
+}
+
+impl<F: RuntimeFactors> Deployment<F> {
+    /// Create a new [`Deployment`] of the given server's version of the app.
+    pub fn new(server: Arc<HttpServer<F>>) -> Self {
+        Self {
+            current: RwLock::new(server),
+            base_url: OnceLock::new(),
+        }
+    }
+
+    /// The server for the current version of the app.
+    pub fn current(&self) -> Arc<HttpServer<F>> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Switches new requests to the given server's version of the app.
+    ///
+    /// The returned future completes once the previous version has finished
+    /// the requests it was handling.
+    pub fn switch(
+        &self,
+        server: Arc<HttpServer<F>>,
+    ) -> anyhow::Result<impl Future<Output = ()> + Send + 'static> {
+        let current = self.current();
+        anyhow::ensure!(
+            server.listen_addr() == current.listen_addr()
+                && server.tls_config().is_some() == current.tls_config().is_some()
+                && server.protocols() == current.protocols(),
+            "the new version of the app must be served on the same address, scheme and protocols"
+        );
+        server.start_warm_instances()?;
+        if let Some(base_url) = self.base_url.get() {
+            server.print_routes(base_url)?;
+        }
+
+        let previous = std::mem::replace(
+            &mut *self.current.write().unwrap_or_else(PoisonError::into_inner),
+            server,
+        );
+        // Warm instances of the previous version would never be used, and
+        // refer back to its server
+        previous.trigger_app().stop_warm_instances();
+        Ok(previous.drained())
+    }
+
+    /// Serve incoming requests with the current version of the app.
+    pub async fn serve(self: Arc<Self>) -> anyhow::Result<()> {
+        let (listen_addr, tls_config, protocols) = {
+            let server = self.current();
+            server.start_warm_instances()?;
+            (
+                server.listen_addr(),
+                server.tls_config().cloned(),
+                server.protocols(),
+            )
+        };
+        let listener = TcpListener::bind(listen_addr)
+            .await
+            .with_context(|| format!("Unable to listen on {listen_addr}"))?;
+        if let Some(tls_config) = tls_config {
+            self.serve_https(listener, tls_config, protocols).await?;
+        } else {
+            self.serve_http(listener, protocols).await?;
+        }
+        Ok(())
+    }
+
+    async fn serve_http(
+        self: Arc<Self>,
+        listener: TcpListener,
+        protocols: HttpProtocols,
+    ) -> anyhow::Result<()> {
+        self.print_startup_msgs("http", &listener)?;
+        loop {
+            let (stream, client_addr) = listener.accept().await?;
+            self.clone()
+                .serve_connection(stream, Scheme::HTTP, client_addr, protocols, None);
+        }
+    }
+
+    async fn serve_https(
+        self: Arc<Self>,
+        listener: TcpListener,
+        tls_config: TlsConfig,
+        protocols: HttpProtocols,
+    ) -> anyhow::Result<()> {
+        self.print_startup_msgs("https", &listener)?;
+        let server_config = tls_config.server_config(protocols.alpn_protocols()).await?;
+        let alt_svc = if protocols.http3() {
+            let local_addr = listener.local_addr()?;
+            self.clone().serve_http3(local_addr, &server_config)?;
+            Some(HeaderValue::from_str(&format!(
+                "h3=\":{}\"; ma=86400",
+                local_addr.port()
+            ))?)
+        } else {
+            None
+        };
+        let acceptor = TlsAcceptor::from(server_config);
+        loop {
+            let (stream, client_addr) = listener.accept().await?;
+            match acceptor.accept(stream).await {
+                // A TLS-ALPN-01 challenge is answered by the handshake alone
+                Ok(stream) if stream.get_ref().1.alpn_protocol() == Some(ACME_TLS_ALPN) => {}
+                Ok(stream) => self.clone().serve_connection(
+                    stream,
+                    Scheme::HTTPS,
+                    client_addr,
+                    protocols,
+                    alt_svc.clone(),
+                ),
+                Err(err) => tracing::error!(?err, "Failed to start TLS session"),
+            }
+        }
+    }
+
+    #[cfg(feature = "experimental-h3")]
+    fn serve_http3(
+        self: Arc<Self>,
+        addr: SocketAddr,
+        server_config: &rustls::ServerConfig,
+    ) -> anyhow::Result<()> {
+        crate::http3::serve(self, addr, server_config)
+    }
+
+    #[cfg(not(feature = "experimental-h3"))]
+    fn serve_http3(
+        self: Arc<Self>,
+        _addr: SocketAddr,
+        _server_config: &rustls::ServerConfig,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("HTTP/3 support is not enabled in this build")
+    }
+
+    fn print_startup_msgs(&self, scheme: &str, listener: &TcpListener) -> anyhow::Result<()> {
+        let local_addr = listener.local_addr()?;
+        let base_url = format!("{scheme}://{local_addr:?}");
+        self.current().print_startup_msgs(&base_url)?;
+        let _ = self.base_url.set(base_url);
+        Ok(())
+    }
+
+    fn serve_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+        self: Arc<Self>,
+        stream: S,
+        server_scheme: Scheme,
+        client_addr: SocketAddr,
+        protocols: HttpProtocols,
+        alt_svc: Option<HeaderValue>,
+    ) {
+        task::spawn(async move {
+            if let Err(err) = protocols
+                .connection_builder()
+                .serve_connection(
+                    TokioIo::new(stream),
+                    service_fn(move |request| {
+                        // Each request is handled by the version current when it arrives
+                        let response = self.current().instrumented_service_fn(
+                            server_scheme.clone(),
+                            client_addr,
+                            request,
+                        );
+                        let alt_svc = alt_svc.clone();
+                        async move {
+                            let mut response = response.await?;
+                            if let Some(alt_svc) = alt_svc {
+                                response.headers_mut().entry(ALT_SVC).or_insert(alt_svc);
+                            }
+                            anyhow::Ok(response)
+                        }
+                    }),
+                )
+                .await
+            {
+                tracing::warn!("Error serving HTTP connection: {err:?}");
+            }
+        });
+    }
+}
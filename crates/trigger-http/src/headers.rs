@@ -80,8 +80,28 @@ pub fn compute_default_headers<'a>(
     Ok(res)
 }
 
+/// Sets the `Host` header of an HTTP/2 or HTTP/3 request from its
+/// `:authority`, so that components see the header as they would over
+/// HTTP/1.1.
+pub fn set_host_header(req: &mut Request<Body>) {
+    if req.version() < http::Version::HTTP_2 || req.headers().contains_key(http::header::HOST) {
+        return;
+    }
+    let host = req
+        .uri()
+        .authority()
+        .and_then(|authority| http::HeaderValue::from_str(authority.as_str()).ok());
+    if let Some(host) = host {
+        req.headers_mut().insert(http::header::HOST, host);
+    }
+}
+
 pub fn strip_forbidden_headers(req: &mut Request<Body>) {
     let headers = req.headers_mut();
+    // Only the trigger may set the claims of a request's token
+    for name in crate::auth::AUTH_HEADERS {
+        headers.remove(name);
+    }
     if let Some(host_header) = headers.get("Host") {
         if let Ok(host) = host_header.to_str() {
             if is_service_chaining_host(host) {
@@ -152,6 +172,22 @@ mod tests {
     use anyhow::Result;
     use spin_http::routes::Router;
 
+    #[test]
+    fn host_header_is_set_from_authority() {
+        let mut req = Request::get("https://example.com:8443/api")
+            .version(http::Version::HTTP_2)
+            .body(Default::default())
+            .unwrap();
+        set_host_header(&mut req);
+        assert_eq!(req.headers()[http::header::HOST], "example.com:8443");
+
+        let mut req = Request::get("https://example.com/api")
+            .body(Default::default())
+            .unwrap();
+        set_host_header(&mut req);
+        assert!(!req.headers().contains_key(http::header::HOST));
+    }
+
     #[test]
     fn test_spin_header_keys() {
         assert_eq!(
@@ -314,6 +350,20 @@ mod tests {
         assert!(req.headers().get("Host").is_none());
     }
 
+    #[test]
+    fn auth_headers_are_removed() {
+        let mut req = Request::get("http://example.com")
+            .header("spin-auth-subject", "admin")
+            .header("accept", "text/plain")
+            .body(Default::default())
+            .unwrap();
+
+        strip_forbidden_headers(&mut req);
+
+        assert_eq!(1, req.headers().len());
+        assert!(req.headers().get("spin-auth-subject").is_none());
+    }
+
     #[test]
     fn non_forbidden_headers_are_not_removed() {
         let mut req = Request::get("http://test.example.com")
@@ -0,0 +1,130 @@
+//! Serving requests over HTTP/3, on a QUIC endpoint beside the TCP listener.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::Context;
+use futures::{channel::mpsc, SinkExt};
+use http::{Request, Response};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::{Buf, Bytes, Frame};
+use spin_factors::RuntimeFactors;
+use tokio_rustls::rustls;
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Deployment;
+
+type RequestResolver = h3::server::RequestResolver<h3_quinn::Connection, Bytes>;
+type RecvStream = h3::server::RequestStream<h3_quinn::RecvStream, Bytes>;
+
+/// Serves the deployment over HTTP/3 on the given UDP address, with the
+/// certificates of the given TLS config.
+pub(crate) fn serve<F: RuntimeFactors>(
+    deployment: Arc<Deployment<F>>,
+    addr: SocketAddr,
+    tls: &rustls::ServerConfig,
+) -> anyhow::Result<()> {
+    let mut tls = tls.clone();
+    tls.alpn_protocols = vec![b"h3".to_vec()];
+    let crypto = quinn::crypto::rustls::QuicServerConfig::try_from(tls)
+        .context("the TLS configuration does not support QUIC")?;
+    let endpoint =
+        quinn::Endpoint::server(quinn::ServerConfig::with_crypto(Arc::new(crypto)), addr)
+            .with_context(|| format!("Unable to listen for HTTP/3 on {addr}"))?;
+    tracing::info!("Serving HTTP/3 on {addr}");
+    tokio::spawn(async move {
+        while let Some(incoming) = endpoint.accept().await {
+            let deployment = deployment.clone();
+            tokio::spawn(async move {
+                if let Err(err) = serve_connection(deployment, incoming).await {
+                    tracing::warn!("Error serving HTTP/3 connection: {err:?}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+async fn serve_connection<F: RuntimeFactors>(
+    deployment: Arc<Deployment<F>>,
+    incoming: quinn::Incoming,
+) -> anyhow::Result<()> {
+    let connection = incoming.await?;
+    let client_addr = connection.remote_address();
+    let mut connection = h3::server::builder()
+        .build(h3_quinn::Connection::new(connection))
+        .await?;
+    loop {
+        match connection.accept().await {
+            Ok(Some(resolver)) => {
+                let deployment = deployment.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = serve_request(deployment, resolver, client_addr).await {
+                        tracing::warn!("Error serving HTTP/3 request: {err:?}");
+                    }
+                });
+            }
+            Ok(None) => return Ok(()),
+            Err(err) if err.is_h3_no_error() => return Ok(()),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+async fn serve_request<F: RuntimeFactors>(
+    deployment: Arc<Deployment<F>>,
+    resolver: RequestResolver,
+    client_addr: SocketAddr,
+) -> anyhow::Result<()> {
+    let (req, stream) = resolver.resolve_request().await?;
+    let (mut send, recv) = stream.split();
+    let req = Request::from_parts(req.into_parts().0, request_body(recv));
+
+    // Each request is handled by the version current when it arrives
+    let res = deployment
+        .current()
+        .instrumented_handle(http::uri::Scheme::HTTPS, client_addr, req)
+        .await?;
+
+    let (parts, mut body) = res.into_parts();
+    send.send_response(Response::from_parts(parts, ())).await?;
+    while let Some(frame) = body.frame().await {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(err) => {
+                // The client sees the stream reset rather than finished
+                tracing::warn!("Error streaming HTTP/3 response: {err}");
+                return Ok(());
+            }
+        };
+        match frame.into_data() {
+            Ok(data) => send.send_data(data).await?,
+            Err(frame) => {
+                if let Ok(trailers) = frame.into_trailers() {
+                    send.send_trailers(trailers).await?;
+                }
+            }
+        }
+    }
+    send.finish().await?;
+    Ok(())
+}
+
+/// The body of a request, read from its stream.
+fn request_body(mut recv: RecvStream) -> crate::Body {
+    // The stream is read by its own task since a body must be `Sync`
+    let (mut tx, rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        loop {
+            let frame = match recv.recv_data().await {
+                Ok(Some(mut data)) => Ok(Frame::data(data.copy_to_bytes(data.remaining()))),
+                Ok(None) => break,
+                Err(err) => Err(ErrorCode::InternalError(Some(err.to_string()))),
+            };
+            let is_err = frame.is_err();
+            if tx.send(frame).await.is_err() || is_err {
+                break;
+            }
+        }
+    });
+    StreamBody::new(rx).boxed()
+}
@@ -1,15 +1,29 @@
 //! Implementation for the Spin HTTP engine.
 
+mod acme;
+mod auth;
+mod cache;
+mod client_ip;
+mod cors;
+mod deployment;
 mod headers;
+#[cfg(feature = "experimental-h3")]
+mod http3;
 mod instrument;
 mod outbound_http;
+mod policy;
+mod protocols;
+mod rate_limit;
 mod server;
 mod spin;
+mod static_files;
+mod streaming;
 mod tls;
 mod wagi;
 mod wasi;
 
 use std::{
+    convert::Infallible,
     error::Error,
     net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
     path::PathBuf,
@@ -18,14 +32,24 @@ use std::{
 
 use anyhow::{bail, Context};
 use clap::Args;
-use serde::Deserialize;
 use spin_app::App;
 use spin_factors::RuntimeFactors;
-use spin_trigger::Trigger;
+use spin_http::config::HttpAppTriggerConfig;
+use spin_trigger::{
+    cli::{
+        SPIN_ACME_CACHE_DIR, SPIN_ACME_CHALLENGE, SPIN_ACME_CONTACT, SPIN_ACME_DIRECTORY,
+        SPIN_ACME_DOMAINS, SPIN_ACME_HTTP_LISTEN, SPIN_TLS_CERT, SPIN_TLS_KEY,
+    },
+    redeploy::Redeploys,
+    Trigger,
+};
 use wasmtime_wasi_http::bindings::http::types::ErrorCode;
 
+pub use deployment::Deployment;
+pub use protocols::{HttpProtocol, HttpProtocols};
 pub use server::HttpServer;
 
+pub use acme::{AcmeChallenge, AcmeConfig};
 pub use tls::TlsConfig;
 
 pub(crate) use wasmtime_wasi_http::body::HyperIncomingBody as Body;
@@ -44,21 +68,87 @@ pub struct CliArgs {
     pub address: SocketAddr,
 
     /// The path to the certificate to use for https, if this is not set, normal http will be used. The cert should be in PEM format
-    #[clap(long, env = "SPIN_TLS_CERT", requires = "tls-key")]
+    #[clap(long, env = SPIN_TLS_CERT, requires = "tls-key")]
     pub tls_cert: Option<PathBuf>,
 
     /// The path to the certificate key to use for https, if this is not set, normal http will be used. The key should be in PKCS#8 format
-    #[clap(long, env = "SPIN_TLS_KEY", requires = "tls-cert")]
+    #[clap(long, env = SPIN_TLS_KEY, requires = "tls-cert")]
     pub tls_key: Option<PathBuf>,
+
+    /// Serve https with a certificate for these domains, obtained and renewed
+    /// automatically from an ACME certificate authority. Ignored if
+    /// --tls-cert is set
+    #[clap(
+        long = "acme-domain",
+        env = SPIN_ACME_DOMAINS,
+        multiple_occurrences = true,
+        use_value_delimiter = true
+    )]
+    pub acme_domains: Vec<String>,
+
+    /// An email address the ACME certificate authority may contact about
+    /// the certificates, such as before they expire
+    #[clap(long, env = SPIN_ACME_CONTACT)]
+    pub acme_contact: Option<String>,
+
+    /// The URL of the ACME certificate authority's directory
+    #[clap(long, env = SPIN_ACME_DIRECTORY, default_value = acme::LETS_ENCRYPT_DIRECTORY)]
+    pub acme_directory: String,
+
+    /// The directory in which to keep the ACME account and certificates
+    /// between runs. Without it, a new certificate is obtained each time the
+    /// trigger starts, which certificate authorities limit the rate of
+    #[clap(long, env = SPIN_ACME_CACHE_DIR)]
+    pub acme_cache_dir: Option<PathBuf>,
+
+    /// How the ACME certificate authority validates control of the domains:
+    /// "tls-alpn-01", which requires listening on port 443, or "http-01",
+    /// which answers on --acme-http-listen
+    #[clap(long, env = SPIN_ACME_CHALLENGE, default_value = "tls-alpn-01")]
+    pub acme_challenge: AcmeChallenge,
+
+    /// The address on which to answer ACME HTTP-01 challenges
+    #[clap(long, env = SPIN_ACME_HTTP_LISTEN, default_value = "0.0.0.0:80")]
+    pub acme_http_listen: SocketAddr,
+
+    /// The versions of HTTP to serve: "http1", "h2", and, in builds with the
+    /// experimental-h3 feature, "h3" over QUIC on the same port, which
+    /// requires TLS
+    #[clap(
+        long = "http-protocol",
+        env = "SPIN_HTTP_PROTOCOLS",
+        multiple_occurrences = true,
+        use_value_delimiter = true,
+        default_value = "http1,h2"
+    )]
+    pub http_protocols: Vec<HttpProtocol>,
+
+    /// Serve the resource usage of each component, as JSON, at
+    /// /.well-known/spin/usage
+    #[clap(long, env = "SPIN_HTTP_USAGE_ENDPOINT", takes_value = false)]
+    pub usage_endpoint: bool,
 }
 
 impl CliArgs {
     fn into_tls_config(self) -> Option<TlsConfig> {
         match (self.tls_cert, self.tls_key) {
-            (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+            (Some(cert_path), Some(key_path)) => Some(TlsConfig::Files {
                 cert_path,
                 key_path,
             }),
+            (None, None) if !self.acme_domains.is_empty() => {
+                if self.acme_cache_dir.is_none() {
+                    terminal::warn!("No ACME cache directory is set, so certificates will not be kept between runs. Set --acme-cache-dir to keep them.");
+                }
+                Some(TlsConfig::Acme(AcmeConfig {
+                    domains: self.acme_domains,
+                    contact: self.acme_contact,
+                    directory_url: self.acme_directory,
+                    cache_dir: self.acme_cache_dir,
+                    challenge: self.acme_challenge,
+                    http_challenge_addr: self.acme_http_listen,
+                }))
+            }
             (None, None) => None,
             _ => unreachable!(),
         }
@@ -73,6 +163,8 @@ pub struct HttpTrigger {
     /// If the port is set to 0, the actual address will be determined by the OS.
     listen_addr: SocketAddr,
     tls_config: Option<TlsConfig>,
+    protocols: HttpProtocols,
+    usage_endpoint: bool,
 }
 
 impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
@@ -82,17 +174,56 @@ impl<F: RuntimeFactors> Trigger<F> for HttpTrigger {
     type InstanceState = ();
 
     fn new(cli_args: Self::CliArgs, app: &spin_app::App) -> anyhow::Result<Self> {
-        Self::new(app, cli_args.address, cli_args.into_tls_config())
+        let usage_endpoint = cli_args.usage_endpoint;
+        let protocols = HttpProtocols::new(&cli_args.http_protocols)?;
+        let mut trigger = Self::new(app, cli_args.address, cli_args.into_tls_config())?;
+        if protocols.http3() && trigger.tls_config.is_none() {
+            bail!("h3 requires TLS: set --tls-cert and --tls-key, or --acme-domain");
+        }
+        trigger.protocols = protocols;
+        trigger.usage_endpoint = usage_endpoint;
+        Ok(trigger)
     }
 
     async fn run(self, trigger_app: TriggerApp<F>) -> anyhow::Result<()> {
-        let server = self.into_server(trigger_app)?;
+        let server = self.into_server(trigger_app).await?;
 
         server.serve().await?;
 
         Ok(())
     }
 
+    async fn run_redeployable(
+        self,
+        trigger_app: TriggerApp<F>,
+        mut redeploys: Redeploys<Self, F>,
+    ) -> anyhow::Result<()> {
+        let deployment = Arc::new(Deployment::new(self.server(trigger_app).await?));
+
+        let switch = async {
+            let (trigger, deployment) = (&self, &deployment);
+            while let Some(redeploy) = redeploys.next().await {
+                redeploy
+                    .switch(|trigger_app| async move {
+                        Self::validate_app(trigger_app.app())?;
+                        let drained = deployment.switch(trigger.server(trigger_app).await?)?;
+                        tracing::info!("Switched to the new version of the app");
+                        tokio::spawn(async move {
+                            drained.await;
+                            tracing::info!("The previous version of the app has drained");
+                        });
+                        Ok(())
+                    })
+                    .await;
+            }
+            std::future::pending::<Infallible>().await
+        };
+        tokio::select! {
+            result = deployment.clone().serve() => result,
+            never = switch => match never {},
+        }
+    }
+
     fn supported_host_requirements() -> Vec<&'static str> {
         vec![spin_app::locked::SERVICE_CHAINING_KEY]
     }
@@ -110,29 +241,35 @@ impl HttpTrigger {
         Ok(Self {
             listen_addr,
             tls_config,
+            protocols: HttpProtocols::default(),
+            usage_endpoint: false,
         })
     }
 
     /// Turn this [`HttpTrigger`] into an [`HttpServer`].
-    pub fn into_server<F: RuntimeFactors>(
+    pub async fn into_server<F: RuntimeFactors>(
         self,
         trigger_app: TriggerApp<F>,
     ) -> anyhow::Result<Arc<HttpServer<F>>> {
-        let Self {
-            listen_addr,
-            tls_config,
-        } = self;
-        let server = Arc::new(HttpServer::new(listen_addr, tls_config, trigger_app)?);
-        Ok(server)
+        self.server(trigger_app).await
     }
 
-    fn validate_app(app: &App) -> anyhow::Result<()> {
-        #[derive(Deserialize)]
-        #[serde(deny_unknown_fields)]
-        struct TriggerMetadata {
-            base: Option<String>,
+    async fn server<F: RuntimeFactors>(
+        &self,
+        trigger_app: TriggerApp<F>,
+    ) -> anyhow::Result<Arc<HttpServer<F>>> {
+        let mut server =
+            HttpServer::new(self.listen_addr, self.tls_config.clone(), trigger_app).await?;
+        server.set_protocols(self.protocols);
+        if self.usage_endpoint {
+            server.enable_usage_endpoint();
         }
-        if let Some(TriggerMetadata { base: Some(base) }) = app.get_trigger_metadata("http")? {
+        Ok(Arc::new(server))
+    }
+
+    fn validate_app(app: &App) -> anyhow::Result<()> {
+        let config = app.get_trigger_metadata::<HttpAppTriggerConfig>("http")?;
+        if let Some(base) = config.and_then(|config| config.base) {
             if base == "/" {
                 tracing::warn!("This application has the deprecated trigger 'base' set to the default value '/'. This may be an error in the future!");
             } else {
@@ -9,7 +9,7 @@ use spin_factor_outbound_http::intercept::{self, InterceptOutcome, InterceptRequ
 use spin_factor_outbound_networking::config::allowed_hosts::parse_service_chaining_target;
 use spin_factors::RuntimeFactors;
 use spin_http::routes::RouteMatch;
-use wasmtime_wasi_http::{HttpError, HttpResult};
+use wasmtime_wasi_http::{bindings::http::types::ErrorCode, HttpError, HttpResult};
 
 use crate::HttpServer;
 
@@ -31,6 +31,12 @@ impl<F: RuntimeFactors> intercept::OutboundHttpInterceptor for OutboundHttpInter
     async fn intercept(&self, request: InterceptRequest) -> HttpResult<InterceptOutcome> {
         // Handle service chaining requests
         if let Some(component_id) = parse_service_chaining_target(request.uri()) {
+            if !self.server.has_http_component(&component_id) {
+                tracing::warn!(
+                    "Service chaining request to {component_id:?}, which has no HTTP trigger"
+                );
+                return Err(ErrorCode::DestinationNotFound.into());
+            }
             let req = request.into_hyper_request();
             let path = req.uri().path().to_owned();
             let route_match = RouteMatch::synthetic(component_id, path);
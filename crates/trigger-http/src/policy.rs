@@ -0,0 +1,455 @@
+//! Per-route limits on the requests a component is invoked for, and on the
+//! responses it sends.
+
+use std::{
+    net::IpAddr,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use http::{
+    header::{ALLOW, CONTENT_LENGTH, RETRY_AFTER},
+    HeaderMap, Method, Request, Response, StatusCode,
+};
+use http_body_util::BodyExt;
+use hyper::body::{Body as _, Bytes, Frame, SizeHint};
+use ip_network::IpNetwork;
+use spin_http::{body, config::HttpTriggerConfig};
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::{rate_limit::RateLimit, Body};
+
+/// The limits a route places on requests and responses.
+#[derive(Debug)]
+pub(crate) struct RoutePolicy {
+    max_request_body_size: Option<u64>,
+    max_response_body_size: Option<u64>,
+    max_header_size: Option<u64>,
+    allowed_methods: Option<Vec<Method>>,
+    allowed_ips: Option<Vec<IpNetwork>>,
+    denied_ips: Vec<IpNetwork>,
+    rate_limit: Option<RateLimit>,
+}
+
+/// Why a request was refused before the component was invoked.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Refusal {
+    MethodNotAllowed,
+    HeadersTooLarge,
+    BodyTooLarge,
+    ClientNotAllowed,
+    /// The client has made too many requests, and may retry after the given
+    /// time.
+    RateLimited(Duration),
+}
+
+impl std::fmt::Display for Refusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::MethodNotAllowed => "method not allowed",
+            Self::HeadersTooLarge => "headers exceed max_header_size",
+            Self::BodyTooLarge => "body exceeds max_request_body_size",
+            Self::ClientNotAllowed => "client address not allowed",
+            Self::RateLimited(_) => "rate limit exceeded",
+        })
+    }
+}
+
+impl RoutePolicy {
+    /// Returns the policy of the route with the given trigger config, or
+    /// `None` if it sets no limits.
+    pub fn from_config(config: &HttpTriggerConfig) -> anyhow::Result<Option<Self>> {
+        let allowed_methods = config
+            .allowed_methods
+            .as_ref()
+            .map(|methods| {
+                methods
+                    .iter()
+                    .map(|method| {
+                        Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                            .with_context(|| format!("invalid allowed method {method:?}"))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .transpose()?;
+        let allowed_ips = config
+            .allowed_ips
+            .as_deref()
+            .map(parse_networks)
+            .transpose()
+            .context("invalid allowed_ips")?;
+        let denied_ips = parse_networks(config.denied_ips.as_deref().unwrap_or_default())
+            .context("invalid denied_ips")?;
+        let rate_limit = config
+            .rate_limit
+            .as_ref()
+            .map(RateLimit::from_config)
+            .transpose()
+            .context("invalid rate_limit")?;
+        let policy = Self {
+            max_request_body_size: config.max_request_body_size,
+            max_response_body_size: config.max_response_body_size,
+            max_header_size: config.max_header_size,
+            allowed_methods,
+            allowed_ips,
+            denied_ips,
+            rate_limit,
+        };
+        let has_limits = policy.max_request_body_size.is_some()
+            || policy.max_response_body_size.is_some()
+            || policy.max_header_size.is_some()
+            || policy.allowed_methods.is_some()
+            || policy.allowed_ips.is_some()
+            || !policy.denied_ips.is_empty()
+            || policy.rate_limit.is_some();
+        Ok(has_limits.then_some(policy))
+    }
+
+    /// Checks the client's address against the route's allowed and denied
+    /// addresses.
+    pub fn check_client(&self, client_ip: IpAddr) -> Result<(), Refusal> {
+        // A client of a dual-stack listener may have an IPv4-mapped address
+        let client_ip = client_ip.to_canonical();
+        let allowed = self
+            .allowed_ips
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(|network| network.contains(client_ip)));
+        let denied = self
+            .denied_ips
+            .iter()
+            .any(|network| network.contains(client_ip));
+        if allowed && !denied {
+            Ok(())
+        } else {
+            Err(Refusal::ClientNotAllowed)
+        }
+    }
+
+    /// The route's limit on the rate of each client's requests.
+    pub fn rate_limit(&self) -> Option<&RateLimit> {
+        self.rate_limit.as_ref()
+    }
+
+    /// Checks a request against the policy before the component is invoked.
+    ///
+    /// A request body without a declared length can't be checked up front;
+    /// it is limited as the component reads it by [`RoutePolicy::limit_request`].
+    pub fn check_request(&self, req: &Request<Body>) -> Result<(), Refusal> {
+        if let Some(allowed) = &self.allowed_methods {
+            if !allowed.contains(req.method()) {
+                return Err(Refusal::MethodNotAllowed);
+            }
+        }
+        if let Some(max) = self.max_header_size {
+            if headers_size(req.headers()) > max {
+                return Err(Refusal::HeadersTooLarge);
+            }
+        }
+        if let Some(max) = self.max_request_body_size {
+            let declared = req
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+            if declared.is_some_and(|len| len > max) {
+                return Err(Refusal::BodyTooLarge);
+            }
+        }
+        Ok(())
+    }
+
+    /// Limits the request body the component may read.
+    pub fn limit_request(&self, req: Request<Body>) -> Request<Body> {
+        match self.max_request_body_size {
+            Some(max) => req.map(|inner| {
+                LimitedBody::new(inner, max, ErrorCode::HttpRequestBodySize(Some(max))).boxed()
+            }),
+            None => req,
+        }
+    }
+
+    /// Limits the response body the component may send. Returns `None` if the
+    /// response declares a length over the limit.
+    pub fn limit_response(&self, res: Response<Body>) -> Option<Response<Body>> {
+        let Some(max) = self.max_response_body_size else {
+            return Some(res);
+        };
+        let declared = res
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+            .or_else(|| res.body().size_hint().exact());
+        if declared.is_some_and(|len| len > max) {
+            return None;
+        }
+        Some(res.map(|inner| {
+            LimitedBody::new(inner, max, ErrorCode::HttpResponseBodySize(Some(max))).boxed()
+        }))
+    }
+
+    /// Creates the response to a refused request.
+    pub fn refusal_response(&self, refusal: Refusal) -> anyhow::Result<Response<Body>> {
+        let mut builder = Response::builder();
+        builder = match refusal {
+            Refusal::MethodNotAllowed => {
+                let allowed = self
+                    .allowed_methods
+                    .iter()
+                    .flatten()
+                    .map(Method::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                builder
+                    .status(StatusCode::METHOD_NOT_ALLOWED)
+                    .header(ALLOW, allowed)
+            }
+            Refusal::HeadersTooLarge => builder.status(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE),
+            Refusal::BodyTooLarge => builder.status(StatusCode::PAYLOAD_TOO_LARGE),
+            Refusal::ClientNotAllowed => builder.status(StatusCode::FORBIDDEN),
+            Refusal::RateLimited(retry_after) => builder
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header(RETRY_AFTER, retry_after.as_secs_f64().ceil() as u64),
+        };
+        Ok(builder.body(body::empty())?)
+    }
+}
+
+/// Returns whether an error arose from a request body over its route's
+/// limit, so that the request can be refused rather than fail.
+pub(crate) fn is_request_body_too_large(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<ErrorCode>(),
+            Some(ErrorCode::HttpRequestBodySize(_))
+        )
+    })
+}
+
+/// Parses IP addresses, and CIDR ranges of them.
+pub(crate) fn parse_networks(networks: &[String]) -> anyhow::Result<Vec<IpNetwork>> {
+    networks
+        .iter()
+        .map(|network| match network.parse::<IpAddr>() {
+            Ok(ip) => Ok(IpNetwork::from(ip)),
+            Err(_) => IpNetwork::from_str_truncate(network).map_err(|err| {
+                anyhow::anyhow!("{network:?} is not an IP address or CIDR range: {err}")
+            }),
+        })
+        .collect()
+}
+
+/// The size of headers as sent over HTTP/1.1, each as `name: value\r\n`.
+fn headers_size(headers: &HeaderMap) -> u64 {
+    headers
+        .iter()
+        .map(|(name, value)| (name.as_str().len() + value.len() + 4) as u64)
+        .sum()
+}
+
+/// A body which fails once it has produced more than a given number of
+/// bytes.
+struct LimitedBody {
+    inner: Body,
+    remaining: u64,
+    error: Option<ErrorCode>,
+}
+
+impl LimitedBody {
+    fn new(inner: Body, max: u64, error: ErrorCode) -> Self {
+        Self {
+            inner,
+            remaining: max,
+            error: Some(error),
+        }
+    }
+}
+
+impl hyper::body::Body for LimitedBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let Some(error) = &this.error else {
+            // The limit was already exceeded
+            return Poll::Ready(None);
+        };
+        match Pin::new(&mut this.inner).poll_frame(cx) {
+            Poll::Ready(Some(Ok(frame))) => {
+                let len = frame.data_ref().map_or(0, |data| data.len() as u64);
+                match this.remaining.checked_sub(len) {
+                    Some(remaining) => {
+                        this.remaining = remaining;
+                        Poll::Ready(Some(Ok(frame)))
+                    }
+                    None => {
+                        tracing::warn!("Body exceeded its route's size limit: {error}");
+                        Poll::Ready(this.error.take().map(Err))
+                    }
+                }
+            }
+            other => other,
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.error.is_none() || self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{stream, StreamExt};
+    use http_body_util::StreamBody;
+
+    use super::*;
+
+    fn policy(config: HttpTriggerConfig) -> RoutePolicy {
+        RoutePolicy::from_config(&config).unwrap().unwrap()
+    }
+
+    fn request(method: Method, headers: &[(&str, &str)]) -> Request<Body> {
+        let mut builder = Request::builder().method(method).uri("/test");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(body::empty()).unwrap()
+    }
+
+    fn streamed(chunks: Vec<&'static [u8]>) -> Body {
+        let frames = stream::iter(chunks).map(|chunk| Ok(Frame::data(Bytes::from_static(chunk))));
+        BodyExt::boxed(StreamBody::new(frames))
+    }
+
+    #[test]
+    fn clients_are_checked_against_allowed_and_denied_ips() {
+        let policy = policy(HttpTriggerConfig {
+            allowed_ips: Some(vec!["10.0.0.0/8".into(), "192.168.1.20".into()]),
+            denied_ips: Some(vec!["10.1.0.0/16".into()]),
+            ..Default::default()
+        });
+        let check = |ip: &str| policy.check_client(ip.parse().unwrap());
+        assert_eq!(check("10.2.3.4"), Ok(()));
+        assert_eq!(check("192.168.1.20"), Ok(()));
+        assert_eq!(check("::ffff:10.2.3.4"), Ok(()));
+        assert_eq!(check("10.1.2.3"), Err(Refusal::ClientNotAllowed));
+        assert_eq!(check("192.168.1.21"), Err(Refusal::ClientNotAllowed));
+
+        let response = policy.refusal_response(Refusal::ClientNotAllowed).unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = policy
+            .refusal_response(Refusal::RateLimited(Duration::from_millis(1500)))
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "2");
+
+        assert!(RoutePolicy::from_config(&HttpTriggerConfig {
+            denied_ips: Some(vec!["10.0.0.0/33".into()]),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn config_without_limits_has_no_policy() {
+        let config = HttpTriggerConfig::default();
+        assert!(RoutePolicy::from_config(&config).unwrap().is_none());
+    }
+
+    #[test]
+    fn disallowed_method_is_refused() {
+        let policy = policy(HttpTriggerConfig {
+            allowed_methods: Some(vec!["get".into(), "POST".into()]),
+            ..Default::default()
+        });
+        assert_eq!(policy.check_request(&request(Method::GET, &[])), Ok(()));
+        assert_eq!(
+            policy.check_request(&request(Method::DELETE, &[])),
+            Err(Refusal::MethodNotAllowed)
+        );
+        let response = policy.refusal_response(Refusal::MethodNotAllowed).unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[ALLOW], "GET, POST");
+    }
+
+    #[test]
+    fn large_headers_are_refused() {
+        let policy = policy(HttpTriggerConfig {
+            max_header_size: Some(32),
+            ..Default::default()
+        });
+        assert_eq!(
+            policy.check_request(&request(Method::GET, &[("a", "b")])),
+            Ok(())
+        );
+        let long = "x".repeat(32);
+        assert_eq!(
+            policy.check_request(&request(Method::GET, &[("a", &long)])),
+            Err(Refusal::HeadersTooLarge)
+        );
+    }
+
+    #[test]
+    fn declared_large_body_is_refused() {
+        let policy = policy(HttpTriggerConfig {
+            max_request_body_size: Some(1024),
+            ..Default::default()
+        });
+        assert_eq!(
+            policy.check_request(&request(Method::POST, &[("content-length", "1024")])),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check_request(&request(Method::POST, &[("content-length", "1025")])),
+            Err(Refusal::BodyTooLarge)
+        );
+    }
+
+    #[tokio::test]
+    async fn streamed_request_body_is_limited() {
+        let policy = policy(HttpTriggerConfig {
+            max_request_body_size: Some(4),
+            ..Default::default()
+        });
+        let req = Request::new(streamed(vec![b"abc", b"def"]));
+        let err = policy
+            .limit_request(req)
+            .into_body()
+            .collect()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ErrorCode::HttpRequestBodySize(Some(4))));
+        assert!(is_request_body_too_large(&anyhow::Error::new(err)));
+    }
+
+    #[tokio::test]
+    async fn response_body_is_limited() {
+        let policy = policy(HttpTriggerConfig {
+            max_response_body_size: Some(4),
+            ..Default::default()
+        });
+        let sized = Response::new(body::full(Bytes::from_static(b"too long")));
+        assert!(policy.limit_response(sized).is_none());
+
+        let body = policy
+            .limit_response(Response::new(streamed(vec![b"ab", b"cd"])))
+            .unwrap()
+            .into_body();
+        assert_eq!(body.collect().await.unwrap().to_bytes(), "abcd");
+
+        let body = policy
+            .limit_response(Response::new(streamed(vec![b"abc", b"de"])))
+            .unwrap()
+            .into_body();
+        let err = body.collect().await.unwrap_err();
+        assert!(matches!(err, ErrorCode::HttpResponseBodySize(Some(4))));
+    }
+}
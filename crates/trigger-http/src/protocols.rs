@@ -0,0 +1,127 @@
+use std::str::FromStr;
+
+use hyper_util::{rt::TokioExecutor, server::conn::auto};
+
+/// A version of HTTP the trigger's listener can serve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HttpProtocol {
+    /// HTTP/1.1
+    Http1,
+    /// HTTP/2: negotiated by ALPN over TLS, or with prior knowledge (h2c)
+    /// over plain TCP.
+    Http2,
+    /// HTTP/3 over QUIC, which requires TLS and is advertised to clients of
+    /// the TCP listener with `Alt-Svc`. Experimental.
+    Http3,
+}
+
+impl FromStr for HttpProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "http1" | "http/1.1" => Ok(Self::Http1),
+            "h2" | "http2" => Ok(Self::Http2),
+            "h3" | "http3" => Ok(Self::Http3),
+            _ => Err(format!(
+                "unknown HTTP protocol {s:?}; expected \"http1\", \"h2\" or \"h3\""
+            )),
+        }
+    }
+}
+
+/// The versions of HTTP a listener serves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HttpProtocols {
+    http1: bool,
+    http2: bool,
+    http3: bool,
+}
+
+impl Default for HttpProtocols {
+    fn default() -> Self {
+        Self {
+            http1: true,
+            http2: true,
+            http3: false,
+        }
+    }
+}
+
+impl HttpProtocols {
+    /// Creates the set of the given protocols.
+    pub fn new(protocols: &[HttpProtocol]) -> anyhow::Result<Self> {
+        let protocols = Self {
+            http1: protocols.contains(&HttpProtocol::Http1),
+            http2: protocols.contains(&HttpProtocol::Http2),
+            http3: protocols.contains(&HttpProtocol::Http3),
+        };
+        anyhow::ensure!(
+            protocols.http1 || protocols.http2,
+            "the listener must serve http1 or h2; h3 is only discovered through them"
+        );
+        if protocols.http3 && !cfg!(feature = "experimental-h3") {
+            anyhow::bail!(
+                "h3 is experimental, and requires Spin built with the `experimental-h3` feature"
+            );
+        }
+        Ok(protocols)
+    }
+
+    /// Whether HTTP/3 is served.
+    pub fn http3(&self) -> bool {
+        self.http3
+    }
+
+    /// The ALPN protocols a TLS listener offers, most preferred first.
+    pub(crate) fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        let mut alpn = vec![];
+        if self.http2 {
+            alpn.push(b"h2".to_vec());
+        }
+        if self.http1 {
+            alpn.push(b"http/1.1".to_vec());
+        }
+        alpn
+    }
+
+    /// Returns a builder of connections over TCP, which detects whether
+    /// each speaks HTTP/1.1 or HTTP/2.
+    pub(crate) fn connection_builder(&self) -> auto::Builder<TokioExecutor> {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder.http1().keep_alive(true);
+        match (self.http1, self.http2) {
+            (true, false) => builder.http1_only(),
+            (false, true) => builder.http2_only(),
+            _ => builder,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protocols(names: &[&str]) -> anyhow::Result<HttpProtocols> {
+        let protocols: Vec<HttpProtocol> = names.iter().map(|name| name.parse().unwrap()).collect();
+        HttpProtocols::new(&protocols)
+    }
+
+    #[test]
+    fn alpn_prefers_h2() {
+        assert_eq!(
+            protocols(&["http1", "h2"]).unwrap().alpn_protocols(),
+            [b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+        assert_eq!(
+            protocols(&["HTTP/1.1"]).unwrap().alpn_protocols(),
+            [b"http/1.1".to_vec()]
+        );
+    }
+
+    #[test]
+    fn h3_alone_is_rejected() {
+        assert!(protocols(&["h3"]).is_err());
+        assert!("spdy".parse::<HttpProtocol>().is_err());
+    }
+}
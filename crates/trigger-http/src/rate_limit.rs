@@ -0,0 +1,303 @@
+//! Limits on the rate of each client's requests to a route.
+//!
+//! Each client of a route has a token bucket, which refills at the route's
+//! rate up to its burst size; a request takes a token, and is refused if
+//! there are none. Buckets are kept in memory, or in a key-value store so
+//! that they are shared by every process serving the app.
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use http::{HeaderName, Request};
+use serde::{Deserialize, Serialize};
+use spin_factor_key_value::SwapError;
+use spin_http::config::RateLimitConfig;
+
+use crate::Body;
+
+/// The prefix of the keys of token buckets in a key-value store.
+const KEY_PREFIX: &str = "spin-http-rate-limit/";
+
+/// How many times a bucket in a key-value store is retried when another
+/// process updates it concurrently.
+const MAX_SWAP_ATTEMPTS: usize = 8;
+
+/// The number of in-memory buckets above which full ones are discarded.
+const MEMORY_PRUNE_THRESHOLD: usize = 10_000;
+
+/// A route's limit on the rate of each client's requests.
+#[derive(Debug)]
+pub(crate) struct RateLimit {
+    /// The most tokens a bucket holds.
+    capacity: f64,
+    /// The tokens added to a bucket each second.
+    per_second: f64,
+    key: ClientKey,
+}
+
+/// What identifies a client.
+#[derive(Debug, PartialEq)]
+enum ClientKey {
+    Ip,
+    Header(HeaderName),
+}
+
+impl RateLimit {
+    pub fn from_config(config: &RateLimitConfig) -> anyhow::Result<Self> {
+        let per = config.per.duration();
+        if per.is_zero() {
+            bail!("per must be greater than zero");
+        }
+        let key = match config.key.as_deref() {
+            None | Some("ip") => ClientKey::Ip,
+            Some(key) => match key.strip_prefix("header:") {
+                Some(name) => ClientKey::Header(
+                    HeaderName::from_bytes(name.as_bytes())
+                        .with_context(|| format!("invalid header name {name:?}"))?,
+                ),
+                None => bail!("key must be \"ip\" or \"header:<name>\", not {key:?}"),
+            },
+        };
+        Ok(Self {
+            capacity: config.burst.unwrap_or(config.requests).get().into(),
+            per_second: f64::from(config.requests.get()) / per.as_secs_f64(),
+            key,
+        })
+    }
+
+    /// The key identifying the client making the request.
+    fn client_key(&self, req: &Request<Body>, client_ip: IpAddr) -> String {
+        if let ClientKey::Header(name) = &self.key {
+            if let Some(value) = req.headers().get(name) {
+                return format!("header:{}", String::from_utf8_lossy(value.as_bytes()));
+            }
+        }
+        format!("ip:{}", client_ip.to_canonical())
+    }
+}
+
+/// The token buckets of the clients of an app's routes.
+pub(crate) enum RateLimiter {
+    Memory(Mutex<HashMap<String, Bucket>>),
+    KeyValue(Arc<dyn spin_factor_key_value::Store>),
+}
+
+impl RateLimiter {
+    /// Creates a limiter which keeps buckets in the given key-value store, or
+    /// in memory if there is none.
+    pub fn new(store: Option<Arc<dyn spin_factor_key_value::Store>>) -> Self {
+        match store {
+            Some(store) => Self::KeyValue(store),
+            None => Self::Memory(Default::default()),
+        }
+    }
+
+    /// Takes a token from the bucket of the client making the request to the
+    /// route, or returns how long the client must wait for one.
+    pub async fn check(
+        &self,
+        route: &str,
+        limit: &RateLimit,
+        req: &Request<Body>,
+        client_ip: IpAddr,
+    ) -> Result<(), Duration> {
+        let key = format!(
+            "{KEY_PREFIX}{}",
+            spin_common::sha256::hex_digest_from_bytes(format!(
+                "{route}\n{}",
+                limit.client_key(req, client_ip)
+            ))
+        );
+        let now = unix_millis(SystemTime::now());
+        match self {
+            Self::Memory(buckets) => {
+                let mut buckets = buckets.lock().unwrap();
+                if buckets.len() >= MEMORY_PRUNE_THRESHOLD && !buckets.contains_key(&key) {
+                    buckets.retain(|_, bucket| !bucket.clone().refill(limit, now).is_full(limit));
+                }
+                let bucket = buckets
+                    .entry(key)
+                    .or_insert_with(|| Bucket::full(limit, now));
+                *bucket = bucket.clone().refill(limit, now);
+                bucket.take(limit)
+            }
+            Self::KeyValue(store) => match take_stored(store.as_ref(), &key, limit, now).await {
+                Ok(taken) => taken,
+                Err(err) => {
+                    // An unavailable store shouldn't take the app down with it
+                    tracing::warn!("Failed to check rate limit: {err:?}");
+                    Ok(())
+                }
+            },
+        }
+    }
+}
+
+/// Takes a token from a bucket in a key-value store.
+async fn take_stored(
+    store: &dyn spin_factor_key_value::Store,
+    key: &str,
+    limit: &RateLimit,
+    now: u64,
+) -> anyhow::Result<Result<(), Duration>> {
+    for _ in 0..MAX_SWAP_ATTEMPTS {
+        let cas = store
+            .new_compare_and_swap(0, key)
+            .await
+            .map_err(|err| anyhow::anyhow!("{err:?}"))?;
+        let current = cas
+            .current()
+            .await
+            .map_err(|err| anyhow::anyhow!("{err:?}"))?;
+        let mut bucket = match current {
+            Some(current) => serde_json::from_slice::<Bucket>(&current)
+                .unwrap_or_else(|_| Bucket::full(limit, now))
+                .refill(limit, now),
+            None => Bucket::full(limit, now),
+        };
+        let taken = bucket.take(limit);
+        if taken.is_err() {
+            // An empty bucket needn't be written back to refuse the request
+            return Ok(taken);
+        }
+        match cas.swap(serde_json::to_vec(&bucket)?).await {
+            Ok(()) => return Ok(taken),
+            Err(SwapError::CasFailed(_)) => continue,
+            Err(SwapError::Other(err)) => bail!(err),
+        }
+    }
+    bail!("bucket {key:?} was updated concurrently too many times")
+}
+
+/// A client's token bucket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Bucket {
+    tokens: f64,
+    /// When the tokens were counted, in milliseconds since the Unix epoch.
+    updated: u64,
+}
+
+impl Bucket {
+    fn full(limit: &RateLimit, now: u64) -> Self {
+        Self {
+            tokens: limit.capacity,
+            updated: now,
+        }
+    }
+
+    /// Adds the tokens the bucket has gained since it was last updated.
+    fn refill(self, limit: &RateLimit, now: u64) -> Self {
+        let elapsed = now.saturating_sub(self.updated) as f64 / 1000.0;
+        Self {
+            tokens: (self.tokens + elapsed * limit.per_second).min(limit.capacity),
+            updated: now.max(self.updated),
+        }
+    }
+
+    fn is_full(&self, limit: &RateLimit) -> bool {
+        self.tokens >= limit.capacity
+    }
+
+    /// Takes a token, or returns how long until there is one.
+    fn take(&mut self, limit: &RateLimit) -> Result<(), Duration> {
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / limit.per_second,
+            ))
+        }
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+
+    fn limit(requests: u32, per: &str, burst: Option<u32>, key: Option<&str>) -> RateLimit {
+        RateLimit::from_config(&RateLimitConfig {
+            requests: NonZeroU32::new(requests).unwrap(),
+            per: per.to_owned().try_into().unwrap(),
+            burst: burst.and_then(NonZeroU32::new),
+            key: key.map(str::to_owned),
+        })
+        .unwrap()
+    }
+
+    fn request(headers: &[(&str, &str)]) -> Request<Body> {
+        let mut builder = Request::get("/test");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(spin_http::body::empty()).unwrap()
+    }
+
+    #[test]
+    fn buckets_refill_at_the_limit_rate() {
+        let limit = limit(2, "1s", Some(4), None);
+        let mut bucket = Bucket::full(&limit, 0);
+        for _ in 0..4 {
+            assert_eq!(bucket.take(&limit), Ok(()));
+        }
+        assert_eq!(bucket.take(&limit), Err(Duration::from_millis(500)));
+
+        let mut bucket = bucket.refill(&limit, 750);
+        assert_eq!(bucket.take(&limit), Ok(()));
+        assert_eq!(bucket.take(&limit), Err(Duration::from_millis(250)));
+
+        let bucket = bucket.refill(&limit, 60_000);
+        assert!(bucket.is_full(&limit));
+    }
+
+    #[test]
+    fn clients_are_keyed_by_ip_or_header() {
+        let ip: IpAddr = "::ffff:10.0.0.1".parse().unwrap();
+        let by_ip = limit(1, "1s", None, None);
+        assert_eq!(by_ip.client_key(&request(&[]), ip), "ip:10.0.0.1");
+
+        let by_header = limit(1, "1s", None, Some("header:x-api-key"));
+        assert_eq!(
+            by_header.client_key(&request(&[("x-api-key", "secret")]), ip),
+            "header:secret"
+        );
+        assert_eq!(by_header.client_key(&request(&[]), ip), "ip:10.0.0.1");
+
+        let invalid = RateLimitConfig {
+            requests: NonZeroU32::MIN,
+            per: "1s".to_owned().try_into().unwrap(),
+            burst: None,
+            key: Some("cookie:session".into()),
+        };
+        assert!(RateLimit::from_config(&invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn each_client_is_limited_separately() {
+        let limiter = RateLimiter::new(None);
+        let limit = limit(1, "1h", None, None);
+        let req = request(&[]);
+        let alice = "10.0.0.1".parse().unwrap();
+        let bob = "10.0.0.2".parse().unwrap();
+        assert!(limiter.check("/api", &limit, &req, alice).await.is_ok());
+        assert!(limiter.check("/api", &limit, &req, bob).await.is_ok());
+        assert!(limiter.check("/other", &limit, &req, alice).await.is_ok());
+        let retry_after = limiter
+            .check("/api", &limit, &req, alice)
+            .await
+            .unwrap_err();
+        assert!(retry_after > Duration::from_secs(3500));
+    }
+}
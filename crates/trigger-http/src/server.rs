@@ -1,44 +1,50 @@
-use std::{collections::HashMap, future::Future, io::IsTerminal, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap, future::Future, io::IsTerminal, net::SocketAddr, sync::Arc, time::Instant,
+};
 
-use anyhow::{bail, Context};
+use anyhow::{anyhow, bail, Context};
 use http::{
     uri::{Authority, Scheme},
     Request, Response, StatusCode, Uri,
 };
 use http_body_util::BodyExt;
-use hyper::{
-    body::{Bytes, Incoming},
-    server::conn::http1,
-    service::service_fn,
-};
-use hyper_util::rt::TokioIo;
+use hyper::body::{Bytes, Incoming};
 use spin_app::{APP_DESCRIPTION_KEY, APP_NAME_KEY};
+use spin_factor_key_value::KeyValueFactor;
 use spin_factor_outbound_http::{OutboundHttpFactor, SelfRequestOrigin};
+use spin_factor_variables::VariablesFactor;
 use spin_factors::RuntimeFactors;
+use spin_factors_executor::{is_execution_timeout, ConcurrencyLimit, Overloaded};
 use spin_http::{
     app_info::AppInfo,
     body,
-    config::{HttpExecutorType, HttpTriggerConfig},
+    config::{HttpAppTriggerConfig, HttpExecutorType, HttpTriggerConfig, ResponseCacheConfig},
     routes::{RouteMatch, Router},
     trigger::HandlerType,
 };
-use tokio::{
-    io::{AsyncRead, AsyncWrite},
-    net::TcpListener,
-    task,
-};
+use spin_serde::HumanDuration;
+use tokio::sync::watch;
 use tracing::Instrument;
 use wasmtime_wasi::p2::bindings::CommandIndices;
 use wasmtime_wasi_http::body::HyperOutgoingBody;
 
 use crate::{
-    headers::strip_forbidden_headers,
+    auth::RouteAuth,
+    cache::{ResponseCache, RouteCaching},
+    client_ip::TrustedProxies,
+    cors::RouteCors,
+    deployment::Deployment,
+    headers::{set_host_header, strip_forbidden_headers},
     instrument::{finalize_http_span, http_span, instrument_error, MatchedRoute},
     outbound_http::OutboundHttpInterceptor,
+    policy::{is_request_body_too_large, Refusal, RoutePolicy},
+    rate_limit::RateLimiter,
     spin::SpinHttpExecutor,
+    static_files::StaticRoutes,
+    streaming::{hold_permit, stream_response},
     wagi::WagiHttpExecutor,
     wasi::WasiHttpExecutor,
-    Body, NotFoundRouteKind, TlsConfig, TriggerApp, TriggerInstanceBuilder,
+    Body, HttpProtocols, NotFoundRouteKind, TlsConfig, TriggerApp, TriggerInstanceBuilder,
 };
 
 /// An HTTP server which runs Spin apps.
@@ -49,26 +55,64 @@ pub struct HttpServer<F: RuntimeFactors> {
     tls_config: Option<TlsConfig>,
     /// Request router.
     router: Router,
+    /// Routes served from files on the host, ahead of the router.
+    static_routes: StaticRoutes,
     /// The app being triggered.
     trigger_app: TriggerApp<F>,
     // Component ID -> component trigger config
     component_trigger_configs: HashMap<String, HttpTriggerConfig>,
     // Component ID -> handler type
     component_handler_types: HashMap<String, HandlerType>,
+    // Component ID -> concurrency limit, for routes with one
+    component_route_limits: HashMap<String, ConcurrencyLimit>,
+    // Component ID -> request and response limits, for routes with any
+    component_route_policies: HashMap<String, RoutePolicy>,
+    // Component ID -> authentication, for routes which require it
+    component_route_auth: HashMap<String, RouteAuth>,
+    // Component ID -> CORS policy, for routes with one
+    component_route_cors: HashMap<String, RouteCors>,
+    // Component ID -> response caching, for routes whose responses are cached
+    component_route_caching: HashMap<String, RouteCaching>,
+    /// The cache of responses, if any route's responses are cached.
+    response_cache: Option<ResponseCache>,
+    /// The clients' token buckets, if any route limits their request rate.
+    rate_limiter: Option<RateLimiter>,
+    /// The proxies trusted to name the clients they forward requests for.
+    trusted_proxies: TrustedProxies,
+    /// Whether to serve the app's resource usage.
+    usage_endpoint: bool,
+    /// The versions of HTTP the listener serves.
+    protocols: HttpProtocols,
+    /// Closed when the server is dropped, once it has finished its requests.
+    dropped: watch::Sender<()>,
 }
 
 impl<F: RuntimeFactors> HttpServer<F> {
     /// Create a new [`HttpServer`].
-    pub fn new(
+    ///
+    /// If the app's factors include variables, variable templates in the
+    /// trigger configs (such as routes) are resolved.
+    pub async fn new(
         listen_addr: SocketAddr,
         tls_config: Option<TlsConfig>,
         trigger_app: TriggerApp<F>,
     ) -> anyhow::Result<Self> {
-        // This needs to be a vec before building the router to handle duplicate routes
-        let component_trigger_configs = Vec::from_iter(
-            trigger_app
+        let trigger_configs = match trigger_app.configured_app().app_state::<VariablesFactor>() {
+            Ok(variables) => {
+                variables
+                    .resolve_trigger_configs::<HttpTriggerConfig>(trigger_app.app(), "http")
+                    .await?
+            }
+            Err(_) => trigger_app
                 .app()
                 .trigger_configs::<HttpTriggerConfig>("http")?
+                .into_iter()
+                .map(|(id, config)| (id.to_owned(), config))
+                .collect(),
+        };
+        // This needs to be a vec before building the router to handle duplicate routes
+        let component_trigger_configs = Vec::from_iter(
+            trigger_configs
                 .into_iter()
                 .map(|(_, config)| (config.component.clone(), config)),
         );
@@ -103,6 +147,8 @@ impl<F: RuntimeFactors> HttpServer<F> {
             router.routes().collect::<Vec<_>>()
         );
 
+        let static_routes = StaticRoutes::from_app(trigger_app.app())?;
+
         // Now that router is built we can merge duplicate routes by component
         let component_trigger_configs = HashMap::from_iter(component_trigger_configs);
 
@@ -126,69 +172,245 @@ impl<F: RuntimeFactors> HttpServer<F> {
                 Ok((component_id.clone(), handler_type))
             })
             .collect::<anyhow::Result<_>>()?;
+
+        let mut component_route_limits = HashMap::new();
+        let mut component_route_policies = HashMap::new();
+        let mut component_route_cors = HashMap::new();
+        let mut component_route_auth = HashMap::new();
+        for (component_id, trigger_config) in &component_trigger_configs {
+            if let Some(status) = trigger_config.overload_status {
+                anyhow::ensure!(
+                    status == 429 || status == 503,
+                    "HTTP trigger for component '{component_id}' has overload_status {status}; it must be 429 or 503"
+                );
+            }
+            anyhow::ensure!(
+                !trigger_config
+                    .response_idle_timeout
+                    .as_ref()
+                    .is_some_and(|timeout| timeout.duration().is_zero()),
+                "HTTP trigger for component '{component_id}' has a zero response_idle_timeout; it must be greater than zero"
+            );
+            if let Some(max) = trigger_config.max_concurrent_requests {
+                let limit = ConcurrencyLimit::new(
+                    max.get() as usize,
+                    trigger_config.max_queued_requests.map(|max| max as usize),
+                    trigger_config
+                        .queue_timeout
+                        .as_ref()
+                        .map(HumanDuration::duration),
+                );
+                component_route_limits.insert(component_id.clone(), limit);
+            }
+            let policy = RoutePolicy::from_config(trigger_config).with_context(|| {
+                format!("HTTP trigger for component '{component_id}' has an invalid policy")
+            })?;
+            if let Some(policy) = policy {
+                component_route_policies.insert(component_id.clone(), policy);
+            }
+            if let Some(cors) = &trigger_config.cors {
+                let cors = RouteCors::from_config(cors).with_context(|| {
+                    format!(
+                        "HTTP trigger for component '{component_id}' has an invalid CORS policy"
+                    )
+                })?;
+                component_route_cors.insert(component_id.clone(), cors);
+            }
+            if let Some(auth) = &trigger_config.auth {
+                let auth = RouteAuth::from_config(auth).with_context(|| {
+                    format!("HTTP trigger for component '{component_id}' has invalid auth")
+                })?;
+                component_route_auth.insert(component_id.clone(), auth);
+            }
+        }
+
+        let app_config = trigger_app
+            .app()
+            .get_trigger_metadata::<HttpAppTriggerConfig>("http")?
+            .unwrap_or_default();
+        let component_route_caching = component_trigger_configs
+            .iter()
+            .filter_map(|(component_id, trigger_config)| {
+                let caching = RouteCaching::from_config(
+                    trigger_config.cache.as_ref(),
+                    app_config.cache.is_some(),
+                )?;
+                Some((component_id.clone(), caching))
+            })
+            .collect::<HashMap<_, _>>();
+        let response_cache = if component_route_caching.is_empty() {
+            None
+        } else {
+            let config = app_config.cache.unwrap_or_default();
+            Some(Self::response_cache(&trigger_app, &config).await?)
+        };
+        let rate_limiter = if component_route_policies
+            .values()
+            .any(|policy| policy.rate_limit().is_some())
+        {
+            let store = match &app_config.rate_limit_store {
+                Some(label) => {
+                    Some(Self::key_value_store(&trigger_app, label, "rate limit").await?)
+                }
+                None => None,
+            };
+            Some(RateLimiter::new(store))
+        } else {
+            None
+        };
+        let trusted_proxies = TrustedProxies::from_config(&app_config.trusted_proxies)
+            .context("HTTP trigger has invalid trusted_proxies")?;
+
         Ok(Self {
             listen_addr,
             tls_config,
             router,
+            static_routes,
             trigger_app,
             component_trigger_configs,
             component_handler_types,
+            component_route_limits,
+            component_route_policies,
+            component_route_auth,
+            component_route_cors,
+            component_route_caching,
+            response_cache,
+            rate_limiter,
+            trusted_proxies,
+            usage_endpoint: false,
+            protocols: HttpProtocols::default(),
+            dropped: watch::channel(()).0,
         })
     }
 
-    /// Serve incoming requests over the provided [`TcpListener`].
-    pub async fn serve(self: Arc<Self>) -> anyhow::Result<()> {
-        let listener = TcpListener::bind(self.listen_addr).await.with_context(|| {
-            format!(
-                "Unable to listen on {listen_addr}",
-                listen_addr = self.listen_addr
-            )
-        })?;
-        if let Some(tls_config) = self.tls_config.clone() {
-            self.serve_https(listener, tls_config).await?;
-        } else {
-            self.serve_http(listener).await?;
+    /// Creates the response cache, in the key-value store the config names
+    /// or else in memory.
+    async fn response_cache(
+        trigger_app: &TriggerApp<F>,
+        config: &ResponseCacheConfig,
+    ) -> anyhow::Result<ResponseCache> {
+        let store = match &config.key_value_store {
+            Some(label) => Some(Self::key_value_store(trigger_app, label, "response cache").await?),
+            None => None,
+        };
+        Ok(ResponseCache::new(config, store))
+    }
+
+    /// Opens the key-value store with the given label, for the given use.
+    async fn key_value_store(
+        trigger_app: &TriggerApp<F>,
+        label: &str,
+        usage: &str,
+    ) -> anyhow::Result<Arc<dyn spin_factor_key_value::Store>> {
+        let store_manager = trigger_app
+            .configured_app()
+            .app_state::<KeyValueFactor>()
+            .with_context(|| format!("the {usage} store requires key-value support"))?
+            .store_manager();
+        if !store_manager.is_defined(label) {
+            bail!("{usage} store {label:?} is not a configured key-value store");
         }
-        Ok(())
+        store_manager
+            .get(label)
+            .await
+            .map_err(|err| anyhow!("failed to open the {usage} store {label:?}: {err:?}"))
     }
 
-    async fn serve_http(self: Arc<Self>, listener: TcpListener) -> anyhow::Result<()> {
-        self.print_startup_msgs("http", &listener)?;
-        loop {
-            let (stream, client_addr) = listener.accept().await?;
-            self.clone()
-                .serve_connection(stream, Scheme::HTTP, client_addr);
+    /// Serves the resource usage of each of the app's components, as JSON,
+    /// at `/.well-known/spin/usage`.
+    pub fn enable_usage_endpoint(&mut self) {
+        self.usage_endpoint = true;
+    }
+
+    /// Sets the versions of HTTP the listener serves.
+    pub fn set_protocols(&mut self, protocols: HttpProtocols) {
+        self.protocols = protocols;
+    }
+
+    /// Serve incoming requests with this server's app.
+    ///
+    /// To serve new versions of the app in its place, serve a [`Deployment`]
+    /// of the server instead.
+    pub async fn serve(self: Arc<Self>) -> anyhow::Result<()> {
+        Arc::new(Deployment::new(self)).serve().await
+    }
+
+    /// The address the server listens on.
+    pub(crate) fn listen_addr(&self) -> SocketAddr {
+        self.listen_addr
+    }
+
+    /// The TLS configuration for the server.
+    pub(crate) fn tls_config(&self) -> Option<&TlsConfig> {
+        self.tls_config.as_ref()
+    }
+
+    /// The versions of HTTP the listener serves.
+    pub(crate) fn protocols(&self) -> HttpProtocols {
+        self.protocols
+    }
+
+    /// The app being triggered.
+    pub(crate) fn trigger_app(&self) -> &TriggerApp<F> {
+        &self.trigger_app
+    }
+
+    /// Returns a future which completes once the server has been dropped,
+    /// and so has finished every request it was handling.
+    pub(crate) fn drained(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut dropped = self.dropped.subscribe();
+        async move {
+            let _ = dropped.changed().await;
         }
     }
 
-    async fn serve_https(
-        self: Arc<Self>,
-        listener: TcpListener,
-        tls_config: TlsConfig,
-    ) -> anyhow::Result<()> {
-        self.print_startup_msgs("https", &listener)?;
-        let acceptor = tls_config.server_config()?;
-        loop {
-            let (stream, client_addr) = listener.accept().await?;
-            match acceptor.accept(stream).await {
-                Ok(stream) => self
-                    .clone()
-                    .serve_connection(stream, Scheme::HTTPS, client_addr),
-                Err(err) => tracing::error!(?err, "Failed to start TLS session"),
-            }
+    /// Sets up outbound HTTP request origin and service chaining for every
+    /// instance the app prepares, and starts its components' warm instances.
+    pub(crate) fn start_warm_instances(self: &Arc<Self>) -> anyhow::Result<()> {
+        let origin = SelfRequestOrigin::create(self.scheme(), &self.listen_addr.to_string())?;
+        // The app is owned by the server, so refer to the server weakly
+        let server = Arc::downgrade(self);
+        self.trigger_app.start_warm_instances(Arc::new(
+            move |builder: &mut TriggerInstanceBuilder<F>| {
+                let server = server.upgrade().context("the HTTP server has stopped")?;
+                let outbound_http = Self::outbound_http(builder)?;
+                outbound_http.set_self_request_origin(origin.clone());
+                outbound_http.set_request_interceptor(OutboundHttpInterceptor::new(server))
+            },
+        ))
+    }
+
+    fn outbound_http<'a>(
+        builder: &'a mut TriggerInstanceBuilder<F>,
+    ) -> anyhow::Result<&'a mut spin_factor_outbound_http::InstanceState> {
+        // The outbound HTTP factor is required since both inbound and outbound wasi HTTP
+        // implementations assume they use the same underlying wasmtime resource storage.
+        // Eventually, we may be able to factor this out to a separate factor.
+        builder.factor_builder::<OutboundHttpFactor>().context(
+            "The wasi HTTP trigger was configured without the required wasi outbound http support",
+        )
+    }
+
+    /// The scheme over which this server serves requests.
+    fn scheme(&self) -> Scheme {
+        if self.tls_config.is_some() {
+            Scheme::HTTPS
+        } else {
+            Scheme::HTTP
         }
     }
 
     /// Handles incoming requests using an HTTP executor.
     ///
-    /// This method handles well known paths and routes requests to the handler when the router
-    /// matches the requests path.
+    /// This method handles well known paths, serves static files, and routes requests to the
+    /// handler when the router matches the requests path.
     pub async fn handle(
         self: &Arc<Self>,
         mut req: Request<Body>,
         server_scheme: Scheme,
         client_addr: SocketAddr,
     ) -> anyhow::Result<Response<Body>> {
+        set_host_header(&mut req);
         strip_forbidden_headers(&mut req);
 
         spin_telemetry::extract_trace_context(&req);
@@ -205,14 +427,36 @@ impl<F: RuntimeFactors> HttpServer<F> {
                     path,
                 )),
                 "info" => self.app_info(path),
+                "usage" if self.usage_endpoint => self.resource_usage(path),
                 _ => Self::not_found(NotFoundRouteKind::WellKnown),
             };
         }
 
+        if let Some((route, response)) = self.static_routes.serve(&req).await? {
+            return Ok(MatchedRoute::with_response_extension(response, route));
+        }
+
         match self.router.route(&path) {
             Ok(route_match) => {
-                self.handle_trigger_route(req, route_match, server_scheme, client_addr)
-                    .await
+                // Preflight requests are answered without the component, and
+                // regardless of the route's other limits
+                let cors = self.component_route_cors.get(route_match.component_id());
+                let Some(cors) = cors else {
+                    return self
+                        .handle_trigger_route(req, route_match, server_scheme, client_addr)
+                        .await;
+                };
+                if let Some(res) = cors.preflight(&req)? {
+                    return Ok(MatchedRoute::with_response_extension(
+                        res,
+                        route_match.raw_route(),
+                    ));
+                }
+                let origin = req.headers().get(http::header::ORIGIN).cloned();
+                let res = self
+                    .handle_trigger_route(req, route_match, server_scheme, client_addr)
+                    .await?;
+                Ok(cors.apply(origin.as_ref(), res))
             }
             Err(_) => Self::not_found(NotFoundRouteKind::Normal(path.to_string())),
         }
@@ -239,26 +483,106 @@ impl<F: RuntimeFactors> HttpServer<F> {
             spin.request_count = 1,
             trigger_type = "http",
             app_id = app_id,
-            component_id = component_id
+            component_id = component_id,
+            route = route_match.raw_route()
         );
+        // Includes any wait for the route's concurrency limit
+        let started = Instant::now();
+
+        let trigger_config = self.component_trigger_configs.get(component_id).unwrap();
+        let client_ip = self
+            .trusted_proxies
+            .client_ip(req.headers(), client_addr.ip());
+
+        // Requests the route doesn't accept are refused before queueing
+        let policy = self.component_route_policies.get(component_id);
+        if let Some(policy) = policy {
+            let checked = policy
+                .check_client(client_ip)
+                .and_then(|()| policy.check_request(&req));
+            let checked = match (checked, policy.rate_limit(), &self.rate_limiter) {
+                (Ok(()), Some(limit), Some(limiter)) => limiter
+                    .check(route_match.raw_route(), limit, &req, client_ip)
+                    .await
+                    .map_err(Refusal::RateLimited),
+                (checked, _, _) => checked,
+            };
+            if let Err(refusal) = checked {
+                tracing::info!("Refusing request to component {component_id}: {refusal}");
+                return Self::refused(policy, refusal, route_match.raw_route());
+            }
+            req = policy.limit_request(req);
+        }
+
+        // Requests which fail authentication never reach the component
+        if let Some(auth) = self.component_route_auth.get(component_id) {
+            if let Err(err) = auth.authenticate(&mut req).await {
+                tracing::info!("Refusing request to component {component_id}: {err}");
+                return Ok(MatchedRoute::with_response_extension(
+                    err.response()?,
+                    route_match.raw_route(),
+                ));
+            }
+        }
+
+        // A cached response is served without waiting for, or running, the
+        // component
+        let cache = self
+            .response_cache
+            .as_ref()
+            .zip(self.component_route_caching.get(component_id));
+        let cached_request = cache
+            .and_then(|(cache, caching)| cache.request(route_match.raw_route(), caching, &req));
+        if let (Some((cache, _)), Some(cached_request)) = (cache, &cached_request) {
+            if let Some(res) = cache.lookup(cached_request).await {
+                return Ok(MatchedRoute::with_response_extension(
+                    res,
+                    route_match.raw_route(),
+                ));
+            }
+        }
+
+        // The permit is held until the response has been sent
+        let route_permit = match self.component_route_limits.get(component_id) {
+            Some(limit) => match limit.acquire().await {
+                Ok(permit) => Some(permit),
+                Err(err) => {
+                    tracing::info!("Refusing request to component {component_id}: {err}");
+                    return Self::overloaded(trigger_config, route_match.raw_route());
+                }
+            },
+            None => None,
+        };
 
         let mut instance_builder = self.trigger_app.prepare(component_id)?;
 
-        // Set up outbound HTTP request origin and service chaining
-        // The outbound HTTP factor is required since both inbound and outbound wasi HTTP
-        // implementations assume they use the same underlying wasmtime resource storage.
-        // Eventually, we may be able to factor this out to a separate factor.
-        let outbound_http = instance_builder
-            .factor_builder::<OutboundHttpFactor>()
-            .context(
-            "The wasi HTTP trigger was configured without the required wasi outbound http support",
-        )?;
-        let origin = SelfRequestOrigin::create(server_scheme, &self.listen_addr.to_string())?;
-        outbound_http.set_self_request_origin(origin);
-        outbound_http.set_request_interceptor(OutboundHttpInterceptor::new(self.clone()))?;
+        // Identifies the request in the component's log lines
+        let request_id = req
+            .headers()
+            .get("x-request-id")
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let invocation_context = instance_builder.invocation_context();
+        invocation_context.set("request_id", request_id);
+        invocation_context.set("route", route_match.raw_route());
+        // The rest of the request's context is for the context host interface
+        invocation_context.set("client_ip", client_ip.to_string());
+        invocation_context.set("path_info", route_match.trailing_wildcard());
+        for (name, value) in route_match.named_wildcards() {
+            invocation_context.set(format!("param.{name}"), value);
+        }
+
+        // Outbound HTTP is set up for the server's own scheme in
+        // `start_warm_instances`; a request over another scheme (a chained
+        // request to an HTTPS server) needs its own origin, and so a fresh
+        // instance rather than a warm one.
+        if server_scheme != self.scheme() {
+            let origin = SelfRequestOrigin::create(server_scheme, &self.listen_addr.to_string())?;
+            Self::outbound_http(&mut instance_builder)?.set_self_request_origin(origin);
+        }
 
         // Prepare HTTP executor
-        let trigger_config = self.component_trigger_configs.get(component_id).unwrap();
         let handler_type = self.component_handler_types.get(component_id).unwrap();
         let executor = trigger_config
             .executor
@@ -295,11 +619,62 @@ impl<F: RuntimeFactors> HttpServer<F> {
                     .await
             }
         };
+        spin_telemetry::metrics::histogram!(
+            spin.request_duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+            trigger_type = "http",
+            app_id = app_id,
+            component_id = component_id,
+            route = route_match.raw_route()
+        );
         match res {
-            Ok(res) => Ok(MatchedRoute::with_response_extension(
-                res,
-                route_match.raw_route(),
-            )),
+            Ok(res) => {
+                let idle_timeout = trigger_config
+                    .response_idle_timeout
+                    .as_ref()
+                    .map(HumanDuration::duration);
+                let res = match policy {
+                    Some(policy) => match policy.limit_response(res) {
+                        Some(res) => res,
+                        None => {
+                            tracing::error!(
+                                "Component {component_id} sent a response larger than its route's max_response_body_size"
+                            );
+                            return Self::internal_error(None, route_match.raw_route());
+                        }
+                    },
+                    None => res,
+                };
+                let res = match (cache, cached_request) {
+                    (Some((cache, _)), Some(cached_request)) => cache.store(cached_request, res),
+                    _ => res,
+                };
+                let res = stream_response(res, idle_timeout);
+                let res = match route_permit {
+                    Some(permit) => hold_permit(res, permit),
+                    None => res,
+                };
+                Ok(MatchedRoute::with_response_extension(
+                    res,
+                    route_match.raw_route(),
+                ))
+            }
+            Err(err) if err.chain().any(|cause| cause.is::<Overloaded>()) => {
+                tracing::info!("Refusing request to component {component_id}: {err:#}");
+                Self::overloaded(trigger_config, route_match.raw_route())
+            }
+            Err(err) if policy.is_some() && is_request_body_too_large(&err) => {
+                tracing::info!("Refusing request to component {component_id}: {err:#}");
+                Self::refused(
+                    policy.unwrap(),
+                    Refusal::BodyTooLarge,
+                    route_match.raw_route(),
+                )
+            }
+            Err(err) if is_execution_timeout(&err) => {
+                self.trigger_app
+                    .record_execution_timeout("http", component_id);
+                Self::timed_out(route_match.raw_route())
+            }
             Err(err) => {
                 tracing::error!("Error processing request: {err:?}");
                 instrument_error(&err);
@@ -308,6 +683,12 @@ impl<F: RuntimeFactors> HttpServer<F> {
         }
     }
 
+    /// Returns whether the given component has an HTTP trigger, and so can
+    /// handle requests.
+    pub(crate) fn has_http_component(&self, component_id: &str) -> bool {
+        self.component_trigger_configs.contains_key(component_id)
+    }
+
     /// Returns spin status information.
     fn app_info(&self, route: String) -> anyhow::Result<Response<Body>> {
         let info = AppInfo::new(self.trigger_app.app());
@@ -320,6 +701,30 @@ impl<F: RuntimeFactors> HttpServer<F> {
         ))
     }
 
+    /// Returns the resource usage of each component.
+    fn resource_usage(&self, route: String) -> anyhow::Result<Response<Body>> {
+        let components: serde_json::Map<_, _> = self
+            .trigger_app
+            .resource_usage()
+            .into_iter()
+            .map(|(component_id, usage)| {
+                let usage = serde_json::json!({
+                    "invocations": usage.invocations,
+                    "fuel_consumed": usage.fuel_consumed,
+                    "peak_memory": usage.peak_memory,
+                });
+                (component_id, usage)
+            })
+            .collect();
+        let body = serde_json::to_vec_pretty(&serde_json::json!({ "components": components }))?;
+        Ok(MatchedRoute::with_response_extension(
+            Response::builder()
+                .header("content-type", "application/json")
+                .body(body::full(body.into()))?,
+            route,
+        ))
+    }
+
     /// Creates an HTTP 500 response.
     fn internal_error(
         body: Option<&str>,
@@ -338,6 +743,46 @@ impl<F: RuntimeFactors> HttpServer<F> {
         ))
     }
 
+    /// Creates the response to a request refused because its route or
+    /// component is at capacity: by default, an HTTP 503 response.
+    fn overloaded(
+        trigger_config: &HttpTriggerConfig,
+        route: impl Into<String>,
+    ) -> anyhow::Result<Response<Body>> {
+        let status = match trigger_config.overload_status {
+            Some(429) => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        Ok(MatchedRoute::with_response_extension(
+            Response::builder().status(status).body(body::empty())?,
+            route,
+        ))
+    }
+
+    /// Creates the response to a request refused by its route's policy: an
+    /// HTTP 403, 405, 413, 429 or 431 response.
+    fn refused(
+        policy: &RoutePolicy,
+        refusal: Refusal,
+        route: impl Into<String>,
+    ) -> anyhow::Result<Response<Body>> {
+        Ok(MatchedRoute::with_response_extension(
+            policy.refusal_response(refusal)?,
+            route,
+        ))
+    }
+
+    /// Creates the response to a request whose handler ran past its
+    /// execution timeout: an HTTP 504 response.
+    fn timed_out(route: impl Into<String>) -> anyhow::Result<Response<Body>> {
+        Ok(MatchedRoute::with_response_extension(
+            Response::builder()
+                .status(StatusCode::GATEWAY_TIMEOUT)
+                .body(body::empty())?,
+            route,
+        ))
+    }
+
     /// Creates an HTTP 404 response.
     fn not_found(kind: NotFoundRouteKind) -> anyhow::Result<Response<Body>> {
         use std::sync::atomic::{AtomicBool, Ordering};
@@ -354,63 +799,45 @@ impl<F: RuntimeFactors> HttpServer<F> {
             .body(body::empty())?)
     }
 
-    fn serve_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+    pub(crate) async fn instrumented_service_fn(
         self: Arc<Self>,
-        stream: S,
         server_scheme: Scheme,
         client_addr: SocketAddr,
-    ) {
-        task::spawn(async move {
-            if let Err(err) = http1::Builder::new()
-                .keep_alive(true)
-                .serve_connection(
-                    TokioIo::new(stream),
-                    service_fn(move |request| {
-                        self.clone().instrumented_service_fn(
-                            server_scheme.clone(),
-                            client_addr,
-                            request,
-                        )
-                    }),
-                )
-                .await
-            {
-                tracing::warn!("Error serving HTTP connection: {err:?}");
-            }
+        request: Request<Incoming>,
+    ) -> anyhow::Result<Response<HyperOutgoingBody>> {
+        let request = request.map(|body: Incoming| {
+            body.map_err(wasmtime_wasi_http::hyper_response_error)
+                .boxed()
         });
+        self.instrumented_handle(server_scheme, client_addr, request)
+            .await
     }
 
-    async fn instrumented_service_fn(
+    /// Handles a request, whichever version of HTTP it arrived over, within
+    /// a span for it.
+    pub(crate) async fn instrumented_handle(
         self: Arc<Self>,
         server_scheme: Scheme,
         client_addr: SocketAddr,
-        request: Request<Incoming>,
+        request: Request<Body>,
     ) -> anyhow::Result<Response<HyperOutgoingBody>> {
         let span = http_span!(request, client_addr);
         let method = request.method().to_string();
         async {
-            let result = self
-                .handle(
-                    request.map(|body: Incoming| {
-                        body.map_err(wasmtime_wasi_http::hyper_response_error)
-                            .boxed()
-                    }),
-                    server_scheme,
-                    client_addr,
-                )
-                .await;
+            let result = self.handle(request, server_scheme, client_addr).await;
             finalize_http_span(result, method)
         }
         .instrument(span)
         .await
     }
 
-    fn print_startup_msgs(&self, scheme: &str, listener: &TcpListener) -> anyhow::Result<()> {
-        let local_addr = listener.local_addr()?;
-        let base_url = format!("{scheme}://{local_addr:?}");
+    pub(crate) fn print_startup_msgs(&self, base_url: &str) -> anyhow::Result<()> {
         terminal::step!("\nServing", "{base_url}");
         tracing::info!("Serving {base_url}");
+        self.print_routes(base_url)
+    }
 
+    pub(crate) fn print_routes(&self, base_url: &str) -> anyhow::Result<()> {
         println!("Available Routes:");
         for (route, component_id) in self.router.routes() {
             println!("  {component_id}: {base_url}{route}");
@@ -420,6 +847,9 @@ impl<F: RuntimeFactors> HttpServer<F> {
                 }
             }
         }
+        for (route, dir) in self.static_routes.routes() {
+            println!("  static files in {}: {base_url}{route}", dir.display());
+        }
         Ok(())
     }
 }
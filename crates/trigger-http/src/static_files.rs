@@ -0,0 +1,492 @@
+//! Serving files from directories on the host for an app's static routes,
+//! without instantiating a component.
+
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Context;
+use futures::{channel::mpsc, SinkExt};
+use http::{header, HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::{Bytes, Frame};
+use spin_app::{App, MetadataKey};
+use spin_http::{
+    body,
+    config::{HttpAppTriggerConfig, StaticRouteConfig},
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// The URL the app was loaded from; a `file:` URL of its manifest for a
+/// local app.
+const ORIGIN_KEY: MetadataKey = MetadataKey::new("origin");
+
+/// The size of the chunks in which files are read.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// An app's static routes.
+#[derive(Default)]
+pub(crate) struct StaticRoutes {
+    /// The routes, longest prefix first.
+    routes: Vec<StaticRoute>,
+}
+
+struct StaticRoute {
+    /// The route as configured, such as `/assets/...`.
+    route: String,
+    /// The path the route matches, and under which files are found, such as
+    /// `/assets`.
+    prefix: String,
+    /// The canonical path of the directory served.
+    dir: PathBuf,
+    cache_control: Option<HeaderValue>,
+}
+
+impl StaticRoutes {
+    /// The static routes in the app's `[application.trigger.http]` table.
+    /// Relative directories are resolved against the app's directory.
+    pub(crate) fn from_app(app: &App) -> anyhow::Result<Self> {
+        let Some(config) = app.get_trigger_metadata::<HttpAppTriggerConfig>("http")? else {
+            return Ok(Self::default());
+        };
+        let app_dir = app
+            .get_metadata(ORIGIN_KEY)?
+            .and_then(|origin| spin_common::url::parse_file_url(&origin).ok())
+            .and_then(|manifest| manifest.parent().map(Path::to_owned));
+        let mut routes = config
+            .static_routes
+            .into_iter()
+            .map(|config| StaticRoute::new(config, app_dir.as_deref()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        routes.sort_by_key(|route| std::cmp::Reverse(route.prefix.len()));
+        Ok(Self { routes })
+    }
+
+    /// The configured routes, with the directories they serve.
+    pub(crate) fn routes(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.routes
+            .iter()
+            .map(|route| (route.route.as_str(), route.dir.as_path()))
+    }
+
+    /// Serves the request from the first static route with a file for it,
+    /// returning the route with the response. Returns `None` if no route
+    /// has the file, or the request is not a `GET` or `HEAD`, so that the
+    /// request can be routed to a component.
+    pub(crate) async fn serve(
+        &self,
+        req: &Request<Body>,
+    ) -> anyhow::Result<Option<(String, Response<Body>)>> {
+        if self.routes.is_empty() || !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return Ok(None);
+        }
+        let path = req.uri().path();
+        for route in &self.routes {
+            let Some(rest) = route.match_path(path) else {
+                continue;
+            };
+            let Some(file) = route.find_file(rest).await else {
+                continue;
+            };
+            tracing::trace!("Serving {path} from static file {}", file.display());
+            let response = route.respond(req, &file).await?;
+            return Ok(Some((route.route.clone(), response)));
+        }
+        Ok(None)
+    }
+}
+
+impl StaticRoute {
+    fn new(config: StaticRouteConfig, app_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let prefix = config
+            .route
+            .strip_suffix("/...")
+            .with_context(|| {
+                format!(
+                    "static route {:?} must be a wildcard route, such as `/assets/...`",
+                    config.route
+                )
+            })?
+            .to_owned();
+        let dir = match app_dir {
+            _ if config.dir.is_absolute() => config.dir,
+            Some(app_dir) => app_dir.join(config.dir),
+            None => anyhow::bail!(
+                "static route {:?} has a relative directory, which requires a local app",
+                config.route
+            ),
+        };
+        let dir = dir.canonicalize().with_context(|| {
+            format!(
+                "static route {:?} serves directory {}, which was not found",
+                config.route,
+                dir.display()
+            )
+        })?;
+        let cache_control = config
+            .cache_control
+            .map(|value| HeaderValue::try_from(value.as_str()))
+            .transpose()
+            .with_context(|| {
+                format!(
+                    "static route {:?} has an invalid cache_control",
+                    config.route
+                )
+            })?;
+        Ok(Self {
+            route: config.route,
+            prefix,
+            dir,
+            cache_control,
+        })
+    }
+
+    /// The part of the path under the route, if the route matches it.
+    fn match_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let rest = path.strip_prefix(&self.prefix)?;
+        (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+    }
+
+    /// Finds the file for the part of a request path under the route: the
+    /// `index.html` of a directory, or else a file. Paths which would leave
+    /// the route's directory are not found.
+    async fn find_file(&self, rest: &str) -> Option<PathBuf> {
+        let mut path = self.dir.join(relative_path(rest)?);
+        if tokio::fs::metadata(&path).await.ok()?.is_dir() {
+            path.push("index.html");
+        }
+        // Symbolic links may point out of the directory
+        let path = tokio::fs::canonicalize(&path).await.ok()?;
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+        (path.starts_with(&self.dir) && metadata.is_file()).then_some(path)
+    }
+
+    /// Responds with the file, or the range of it requested, unless the
+    /// client's copy is current.
+    async fn respond(&self, req: &Request<Body>, path: &Path) -> anyhow::Result<Response<Body>> {
+        let mut file = tokio::fs::File::open(path)
+            .await
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let metadata = file.metadata().await?;
+        let len = metadata.len();
+        let etag = etag(len, metadata.modified().ok());
+
+        let mut response = Response::builder()
+            .header(header::ETAG, &etag)
+            .header(header::ACCEPT_RANGES, "bytes");
+        if let Some(cache_control) = &self.cache_control {
+            response = response.header(header::CACHE_CONTROL, cache_control);
+        }
+        if is_not_modified(req.headers(), &etag) {
+            return Ok(response
+                .status(StatusCode::NOT_MODIFIED)
+                .body(body::empty())?);
+        }
+        response = response.header(header::CONTENT_TYPE, content_type(path));
+
+        let range = if if_range_matches(req.headers(), &etag) {
+            req.headers()
+                .get(header::RANGE)
+                .and_then(|range| range.to_str().ok())
+                .map_or(ByteRange::Full, |range| byte_range(range, len))
+        } else {
+            ByteRange::Full
+        };
+        let (start, end) = match range {
+            ByteRange::Full => (0, len),
+            ByteRange::Partial(start, end) => {
+                response = response.status(StatusCode::PARTIAL_CONTENT).header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {start}-{}/{len}", end - 1),
+                );
+                (start, end)
+            }
+            ByteRange::Unsatisfiable => {
+                return Ok(response
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+                    .body(body::empty())?);
+            }
+        };
+        response = response.header(header::CONTENT_LENGTH, end - start);
+        if req.method() == Method::HEAD {
+            return Ok(response.body(body::empty())?);
+        }
+        file.seek(SeekFrom::Start(start)).await?;
+        Ok(response.body(file_body(file, end - start))?)
+    }
+}
+
+/// The relative path of the file for the part of a request path under a
+/// route, or `None` if it has segments which could leave the directory.
+fn relative_path(rest: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for segment in rest.split('/').filter(|segment| !segment.is_empty()) {
+        let segment = percent_encoding::percent_decode_str(segment)
+            .decode_utf8()
+            .ok()?;
+        if segment == "."
+            || segment == ".."
+            || segment.contains(['/', '\\', '\0'])
+            || Path::new(segment.as_ref()).has_root()
+        {
+            return None;
+        }
+        path.push(segment.as_ref());
+    }
+    Some(path)
+}
+
+/// An entity tag for a file, from its size and modification time.
+fn etag(len: u64, modified: Option<std::time::SystemTime>) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos());
+    format!("\"{len:x}-{modified:x}\"")
+}
+
+/// Whether the request's `If-None-Match` header matches the entity tag.
+fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// Whether a range request applies to the current file: if the request has
+/// an `If-Range` header, it must be the file's entity tag.
+fn if_range_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_RANGE)
+        .is_none_or(|value| value.as_bytes() == etag.as_bytes())
+}
+
+/// The part of a file a request's `Range` header asks for.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// The whole file: the header asked for several ranges, or is not
+    /// understood, and so is ignored.
+    Full,
+    /// The bytes from the start up to, but not including, the end.
+    Partial(u64, u64),
+    /// A range entirely beyond the end of the file.
+    Unsatisfiable,
+}
+
+/// Parses a `Range` header for a file of the given length. Only a single
+/// range of bytes is served.
+fn byte_range(range: &str, len: u64) -> ByteRange {
+    let Some(range) = range.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    let Some((start, end)) = range.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    if range.contains(',') {
+        return ByteRange::Full;
+    }
+    let (start, end) = (start.trim(), end.trim());
+    if start.is_empty() {
+        // The last bytes of the file
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(len.saturating_sub(suffix), len),
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = start.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = if end.is_empty() {
+        len
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => end.saturating_add(1).min(len),
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+/// The `Content-Type` of a file, from its extension.
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("wasm") => "application/wasm",
+        Some("pdf") => "application/pdf",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("zip") => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A body of the given number of bytes of the file, from its current
+/// position.
+fn file_body(mut file: tokio::fs::File, len: u64) -> Body {
+    // The file is read by its own task since a body must be `Sync`
+    let (mut tx, rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        let mut remaining = len;
+        while remaining > 0 {
+            let mut chunk = vec![0; remaining.min(CHUNK_SIZE as u64) as usize];
+            let frame = match file.read(&mut chunk).await {
+                Ok(0) => Err(ErrorCode::InternalError(Some(
+                    "static file was truncated while being served".into(),
+                ))),
+                Ok(read) => {
+                    chunk.truncate(read);
+                    remaining -= read as u64;
+                    Ok(Frame::data(Bytes::from(chunk)))
+                }
+                Err(err) => Err(ErrorCode::InternalError(Some(err.to_string()))),
+            };
+            let is_err = frame.is_err();
+            if tx.send(frame).await.is_err() || is_err {
+                break;
+            }
+        }
+    });
+    StreamBody::new(rx).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_are_parsed() {
+        assert_eq!(byte_range("bytes=0-99", 1000), ByteRange::Partial(0, 100));
+        assert_eq!(
+            byte_range("bytes=900-", 1000),
+            ByteRange::Partial(900, 1000)
+        );
+        assert_eq!(
+            byte_range("bytes=-100", 1000),
+            ByteRange::Partial(900, 1000)
+        );
+        assert_eq!(byte_range("bytes=-2000", 1000), ByteRange::Partial(0, 1000));
+        assert_eq!(
+            byte_range("bytes=500-2000", 1000),
+            ByteRange::Partial(500, 1000)
+        );
+        assert_eq!(byte_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range("bytes=0-9,20-29", 1000), ByteRange::Full);
+        assert_eq!(byte_range("bytes=9-0", 1000), ByteRange::Full);
+        assert_eq!(byte_range("items=0-9", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn paths_cannot_leave_the_directory() {
+        assert_eq!(
+            relative_path("/css/site%20main.css"),
+            Some(PathBuf::from("css/site main.css"))
+        );
+        assert_eq!(relative_path("/"), Some(PathBuf::new()));
+        assert_eq!(relative_path("/../secret"), None);
+        assert_eq!(relative_path("/css/%2e%2e/%2e%2e/secret"), None);
+        assert_eq!(relative_path("/css/..%2f..%2fsecret"), None);
+        assert_eq!(relative_path("/css/..%5csecret"), None);
+    }
+
+    #[test]
+    fn etags_are_matched() {
+        let etag = etag(42, Some(UNIX_EPOCH));
+        let mut headers = HeaderMap::new();
+        assert!(!is_not_modified(&headers, &etag));
+        assert!(if_range_matches(&headers, &etag));
+
+        headers.insert(
+            header::IF_NONE_MATCH,
+            format!("\"other\", W/{etag}").try_into().unwrap(),
+        );
+        assert!(is_not_modified(&headers, &etag));
+
+        headers.insert(header::IF_RANGE, "\"other\"".try_into().unwrap());
+        assert!(!if_range_matches(&headers, &etag));
+    }
+
+    #[tokio::test]
+    async fn files_are_served_with_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/index.html"), "<h1>Docs</h1>").unwrap();
+        std::fs::write(dir.path().join("data.txt"), "0123456789").unwrap();
+        let route = StaticRoute::new(
+            StaticRouteConfig {
+                route: "/static/...".into(),
+                dir: dir.path().into(),
+                cache_control: Some("max-age=60".into()),
+            },
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(route.match_path("/static/data.txt"), Some("/data.txt"));
+        assert_eq!(route.match_path("/staticfiles/data.txt"), None);
+        assert!(route.find_file("/missing.txt").await.is_none());
+        let index = route.find_file("/docs/").await.unwrap();
+        assert!(index.ends_with("docs/index.html"));
+
+        let file = route.find_file("/data.txt").await.unwrap();
+        let req = Request::get("/static/data.txt")
+            .header(header::RANGE, "bytes=2-5")
+            .body(body::empty())
+            .unwrap();
+        let response = route.respond(&req, &file).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 2-5/10");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "max-age=60");
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        let etag = response.headers()[header::ETAG].clone();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "2345");
+
+        let req = Request::get("/static/data.txt")
+            .header(header::IF_NONE_MATCH, etag)
+            .body(body::empty())
+            .unwrap();
+        let response = route.respond(&req, &file).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+}
@@ -0,0 +1,228 @@
+//! Streaming of response bodies to clients.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use http::{header::CONTENT_LENGTH, Response};
+use http_body_util::BodyExt;
+use hyper::body::{Body as _, Bytes, Frame, SizeHint};
+use tokio::{
+    sync::OwnedSemaphorePermit,
+    time::{Instant, Sleep},
+};
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+
+use crate::Body;
+
+/// Prepares a response to be streamed to the client.
+///
+/// A response of unknown length, such as a stream of server-sent events, is
+/// flushed to the client after each chunk the component writes, rather than
+/// once the connection's write buffer fills. If `idle_timeout` is set, the
+/// response fails once its body has gone that long without producing a chunk.
+pub(crate) fn stream_response(
+    response: Response<Body>,
+    idle_timeout: Option<Duration>,
+) -> Response<Body> {
+    let flush_each_frame = !response.headers().contains_key(CONTENT_LENGTH)
+        && response.body().size_hint().exact().is_none();
+    if !flush_each_frame && idle_timeout.is_none() {
+        return response;
+    }
+    response.map(|body| StreamingBody::new(body, flush_each_frame, idle_timeout).boxed())
+}
+
+/// A response body which flushes each frame and enforces an idle timeout.
+///
+/// Frames are passed through as they are produced, so that when the client
+/// is slow to read, backpressure reaches the component's output stream.
+struct StreamingBody {
+    inner: Body,
+    flush_each_frame: bool,
+    /// Whether a frame has been returned which the connection has not yet
+    /// had the chance to flush.
+    flush_pending: bool,
+    idle: Option<IdleTimeout>,
+}
+
+struct IdleTimeout {
+    timeout: Duration,
+    deadline: Pin<Box<Sleep>>,
+}
+
+impl StreamingBody {
+    fn new(inner: Body, flush_each_frame: bool, idle_timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            flush_each_frame,
+            flush_pending: false,
+            idle: idle_timeout.map(|timeout| IdleTimeout {
+                timeout,
+                deadline: Box::pin(tokio::time::sleep(timeout)),
+            }),
+        }
+    }
+}
+
+impl hyper::body::Body for StreamingBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+
+        // The connection flushes what it has buffered whenever the body is
+        // not ready, so yielding once after each frame sends it immediately.
+        if this.flush_pending {
+            this.flush_pending = false;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        match Pin::new(&mut this.inner).poll_frame(cx) {
+            Poll::Ready(frame) => {
+                if let Some(idle) = &mut this.idle {
+                    idle.deadline.as_mut().reset(Instant::now() + idle.timeout);
+                }
+                this.flush_pending = this.flush_each_frame && frame.is_some();
+                Poll::Ready(frame)
+            }
+            Poll::Pending => {
+                if let Some(idle) = &mut this.idle {
+                    if idle.deadline.as_mut().poll(cx).is_ready() {
+                        tracing::warn!(
+                            "Response body produced no data for {:?}; ending the response",
+                            idle.timeout
+                        );
+                        this.idle = None;
+                        return Poll::Ready(Some(Err(ErrorCode::HttpResponseTimeout)));
+                    }
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Holds a concurrency permit until the response body has been sent, since
+/// the component may still be producing it after the response starts.
+pub(crate) fn hold_permit(
+    response: Response<Body>,
+    permit: OwnedSemaphorePermit,
+) -> Response<Body> {
+    response.map(|inner| {
+        PermitBody {
+            inner,
+            _permit: permit,
+        }
+        .boxed()
+    })
+}
+
+/// A response body which holds a concurrency permit until it is dropped.
+struct PermitBody {
+    inner: Body,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl hyper::body::Body for PermitBody {
+    type Data = Bytes;
+    type Error = ErrorCode;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.get_mut().inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{stream, StreamExt};
+    use http_body_util::StreamBody;
+
+    use super::*;
+
+    fn streamed_response(chunks: Vec<&'static [u8]>) -> Response<Body> {
+        let frames = stream::iter(chunks)
+            .map(|chunk| Ok(Frame::data(Bytes::from_static(chunk))))
+            .chain(stream::pending());
+        Response::new(BodyExt::boxed(StreamBody::new(frames)))
+    }
+
+    async fn poll_once(body: &mut Body) -> Poll<Option<Result<Frame<Bytes>, ErrorCode>>> {
+        futures::future::poll_fn(|cx| Poll::Ready(Pin::new(&mut *body).poll_frame(cx))).await
+    }
+
+    #[tokio::test]
+    async fn yields_after_each_frame_of_streamed_response() {
+        let mut body = stream_response(streamed_response(vec![b"one", b"two"]), None).into_body();
+
+        let Poll::Ready(Some(Ok(frame))) = poll_once(&mut body).await else {
+            panic!("expected first frame");
+        };
+        assert_eq!(frame.into_data().unwrap(), "one");
+        assert!(poll_once(&mut body).await.is_pending());
+        let Poll::Ready(Some(Ok(frame))) = poll_once(&mut body).await else {
+            panic!("expected second frame");
+        };
+        assert_eq!(frame.into_data().unwrap(), "two");
+    }
+
+    #[tokio::test]
+    async fn sized_response_is_not_wrapped() {
+        let response = Response::new(spin_http::body::full(Bytes::from_static(b"done")));
+        let mut body = stream_response(response, None).into_body();
+
+        let Poll::Ready(Some(Ok(_))) = poll_once(&mut body).await else {
+            panic!("expected frame");
+        };
+        assert!(matches!(poll_once(&mut body).await, Poll::Ready(None)));
+    }
+
+    #[tokio::test]
+    async fn idle_response_times_out() {
+        let response = streamed_response(vec![b"event"]);
+        let mut body = stream_response(response, Some(Duration::from_millis(10))).into_body();
+
+        assert!(body.frame().await.unwrap().is_ok());
+        let err = body.frame().await.unwrap().unwrap_err();
+        assert!(matches!(err, ErrorCode::HttpResponseTimeout));
+    }
+
+    #[tokio::test]
+    async fn permit_is_held_until_body_is_dropped() {
+        let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(1));
+        let permit = semaphore.clone().try_acquire_owned().unwrap();
+        let mut body = hold_permit(streamed_response(vec![b"event"]), permit).into_body();
+
+        assert!(body.frame().await.unwrap().is_ok());
+        assert_eq!(semaphore.available_permits(), 0);
+        drop(body);
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+}
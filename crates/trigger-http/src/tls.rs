@@ -4,31 +4,51 @@ use std::{
     path::{Path, PathBuf},
     sync::Arc,
 };
-use tokio_rustls::{rustls, TlsAcceptor};
+use tokio_rustls::rustls;
+
+use crate::acme::AcmeConfig;
 
 // TODO: dedupe with spin-factor-outbound-networking (spin-tls crate?)
 
 /// TLS configuration for the server.
 #[derive(Clone)]
-pub struct TlsConfig {
-    /// Path to TLS certificate.
-    pub cert_path: PathBuf,
-    /// Path to TLS key.
-    pub key_path: PathBuf,
+pub enum TlsConfig {
+    /// A certificate and key read from files.
+    Files {
+        /// Path to TLS certificate.
+        cert_path: PathBuf,
+        /// Path to TLS key.
+        key_path: PathBuf,
+    },
+    /// A certificate obtained, and renewed, from an ACME certificate authority.
+    Acme(AcmeConfig),
 }
 
 impl TlsConfig {
-    // Creates a TLS acceptor from server config.
-    pub(super) fn server_config(&self) -> anyhow::Result<TlsAcceptor> {
-        let certs = load_certs(&self.cert_path)?;
-        let private_key = load_key(&self.key_path)?;
-
-        let cfg = rustls::ServerConfig::builder()
-            .with_no_client_auth()
-            .with_single_cert(certs, private_key)
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
-
-        Ok(Arc::new(cfg).into())
+    // Creates a server config which offers the given ALPN protocols.
+    pub(super) async fn server_config(
+        &self,
+        alpn_protocols: Vec<Vec<u8>>,
+    ) -> anyhow::Result<Arc<rustls::ServerConfig>> {
+        let cfg = match self {
+            Self::Files {
+                cert_path,
+                key_path,
+            } => {
+                let certs = load_certs(cert_path)?;
+                let private_key = load_key(key_path)?;
+
+                let mut cfg = rustls::ServerConfig::builder()
+                    .with_no_client_auth()
+                    .with_single_cert(certs, private_key)
+                    .map_err(|e| anyhow::anyhow!("{}", e))?;
+                cfg.alpn_protocols = alpn_protocols;
+                cfg
+            }
+            Self::Acme(acme) => acme.server_config(alpn_protocols).await?,
+        };
+
+        Ok(Arc::new(cfg))
     }
 }
 
@@ -0,0 +1,326 @@
+//! Implementation for the Spin job trigger, which runs the background jobs
+//! enqueued by components.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use spin_factor_jobs::{
+    queue::{Job, JobQueue},
+    JobsFactor,
+};
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_serde::HumanDuration;
+use spin_trigger::{cli::NoCliArgs, is_execution_timeout, App, Trigger, TriggerApp};
+use spin_world::exports::spin::jobs::inbound_jobs::GuestIndices;
+use spin_world::spin::jobs::types;
+use tokio::time::MissedTickBehavior;
+use tracing::{instrument, Level};
+
+/// How often the store is checked for due jobs.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a job is claimed for when it starts to run. If the trigger stops
+/// without settling the job, it runs again after this time.
+const LEASE: Duration = Duration::from_secs(15 * 60);
+
+/// The number of attempts at a job, if a trigger does not set it.
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry of a job, if a trigger does not set it.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest delay between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Runs components for jobs enqueued through the `spin:jobs/jobs` interface.
+pub struct JobTrigger;
+
+/// Job trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Name of the jobs to run
+    job: String,
+    /// How many times to attempt a job before moving it to the dead letters
+    max_attempts: Option<u32>,
+    /// Delay before the first retry, which doubles for each later retry
+    backoff: Option<HumanDuration>,
+}
+
+impl TriggerConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.job.is_empty() {
+            bail!("`job` must not be empty");
+        }
+        if self.max_attempts == Some(0) {
+            bail!("`max_attempts` must be at least 1");
+        }
+        if self
+            .backoff
+            .as_ref()
+            .is_some_and(|b| b.duration().is_zero())
+        {
+            bail!("`backoff` must be greater than zero");
+        }
+        Ok(())
+    }
+}
+
+impl<F: RuntimeFactors> Trigger<F> for JobTrigger {
+    const TYPE: &'static str = "job";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        // Handlers share the trigger app, which the app states are borrowed from
+        let trigger_app = Arc::new(trigger_app);
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("JobTrigger depends on VariablesFactor")?;
+        let queue = trigger_app
+            .configured_app()
+            .app_state::<JobsFactor>()
+            .context("JobTrigger depends on JobsFactor")?
+            .queue()
+            .clone();
+
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+
+        // Maps <job name> -> <handler>
+        let mut handlers = HashMap::new();
+        for (trigger_id, config) in app_variables
+            .resolve_trigger_configs::<TriggerConfig>(trigger_app.app(), trigger_type)
+            .await?
+        {
+            config
+                .validate()
+                .with_context(|| format!("invalid job trigger {trigger_id:?}"))?;
+            if handlers.contains_key(&config.job) {
+                bail!(
+                    "more than one job trigger handles job {:?}; job trigger {trigger_id:?} must use another name",
+                    config.job
+                );
+            }
+            let component_id = config.component;
+            let guest_indices = GuestIndices::new(trigger_app.get_instance_pre(&component_id)?)
+                .with_context(|| {
+                    format!(
+                        "component {component_id:?} of job trigger {trigger_id:?} does not export spin:jobs/inbound-jobs"
+                    )
+                })?;
+            handlers.insert(
+                config.job,
+                Handler {
+                    component_id,
+                    guest_indices,
+                    max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+                    backoff: config
+                        .backoff
+                        .as_ref()
+                        .map(HumanDuration::duration)
+                        .unwrap_or(DEFAULT_BACKOFF),
+                },
+            );
+        }
+
+        println!(
+            "Running jobs from key-value store {:?}:",
+            queue.store_label()
+        );
+        let mut names: Vec<_> = handlers.keys().collect();
+        names.sort();
+        for name in names {
+            println!("\t{name}: [{}]", handlers[name].component_id);
+        }
+
+        Arc::new(Runner {
+            queue,
+            handlers,
+            running: Default::default(),
+            trigger_app,
+        })
+        .run()
+        .await
+    }
+}
+
+/// The settings of a single job trigger.
+struct Handler {
+    component_id: String,
+    guest_indices: GuestIndices,
+    max_attempts: u32,
+    backoff: Duration,
+}
+
+/// Claims and runs due jobs, each in its own task.
+struct Runner<F: RuntimeFactors> {
+    queue: JobQueue,
+    handlers: HashMap<String, Handler>,
+    /// The IDs of the jobs this trigger is running.
+    running: Mutex<HashSet<String>>,
+    trigger_app: Arc<TriggerApp<JobTrigger, F>>,
+}
+
+impl<F: RuntimeFactors> Runner<F> {
+    async fn run(self: Arc<Self>) -> anyhow::Result<()> {
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            poll.tick().await;
+            let jobs = match self.queue.due_jobs().await {
+                Ok(jobs) => jobs,
+                Err(err) => {
+                    tracing::warn!("Failed to list due jobs: {err:?}");
+                    continue;
+                }
+            };
+            for job in jobs {
+                // Jobs without a trigger wait for one to be added
+                if !self.handlers.contains_key(&job.name)
+                    || self.running.lock().unwrap().contains(&job.id)
+                {
+                    continue;
+                }
+                match self.queue.claim(&job, LEASE).await {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(err) => {
+                        tracing::warn!("Failed to claim job {}: {err:?}", job.id);
+                        continue;
+                    }
+                }
+                self.running.lock().unwrap().insert(job.id.clone());
+                let runner = self.clone();
+                tokio::spawn(async move {
+                    let id = job.id.clone();
+                    runner.settle(job).await;
+                    runner.running.lock().unwrap().remove(&id);
+                });
+            }
+        }
+    }
+
+    /// Runs a claimed job, then completes it if the component succeeded, or
+    /// retries or dead-letters it if not.
+    async fn settle(&self, job: Job) {
+        // Indexing is safe because only jobs with handlers are claimed
+        let handler = &self.handlers[&job.name];
+        let settled = match self.handle_job(handler, &job).await {
+            Ok(()) => self.queue.complete(&job).await,
+            Err(err) => {
+                if is_execution_timeout(&err) {
+                    self.trigger_app
+                        .record_execution_timeout("job", &handler.component_id);
+                }
+                let error = format!("{err:#}");
+                let failures = job.failures + 1;
+                if failures >= handler.max_attempts {
+                    tracing::warn!(
+                        "Job {} ({}) failed after {failures} attempts; moving it to dead letters: {error}",
+                        job.id,
+                        job.name
+                    );
+                    self.queue.dead_letter(&job, &error).await
+                } else {
+                    tracing::info!("Component {} handler failed: {error}", handler.component_id);
+                    self.queue
+                        .retry(&job, &error, retry_delay(handler.backoff, failures))
+                        .await
+                }
+            }
+        };
+        if let Err(err) = settled {
+            tracing::warn!("Failed to settle job {}: {err:?}", job.id);
+        }
+    }
+
+    #[instrument(name = "spin_trigger_job.handle_job", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("{} process", job.name),
+        otel.kind = "consumer",
+        job.id = job.id,
+        job.attempt = job.failures + 1,
+    ))]
+    async fn handle_job(&self, handler: &Handler, job: &Job) -> anyhow::Result<()> {
+        let component_id = handler.component_id.as_str();
+
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "job",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let (instance, mut store) = self
+            .trigger_app
+            .prepare(component_id)?
+            .instantiate(())
+            .await?;
+        let guest = handler.guest_indices.load(&mut store, &instance)?;
+
+        let wit_job = types::Job {
+            id: job.id.clone(),
+            name: job.name.clone(),
+            payload: job.payload.to_vec(),
+            attempt: job.failures + 1,
+        };
+        guest
+            .call_handle_job(&mut store, &wit_job)
+            .await?
+            .map_err(anyhow::Error::msg)
+            .context("job handler returned an error")
+    }
+}
+
+/// The delay before retrying a job which has failed the given number of
+/// times: the backoff, doubled for each failure after the first.
+fn retry_delay(backoff: Duration, failures: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+    backoff.saturating_mul(factor).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_limit() {
+        let backoff = Duration::from_secs(1);
+        assert_eq!(retry_delay(backoff, 1), Duration::from_secs(1));
+        assert_eq!(retry_delay(backoff, 2), Duration::from_secs(2));
+        assert_eq!(retry_delay(backoff, 4), Duration::from_secs(8));
+        assert_eq!(retry_delay(backoff, 40), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn invalid_configs_are_refused() {
+        for toml in [
+            r#"job = """#,
+            r#"job = "email"
+            max_attempts = 0"#,
+            r#"job = "email"
+            backoff = "0s""#,
+        ] {
+            let mut config: toml::Table = toml.parse().unwrap();
+            config.insert("component".into(), "worker".into());
+            let config: TriggerConfig = config.try_into().unwrap();
+            config
+                .validate()
+                .expect_err(&format!("{toml} should be invalid"));
+        }
+    }
+}
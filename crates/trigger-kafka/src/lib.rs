@@ -0,0 +1,346 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use rdkafka::consumer::{CommitMode, Consumer as _, StreamConsumer};
+use rdkafka::message::{BorrowedMessage, Headers as _, Message as _};
+use rdkafka::{ClientConfig, Offset};
+use serde::Deserialize;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{cli::NoCliArgs, is_execution_timeout, App, Trigger, TriggerApp};
+use spin_world::exports::spin::kafka::inbound_kafka::{self, GuestIndices, Header};
+use tracing::{instrument, Level};
+
+/// Runs components for messages consumed from Kafka topics.
+pub struct KafkaTrigger;
+
+/// Kafka trigger metadata.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerMetadata {
+    /// Bootstrap brokers used by triggers which do not set their own
+    #[serde(default)]
+    brokers: Vec<String>,
+}
+
+/// Kafka trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Topics to consume from
+    topics: Vec<String>,
+    /// Consumer group to join
+    group_id: String,
+    /// Optionally override bootstrap brokers for trigger
+    brokers: Option<Vec<String>>,
+    /// When to commit the offsets of consumed messages
+    #[serde(default)]
+    offset_commit: OffsetCommitPolicy,
+}
+
+/// When the trigger commits the offset of a consumed message.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum OffsetCommitPolicy {
+    /// Commit periodically in the background. Messages being handled when
+    /// the host stops may be lost.
+    Auto,
+    /// Commit once the handler has returned, whatever its result.
+    #[default]
+    AfterHandler,
+    /// Commit once the handler has succeeded. A failed message is retried
+    /// until it succeeds, holding up the rest of its partition.
+    AfterSuccess,
+}
+
+/// How long to wait before retrying a message under
+/// [`OffsetCommitPolicy::AfterSuccess`], or before receiving again after a
+/// consumer error.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+impl<F: RuntimeFactors> Trigger<F> for KafkaTrigger {
+    const TYPE: &'static str = "kafka";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        // Consumers share the trigger app, which the variables are borrowed from
+        let trigger_app = Arc::new(trigger_app);
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("KafkaTrigger depends on VariablesFactor")?;
+
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+        let metadata = trigger_app
+            .app()
+            .get_trigger_metadata::<TriggerMetadata>(trigger_type)?
+            .unwrap_or_default();
+
+        // Resolve trigger configs before starting any consumers
+        let mut consumers = Vec::new();
+        for (trigger_id, config) in trigger_app
+            .app()
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+            .into_iter()
+            .collect::<Vec<_>>()
+        {
+            let component_id = config.component;
+            let resolve = |field: &'static str, expr: String| {
+                let component_id = &component_id;
+                async move {
+                    app_variables
+                        .resolve_expression(expr.clone())
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "failed to resolve kafka trigger {field} {expr:?} for component {component_id}"
+                            )
+                        })
+                }
+            };
+
+            let mut brokers = Vec::new();
+            for broker in config.brokers.as_ref().unwrap_or(&metadata.brokers) {
+                brokers.push(resolve("broker", broker.clone()).await?);
+            }
+            if brokers.is_empty() {
+                anyhow::bail!("kafka trigger {trigger_id:?} has no brokers");
+            }
+            if let Some(broker) = brokers.iter().find(|broker| broker.contains("://")) {
+                anyhow::bail!(
+                    "kafka trigger {trigger_id:?} has invalid broker {broker:?}: expected the form `<host>:<port>`"
+                );
+            }
+            let mut topics = Vec::new();
+            for topic in &config.topics {
+                topics.push(resolve("topic", topic.clone()).await?);
+            }
+            if topics.is_empty() {
+                anyhow::bail!("kafka trigger {trigger_id:?} has no topics");
+            }
+            let group_id = resolve("group ID", config.group_id).await?;
+            if group_id.is_empty() {
+                anyhow::bail!("kafka trigger {trigger_id:?} has an empty group ID");
+            }
+
+            let guest_indices = GuestIndices::new(trigger_app.get_instance_pre(&component_id)?)
+                .with_context(|| {
+                    format!(
+                        "component {component_id:?} of kafka trigger {trigger_id:?} does not export spin:kafka/inbound-kafka"
+                    )
+                })?;
+
+            consumers.push(Consumer::new(
+                ConsumerConfig {
+                    brokers,
+                    topics,
+                    group_id,
+                    offset_commit: config.offset_commit,
+                },
+                component_id,
+                guest_indices,
+                trigger_app.clone(),
+            )?);
+        }
+
+        println!("Active consumers:");
+        for consumer in &consumers {
+            println!(
+                "\t{}/{} (group {}): [{}]",
+                consumer.config.brokers.join(","),
+                consumer.config.topics.join(","),
+                consumer.config.group_id,
+                consumer.component_id
+            );
+        }
+
+        // Wait for any task to complete
+        let tasks = consumers
+            .into_iter()
+            .map(|consumer| tokio::spawn(consumer.run()));
+        let (res, _, _) = futures::future::select_all(tasks).await;
+        res?
+    }
+}
+
+/// The resolved settings of a single Kafka trigger.
+struct ConsumerConfig {
+    brokers: Vec<String>,
+    topics: Vec<String>,
+    group_id: String,
+    offset_commit: OffsetCommitPolicy,
+}
+
+/// Consumes messages for a single Kafka trigger, handling them one at a time.
+struct Consumer<F: RuntimeFactors> {
+    config: ConsumerConfig,
+    consumer: StreamConsumer,
+    component_id: String,
+    guest_indices: GuestIndices,
+    trigger_app: Arc<TriggerApp<KafkaTrigger, F>>,
+}
+
+impl<F: RuntimeFactors> Consumer<F> {
+    fn new(
+        config: ConsumerConfig,
+        component_id: String,
+        guest_indices: GuestIndices,
+        trigger_app: Arc<TriggerApp<KafkaTrigger, F>>,
+    ) -> anyhow::Result<Self> {
+        let auto_commit = config.offset_commit == OffsetCommitPolicy::Auto;
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", config.brokers.join(","))
+            .set("group.id", &config.group_id)
+            .set("enable.auto.commit", auto_commit.to_string())
+            .create()
+            .with_context(|| {
+                format!(
+                    "Kafka trigger failed to create consumer for {}",
+                    config.brokers.join(",")
+                )
+            })?;
+        let topics = config.topics.iter().map(String::as_str).collect::<Vec<_>>();
+        consumer.subscribe(&topics).with_context(|| {
+            format!(
+                "Kafka trigger failed to subscribe to topics {:?}",
+                config.topics
+            )
+        })?;
+        Ok(Self {
+            config,
+            consumer,
+            component_id,
+            guest_indices,
+            trigger_app,
+        })
+    }
+
+    async fn run(self) -> anyhow::Result<()> {
+        loop {
+            let msg = match self.consumer.recv().await {
+                Ok(msg) => msg,
+                Err(err) => {
+                    tracing::error!(
+                        "Error receiving message for group {}: {err}",
+                        self.config.group_id
+                    );
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+
+            let result = self.handle_message(&msg).await;
+            match &result {
+                Err(err) if is_execution_timeout(err) => self
+                    .trigger_app
+                    .record_execution_timeout("kafka", &self.component_id),
+                Err(err) => {
+                    tracing::info!("Component {} handler failed: {err}", self.component_id)
+                }
+                Ok(()) => {}
+            }
+
+            match (self.config.offset_commit, result) {
+                (OffsetCommitPolicy::Auto, _) => {}
+                (OffsetCommitPolicy::AfterHandler, _)
+                | (OffsetCommitPolicy::AfterSuccess, Ok(())) => {
+                    if let Err(err) = self.consumer.commit_message(&msg, CommitMode::Async) {
+                        tracing::error!(
+                            "Error committing offset {} of {}/{}: {err}",
+                            msg.offset(),
+                            msg.topic(),
+                            msg.partition()
+                        );
+                    }
+                }
+                (OffsetCommitPolicy::AfterSuccess, Err(_)) => {
+                    // Rewind the partition so that the message is received again
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    self.consumer
+                        .seek(
+                            msg.topic(),
+                            msg.partition(),
+                            Offset::Offset(msg.offset()),
+                            RETRY_DELAY,
+                        )
+                        .with_context(|| {
+                            format!(
+                                "Kafka trigger failed to rewind {}/{} to offset {}",
+                                msg.topic(),
+                                msg.partition(),
+                                msg.offset()
+                            )
+                        })?;
+                }
+            }
+        }
+    }
+
+    #[instrument(name = "spin_trigger_kafka.handle_message", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("{} receive", msg.topic()),
+        otel.kind = "consumer",
+        messaging.operation = "receive",
+        messaging.system = "kafka"
+    ))]
+    async fn handle_message(&self, msg: &BorrowedMessage<'_>) -> anyhow::Result<()> {
+        let component_id = self.component_id.as_str();
+        tracing::trace!(
+            topic = msg.topic(),
+            partition = msg.partition(),
+            offset = msg.offset(),
+            "Received message"
+        );
+
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "kafka",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let (instance, mut store) = self
+            .trigger_app
+            .prepare(component_id)?
+            .instantiate(())
+            .await?;
+        let guest = self.guest_indices.load(&mut store, &instance)?;
+
+        guest
+            .call_handle_message(&mut store, &to_message(msg))
+            .await?
+            .context("Kafka handler returned an error")
+    }
+}
+
+fn to_message(msg: &BorrowedMessage<'_>) -> inbound_kafka::Message {
+    let headers = msg
+        .headers()
+        .map(|headers| {
+            headers
+                .iter()
+                .map(|header| Header {
+                    key: header.key.to_owned(),
+                    value: header.value.map(<[u8]>::to_vec),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    inbound_kafka::Message {
+        topic: msg.topic().to_owned(),
+        partition: msg.partition(),
+        offset: msg.offset(),
+        key: msg.key().map(<[u8]>::to_vec),
+        payload: msg.payload().unwrap_or_default().to_vec(),
+        headers,
+        timestamp: msg.timestamp().to_millis(),
+    }
+}
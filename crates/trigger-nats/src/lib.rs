@@ -0,0 +1,249 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use futures::StreamExt;
+use serde::Deserialize;
+use spin_factor_outbound_nats::runtime_config::DEFAULT_CONNECTION_LABEL;
+use spin_factor_outbound_nats::{message_from_nats, OutboundNatsFactor};
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_trigger::{cli::NoCliArgs, is_execution_timeout, App, Trigger, TriggerApp};
+use spin_world::exports::spin::nats::inbound_nats::GuestIndices;
+use tracing::{instrument, Level};
+
+/// Runs components for messages published to NATS subjects.
+pub struct NatsTrigger;
+
+/// NATS trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Subject to subscribe to, which may contain wildcards
+    subject: String,
+    /// Optionally share the subject's messages with other subscribers in a
+    /// queue group
+    queue_group: Option<String>,
+    /// Runtime config label of the connection to use
+    connection: Option<String>,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for NatsTrigger {
+    const TYPE: &'static str = "nats";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        // Subscriptions share the trigger app, which the app states are borrowed from
+        let trigger_app = Arc::new(trigger_app);
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("NatsTrigger depends on VariablesFactor")?;
+        let nats = trigger_app
+            .configured_app()
+            .app_state::<OutboundNatsFactor>()
+            .context("NatsTrigger depends on OutboundNatsFactor")?;
+
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+
+        // Maps <connection label> -> <subscriptions>
+        let mut connection_subscriptions: HashMap<String, Vec<SubscriptionConfig>> = HashMap::new();
+
+        // Resolve trigger configs before connecting to any servers
+        for (trigger_id, config) in trigger_app
+            .app()
+            .trigger_configs::<TriggerConfig>(trigger_type)?
+            .into_iter()
+            .collect::<Vec<_>>()
+        {
+            let component_id = config.component;
+
+            let subject_expr = &config.subject;
+            let subject = app_variables
+                .resolve_expression(subject_expr.clone())
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to resolve nats trigger subject {subject_expr:?} for component {component_id}"
+                    )
+                })?;
+            if subject.is_empty() {
+                anyhow::bail!("nats trigger {trigger_id:?} has an empty subject");
+            }
+
+            let queue_group = match &config.queue_group {
+                Some(queue_group_expr) => Some(
+                    app_variables
+                        .resolve_expression(queue_group_expr.clone())
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "failed to resolve nats trigger queue group {queue_group_expr:?} for component {component_id}"
+                            )
+                        })?,
+                ),
+                None => None,
+            };
+
+            let connection = config
+                .connection
+                .unwrap_or_else(|| DEFAULT_CONNECTION_LABEL.to_owned());
+            if nats.connection_config(&connection).is_none() {
+                anyhow::bail!(
+                    "nats trigger {trigger_id:?} uses connection {connection:?}, which is not configured in the runtime config"
+                );
+            }
+
+            let guest_indices = GuestIndices::new(trigger_app.get_instance_pre(&component_id)?)
+                .with_context(|| {
+                    format!(
+                        "component {component_id:?} of nats trigger {trigger_id:?} does not export spin:nats/inbound-nats"
+                    )
+                })?;
+
+            connection_subscriptions
+                .entry(connection)
+                .or_default()
+                .push(SubscriptionConfig {
+                    subject,
+                    queue_group,
+                    component_id,
+                    guest_indices,
+                });
+        }
+
+        // Connect and subscribe
+        println!("Active subscriptions:");
+        let mut subscription_tasks = Vec::new();
+        for (connection, subscriptions) in connection_subscriptions {
+            // Unwrap is safe because connections were checked above
+            let config = nats.connection_config(&connection).unwrap();
+            tracing::info!("Connecting to NATS server at {}", config.url);
+            let client = spin_factor_outbound_nats::connect(config)
+                .await
+                .context("NATS trigger failed to connect")?;
+
+            for subscription in subscriptions {
+                let subject = subscription.subject.clone();
+                let subscriber = match &subscription.queue_group {
+                    Some(queue_group) => {
+                        client
+                            .queue_subscribe(subject.clone(), queue_group.clone())
+                            .await
+                    }
+                    None => client.subscribe(subject.clone()).await,
+                }
+                .with_context(|| {
+                    format!("NATS trigger failed to subscribe to subject {subject:?}")
+                })?;
+                let queue_group = subscription
+                    .queue_group
+                    .as_ref()
+                    .map(|queue_group| format!(" (queue group {queue_group})"))
+                    .unwrap_or_default();
+                println!(
+                    "\t{connection}/{subject}{queue_group}: [{}]",
+                    subscription.component_id
+                );
+
+                let subscription = Arc::new(Subscription {
+                    config: subscription,
+                    client: client.clone(),
+                    trigger_app: trigger_app.clone(),
+                });
+                subscription_tasks.push(tokio::spawn(subscription.run(subscriber)));
+            }
+        }
+
+        // Wait for any task to complete
+        let (res, _, _) = futures::future::select_all(subscription_tasks).await;
+        res?
+    }
+}
+
+/// The resolved settings of a single NATS trigger.
+struct SubscriptionConfig {
+    subject: String,
+    queue_group: Option<String>,
+    component_id: String,
+    guest_indices: GuestIndices,
+}
+
+/// Handles the messages of a single NATS trigger's subscription, each in its
+/// own task.
+struct Subscription<F: RuntimeFactors> {
+    config: SubscriptionConfig,
+    client: async_nats::Client,
+    trigger_app: Arc<TriggerApp<NatsTrigger, F>>,
+}
+
+impl<F: RuntimeFactors> Subscription<F> {
+    async fn run(self: Arc<Self>, mut subscriber: async_nats::Subscriber) -> anyhow::Result<()> {
+        while let Some(msg) = subscriber.next().await {
+            let subscription = self.clone();
+            tokio::spawn(async move {
+                match subscription.handle_message(msg).await {
+                    Err(err) if is_execution_timeout(&err) => subscription
+                        .trigger_app
+                        .record_execution_timeout("nats", &subscription.config.component_id),
+                    Err(err) => tracing::info!(
+                        "Component {} handler failed: {err}",
+                        subscription.config.component_id
+                    ),
+                    Ok(()) => {}
+                }
+            });
+        }
+        Err(anyhow::anyhow!(
+            "subscription to {:?} ended",
+            self.config.subject
+        ))
+    }
+
+    #[instrument(name = "spin_trigger_nats.handle_message", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("{} receive", msg.subject),
+        otel.kind = "consumer",
+        messaging.operation = "receive",
+        messaging.system = "nats"
+    ))]
+    async fn handle_message(&self, msg: async_nats::Message) -> anyhow::Result<()> {
+        let component_id = self.config.component_id.as_str();
+        tracing::trace!(subject = %msg.subject, "Received message");
+
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "nats",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let (instance, mut store) = self
+            .trigger_app
+            .prepare(component_id)?
+            .instantiate(())
+            .await?;
+        let guest = self.config.guest_indices.load(&mut store, &instance)?;
+
+        let reply_to = msg.reply.clone();
+        let reply = guest
+            .call_handle_message(&mut store, &message_from_nats(msg))
+            .await?
+            .context("NATS handler returned an error")?;
+
+        if let (Some(reply_to), Some(reply)) = (reply_to, reply) {
+            self.client
+                .publish(reply_to, reply.into())
+                .await
+                .context("failed to publish reply")?;
+        }
+        Ok(())
+    }
+}
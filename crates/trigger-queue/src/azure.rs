@@ -0,0 +1,94 @@
+//! Azure Queue Storage queues.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use azure_storage::StorageCredentials;
+use azure_storage_queues::{PopReceipt, QueueClient, QueueServiceClient};
+use spin_core::async_trait;
+
+use crate::backend::{QueueBackend, ReceivedMessage};
+
+/// The most messages Azure returns from a single receive.
+const MAX_MESSAGES: usize = 32;
+
+/// Azure queues do not support long polling, so an empty queue is polled
+/// again after this delay.
+const EMPTY_POLL_DELAY: Duration = Duration::from_secs(1);
+
+/// An Azure storage queue.
+pub(crate) struct AzureQueue {
+    client: QueueClient,
+}
+
+impl AzureQueue {
+    /// Creates a client for a queue in the given storage account. If no
+    /// access key is given, credentials are read from the environment, as by
+    /// the Azure SDK's default credential chain.
+    pub fn new(account: String, queue: String, access_key: Option<String>) -> anyhow::Result<Self> {
+        let credentials = match access_key {
+            Some(key) => StorageCredentials::access_key(account.clone(), key),
+            None => {
+                StorageCredentials::token_credential(azure_identity::create_default_credential()?)
+            }
+        };
+        let client = QueueServiceClient::new(account, credentials).queue_client(queue);
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl QueueBackend for AzureQueue {
+    async fn receive(
+        &self,
+        max: usize,
+        visibility_timeout: Duration,
+    ) -> anyhow::Result<Vec<ReceivedMessage>> {
+        let response = self
+            .client
+            .get_messages()
+            .number_of_messages(max.min(MAX_MESSAGES) as u8)
+            .visibility_timeout(visibility_timeout)
+            .await
+            .context("failed to receive Azure queue messages")?;
+        if response.messages.is_empty() {
+            tokio::time::sleep(EMPTY_POLL_DELAY).await;
+        }
+        Ok(response
+            .messages
+            .into_iter()
+            .map(|message| ReceivedMessage {
+                id: message.message_id,
+                receipt: message.pop_receipt,
+                body: message.message_text.into_bytes(),
+                attributes: vec![],
+                delivery_count: message.dequeue_count.try_into().unwrap_or(u32::MAX),
+            })
+            .collect())
+    }
+
+    async fn delete(&self, id: &str, receipt: &str) -> anyhow::Result<()> {
+        self.client
+            .pop_receipt_client(PopReceipt::new(id, receipt))
+            .delete()
+            .await
+            .context("failed to delete Azure queue message")?;
+        Ok(())
+    }
+
+    async fn set_visibility(
+        &self,
+        id: &str,
+        receipt: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<String> {
+        let response = self
+            .client
+            .pop_receipt_client(PopReceipt::new(id, receipt))
+            .update(timeout)
+            .await
+            .context("failed to change Azure queue message visibility")?;
+        // Updating a message changes its pop receipt
+        Ok(response.pop_receipt)
+    }
+}
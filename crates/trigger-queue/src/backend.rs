@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use spin_core::async_trait;
+
+/// A message received from a queue, hidden from other receivers until it is
+/// settled or its visibility timeout expires.
+pub(crate) struct ReceivedMessage {
+    /// The ID the queue service assigned to the message.
+    pub id: String,
+    /// Identifies this receipt of the message, to settle it.
+    pub receipt: String,
+    pub body: Vec<u8>,
+    pub attributes: Vec<(String, String)>,
+    /// How many times the message has been received, including this time.
+    pub delivery_count: u32,
+}
+
+/// A queue in a cloud queue service.
+#[async_trait]
+pub(crate) trait QueueBackend: Send + Sync {
+    /// Receives up to `max` messages, or fewer if the service limits how many
+    /// may be received at once, hiding them from other receivers for the
+    /// visibility timeout. May wait for messages to arrive.
+    async fn receive(
+        &self,
+        max: usize,
+        visibility_timeout: Duration,
+    ) -> anyhow::Result<Vec<ReceivedMessage>>;
+
+    /// Deletes a received message from the queue.
+    async fn delete(&self, id: &str, receipt: &str) -> anyhow::Result<()>;
+
+    /// Hides a received message from other receivers for the given time from
+    /// now. A zero timeout abandons the message, making it visible again.
+    /// Returns the receipt to settle the message with from now on.
+    async fn set_visibility(
+        &self,
+        id: &str,
+        receipt: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<String>;
+}
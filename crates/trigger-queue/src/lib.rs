@@ -0,0 +1,426 @@
+//! Implementation for the Spin queue trigger, which runs components for
+//! messages in cloud queue services.
+
+mod azure;
+mod backend;
+mod sqs;
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_serde::HumanDuration;
+use spin_trigger::{cli::NoCliArgs, is_execution_timeout, App, Trigger, TriggerApp};
+use spin_world::exports::spin::queue::inbound_queue::{self, GuestIndices};
+use tokio::{sync::Semaphore, time::MissedTickBehavior};
+use tracing::{instrument, Level};
+
+use azure::AzureQueue;
+use backend::{QueueBackend, ReceivedMessage};
+use sqs::SqsQueue;
+
+/// The visibility timeout of messages, if a trigger does not set one.
+const DEFAULT_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The longest visibility timeout SQS allows.
+const MAX_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// How long to wait before receiving again after a receive fails.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Runs components for messages received from Amazon SQS or Azure Queue
+/// Storage queues.
+pub struct QueueTrigger;
+
+/// Queue trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// The queue service
+    backend: Backend,
+    /// The SQS queue URL, or the Azure queue name
+    queue: String,
+    /// The AWS region of an SQS queue, if not set in the environment
+    region: Option<String>,
+    /// The Azure storage account of the queue
+    account: Option<String>,
+    /// An Azure storage account access key, if not using the environment's
+    /// credentials
+    access_key: Option<String>,
+    /// Maximum number of messages to handle at once
+    concurrency: Option<u32>,
+    /// How long a received message is hidden from other receivers, which is
+    /// extended while the component handles it
+    visibility_timeout: Option<HumanDuration>,
+}
+
+/// A cloud queue service.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Backend {
+    /// Amazon Simple Queue Service
+    Sqs,
+    /// Azure Queue Storage
+    Azure,
+}
+
+impl TriggerConfig {
+    /// Checks that the settings apply to the backend and are in range.
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.queue.is_empty() {
+            bail!("`queue` must not be empty");
+        }
+        match self.backend {
+            Backend::Sqs => {
+                for (name, value) in [("account", &self.account), ("access_key", &self.access_key)]
+                {
+                    if value.is_some() {
+                        bail!("`{name}` applies only to the azure backend");
+                    }
+                }
+            }
+            Backend::Azure => {
+                if self.region.is_some() {
+                    bail!("`region` applies only to the sqs backend");
+                }
+                if self.account.is_none() {
+                    bail!("the azure backend requires `account`");
+                }
+            }
+        }
+        if self.concurrency == Some(0) {
+            bail!("`concurrency` must be at least 1");
+        }
+        let visibility_timeout = self.visibility_timeout();
+        if visibility_timeout < Duration::from_secs(1)
+            || visibility_timeout > MAX_VISIBILITY_TIMEOUT
+        {
+            bail!("`visibility_timeout` must be between 1 second and 12 hours");
+        }
+        Ok(())
+    }
+
+    fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(1) as usize
+    }
+
+    fn visibility_timeout(&self) -> Duration {
+        self.visibility_timeout
+            .as_ref()
+            .map(HumanDuration::duration)
+            .unwrap_or(DEFAULT_VISIBILITY_TIMEOUT)
+    }
+}
+
+impl<F: RuntimeFactors> Trigger<F> for QueueTrigger {
+    const TYPE: &'static str = "queue";
+
+    type CliArgs = NoCliArgs;
+
+    type InstanceState = ();
+
+    fn new(_cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn run(self, trigger_app: TriggerApp<Self, F>) -> anyhow::Result<()> {
+        // Receivers share the trigger app, which the variables are borrowed from
+        let trigger_app = Arc::new(trigger_app);
+        let app_variables = trigger_app
+            .configured_app()
+            .app_state::<VariablesFactor>()
+            .context("QueueTrigger depends on VariablesFactor")?;
+
+        let trigger_type = <Self as Trigger<F>>::TYPE;
+
+        // Resolve and check trigger configs before connecting to any queues
+        let trigger_configs = app_variables
+            .resolve_trigger_configs::<TriggerConfig>(trigger_app.app(), trigger_type)
+            .await?;
+        for (trigger_id, config) in &trigger_configs {
+            config
+                .validate()
+                .with_context(|| format!("invalid queue trigger {trigger_id:?}"))?;
+        }
+
+        let mut receivers = Vec::new();
+        for (trigger_id, config) in trigger_configs {
+            let component_id = config.component.clone();
+            let guest_indices = GuestIndices::new(trigger_app.get_instance_pre(&component_id)?)
+                .with_context(|| {
+                    format!(
+                        "component {component_id:?} of queue trigger {trigger_id:?} does not export spin:queue/inbound-queue"
+                    )
+                })?;
+
+            let concurrency = config.concurrency();
+            let visibility_timeout = config.visibility_timeout();
+            let (queue, system): (Box<dyn QueueBackend>, _) = match config.backend {
+                Backend::Sqs => (
+                    Box::new(SqsQueue::new(config.queue.clone(), config.region).await),
+                    "aws_sqs",
+                ),
+                Backend::Azure => {
+                    // Unwrap is safe because the config was validated above
+                    let account = config.account.unwrap();
+                    let queue = AzureQueue::new(account, config.queue.clone(), config.access_key)
+                        .with_context(|| format!("invalid queue trigger {trigger_id:?}"))?;
+                    (Box::new(queue), "azure_queue")
+                }
+            };
+
+            receivers.push(Receiver {
+                queue_name: config.queue,
+                component_id,
+                system,
+                queue,
+                concurrency: Arc::new(Semaphore::new(concurrency)),
+                visibility_timeout,
+                guest_indices,
+                trigger_app: trigger_app.clone(),
+            });
+        }
+
+        println!("Active queues:");
+        for receiver in &receivers {
+            println!(
+                "\t{}: [{}] (concurrency {})",
+                receiver.queue_name,
+                receiver.component_id,
+                receiver.concurrency.available_permits()
+            );
+        }
+
+        let receiver_tasks = receivers
+            .into_iter()
+            .map(|receiver| tokio::spawn(Arc::new(receiver).run()))
+            .collect::<Vec<_>>();
+
+        // Wait for any task to complete
+        let (res, _, _) = futures::future::select_all(receiver_tasks).await;
+        res?
+    }
+}
+
+/// Receives the messages of a single queue trigger, handling each in its own
+/// task, up to the trigger's concurrency.
+struct Receiver<F: RuntimeFactors> {
+    queue_name: String,
+    component_id: String,
+    /// The `messaging.system` of the queue service, for telemetry.
+    system: &'static str,
+    queue: Box<dyn QueueBackend>,
+    /// Holds a permit for each message being handled.
+    concurrency: Arc<Semaphore>,
+    visibility_timeout: Duration,
+    guest_indices: GuestIndices,
+    trigger_app: Arc<TriggerApp<QueueTrigger, F>>,
+}
+
+impl<F: RuntimeFactors> Receiver<F> {
+    async fn run(self: Arc<Self>) -> anyhow::Result<()> {
+        loop {
+            // Wait for a handler to be free, then receive as many messages as
+            // there are free handlers
+            let mut permits = vec![self.concurrency.clone().acquire_owned().await?];
+            while let Ok(permit) = self.concurrency.clone().try_acquire_owned() {
+                permits.push(permit);
+            }
+            let messages = match self
+                .queue
+                .receive(permits.len(), self.visibility_timeout)
+                .await
+            {
+                Ok(messages) => messages,
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to receive messages from queue {}: {err:?}",
+                        self.queue_name
+                    );
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            // Permits without a message are released here
+            for (message, permit) in messages.into_iter().zip(permits) {
+                let receiver = self.clone();
+                tokio::spawn(async move {
+                    receiver.settle(message).await;
+                    drop(permit);
+                });
+            }
+        }
+    }
+
+    /// Handles a message, keeping it hidden while the component runs, then
+    /// deletes it if the component succeeded or abandons it if not.
+    async fn settle(&self, message: ReceivedMessage) {
+        let id = message.id.clone();
+        let mut receipt = message.receipt.clone();
+
+        let extend_every = self.visibility_timeout / 2;
+        let mut extend =
+            tokio::time::interval_at(tokio::time::Instant::now() + extend_every, extend_every);
+        extend.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let handled = self.handle_message(message);
+        tokio::pin!(handled);
+        let result = loop {
+            tokio::select! {
+                result = &mut handled => break result,
+                _ = extend.tick() => {
+                    match self.queue.set_visibility(&id, &receipt, self.visibility_timeout).await {
+                        Ok(new_receipt) => receipt = new_receipt,
+                        Err(err) => tracing::warn!(
+                            "Failed to extend visibility of message {id} from queue {}: {err:?}",
+                            self.queue_name
+                        ),
+                    }
+                }
+            }
+        };
+
+        let settled = match result {
+            Ok(()) => self.queue.delete(&id, &receipt).await,
+            Err(err) => {
+                if is_execution_timeout(&err) {
+                    self.trigger_app
+                        .record_execution_timeout("queue", &self.component_id);
+                } else {
+                    tracing::info!("Component {} handler failed: {err}", self.component_id);
+                }
+                self.queue
+                    .set_visibility(&id, &receipt, Duration::ZERO)
+                    .await
+                    .map(drop)
+            }
+        };
+        if let Err(err) = settled {
+            tracing::warn!(
+                "Failed to settle message {id} from queue {}: {err:?}",
+                self.queue_name
+            );
+        }
+    }
+
+    #[instrument(name = "spin_trigger_queue.handle_message", skip_all, err(level = Level::INFO), fields(
+        otel.name = format!("{} receive", self.queue_name),
+        otel.kind = "consumer",
+        messaging.operation = "receive",
+        messaging.system = self.system,
+        messaging.message.id = message.id,
+    ))]
+    async fn handle_message(&self, message: ReceivedMessage) -> anyhow::Result<()> {
+        let component_id = self.component_id.as_str();
+        tracing::trace!(delivery_count = message.delivery_count, "Received message");
+
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "queue",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let (instance, mut store) = self
+            .trigger_app
+            .prepare(component_id)?
+            .instantiate(())
+            .await?;
+        let guest = self.guest_indices.load(&mut store, &instance)?;
+
+        let message = inbound_queue::Message {
+            id: message.id,
+            body: message.body,
+            attributes: message
+                .attributes
+                .into_iter()
+                .map(|(name, value)| inbound_queue::Attribute { name, value })
+                .collect(),
+            delivery_count: message.delivery_count,
+        };
+        guest
+            .call_handle_message(&mut store, &message)
+            .await?
+            .map_err(anyhow::Error::msg)
+            .context("queue handler returned an error")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> TriggerConfig {
+        let mut config: toml::Table = toml.parse().unwrap();
+        config.insert("component".into(), "handler".into());
+        config.try_into().unwrap()
+    }
+
+    #[test]
+    fn defaults_apply() {
+        let config = config(
+            r#"
+            backend = "sqs"
+            queue = "https://sqs.us-east-1.amazonaws.com/123456789012/orders"
+            "#,
+        );
+        config.validate().unwrap();
+        assert_eq!(config.concurrency(), 1);
+        assert_eq!(config.visibility_timeout(), DEFAULT_VISIBILITY_TIMEOUT);
+    }
+
+    #[test]
+    fn settings_must_apply_to_backend() {
+        config(
+            r#"
+            backend = "azure"
+            queue = "orders"
+            account = "myaccount"
+            access_key = "key"
+            "#,
+        )
+        .validate()
+        .unwrap();
+        for toml in [
+            r#"backend = "azure"
+            queue = "orders""#,
+            r#"backend = "azure"
+            queue = "orders"
+            account = "myaccount"
+            region = "us-east-1""#,
+            r#"backend = "sqs"
+            queue = "orders"
+            account = "myaccount""#,
+            r#"backend = "sqs"
+            queue = "orders"
+            access_key = "key""#,
+        ] {
+            config(toml)
+                .validate()
+                .expect_err(&format!("{toml} should be invalid"));
+        }
+    }
+
+    #[test]
+    fn limits_are_checked() {
+        for toml in [
+            r#"concurrency = 0"#,
+            r#"visibility_timeout = "0s""#,
+            r#"visibility_timeout = "13h""#,
+            r#"queue = """#,
+        ] {
+            let mut config: toml::Table = toml.parse().unwrap();
+            config.entry("backend").or_insert("sqs".into());
+            config.entry("queue").or_insert("orders".into());
+            config.insert("component".into(), "handler".into());
+            let config: TriggerConfig = config.try_into().unwrap();
+            config
+                .validate()
+                .expect_err(&format!("{toml} should be invalid"));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Amazon SQS queues.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_sqs::{
+    error::DisplayErrorContext,
+    types::{Message, MessageSystemAttributeName},
+    Client,
+};
+use spin_core::async_trait;
+
+use crate::backend::{QueueBackend, ReceivedMessage};
+
+/// The most messages SQS returns from a single receive.
+const MAX_MESSAGES: usize = 10;
+
+/// How long a receive waits for messages to arrive at an empty queue, which
+/// is the longest SQS allows.
+const WAIT_TIME_SECONDS: i32 = 20;
+
+/// An SQS queue. Credentials, and the region if not given, are read from
+/// the environment, as by the AWS CLI.
+///
+/// See https://docs.aws.amazon.com/cli/latest/userguide/cli-chap-authentication.html for options.
+pub(crate) struct SqsQueue {
+    client: Client,
+    queue_url: String,
+}
+
+impl SqsQueue {
+    pub async fn new(queue_url: String, region: Option<String>) -> Self {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = region {
+            loader = loader.region(Region::new(region));
+        }
+        let client = Client::new(&loader.load().await);
+        Self { client, queue_url }
+    }
+}
+
+#[async_trait]
+impl QueueBackend for SqsQueue {
+    async fn receive(
+        &self,
+        max: usize,
+        visibility_timeout: Duration,
+    ) -> anyhow::Result<Vec<ReceivedMessage>> {
+        let output = self
+            .client
+            .receive_message()
+            .queue_url(&self.queue_url)
+            .max_number_of_messages(max.min(MAX_MESSAGES) as i32)
+            .visibility_timeout(seconds(visibility_timeout))
+            .wait_time_seconds(WAIT_TIME_SECONDS)
+            .message_attribute_names("All")
+            .message_system_attribute_names(MessageSystemAttributeName::ApproximateReceiveCount)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", DisplayErrorContext(e)))
+            .context("failed to receive SQS messages")?;
+        Ok(output
+            .messages
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(received_message)
+            .collect())
+    }
+
+    async fn delete(&self, _id: &str, receipt: &str) -> anyhow::Result<()> {
+        self.client
+            .delete_message()
+            .queue_url(&self.queue_url)
+            .receipt_handle(receipt)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", DisplayErrorContext(e)))
+            .context("failed to delete SQS message")?;
+        Ok(())
+    }
+
+    async fn set_visibility(
+        &self,
+        _id: &str,
+        receipt: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<String> {
+        self.client
+            .change_message_visibility()
+            .queue_url(&self.queue_url)
+            .receipt_handle(receipt)
+            .visibility_timeout(seconds(timeout))
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", DisplayErrorContext(e)))
+            .context("failed to change SQS message visibility")?;
+        Ok(receipt.to_owned())
+    }
+}
+
+fn received_message(message: Message) -> Option<ReceivedMessage> {
+    // A message cannot be settled without its receipt handle
+    let receipt = message.receipt_handle()?.to_owned();
+    let delivery_count = message
+        .attributes()
+        .and_then(|attributes| attributes.get(&MessageSystemAttributeName::ApproximateReceiveCount))
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(1);
+    let mut attributes: Vec<_> = message
+        .message_attributes()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| Some((name.clone(), value.string_value()?.to_owned())))
+        .collect();
+    attributes.sort();
+    Some(ReceivedMessage {
+        id: message.message_id().unwrap_or_default().to_owned(),
+        receipt,
+        body: message.body().unwrap_or_default().as_bytes().to_vec(),
+        attributes,
+        delivery_count,
+    })
+}
+
+/// SQS timeouts are in whole seconds, so this rounds up.
+fn seconds(duration: Duration) -> i32 {
+    duration.as_secs_f64().ceil() as i32
+}
@@ -6,7 +6,7 @@ use redis::{Client, Msg};
 use serde::Deserialize;
 use spin_factor_variables::VariablesFactor;
 use spin_factors::RuntimeFactors;
-use spin_trigger::{cli::NoCliArgs, App, Trigger, TriggerApp};
+use spin_trigger::{cli::NoCliArgs, is_execution_timeout, App, Trigger, TriggerApp};
 use spin_world::exports::fermyon::spin::inbound_redis;
 use tracing::{instrument, Level};
 
@@ -188,7 +188,12 @@ impl<F: RuntimeFactors> Subscriber<F> {
             tracing::trace!("Executing Redis component {component_id}");
             self.dispatch_handler(&msg, component_id)
                 .inspect_err(move |err| {
-                    tracing::info!("Component {component_id} handler failed: {err}");
+                    if is_execution_timeout(err) {
+                        self.trigger_app
+                            .record_execution_timeout("redis", component_id);
+                    } else {
+                        tracing::info!("Component {component_id} handler failed: {err}");
+                    }
                 })
         });
         futures::future::join_all(dispatch_futures).await;
@@ -0,0 +1,220 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use anyhow::Result;
+use spin_core::wasmtime::component::Resource;
+use spin_world::spin::tcp::types::{self, Error};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::Instant,
+};
+
+/// The most bytes returned by a single read.
+const MAX_READ_LEN: u32 = 64 * 1024;
+
+/// An accepted connection, with the limits of its trigger.
+pub(crate) struct Connection {
+    stream: TcpStream,
+    peer_addr: SocketAddr,
+    local_addr: SocketAddr,
+    idle_timeout: Duration,
+    /// When the connection must close, if it may only stay open for a time.
+    deadline: Option<Instant>,
+}
+
+impl Connection {
+    pub fn new(
+        stream: TcpStream,
+        peer_addr: SocketAddr,
+        idle_timeout: Duration,
+        deadline: Option<Instant>,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            local_addr: stream.local_addr()?,
+            stream,
+            peer_addr,
+            idle_timeout,
+            deadline,
+        })
+    }
+
+    /// The time by which the next read or write must finish.
+    fn io_deadline(&self) -> Instant {
+        let idle_deadline = Instant::now() + self.idle_timeout;
+        self.deadline
+            .map_or(idle_deadline, |deadline| deadline.min(idle_deadline))
+    }
+
+    pub async fn read(&mut self, max_len: u32) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![0; max_len.min(MAX_READ_LEN) as usize];
+        if buf.is_empty() {
+            return Ok(buf);
+        }
+        let deadline = self.io_deadline();
+        let len = with_deadline(deadline, self.stream.read(&mut buf)).await?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    pub async fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        let deadline = self.io_deadline();
+        with_deadline(deadline, self.stream.write_all(data)).await
+    }
+
+    pub async fn shutdown_write(&mut self) -> Result<(), Error> {
+        let deadline = self.io_deadline();
+        with_deadline(deadline, self.stream.shutdown()).await
+    }
+}
+
+async fn with_deadline<T>(
+    deadline: Instant,
+    io: impl std::future::Future<Output = io::Result<T>>,
+) -> Result<T, Error> {
+    match tokio::time::timeout_at(deadline, io).await {
+        Ok(result) => result.map_err(io_error),
+        Err(_) => Err(Error::TimedOut),
+    }
+}
+
+/// Converts an error for a function which cannot return one into a trap.
+fn trap(err: Error) -> anyhow::Error {
+    anyhow::anyhow!("{err:?}")
+}
+
+fn io_error(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::BrokenPipe
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::UnexpectedEof => Error::Closed,
+        _ => Error::Other(err.to_string()),
+    }
+}
+
+/// The TCP trigger's state of an instance: the connection it handles.
+pub struct InstanceState {
+    connections: spin_resource_table::Table<Connection>,
+}
+
+impl InstanceState {
+    /// Creates the state of an instance handling the given connection, and
+    /// the resource to pass to the instance for it.
+    pub(crate) fn new(connection: Connection) -> (Self, Resource<types::Connection>) {
+        let mut connections = spin_resource_table::Table::new(1);
+        let rep = connections
+            .push(connection)
+            .expect("a new table should have room for a connection");
+        (Self { connections }, Resource::new_own(rep))
+    }
+
+    fn get_conn(
+        &mut self,
+        connection: &Resource<types::Connection>,
+    ) -> Result<&mut Connection, Error> {
+        self.connections
+            .get_mut(connection.rep())
+            .ok_or_else(|| Error::Other("could not find connection for resource".into()))
+    }
+}
+
+impl types::Host for InstanceState {
+    fn convert_error(&mut self, error: Error) -> Result<Error> {
+        Ok(error)
+    }
+}
+
+impl types::HostConnection for InstanceState {
+    async fn peer_address(&mut self, connection: Resource<types::Connection>) -> Result<String> {
+        let connection = self.get_conn(&connection).map_err(trap)?;
+        Ok(connection.peer_addr.to_string())
+    }
+
+    async fn local_address(&mut self, connection: Resource<types::Connection>) -> Result<String> {
+        let connection = self.get_conn(&connection).map_err(trap)?;
+        Ok(connection.local_addr.to_string())
+    }
+
+    async fn read(
+        &mut self,
+        connection: Resource<types::Connection>,
+        max_len: u32,
+    ) -> Result<Vec<u8>, Error> {
+        self.get_conn(&connection)?.read(max_len).await
+    }
+
+    async fn write(
+        &mut self,
+        connection: Resource<types::Connection>,
+        data: Vec<u8>,
+    ) -> Result<(), Error> {
+        self.get_conn(&connection)?.write(&data).await
+    }
+
+    async fn shutdown_write(
+        &mut self,
+        connection: Resource<types::Connection>,
+    ) -> Result<(), Error> {
+        self.get_conn(&connection)?.shutdown_write().await
+    }
+
+    async fn drop(&mut self, connection: Resource<types::Connection>) -> Result<()> {
+        self.connections.remove(connection.rep());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Returns a connection accepted from a new client, and the client.
+    async fn connect(idle_timeout: Duration, timeout: Option<Duration>) -> (Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, peer_addr) = listener.accept().await.unwrap();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let connection = Connection::new(stream, peer_addr, idle_timeout, deadline).unwrap();
+        (connection, client)
+    }
+
+    #[tokio::test]
+    async fn reads_and_writes_until_the_peer_finishes() {
+        let (mut connection, mut client) = connect(Duration::from_secs(5), None).await;
+        client.write_all(b"HELO example.com\r\n").await.unwrap();
+        client.shutdown().await.unwrap();
+        assert_eq!(connection.read(4).await.unwrap(), b"HELO");
+        assert_eq!(connection.read(64).await.unwrap(), b" example.com\r\n");
+        assert_eq!(connection.read(64).await.unwrap(), b"");
+
+        connection.write(b"250 OK\r\n").await.unwrap();
+        connection.shutdown_write().await.unwrap();
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).await.unwrap();
+        assert_eq!(reply, "250 OK\r\n");
+    }
+
+    #[tokio::test]
+    async fn idle_and_open_connections_time_out() {
+        let idle_timeout = Duration::from_millis(300);
+        let (mut connection, _client) = connect(idle_timeout, None).await;
+        let started = Instant::now();
+        assert!(matches!(connection.read(64).await, Err(Error::TimedOut)));
+        assert!(started.elapsed() >= idle_timeout);
+
+        let (mut connection, mut client) =
+            connect(idle_timeout, Some(Duration::from_millis(400))).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        client.write_all(b"ping").await.unwrap();
+        assert_eq!(connection.read(64).await.unwrap(), b"ping");
+        // The connection may stay open for less than the idle timeout more
+        let started = Instant::now();
+        assert!(matches!(connection.read(64).await, Err(Error::TimedOut)));
+        assert!(started.elapsed() < idle_timeout);
+    }
+}
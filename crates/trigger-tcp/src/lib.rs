@@ -0,0 +1,125 @@
+//! Implementation for the Spin TCP trigger, which hands each connection
+//! accepted on a trigger's port to its component.
+
+mod connection;
+mod server;
+
+use std::{net::IpAddr, time::Duration};
+
+use anyhow::bail;
+use clap::Args;
+use serde::Deserialize;
+use spin_core::{wasmtime::component::HasSelf, Linker};
+use spin_factors::RuntimeFactors;
+use spin_serde::HumanDuration;
+use spin_trigger::{App, Trigger};
+
+use connection::InstanceState;
+use server::TcpServer;
+
+/// A [`spin_trigger::TriggerApp`] for the TCP trigger.
+pub(crate) type TriggerApp<F> = spin_trigger::TriggerApp<TcpTrigger, F>;
+
+/// How long a connection may go without reading or writing, if a trigger
+/// does not set it.
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Args)]
+pub struct CliArgs {
+    /// IP address to listen on for TCP connections, on each trigger's port
+    #[clap(
+        long = "tcp-listen",
+        env = "SPIN_TCP_LISTEN_ADDR",
+        default_value = "127.0.0.1"
+    )]
+    pub address: IpAddr,
+}
+
+/// TCP trigger configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerConfig {
+    /// Component ID to invoke
+    component: String,
+    /// Port to accept connections on
+    port: u16,
+    /// How long a connection may go without reading or writing data
+    idle_timeout: Option<HumanDuration>,
+    /// How long a connection may stay open
+    timeout: Option<HumanDuration>,
+}
+
+impl TriggerConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.port == 0 {
+            bail!("`port` must be from 1 to 65535");
+        }
+        for (field, duration) in [
+            ("idle_timeout", &self.idle_timeout),
+            ("timeout", &self.timeout),
+        ] {
+            if duration.as_ref().is_some_and(|d| d.duration().is_zero()) {
+                bail!("`{field}` must be greater than zero");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs components for connections accepted on TCP ports.
+pub struct TcpTrigger {
+    /// The address the trigger's ports are opened on.
+    listen_ip: IpAddr,
+}
+
+impl<F: RuntimeFactors> Trigger<F> for TcpTrigger {
+    const TYPE: &'static str = "tcp";
+
+    type CliArgs = CliArgs;
+    type InstanceState = InstanceState;
+
+    fn new(cli_args: Self::CliArgs, _app: &App) -> anyhow::Result<Self> {
+        Ok(Self {
+            listen_ip: cli_args.address,
+        })
+    }
+
+    fn add_to_linker(
+        &mut self,
+        linker: &mut Linker<spin_factors_executor::InstanceState<F::InstanceState, InstanceState>>,
+    ) -> anyhow::Result<()> {
+        spin_world::spin::tcp::types::add_to_linker::<_, HasSelf<InstanceState>>(linker, |state| {
+            state.executor_instance_state_mut()
+        })
+    }
+
+    async fn run(self, trigger_app: TriggerApp<F>) -> anyhow::Result<()> {
+        TcpServer::new(self.listen_ip, trigger_app)
+            .await?
+            .serve()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_configs_are_refused() {
+        for toml in [
+            "port = 0",
+            r#"port = 2525
+            idle_timeout = "0s""#,
+            r#"port = 2525
+            timeout = "0ms""#,
+        ] {
+            let mut config: toml::Table = toml.parse().unwrap();
+            config.insert("component".into(), "echo".into());
+            let config: TriggerConfig = config.try_into().unwrap();
+            config
+                .validate()
+                .expect_err(&format!("{toml} should be invalid"));
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use spin_app::APP_DESCRIPTION_KEY;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_serde::HumanDuration;
+use spin_trigger::is_execution_timeout;
+use spin_world::exports::spin::tcp::inbound_tcp::GuestIndices;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
+    time::Instant,
+};
+use tracing::Instrument;
+
+use crate::{
+    connection::{Connection, InstanceState},
+    TriggerApp, TriggerConfig, DEFAULT_IDLE_TIMEOUT,
+};
+
+/// Accepts connections on the ports of an app's TCP triggers.
+pub(crate) struct TcpServer<F: RuntimeFactors> {
+    listen_ip: IpAddr,
+    /// Maps <port> -> <the component handling its connections>
+    handlers: HashMap<u16, Handler>,
+    /// The app being triggered.
+    trigger_app: TriggerApp<F>,
+}
+
+/// The settings of a single TCP trigger.
+struct Handler {
+    component_id: String,
+    guest_indices: GuestIndices,
+    idle_timeout: Duration,
+    timeout: Option<Duration>,
+}
+
+impl<F: RuntimeFactors> TcpServer<F> {
+    /// Create a new [`TcpServer`].
+    ///
+    /// If the app's factors include variables, variable templates in the
+    /// trigger configs are resolved.
+    pub(crate) async fn new(listen_ip: IpAddr, trigger_app: TriggerApp<F>) -> anyhow::Result<Self> {
+        let trigger_configs = match trigger_app.configured_app().app_state::<VariablesFactor>() {
+            Ok(variables) => {
+                variables
+                    .resolve_trigger_configs::<TriggerConfig>(trigger_app.app(), "tcp")
+                    .await?
+            }
+            Err(_) => trigger_app
+                .app()
+                .trigger_configs::<TriggerConfig>("tcp")?
+                .into_iter()
+                .map(|(id, config)| (id.to_owned(), config))
+                .collect(),
+        };
+
+        let mut handlers = HashMap::new();
+        for (trigger_id, config) in trigger_configs {
+            config
+                .validate()
+                .with_context(|| format!("invalid tcp trigger {trigger_id:?}"))?;
+            if handlers.contains_key(&config.port) {
+                bail!(
+                    "more than one tcp trigger uses port {}; tcp trigger {trigger_id:?} must use another port",
+                    config.port
+                );
+            }
+            let component_id = config.component;
+            let guest_indices = GuestIndices::new(trigger_app.get_instance_pre(&component_id)?)
+                .with_context(|| {
+                    format!(
+                        "component {component_id:?} of tcp trigger {trigger_id:?} does not export spin:tcp/inbound-tcp"
+                    )
+                })?;
+            handlers.insert(
+                config.port,
+                Handler {
+                    component_id,
+                    guest_indices,
+                    idle_timeout: config
+                        .idle_timeout
+                        .as_ref()
+                        .map(HumanDuration::duration)
+                        .unwrap_or(DEFAULT_IDLE_TIMEOUT),
+                    timeout: config.timeout.as_ref().map(HumanDuration::duration),
+                },
+            );
+        }
+
+        Ok(Self {
+            listen_ip,
+            handlers,
+            trigger_app,
+        })
+    }
+
+    /// Accept connections on every trigger's port until a listener fails.
+    pub async fn serve(self) -> anyhow::Result<()> {
+        let server = Arc::new(self);
+        let mut ports: Vec<_> = server.handlers.keys().copied().collect();
+        ports.sort();
+
+        let mut listeners = Vec::with_capacity(ports.len());
+        for port in ports {
+            let addr = SocketAddr::new(server.listen_ip, port);
+            let listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Unable to listen on {addr}"))?;
+            listeners.push((port, listener));
+        }
+        server.print_startup_msgs(&listeners)?;
+
+        let mut accepting = JoinSet::new();
+        for (port, listener) in listeners {
+            accepting.spawn(server.clone().accept(port, listener));
+        }
+        match accepting.join_next().await {
+            Some(result) => result?,
+            None => Ok(()),
+        }
+    }
+
+    async fn accept(self: Arc<Self>, port: u16, listener: TcpListener) -> anyhow::Result<()> {
+        loop {
+            let (stream, peer_addr) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move { server.handle(port, stream, peer_addr).await });
+        }
+    }
+
+    /// Runs the component handling a connection, and closes it when the
+    /// component returns.
+    async fn handle(&self, port: u16, stream: TcpStream, peer_addr: SocketAddr) {
+        // Indexing is safe because only trigger ports are listened on
+        let handler = &self.handlers[&port];
+        let component_id = handler.component_id.as_str();
+        let span = tracing::info_span!(
+            "spin_trigger_tcp.handle_connection",
+            "otel.kind" = "server",
+            "otel.name" = format!("tcp {port}"),
+            "network.transport" = "tcp",
+            "network.peer.address" = %peer_addr.ip(),
+            "network.peer.port" = %peer_addr.port(),
+            "server.port" = port,
+        );
+        async {
+            tracing::info!("Accepted connection from {peer_addr}");
+            match self.handle_connection(handler, stream, peer_addr).await {
+                Ok(()) => {}
+                Err(err) if is_execution_timeout(&err) => {
+                    self.trigger_app
+                        .record_execution_timeout("tcp", component_id);
+                    tracing::error!("Component {component_id} exceeded its execution timeout");
+                }
+                Err(err) => {
+                    tracing::error!("Component {component_id} failed to handle connection: {err:?}")
+                }
+            }
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn handle_connection(
+        &self,
+        handler: &Handler,
+        stream: TcpStream,
+        peer_addr: SocketAddr,
+    ) -> anyhow::Result<()> {
+        let component_id = handler.component_id.as_str();
+
+        spin_telemetry::metrics::monotonic_counter!(
+            spin.request_count = 1,
+            trigger_type = "tcp",
+            app_id = self.trigger_app.app().id(),
+            component_id = component_id
+        );
+
+        let deadline = handler.timeout.map(|timeout| Instant::now() + timeout);
+        let connection = Connection::new(stream, peer_addr, handler.idle_timeout, deadline)?;
+        let (state, connection) = InstanceState::new(connection);
+
+        let handle = async {
+            let (instance, mut store) = self
+                .trigger_app
+                .prepare(component_id)?
+                .instantiate(state)
+                .await?;
+            let guest = handler.guest_indices.load(&mut store, &instance)?;
+            guest
+                .call_handle_connection(&mut store, connection)
+                .await?
+                .map_err(anyhow::Error::msg)
+                .context("connection handler returned an error")
+        };
+        match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, handle)
+                .await
+                .unwrap_or_else(|_| {
+                    tracing::info!("Closing connection from {peer_addr} at its timeout");
+                    Ok(())
+                }),
+            None => handle.await,
+        }
+    }
+
+    fn print_startup_msgs(&self, listeners: &[(u16, TcpListener)]) -> anyhow::Result<()> {
+        terminal::step!("\nServing", "TCP on {}", self.listen_ip);
+        println!("Available Ports:");
+        for (port, listener) in listeners {
+            let local_addr = listener.local_addr()?;
+            tracing::info!("Serving TCP on {local_addr}");
+
+            let component_id = &self.handlers[port].component_id;
+            println!("  {component_id}: {local_addr}");
+            if let Some(component) = self.trigger_app.app().get_component(component_id) {
+                if let Some(description) = component.get_metadata(APP_DESCRIPTION_KEY)? {
+                    println!("    {description}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
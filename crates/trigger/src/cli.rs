@@ -1,10 +1,17 @@
+mod admin;
+mod component_logs;
+mod control;
 mod initial_kv_setter;
+mod invocation_context;
 mod launch_metadata;
 mod max_instance_memory;
 mod sqlite_statements;
 mod stdio;
 mod summary;
+mod tls;
+mod variables;
 
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::{future::Future, sync::Arc};
 
@@ -17,16 +24,28 @@ use spin_common::url::parse_file_url;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::{ComponentLoader, FactorsExecutor};
 
-use crate::{loader::ComponentLoader as ComponentLoaderImpl, Trigger, TriggerApp};
+use crate::{
+    loader::ComponentLoader as ComponentLoaderImpl,
+    redeploy::{self, AppLoader},
+    Trigger, TriggerApp,
+};
+pub use component_logs::{ComponentLogsConfig, LogFormat, LogLevel};
 pub use initial_kv_setter::InitialKvSetterHook;
+pub use invocation_context::InvocationContextHook;
 pub use launch_metadata::LaunchMetadata;
 pub use max_instance_memory::MaxInstanceMemoryHook;
 pub use sqlite_statements::SqlStatementExecutorHook;
 use stdio::FollowComponents;
 pub use stdio::StdioLoggingExecutorHooks;
 pub use summary::{KeyValueDefaultStoreSummaryHook, SqliteDefaultStoreSummaryHook};
+pub use tls::{
+    TlsRuntimeConfig, SPIN_ACME_CACHE_DIR, SPIN_ACME_CHALLENGE, SPIN_ACME_CONTACT,
+    SPIN_ACME_DIRECTORY, SPIN_ACME_DOMAINS, SPIN_ACME_HTTP_LISTEN, SPIN_TLS_CERT, SPIN_TLS_KEY,
+};
+pub use variables::{VariablesSnapshotHook, VariablesValidationHook};
 
 pub const APP_LOG_DIR: &str = "APP_LOG_DIR";
+pub const COMPILED_CACHE_DIR: &str = "SPIN_COMPILED_CACHE_DIR";
 pub const DISABLE_WASMTIME_CACHE: &str = "DISABLE_WASMTIME_CACHE";
 pub const FOLLOW_LOG_OPT: &str = "FOLLOW_ID";
 pub const WASMTIME_CACHE_FILE: &str = "WASMTIME_CACHE_FILE";
@@ -36,6 +55,9 @@ pub const RUNTIME_CONFIG_FILE: &str = "RUNTIME_CONFIG_FILE";
 pub const SPIN_LOCKED_URL: &str = "SPIN_LOCKED_URL";
 pub const SPIN_LOCAL_APP_DIR: &str = "SPIN_LOCAL_APP_DIR";
 pub const SPIN_WORKING_DIR: &str = "SPIN_WORKING_DIR";
+pub const SPIN_ADMIN_LISTEN: &str = "SPIN_ADMIN_LISTEN";
+pub const SPIN_CONTROL_SOCKET: &str = "SPIN_CONTROL_SOCKET";
+pub const SPIN_CONTROL_LISTEN: &str = "SPIN_CONTROL_LISTEN";
 
 /// A command that runs a TriggerExecutor.
 #[derive(Parser, Debug)]
@@ -73,10 +95,31 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     )]
     pub cache: Option<PathBuf>,
 
+    /// Directory in which to keep compiled components, so that later runs
+    /// need not compile them again. The directory may be shared by different
+    /// versions of Spin and by other hosts, but must be trusted: its contents
+    /// are loaded as native code.
+    #[clap(
+        name = COMPILED_CACHE_DIR,
+        long = "compiled-cache-dir",
+        env = COMPILED_CACHE_DIR,
+    )]
+    pub compiled_cache_dir: Option<PathBuf>,
+
     /// Disable Wasmtime's pooling instance allocator.
     #[clap(long = "disable-pooling")]
     pub disable_pooling: bool,
 
+    /// Meter the fuel, roughly the Wasm instructions, consumed by each
+    /// invocation, and report it with the invocation's memory use. This
+    /// slows execution somewhat.
+    #[clap(
+        long = "fuel-metering",
+        env = "SPIN_FUEL_METERING",
+        takes_value = false
+    )]
+    pub fuel_metering: bool,
+
     /// Print output to stdout/stderr only for given component(s)
     #[clap(
         name = FOLLOW_LOG_OPT,
@@ -102,6 +145,25 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
     )]
     pub runtime_config_file: Option<PathBuf>,
 
+    /// Address on which to serve the `/healthz` and `/readyz` admin
+    /// endpoints. Readiness runs the health checks of components which
+    /// declare one.
+    #[clap(long = "admin-listen", env = SPIN_ADMIN_LISTEN)]
+    pub admin_listen: Option<SocketAddr>,
+
+    /// Path of a Unix socket on which to accept control commands, one per
+    /// line: `components`, `variables`, `health`, `metrics`,
+    /// `log-level <directives> | reset`, `reload`, and
+    /// `redeploy <locked app path>` to switch new work to a new version of
+    /// the app while work underway finishes on the current one.
+    #[clap(long = "control-socket", env = SPIN_CONTROL_SOCKET)]
+    pub control_socket: Option<PathBuf>,
+
+    /// Loopback address on which to accept the same control commands as
+    /// `--control-socket`.
+    #[clap(long = "control-listen", env = SPIN_CONTROL_LISTEN)]
+    pub control_listen: Option<SocketAddr>,
+
     /// Set the application state directory path. This is used in the default
     /// locations for logs, key value stores, etc.
     ///
@@ -122,6 +184,11 @@ pub struct FactorsTriggerCommand<T: Trigger<B::Factors>, B: RuntimeFactorsBuilde
 
     #[clap(long = "launch-metadata-only", hide = true)]
     pub launch_metadata_only: bool,
+
+    /// Compile the app's components into the compiled cache directory, then
+    /// exit without running the app.
+    #[clap(long = "precompile-only", hide = true, requires = COMPILED_CACHE_DIR)]
+    pub precompile_only: bool,
 }
 
 /// Configuration options that are common to all triggers.
@@ -146,7 +213,12 @@ pub struct FactorsConfig {
 #[derive(Args)]
 pub struct NoCliArgs;
 
-impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T, B> {
+impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder + 'static> FactorsTriggerCommand<T, B>
+where
+    T: 'static,
+    T::InstanceState: Default,
+    B::CliArgs: Send + Sync + 'static,
+{
     /// Create a new TriggerExecutorBuilder from this TriggerExecutorCommand.
     pub async fn run(self) -> Result<()> {
         // Handle --help-args-only
@@ -180,7 +252,7 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
                 .with_context(|| format!("failed to read manifest at {}", quoted_path(&path)))?;
             let locked =
                 serde_json::from_slice(&contents).context("failed to parse app lock file JSON")?;
-            App::new(locked_url, locked)
+            App::new(locked_url.clone(), locked)
         };
 
         // Validate required host features
@@ -201,6 +273,20 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
             config.disable_pooling();
         }
 
+        if self.fuel_metering {
+            config.enable_fuel_metering();
+        }
+
+        let mut loader = ComponentLoaderImpl::new();
+        if let Some(dir) = &self.compiled_cache_dir {
+            loader.enable_compiled_cache(dir);
+        }
+
+        // Handle --precompile-only
+        if self.precompile_only {
+            return builder.precompile(&app, &loader).await;
+        }
+
         let state_dir = match &self.state_dir {
             // Make sure `--state-dir=""` unsets the state dir
             Some(s) if s.is_empty() => UserProvidedPath::Unset,
@@ -222,14 +308,58 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> FactorsTriggerCommand<T,
             log_dir,
         };
 
-        let run_fut = builder
-            .run(
-                app,
-                common_options,
-                self.builder_args,
-                &ComponentLoaderImpl::new(),
-            )
-            .await?;
+        let control_enabled = self.control_socket.is_some() || self.control_listen.is_some();
+        let (trigger_app, app_loader) = if control_enabled {
+            let (trigger_app, app_loader) = builder
+                .build_redeployable(app, common_options, self.builder_args, loader)
+                .await?;
+            (trigger_app, Some(app_loader))
+        } else {
+            let trigger_app = builder
+                .build(app, common_options, self.builder_args, &loader)
+                .await?;
+            (trigger_app, None)
+        };
+
+        let health_checks = if self.admin_listen.is_some() || control_enabled {
+            Some(Arc::new(admin::HealthChecks::new(&trigger_app)?))
+        } else {
+            None
+        };
+        if let (Some(addr), Some(health_checks)) = (self.admin_listen, &health_checks) {
+            let addr = admin::serve(addr, health_checks.clone()).await?;
+            println!("Serving admin endpoints on http://{addr}");
+        }
+
+        let redeploys = match (app_loader, health_checks) {
+            (Some(app_loader), Some(health_checks)) => {
+                let (redeployer, redeploys) = redeploy::channel();
+                let controller = Arc::new(control::Controller::new(
+                    locked_url,
+                    &trigger_app,
+                    app_loader,
+                    redeployer,
+                    health_checks,
+                ));
+                if let Some(path) = &self.control_socket {
+                    controller.clone().serve_unix(path)?;
+                    println!("Accepting control commands on {}", quoted_path(path));
+                }
+                if let Some(addr) = self.control_listen {
+                    let addr = controller.serve_tcp(addr).await?;
+                    println!("Accepting control commands on {addr}");
+                }
+                Some(redeploys)
+            }
+            _ => None,
+        };
+
+        let run_fut = match redeploys {
+            Some(redeploys) => futures::future::Either::Left(
+                builder.trigger.run_redeployable(trigger_app, redeploys),
+            ),
+            None => futures::future::Either::Right(builder.trigger.run(trigger_app)),
+        };
 
         let (abortable, abort_handle) = futures::future::abortable(run_fut);
         ctrlc::set_handler(move || abort_handle.abort())?;
@@ -313,27 +443,82 @@ impl<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder> TriggerAppBuilder<T, B> {
         options: B::CliArgs,
         loader: &impl ComponentLoader<B::Factors, T::InstanceState>,
     ) -> anyhow::Result<TriggerApp<T, B::Factors>> {
-        let mut core_engine_builder = {
-            self.trigger.update_core_config(&mut self.engine_config)?;
+        let (executor, runtime_config) = self.build_executor(&common_options, &options)?;
 
-            spin_core::Engine::builder(&self.engine_config)?
+        let configured_app = {
+            let _sloth_guard = warn_if_wasm_build_slothful();
+            executor
+                .load_app(app, runtime_config.into(), loader)
+                .await?
         };
-        self.trigger.add_to_linker(core_engine_builder.linker())?;
 
-        let (factors, runtime_config) = B::build(&common_options, &options)?;
+        Ok(configured_app)
+    }
 
-        let mut executor = FactorsExecutor::new(core_engine_builder, factors)?;
-        B::configure_app(&mut executor, &runtime_config, &common_options, &options)?;
-        let executor = Arc::new(executor);
+    /// Build a [`TriggerApp`] as [`Self::build`] does, along with an
+    /// [`AppLoader`] which loads new versions of the app to redeploy.
+    pub async fn build_redeployable<L: ComponentLoader<B::Factors, T::InstanceState>>(
+        &mut self,
+        app: App,
+        common_options: FactorsConfig,
+        options: B::CliArgs,
+        loader: L,
+    ) -> anyhow::Result<(TriggerApp<T, B::Factors>, AppLoader<T, B, L>)> {
+        let (executor, runtime_config) = self.build_executor(&common_options, &options)?;
 
         let configured_app = {
             let _sloth_guard = warn_if_wasm_build_slothful();
             executor
-                .load_app(app, runtime_config.into(), loader)
+                .clone()
+                .load_app(app, runtime_config.into(), &loader)
                 .await?
         };
 
-        Ok(configured_app)
+        let app_loader = AppLoader::new(executor, common_options, options, loader);
+        Ok((configured_app, app_loader))
+    }
+
+    fn build_executor(
+        &mut self,
+        common_options: &FactorsConfig,
+        options: &B::CliArgs,
+    ) -> anyhow::Result<(
+        Arc<FactorsExecutor<B::Factors, T::InstanceState>>,
+        B::RuntimeConfig,
+    )> {
+        let mut core_engine_builder = {
+            self.trigger.update_core_config(&mut self.engine_config)?;
+
+            spin_core::Engine::builder(&self.engine_config)?
+        };
+        self.trigger.add_to_linker(core_engine_builder.linker())?;
+
+        let (factors, runtime_config) = B::build(common_options, options)?;
+
+        let mut executor = FactorsExecutor::new(core_engine_builder, factors)?;
+        B::configure_app(&mut executor, &runtime_config, common_options, options)?;
+        Ok((Arc::new(executor), runtime_config))
+    }
+
+    /// Compile the components of the given [`App`] as [`Self::build`] would,
+    /// for a loader which keeps the compiled components, without building the
+    /// [`TriggerApp`].
+    pub async fn precompile(
+        &mut self,
+        app: &App,
+        loader: &impl ComponentLoader<B::Factors, T::InstanceState>,
+    ) -> anyhow::Result<()> {
+        self.trigger.update_core_config(&mut self.engine_config)?;
+        let engine = spin_core::Engine::<()>::builder(&self.engine_config)?.build();
+
+        let _sloth_guard = warn_if_wasm_build_slothful();
+        for component in app.components() {
+            loader
+                .load_component(engine.as_ref(), &component)
+                .await
+                .with_context(|| format!("failed to precompile component {:?}", component.id()))?;
+        }
+        Ok(())
     }
 
     /// Run the [`TriggerApp`] with the given [`App`] and options.
@@ -0,0 +1,249 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use spin_app::MetadataKey;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{ComponentPreparer, FactorsExecutorApp};
+use spin_world::exports::spin::health::health_check::GuestIndices;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Whether a component exports a health check which readiness depends on.
+const HEALTH_CHECK_KEY: MetadataKey<bool> = MetadataKey::new("health_check");
+/// How long, in milliseconds, a component's health check may run.
+const HEALTH_CHECK_TIMEOUT_KEY: MetadataKey<u64> = MetadataKey::new("health_check_timeout_ms");
+
+const DEFAULT_HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most a request head may take up; admin requests have no body.
+const MAX_REQUEST_HEAD_LEN: usize = 8 * 1024;
+
+/// The health checks of the components of an app which declare one.
+pub(crate) struct HealthChecks<F: RuntimeFactors, U: 'static> {
+    checks: RwLock<Arc<Vec<HealthCheck<F, U>>>>,
+}
+
+struct HealthCheck<F: RuntimeFactors, U: 'static> {
+    preparer: ComponentPreparer<F, U>,
+    guest_indices: GuestIndices,
+    timeout: Duration,
+}
+
+impl<F: RuntimeFactors, U: Default + Send + 'static> HealthChecks<F, U> {
+    pub fn new(app: &FactorsExecutorApp<F, U>) -> anyhow::Result<Self> {
+        let mut checks = vec![];
+        for component in app.app().components() {
+            if !component
+                .get_metadata(HEALTH_CHECK_KEY)?
+                .unwrap_or_default()
+            {
+                continue;
+            }
+            let component_id = component.id();
+            let preparer = app.component_preparer(component_id)?;
+            let guest_indices =
+                GuestIndices::new(preparer.instance_pre()).with_context(|| {
+                    format!(
+                        "component {component_id:?} declares a health check but does not export spin:health/health-check"
+                    )
+                })?;
+            let timeout = component
+                .get_metadata(HEALTH_CHECK_TIMEOUT_KEY)?
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_HEALTH_CHECK_TIMEOUT);
+            checks.push(HealthCheck {
+                preparer,
+                guest_indices,
+                timeout,
+            });
+        }
+        Ok(Self {
+            checks: RwLock::new(Arc::new(checks)),
+        })
+    }
+
+    /// Replaces the checks with those of a new version of the app.
+    pub fn replace(&self, other: Self) {
+        let checks = other
+            .checks
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        *self.checks.write().unwrap_or_else(PoisonError::into_inner) = checks;
+    }
+
+    /// Runs every check, returning a report of readiness as `/readyz` does.
+    pub async fn report(&self) -> serde_json::Value {
+        readiness_report(&self.run().await).1
+    }
+
+    /// Runs every check at once, returning each component's result.
+    async fn run(&self) -> Vec<(String, Result<(), String>)> {
+        let checks = self
+            .checks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        futures::future::join_all(checks.iter().map(|check| async move {
+            (check.preparer.component_id().to_owned(), check.run().await)
+        }))
+        .await
+    }
+}
+
+impl<F: RuntimeFactors, U: Default + Send + 'static> HealthCheck<F, U> {
+    async fn run(&self) -> Result<(), String> {
+        match tokio::time::timeout(self.timeout, self.check()).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(err)) => Err(format!("health check failed: {err:#}")),
+            Err(_) => Err(format!("health check timed out after {:?}", self.timeout)),
+        }
+    }
+
+    async fn check(&self) -> anyhow::Result<Result<(), String>> {
+        let (instance, mut store) = self.preparer.prepare()?.instantiate(U::default()).await?;
+        let guest = self.guest_indices.load(&mut store, &instance)?;
+        guest.call_check(&mut store).await
+    }
+}
+
+/// Serves `/healthz`, which succeeds while the trigger runs, and `/readyz`,
+/// which succeeds only while the app's health checks do, on the given
+/// address. Returns the address listened on.
+pub(crate) async fn serve<F: RuntimeFactors, U: Default + Send + 'static>(
+    addr: SocketAddr,
+    health_checks: Arc<HealthChecks<F, U>>,
+) -> anyhow::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to bind admin listener on {addr}"))?;
+    let local_addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!("Failed to accept admin connection: {err}");
+                    continue;
+                }
+            };
+            let health_checks = health_checks.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handle(stream, &health_checks).await {
+                    tracing::debug!("Failed to serve admin request: {err:#}");
+                }
+            });
+        }
+    });
+    Ok(local_addr)
+}
+
+async fn handle<F: RuntimeFactors, U: Default + Send + 'static>(
+    mut stream: TcpStream,
+    health_checks: &HealthChecks<F, U>,
+) -> anyhow::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        anyhow::ensure!(head.len() < MAX_REQUEST_HEAD_LEN, "request head too long");
+        let read = stream.read(&mut buf).await?;
+        anyhow::ensure!(read > 0, "connection closed mid-request");
+        head.extend_from_slice(&buf[..read]);
+    }
+
+    let (status, body) = match request_target(&String::from_utf8_lossy(&head)) {
+        Some(("GET", "/healthz")) => (200, "ok\n".to_owned()),
+        Some(("GET", "/readyz")) => readiness(&health_checks.run().await),
+        Some((_, "/healthz" | "/readyz")) => (405, "method not allowed\n".to_owned()),
+        _ => (404, "not found\n".to_owned()),
+    };
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    };
+    let content_type = if body.starts_with('{') {
+        "application/json"
+    } else {
+        "text/plain"
+    };
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Returns the method and path of an HTTP request head.
+fn request_target(head: &str) -> Option<(&str, &str)> {
+    let mut parts = head.lines().next()?.split_whitespace();
+    let method = parts.next()?;
+    let target = parts.next()?;
+    let path = target.split('?').next().unwrap_or(target);
+    Some((method, path))
+}
+
+/// Returns the status and JSON body reporting readiness from the results of
+/// health checks.
+fn readiness(results: &[(String, Result<(), String>)]) -> (u16, String) {
+    let (ready, report) = readiness_report(results);
+    (if ready { 200 } else { 503 }, format!("{report}\n"))
+}
+
+/// Returns whether every health check passed, with a report of each
+/// component's result.
+fn readiness_report(results: &[(String, Result<(), String>)]) -> (bool, serde_json::Value) {
+    let ready = results.iter().all(|(_, result)| result.is_ok());
+    let components: serde_json::Map<_, _> = results
+        .iter()
+        .map(|(component_id, result)| {
+            let value = match result {
+                Ok(()) => serde_json::json!({ "status": "ok" }),
+                Err(err) => serde_json::json!({ "status": "failed", "error": err }),
+            };
+            (component_id.clone(), value)
+        })
+        .collect();
+    let report = serde_json::json!({
+        "status": if ready { "ready" } else { "unavailable" },
+        "components": components,
+    });
+    (ready, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_targets_are_parsed() {
+        assert_eq!(
+            request_target("GET /readyz?verbose=1 HTTP/1.1\r\nhost: x\r\n\r\n"),
+            Some(("GET", "/readyz"))
+        );
+        assert_eq!(request_target(""), None);
+    }
+
+    #[test]
+    fn readiness_requires_every_check() {
+        let (status, body) = readiness(&[]);
+        assert_eq!(status, 200);
+        assert!(body.contains(r#""status":"ready""#));
+
+        let (status, body) =
+            readiness(&[("db".into(), Ok(())), ("cache".into(), Err("down".into()))]);
+        assert_eq!(status, 503);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["components"]["db"]["status"], "ok");
+        assert_eq!(body["components"]["cache"]["error"], "down");
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use spin_common::ui::quoted_path;
+use spin_factors_executor::InvocationContext;
+
+/// Component log settings from the `[logging]` table of a runtime config file.
+///
+/// ```toml
+/// [logging]
+/// format = "json"
+/// level = "info"
+///
+/// [logging.components.api]
+/// level = "debug"
+/// file = "logs/api.jsonl"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentLogsConfig {
+    /// The format of each line written.
+    #[serde(default)]
+    format: LogFormat,
+    /// The lowest level of line written for components which do not set one.
+    level: Option<LogLevel>,
+    /// Settings for individual components, by component ID.
+    #[serde(default)]
+    components: HashMap<String, ComponentLogSettings>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ComponentLogSettings {
+    /// The lowest level of line written.
+    level: Option<LogLevel>,
+    /// The file the component's output is written to.
+    file: Option<PathBuf>,
+}
+
+/// The format of component log lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Lines as the component wrote them.
+    #[default]
+    Text,
+    /// A JSON object per line, with the component ID and invocation context.
+    Json,
+}
+
+/// The level of a component log line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+
+    /// Detects the level of a line from a leading level name, such as
+    /// `WARN something` or `[error] something`.
+    fn detect(line: &str) -> Option<Self> {
+        let line = line.trim_start();
+        let line = line.strip_prefix('[').unwrap_or(line);
+        let word_end = line
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(line.len());
+        match line[..word_end].to_ascii_lowercase().as_str() {
+            "trace" => Some(Self::Trace),
+            "debug" => Some(Self::Debug),
+            "info" => Some(Self::Info),
+            "warn" | "warning" => Some(Self::Warn),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+impl ComponentLogsConfig {
+    /// Resolves relative component log file paths against the given directory.
+    pub fn resolve_paths(&mut self, base: &Path) {
+        for settings in self.components.values_mut() {
+            if let Some(file) = &mut settings.file {
+                if file.is_relative() {
+                    *file = base.join(&*file);
+                }
+            }
+        }
+    }
+
+    /// The format of each line written.
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    /// The IDs of the components with their own settings.
+    pub(crate) fn component_ids(&self) -> impl Iterator<Item = &str> {
+        self.components.keys().map(String::as_str)
+    }
+
+    fn level(&self, component_id: &str) -> Option<LogLevel> {
+        self.components
+            .get(component_id)
+            .and_then(|settings| settings.level)
+            .or(self.level)
+    }
+
+    fn file(&self, component_id: &str) -> Option<&Path> {
+        self.components
+            .get(component_id)
+            .and_then(|settings| settings.file.as_deref())
+    }
+}
+
+/// The stream a component wrote a line to.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+
+    /// The level of lines with no level of their own.
+    fn default_level(&self) -> LogLevel {
+        match self {
+            Self::Stdout => LogLevel::Info,
+            Self::Stderr => LogLevel::Warn,
+        }
+    }
+}
+
+/// Writes a component's output a line at a time, filtered by level, in the
+/// configured format.
+pub(crate) struct ComponentLogWriter {
+    component_id: String,
+    stream: Stream,
+    format: LogFormat,
+    min_level: Option<LogLevel>,
+    context: InvocationContext,
+    /// Where lines are written; stderr if unset.
+    file: Option<std::fs::File>,
+    /// Whether lines written to a file are also written to stderr.
+    follow: bool,
+    /// The part of the last line written so far.
+    partial: Vec<u8>,
+}
+
+impl ComponentLogWriter {
+    pub fn new(
+        config: &ComponentLogsConfig,
+        component_id: &str,
+        stream: Stream,
+        log_dir: Option<&Path>,
+        follow: bool,
+        context: InvocationContext,
+    ) -> anyhow::Result<Self> {
+        let path = match config.file(component_id) {
+            Some(file) => Some(file.to_owned()),
+            None => log_dir.map(|log_dir| {
+                let sanitized_component_id = sanitize_filename::sanitize(component_id);
+                match config.format {
+                    LogFormat::Text => {
+                        log_dir.join(format!("{sanitized_component_id}_{}.txt", stream.as_str()))
+                    }
+                    LogFormat::Json => log_dir.join(format!("{sanitized_component_id}.jsonl")),
+                }
+            }),
+        };
+        let file = path
+            .map(|path| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::File::options()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .with_context(|| format!("Failed to open log file {}", quoted_path(&path)))
+            })
+            .transpose()?;
+        Ok(Self {
+            component_id: component_id.to_owned(),
+            stream,
+            format: config.format,
+            min_level: config.level(component_id),
+            context,
+            file,
+            follow,
+            partial: vec![],
+        })
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches('\r');
+        let level = LogLevel::detect(line).unwrap_or(self.stream.default_level());
+        if self.min_level.is_some_and(|min_level| level < min_level) {
+            return Ok(());
+        }
+        let rendered = match self.format {
+            LogFormat::Text => format!("{line}\n"),
+            LogFormat::Json => format!("{}\n", self.json_line(level, line)),
+        };
+        match &mut self.file {
+            Some(file) => {
+                file.write_all(rendered.as_bytes())?;
+                if self.follow {
+                    std::io::stderr().write_all(rendered.as_bytes())?;
+                }
+            }
+            None => std::io::stderr().write_all(rendered.as_bytes())?,
+        }
+        Ok(())
+    }
+
+    fn json_line(&self, level: LogLevel, message: &str) -> serde_json::Value {
+        let mut object: serde_json::Map<_, _> = self
+            .context
+            .fields()
+            .into_iter()
+            .map(|(key, value)| (key, value.into()))
+            .collect();
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        object.insert("timestamp".into(), timestamp.into());
+        object.insert("level".into(), level.as_str().into());
+        object.insert("component_id".into(), self.component_id.clone().into());
+        object.insert("stream".into(), self.stream.as_str().into());
+        object.insert("message".into(), message.into());
+        object.into()
+    }
+}
+
+impl Write for ComponentLogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        spin_telemetry::logs::handle_app_log(buf);
+
+        self.partial.extend_from_slice(buf);
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            self.write_line(&line[..end])?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => std::io::stderr().flush(),
+        }
+    }
+}
+
+impl Drop for ComponentLogWriter {
+    fn drop(&mut self) {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            let _ = self.write_line(&line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_are_detected_from_line_prefixes() {
+        assert_eq!(LogLevel::detect("WARN disk is full"), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::detect("[error] failed"), Some(LogLevel::Error));
+        assert_eq!(LogLevel::detect("  debug: x=1"), Some(LogLevel::Debug));
+        assert_eq!(
+            LogLevel::detect("Warning: deprecated"),
+            Some(LogLevel::Warn)
+        );
+        assert_eq!(LogLevel::detect("Information follows"), None);
+        assert_eq!(LogLevel::detect("errors: 0"), None);
+    }
+
+    #[test]
+    fn component_settings_override_defaults() {
+        let config: ComponentLogsConfig = serde_json::from_value(serde_json::json!({
+            "format": "json",
+            "level": "warn",
+            "components": { "api": { "level": "debug" } },
+        }))
+        .unwrap();
+        assert_eq!(config.format(), LogFormat::Json);
+        assert_eq!(config.level("api"), Some(LogLevel::Debug));
+        assert_eq!(config.level("worker"), Some(LogLevel::Warn));
+    }
+
+    #[test]
+    fn lines_are_written_as_json_with_context() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config: ComponentLogsConfig =
+            serde_json::from_value(serde_json::json!({ "format": "json", "level": "info" }))?;
+        let context = InvocationContext::default();
+        context.set("request_id", "abc");
+        let mut writer = ComponentLogWriter::new(
+            &config,
+            "api",
+            Stream::Stdout,
+            Some(dir.path()),
+            false,
+            context,
+        )?;
+        writer.write_all(b"debug: hidden\nhello ")?;
+        writer.write_all(b"world\nERROR oops")?;
+        drop(writer);
+
+        let logs = std::fs::read_to_string(dir.path().join("api.jsonl"))?;
+        let lines: Vec<serde_json::Value> = logs
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["message"], "hello world");
+        assert_eq!(lines[0]["level"], "info");
+        assert_eq!(lines[0]["component_id"], "api");
+        assert_eq!(lines[0]["stream"], "stdout");
+        assert_eq!(lines[0]["request_id"], "abc");
+        assert_eq!(lines[1]["message"], "ERROR oops");
+        assert_eq!(lines[1]["level"], "error");
+        Ok(())
+    }
+}
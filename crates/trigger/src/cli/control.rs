@@ -0,0 +1,340 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use anyhow::Context as _;
+use spin_app::{App, APP_DESCRIPTION_KEY};
+use spin_common::{ui::quoted_path, url::parse_file_url};
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{AppHandle, ComponentLoader};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::{
+    cli::{admin::HealthChecks, RuntimeFactorsBuilder},
+    redeploy::{AppLoader, Redeployer},
+    Trigger, TriggerApp,
+};
+
+/// A command read from a control connection, one per line.
+#[derive(Debug, PartialEq)]
+enum Command {
+    /// List the app's components.
+    Components,
+    /// Show the resolved values of the app's variables, with secrets redacted.
+    Variables,
+    /// Set which log messages are printed to stderr, or restore those of
+    /// `RUST_LOG` if `None`.
+    LogLevel(Option<String>),
+    /// Run the app's health checks.
+    Health,
+    /// Show the resource usage of each component.
+    Metrics,
+    /// Reload the current version of the app from where it was last loaded.
+    Reload,
+    /// Switch to the locked app at the given path or `file:` URL.
+    Redeploy(String),
+}
+
+impl Command {
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        let line = line.trim();
+        let (name, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let command = match (name, arg.trim()) {
+            ("components", "") => Self::Components,
+            ("variables", "") => Self::Variables,
+            ("health", "") => Self::Health,
+            ("metrics", "") => Self::Metrics,
+            ("reload", "") => Self::Reload,
+            ("components" | "variables" | "health" | "metrics" | "reload", _) => {
+                anyhow::bail!("{name} takes no arguments")
+            }
+            ("log-level", "") => anyhow::bail!("usage: log-level <directives> | reset"),
+            ("log-level", "reset") => Self::LogLevel(None),
+            ("log-level", directives) => Self::LogLevel(Some(directives.to_owned())),
+            ("redeploy", "") => anyhow::bail!("usage: redeploy <locked app path>"),
+            ("redeploy", locked) => Self::Redeploy(locked.to_owned()),
+            ("", _) => anyhow::bail!("no command given"),
+            (name, _) => anyhow::bail!("unknown command {name:?}"),
+        };
+        Ok(command)
+    }
+}
+
+/// Answers commands to inspect and manage the running app.
+///
+/// Each connection sends a command per line, and is answered per command
+/// with `ok`, `ok <JSON>` for commands which return something, or
+/// `error: <reason>`.
+pub(crate) struct Controller<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder, L> {
+    app_loader: AppLoader<T, B, L>,
+    redeployer: Redeployer<T, B::Factors>,
+    health_checks: Arc<HealthChecks<B::Factors, T::InstanceState>>,
+    current: RwLock<CurrentApp<B::Factors>>,
+}
+
+/// The version of the app the trigger is running.
+struct CurrentApp<F: RuntimeFactors> {
+    /// Where the version was loaded from, as a path or `file:` URL.
+    locked: String,
+    handle: AppHandle<F>,
+}
+
+impl<F: RuntimeFactors> Clone for CurrentApp<F> {
+    fn clone(&self) -> Self {
+        Self {
+            locked: self.locked.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl<T, B, L> Controller<T, B, L>
+where
+    T: Trigger<B::Factors> + 'static,
+    T::InstanceState: Default,
+    B: RuntimeFactorsBuilder + 'static,
+    B::CliArgs: Send + Sync + 'static,
+    L: ComponentLoader<B::Factors, T::InstanceState> + Send + Sync + 'static,
+{
+    /// Creates a controller for the given version of the app, loaded from
+    /// `locked`. The health checks are replaced by those of each version
+    /// redeployed.
+    pub fn new(
+        locked: String,
+        trigger_app: &TriggerApp<T, B::Factors>,
+        app_loader: AppLoader<T, B, L>,
+        redeployer: Redeployer<T, B::Factors>,
+        health_checks: Arc<HealthChecks<B::Factors, T::InstanceState>>,
+    ) -> Self {
+        Self {
+            app_loader,
+            redeployer,
+            health_checks,
+            current: RwLock::new(CurrentApp {
+                locked,
+                handle: trigger_app.handle(),
+            }),
+        }
+    }
+
+    /// Listens for commands on a Unix socket at the given path, replacing
+    /// any socket left there by an earlier run.
+    #[cfg(unix)]
+    pub fn serve_unix(self: Arc<Self>, path: &Path) -> anyhow::Result<()> {
+        if std::fs::symlink_metadata(path)
+            .is_ok_and(|meta| std::os::unix::fs::FileTypeExt::is_socket(&meta.file_type()))
+        {
+            std::fs::remove_file(path)?;
+        }
+        let listener = tokio::net::UnixListener::bind(path)
+            .with_context(|| format!("failed to bind control socket {}", quoted_path(path)))?;
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => self.clone().serve_connection(stream),
+                    Err(err) => tracing::warn!("Failed to accept control connection: {err}"),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn serve_unix(self: Arc<Self>, _path: &Path) -> anyhow::Result<()> {
+        anyhow::bail!("control sockets are only supported on Unix")
+    }
+
+    /// Listens for commands on the given loopback address. Returns the
+    /// address listened on.
+    pub async fn serve_tcp(self: Arc<Self>, addr: SocketAddr) -> anyhow::Result<SocketAddr> {
+        anyhow::ensure!(
+            addr.ip().is_loopback(),
+            "the control address must be a loopback address, not {addr}"
+        );
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to listen for control commands on {addr}"))?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => self.clone().serve_connection(stream),
+                    Err(err) => tracing::warn!("Failed to accept control connection: {err}"),
+                }
+            }
+        });
+        Ok(addr)
+    }
+
+    fn serve_connection<S: AsyncRead + AsyncWrite + Send + 'static>(self: Arc<Self>, stream: S) {
+        tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(stream);
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let reply = match self.run(&line).await {
+                    Ok(None) => "ok\n".to_owned(),
+                    Ok(Some(output)) => format!("ok {output}\n"),
+                    Err(err) => format!("error: {err:#}\n"),
+                };
+                if writer.write_all(reply.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Runs the command on the given line, returning its output, if any.
+    async fn run(&self, line: &str) -> anyhow::Result<Option<serde_json::Value>> {
+        let current = self.current();
+        let output = match Command::parse(line)? {
+            Command::Components => Some(components(current.handle.app())?),
+            Command::Variables => {
+                let variables = current
+                    .handle
+                    .configured_app()
+                    .app_state::<VariablesFactor>()
+                    .context("the app has no variables")?;
+                Some(serde_json::to_value(variables.snapshot().await)?)
+            }
+            Command::LogLevel(directives) => {
+                spin_telemetry::set_log_directives(directives.as_deref())?;
+                None
+            }
+            Command::Health => Some(self.health_checks.report().await),
+            Command::Metrics => Some(usage(&current.handle)),
+            Command::Reload => {
+                tracing::info!("Reloading the app from {}", current.locked);
+                self.redeploy(current.locked).await?;
+                None
+            }
+            Command::Redeploy(locked) => {
+                tracing::info!("Redeploying the app from {locked}");
+                self.redeploy(locked).await?;
+                None
+            }
+        };
+        Ok(output)
+    }
+
+    async fn redeploy(&self, locked: String) -> anyhow::Result<()> {
+        let app = load_locked_app(&locked)?;
+        let trigger_app = self
+            .app_loader
+            .load(app)
+            .await
+            .context("failed to load the new version of the app")?;
+        let health_checks = HealthChecks::new(&trigger_app)?;
+        let handle = trigger_app.handle();
+        self.redeployer.redeploy(trigger_app).await?;
+        self.health_checks.replace(health_checks);
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) =
+            CurrentApp { locked, handle };
+        Ok(())
+    }
+
+    fn current(&self) -> CurrentApp<B::Factors> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// Lists each component of the app, with the types of the triggers which
+/// run it.
+fn components(app: &App) -> anyhow::Result<serde_json::Value> {
+    let mut components = serde_json::Map::new();
+    for component in app.components() {
+        let description = component.get_metadata(APP_DESCRIPTION_KEY)?;
+        let triggers: Vec<_> = app
+            .triggers()
+            .filter(|trigger| {
+                trigger
+                    .component()
+                    .is_ok_and(|trigger_component| trigger_component.id() == component.id())
+            })
+            .map(|trigger| trigger.trigger_type().to_owned())
+            .collect();
+        components.insert(
+            component.id().to_owned(),
+            serde_json::json!({
+                "description": description,
+                "triggers": triggers,
+            }),
+        );
+    }
+    Ok(serde_json::json!({ "components": components }))
+}
+
+/// Returns the resource usage of each component, as the HTTP trigger's usage
+/// endpoint does.
+fn usage<F: RuntimeFactors>(handle: &AppHandle<F>) -> serde_json::Value {
+    let components: serde_json::Map<_, _> = handle
+        .resource_usage()
+        .into_iter()
+        .map(|(component_id, usage)| {
+            let usage = serde_json::json!({
+                "invocations": usage.invocations,
+                "fuel_consumed": usage.fuel_consumed,
+                "peak_memory": usage.peak_memory,
+            });
+            (component_id, usage)
+        })
+        .collect();
+    serde_json::json!({ "components": components })
+}
+
+fn load_locked_app(locked: &str) -> anyhow::Result<App> {
+    let path = if locked.starts_with("file:") {
+        parse_file_url(locked)?
+    } else {
+        PathBuf::from(locked)
+    };
+    let contents = std::fs::read(&path)
+        .with_context(|| format!("failed to read locked app at {}", quoted_path(&path)))?;
+    let locked_app =
+        serde_json::from_slice(&contents).context("failed to parse app lock file JSON")?;
+    Ok(App::new(locked, locked_app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(
+            Command::parse("redeploy /apps/v2/spin.lock\n").unwrap(),
+            Command::Redeploy("/apps/v2/spin.lock".into())
+        );
+        assert!(Command::parse("redeploy").is_err());
+        assert!(Command::parse("").is_err());
+        assert!(Command::parse("restart now").is_err());
+    }
+
+    #[test]
+    fn inspection_commands_are_parsed() {
+        assert_eq!(Command::parse("components").unwrap(), Command::Components);
+        assert_eq!(Command::parse(" variables ").unwrap(), Command::Variables);
+        assert_eq!(Command::parse("health").unwrap(), Command::Health);
+        assert_eq!(Command::parse("metrics").unwrap(), Command::Metrics);
+        assert_eq!(Command::parse("reload").unwrap(), Command::Reload);
+        assert!(Command::parse("reload now").is_err());
+    }
+
+    #[test]
+    fn log_level_commands_are_parsed() {
+        assert_eq!(
+            Command::parse("log-level spin_trigger=debug,info").unwrap(),
+            Command::LogLevel(Some("spin_trigger=debug,info".into()))
+        );
+        assert_eq!(
+            Command::parse("log-level reset").unwrap(),
+            Command::LogLevel(None)
+        );
+        assert!(Command::parse("log-level").is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use spin_core::async_trait;
+use spin_factor_context::ContextFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{ExecutorHooks, FactorsInstanceBuilder};
+
+/// An [`ExecutorHooks`] that gives instances their invocation context, so
+/// that components can get it through the context host interface.
+pub struct InvocationContextHook;
+
+#[async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for InvocationContextHook {
+    fn prepare_instance(&self, builder: &mut FactorsInstanceBuilder<F, U>) -> anyhow::Result<()> {
+        let context = builder.invocation_context().clone();
+        if let Some(context_builder) = builder.factor_builder::<ContextFactor>() {
+            context_builder.set_invocation_context(context);
+        }
+        Ok(())
+    }
+}
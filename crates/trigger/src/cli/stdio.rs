@@ -12,6 +12,8 @@ use spin_factors::RuntimeFactors;
 use spin_factors_executor::ExecutorHooks;
 use tokio::io::AsyncWrite;
 
+use super::component_logs::{ComponentLogWriter, ComponentLogsConfig, Stream};
+
 /// Which components should have their logs followed on stdout/stderr.
 #[derive(Clone, Debug, Default)]
 pub enum FollowComponents {
@@ -36,16 +38,26 @@ impl FollowComponents {
 }
 
 /// Implements TriggerHooks, writing logs to a log file and (optionally) stderr
+///
+/// If a `[logging]` table is configured, output is written a line at a time,
+/// filtered by level and in the configured format; otherwise it is written as
+/// the component wrote it.
 pub struct StdioLoggingExecutorHooks {
     follow_components: FollowComponents,
     log_dir: Option<PathBuf>,
+    component_logs: Option<ComponentLogsConfig>,
 }
 
 impl StdioLoggingExecutorHooks {
-    pub fn new(follow_components: FollowComponents, log_dir: Option<PathBuf>) -> Self {
+    pub fn new(
+        follow_components: FollowComponents,
+        log_dir: Option<PathBuf>,
+        component_logs: Option<ComponentLogsConfig>,
+    ) -> Self {
         Self {
             follow_components,
             log_dir,
+            component_logs,
         }
     }
 
@@ -86,6 +98,25 @@ impl StdioLoggingExecutorHooks {
             _ => Ok(()),
         }
     }
+
+    fn validate_component_logs(&self, app: &spin_app::App) -> anyhow::Result<()> {
+        let Some(component_logs) = &self.component_logs else {
+            return Ok(());
+        };
+        let component_ids: HashSet<_> = app.components().map(|c| c.id().to_owned()).collect();
+        let unknown_names: Vec<_> = component_logs
+            .component_ids()
+            .filter(|id| !component_ids.contains(*id))
+            .collect();
+        if unknown_names.is_empty() {
+            Ok(())
+        } else {
+            let unknown_list = bullet_list(&unknown_names);
+            let actual_list = bullet_list(&component_ids);
+            let message = anyhow::anyhow!("The following component(s) configured in [logging.components] do not exist in the application:\n{unknown_list}\nThe following components exist:\n{actual_list}");
+            Err(message)
+        }
+    }
 }
 
 #[async_trait]
@@ -95,6 +126,7 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
         configured_app: &spin_factors::ConfiguredApp<F>,
     ) -> anyhow::Result<()> {
         self.validate_follows(configured_app.app())?;
+        self.validate_component_logs(configured_app.app())?;
         if let Some(dir) = &self.log_dir {
             // Ensure log dir exists if set
             std::fs::create_dir_all(dir)
@@ -110,9 +142,28 @@ impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for StdioLoggingExecutorHooks {
         builder: &mut spin_factors_executor::FactorsInstanceBuilder<F, U>,
     ) -> anyhow::Result<()> {
         let component_id = builder.app_component().id().to_string();
+        let context = builder.invocation_context().clone();
         let Some(wasi_builder) = builder.factor_builder::<WasiFactor>() else {
             return Ok(());
         };
+        if let Some(component_logs) = &self.component_logs {
+            let follow = self.follow_components.should_follow(&component_id);
+            for stream in [Stream::Stdout, Stream::Stderr] {
+                let writer = ComponentLogWriter::new(
+                    component_logs,
+                    &component_id,
+                    stream,
+                    self.log_dir.as_deref(),
+                    follow,
+                    context.clone(),
+                )?;
+                match stream {
+                    Stream::Stdout => wasi_builder.stdout_pipe(writer),
+                    Stream::Stderr => wasi_builder.stderr_pipe(writer),
+                }
+            }
+            return Ok(());
+        }
         wasi_builder.stdout_pipe(self.component_stdio_writer(
             &component_id,
             "stdout",
@@ -0,0 +1,221 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+pub const SPIN_TLS_CERT: &str = "SPIN_TLS_CERT";
+pub const SPIN_TLS_KEY: &str = "SPIN_TLS_KEY";
+pub const SPIN_ACME_DOMAINS: &str = "SPIN_ACME_DOMAINS";
+pub const SPIN_ACME_CONTACT: &str = "SPIN_ACME_CONTACT";
+pub const SPIN_ACME_DIRECTORY: &str = "SPIN_ACME_DIRECTORY";
+pub const SPIN_ACME_CACHE_DIR: &str = "SPIN_ACME_CACHE_DIR";
+pub const SPIN_ACME_CHALLENGE: &str = "SPIN_ACME_CHALLENGE";
+pub const SPIN_ACME_HTTP_LISTEN: &str = "SPIN_ACME_HTTP_LISTEN";
+
+/// The environment variables which set how the HTTP trigger terminates TLS.
+const TLS_ENV_VARS: &[&str] = &[
+    SPIN_TLS_CERT,
+    SPIN_TLS_KEY,
+    SPIN_ACME_DOMAINS,
+    SPIN_ACME_CONTACT,
+    SPIN_ACME_DIRECTORY,
+    SPIN_ACME_CACHE_DIR,
+    SPIN_ACME_CHALLENGE,
+    SPIN_ACME_HTTP_LISTEN,
+];
+
+/// TLS settings for the HTTP trigger's listener from the `[tls]` table of a
+/// runtime config file: either a certificate and key in files, or an
+/// `[tls.acme]` table for certificates obtained from an ACME certificate
+/// authority.
+///
+/// ```toml
+/// [tls.acme]
+/// domains = ["example.com", "www.example.com"]
+/// contact = "admin@example.com"
+/// cache_dir = ".spin/acme"
+/// ```
+///
+/// Like the telemetry settings, these take effect by being passed to the
+/// trigger as environment variables, which its command line options take
+/// precedence over.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsRuntimeConfig {
+    /// The path to the certificate chain, in PEM format.
+    cert_file: Option<PathBuf>,
+    /// The path to the private key, in PEM format.
+    key_file: Option<PathBuf>,
+    /// Settings for obtaining certificates from an ACME certificate authority.
+    acme: Option<AcmeRuntimeConfig>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AcmeRuntimeConfig {
+    /// The domains the certificate is for.
+    domains: Vec<String>,
+    /// An email address the certificate authority may contact.
+    contact: Option<String>,
+    /// The URL of the certificate authority's ACME directory.
+    directory: Option<String>,
+    /// The directory in which the account and certificates are kept.
+    cache_dir: Option<PathBuf>,
+    /// How the certificate authority validates control of the domains.
+    challenge: Option<AcmeChallengeType>,
+    /// The address on which to answer HTTP-01 challenges.
+    http_listen: Option<SocketAddr>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+enum AcmeChallengeType {
+    #[serde(rename = "http-01")]
+    Http01,
+    #[serde(rename = "tls-alpn-01")]
+    TlsAlpn01,
+}
+
+impl AcmeChallengeType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Http01 => "http-01",
+            Self::TlsAlpn01 => "tls-alpn-01",
+        }
+    }
+}
+
+impl TlsRuntimeConfig {
+    /// Checks that the settings describe exactly one source of certificates.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match (&self.cert_file, &self.key_file, &self.acme) {
+            (Some(_), Some(_), None) => Ok(()),
+            (None, None, Some(acme)) => {
+                anyhow::ensure!(
+                    !acme.domains.is_empty(),
+                    "[tls.acme] must list at least one domain"
+                );
+                Ok(())
+            }
+            (Some(_), None, None) | (None, Some(_), None) => {
+                anyhow::bail!("[tls] must set both `cert_file` and `key_file`")
+            }
+            (None, None, None) => {
+                anyhow::bail!("[tls] must set `cert_file` and `key_file`, or an [tls.acme] table")
+            }
+            (_, _, Some(_)) => {
+                anyhow::bail!("[tls] may set `cert_file` and `key_file`, or an [tls.acme] table, but not both")
+            }
+        }
+    }
+
+    /// Resolves relative paths against the given directory, which should be
+    /// that of the runtime config file.
+    pub fn resolve_paths(&mut self, base: &Path) {
+        let acme_cache_dir = self.acme.as_mut().and_then(|acme| acme.cache_dir.as_mut());
+        for path in [
+            self.cert_file.as_mut(),
+            self.key_file.as_mut(),
+            acme_cache_dir,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        }
+    }
+
+    /// The environment variables which configure the HTTP trigger's TLS as
+    /// these settings do.
+    ///
+    /// If any TLS variable is already set in the environment, none are
+    /// returned, so that the environment takes precedence over the runtime
+    /// config without the two being mixed.
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        if TLS_ENV_VARS
+            .iter()
+            .any(|key| std::env::var_os(key).is_some())
+        {
+            return vec![];
+        }
+        let path = |path: &Option<PathBuf>| path.as_ref().map(|p| p.display().to_string());
+        let mut vars = vec![
+            (SPIN_TLS_CERT, path(&self.cert_file)),
+            (SPIN_TLS_KEY, path(&self.key_file)),
+        ];
+        if let Some(acme) = &self.acme {
+            vars.extend([
+                (SPIN_ACME_DOMAINS, Some(acme.domains.join(","))),
+                (SPIN_ACME_CONTACT, acme.contact.clone()),
+                (SPIN_ACME_DIRECTORY, acme.directory.clone()),
+                (SPIN_ACME_CACHE_DIR, path(&acme.cache_dir)),
+                (
+                    SPIN_ACME_CHALLENGE,
+                    acme.challenge.map(|c| c.as_str().to_owned()),
+                ),
+                (
+                    SPIN_ACME_HTTP_LISTEN,
+                    acme.http_listen.map(|addr| addr.to_string()),
+                ),
+            ]);
+        }
+        vars.into_iter()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: serde_json::Value) -> TlsRuntimeConfig {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn acme_settings_map_to_env_vars() {
+        let mut config = parse(serde_json::json!({
+            "acme": {
+                "domains": ["example.com", "www.example.com"],
+                "challenge": "http-01",
+                "cache_dir": "acme",
+            },
+        }));
+        config.validate().unwrap();
+        config.resolve_paths(Path::new("/etc/spin"));
+        let cache_dir = Path::new("/etc/spin").join("acme").display().to_string();
+        assert_eq!(
+            config.env_vars(),
+            [
+                (SPIN_ACME_DOMAINS, "example.com,www.example.com".to_owned()),
+                (SPIN_ACME_CACHE_DIR, cache_dir),
+                (SPIN_ACME_CHALLENGE, "http-01".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn one_source_of_certificates_is_required() {
+        parse(serde_json::json!({ "cert_file": "c.pem", "key_file": "k.pem" }))
+            .validate()
+            .unwrap();
+        assert!(parse(serde_json::json!({ "cert_file": "c.pem" }))
+            .validate()
+            .is_err());
+        assert!(parse(serde_json::json!({})).validate().is_err());
+        assert!(parse(serde_json::json!({
+            "cert_file": "c.pem",
+            "key_file": "k.pem",
+            "acme": { "domains": ["example.com"] },
+        }))
+        .validate()
+        .is_err());
+        assert!(parse(serde_json::json!({ "acme": { "domains": [] } }))
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Context as _;
+use spin_core::async_trait;
+use spin_factor_variables::VariablesFactor;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::ExecutorHooks;
+
+/// Providers which take longer than this to respond at startup are reported.
+const SLOW_PROVIDER_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// An [`ExecutorHooks`] that fetches variable values in bulk and checks them
+/// against their declared types and constraints before the app starts.
+pub struct VariablesValidationHook;
+
+#[async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for VariablesValidationHook {
+    async fn configure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        let Ok(variables_app_state) = configured_app.app_state::<VariablesFactor>() else {
+            return Ok(());
+        };
+        // Values which fail to prefetch are fetched again on first use
+        if let Err(err) = variables_app_state.prefetch().await {
+            tracing::warn!("failed to prefetch application variables: {err}");
+        }
+        for (provider, metrics) in variables_app_state.metrics().providers() {
+            if metrics.max_latency > SLOW_PROVIDER_THRESHOLD {
+                tracing::warn!(
+                    "variables provider {provider:?} took {:?} to respond",
+                    metrics.max_latency
+                );
+            }
+        }
+        variables_app_state
+            .validate_constraints()
+            .await
+            .context("invalid application variable")
+    }
+}
+
+/// An [`ExecutorHooks`] that writes a snapshot of the resolved variable values
+/// to a file before the app starts.
+pub struct VariablesSnapshotHook {
+    path: PathBuf,
+}
+
+impl VariablesSnapshotHook {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl<F: RuntimeFactors, U> ExecutorHooks<F, U> for VariablesSnapshotHook {
+    async fn configure_app(
+        &self,
+        configured_app: &spin_factors::ConfiguredApp<F>,
+    ) -> anyhow::Result<()> {
+        let Ok(variables_app_state) = configured_app.app_state::<VariablesFactor>() else {
+            return Ok(());
+        };
+        let snapshot = variables_app_state.snapshot().await;
+        let json = snapshot
+            .to_json()
+            .context("failed to serialize variables snapshot")?;
+        std::fs::write(&self.path, json).with_context(|| {
+            format!(
+                "failed to write variables snapshot to {}",
+                self.path.display()
+            )
+        })
+    }
+}
@@ -1,14 +1,18 @@
 pub mod cli;
 pub mod loader;
+pub mod redeploy;
 
-use std::future::Future;
+use std::{convert::Infallible, future::Future};
 
 use clap::Args;
 use spin_core::Linker;
 use spin_factors::RuntimeFactors;
 use spin_factors_executor::{FactorsExecutorApp, FactorsInstanceBuilder};
 
+use crate::redeploy::Redeploys;
+
 pub use spin_app::App;
+pub use spin_factors_executor::is_execution_timeout;
 
 /// Type alias for a [`spin_factors_executor::FactorsExecutorApp`] specialized to a [`Trigger`].
 pub type TriggerApp<T, F> = FactorsExecutorApp<F, <T as Trigger<F>>::InstanceState>;
@@ -65,6 +69,34 @@ pub trait Trigger<F: RuntimeFactors>: Sized + Send {
         trigger_app: TriggerApp<Self, F>,
     ) -> impl Future<Output = anyhow::Result<()>> + Send;
 
+    /// Run this trigger, switching new work to each new version of the app
+    /// received from `redeploys`.
+    ///
+    /// By default, redeploys are not supported: the trigger runs the first
+    /// version of the app, and refuses any others.
+    fn run_redeployable(
+        self,
+        trigger_app: TriggerApp<Self, F>,
+        mut redeploys: Redeploys<Self, F>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send {
+        async move {
+            let refuse = async move {
+                while let Some(redeploy) = redeploys.next().await {
+                    redeploy.refuse(anyhow::anyhow!(
+                        "the '{}' trigger does not support redeploys",
+                        Self::TYPE
+                    ));
+                }
+                std::future::pending::<Infallible>().await
+            };
+            let run = self.run(trigger_app);
+            match futures::future::select(std::pin::pin!(run), std::pin::pin!(refuse)).await {
+                futures::future::Either::Left((result, _)) => result,
+                futures::future::Either::Right((never, _)) => match never {},
+            }
+        }
+    }
+
     /// Returns a list of host requirements supported by this trigger specifically.
     ///
     /// See [`App::ensure_needs_only`].
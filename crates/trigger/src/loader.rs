@@ -1,12 +1,19 @@
+mod compiled_cache;
+
+use std::{path::PathBuf, time::Instant};
+
 use anyhow::Context as _;
 use spin_common::{ui::quoted_path, url::parse_file_url};
 use spin_compose::ComponentSourceLoaderFs;
 use spin_core::{async_trait, wasmtime, Component};
 use spin_factors::{AppComponent, RuntimeFactors};
 
+use compiled_cache::CompiledCache;
+
 #[derive(Default)]
 pub struct ComponentLoader {
     _private: (),
+    compiled_cache: Option<CompiledCache>,
     #[cfg(feature = "unsafe-aot-compilation")]
     aot_compilation_enabled: bool,
 }
@@ -17,6 +24,18 @@ impl ComponentLoader {
         Self::default()
     }
 
+    /// Updates the TriggerLoader to keep compiled components in the given
+    /// directory, and to load them from there rather than compile them again.
+    ///
+    /// Compiled components are keyed by their Wasm digest and the settings of
+    /// the engine compiling them, so the directory may be shared by later
+    /// runs, different versions of Spin, and other hosts. Compiled components
+    /// are loaded as native code, so the directory must be no more writable
+    /// than the Spin binary itself.
+    pub fn enable_compiled_cache(&mut self, dir: impl Into<PathBuf>) {
+        self.compiled_cache = Some(CompiledCache::new(dir.into()));
+    }
+
     /// Updates the TriggerLoader to load AOT precompiled components
     ///
     /// **Warning: This feature may bypass important security guarantees of the
@@ -89,6 +108,7 @@ impl<T: RuntimeFactors, U> spin_factors_executor::ComponentLoader<T, U> for Comp
                 .with_context(|| format!("error deserializing component from {path:?}"));
         }
 
+        let started = Instant::now();
         let composed = spin_compose::compose(&ComponentSourceLoaderFs, component.locked)
             .await
             .with_context(|| {
@@ -97,8 +117,23 @@ impl<T: RuntimeFactors, U> spin_factors_executor::ComponentLoader<T, U> for Comp
                     component.locked.id
                 )
             })?;
+        spin_telemetry::metrics::histogram!(
+            spin.composition_duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+            app_id = component.app.id(),
+            component_id = component.id()
+        );
 
-        spin_core::Component::new(engine, composed)
-            .with_context(|| format!("failed to compile component from {}", quoted_path(&path)))
+        let started = Instant::now();
+        let compiled = match &self.compiled_cache {
+            Some(cache) => cache.load_or_compile(engine, &composed),
+            None => spin_core::Component::new(engine, composed),
+        }
+        .with_context(|| format!("failed to compile component from {}", quoted_path(&path)))?;
+        spin_telemetry::metrics::histogram!(
+            spin.component_load_duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+            app_id = component.app.id(),
+            component_id = component.id()
+        );
+        Ok(compiled)
     }
 }
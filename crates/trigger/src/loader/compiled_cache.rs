@@ -0,0 +1,119 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use spin_common::{sha256::hex_digest_from_bytes, ui::quoted_path};
+use spin_core::{wasmtime, Component};
+
+/// A content-addressed directory of compiled components.
+///
+/// A compiled component is keyed by the digest of its Wasm together with a
+/// hash of the settings of the engine which compiled it, so one directory can
+/// be shared by different Spin versions and hosts: an engine only ever finds
+/// components which it could have compiled itself.
+pub(crate) struct CompiledCache {
+    dir: PathBuf,
+}
+
+impl CompiledCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Loads the compiled form of the given Wasm from the cache, or compiles
+    /// it and adds it to the cache.
+    pub fn load_or_compile(
+        &self,
+        engine: &wasmtime::Engine,
+        wasm: &[u8],
+    ) -> anyhow::Result<Component> {
+        let path = self.artifact_path(engine, wasm);
+        if path.exists() {
+            // SAFETY: the cache only holds the output of `Component::serialize`
+            // from engines with the same compatibility hash as this one, and
+            // the caller trusts its directory as it would its own binaries.
+            match unsafe { Component::deserialize_file(engine, &path) } {
+                Ok(component) => return Ok(component),
+                Err(err) => tracing::warn!(
+                    "Recompiling component: cached {} could not be loaded: {err:?}",
+                    quoted_path(&path)
+                ),
+            }
+        }
+
+        let component = Component::new(engine, wasm)?;
+        if let Err(err) = Self::store(&path, &component) {
+            tracing::warn!(
+                "Failed to cache compiled component at {}: {err:?}",
+                quoted_path(&path)
+            );
+        }
+        Ok(component)
+    }
+
+    fn store(path: &Path, component: &Component) -> anyhow::Result<()> {
+        let dir = path.parent().context("cached component has no parent")?;
+        std::fs::create_dir_all(dir)?;
+        // Write the artifact then rename it into place, so that a concurrent
+        // reader never sees one partly written
+        let file = tempfile::NamedTempFile::new_in(dir)?;
+        std::fs::write(file.path(), component.serialize()?)?;
+        file.persist(path)?;
+        Ok(())
+    }
+
+    fn artifact_path(&self, engine: &wasmtime::Engine, wasm: &[u8]) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        let engine_key = format!("{:016x}", hasher.finish());
+        let digest = hex_digest_from_bytes(wasm);
+        self.dir.join(engine_key).join(format!("{digest}.cwasm"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wasmtime compiles the text format as it does the binary format
+    const WAT: &str = "(component (core module))";
+
+    #[test]
+    fn components_are_compiled_once() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = CompiledCache::new(dir.path().to_owned());
+        let engine = wasmtime::Engine::default();
+        let wasm = WAT.as_bytes();
+
+        cache.load_or_compile(&engine, wasm)?;
+        let path = cache.artifact_path(&engine, wasm);
+        assert!(path.exists());
+
+        // A cached artifact is loaded rather than compiled again
+        let modified = std::fs::metadata(&path)?.modified()?;
+        cache.load_or_compile(&engine, wasm)?;
+        assert_eq!(std::fs::metadata(&path)?.modified()?, modified);
+        Ok(())
+    }
+
+    #[test]
+    fn incompatible_engines_do_not_share_artifacts() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = CompiledCache::new(dir.path().to_owned());
+        let wasm = WAT.as_bytes();
+
+        let engine = wasmtime::Engine::default();
+        let mut config = wasmtime::Config::new();
+        config.cranelift_opt_level(wasmtime::OptLevel::None);
+        let other_engine = wasmtime::Engine::new(&config)?;
+
+        assert_ne!(
+            cache.artifact_path(&engine, wasm),
+            cache.artifact_path(&other_engine, wasm)
+        );
+        Ok(())
+    }
+}
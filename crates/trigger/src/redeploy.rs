@@ -0,0 +1,138 @@
+//! Switching a running trigger to new versions of its app.
+//!
+//! A [`Redeployer`] sends new versions of an app, built by an [`AppLoader`]
+//! onto the same engine and factors as the running version, to a trigger run
+//! with [`Trigger::run_redeployable`]. The trigger switches new work to each
+//! version it receives, and leaves work already underway to finish on the
+//! version it replaces.
+
+use std::{future::Future, sync::Arc};
+
+use anyhow::Context as _;
+use spin_app::App;
+use spin_factors::RuntimeFactors;
+use spin_factors_executor::{ComponentLoader, FactorsExecutor};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    cli::{FactorsConfig, RuntimeFactorsBuilder},
+    Trigger, TriggerApp,
+};
+
+/// Creates a [`Redeployer`] and the [`Redeploys`] it sends to.
+pub fn channel<T: Trigger<F>, F: RuntimeFactors>() -> (Redeployer<T, F>, Redeploys<T, F>) {
+    let (tx, rx) = mpsc::channel(1);
+    (Redeployer { tx }, Redeploys { rx })
+}
+
+/// Sends new versions of an app to a running trigger.
+pub struct Redeployer<T: Trigger<F>, F: RuntimeFactors> {
+    tx: mpsc::Sender<Redeploy<T, F>>,
+}
+
+impl<T: Trigger<F>, F: RuntimeFactors> Clone for Redeployer<T, F> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<T: Trigger<F>, F: RuntimeFactors> Redeployer<T, F> {
+    /// Switches the trigger to the given version of the app, returning once
+    /// new work goes to it.
+    pub async fn redeploy(&self, trigger_app: TriggerApp<T, F>) -> anyhow::Result<()> {
+        let (result_tx, result_rx) = oneshot::channel();
+        self.tx
+            .send(Redeploy {
+                trigger_app,
+                result: result_tx,
+            })
+            .await
+            .ok()
+            .context("the trigger has stopped")?;
+        result_rx
+            .await
+            .context("the trigger stopped before switching versions")?
+    }
+}
+
+/// The new versions of an app sent to a running trigger.
+pub struct Redeploys<T: Trigger<F>, F: RuntimeFactors> {
+    rx: mpsc::Receiver<Redeploy<T, F>>,
+}
+
+impl<T: Trigger<F>, F: RuntimeFactors> Redeploys<T, F> {
+    /// Waits for the next version, returning `None` once no more can be sent.
+    pub async fn next(&mut self) -> Option<Redeploy<T, F>> {
+        self.rx.recv().await
+    }
+}
+
+/// A new version of an app for a running trigger to switch to.
+pub struct Redeploy<T: Trigger<F>, F: RuntimeFactors> {
+    trigger_app: TriggerApp<T, F>,
+    result: oneshot::Sender<anyhow::Result<()>>,
+}
+
+impl<T: Trigger<F>, F: RuntimeFactors> Redeploy<T, F> {
+    /// Switches to the new version with `switch`, reporting the outcome to
+    /// the sender.
+    pub async fn switch<Fut>(self, switch: impl FnOnce(TriggerApp<T, F>) -> Fut)
+    where
+        Fut: Future<Output = anyhow::Result<()>>,
+    {
+        let result = switch(self.trigger_app).await;
+        if let Err(err) = &result {
+            tracing::error!("Failed to switch to the new version of the app: {err:#}");
+        }
+        let _ = self.result.send(result);
+    }
+
+    /// Declines to switch to the new version, reporting why to the sender.
+    pub fn refuse(self, err: anyhow::Error) {
+        let _ = self.result.send(Err(err));
+    }
+}
+
+/// Loads new versions of an app onto the engine and factors of the version
+/// first loaded, with freshly built runtime config.
+pub struct AppLoader<T: Trigger<B::Factors>, B: RuntimeFactorsBuilder, L> {
+    executor: Arc<FactorsExecutor<B::Factors, T::InstanceState>>,
+    common_options: FactorsConfig,
+    options: B::CliArgs,
+    loader: L,
+}
+
+impl<T, B, L> AppLoader<T, B, L>
+where
+    T: Trigger<B::Factors>,
+    B: RuntimeFactorsBuilder,
+    L: ComponentLoader<B::Factors, T::InstanceState>,
+{
+    pub(crate) fn new(
+        executor: Arc<FactorsExecutor<B::Factors, T::InstanceState>>,
+        common_options: FactorsConfig,
+        options: B::CliArgs,
+        loader: L,
+    ) -> Self {
+        Self {
+            executor,
+            common_options,
+            options,
+            loader,
+        }
+    }
+
+    /// Loads the given version of the app.
+    pub async fn load(&self, app: App) -> anyhow::Result<TriggerApp<T, B::Factors>> {
+        if let Err(unmet) = app.ensure_needs_only(T::TYPE, &T::supported_host_requirements()) {
+            anyhow::bail!("The new version of the application requires the following features that are not available in this version of the '{}' trigger: {unmet}", T::TYPE);
+        }
+        let (_, runtime_config) = B::build(&self.common_options, &self.options)?;
+        self.executor
+            .clone()
+            .load_app(app, runtime_config.into(), &self.loader)
+            .await
+    }
+}
@@ -0,0 +1,258 @@
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use aws_config::{BehaviorVersion, Region};
+use serde::Deserialize;
+use spin_expressions::{Key, Provider};
+use spin_factors::anyhow::{self, Context as _};
+use spin_world::async_trait;
+use tracing::{instrument, Level};
+
+/// The maximum number of names accepted by a Parameter Store `GetParameters` request.
+const GET_PARAMETERS_MAX_NAMES: usize = 10;
+
+type LazyClient<C> = async_once_cell::Lazy<C, Pin<Box<dyn Future<Output = C> + Send>>>;
+
+/// Configuration for the AWS Secrets Manager variables provider.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AwsSecretsManagerVariablesConfig {
+    /// The AWS region to read secrets from.
+    ///
+    /// If not set, the region is resolved from the standard AWS configuration
+    /// sources (e.g. `AWS_REGION` or the shared config file).
+    #[serde(default)]
+    pub region: Option<String>,
+    /// An optional prefix for secret names.
+    ///
+    /// Unless empty, joined to the variable name with a `/`.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+/// Configuration for the AWS Systems Manager Parameter Store variables provider.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AwsParameterStoreVariablesConfig {
+    /// The AWS region to read parameters from.
+    ///
+    /// If not set, the region is resolved from the standard AWS configuration
+    /// sources (e.g. `AWS_REGION` or the shared config file).
+    #[serde(default)]
+    pub region: Option<String>,
+    /// An optional prefix for parameter names, e.g. `/my-app/prod`.
+    ///
+    /// Unless empty, joined to the variable name with a `/`.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+/// A [`Provider`] that reads variables from AWS Secrets Manager.
+///
+/// Credentials are resolved using the standard AWS credential provider chain.
+pub struct AwsSecretsManagerProvider {
+    prefix: Option<String>,
+    client: LazyClient<aws_sdk_secretsmanager::Client>,
+}
+
+impl AwsSecretsManagerProvider {
+    /// Creates a new `AwsSecretsManagerProvider`.
+    pub fn new(config: AwsSecretsManagerVariablesConfig) -> Self {
+        let AwsSecretsManagerVariablesConfig { region, prefix } = config;
+        let client_fut =
+            Box::pin(
+                async move { aws_sdk_secretsmanager::Client::new(&load_sdk_config(region).await) },
+            );
+        Self {
+            prefix,
+            client: async_once_cell::Lazy::from_future(client_fut),
+        }
+    }
+}
+
+impl std::fmt::Debug for AwsSecretsManagerProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsSecretsManagerProvider")
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Provider for AwsSecretsManagerProvider {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        Ok(self.get_versioned(key).await?.map(|(value, _)| value))
+    }
+
+    // Versions are the secret's version ID, which changes when it is rotated
+    #[instrument(name = "spin_variables.get_from_aws_secrets_manager", level = Level::DEBUG, skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get_versioned(&self, key: &Key) -> anyhow::Result<Option<(String, Option<String>)>> {
+        let secret_id = prefixed_name(self.prefix.as_deref(), key);
+        let result = self
+            .client
+            .get_unpin()
+            .await
+            .get_secret_value()
+            .secret_id(&secret_id)
+            .send()
+            .await;
+        match result {
+            Ok(output) => {
+                let version = output.version_id;
+                if let Some(value) = output.secret_string {
+                    return Ok(Some((value, version)));
+                }
+                let Some(binary) = output.secret_binary else {
+                    return Ok(None);
+                };
+                let value = String::from_utf8(binary.into_inner())
+                    .with_context(|| format!("AWS secret {secret_id:?} is not valid UTF-8"))?;
+                Ok(Some((value, version)))
+            }
+            Err(err) => {
+                let err = err.into_service_error();
+                if err.is_resource_not_found_exception() {
+                    // Secrets Manager doesn't have this entry so pass along the chain
+                    Ok(None)
+                } else {
+                    Err(err).context("Failed to read variable from AWS Secrets Manager")
+                }
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        "aws_secrets_manager"
+    }
+}
+
+/// A [`Provider`] that reads variables from AWS Systems Manager Parameter Store.
+///
+/// `SecureString` parameters are decrypted. Credentials are resolved using the
+/// standard AWS credential provider chain.
+pub struct AwsParameterStoreProvider {
+    prefix: Option<String>,
+    client: LazyClient<aws_sdk_ssm::Client>,
+}
+
+impl AwsParameterStoreProvider {
+    /// Creates a new `AwsParameterStoreProvider`.
+    pub fn new(config: AwsParameterStoreVariablesConfig) -> Self {
+        let AwsParameterStoreVariablesConfig { region, prefix } = config;
+        let client_fut =
+            Box::pin(async move { aws_sdk_ssm::Client::new(&load_sdk_config(region).await) });
+        Self {
+            prefix,
+            client: async_once_cell::Lazy::from_future(client_fut),
+        }
+    }
+}
+
+impl std::fmt::Debug for AwsParameterStoreProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsParameterStoreProvider")
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Provider for AwsParameterStoreProvider {
+    #[instrument(name = "spin_variables.get_from_aws_parameter_store", level = Level::DEBUG, skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        let name = prefixed_name(self.prefix.as_deref(), key);
+        let result = self
+            .client
+            .get_unpin()
+            .await
+            .get_parameter()
+            .name(&name)
+            .with_decryption(true)
+            .send()
+            .await;
+        match result {
+            Ok(output) => Ok(output.parameter.and_then(|p| p.value)),
+            Err(err) => {
+                let err = err.into_service_error();
+                if err.is_parameter_not_found() {
+                    // Parameter Store doesn't have this entry so pass along the chain
+                    Ok(None)
+                } else {
+                    Err(err).context("Failed to read variable from AWS Parameter Store")
+                }
+            }
+        }
+    }
+
+    #[instrument(name = "spin_variables.get_many_from_aws_parameter_store", level = Level::DEBUG, skip_all, err(level = Level::INFO), fields(keys = keys.len(), otel.kind = "client"))]
+    async fn get_many_versioned(
+        &self,
+        keys: &[Key<'_>],
+    ) -> anyhow::Result<Vec<Option<(String, Option<String>)>>> {
+        let names: Vec<_> = keys
+            .iter()
+            .map(|key| prefixed_name(self.prefix.as_deref(), key))
+            .collect();
+        let client = self.client.get_unpin().await;
+        let mut values = HashMap::new();
+        for chunk in names.chunks(GET_PARAMETERS_MAX_NAMES) {
+            let output = client
+                .get_parameters()
+                .set_names(Some(chunk.to_vec()))
+                .with_decryption(true)
+                .send()
+                .await
+                .context("Failed to read variables from AWS Parameter Store")?;
+            // Names which don't exist are reported as invalid rather than as errors
+            for parameter in output.parameters.unwrap_or_default() {
+                if let (Some(name), Some(value)) = (parameter.name, parameter.value) {
+                    values.insert(name, value);
+                }
+            }
+        }
+        Ok(names
+            .iter()
+            .map(|name| values.remove(name).map(|value| (value, None)))
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        "aws_parameter_store"
+    }
+}
+
+/// Loads the AWS SDK config using the standard credential chain, optionally
+/// overriding the region.
+async fn load_sdk_config(region: Option<String>) -> aws_config::SdkConfig {
+    let mut loader = aws_config::defaults(BehaviorVersion::latest());
+    if let Some(region) = region {
+        loader = loader.region(Region::new(region));
+    }
+    loader.load().await
+}
+
+/// Maps a variable key to a remote name by joining it to the given prefix.
+fn prefixed_name(prefix: Option<&str>, key: &Key) -> String {
+    match prefix {
+        Some(prefix) if !prefix.is_empty() => {
+            format!("{}/{}", prefix.trim_end_matches('/'), key.as_str())
+        }
+        _ => key.as_str().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prefixed_name_joins_with_slash() {
+        let key = Key::new("db_password").unwrap();
+        assert_eq!(prefixed_name(None, &key), "db_password");
+        assert_eq!(prefixed_name(Some(""), &key), "db_password");
+        assert_eq!(prefixed_name(Some("my-app"), &key), "my-app/db_password");
+        assert_eq!(
+            prefixed_name(Some("/my-app/prod/"), &key),
+            "/my-app/prod/db_password"
+        );
+    }
+}
@@ -151,6 +151,10 @@ impl Provider for AzureKeyVaultProvider {
             .context("Failed to read variable from Azure Key Vault")?;
         Ok(Some(secret.value))
     }
+
+    fn name(&self) -> &str {
+        "azure_key_vault"
+    }
 }
 
 impl From<AzureAuthorityHost> for Url {
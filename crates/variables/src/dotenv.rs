@@ -0,0 +1,134 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use futures::stream::BoxStream;
+use serde::Deserialize;
+use spin_expressions::{Key, Provider};
+use spin_factors::anyhow::{self, Context as _};
+use spin_world::async_trait;
+use tracing::{instrument, Level};
+
+use crate::file::WatchedValues;
+
+/// Configuration for the dotenv variables provider.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DotenvVariablesConfig {
+    /// The path to the 'dotenv' file. Defaults to `.env`.
+    #[serde(default = "default_dotenv_path")]
+    pub path: PathBuf,
+    /// A prefix to add to variable names when looking them up in the file.
+    ///
+    /// Unless empty, joined to the variable name with an underscore.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Whether to reload the file when it changes.
+    ///
+    /// If set, changes to variable values are also reported to subscribers.
+    #[serde(default)]
+    pub watch: bool,
+}
+
+fn default_dotenv_path() -> PathBuf {
+    ".env".into()
+}
+
+/// A [`Provider`] that reads variables from a 'dotenv' file.
+///
+/// Unlike the environment variables provider, values are read only from the
+/// file, and variable names are looked up as upper-cased keys, e.g. the
+/// variable `db_url` is read from `DB_URL`.
+#[derive(Debug)]
+pub struct DotenvVariablesProvider {
+    prefix: Option<String>,
+    values: Arc<WatchedValues>,
+}
+
+impl DotenvVariablesProvider {
+    /// Creates a new `DotenvVariablesProvider`, loading the file immediately.
+    pub fn new(config: DotenvVariablesConfig) -> anyhow::Result<Self> {
+        let values = WatchedValues::new(config.path, config.watch, |path| {
+            dotenvy::from_path_iter(path)
+                .with_context(|| format!("failed to read dotenv file {path:?}"))?
+                .collect::<Result<HashMap<_, _>, _>>()
+                .with_context(|| format!("failed to parse dotenv file {path:?}"))
+        })?;
+        Ok(Self {
+            prefix: config.prefix,
+            values: Arc::new(values),
+        })
+    }
+
+    fn dotenv_key(&self, key: &Key) -> String {
+        let upper_key = key.as_str().to_ascii_uppercase();
+        match self.prefix.as_deref() {
+            Some(prefix) if !prefix.is_empty() => format!("{prefix}_{upper_key}"),
+            _ => upper_key,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for DotenvVariablesProvider {
+    #[instrument(name = "spin_variables.get_from_dotenv", level = Level::DEBUG, skip(self), err(level = Level::INFO))]
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        let values = self.values.current_async().await?;
+        Ok(values.get(&self.dotenv_key(key)).cloned())
+    }
+
+    fn name(&self) -> &str {
+        "dotenv"
+    }
+
+    fn subscribe(&self, key: &Key) -> Option<BoxStream<'static, ()>> {
+        self.values.subscribe_as(self.dotenv_key(key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn provider_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(
+            &path,
+            "DB_URL=postgres://db\nAPP_DB_URL=postgres://app-db\n",
+        )
+        .unwrap();
+
+        let key = Key::new("db_url").unwrap();
+        let provider = DotenvVariablesProvider::new(DotenvVariablesConfig {
+            path: path.clone(),
+            prefix: None,
+            watch: false,
+        })
+        .unwrap();
+        assert_eq!(
+            provider.get(&key).await.unwrap().as_deref(),
+            Some("postgres://db")
+        );
+
+        let provider = DotenvVariablesProvider::new(DotenvVariablesConfig {
+            path,
+            prefix: Some("APP".into()),
+            watch: true,
+        })
+        .unwrap();
+        assert_eq!(
+            provider.get(&key).await.unwrap().as_deref(),
+            Some("postgres://app-db")
+        );
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        DotenvVariablesProvider::new(DotenvVariablesConfig {
+            path: "definitely/not/a/real/.env".into(),
+            prefix: None,
+            watch: false,
+        })
+        .unwrap_err();
+    }
+}
@@ -2,15 +2,18 @@ use std::{
     collections::HashMap,
     env::VarError,
     path::{Path, PathBuf},
-    sync::OnceLock,
+    sync::{Arc, OnceLock},
 };
 
+use futures::stream::BoxStream;
 use serde::Deserialize;
 use spin_expressions::{Key, Provider};
-use spin_factors::anyhow::{self, Context as _};
+use spin_factors::anyhow;
 use spin_world::async_trait;
 use tracing::{instrument, Level};
 
+use crate::watch::{poll_changes, POLL_INTERVAL};
+
 /// Configuration for the environment variables provider.
 #[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -27,7 +30,7 @@ pub struct EnvVariablesConfig {
 
 const DEFAULT_ENV_PREFIX: &str = "SPIN_VARIABLE";
 
-type EnvFetcherFn = Box<dyn Fn(&str) -> Result<String, VarError> + Send + Sync>;
+type EnvFetcherFn = Arc<dyn Fn(&str) -> Result<String, VarError> + Send + Sync>;
 
 /// A [`Provider`] that uses environment variables.
 pub struct EnvVariablesProvider {
@@ -41,7 +44,7 @@ impl Default for EnvVariablesProvider {
     fn default() -> Self {
         Self {
             prefix: None,
-            env_fetcher: Box::new(|s| std::env::var(s)),
+            env_fetcher: Arc::new(|s| std::env::var(s)),
             dotenv_path: Some(".env".into()),
             dotenv_cache: Default::default(),
         }
@@ -64,31 +67,32 @@ impl EnvVariablesProvider {
         Self {
             prefix: prefix.map(Into::into),
             dotenv_path,
-            env_fetcher: Box::new(env_fetcher),
+            env_fetcher: Arc::new(env_fetcher),
             dotenv_cache: Default::default(),
         }
     }
 
     /// Gets the value of a variable from the environment.
     fn get_sync(&self, key: &Key) -> anyhow::Result<Option<String>> {
-        let prefix = self
-            .prefix
-            .clone()
-            .unwrap_or_else(|| DEFAULT_ENV_PREFIX.to_string());
+        self.query_env(&self.env_key(key))
+    }
 
+    /// Returns the name of the environment variable for the given key.
+    fn env_key(&self, key: &Key) -> String {
+        let prefix = self.prefix.as_deref().unwrap_or(DEFAULT_ENV_PREFIX);
         let upper_key = key.as_ref().to_ascii_uppercase();
-        let env_key = format!("{prefix}_{upper_key}");
-
-        self.query_env(&env_key)
+        format!("{prefix}_{upper_key}")
     }
 
     /// Queries the environment for a variable defaulting to dotenv.
     fn query_env(&self, env_key: &str) -> anyhow::Result<Option<String>> {
         match (self.env_fetcher)(env_key) {
-            Err(std::env::VarError::NotPresent) => self.get_dotenv(env_key),
-            other => other
-                .map(Some)
-                .with_context(|| format!("failed to resolve env var {env_key}")),
+            Ok(value) => Ok(Some(value)),
+            Err(VarError::NotPresent) => self.get_dotenv(env_key),
+            // The error's Debug output would include the (possibly secret) value
+            Err(VarError::NotUnicode(_)) => {
+                anyhow::bail!("failed to resolve env var {env_key}: value is not valid unicode")
+            }
         }
     }
 
@@ -132,6 +136,20 @@ impl Provider for EnvVariablesProvider {
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
         tokio::task::block_in_place(|| self.get_sync(key))
     }
+
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    fn subscribe(&self, key: &Key) -> Option<BoxStream<'static, ()>> {
+        let env_key = self.env_key(key);
+        // The dotenv file is only read once, so its value can't change
+        let dotenv_value = self.get_dotenv(&env_key).ok().flatten();
+        let env_fetcher = self.env_fetcher.clone();
+        Some(poll_changes(POLL_INTERVAL, move || {
+            env_fetcher(&env_key).ok().or_else(|| dotenv_value.clone())
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use spin_expressions::{Key, Provider};
+use spin_factors::anyhow::{self, Context as _};
+use spin_world::async_trait;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    process::{Child, ChildStdin, ChildStdout, Command},
+    sync::Mutex,
+};
+use tracing::{instrument, Level};
+
+/// The protocol versions this provider supports, newest first.
+const PROTOCOL_VERSIONS: &[u32] = &[1];
+
+/// How long to wait for a response if the configuration doesn't say.
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+/// Configuration for the external process variables provider.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalVariablesConfig {
+    /// The program to run.
+    pub command: PathBuf,
+    /// Arguments to pass to the program.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// How long to wait for the program to respond to each request, in
+    /// milliseconds. Defaults to 5000.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// A [`Provider`] that asks a separate program for variable values, so that
+/// bespoke secret stores can be used without changes to Spin.
+///
+/// The program is started on first use and speaks JSON over stdio, one
+/// message per line:
+///
+/// 1. Spin sends `{"type":"hello","versions":[1]}` listing the protocol
+///    versions it supports. The program replies with the version it chose,
+///    e.g. `{"version":1}`.
+/// 2. For each lookup, Spin sends `{"type":"get","id":1,"keys":["db_host"]}`.
+///    The program replies `{"id":1,"values":{"db_host":"..."}}`, omitting
+///    keys it has no value for, or `{"id":1,"error":"..."}`.
+///
+/// If the program doesn't respond within the timeout, or breaks the
+/// protocol, it is killed and started again for the next lookup.
+#[derive(Debug)]
+pub struct ExternalVariablesProvider {
+    command: PathBuf,
+    args: Vec<String>,
+    timeout: Duration,
+    process: Mutex<Option<Process>>,
+}
+
+impl ExternalVariablesProvider {
+    /// Creates a new `ExternalVariablesProvider`. The program isn't started
+    /// until the first lookup.
+    pub fn new(config: ExternalVariablesConfig) -> Self {
+        Self {
+            command: config.command,
+            args: config.args,
+            timeout: Duration::from_millis(config.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS)),
+            process: Default::default(),
+        }
+    }
+
+    async fn fetch(&self, keys: &[Key<'_>]) -> anyhow::Result<HashMap<String, String>> {
+        let mut process = self.process.lock().await;
+        let result = tokio::time::timeout(self.timeout, self.fetch_from(&mut process, keys)).await;
+        match result {
+            Ok(Ok(Ok(values))) => Ok(values),
+            // The program reported an error but is still usable
+            Ok(Ok(Err(error))) => Err(anyhow::anyhow!(
+                "variables provider {:?} failed: {error}",
+                self.command
+            )),
+            Ok(Err(err)) => {
+                *process = None;
+                Err(err)
+            }
+            Err(_) => {
+                *process = None;
+                anyhow::bail!(
+                    "variables provider {:?} did not respond within {:?}",
+                    self.command,
+                    self.timeout
+                )
+            }
+        }
+    }
+
+    async fn fetch_from(
+        &self,
+        process: &mut Option<Process>,
+        keys: &[Key<'_>],
+    ) -> anyhow::Result<Result<HashMap<String, String>, String>> {
+        if process.is_none() {
+            *process = Some(Process::start(&self.command, &self.args).await?);
+        }
+        let process = process.as_mut().unwrap();
+        process.next_id += 1;
+        let id = process.next_id;
+        let keys = keys.iter().map(|key| key.as_str()).collect();
+        let response: GetResponse = process.call(&Request::Get { id, keys }).await?;
+        anyhow::ensure!(
+            response.id == id,
+            "variables provider {:?} replied to request {} instead of {id}",
+            self.command,
+            response.id
+        );
+        Ok(match response.error {
+            Some(error) => Err(error),
+            None => Ok(response.values),
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for ExternalVariablesProvider {
+    #[instrument(name = "spin_variables.get_from_external", level = Level::DEBUG, skip(self), err(level = Level::INFO), fields(otel.kind = "client"))]
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        let mut values = self.fetch(std::slice::from_ref(key)).await?;
+        Ok(values.remove(key.as_str()))
+    }
+
+    async fn get_many_versioned(
+        &self,
+        keys: &[Key<'_>],
+    ) -> anyhow::Result<Vec<Option<(String, Option<String>)>>> {
+        let mut values = self.fetch(keys).await?;
+        Ok(keys
+            .iter()
+            .map(|key| values.remove(key.as_str()).map(|value| (value, None)))
+            .collect())
+    }
+
+    fn name(&self) -> &str {
+        "external"
+    }
+}
+
+/// A running provider program.
+#[derive(Debug)]
+struct Process {
+    // Held so that the program is killed when the process is dropped
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+}
+
+impl Process {
+    /// Starts the program and negotiates the protocol version.
+    async fn start(command: &Path, args: &[String]) -> anyhow::Result<Self> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("failed to start variables provider {command:?}"))?;
+        let stdin = child.stdin.take().context("missing stdin")?;
+        let stdout = BufReader::new(child.stdout.take().context("missing stdout")?).lines();
+        let mut process = Self {
+            _child: child,
+            stdin,
+            stdout,
+            next_id: 0,
+        };
+        let hello: HelloResponse = process
+            .call(&Request::Hello {
+                versions: PROTOCOL_VERSIONS,
+            })
+            .await
+            .with_context(|| format!("variables provider {command:?} failed the handshake"))?;
+        anyhow::ensure!(
+            PROTOCOL_VERSIONS.contains(&hello.version),
+            "variables provider {command:?} chose unsupported protocol version {}",
+            hello.version
+        );
+        Ok(process)
+    }
+
+    /// Sends a request and reads the response.
+    async fn call<T: DeserializeOwned>(&mut self, request: &Request<'_>) -> anyhow::Result<T> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        self.stdin.write_all(&line).await?;
+        self.stdin.flush().await?;
+        let response = self
+            .stdout
+            .next_line()
+            .await?
+            .context("variables provider exited")?;
+        serde_json::from_str(&response).context("invalid response from variables provider")
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request<'a> {
+    Hello { versions: &'a [u32] },
+    Get { id: u64, keys: Vec<&'a str> },
+}
+
+#[derive(Deserialize)]
+struct HelloResponse {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct GetResponse {
+    id: u64,
+    #[serde(default)]
+    values: HashMap<String, String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    fn shell_provider(script: &str, timeout_ms: u64) -> ExternalVariablesProvider {
+        ExternalVariablesProvider::new(ExternalVariablesConfig {
+            command: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            timeout_ms: Some(timeout_ms),
+        })
+    }
+
+    #[tokio::test]
+    async fn provider_get() {
+        let provider = shell_provider(
+            r#"
+            read hello
+            echo '{"version":1}'
+            while read request; do
+                id=$(echo "$request" | sed 's/.*"id":\([0-9]*\).*/\1/')
+                echo "{\"id\":$id,\"values\":{\"db_host\":\"example.com\"}}"
+            done
+            "#,
+            5000,
+        );
+        let key = Key::new("db_host").unwrap();
+        assert_eq!(
+            provider.get(&key).await.unwrap(),
+            Some("example.com".to_string())
+        );
+        let keys = [Key::new("db_host").unwrap(), Key::new("db_port").unwrap()];
+        assert_eq!(
+            provider.get_many(&keys).await.unwrap(),
+            [Some("example.com".to_string()), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn handshake_failure() {
+        // Echoes the hello request, which is not a valid hello response
+        let provider = shell_provider("cat", 5000);
+        let err = provider
+            .get(&Key::new("db_host").unwrap())
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("handshake"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let provider = shell_provider("sleep 10", 100);
+        let err = provider
+            .get(&Key::new("db_host").unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("did not respond"), "{err}");
+    }
+}
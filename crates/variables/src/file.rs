@@ -0,0 +1,341 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use futures::stream::BoxStream;
+use serde::Deserialize;
+use spin_expressions::{Key, Provider};
+use spin_factors::anyhow::{self, Context as _};
+use spin_world::async_trait;
+use tracing::{instrument, Level};
+
+use crate::watch::{poll_changes, POLL_INTERVAL};
+
+/// The format of a file read by the [`FileVariablesProvider`].
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl FileFormat {
+    /// Infers the format from the extension of the given path.
+    fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(Self::Json),
+            Some("toml") => Ok(Self::Toml),
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            _ => anyhow::bail!(
+                "cannot infer the format of variables file {path:?}; set `format` to one of 'json', 'toml' or 'yaml'"
+            ),
+        }
+    }
+
+    fn parse(&self, contents: &str) -> anyhow::Result<serde_json::Value> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(contents)?,
+            Self::Toml => serde_json::to_value(toml::from_str::<toml::Table>(contents)?)?,
+            Self::Yaml => serde_yaml::from_str(contents)?,
+        })
+    }
+}
+
+/// Configuration for the file variables provider.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileVariablesConfig {
+    /// The path to a JSON, TOML or YAML file containing variable values.
+    pub path: PathBuf,
+    /// The format of the file. If not set, it is inferred from the file extension.
+    #[serde(default)]
+    pub format: Option<FileFormat>,
+    /// Whether to reload the file when it changes.
+    ///
+    /// If set, changes to variable values are also reported to subscribers.
+    #[serde(default)]
+    pub watch: bool,
+}
+
+/// A [`Provider`] that reads variables from a JSON, TOML or YAML file.
+///
+/// The file must contain a table at the top level. Nested tables are flattened
+/// by joining keys with an underscore, so `{ db = { host = "..." } }` provides
+/// the variable `db_host`. Numbers and booleans are provided as strings.
+#[derive(Debug)]
+pub struct FileVariablesProvider {
+    values: Arc<WatchedValues>,
+}
+
+impl FileVariablesProvider {
+    /// Creates a new `FileVariablesProvider`, loading the file immediately.
+    pub fn new(config: FileVariablesConfig) -> anyhow::Result<Self> {
+        let format = match config.format {
+            Some(format) => format,
+            None => FileFormat::from_path(&config.path)?,
+        };
+        let values = WatchedValues::new(config.path, config.watch, move |path| {
+            load_file(path, format)
+        })?;
+        Ok(Self {
+            values: Arc::new(values),
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for FileVariablesProvider {
+    #[instrument(name = "spin_variables.get_from_file", level = Level::DEBUG, skip(self), err(level = Level::INFO))]
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        let values = self.values.current_async().await?;
+        Ok(values.get(key.as_str()).cloned())
+    }
+
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn subscribe(&self, key: &Key) -> Option<BoxStream<'static, ()>> {
+        self.values.subscribe(key)
+    }
+}
+
+fn load_file(path: &Path, format: FileFormat) -> anyhow::Result<HashMap<String, String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read variables file {path:?}"))?;
+    let value = format
+        .parse(&contents)
+        .with_context(|| format!("failed to parse variables file {path:?}"))?;
+    let serde_json::Value::Object(table) = value else {
+        anyhow::bail!("variables file {path:?} must contain a table at the top level");
+    };
+    let mut values = HashMap::new();
+    flatten_into(&mut values, None, table)
+        .with_context(|| format!("invalid variables file {path:?}"))?;
+    Ok(values)
+}
+
+fn flatten_into(
+    values: &mut HashMap<String, String>,
+    prefix: Option<&str>,
+    table: serde_json::Map<String, serde_json::Value>,
+) -> anyhow::Result<()> {
+    for (key, value) in table {
+        let key = match prefix {
+            Some(prefix) => format!("{prefix}_{key}"),
+            None => key,
+        };
+        let value = match value {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::Bool(b) => b.to_string(),
+            serde_json::Value::Object(table) => {
+                flatten_into(values, Some(&key), table)?;
+                continue;
+            }
+            serde_json::Value::Null | serde_json::Value::Array(_) => {
+                anyhow::bail!("value of {key:?} must be a string, number, boolean or table")
+            }
+        };
+        values.insert(key, value);
+    }
+    Ok(())
+}
+
+type LoadFn = Box<dyn Fn(&Path) -> anyhow::Result<HashMap<String, String>> + Send + Sync>;
+
+/// Values loaded from a file, optionally reloaded when the file changes.
+pub(crate) struct WatchedValues {
+    path: PathBuf,
+    watch: bool,
+    load: LoadFn,
+    loaded: Mutex<Loaded>,
+}
+
+struct Loaded {
+    /// The modification time of the file when it was loaded.
+    modified: Option<SystemTime>,
+    values: Arc<HashMap<String, String>>,
+}
+
+impl WatchedValues {
+    pub(crate) fn new(
+        path: PathBuf,
+        watch: bool,
+        load: impl Fn(&Path) -> anyhow::Result<HashMap<String, String>> + Send + Sync + 'static,
+    ) -> anyhow::Result<Self> {
+        let modified = modified_time(&path);
+        let values = load(&path)?;
+        Ok(Self {
+            path,
+            watch,
+            load: Box::new(load),
+            loaded: Mutex::new(Loaded {
+                modified,
+                values: Arc::new(values),
+            }),
+        })
+    }
+
+    /// Returns the current values, reloading them first if watching is
+    /// enabled and the file has changed since it was last loaded.
+    pub(crate) fn current(&self) -> Arc<HashMap<String, String>> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if self.watch {
+            let modified = modified_time(&self.path);
+            if modified != loaded.modified {
+                match (self.load)(&self.path) {
+                    Ok(values) => {
+                        *loaded = Loaded {
+                            modified,
+                            values: Arc::new(values),
+                        }
+                    }
+                    // Keep serving the last good values, e.g. if the file is mid-write
+                    Err(err) => {
+                        tracing::warn!("failed to reload variables from {:?}: {err:#}", self.path)
+                    }
+                }
+            }
+        }
+        loaded.values.clone()
+    }
+
+    /// Like [`WatchedValues::current`], but any reload runs on a blocking
+    /// thread rather than blocking the async runtime.
+    pub(crate) async fn current_async(
+        self: &Arc<Self>,
+    ) -> anyhow::Result<Arc<HashMap<String, String>>> {
+        if !self.watch {
+            return Ok(self.loaded.lock().unwrap().values.clone());
+        }
+        let values = self.clone();
+        Ok(tokio::task::spawn_blocking(move || values.current()).await?)
+    }
+
+    /// Subscribes to changes to the value of the given key, if watching is
+    /// enabled.
+    pub(crate) fn subscribe(self: &Arc<Self>, key: &Key) -> Option<BoxStream<'static, ()>> {
+        self.subscribe_as(key.as_str().to_owned())
+    }
+
+    /// Like [`WatchedValues::subscribe`], for a key which has already been
+    /// mapped to its name in the file.
+    pub(crate) fn subscribe_as(self: &Arc<Self>, name: String) -> Option<BoxStream<'static, ()>> {
+        if !self.watch {
+            return None;
+        }
+        let values = self.clone();
+        Some(poll_changes(POLL_INTERVAL, move || {
+            values.current().get(&name).cloned()
+        }))
+    }
+}
+
+impl std::fmt::Debug for WatchedValues {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WatchedValues")
+            .field("path", &self.path)
+            .field("watch", &self.watch)
+            .finish()
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn load_str(contents: &str, format: FileFormat) -> anyhow::Result<HashMap<String, String>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("variables");
+        std::fs::write(&path, contents)?;
+        load_file(&path, format)
+    }
+
+    #[test]
+    fn loads_all_formats() {
+        for (contents, format) in [
+            (
+                r#"{"host": "localhost", "db": {"port": 5432, "tls": true}}"#,
+                FileFormat::Json,
+            ),
+            (
+                "host = 'localhost'\n[db]\nport = 5432\ntls = true",
+                FileFormat::Toml,
+            ),
+            (
+                "host: localhost\ndb:\n  port: 5432\n  tls: true",
+                FileFormat::Yaml,
+            ),
+        ] {
+            let values = load_str(contents, format).unwrap();
+            assert_eq!(values["host"], "localhost", "{format:?}");
+            assert_eq!(values["db_port"], "5432", "{format:?}");
+            assert_eq!(values["db_tls"], "true", "{format:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_non_scalar_values() {
+        load_str(r#"{"hosts": ["a", "b"]}"#, FileFormat::Json).unwrap_err();
+        load_str(r#"["a", "b"]"#, FileFormat::Json).unwrap_err();
+    }
+
+    #[test]
+    fn infers_format_from_extension() {
+        assert_eq!(
+            FileFormat::from_path(Path::new("vars.yml")).unwrap(),
+            FileFormat::Yaml
+        );
+        FileFormat::from_path(Path::new("vars.ini")).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn provider_get_on_current_thread_runtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vars.json");
+        std::fs::write(&path, r#"{"db": {"url": "postgres://db"}}"#).unwrap();
+        let key = Key::new("db_url").unwrap();
+        for watch in [false, true] {
+            let provider = FileVariablesProvider::new(FileVariablesConfig {
+                path: path.clone(),
+                format: None,
+                watch,
+            })
+            .unwrap();
+            assert_eq!(
+                provider.get(&key).await.unwrap().as_deref(),
+                Some("postgres://db")
+            );
+        }
+    }
+
+    #[test]
+    fn watched_values_reload_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vars.json");
+        std::fs::write(&path, r#"{"a": "1"}"#).unwrap();
+        let values =
+            WatchedValues::new(path.clone(), true, |p| load_file(p, FileFormat::Json)).unwrap();
+        assert_eq!(values.current()["a"], "1");
+
+        std::fs::write(&path, r#"{"a": "2"}"#).unwrap();
+        // Force a distinct modification time regardless of filesystem resolution
+        let later = SystemTime::now() + std::time::Duration::from_secs(10);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(values.current()["a"], "2");
+    }
+}
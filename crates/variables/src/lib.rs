@@ -1,23 +1,45 @@
 //! The runtime configuration for the variables factor used in the Spin CLI.
 
+mod aws;
 mod azure_key_vault;
+mod dotenv;
 mod env;
+mod external;
+mod file;
+mod snapshot;
 mod statik;
 mod vault;
+mod watch;
 
+pub use aws::*;
 pub use azure_key_vault::*;
+pub use dotenv::*;
 pub use env::*;
+pub use external::*;
+pub use file::*;
+pub use snapshot::*;
 pub use statik::*;
 pub use vault::*;
 
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
 use serde::Deserialize;
-use spin_expressions::Provider;
+use spin_expressions::{CachedProvider, Provider, RoutedProvider};
 use spin_factors::{anyhow, runtime_config::toml::GetTomlValue};
 
 use spin_factor_variables::runtime_config::RuntimeConfig;
 
 /// Resolves a runtime configuration for the variables factor from a TOML table.
 pub fn runtime_config_from_toml(table: &impl GetTomlValue) -> anyhow::Result<RuntimeConfig> {
+    let trusted_components = match table.get("variables") {
+        Some(value) => {
+            value
+                .clone()
+                .try_into::<VariablesConfig>()?
+                .trusted_components
+        }
+        None => vec![],
+    };
     // Always include the environment variable provider.
     let var_provider = vec![Box::<EnvVariablesProvider>::default() as _];
     let value = table
@@ -26,6 +48,7 @@ pub fn runtime_config_from_toml(table: &impl GetTomlValue) -> anyhow::Result<Run
     let Some(array) = value else {
         return Ok(RuntimeConfig {
             providers: var_provider,
+            trusted_components,
         });
     };
 
@@ -35,13 +58,35 @@ pub fn runtime_config_from_toml(table: &impl GetTomlValue) -> anyhow::Result<Run
         .map(VariableProviderConfiguration::into_provider)
         .collect::<anyhow::Result<Vec<_>>>()?;
     providers.extend(var_provider);
-    Ok(RuntimeConfig { providers })
+    Ok(RuntimeConfig {
+        providers,
+        trusted_components,
+    })
+}
+
+/// Runtime configuration for variables which isn't specific to a provider.
+///
+/// ```toml
+/// [variables]
+/// trusted_components = ["admin"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariablesConfig {
+    /// Components which may read any application variable, not just those in
+    /// their own `variables` table.
+    #[serde(default)]
+    pub trusted_components: Vec<String>,
 }
 
 /// A runtime configuration used in the Spin CLI for one type of variable provider.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum VariableProviderConfiguration {
+    /// A provider that uses AWS Secrets Manager.
+    AwsSecretsManager(AwsSecretsManagerVariablesConfig),
+    /// A provider that uses AWS Systems Manager Parameter Store.
+    AwsParameterStore(AwsParameterStoreVariablesConfig),
     /// A provider that uses Azure Key Vault.
     AzureKeyVault(AzureKeyVaultVariablesConfig),
     /// A static provider of variables.
@@ -50,6 +95,78 @@ pub enum VariableProviderConfiguration {
     Vault(VaultVariablesProvider),
     /// An environment variable provider.
     Env(EnvVariablesConfig),
+    /// A provider that reads a 'dotenv' file.
+    Dotenv(DotenvVariablesConfig),
+    /// A provider that reads a JSON, TOML or YAML file.
+    File(FileVariablesConfig),
+    /// A provider that asks a separate program for values.
+    External(ExternalVariablesConfig),
+    /// A provider that reads a variables snapshot.
+    Snapshot(SnapshotVariablesConfig),
+    /// A provider that routes variables to other providers by name prefix.
+    Routed(RoutedVariablesConfig),
+    /// A provider that caches the values of another provider.
+    Cached(CachedVariablesConfig),
+}
+
+/// Configuration for a provider that caches the values of another provider.
+///
+/// ```toml
+/// [[variables_provider]]
+/// type = "cached"
+/// ttl_secs = 300
+/// stale_while_revalidate_secs = 60
+/// key_ttl_secs = { db_password = 30 }
+/// provider = { type = "vault", url = "http://127.0.0.1:8200", token = "root", mount = "secret" }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CachedVariablesConfig {
+    /// How long to reuse values for, in seconds.
+    pub ttl_secs: u64,
+    /// How long to remember that the provider has no value for a variable,
+    /// in seconds. Defaults to `ttl_secs`.
+    #[serde(default)]
+    pub negative_ttl_secs: Option<u64>,
+    /// How long after a value expires it may still be used while a fresh
+    /// value is fetched in the background, in seconds. Defaults to 0.
+    #[serde(default)]
+    pub stale_while_revalidate_secs: u64,
+    /// TTLs for particular variables, in seconds, overriding `ttl_secs`.
+    #[serde(default)]
+    pub key_ttl_secs: HashMap<String, u64>,
+    /// The provider whose values are cached.
+    pub provider: Box<VariableProviderConfiguration>,
+}
+
+/// Configuration for a provider that routes variables to other providers by
+/// name prefix.
+///
+/// ```toml
+/// [[variables_provider]]
+/// type = "routed"
+///
+/// [[variables_provider.routes]]
+/// prefix = "db_"
+/// provider = { type = "vault", url = "http://127.0.0.1:8200", token = "root", mount = "secret" }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutedVariablesConfig {
+    /// The routes. Each variable is routed to the provider with the longest
+    /// matching prefix.
+    pub routes: Vec<RouteConfig>,
+}
+
+/// A route of a [`RoutedVariablesConfig`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    /// The prefix of the variable names to route. An empty prefix matches
+    /// all variables.
+    pub prefix: String,
+    /// The provider for variables with the prefix.
+    pub provider: VariableProviderConfiguration,
 }
 
 impl VariableProviderConfiguration {
@@ -62,10 +179,49 @@ impl VariableProviderConfiguration {
                 |s| std::env::var(s),
                 config.dotenv_path,
             )),
+            VariableProviderConfiguration::Dotenv(config) => {
+                Box::new(DotenvVariablesProvider::new(config)?)
+            }
+            VariableProviderConfiguration::File(config) => {
+                Box::new(FileVariablesProvider::new(config)?)
+            }
+            VariableProviderConfiguration::External(config) => {
+                Box::new(ExternalVariablesProvider::new(config))
+            }
+            VariableProviderConfiguration::Snapshot(config) => {
+                Box::new(SnapshotVariablesProvider::new(config)?)
+            }
             VariableProviderConfiguration::Vault(provider) => Box::new(provider),
+            VariableProviderConfiguration::AwsSecretsManager(config) => {
+                Box::new(AwsSecretsManagerProvider::new(config))
+            }
+            VariableProviderConfiguration::AwsParameterStore(config) => {
+                Box::new(AwsParameterStoreProvider::new(config))
+            }
             VariableProviderConfiguration::AzureKeyVault(config) => Box::new(
                 AzureKeyVaultProvider::create(config.vault_url.clone(), config.try_into()?)?,
             ),
+            VariableProviderConfiguration::Routed(config) => {
+                let mut routed = RoutedProvider::new();
+                for route in config.routes {
+                    routed.route(route.prefix, route.provider.into_provider()?);
+                }
+                Box::new(routed)
+            }
+            VariableProviderConfiguration::Cached(config) => {
+                let inner = Arc::from(config.provider.into_provider()?);
+                let mut cached = CachedProvider::new(inner, Duration::from_secs(config.ttl_secs))
+                    .with_stale_while_revalidate(Duration::from_secs(
+                        config.stale_while_revalidate_secs,
+                    ));
+                if let Some(negative_ttl_secs) = config.negative_ttl_secs {
+                    cached = cached.with_negative_ttl(Duration::from_secs(negative_ttl_secs));
+                }
+                for (key, ttl_secs) in config.key_ttl_secs {
+                    cached = cached.with_key_ttl(key, Duration::from_secs(ttl_secs));
+                }
+                Box::new(cached)
+            }
         };
         Ok(provider)
     }
@@ -0,0 +1,95 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::Deserialize;
+use spin_expressions::{Key, Provider, Snapshot};
+use spin_factors::anyhow::{self, Context as _};
+use spin_world::async_trait;
+
+/// Configuration for the snapshot variables provider.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotVariablesConfig {
+    /// The path to a snapshot written by `spin up --variables-snapshot`.
+    pub path: PathBuf,
+}
+
+/// A [`Provider`] that replays the values recorded in a variables [`Snapshot`].
+///
+/// Secret values are never recorded in snapshots, so they must come from
+/// another provider.
+#[derive(Debug)]
+pub struct SnapshotVariablesProvider {
+    values: HashMap<String, String>,
+}
+
+impl SnapshotVariablesProvider {
+    /// Creates a new `SnapshotVariablesProvider`, loading the snapshot immediately.
+    pub fn new(config: SnapshotVariablesConfig) -> anyhow::Result<Self> {
+        let contents = std::fs::read(&config.path)
+            .with_context(|| format!("failed to read variables snapshot {:?}", config.path))?;
+        let snapshot = Snapshot::from_json(&contents)
+            .with_context(|| format!("failed to parse variables snapshot {:?}", config.path))?;
+        Ok(Self::from_snapshot(snapshot))
+    }
+
+    /// Creates a new `SnapshotVariablesProvider` from a snapshot.
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        let values = snapshot
+            .variables
+            .into_iter()
+            .filter_map(|(key, variable)| Some((key, variable.value?)))
+            .collect();
+        Self { values }
+    }
+}
+
+#[async_trait]
+impl Provider for SnapshotVariablesProvider {
+    async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
+        Ok(self.values.get(key.as_str()).cloned())
+    }
+
+    fn name(&self) -> &str {
+        "snapshot"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_expressions::SnapshotVariable;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn provides_recorded_values() {
+        let snapshot = Snapshot {
+            profile: None,
+            variables: [
+                (
+                    "api_url".to_string(),
+                    SnapshotVariable {
+                        value: Some("https://example.com".into()),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "password".to_string(),
+                    SnapshotVariable {
+                        secret: true,
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+        };
+        let provider = SnapshotVariablesProvider::from_snapshot(snapshot);
+        assert_eq!(
+            provider.get(&Key::new("api_url").unwrap()).await.unwrap(),
+            Some("https://example.com".to_string())
+        );
+        assert_eq!(
+            provider.get(&Key::new("password").unwrap()).await.unwrap(),
+            None
+        );
+    }
+}
@@ -15,4 +15,8 @@ impl Provider for StaticVariablesProvider {
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>> {
         Ok(self.values.get(key.as_str()).cloned())
     }
+
+    fn name(&self) -> &str {
+        "static"
+    }
 }
@@ -52,4 +52,8 @@ impl Provider for VaultVariablesProvider {
             Err(e) => Err(e).context("Failed to check Vault for config"),
         }
     }
+
+    fn name(&self) -> &str {
+        "vault"
+    }
 }
@@ -0,0 +1,57 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::{stream::BoxStream, StreamExt};
+
+/// How often subscribed values are checked for changes.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Returns a stream which yields each time the value returned by `poll`
+/// changes, checking at the given interval. `poll` may block, and is called
+/// on a blocking thread.
+pub(crate) fn poll_changes(
+    interval: Duration,
+    poll: impl Fn() -> Option<String> + Send + Sync + 'static,
+) -> BoxStream<'static, ()> {
+    let poll = Arc::new(poll);
+    futures::stream::unfold((poll, None), move |(poll, mut last)| async move {
+        loop {
+            let current = {
+                let poll = poll.clone();
+                // End the stream if polling panicked
+                tokio::task::spawn_blocking(move || poll()).await.ok()?
+            };
+            match last.replace(current.clone()) {
+                // The first poll only establishes the starting value
+                None => (),
+                Some(previous) if previous != current => return Some(((), (poll, last))),
+                Some(_) => (),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn yields_on_change() {
+        let interval = Duration::from_millis(10);
+        let value = Arc::new(Mutex::new(Some("a".to_string())));
+        let mut changes = poll_changes(interval, {
+            let value = value.clone();
+            move || value.lock().unwrap().clone()
+        });
+
+        let next = tokio::spawn(async move { changes.next().await });
+        tokio::time::sleep(interval * 5).await;
+        assert!(!next.is_finished());
+
+        *value.lock().unwrap() = None;
+        assert_eq!(next.await.unwrap(), Some(()));
+    }
+}
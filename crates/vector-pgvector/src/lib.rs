@@ -0,0 +1,219 @@
+//! A vector store in a PostgreSQL table, searched with the pgvector
+//! extension.
+//!
+//! The extension must be installed in the database. The table is created if
+//! it does not exist, with `id`, `embedding` and `metadata` columns, and may
+//! be given an index to speed up queries.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::ensure;
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use spin_factor_vector::{metadata_from_json, metadata_to_json, Distance, VectorStore};
+use spin_world::spin::vector::types::{Error, Metadata, Point, ScoredPoint};
+use tokio::sync::Mutex;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+
+pub use tokio_postgres::Config;
+
+/// A vector store in a PostgreSQL table.
+pub struct PgVectorStore {
+    config: Config,
+    table: String,
+    distance: Distance,
+    /// The client shared by all instances, which is connected when the
+    /// store is first used and reconnected if the connection closes.
+    client: Mutex<Option<Arc<Client>>>,
+}
+
+impl PgVectorStore {
+    /// Create a store in the given table, which is a name of letters,
+    /// digits and underscores, optionally qualified by a schema name.
+    pub fn new(config: Config, table: &str, distance: Distance) -> anyhow::Result<Self> {
+        ensure!(
+            table.split('.').count() <= 2 && table.split('.').all(is_identifier),
+            "invalid pgvector table name {table:?}; names may only have letters, digits and underscores"
+        );
+        Ok(Self {
+            config,
+            table: table
+                .split('.')
+                .map(|name| format!("\"{name}\""))
+                .collect::<Vec<_>>()
+                .join("."),
+            distance,
+            client: Mutex::new(None),
+        })
+    }
+
+    async fn client(&self) -> Result<Arc<Client>, Error> {
+        let mut client = self.client.lock().await;
+        if let Some(client) = client.as_ref().filter(|client| !client.is_closed()) {
+            return Ok(client.clone());
+        }
+        let new = Arc::new(
+            spin_postgres_client::connect(&self.config)
+                .await
+                .map_err(|e| Error::Other(format!("failed to connect to PostgreSQL: {e:#}")))?,
+        );
+        new.batch_execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                id TEXT PRIMARY KEY,
+                embedding vector NOT NULL,
+                metadata JSONB NOT NULL
+            )",
+            self.table
+        ))
+        .await
+        .map_err(query_failed)?;
+        *client = Some(new.clone());
+        Ok(new)
+    }
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error> {
+        // A statement may not update a row twice, so only the last point
+        // with each ID is kept
+        let mut latest = HashMap::with_capacity(points.len());
+        for point in points {
+            latest.insert(point.id, (point.vector, point.metadata));
+        }
+        let mut ids = Vec::with_capacity(latest.len());
+        let mut embeddings = Vec::with_capacity(latest.len());
+        let mut metadata = Vec::with_capacity(latest.len());
+        for (id, (vector, meta)) in latest {
+            ids.push(id);
+            embeddings.push(vector_literal(&vector));
+            metadata.push(Value::Object(metadata_to_json(meta)).to_string());
+        }
+
+        let client = self.client().await?;
+        client
+            .execute(
+                &format!(
+                    "INSERT INTO {} (id, embedding, metadata)
+                    SELECT id, embedding::vector, metadata::jsonb
+                    FROM UNNEST($1::text[], $2::text[], $3::text[]) AS p (id, embedding, metadata)
+                    ON CONFLICT (id) DO UPDATE SET embedding = excluded.embedding, metadata = excluded.metadata",
+                    self.table
+                ),
+                &[&ids, &embeddings, &metadata],
+            )
+            .await
+            .map_err(query_failed)?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        limit: u32,
+        filter: Metadata,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        // The operators give distances, where lower is more similar, which
+        // are turned into scores
+        let (operator, score) = match self.distance {
+            Distance::Cosine => ("<=>", "1 - (embedding <=> $1::text::vector)"),
+            Distance::DotProduct => ("<#>", "(embedding <#> $1::text::vector) * -1"),
+        };
+        let filter = Value::Object(metadata_to_json(filter)).to_string();
+
+        let client = self.client().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT id, metadata::text, ({score})::real FROM {}
+                    WHERE metadata @> $2::text::jsonb
+                    ORDER BY embedding {operator} $1::text::vector
+                    LIMIT $3",
+                    self.table
+                ),
+                &[&vector_literal(&vector), &filter, &i64::from(limit)],
+            )
+            .await
+            .map_err(query_failed)?;
+        rows.iter()
+            .map(|row| {
+                let metadata: String = row.try_get(1).map_err(query_failed)?;
+                let metadata: Map<String, Value> = serde_json::from_str(&metadata)
+                    .map_err(|e| Error::Other(format!("invalid metadata: {e}")))?;
+                Ok(ScoredPoint {
+                    id: row.try_get(0).map_err(query_failed)?,
+                    score: row.try_get(2).map_err(query_failed)?,
+                    metadata: metadata_from_json(metadata),
+                })
+            })
+            .collect()
+    }
+
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error> {
+        let client = self.client().await?;
+        client
+            .execute(
+                &format!("DELETE FROM {} WHERE id = ANY($1)", self.table),
+                &[&ids],
+            )
+            .await
+            .map_err(query_failed)?;
+        Ok(())
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("pgvector table {}", self.table))
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Formats a vector as pgvector's text representation, e.g. `[1,0.5]`.
+fn vector_literal(vector: &[f32]) -> String {
+    let elements = vector
+        .iter()
+        .map(f32::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("[{elements}]")
+}
+
+fn query_failed(e: tokio_postgres::Error) -> Error {
+    match e.as_db_error() {
+        // pgvector reports vectors of different dimensions as data exceptions
+        Some(db_error) if db_error.code() == &SqlState::DATA_EXCEPTION => {
+            Error::InvalidInput(db_error.message().to_owned())
+        }
+        Some(db_error) => Error::Other(db_error.message().to_owned()),
+        None => Error::Other(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_names_are_quoted_identifiers() {
+        let config: Config = "host=localhost".parse().unwrap();
+        let store = PgVectorStore::new(config.clone(), "rag.documents", Distance::Cosine).unwrap();
+        assert_eq!(store.table, r#""rag"."documents""#);
+        for table in ["", "docs; DROP TABLE x", "a.b.c", "1docs", "docs\""] {
+            assert!(
+                PgVectorStore::new(config.clone(), table, Distance::Cosine).is_err(),
+                "{table:?} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn vectors_are_formatted_for_pgvector() {
+        assert_eq!(vector_literal(&[1.0, 0.5, -2.25]), "[1,0.5,-2.25]");
+    }
+}
@@ -0,0 +1,273 @@
+//! A vector store kept in a local SQLite database.
+//!
+//! Vectors are stored as blobs and queries compare the query vector with
+//! every stored vector which passes the filter, so results are exact but
+//! queries take time proportional to the size of the store.
+
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use rusqlite::{params, OptionalExtension};
+use serde_json::{Map, Value};
+use spin_factor_vector::{metadata_from_json, metadata_to_json, Distance, VectorStore};
+use spin_world::spin::vector::types::{Error, Metadata, Point, ScoredPoint};
+
+/// A vector store in a SQLite database.
+pub struct SqliteVectorStore {
+    /// The connection to the database, which is opened when the store is
+    /// first used.
+    connection: Arc<Mutex<Option<rusqlite::Connection>>>,
+    distance: Distance,
+    path: Option<PathBuf>,
+}
+
+impl SqliteVectorStore {
+    /// Create a store in the database at the given path, or in memory if
+    /// there is no path. The database is created when the store is first
+    /// used if it does not exist.
+    pub fn new(path: Option<PathBuf>, distance: Distance) -> Self {
+        Self {
+            connection: Default::default(),
+            distance,
+            path,
+        }
+    }
+
+    /// Runs a function with the connection on a blocking thread.
+    async fn with_connection<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut rusqlite::Connection) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        let connection = self.connection.clone();
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap();
+            if connection.is_none() {
+                *connection = Some(open(path.as_deref()).map_err(|e| other(format!("{e:#}")))?);
+            }
+            f(connection.as_mut().unwrap())
+        })
+        .await
+        .map_err(other)?
+    }
+}
+
+fn open(path: Option<&Path>) -> anyhow::Result<rusqlite::Connection> {
+    let connection = match path {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).with_context(|| {
+                    format!(
+                        "failed to create vector store directory '{}'",
+                        parent.display()
+                    )
+                })?;
+            }
+            rusqlite::Connection::open(path)
+        }
+        None => rusqlite::Connection::open_in_memory(),
+    }?;
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS spin_vector_points (
+            id TEXT PRIMARY KEY,
+            vector BLOB NOT NULL,
+            metadata TEXT NOT NULL
+        )",
+    )?;
+    Ok(connection)
+}
+
+#[async_trait]
+impl VectorStore for SqliteVectorStore {
+    async fn upsert(&self, points: Vec<Point>) -> Result<(), Error> {
+        self.with_connection(move |connection| {
+            let tx = connection.transaction().map_err(other)?;
+            let mut dimension = stored_dimension(&tx)?;
+            {
+                let mut statement = tx
+                    .prepare_cached(
+                        "INSERT INTO spin_vector_points (id, vector, metadata) VALUES (?1, ?2, ?3)
+                        ON CONFLICT (id) DO UPDATE SET vector = excluded.vector, metadata = excluded.metadata",
+                    )
+                    .map_err(other)?;
+                for point in points {
+                    check_dimension(*dimension.get_or_insert(point.vector.len()), &point.vector)?;
+                    statement
+                        .execute(params![
+                            point.id,
+                            encode_vector(&point.vector),
+                            Value::Object(metadata_to_json(point.metadata)).to_string(),
+                        ])
+                        .map_err(other)?;
+                }
+            }
+            tx.commit().map_err(other)
+        })
+        .await
+    }
+
+    async fn query(
+        &self,
+        vector: Vec<f32>,
+        limit: u32,
+        filter: Metadata,
+    ) -> Result<Vec<ScoredPoint>, Error> {
+        let distance = self.distance;
+        self.with_connection(move |connection| {
+            if let Some(dimension) = stored_dimension(connection)? {
+                check_dimension(dimension, &vector)?;
+            }
+            let filter = metadata_to_json(filter);
+            let mut statement = connection
+                .prepare_cached("SELECT id, vector, metadata FROM spin_vector_points")
+                .map_err(other)?;
+            let mut rows = statement.query([]).map_err(other)?;
+            let mut matches = vec![];
+            while let Some(row) = rows.next().map_err(other)? {
+                let metadata: String = row.get(2).map_err(other)?;
+                let metadata: Map<String, Value> =
+                    serde_json::from_str(&metadata).map_err(other)?;
+                if !filter.iter().all(|(k, v)| metadata.get(k) == Some(v)) {
+                    continue;
+                }
+                let stored: Vec<u8> = row.get(1).map_err(other)?;
+                matches.push(ScoredPoint {
+                    id: row.get(0).map_err(other)?,
+                    score: distance.score(&vector, &decode_vector(&stored)),
+                    metadata: metadata_from_json(metadata),
+                });
+            }
+            matches.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+            matches.truncate(limit as usize);
+            Ok(matches)
+        })
+        .await
+    }
+
+    async fn delete(&self, ids: Vec<String>) -> Result<(), Error> {
+        self.with_connection(move |connection| {
+            let tx = connection.transaction().map_err(other)?;
+            {
+                let mut statement = tx
+                    .prepare_cached("DELETE FROM spin_vector_points WHERE id = ?1")
+                    .map_err(other)?;
+                for id in ids {
+                    statement.execute([id]).map_err(other)?;
+                }
+            }
+            tx.commit().map_err(other)
+        })
+        .await
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(match &self.path {
+            Some(path) => format!("SQLite at {}", path.display()),
+            None => "SQLite in memory".to_owned(),
+        })
+    }
+}
+
+/// The dimension of the vectors in the store, if it has any.
+fn stored_dimension(connection: &rusqlite::Connection) -> Result<Option<usize>, Error> {
+    connection
+        .query_row(
+            "SELECT length(vector) FROM spin_vector_points LIMIT 1",
+            [],
+            |row| row.get::<_, usize>(0),
+        )
+        .optional()
+        .map(|bytes| bytes.map(|bytes| bytes / size_of::<f32>()))
+        .map_err(other)
+}
+
+fn check_dimension(dimension: usize, vector: &[f32]) -> Result<(), Error> {
+    if vector.len() != dimension {
+        return Err(Error::InvalidInput(format!(
+            "vector has dimension {}, but the store's vectors have dimension {dimension}",
+            vector.len()
+        )));
+    }
+    Ok(())
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(size_of::<f32>())
+        .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
+
+fn other(e: impl std::fmt::Display) -> Error {
+    Error::Other(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_world::spin::vector::types::MetadataValue;
+
+    use super::*;
+
+    fn point(id: &str, vector: Vec<f32>, kind: &str) -> Point {
+        Point {
+            id: id.into(),
+            vector,
+            metadata: vec![("kind".into(), MetadataValue::Text(kind.into()))],
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_most_similar_points() {
+        let store = SqliteVectorStore::new(None, Distance::Cosine);
+        store
+            .upsert(vec![
+                point("a", vec![1.0, 0.0], "doc"),
+                point("b", vec![0.0, 1.0], "doc"),
+                point("c", vec![0.9, 0.1], "faq"),
+            ])
+            .await
+            .unwrap();
+
+        let ids = |matches: Vec<ScoredPoint>| matches.into_iter().map(|m| m.id).collect::<Vec<_>>();
+        let matches = store.query(vec![1.0, 0.0], 2, vec![]).await.unwrap();
+        assert_eq!(ids(matches), ["a", "c"]);
+
+        let filter = vec![("kind".into(), MetadataValue::Text("doc".into()))];
+        let matches = store.query(vec![1.0, 0.0], 10, filter).await.unwrap();
+        assert_eq!(ids(matches), ["a", "b"]);
+
+        store
+            .upsert(vec![point("b", vec![1.0, 0.0], "doc")])
+            .await
+            .unwrap();
+        store.delete(vec!["a".into()]).await.unwrap();
+        let matches = store.query(vec![1.0, 0.0], 10, vec![]).await.unwrap();
+        assert_eq!(ids(matches), ["b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn refuses_vectors_of_another_dimension() {
+        let store = SqliteVectorStore::new(None, Distance::DotProduct);
+        store
+            .upsert(vec![point("a", vec![1.0, 0.0], "doc")])
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.upsert(vec![point("b", vec![1.0], "doc")]).await,
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            store.query(vec![1.0, 0.0, 0.0], 1, vec![]).await,
+            Err(Error::InvalidInput(_))
+        ));
+    }
+}
@@ -12,6 +12,14 @@ wasmtime::component::bindgen!({
         include fermyon:spin/platform@3.0.0;
         include spin:up/platform@3.2.0;
         include wasi:keyvalue/imports@0.2.0-draft2;
+        export spin:amqp/inbound-amqp@3.0.0;
+        export spin:grpc/inbound-grpc@3.0.0;
+        export spin:health/health-check@3.0.0;
+        export spin:jobs/inbound-jobs@3.0.0;
+        export spin:kafka/inbound-kafka@3.0.0;
+        export spin:nats/inbound-nats@3.0.0;
+        export spin:queue/inbound-queue@3.0.0;
+        export spin:tcp/inbound-tcp@3.0.0;
     }
     "#,
     path: "../../wit",
@@ -31,8 +39,19 @@ wasmtime::component::bindgen!({
         "fermyon:spin/sqlite@2.0.0/error" => v2::sqlite::Error,
         "fermyon:spin/sqlite/error" => v1::sqlite::Error,
         "fermyon:spin/variables@2.0.0/error" => v2::variables::Error,
+        "spin:amqp/types/error" => spin::amqp::types::Error,
+        "spin:blob-store/types/error" => spin::blob_store::types::Error,
+        "spin:email/types/error" => spin::email::types::Error,
+        "spin:jobs/types/error" => spin::jobs::types::Error,
+        "spin:kafka/types/error" => spin::kafka::types::Error,
+        "spin:locks/locks/error" => spin::locks::locks::Error,
+        "spin:nats/types/error" => spin::nats::types::Error,
+        "spin:observe/types/error" => spin::observe::types::Error,
         "spin:postgres/postgres/error" => spin::postgres::postgres::Error,
+        "spin:sessions/types/error" => spin::sessions::types::Error,
         "spin:sqlite/sqlite/error" => spin::sqlite::sqlite::Error,
+        "spin:tcp/types/error" => spin::tcp::types::Error,
+        "spin:vector/types/error" => spin::vector::types::Error,
         "wasi:config/store@0.2.0-draft-2024-09-27/error" => wasi::config::store::Error,
         "wasi:keyvalue/store/error" => wasi::keyvalue::store::Error,
         "wasi:keyvalue/atomics/cas-error" => wasi::keyvalue::atomics::CasError,
@@ -1,3 +1,5 @@
+use std::ffi::OsString;
+
 use anyhow::{Context, Error};
 use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use lazy_static::lazy_static;
@@ -5,11 +7,13 @@ use spin_cli::commands::external::predefined_externals;
 use spin_cli::commands::maintenance::MaintenanceCommands;
 use spin_cli::commands::{
     build::BuildCommand,
+    bundle::BundleCommand,
     cloud::{DeployCommand, LoginCommand},
     doctor::DoctorCommand,
     external::execute_external_subcommand,
     new::{AddCommand, NewCommand},
     plugins::PluginCommands,
+    precompile::PrecompileCommand,
     registry::RegistryCommands,
     templates::TemplateCommands,
     up::UpCommand,
@@ -19,8 +23,16 @@ use spin_cli::{build_info::*, subprocess::ExitStatusError};
 use spin_runtime_factors::FactorsBuilder;
 use spin_trigger::cli::help::HelpArgsOnlyTrigger;
 use spin_trigger::cli::FactorsTriggerCommand;
+use spin_trigger_amqp::AmqpTrigger;
+use spin_trigger_cron::CronTrigger;
+use spin_trigger_grpc::GrpcTrigger;
 use spin_trigger_http::HttpTrigger;
+use spin_trigger_job::JobTrigger;
+use spin_trigger_kafka::KafkaTrigger;
+use spin_trigger_nats::NatsTrigger;
+use spin_trigger_queue::QueueTrigger;
 use spin_trigger_redis::RedisTrigger;
+use spin_trigger_tcp::TcpTrigger;
 
 #[tokio::main]
 async fn main() {
@@ -65,7 +77,7 @@ async fn _main() -> anyhow::Result<()> {
         cmd = cmd.after_help("* implemented via plugin");
     }
 
-    let matches = cmd.clone().get_matches();
+    let matches = cmd.clone().get_matches_from(args());
 
     if let Some((subcmd, _)) = matches.subcommand() {
         if plugin_help_entries.iter().any(|e| e.name == subcmd) {
@@ -80,6 +92,32 @@ async fn _main() -> anyhow::Result<()> {
         .inspect_err(|err| tracing::debug!(?err))
 }
 
+/// The command line arguments. If this executable has an app embedded in it
+/// (see `spin bundle --executable`), running it with no subcommand, or with
+/// `up`, runs the app.
+fn args() -> Vec<OsString> {
+    let mut args = std::env::args_os();
+    let exe = args.next().unwrap_or_else(|| "spin".into());
+    let mut rest: Vec<OsString> = args.collect();
+    let runs_app = match rest.first().and_then(|arg| arg.to_str()) {
+        None => true,
+        Some(arg) => arg.starts_with('-') || arg == "up",
+    };
+    let embedded_app = std::env::current_exe()
+        .ok()
+        .filter(|path| runs_app && spin_bundle::executable::has_embedded_bundle(path));
+    let Some(app) = embedded_app else {
+        return std::iter::once(exe).chain(rest).collect();
+    };
+    if rest.first().is_some_and(|arg| arg == "up") {
+        rest.remove(0);
+    }
+    [exe, "up".into(), "--from".into(), app.into()]
+        .into_iter()
+        .chain(rest)
+        .collect()
+}
+
 fn print_error_chain(err: anyhow::Error) {
     if let Some(cause) = err.source() {
         let is_multiple = cause.source().is_some();
@@ -136,6 +174,8 @@ enum SpinApp {
     #[clap(alias = "w")]
     Watch(WatchCommand),
     Doctor(DoctorCommand),
+    Precompile(PrecompileCommand),
+    Bundle(BundleCommand),
     #[clap(subcommand, hide = true)]
     Maintenance(MaintenanceCommands),
 }
@@ -144,6 +184,14 @@ enum SpinApp {
 enum TriggerCommands {
     Http(FactorsTriggerCommand<HttpTrigger, FactorsBuilder>),
     Redis(FactorsTriggerCommand<RedisTrigger, FactorsBuilder>),
+    Cron(FactorsTriggerCommand<CronTrigger, FactorsBuilder>),
+    Kafka(FactorsTriggerCommand<KafkaTrigger, FactorsBuilder>),
+    Nats(FactorsTriggerCommand<NatsTrigger, FactorsBuilder>),
+    Amqp(FactorsTriggerCommand<AmqpTrigger, FactorsBuilder>),
+    Grpc(FactorsTriggerCommand<GrpcTrigger, FactorsBuilder>),
+    Job(FactorsTriggerCommand<JobTrigger, FactorsBuilder>),
+    Queue(FactorsTriggerCommand<QueueTrigger, FactorsBuilder>),
+    Tcp(FactorsTriggerCommand<TcpTrigger, FactorsBuilder>),
     #[clap(name = spin_cli::HELP_ARGS_ONLY_TRIGGER_TYPE, hide = true)]
     HelpArgsOnly(FactorsTriggerCommand<HelpArgsOnlyTrigger, FactorsBuilder>),
 }
@@ -162,11 +210,21 @@ impl SpinApp {
             Self::Build(cmd) => cmd.run().await,
             Self::Trigger(TriggerCommands::Http(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::Redis(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Cron(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Kafka(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Nats(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Amqp(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Grpc(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Job(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Queue(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Tcp(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::HelpArgsOnly(cmd)) => cmd.run().await,
             Self::Plugins(cmd) => cmd.run().await,
             Self::External(cmd) => execute_external_subcommand(cmd, app).await,
             Self::Watch(cmd) => cmd.run().await,
             Self::Doctor(cmd) => cmd.run().await,
+            Self::Precompile(cmd) => cmd.run().await,
+            Self::Bundle(cmd) => cmd.run().await,
             Self::Maintenance(cmd) => cmd.run(SpinApp::command()).await,
         }
     }
@@ -2,6 +2,8 @@
 
 /// Commands for building Spin applications.
 pub mod build;
+/// Command for packaging an application into a bundle for offline use.
+pub mod bundle;
 /// Commands for publishing applications to the Fermyon Platform.
 pub mod cloud;
 /// Command for running the Spin Doctor.
@@ -14,6 +16,8 @@ pub mod maintenance;
 pub mod new;
 /// Command for adding a plugin to Spin
 pub mod plugins;
+/// Command for compiling an application's components ahead of time.
+pub mod precompile;
 /// Commands for working with OCI registries.
 pub mod registry;
 /// Commands for working with templates.
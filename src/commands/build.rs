@@ -5,7 +5,7 @@ use clap::Parser;
 
 use crate::{
     directory_rels::notify_if_nondefault_rel,
-    opts::{APP_MANIFEST_FILE_OPT, BUILD_UP_OPT},
+    opts::{APP_MANIFEST_FILE_OPT, BUILD_PROFILE_ENV, BUILD_UP_OPT, ENVIRONMENT_ENV},
 };
 
 use super::up::UpCommand;
@@ -29,6 +29,15 @@ pub struct BuildCommand {
     #[clap(short = 'c', long, multiple = true)]
     pub component_id: Vec<String>,
 
+    /// The manifest environment (`[environments.<name>]`) whose build commands to use.
+    #[clap(long, env = ENVIRONMENT_ENV)]
+    pub environment: Option<String>,
+
+    /// The build profile (`[component.<id>.build.profiles.<name>]`) to use.
+    /// Components which do not define the profile use their default build.
+    #[clap(long, env = BUILD_PROFILE_ENV)]
+    pub profile: Option<String>,
+
     /// Run the application after building.
     #[clap(name = BUILD_UP_OPT, short = 'u', long = "up")]
     pub up: bool,
@@ -43,7 +52,13 @@ impl BuildCommand {
             spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
         notify_if_nondefault_rel(&manifest_file, distance);
 
-        spin_build::build(&manifest_file, &self.component_id).await?;
+        spin_build::build(
+            &manifest_file,
+            &self.component_id,
+            self.environment.as_deref(),
+            self.profile.as_deref(),
+        )
+        .await?;
 
         if self.up {
             let mut cmd = UpCommand::parse_from(
@@ -54,6 +69,8 @@ impl BuildCommand {
                 .chain(self.up_args),
             );
             cmd.file_source = Some(manifest_file);
+            // Run the app in the environment it was built for
+            cmd.environment = self.environment;
             cmd.run().await
         } else {
             Ok(())
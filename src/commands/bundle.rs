@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use spin_loader::{LoadOptions, LockfileMode};
+
+use crate::{
+    directory_rels::notify_if_nondefault_rel,
+    opts::{ALWAYS_BUILD_ENV, APP_MANIFEST_FILE_OPT},
+};
+
+/// Package an application into a bundle, which `spin up` can run without
+/// registry or network access, or into an executable which runs it.
+///
+/// Bundles are not signed, so cannot satisfy the `[signatures]` trust policy
+/// of a runtime config: `spin up` refuses to run a bundle while there is one,
+/// unless given `--allow-unsigned-bundle`.
+#[derive(Parser, Debug)]
+#[clap(
+    about = "Package the application, with its components, files and runtime config, into a bundle which `spin up --from` can run offline, or into a single executable"
+)]
+pub struct BundleCommand {
+    /// The application to bundle. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// The path of the bundle to write, which should end in `.tar.gz`.
+    #[clap(short = 'o', long = "output")]
+    pub output: PathBuf,
+
+    /// Write a single executable which runs the application, rather than a
+    /// bundle. The executable is a copy of Spin with the bundle embedded, so
+    /// runs where Spin is not installed: running it is equivalent to
+    /// `spin up`, and it accepts the same options. On macOS, the executable
+    /// must be re-signed before it will run. As for bundles, the executable
+    /// refuses to run with a runtime config which has a `[signatures]` trust
+    /// policy, unless given `--allow-unsigned-bundle`.
+    #[clap(long, takes_value = false)]
+    pub executable: bool,
+
+    /// The Spin executable to embed the bundle in, such as a build of Spin
+    /// for another platform. If omitted, this Spin executable is used.
+    #[clap(long, requires = "executable")]
+    pub launcher: Option<PathBuf>,
+
+    /// A runtime config file to include in the bundle, with the files it
+    /// refers to by relative paths. `spin up` uses it when running the bundle
+    /// unless given another.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Specifies to perform `spin build` before bundling the application.
+    #[clap(long, takes_value = false, env = ALWAYS_BUILD_ENV)]
+    pub build: bool,
+
+    /// Cache directory for downloaded components.
+    #[clap(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Require remote component sources and packages to be pinned by an
+    /// up-to-date spin.lock, rather than updating it.
+    #[clap(long, takes_value = false)]
+    pub locked: bool,
+}
+
+impl BundleCommand {
+    pub async fn run(self) -> Result<()> {
+        let (app_file, distance) =
+            spin_common::paths::find_manifest_file_path(self.app_source.as_ref())?;
+        notify_if_nondefault_rel(&app_file, distance);
+
+        if self.build {
+            spin_build::build(&app_file, &[], None, None).await?;
+        }
+
+        if !self.executable {
+            spin_bundle::export(
+                &app_file,
+                self.load_options(),
+                self.runtime_config_file.as_deref(),
+                self.cache_dir,
+                &self.output,
+            )
+            .await?;
+            println!("Wrote bundle {}", self.output.display());
+            return Ok(());
+        }
+
+        let launcher = match self.launcher {
+            Some(launcher) => launcher,
+            None => std::env::current_exe().context("could not find the Spin executable")?,
+        };
+        let staging_dir = tempfile::tempdir()?;
+        let bundle = staging_dir.path().join("app.tar.gz");
+        spin_bundle::export(
+            &app_file,
+            self.load_options(),
+            self.runtime_config_file.as_deref(),
+            self.cache_dir,
+            &bundle,
+        )
+        .await?;
+        spin_bundle::executable::embed(&launcher, &bundle, &self.output)?;
+        println!("Wrote executable {}", self.output.display());
+        Ok(())
+    }
+
+    fn load_options(&self) -> LoadOptions {
+        LoadOptions {
+            lockfile_mode: if self.locked {
+                LockfileMode::Locked
+            } else {
+                LockfileMode::Update
+            },
+            ..Default::default()
+        }
+    }
+}
@@ -44,7 +44,7 @@ pub struct GenerateSchema {
 
 impl GenerateSchema {
     async fn run(&self) -> anyhow::Result<()> {
-        let schema = schemars::schema_for!(spin_manifest::schema::v2::AppManifest);
+        let schema = spin_manifest::json_schema::app_manifest_schema();
         let schema_json = serde_json::to_string_pretty(&schema)?;
         write(&self.output, &schema_json)?;
         Ok(())
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use clap::{CommandFactory, Parser};
+
+use super::up::UpCommand;
+
+/// Compile the components of a Spin application ahead of time.
+#[derive(Parser, Debug)]
+#[clap(
+    about = "Compile the application's components ahead of time, for runs with the same --compiled-cache-dir",
+    allow_hyphen_values = true,
+    disable_help_flag = true
+)]
+pub struct PrecompileCommand {
+    #[clap(flatten)]
+    pub up: UpCommand,
+}
+
+impl PrecompileCommand {
+    pub async fn run(self) -> Result<()> {
+        let usage = Self::command()
+            .name("spin-precompile")
+            .bin_name("spin precompile");
+        let up = UpCommand {
+            precompile: true,
+            ..self.up
+        };
+        up.run_with_usage(usage).await
+    }
+}
@@ -3,9 +3,12 @@ use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use indicatif::{ProgressBar, ProgressStyle};
 use spin_common::arg_parser::parse_kv;
+use spin_loader::{LoadOptions, LockfileMode};
 use spin_oci::{client::InferPredefinedAnnotations, Client, ComposeMode};
 use std::{io::Read, path::PathBuf, time::Duration};
 
+use super::up::UpCommand;
+
 /// Commands for working with OCI registries to distribute applications.
 #[derive(Subcommand, Debug)]
 pub enum RegistryCommands {
@@ -75,6 +78,16 @@ pub struct Push {
     /// Any existing value will be overwritten. Can be used multiple times.
     #[clap(long = "annotation", parse(try_from_str = parse_kv))]
     pub annotations: Vec<(String, String)>,
+
+    /// Require remote component sources and packages to be pinned by an
+    /// up-to-date spin.lock, rather than updating it.
+    #[clap(long, takes_value = false)]
+    pub locked: bool,
+
+    /// The manifest environment (`[environments.<name>]`) to build and push
+    /// the application for.
+    #[clap(long, env = ENVIRONMENT_ENV)]
+    pub environment: Option<String>,
 }
 
 impl Push {
@@ -84,7 +97,7 @@ impl Push {
         notify_if_nondefault_rel(&app_file, distance);
 
         if self.build {
-            spin_build::build(&app_file, &[]).await?;
+            spin_build::build(&app_file, &[], self.environment.as_deref(), None).await?;
         }
 
         let annotations = if self.annotations.is_empty() {
@@ -103,9 +116,20 @@ impl Push {
             ComposeMode::Skip
         };
 
+        let load_options = LoadOptions {
+            lockfile_mode: if self.locked {
+                LockfileMode::Locked
+            } else {
+                LockfileMode::Update
+            },
+            environment: self.environment.clone(),
+            ..Default::default()
+        };
+
         let digest = client
             .push(
                 &app_file,
+                load_options,
                 &self.reference,
                 annotations,
                 InferPredefinedAnnotations::All,
@@ -140,6 +164,11 @@ pub struct Pull {
     /// Cache directory for downloaded registry data.
     #[clap(long)]
     pub cache_dir: Option<PathBuf>,
+
+    /// Also compile the app's components into this directory, for runs with
+    /// the same --compiled-cache-dir.
+    #[clap(long)]
+    pub compiled_cache_dir: Option<PathBuf>,
 }
 
 impl Pull {
@@ -147,10 +176,23 @@ impl Pull {
     pub async fn run(self) -> Result<()> {
         let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone()).await?;
 
-        let _spinner = create_dotted_spinner(2000, "Pulling app from the Registry".to_owned());
+        let spinner = create_dotted_spinner(2000, "Pulling app from the Registry".to_owned());
 
         client.pull(&self.reference).await?;
+        spinner.finish_and_clear();
         println!("Successfully pulled the app from the registry");
+
+        if let Some(compiled_cache_dir) = self.compiled_cache_dir {
+            let up = UpCommand {
+                registry_source: Some(self.reference),
+                insecure: self.insecure,
+                cache_dir: self.cache_dir,
+                precompile: true,
+                trigger_args: vec!["--compiled-cache-dir".into(), compiled_cache_dir.into()],
+                ..Default::default()
+            };
+            up.run().await?;
+        }
         Ok(())
     }
 }
@@ -4,6 +4,7 @@ use std::{
     collections::{HashMap, HashSet},
     ffi::OsString,
     fmt::Debug,
+    net::SocketAddr,
     path::{Path, PathBuf},
     process::Stdio,
 };
@@ -14,9 +15,12 @@ use reqwest::Url;
 use spin_app::locked::LockedApp;
 use spin_common::ui::quoted_path;
 use spin_factor_outbound_networking::validate_service_chaining_for_components;
-use spin_loader::FilesMountStrategy;
-use spin_oci::OciLoader;
-use spin_trigger::cli::{LaunchMetadata, SPIN_LOCAL_APP_DIR, SPIN_LOCKED_URL, SPIN_WORKING_DIR};
+use spin_loader::{FilesMountStrategy, LockfileMode};
+use spin_oci::{signature::TrustPolicy, OciLoader};
+use spin_trigger::cli::{
+    LaunchMetadata, TlsRuntimeConfig, RUNTIME_CONFIG_FILE, SPIN_ADMIN_LISTEN, SPIN_LOCAL_APP_DIR,
+    SPIN_LOCKED_URL, SPIN_WORKING_DIR,
+};
 use tempfile::TempDir;
 
 use crate::{directory_rels::notify_if_nondefault_rel, opts::*};
@@ -48,7 +52,9 @@ pub struct UpCommand {
     pub help: bool,
 
     /// The application to run. This may be a manifest (spin.toml) file, a
-    /// directory containing a spin.toml file, a remote registry reference, or a Wasm module (a .wasm file).
+    /// directory containing a spin.toml file, a remote registry reference, a Wasm module (a .wasm file),
+    /// or a bundle written by `spin bundle`. Bundles are not signed, so are refused if the runtime
+    /// config has a `[signatures]` trust policy, unless `--allow-unsigned-bundle` is given.
     /// If omitted, it defaults to "spin.toml".
     #[clap(
         name = APPLICATION_OPT,
@@ -88,6 +94,12 @@ pub struct UpCommand {
     )]
     pub insecure: bool,
 
+    /// Run a bundle even though the runtime config has a `[signatures]` trust
+    /// policy. Bundles are not signed, so cannot satisfy the policy: the bundle
+    /// is run as-is, and must come from a source you trust.
+    #[clap(long, takes_value = false)]
+    pub allow_unsigned_bundle: bool,
+
     /// Pass an environment variable (key=value) to all components of the application.
     #[clap(short = 'e', long = "env", parse(try_from_str = parse_env_var))]
     pub env: Vec<(String, String)>,
@@ -108,6 +120,21 @@ pub struct UpCommand {
     #[clap(long, takes_value = false)]
     pub direct_mounts: bool,
 
+    /// For local apps, require remote component sources and packages to be pinned by an up-to-date
+    /// spin.lock, rather than updating it.
+    #[clap(long, takes_value = false)]
+    pub locked: bool,
+
+    /// For local apps, the manifest environment (`[environments.<name>]`) to apply.
+    /// This also selects the variables profile of the same name, if there is one.
+    #[clap(long, env = ENVIRONMENT_ENV)]
+    pub environment: Option<String>,
+
+    /// For local apps, fail if the manifest has fields which Spin does not recognise, or which
+    /// are deprecated, rather than warning about them.
+    #[clap(long, takes_value = false)]
+    pub deny_unknown_fields: bool,
+
     /// For local apps, specifies to perform `spin build` before running the application.
     ///
     /// This is ignored on remote applications, as they are already built.
@@ -118,21 +145,35 @@ pub struct UpCommand {
     #[clap(short = 'c', long = "component-id")]
     pub components: Vec<String>,
 
+    /// Address on which to serve the `/healthz` and `/readyz` admin
+    /// endpoints. In an application with several triggers, these are served
+    /// by the first.
+    #[clap(long = "admin-listen", env = SPIN_ADMIN_LISTEN)]
+    pub admin_listen: Option<SocketAddr>,
+
     /// All other args, to be passed through to the trigger
     #[clap(hide = true)]
     pub trigger_args: Vec<OsString>,
+
+    /// Have the triggers compile the application's components, rather than
+    /// run them. Set by `spin precompile`.
+    #[clap(skip)]
+    pub precompile: bool,
 }
 
 impl UpCommand {
     pub async fn run(self) -> Result<()> {
-        // For displaying help, first print `spin up`'s own usage text, then
+        let usage = Self::command().name("spin-up").bin_name("spin up");
+        self.run_with_usage(usage).await
+    }
+
+    /// Runs the command, with the given usage text for `--help`.
+    pub(crate) async fn run_with_usage(self, mut usage: clap::Command<'static>) -> Result<()> {
+        // For displaying help, first print the command's own usage text, then
         // attempt to load an app and print trigger-type-specific usage.
         let help = self.help;
         if help {
-            Self::command()
-                .name("spin-up")
-                .bin_name("spin up")
-                .print_help()?;
+            usage.print_help()?;
             println!();
         }
         self.run_inner().await.or_else(|err| {
@@ -145,7 +186,7 @@ impl UpCommand {
         })
     }
 
-    async fn run_inner(self) -> Result<()> {
+    async fn run_inner(mut self) -> Result<()> {
         let app_source = self.app_source();
 
         if app_source == AppSource::None {
@@ -168,7 +209,32 @@ impl UpCommand {
             .canonicalize()
             .context("Could not canonicalize working directory")?;
 
+        if let AppSource::Bundle(path) = &app_source {
+            if !self.allow_unsigned_bundle && self.trust_policy()?.is_some() {
+                let kind = if spin_bundle::executable::has_embedded_bundle(path) {
+                    "an app executable"
+                } else {
+                    "a bundle"
+                };
+                bail!(
+                    "{app_source} is {kind}, which is not signed, but the runtime config requires apps to satisfy its [signatures] trust policy. \
+                    If you trust it, run it with --allow-unsigned-bundle."
+                );
+            }
+        }
+
         let resolved_app_source = self.resolve_app_source(&app_source, &working_dir).await?;
+        if let ResolvedAppSource::Bundle {
+            runtime_config_file: Some(path),
+            ..
+        } = &resolved_app_source
+        {
+            // A runtime config given on the command line takes precedence
+            if self.runtime_config_file().is_none() {
+                self.trigger_args
+                    .extend(["--runtime-config-file".into(), path.into()]);
+            }
+        }
         if self.help {
             let trigger_cmds =
                 trigger_commands_for_trigger_types(resolved_app_source.trigger_types())
@@ -191,7 +257,7 @@ impl UpCommand {
         }
 
         if self.build {
-            app_source.build().await?;
+            app_source.build(self.environment.as_deref()).await?;
         }
         let mut locked_app = self
             .load_resolved_app_source(resolved_app_source, &working_dir)
@@ -229,18 +295,36 @@ impl UpCommand {
         let locked_url = self.write_locked_app(&locked_app, &working_dir).await?;
 
         let local_app_dir = app_source.local_app_dir().map(Into::into);
+        let (otel_env, prometheus_env) = self.telemetry_env_vars();
+        let tls_env = self.tls_env_vars();
 
         let run_opts = RunTriggerOpts {
             locked_url,
             working_dir,
             local_app_dir,
+            otel_env,
+            prometheus_env,
+            tls_env,
+            admin_listen: self.admin_listen,
         };
 
+        let precompile = self.precompile;
         let trigger_processes = self.start_trigger_processes(trigger_cmds, run_opts).await?;
         let pids = get_pids(&trigger_processes);
 
         set_kill_on_ctrl_c(&pids)?;
 
+        if precompile {
+            // Each trigger exits once it has compiled its components
+            for mut process in trigger_processes {
+                let status = process.wait().await?;
+                if !status.success() {
+                    return Err(crate::subprocess::ExitStatusError::new(status).into());
+                }
+            }
+            return Ok(());
+        }
+
         let trigger_tasks = trigger_processes
             .into_iter()
             .map(|mut ch| tokio::task::spawn(async move { ch.wait().await }))
@@ -304,7 +388,7 @@ impl UpCommand {
     async fn start_trigger_processes(
         self,
         trigger_cmds: Vec<Vec<String>>,
-        run_opts: RunTriggerOpts,
+        mut run_opts: RunTriggerOpts,
     ) -> anyhow::Result<Vec<tokio::process::Child>> {
         let is_multi = trigger_cmds.len() > 1;
 
@@ -332,6 +416,19 @@ impl UpCommand {
 
         let mut trigger_processes = Vec::with_capacity(trigger_cmds.len());
 
+        if is_multi && !run_opts.prometheus_env.is_empty() {
+            terminal::warn!(
+                "Prometheus metrics are only served by '{}', the first of this application's triggers.",
+                trigger_cmds[0].join(" ")
+            );
+        }
+        if is_multi && run_opts.admin_listen.is_some() {
+            terminal::warn!(
+                "Admin endpoints are only served by '{}', the first of this application's triggers.",
+                trigger_cmds[0].join(" ")
+            );
+        }
+
         for cmd in trigger_cmds {
             let meta = trigger_metas.as_ref().and_then(|ms| ms.get(&cmd));
             let trigger_args = match meta {
@@ -343,6 +440,8 @@ impl UpCommand {
                 .await
                 .context("Failed to start trigger process")?;
             trigger_processes.push(child);
+            run_opts.prometheus_env.clear();
+            run_opts.admin_listen = None;
 
             if is_multi {
                 // Allow time for the child `spin` process to launch the trigger
@@ -370,12 +469,29 @@ impl UpCommand {
             locked_url,
             working_dir,
             local_app_dir,
+            otel_env,
+            prometheus_env,
+            tls_env,
+            admin_listen,
         }) = opts
         {
             cmd.env(SPIN_LOCKED_URL, locked_url)
                 .env(SPIN_WORKING_DIR, &working_dir)
+                .envs(otel_env)
+                .envs(tls_env)
                 .args(trigger_args);
 
+            if self.precompile {
+                cmd.arg("--precompile-only");
+            } else {
+                cmd.envs(prometheus_env);
+            }
+
+            match admin_listen {
+                Some(addr) if !self.precompile => cmd.env(SPIN_ADMIN_LISTEN, addr.to_string()),
+                _ => cmd.env_remove(SPIN_ADMIN_LISTEN),
+            };
+
             if let Some(local_app_dir) = local_app_dir {
                 cmd.env(SPIN_LOCAL_APP_DIR, local_app_dir);
             }
@@ -466,6 +582,9 @@ impl UpCommand {
                 let mut client = spin_oci::Client::new(self.insecure, self.cache_dir.clone())
                     .await
                     .context("cannot create registry client")?;
+                if let Some(policy) = self.trust_policy()? {
+                    client.set_trust_policy(&policy)?;
+                }
 
                 let locked_app = OciLoader::new(working_dir)
                     .load_app(&mut client, reference)
@@ -475,6 +594,15 @@ impl UpCommand {
             AppSource::BareWasm(path) => ResolvedAppSource::BareWasm {
                 wasm_path: path.clone(),
             },
+            AppSource::Bundle(path) => {
+                let unpacked = spin_bundle::unpack(path, &working_dir.join("bundle"))
+                    .await
+                    .with_context(|| format!("cannot unpack bundle {}", quoted_path(path)))?;
+                ResolvedAppSource::Bundle {
+                    locked_app: unpacked.locked_app,
+                    runtime_config_file: unpacked.runtime_config_file,
+                }
+            }
             AppSource::Unresolvable(err) => bail!("{err}"),
             AppSource::None => bail!("Internal error - should have shown help"),
         })
@@ -493,16 +621,31 @@ impl UpCommand {
                 } else {
                     FilesMountStrategy::Copy(working_dir.join("assets"))
                 };
-                spin_loader::from_file(&manifest_path, files_mount_strategy, self.cache_dir.clone())
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "Failed to load manifest from {}",
-                            quoted_path(&manifest_path)
-                        )
-                    })
+                let options = spin_loader::LoadOptions {
+                    lockfile_mode: if self.locked {
+                        LockfileMode::Locked
+                    } else {
+                        LockfileMode::Update
+                    },
+                    environment: self.environment.clone(),
+                    deny_unknown_fields: self.deny_unknown_fields,
+                };
+                spin_loader::from_file_with_options(
+                    &manifest_path,
+                    files_mount_strategy,
+                    self.cache_dir.clone(),
+                    options,
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load manifest from {}",
+                        quoted_path(&manifest_path)
+                    )
+                })
             }
-            ResolvedAppSource::OciRegistry { locked_app } => Ok(locked_app),
+            ResolvedAppSource::OciRegistry { locked_app }
+            | ResolvedAppSource::Bundle { locked_app, .. } => Ok(locked_app),
             ResolvedAppSource::BareWasm { wasm_path } => spin_loader::from_wasm_file(&wasm_path)
                 .await
                 .with_context(|| {
@@ -520,6 +663,91 @@ impl UpCommand {
         }
     }
 
+    /// The runtime config file which is passed on to the triggers, if any.
+    fn runtime_config_file(&self) -> Option<PathBuf> {
+        let from_args = self.group_trigger_args().into_iter().find_map(|group| {
+            match group[0].to_str()?.strip_prefix("--runtime-config-file")? {
+                "" => group.get(1).map(PathBuf::from),
+                value => value.strip_prefix('=').map(PathBuf::from),
+            }
+        });
+        from_args.or_else(|| std::env::var_os(RUNTIME_CONFIG_FILE).map(PathBuf::from))
+    }
+
+    /// The environment variables which apply the telemetry settings of the
+    /// runtime config to the triggers: those for OTLP export, and those for
+    /// the Prometheus metrics listener.
+    ///
+    /// Telemetry is initialized from the environment as a trigger process
+    /// starts, before the trigger reads its runtime config.
+    fn telemetry_env_vars(&self) -> (Vec<(&'static str, String)>, Vec<(&'static str, String)>) {
+        let Some((_, toml)) = self.runtime_config_toml() else {
+            return Default::default();
+        };
+        let otel = spin_telemetry::OtelRuntimeConfig::from_toml(&toml)
+            .ok()
+            .flatten()
+            .map(|otel| otel.env_vars());
+        let prometheus = spin_telemetry::PrometheusRuntimeConfig::from_toml(&toml)
+            .ok()
+            .flatten()
+            .map(|prometheus| prometheus.env_vars());
+        (otel.unwrap_or_default(), prometheus.unwrap_or_default())
+    }
+
+    /// The environment variables which apply the `[tls]` settings of the
+    /// runtime config to the HTTP trigger's listener.
+    fn tls_env_vars(&self) -> Vec<(&'static str, String)> {
+        let Some((path, toml)) = self.runtime_config_toml() else {
+            return vec![];
+        };
+        let Some(mut tls) = toml
+            .get("tls")
+            .and_then(|value| value.clone().try_into::<TlsRuntimeConfig>().ok())
+        else {
+            return vec![];
+        };
+        if let Some(dir) = path.parent() {
+            tls.resolve_paths(dir);
+        }
+        tls.env_vars()
+    }
+
+    /// The `[signatures]` trust policy of the runtime config, if any, which
+    /// apps pulled from registries must satisfy. Bundles carry no signatures,
+    /// so are refused while there is a policy, unless explicitly allowed.
+    ///
+    /// Unlike other settings, it is checked before the triggers run, so
+    /// problems with the runtime config file are reported here.
+    fn trust_policy(&self) -> anyhow::Result<Option<TrustPolicy>> {
+        let Some(path) = self.runtime_config_file() else {
+            return Ok(None);
+        };
+        let contents = std::fs::read_to_string(&path).with_context(|| {
+            format!("failed to read runtime config file {}", quoted_path(&path))
+        })?;
+        let toml = toml::from_str(&contents).with_context(|| {
+            format!(
+                "failed to parse runtime config file {} as toml",
+                quoted_path(&path)
+            )
+        })?;
+        let mut policy = TrustPolicy::from_toml(&toml)?;
+        if let (Some(policy), Some(dir)) = (&mut policy, path.parent()) {
+            policy.resolve_paths(dir);
+        }
+        Ok(policy)
+    }
+
+    /// The runtime config file, if any, and its contents.
+    fn runtime_config_toml(&self) -> Option<(PathBuf, toml::Table)> {
+        // Any problem with the file is reported by the triggers, which read it in full
+        let path = self.runtime_config_file()?;
+        let contents = std::fs::read_to_string(&path).ok()?;
+        let toml = toml::from_str(&contents).ok()?;
+        Some((path, toml))
+    }
+
     fn group_trigger_args(&self) -> Vec<Vec<&OsString>> {
         let mut groups = vec![];
 
@@ -605,6 +833,17 @@ struct RunTriggerOpts {
     locked_url: String,
     working_dir: PathBuf,
     local_app_dir: Option<PathBuf>,
+    /// OTLP export settings from the runtime config, as environment variables.
+    otel_env: Vec<(&'static str, String)>,
+    /// Prometheus metrics listener settings from the runtime config, as
+    /// environment variables. Only one trigger process can listen.
+    prometheus_env: Vec<(&'static str, String)>,
+    /// TLS settings for the HTTP trigger from the runtime config, as
+    /// environment variables.
+    tls_env: Vec<(&'static str, String)>,
+    /// The address on which to serve admin endpoints. Only one trigger
+    /// process can listen.
+    admin_listen: Option<SocketAddr>,
 }
 
 enum WorkingDirectory {
@@ -671,7 +910,8 @@ fn trigger_commands_for_trigger_types(trigger_types: Vec<&str>) -> Result<Vec<Ve
     trigger_types
         .iter()
         .map(|&t| match t {
-            "http" | "redis" => Ok(trigger_command(t)),
+            "http" | "redis" | "cron" | "kafka" | "nats" | "amqp" | "grpc" | "queue" | "job"
+            | "tcp" => Ok(trigger_command(t)),
             _ => {
                 let cmd = resolve_trigger_plugin(t)?;
                 Ok(vec![cmd])
@@ -847,6 +1087,20 @@ mod test {
             .expect("Failed to parse implicit source with trigger option");
     }
 
+    #[test]
+    fn finds_runtime_config_file_in_trigger_args() {
+        let cmd = UpCommand::try_parse_from(["up", "--runtime-config-file", "rc.toml"]).unwrap();
+        assert_eq!(Some(PathBuf::from("rc.toml")), cmd.runtime_config_file());
+        let cmd = UpCommand::try_parse_from([
+            "up",
+            "--listen",
+            "127.0.0.1:39453",
+            "--runtime-config-file=rc.toml",
+        ])
+        .unwrap();
+        assert_eq!(Some(PathBuf::from("rc.toml")), cmd.runtime_config_file());
+    }
+
     #[test]
     fn group_no_args_is_empty() {
         let cmd = UpCommand::try_parse_from(["up"]).unwrap();
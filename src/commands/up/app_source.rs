@@ -13,6 +13,7 @@ pub enum AppSource {
     File(PathBuf),
     OciRegistry(String),
     BareWasm(PathBuf),
+    Bundle(PathBuf),
     Unresolvable(String),
     None,
 }
@@ -34,6 +35,8 @@ impl AppSource {
             Ok(file) => {
                 if is_wasm_file(&file) {
                     Self::BareWasm(file)
+                } else if is_bundle_file(&file) {
+                    Self::Bundle(file)
                 } else {
                     Self::File(file)
                 }
@@ -56,9 +59,9 @@ impl AppSource {
         }
     }
 
-    pub async fn build(&self) -> anyhow::Result<()> {
+    pub async fn build(&self, environment: Option<&str>) -> anyhow::Result<()> {
         match self {
-            Self::File(path) => spin_build::build(path, &[]).await,
+            Self::File(path) => spin_build::build(path, &[], environment, None).await,
             _ => Ok(()),
         }
     }
@@ -69,12 +72,19 @@ fn is_wasm_file(path: &Path) -> bool {
     extn.is_some_and(|e| e == "wasm" || e == "wat")
 }
 
+fn is_bundle_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(std::ffi::OsStr::to_str);
+    name.is_some_and(|n| n.ends_with(".tar.gz") || n.ends_with(".tgz"))
+        || spin_bundle::executable::has_embedded_bundle(path)
+}
+
 impl std::fmt::Display for AppSource {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::File(path) => write!(f, "local app {}", quoted_path(path)),
             Self::OciRegistry(reference) => write!(f, "remote app {reference:?}"),
             Self::BareWasm(path) => write!(f, "Wasm file {}", quoted_path(path)),
+            Self::Bundle(path) => write!(f, "bundle {}", quoted_path(path)),
             Self::Unresolvable(s) => write!(f, "unknown app source: {s:?}"),
             Self::None => write!(f, "<no source>"),
         }
@@ -96,6 +106,10 @@ pub enum ResolvedAppSource {
     OciRegistry {
         locked_app: LockedApp,
     },
+    Bundle {
+        locked_app: LockedApp,
+        runtime_config_file: Option<PathBuf>,
+    },
 }
 
 impl ResolvedAppSource {
@@ -106,7 +120,8 @@ impl ResolvedAppSource {
                 .keys()
                 .map(|s| s.as_str())
                 .collect::<HashSet<_>>(),
-            ResolvedAppSource::OciRegistry { locked_app } => locked_app
+            ResolvedAppSource::OciRegistry { locked_app }
+            | ResolvedAppSource::Bundle { locked_app, .. } => locked_app
                 .triggers
                 .iter()
                 .map(|t| t.trigger_type.as_str())
@@ -14,7 +14,10 @@ use watchexec::Watchexec;
 
 use crate::{
     directory_rels::notify_if_nondefault_rel,
-    opts::{APP_MANIFEST_FILE_OPT, WATCH_CLEAR_OPT, WATCH_DEBOUNCE_OPT, WATCH_SKIP_BUILD_OPT},
+    opts::{
+        APP_MANIFEST_FILE_OPT, BUILD_PROFILE_ENV, WATCH_CLEAR_OPT, WATCH_DEBOUNCE_OPT,
+        WATCH_SKIP_BUILD_OPT,
+    },
 };
 
 mod buildifier;
@@ -65,6 +68,11 @@ pub struct WatchCommand {
     #[clap(name = WATCH_SKIP_BUILD_OPT, long = "skip-build")]
     pub skip_build: bool,
 
+    /// The build profile (`[component.<id>.build.profiles.<name>]`) to build
+    /// with, and whose `watch` files to watch.
+    #[clap(long, env = BUILD_PROFILE_ENV)]
+    pub profile: Option<String>,
+
     /// Arguments to be passed through to spin up.
     #[clap()]
     pub up_args: Vec<String>,
@@ -112,6 +120,7 @@ impl WatchCommand {
         let mut buildifier = Buildifier {
             spin_bin: spin_bin.clone(),
             manifest: manifest_file.clone(),
+            profile: self.profile.clone(),
             clear_screen: self.clear,
             has_ever_built: false,
             watched_changes: source_code_rx,
@@ -156,7 +165,9 @@ impl WatchCommand {
         let (build_watcher, build_files_watcher_handle) = if self.skip_build {
             ReconfigurableWatcher::dummy()
         } else {
-            let build_filterer = Box::new(BuildFilterFactory);
+            let build_filterer = Box::new(BuildFilterFactory {
+                profile: self.profile.clone(),
+            });
             self.spawn_watchexec(
                 &manifest_file,
                 &manifest_dir,
@@ -280,8 +291,7 @@ pub struct RuntimeConfigFactory {
 
 impl RuntimeConfigFactory {
     async fn build_config(&self) -> anyhow::Result<watchexec::config::RuntimeConfig> {
-        let manifest_str = tokio::fs::read_to_string(&self.manifest_file).await?;
-        let manifest = spin_manifest::manifest_from_str(&manifest_str)?;
+        let manifest = spin_manifest::manifest_from_file(&self.manifest_file)?;
         let filterer = self
             .filter_factory
             .build_filter(&self.manifest_file, &self.manifest_dir, &manifest)
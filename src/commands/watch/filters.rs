@@ -24,7 +24,9 @@ pub(crate) struct ArtifactFilterFactory {
     pub skip_assets: bool,
 }
 
-pub(crate) struct BuildFilterFactory;
+pub(crate) struct BuildFilterFactory {
+    pub profile: Option<String>,
+}
 pub(crate) struct ManifestFilterFactory;
 
 #[async_trait]
@@ -36,7 +38,7 @@ impl FilterFactory for ArtifactFilterFactory {
         manifest: &v2::AppManifest,
     ) -> anyhow::Result<Arc<dyn Filterer>> {
         let manifest_glob = if self.skip_build {
-            vec![manifest_path_to_watch(manifest_file)?]
+            manifest_paths_to_watch(manifest_file, manifest_dir)?
         } else {
             vec![] // In this case, manifest changes trigger a rebuild, which will poke the uppificator anyway
         };
@@ -92,13 +94,14 @@ impl FilterFactory for BuildFilterFactory {
         let mut filterers: Vec<Box<dyn Filterer>> =
             Vec::with_capacity(manifest.components.len() + 1);
 
-        let manifest_globs = vec![manifest_path_to_watch(manifest_file)?];
+        let manifest_globs = manifest_paths_to_watch(manifest_file, manifest_dir)?;
         let manifest_filterer = globset_filter(manifest_dir, manifest_globs).await?;
 
         filterers.push(Box::new(manifest_filterer));
 
         for (cid, c) in &manifest.components {
-            if let Some(build_globs) = create_source_globs(cid.as_ref(), c) {
+            if let Some(build_globs) = create_source_globs(cid.as_ref(), c, self.profile.as_deref())
+            {
                 let build_filterer = globset_filter(manifest_dir, build_globs).await?;
                 filterers.push(Box::new(build_filterer));
             }
@@ -110,8 +113,8 @@ impl FilterFactory for BuildFilterFactory {
     }
 }
 
-fn create_source_globs(cid: &str, c: &v2::Component) -> Option<Vec<String>> {
-    let build = c.build.as_ref()?;
+fn create_source_globs(cid: &str, c: &v2::Component, profile: Option<&str>) -> Option<Vec<String>> {
+    let build = c.build.as_ref()?.with_profile(profile);
     if build.watch.is_empty() {
         eprintln!(
             "You haven't configured what to watch for the component: '{cid}'. Learn how to configure Spin watch at https://developer.fermyon.com/common/cli-reference#watch"
@@ -159,9 +162,9 @@ impl FilterFactory for ManifestFilterFactory {
         manifest_dir: &Path,
         _: &v2::AppManifest,
     ) -> anyhow::Result<Arc<dyn Filterer>> {
-        let manifest_glob = manifest_path_to_watch(manifest_file)?;
+        let manifest_globs = manifest_paths_to_watch(manifest_file, manifest_dir)?;
 
-        let filterer = globset_filter(manifest_dir, [manifest_glob]).await?;
+        let filterer = globset_filter(manifest_dir, manifest_globs).await?;
 
         Ok(Arc::new(filterer))
     }
@@ -184,12 +187,19 @@ async fn globset_filter(
 }
 
 // Although manifest dir must be absolute, and most things are safer with abs
-// file paths, the manifest _path_ for the watchers must be relative to manifest dir
-fn manifest_path_to_watch(path: &Path) -> anyhow::Result<String> {
+// file paths, the manifest _paths_ for the watchers must be relative to manifest dir.
+// These are the manifest itself and the files it includes.
+fn manifest_paths_to_watch(path: &Path, manifest_dir: &Path) -> anyhow::Result<Vec<String>> {
     let rel_path = path
         .file_name()
         .with_context(|| format!("resolved manifest {} has no filename", quoted_path(path)))?;
-    Ok(rel_path.to_string_lossy().to_string())
+    let mut paths = vec![rel_path.to_string_lossy().to_string()];
+    for included in spin_manifest::included_files(path)? {
+        let included = included.strip_prefix(manifest_dir).unwrap_or(&included);
+        // The glob engine needs forward slashes, as for build watch globs
+        paths.push(included.to_string_lossy().replace('\\', "/"));
+    }
+    Ok(paths)
 }
 
 fn standard_ignores() -> Vec<(String, Option<PathBuf>)> {
@@ -114,7 +114,7 @@ async fn initialize_trigger(
             &ComponentLoader::new(),
         )
         .await?;
-    let server = builder.trigger.into_server(trigger_app)?;
+    let server = builder.trigger.into_server(trigger_app).await?;
 
     Ok(InProcessSpin::new(server))
 }